@@ -1,8 +1,8 @@
 #!/usr/bin/env bash
 set -euxo pipefail
 
-cargo build --release --target aarch64-apple-darwin
-cargo build --release --target x86_64-apple-darwin
+cargo build --release --features cli --target aarch64-apple-darwin
+cargo build --release --features cli --target x86_64-apple-darwin
 rm -rf target/universal-apple-darwin/release || true
 mkdir -p target/universal-apple-darwin/release
 
@@ -7,7 +7,7 @@ if ! command -v cross &> /dev/null; then
     cargo install cross
 fi
 
-cross build --release --target $1
+cross build --release --features cli --target $1
 
 ARTIFACT_DIR="$DIR/../../target/dev-scope-$1"
 rm -rf $ARTIFACT_DIR || true
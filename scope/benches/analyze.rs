@@ -0,0 +1,98 @@
+//! Compares matching each line against every known error regex with matching it against the
+//! `KnownErrorPrefilter` first. Run with `cargo bench --bench analyze`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dev_scope::prelude::{KnownError, KnownErrorPrefilter, KnownErrorSeverity, ModelMetadata};
+use regex::Regex;
+
+const LINES: usize = 10_000;
+
+fn known_errors(count: usize) -> Vec<KnownError> {
+    (0..count)
+        .map(|idx| {
+            let pattern = format!(r"error E{:04}: .* (failed|timed out)", idx);
+            KnownError {
+                full_name: format!("ScopeKnownError/error-{}", idx),
+                metadata: ModelMetadata::new(&format!("error-{}", idx)),
+                regex: Regex::new(&pattern).unwrap(),
+                pattern,
+                help_text: "help".to_string(),
+                priority: 0,
+                severity: KnownErrorSeverity::Error,
+                context_lines: 0,
+                fix_ref: None,
+                fix: None,
+            }
+        })
+        .collect()
+}
+
+/// Mostly ordinary build output, with a known error every thousand lines.
+fn log_lines() -> Vec<String> {
+    (0..LINES)
+        .map(|idx| {
+            if idx % 1000 == 999 {
+                format!("error E{:04}: step {} failed", idx % 100, idx)
+            } else {
+                format!(
+                    "[{:>6}] Compiling crate-{} v0.1.{} (/src/crate-{})",
+                    idx, idx, idx, idx
+                )
+            }
+        })
+        .collect()
+}
+
+fn bench_matching(c: &mut Criterion) {
+    let lines = log_lines();
+    let mut group = c.benchmark_group("known error matching");
+    group.throughput(Throughput::Elements(LINES as u64));
+
+    for count in [10, 100, 500] {
+        let known_errors = known_errors(count);
+        group.bench_with_input(
+            BenchmarkId::new("every regex", count),
+            &known_errors,
+            |b, known_errors| {
+                b.iter(|| {
+                    lines
+                        .iter()
+                        .map(|line| {
+                            known_errors
+                                .iter()
+                                .filter(|ke| ke.regex.is_match(line))
+                                .count()
+                        })
+                        .sum::<usize>()
+                })
+            },
+        );
+
+        let prefilter = KnownErrorPrefilter::new(&known_errors).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("prefilter", count),
+            &known_errors,
+            |b, known_errors| {
+                b.iter(|| {
+                    lines
+                        .iter()
+                        .map(|line| {
+                            let candidates = prefilter.candidates(line);
+                            if candidates.is_empty() {
+                                return 0;
+                            }
+                            known_errors
+                                .iter()
+                                .filter(|ke| candidates.contains(ke) && ke.regex.is_match(line))
+                                .count()
+                        })
+                        .sum::<usize>()
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_matching);
+criterion_main!(benches);
@@ -1,71 +1,595 @@
 use super::error::AnalyzeError;
+use super::fixes::{FixDecision, KnownErrorFixRegistry, DEFAULT_MAX_FIX_RUNS};
+use super::html::HtmlReport;
+use super::matcher::{KnownErrorMatcher, RegexKnownErrorMatcher};
+use super::prefilter::KnownErrorPrefilter;
+use super::sarif::SarifLog;
+use crate::doctor::{action_env_vars, DefaultGlobWalker, GlobWalker};
 use crate::models::HelpMetadata;
 use crate::prelude::{
-    CaptureError, CaptureOpts, DefaultExecutionProvider, ExecutionProvider, OutputDestination,
+    redirect_stdout_to_stderr, CaptureError, CaptureOpts, DefaultExecutionProvider,
+    ExecutionProvider, OutputDestination,
 };
-use crate::shared::prelude::FoundConfig;
-use anyhow::Result;
-use clap::{Args, Subcommand};
-use std::collections::BTreeMap;
+use crate::shared::prelude::{
+    DefaultInteraction, DoctorGroupCachePath, FoundConfig, KnownError, KnownErrorFix,
+    KnownErrorSeverity, KnownErrorStats, NoOpProgressReporter, ProgressReporter, PromptAnswer,
+    SpooledReader, UserInteraction,
+};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "cli")]
+use clap::{Args, Subcommand, ValueEnum};
+use futures::stream::{self, StreamExt};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
 use std::env;
-use std::io::Cursor;
-use std::path::PathBuf;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::fs::File;
-use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader, Stdin};
-use tracing::{debug, info, warn};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncSeekExt, BufReader, Stdin};
+use tracing::{debug, info, instrument, warn};
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 pub struct AnalyzeArgs {
-    #[clap(subcommand)]
+    #[cfg_attr(feature = "cli", clap(subcommand))]
     command: AnalyzeCommands,
+
+    /// When set, every known error that matches a line will be reported. By default only the
+    /// most specific known error is reported for each line.
+    #[cfg_attr(feature = "cli", arg(long, global(true), default_value = "false"))]
+    allow_multiple_matches: bool,
+
+    /// Format of the known errors that were found. SARIF is printed on stdout once the input is
+    /// analyzed, other output moves to stderr.
+    #[cfg_attr(feature = "cli", arg(long, global(true), value_enum, default_value_t = AnalyzeOutputFormat::Text))]
+    format: AnalyzeOutputFormat,
+
+    /// Lines longer than this many bytes are cut short before they're matched, so a huge line
+    /// can't stall matching. `0` matches lines of any length.
+    #[cfg_attr(feature = "cli", arg(long, global(true), default_value_t = DEFAULT_MAX_LINE_LENGTH))]
+    max_line_length: usize,
+
+    /// Most times the same fix runs in one invocation, no matter how often its known errors
+    /// match. Each fix is asked about once and the answer is remembered. `0` allows any number
+    /// of runs.
+    #[cfg_attr(feature = "cli", arg(long, global(true), default_value_t = DEFAULT_MAX_FIX_RUNS))]
+    max_fix_runs: usize,
+
+    /// Lowest severity of a known error that makes `scope analyze` exit non-zero. Known errors
+    /// below it are still reported.
+    #[cfg_attr(feature = "cli", arg(long, global(true), value_enum, default_value_t = KnownErrorSeverity::Error))]
+    fail_on: KnownErrorSeverity,
+
+    /// Write a standalone HTML page to this path, with each known error that was found, the
+    /// lines around it, links to its help and what happened to its fix. Meant to be attached
+    /// to support tickets instead of the raw log.
+    #[cfg_attr(feature = "cli", arg(long, global(true), value_name = "PATH"))]
+    html_report: Option<PathBuf>,
+
+    /// Lines shown before and after each known error in `--html-report`.
+    #[cfg_attr(feature = "cli", arg(long, global(true), default_value_t = DEFAULT_REPORT_CONTEXT_LINES))]
+    report_context_lines: usize,
+}
+
+/// Default for `--max-line-length`.
+pub const DEFAULT_MAX_LINE_LENGTH: usize = 16 * 1024;
+
+/// Default for `--report-context-lines`.
+pub const DEFAULT_REPORT_CONTEXT_LINES: usize = 3;
+
+/// Added to lines cut short by `--max-line-length`.
+const TRUNCATED_LINE_MARKER: &str = " [truncated]";
+
+/// Bytes at the start of a file checked for binary content, the same amount git checks.
+const BINARY_CHECK_LEN: usize = 8000;
+
+/// Most files `scope analyze logs` reads at the same time.
+const MAX_CONCURRENT_FILES: usize = 8;
+
+/// How `scope analyze` reports the known errors it found.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum AnalyzeOutputFormat {
+    /// Messages for people as errors are found
+    #[default]
+    Text,
+    /// A SARIF 2.1.0 document, for GitHub code scanning and IDEs
+    Sarif,
+}
+
+/// Options that control how input is analyzed for known errors. These can be deserialized from
+/// JSON or YAML, using the camelCase field names; missing fields take their defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalyzeOptions {
+    /// Report every known error that matches a line, instead of only the most specific one.
+    pub allow_multiple_matches: bool,
+    /// Longest line, in bytes, that's matched in full. Longer lines are cut short, `0` means
+    /// no limit.
+    pub max_line_length: usize,
+    /// Most times the same fix runs, `0` means no limit.
+    pub max_fix_runs: usize,
+    /// Lowest severity of a known error that fails the analysis.
+    pub fail_on: KnownErrorSeverity,
+    /// Lines kept before and after each known error that's found, for reports.
+    pub report_context_lines: usize,
 }
 
-#[derive(Debug, Subcommand)]
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self {
+            allow_multiple_matches: false,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+            max_fix_runs: DEFAULT_MAX_FIX_RUNS,
+            fail_on: KnownErrorSeverity::Error,
+            report_context_lines: DEFAULT_REPORT_CONTEXT_LINES,
+        }
+    }
+}
+
+impl AnalyzeOptions {
+    /// Whether finding `known_error` makes the analysis fail.
+    pub fn fails_on(&self, known_error: &KnownError) -> bool {
+        known_error.severity >= self.fail_on
+    }
+}
+
+impl From<&AnalyzeArgs> for AnalyzeOptions {
+    fn from(args: &AnalyzeArgs) -> Self {
+        Self {
+            allow_multiple_matches: args.allow_multiple_matches,
+            max_line_length: args.max_line_length,
+            max_fix_runs: args.max_fix_runs,
+            fail_on: args.fail_on,
+            report_context_lines: args.report_context_lines,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Subcommand))]
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
 enum AnalyzeCommands {
     /// Reads a log file and detects errors in it
-    #[clap(alias("log"))]
+    #[cfg_attr(feature = "cli", clap(alias("log")))]
     Logs(AnalyzeLogsArgs),
 
     /// Runs a command and detects errors in the output
-    #[clap()]
+    #[cfg_attr(feature = "cli", clap())]
     Command(AnalyzeCommandArgs),
+
+    /// Follows a log file, like `tail -f`, and detects errors as lines are written to it
+    #[cfg_attr(feature = "cli", clap())]
+    Follow(AnalyzeFollowArgs),
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 struct AnalyzeLogsArgs {
-    /// Location that the logs should be searched, for stdin use '-'
-    location: String,
+    /// Locations that the logs should be searched, for stdin use '-'. Globs, like
+    /// `logs/**/*.log`, and directories search every file they match
+    #[cfg_attr(feature = "cli", arg(required = true, value_name = "LOCATION"))]
+    locations: Vec<String>,
 }
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 struct AnalyzeCommandArgs {
+    /// Run the command in a pseudo-terminal, for commands that write different output, like
+    /// without colors, when it isn't a terminal. Stdout and stderr are captured together
+    #[cfg_attr(feature = "cli", arg(long))]
+    pty: bool,
+
     /// The command to run
-    #[arg(last = true, required = true)]
+    #[cfg_attr(feature = "cli", arg(last = true, required = true))]
     command: Vec<String>,
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+struct AnalyzeFollowArgs {
+    /// Log file to follow
+    location: PathBuf,
+
+    /// Analyze the lines already in the file too, instead of only new lines
+    #[cfg_attr(feature = "cli", arg(long))]
+    from_start: bool,
+
+    /// Offer to run the fix of a known error as soon as it's found
+    #[cfg_attr(feature = "cli", arg(long))]
+    fix: bool,
+}
+
 pub async fn analyze_root(found_config: &FoundConfig, args: &AnalyzeArgs) -> Result<i32> {
+    analyze_root_with_matcher(found_config, args, &RegexKnownErrorMatcher).await
+}
+
+/// Same as [`analyze_root`], but uses `matcher` to decide which known errors are present.
+pub async fn analyze_root_with_matcher(
+    found_config: &FoundConfig,
+    args: &AnalyzeArgs,
+    matcher: &dyn KnownErrorMatcher,
+) -> Result<i32> {
+    analyze_root_with_progress(found_config, args, matcher, &NoOpProgressReporter).await
+}
+
+/// Same as [`analyze_root_with_matcher`], but `progress` is told about each line that's
+/// analyzed, the commands that run and the fixes the user is asked about.
+pub async fn analyze_root_with_progress(
+    found_config: &FoundConfig,
+    args: &AnalyzeArgs,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+) -> Result<i32> {
+    analyze_root_with_interaction(
+        found_config,
+        args,
+        matcher,
+        progress,
+        &DefaultInteraction::default(),
+    )
+    .await
+}
+
+/// Same as [`analyze_root_with_progress`], but the user is asked whether to run the fixes of
+/// known errors with `interaction`, instead of on the terminal.
+#[instrument("scope analyze", skip_all, fields(run_id = %found_config.run_id))]
+pub async fn analyze_root_with_interaction(
+    found_config: &FoundConfig,
+    args: &AnalyzeArgs,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    interaction: &dyn UserInteraction,
+) -> Result<i32> {
+    let options = AnalyzeOptions::from(args);
+    let reporting = FoundErrorReporting {
+        format: args.format,
+        html_report: args.html_report.as_deref(),
+        interaction,
+    };
+    if args.format == AnalyzeOutputFormat::Sarif {
+        redirect_stdout_to_stderr();
+    }
+
     match &args.command {
-        AnalyzeCommands::Logs(args) => analyze_logs(found_config, args).await,
-        AnalyzeCommands::Command(args) => analyze_command(found_config, args).await,
+        AnalyzeCommands::Logs(logs_args) => {
+            analyze_logs(
+                found_config,
+                &options,
+                matcher,
+                progress,
+                &reporting,
+                logs_args,
+            )
+            .await
+        }
+        AnalyzeCommands::Command(command_args) => {
+            analyze_command(
+                found_config,
+                &options,
+                matcher,
+                progress,
+                &reporting,
+                command_args,
+            )
+            .await
+        }
+        AnalyzeCommands::Follow(_) if args.format == AnalyzeOutputFormat::Sarif => Err(anyhow!(
+            "`scope analyze follow` doesn't support `--format sarif`"
+        )),
+        AnalyzeCommands::Follow(_) if reporting.html_report.is_some() => Err(anyhow!(
+            "`scope analyze follow` doesn't support `--html-report`"
+        )),
+        AnalyzeCommands::Follow(follow_args) => {
+            analyze_follow(
+                found_config,
+                &options,
+                matcher,
+                progress,
+                interaction,
+                follow_args,
+            )
+            .await
+        }
     }
 }
 
-async fn analyze_logs(found_config: &FoundConfig, args: &AnalyzeLogsArgs) -> Result<i32> {
-    let has_known_error = match args.location.as_str() {
-        "-" => process_lines(found_config, read_from_stdin().await?).await?,
-        file_path => process_lines(found_config, read_from_file(file_path).await?).await?,
-    };
+/// A known error found by [`analyze_text`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownErrorMatch {
+    /// Full name of the known error, like `ScopeKnownError/disk-full`.
+    pub name: String,
+    pub severity: KnownErrorSeverity,
+    pub help_text: String,
+    /// Doctor action that fixes the known error, as `group/action`.
+    pub fix: Option<String>,
+    /// Line the known error was found on, starting at 1.
+    pub line_number: usize,
+    pub line: String,
+}
+
+/// The known errors in `text`, in the order they were found. Nothing is reported, fixes aren't
+/// offered and the statistics aren't changed, for tools that show the known errors their own
+/// way, like an editor.
+pub async fn analyze_text(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    text: &str,
+) -> Result<Vec<KnownErrorMatch>> {
+    let found_errors = process_lines(
+        found_config,
+        options,
+        matcher,
+        &NoOpProgressReporter,
+        text.as_bytes(),
+    )
+    .await?;
+
+    Ok(found_errors
+        .into_iter()
+        .map(|found| KnownErrorMatch {
+            name: found.known_error.full_name,
+            severity: found.known_error.severity,
+            help_text: found.known_error.help_text,
+            fix: found.known_error.fix_ref,
+            line_number: found.line_number,
+            line: found.line,
+        })
+        .collect())
+}
+
+/// How the known errors that were found are reported, and how the user is asked about their
+/// fixes.
+struct FoundErrorReporting<'a> {
+    format: AnalyzeOutputFormat,
+    /// File the HTML report is written to, see `--html-report`.
+    html_report: Option<&'a Path>,
+    interaction: &'a dyn UserInteraction,
+}
+
+/// A known error found by [`process_lines`], with where it was found.
+#[derive(Debug, Clone)]
+pub(super) struct FoundKnownError {
+    pub(super) known_error: KnownError,
+    /// Line the known error was found on, starting at 1. With `contextLines`, the last line of
+    /// the match.
+    pub(super) line_number: usize,
+    /// Where the line starts in the input, in bytes.
+    pub(super) byte_offset: usize,
+    pub(super) line: String,
+    /// Up to `reportContextLines` lines before `line`.
+    pub(super) lines_before: Vec<String>,
+    /// Up to `reportContextLines` lines after `line`.
+    pub(super) lines_after: Vec<String>,
+}
+
+async fn analyze_logs(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    reporting: &FoundErrorReporting<'_>,
+    args: &AnalyzeLogsArgs,
+) -> Result<i32> {
+    if args.locations.iter().any(|location| location == "-") {
+        if args.locations.len() > 1 {
+            return Err(anyhow!(
+                "`-` reads from stdin, it can't be combined with other locations"
+            ));
+        }
+        let input = read_from_stdin().await?;
+        let found_errors = process_lines(found_config, options, matcher, progress, input).await?;
+        return report_found_errors(
+            found_config,
+            options,
+            progress,
+            reporting,
+            &[(None, found_errors.as_slice())],
+        )
+        .await;
+    }
+
+    let files = expand_locations(
+        &DefaultGlobWalker::default(),
+        &found_config.working_dir,
+        &args.locations,
+    )?;
+    if let ([_], [file]) = (args.locations.as_slice(), files.as_slice()) {
+        let found_errors = analyze_file(found_config, options, matcher, progress, file).await?;
+        return report_found_errors(
+            found_config,
+            options,
+            progress,
+            reporting,
+            &[(Some(file.as_str()), found_errors.as_slice())],
+        )
+        .await;
+    }
+
+    let results: Vec<(String, Result<Vec<FoundKnownError>>)> = stream::iter(files)
+        .map(|file| async move {
+            let found_errors = analyze_file(found_config, options, matcher, progress, &file).await;
+            (file, found_errors)
+        })
+        .buffered(MAX_CONCURRENT_FILES)
+        .collect()
+        .await;
+
+    for line in file_summary(&found_config.working_dir, options, &results) {
+        info!(target: "always", "{}", line);
+    }
 
-    if has_known_error {
+    let names: Vec<_> = results
+        .iter()
+        .map(|(file, _)| display_file(&found_config.working_dir, file))
+        .collect();
+    let inputs: Vec<_> = results
+        .iter()
+        .zip(&names)
+        .filter_map(|((_, found_errors), name)| {
+            Some((Some(name.as_str()), found_errors.as_ref().ok()?.as_slice()))
+        })
+        .collect();
+    let exit_code =
+        report_found_errors(found_config, options, progress, reporting, &inputs).await?;
+
+    if results
+        .iter()
+        .any(|(_, found_errors)| found_errors.is_err())
+    {
         Ok(1)
     } else {
-        Ok(0)
+        Ok(exit_code)
     }
 }
 
-async fn analyze_command(found_config: &FoundConfig, args: &AnalyzeCommandArgs) -> Result<i32> {
-    let exec_runner = DefaultExecutionProvider::default();
+/// The files to analyze for `locations`. Globs and directories are expanded to the files they
+/// match, relative to `working_dir`. Other locations are kept as they are, so a missing file is
+/// reported when it's read.
+fn expand_locations(
+    glob_walker: &dyn GlobWalker,
+    working_dir: &Path,
+    locations: &[String],
+) -> Result<Vec<String>> {
+    let mut files = Vec::new();
+    for location in locations {
+        let glob = if location.contains(['*', '?', '[']) {
+            location.clone()
+        } else if working_dir.join(location).is_dir() {
+            format!("{}/**/*", location.trim_end_matches('/'))
+        } else {
+            files.push(location.clone());
+            continue;
+        };
+
+        let mut found: Vec<_> = glob_walker
+            .matching_files(&DoctorGroupCachePath {
+                paths: vec![glob],
+                base_path: working_dir.to_path_buf(),
+                ignore_paths: Vec::new(),
+                respect_gitignore: false,
+            })?
+            .into_iter()
+            .filter(|path| path.is_file())
+            .map(|path| path.display().to_string())
+            .collect();
+        if found.is_empty() {
+            warn!(target: "always", "`{}` didn't match any files", location);
+        }
+        found.sort();
+        files.extend(found);
+    }
+
+    let files: Vec<_> = files.into_iter().unique().collect();
+    if files.is_empty() {
+        return Err(anyhow!("No files matched {}", locations.join(", ")));
+    }
+    Ok(files)
+}
+
+/// `file` relative to `working_dir` when it's inside it, for output that's shorter to read and
+/// the same on every machine.
+fn display_file(working_dir: &Path, file: &str) -> String {
+    Path::new(file)
+        .strip_prefix(working_dir)
+        .map(|relative| relative.display().to_string())
+        .unwrap_or_else(|_| file.to_string())
+}
+
+/// Known errors found in `file_path`. A file that looks binary is skipped.
+async fn analyze_file(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    file_path: &str,
+) -> Result<Vec<FoundKnownError>> {
+    let mut reader = read_from_file(file_path).await?;
+    if looks_binary(&mut reader).await? {
+        warn!(target: "always", "{} looks like a binary file, skipping it", file_path);
+        return Ok(Vec::new());
+    }
+
+    process_lines(found_config, options, matcher, progress, reader).await
+}
+
+/// A table with a row for each file analyzed by `scope analyze logs`, with the known errors found
+/// in it and whether they fail the analysis.
+fn file_summary(
+    working_dir: &Path,
+    options: &AnalyzeOptions,
+    results: &[(String, Result<Vec<FoundKnownError>>)],
+) -> Vec<String> {
+    let header = [
+        "File".to_string(),
+        "Known errors".to_string(),
+        "Result".to_string(),
+    ];
+    let rows: Vec<_> = results
+        .iter()
+        .map(|(file, found_errors)| {
+            let file = display_file(working_dir, file);
+            match found_errors {
+                Ok(found_errors) => {
+                    let names = found_errors
+                        .iter()
+                        .map(|found| found.known_error.name())
+                        .unique()
+                        .join(", ");
+                    let failed = found_errors
+                        .iter()
+                        .any(|found| options.fails_on(&found.known_error));
+                    [
+                        file,
+                        if names.is_empty() {
+                            "-".to_string()
+                        } else {
+                            names
+                        },
+                        if failed { "failed" } else { "passed" }.to_string(),
+                    ]
+                }
+                Err(e) => [file, "-".to_string(), format!("unable to read: {}", e)],
+            }
+        })
+        .collect();
+
+    let width = |column: usize| {
+        std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or_default()
+    };
+    let (file_width, errors_width) = (width(0), width(1));
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|[file, errors, result]| {
+            format!(
+                "{:<file_width$}  {:<errors_width$}  {}",
+                file, errors, result
+            )
+        })
+        .collect()
+}
+
+async fn analyze_command(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    reporting: &FoundErrorReporting<'_>,
+    args: &AnalyzeCommandArgs,
+) -> Result<i32> {
+    let exec_runner = if args.pty {
+        DefaultExecutionProvider::with_pty()
+    } else {
+        DefaultExecutionProvider::default()
+    };
 
     let command = args.command.clone();
     let path = env::var("PATH").unwrap_or_default();
@@ -76,75 +600,597 @@ async fn analyze_command(found_config: &FoundConfig, args: &AnalyzeCommandArgs)
         path: &path,
         args: &command,
         output_dest: OutputDestination::StandardOutWithPrefix("analyzing".to_string()),
+        timeout: None,
+        progress: None,
     };
 
-    let has_known_error = process_lines(
+    progress.start_command(&command.join(" "));
+    let found_errors = process_lines(
         found_config,
+        options,
+        matcher,
+        progress,
         read_from_command(&exec_runner, capture_opts).await?,
     )
     .await?;
 
-    if has_known_error {
+    report_found_errors(
+        found_config,
+        options,
+        progress,
+        reporting,
+        &[(None, found_errors.as_slice())],
+    )
+    .await
+}
+
+/// Offer the fixes of the known errors that were found and, for SARIF, print them. With an
+/// `html_report`, they're written to an HTML page there too. `inputs` are the known errors
+/// found in each input, with the file that was analyzed, if any. Each fix is offered once, even
+/// when its known errors were found in several files.
+async fn report_found_errors(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    progress: &dyn ProgressReporter,
+    reporting: &FoundErrorReporting<'_>,
+    inputs: &[(Option<&str>, &[FoundKnownError])],
+) -> Result<i32> {
+    let found_errors = || {
+        inputs
+            .iter()
+            .flat_map(|(_, found_errors)| found_errors.iter())
+    };
+    let mut fixes = KnownErrorFixRegistry::new(options.max_fix_runs);
+    offer_fixes(
+        found_config,
+        reporting.interaction,
+        progress,
+        found_errors().map(|found| &found.known_error),
+        &mut fixes,
+    )
+    .await?;
+    let mut found = KnownErrorStats::default();
+    let now = chrono::Utc::now();
+    for found_error in found_errors() {
+        found.record_detected(found_error.known_error.name(), now);
+    }
+    record_stats(found, &fixes).await;
+
+    if reporting.format == AnalyzeOutputFormat::Sarif {
+        let sarif = SarifLog::new(inputs);
+        println!("{}", serde_json::to_string_pretty(&sarif)?);
+    }
+
+    if let Some(path) = reporting.html_report {
+        let html = HtmlReport::new(found_config, inputs, &fixes).render()?;
+        tokio::fs::write(path, html)
+            .await
+            .map_err(|e| anyhow!("unable to write {}. {}", path.display(), e))?;
+        info!(target: "always", "Wrote the HTML report to {}", path.display());
+    }
+
+    if found_errors().any(|found| options.fails_on(&found.known_error)) {
         Ok(1)
     } else {
         Ok(0)
     }
 }
 
-async fn process_lines<T>(found_config: &FoundConfig, input: T) -> Result<bool>
+/// Report known errors as lines are added to a file, until Ctrl-C is pressed. Every time a known
+/// error is found it's reported, but its fix is asked about only once and runs at most
+/// `max_fix_runs` times.
+async fn analyze_follow(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    interaction: &dyn UserInteraction,
+    args: &AnalyzeFollowArgs,
+) -> Result<i32> {
+    let mut follower = FileFollower::open(&args.location, args.from_start).await?;
+    let mut analyzer = LineAnalyzer::new(found_config, options, matcher, false);
+    let mut fixes = KnownErrorFixRegistry::new(options.max_fix_runs);
+    let mut found_any = false;
+    let mut found = KnownErrorStats::default();
+
+    info!(target: "always", "Following {}, press Ctrl-C to stop", args.location.display());
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    loop {
+        let line = tokio::select! {
+            _ = &mut ctrl_c => break,
+            line = follower.next_line() => line?,
+        };
+
+        let Some(line) = prepare_line(&line, options.max_line_length) else {
+            analyzer.skip_line();
+            continue;
+        };
+        progress.output_line(&line);
+        let found_errors = analyzer.analyze_line(line).await;
+        found_any |= found_errors.iter().any(|ke| options.fails_on(ke));
+        if args.fix {
+            offer_fixes(
+                found_config,
+                interaction,
+                progress,
+                &found_errors,
+                &mut fixes,
+            )
+            .await?;
+        }
+        let now = chrono::Utc::now();
+        for known_error in &found_errors {
+            found.record_detected(known_error.name(), now);
+        }
+    }
+    record_stats(found, &fixes).await;
+
+    if found_any {
+        Ok(1)
+    } else {
+        Ok(0)
+    }
+}
+
+#[instrument("analyze matching", skip_all, fields(known_error.count = found_config.known_error.len()))]
+async fn process_lines<T>(
+    found_config: &FoundConfig,
+    options: &AnalyzeOptions,
+    matcher: &dyn KnownErrorMatcher,
+    progress: &dyn ProgressReporter,
+    mut input: T,
+) -> Result<Vec<FoundKnownError>>
 where
     T: AsyncRead,
     T: AsyncBufReadExt,
     T: Unpin,
 {
-    let mut has_known_error = false;
-    let mut known_errors: BTreeMap<_, _> = found_config.known_error.clone();
-    let mut line_number = 0;
+    let mut found_errors: Vec<FoundKnownError> = Vec::new();
+    let mut analyzer = LineAnalyzer::new(found_config, options, matcher, true);
+    let started = Instant::now();
+    let mut binary_lines = 0;
+    let context_lines = options.report_context_lines;
+    let mut recent_lines: VecDeque<String> = VecDeque::with_capacity(context_lines + 1);
+    // Known errors, by index in `found_errors`, that still need lines after them.
+    let mut needs_lines_after: Vec<usize> = Vec::new();
+
+    let mut buffer = Vec::new();
+    let mut byte_offset = 0;
+    loop {
+        buffer.clear();
+        let read = input.read_until(b'\n', &mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        let text = String::from_utf8_lossy(&buffer);
+        let Some(line) = prepare_line(&text, options.max_line_length) else {
+            analyzer.skip_line();
+            binary_lines += 1;
+            byte_offset += read;
+            continue;
+        };
+        progress.output_line(&line);
+
+        needs_lines_after.retain(|idx| {
+            let lines_after = &mut found_errors[*idx].lines_after;
+            lines_after.push(line.clone());
+            lines_after.len() < context_lines
+        });
+        let matching = !analyzer.known_errors.is_empty();
+        let found = if matching {
+            analyzer.analyze_line(line.clone()).await
+        } else {
+            Vec::new()
+        };
+        for known_error in found {
+            if context_lines > 0 {
+                needs_lines_after.push(found_errors.len());
+            }
+            found_errors.push(FoundKnownError {
+                known_error,
+                line_number: analyzer.line_number,
+                byte_offset,
+                line: line.clone(),
+                lines_before: recent_lines.iter().cloned().collect(),
+                lines_after: Vec::new(),
+            });
+        }
+        byte_offset += read;
+        if context_lines > 0 {
+            if recent_lines.len() == context_lines {
+                recent_lines.pop_front();
+            }
+            recent_lines.push_back(line);
+        }
+
+        if matching && analyzer.known_errors.is_empty() {
+            info!(target: "always", "All known errors detected, ignoring rest of output.");
+        }
+        // The lines after the last known error are still read for reports.
+        if analyzer.known_errors.is_empty() && needs_lines_after.is_empty() {
+            break;
+        }
+    }
+
+    if binary_lines > 0 {
+        info!(target: "always", "Skipped {} lines that looked binary", binary_lines);
+    }
+    let elapsed = started.elapsed();
+    let lines_per_second = analyzer.line_number as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+    info!(
+        monotonic_counter.analyze_lines_scanned = analyzer.line_number as u64,
+        histogram.analyze_lines_per_second = lines_per_second,
+        "Scanned {} lines in {:?}, {:.0} lines per second",
+        analyzer.line_number,
+        elapsed,
+        lines_per_second
+    );
+
+    Ok(found_errors)
+}
+
+/// The line to match from `text`, without its line ending and cut to `max_line_length`. `None`
+/// when the line looks binary, since matching it is slow and can't find anything useful.
+fn prepare_line(text: &str, max_line_length: usize) -> Option<String> {
+    let line = text.trim_end_matches(['\n', '\r']);
+    if line.contains('\0') {
+        return None;
+    }
+    if max_line_length == 0 || line.len() <= max_line_length {
+        return Some(line.to_string());
+    }
+
+    let mut end = max_line_length;
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    debug!("Cutting a line of {} bytes to {}", line.len(), end);
+    Some(format!("{}{}", &line[..end], TRUNCATED_LINE_MARKER))
+}
+
+/// Whether the start of `reader` has a NUL byte, which text files don't.
+async fn looks_binary<R>(reader: &mut R) -> std::io::Result<bool>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let start = reader.fill_buf().await?;
+    Ok(start.iter().take(BINARY_CHECK_LEN).any(|byte| *byte == 0))
+}
+
+/// Matches lines against known errors one at a time, keeping the lines before them for known
+/// errors with `contextLines`.
+struct LineAnalyzer<'a> {
+    matcher: &'a dyn KnownErrorMatcher,
+    known_errors: Vec<KnownError>,
+    /// Built once from every known error, when `matcher` only matches with their regexes.
+    prefilter: Option<KnownErrorPrefilter>,
+    allow_multiple_matches: bool,
+    /// Stop looking for a known error once it's found. Otherwise it's reported every time.
+    report_once: bool,
+    recent_lines: VecDeque<String>,
+    max_context_lines: usize,
+    line_number: usize,
+    /// Line each known error was last found on, so a multi-line match isn't reported again
+    /// while its lines are still in context.
+    last_found: BTreeMap<String, usize>,
+}
+
+impl<'a> LineAnalyzer<'a> {
+    fn new(
+        found_config: &FoundConfig,
+        options: &AnalyzeOptions,
+        matcher: &'a dyn KnownErrorMatcher,
+        report_once: bool,
+    ) -> Self {
+        let mut known_errors: Vec<KnownError> =
+            found_config.known_error.values().cloned().collect();
+        known_errors.sort_by(|a, b| a.cmp_priority(b));
+        let max_context_lines = known_errors
+            .iter()
+            .map(|ke| ke.context_lines)
+            .max()
+            .unwrap_or_default();
+        let prefilter = if matcher.matches_regex() {
+            KnownErrorPrefilter::new(&known_errors)
+        } else {
+            None
+        };
+
+        Self {
+            matcher,
+            known_errors,
+            prefilter,
+            allow_multiple_matches: options.allow_multiple_matches,
+            report_once,
+            recent_lines: VecDeque::with_capacity(max_context_lines + 1),
+            max_context_lines,
+            line_number: 0,
+            last_found: BTreeMap::new(),
+        }
+    }
+
+    /// Count a line that isn't matched. It also breaks up the lines before it, so a multi-line
+    /// match can't span it.
+    fn skip_line(&mut self) {
+        self.recent_lines.clear();
+        self.line_number += 1;
+    }
 
-    let mut lines = input.lines();
+    /// Report the known errors found on `line`, most specific first.
+    async fn analyze_line(&mut self, line: String) -> Vec<KnownError> {
+        if self.recent_lines.len() > self.max_context_lines {
+            self.recent_lines.pop_front();
+        }
+        let candidates = self
+            .prefilter
+            .as_ref()
+            .map(|prefilter| prefilter.candidates(&line));
+        self.recent_lines.push_back(line);
+        let line_number = self.line_number;
+        self.line_number += 1;
+        // Every known error without `contextLines` is in the prefilter, so when it finds nothing
+        // only the others could match.
+        if self.max_context_lines == 0 && candidates.as_ref().is_some_and(|c| c.is_empty()) {
+            return Vec::new();
+        }
 
-    while let Some(line) = lines.next_line().await? {
-        let mut known_errors_to_remove = Vec::new();
-        for (name, ke) in &known_errors {
+        let mut found: Vec<(usize, KnownError)> = Vec::new();
+        for (idx, ke) in self.known_errors.iter().enumerate() {
+            if self
+                .last_found
+                .get(&ke.full_name)
+                .is_some_and(|last| line_number <= last + ke.context_lines)
+            {
+                continue;
+            }
+            if candidates.as_ref().is_some_and(|c| !c.contains(ke)) {
+                continue;
+            }
             debug!("Checking known error {}", ke.name());
-            if ke.regex.is_match(&line) {
-                warn!(target: "always", "Known error '{}' found on line {}", ke.name(), line_number);
-                info!(target: "always", "\t==> {}", ke.help_text);
-                known_errors_to_remove.push(name.clone());
-                has_known_error = true;
+            let text = with_context(&self.recent_lines, ke.context_lines);
+            if self.matcher.is_match(ke, &text).await {
+                debug!(
+                    known_error.name = ke.name(),
+                    line = line_number,
+                    "known error matched"
+                );
+                if ke.severity == KnownErrorSeverity::Info {
+                    info!(target: "always", "Known error '{}' found on line {} [{}]", ke.name(), line_number, ke.severity);
+                } else {
+                    warn!(target: "always", "Known error '{}' found on line {} [{}]", ke.name(), line_number, ke.severity);
+                }
+                let matched = ke.for_match(&text);
+                info!(target: "always", "\t==> {}", matched.help_text);
+                found.push((idx, matched));
+                if !self.allow_multiple_matches {
+                    break;
+                }
             }
         }
 
-        for name in known_errors_to_remove {
-            known_errors.remove(&name);
+        if self.report_once {
+            for (idx, _) in found.iter().rev() {
+                self.known_errors.remove(*idx);
+            }
+        } else {
+            for (_, ke) in &found {
+                self.last_found.insert(ke.full_name.clone(), line_number);
+            }
         }
+        found.into_iter().map(|(_, ke)| ke).collect()
+    }
+}
 
-        line_number += 1;
+/// The last line, preceded by up to `context_lines` lines before it, joined with `\n`.
+fn with_context(recent_lines: &VecDeque<String>, context_lines: usize) -> String {
+    let skip = recent_lines.len().saturating_sub(context_lines + 1);
+    recent_lines.iter().skip(skip).join("\n")
+}
 
-        if known_errors.is_empty() {
-            info!(target: "always", "All known errors detected, ignoring rest of output.");
-            break;
+/// Add `found`, the counts of the known errors that were found, and how their fixes went to the
+/// stats for `scope stats`. Stats that can't be saved are only logged, they shouldn't fail the
+/// analysis.
+async fn record_stats(mut found: KnownErrorStats, fixes: &KnownErrorFixRegistry) {
+    fixes.record_stats(&mut found);
+    let path = KnownErrorStats::default_path();
+    let update_path = path.clone();
+    let saved = tokio::task::spawn_blocking(move || {
+        KnownErrorStats::update(&update_path, |stats| stats.add(&found))
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.into()));
+    if let Err(e) = saved {
+        debug!(
+            "Unable to save known error stats to {}: {:?}",
+            path.display(),
+            e
+        );
+    }
+}
+
+/// Offer to run the doctor fixes referenced by the known errors that were found. Each fix is
+/// asked about once, even when several known errors reference it; `fixes` remembers the answers
+/// and how often each fix ran.
+async fn offer_fixes<'a>(
+    found_config: &FoundConfig,
+    interaction: &dyn UserInteraction,
+    progress: &dyn ProgressReporter,
+    found_errors: impl IntoIterator<Item = &'a KnownError>,
+    fixes: &mut KnownErrorFixRegistry,
+) -> Result<()> {
+    let exec_runner = DefaultExecutionProvider::default();
+
+    for (known_error, fix) in found_errors
+        .into_iter()
+        .filter_map(|ke| ke.fix.as_ref().map(|fix| (ke, fix)))
+    {
+        match fixes.decide(known_error, fix) {
+            FixDecision::Skip => continue,
+            FixDecision::Run => {
+                info!(target: "always", "\t==> Running the fix from `{}/{}` again, it was approved earlier", fix.group, fix.action);
+            }
+            FixDecision::Ask if fix.fix.command.is_none() => {
+                if let Some(help_text) = &fix.fix.help_text {
+                    info!(target: "always", "\t==> {}", help_text);
+                }
+                // There's nothing to run, the help only needs to be shown once.
+                fixes.record_answer(known_error, fix, PromptAnswer::Denied);
+                continue;
+            }
+            FixDecision::Ask => {
+                let default_prompt = format!("Run the fix from `{}/{}`?", fix.group, fix.action);
+                let (prompt, help_text) = match &fix.fix.prompt {
+                    Some(prompt) => (prompt.text.as_str(), prompt.extra_context.as_deref()),
+                    None => (default_prompt.as_str(), None),
+                };
+
+                progress.fix_prompt(prompt);
+                let answer = interaction.confirm(prompt, help_text);
+                fixes.record_answer(known_error, fix, answer);
+                match answer {
+                    PromptAnswer::Approved => {}
+                    PromptAnswer::Denied => continue,
+                    PromptAnswer::NotInteractive => {
+                        info!(target: "always", "\t==> A fix is available, run `scope doctor run --only {}` to apply it", fix.group);
+                        continue;
+                    }
+                }
+            }
         }
+
+        fixes.record_run(fix);
+        let succeeded = run_fix(found_config, &exec_runner, progress, known_error, fix).await?;
+        fixes.record_result(fix, succeeded);
     }
 
-    Ok(has_known_error)
+    Ok(())
+}
+
+#[instrument("analyze fix", skip_all, fields(run_id = %found_config.run_id, known_error.name = known_error.name(), model.name = fix.group, action.name = fix.action))]
+async fn run_fix(
+    found_config: &FoundConfig,
+    exec_runner: &DefaultExecutionProvider,
+    progress: &dyn ProgressReporter,
+    known_error: &KnownError,
+    fix: &KnownErrorFix,
+) -> Result<bool> {
+    let commands = fix
+        .fix
+        .command
+        .as_ref()
+        .map(|command| command.commands.clone())
+        .unwrap_or_default();
+
+    for command in commands {
+        progress.start_command(&command);
+        let args = vec![command];
+        let capture = exec_runner
+            .run_command(CaptureOpts {
+                working_dir: fix
+                    .working_dir
+                    .as_deref()
+                    .unwrap_or(&found_config.working_dir),
+                args: &args,
+                output_dest: OutputDestination::StandardOutWithPrefix(format!(
+                    "{}/{}",
+                    fix.group, fix.action
+                )),
+                path: &fix.exec_path,
+                env_vars: action_env_vars(&fix.env),
+                timeout: None,
+                progress: Some(progress),
+            })
+            .await?;
+
+        if capture.exit_code != Some(0) {
+            warn!(target: "always", "Fix from `{}/{}` failed", fix.group, fix.action);
+            return Ok(false);
+        }
+    }
+
+    info!(target: "always", "Fix from `{}/{}` succeeded", fix.group, fix.action);
+    Ok(true)
 }
 
 async fn read_from_command(
     exec_runner: &DefaultExecutionProvider,
     capture_opts: CaptureOpts<'_>,
-) -> Result<BufReader<Cursor<String>>, CaptureError> {
+) -> Result<SpooledReader, CaptureError> {
     let output = exec_runner.run_command(capture_opts).await?;
 
-    let cursor = Cursor::new(output.generate_user_output());
-
-    Ok(BufReader::new(cursor))
+    Ok(output.user_output_reader().await?)
 }
 
 async fn read_from_stdin() -> Result<BufReader<Stdin>, AnalyzeError> {
     Ok(BufReader::new(tokio::io::stdin()))
 }
 
+/// How often [`FileFollower`] checks for new lines once it has read everything.
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Reads the lines added to a file, like `tail -f`. When the file is truncated, for example by log
+/// rotation, it's read again from the start.
+struct FileFollower {
+    path: PathBuf,
+    reader: BufReader<File>,
+    position: u64,
+    /// A line that's been partly written.
+    pending: Vec<u8>,
+}
+
+impl FileFollower {
+    async fn open(path: &Path, from_start: bool) -> Result<Self, AnalyzeError> {
+        if !path.exists() {
+            return Err(AnalyzeError::FileNotFound {
+                file_name: path.display().to_string(),
+            });
+        }
+        let mut file = File::open(path).await?;
+        let position = if from_start {
+            0
+        } else {
+            file.seek(SeekFrom::End(0)).await?
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            position,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Wait for the next complete line, without the line ending.
+    async fn next_line(&mut self) -> Result<String, AnalyzeError> {
+        loop {
+            let read = self.reader.read_until(b'\n', &mut self.pending).await?;
+            self.position += read as u64;
+            if self.pending.ends_with(b"\n") {
+                let line = String::from_utf8_lossy(&self.pending)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string();
+                self.pending.clear();
+                return Ok(line);
+            }
+
+            if read == 0 {
+                if tokio::fs::metadata(&self.path).await?.len() < self.position {
+                    debug!(
+                        "{} was truncated, reading from the start",
+                        self.path.display()
+                    );
+                    self.reader.seek(SeekFrom::Start(0)).await?;
+                    self.position = 0;
+                    self.pending.clear();
+                }
+                tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
 async fn read_from_file(file_name: &str) -> Result<BufReader<File>, AnalyzeError> {
     let file_path = PathBuf::from(file_name);
     if !file_path.exists() {
@@ -154,3 +1200,449 @@ async fn read_from_file(file_name: &str) -> Result<BufReader<File>, AnalyzeError
     }
     Ok(BufReader::new(File::open(file_path).await?))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::shared::prelude::{
+        DoctorGroupActionCommand, DoctorGroupActionFixBuilder, RecordingProgressReporter,
+    };
+    use assert_fs::prelude::*;
+    use async_trait::async_trait;
+    use regex::Regex;
+    use std::io::Cursor;
+
+    /// Matches when the line contains the known error's name, ignoring the configured pattern.
+    struct NameMatcher;
+
+    #[async_trait]
+    impl KnownErrorMatcher for NameMatcher {
+        async fn is_match(&self, known_error: &KnownError, input: &str) -> bool {
+            input.contains(&known_error.metadata.name)
+        }
+    }
+
+    fn known_error(name: &str, pattern: &str) -> KnownError {
+        KnownError {
+            full_name: format!("ScopeKnownError/{}", name),
+            metadata: ModelMetadata::new(name),
+            pattern: pattern.to_string(),
+            regex: Regex::new(pattern).unwrap(),
+            help_text: "help".to_string(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines: 0,
+            fix_ref: None,
+            fix: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_uses_matcher() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        for ke in [
+            known_error("disk", "no space"),
+            known_error("auth", "denied"),
+        ] {
+            found_config.known_error.insert(ke.name().to_string(), ke);
+        }
+        let input = || BufReader::new(Cursor::new("the disk is full\nauth expired\n".to_string()));
+        let options = AnalyzeOptions::default();
+
+        let found = process_lines(
+            &found_config,
+            &options,
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input(),
+        )
+        .await?;
+        assert!(found.is_empty());
+
+        let found = process_lines(
+            &found_config,
+            &options,
+            &NameMatcher,
+            &NoOpProgressReporter,
+            input(),
+        )
+        .await?;
+        assert_eq!(
+            vec!["disk", "auth"],
+            found
+                .iter()
+                .map(|found| found.known_error.name())
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_reports_each_line() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let ke = known_error("disk", "no space");
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let input = BufReader::new(Cursor::new(
+            "starting\nno space left\nignored\n".to_string(),
+        ));
+        let progress = RecordingProgressReporter::default();
+        let options = AnalyzeOptions {
+            report_context_lines: 0,
+            ..Default::default()
+        };
+
+        process_lines(
+            &found_config,
+            &options,
+            &RegexKnownErrorMatcher,
+            &progress,
+            input,
+        )
+        .await?;
+        assert_eq!(
+            vec!["output starting", "output no space left"],
+            progress.events()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_matches_across_context_lines() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let mut traceback = known_error("traceback", "^Traceback.*\\n(?:.*\\n)*KeyError");
+        traceback.regex = regex::RegexBuilder::new(&traceback.pattern)
+            .multi_line(true)
+            .build()
+            .unwrap();
+        traceback.context_lines = 3;
+        let single_line = known_error("single-line", "^Traceback.*\\n(?:.*\\n)*KeyError");
+        for ke in [traceback, single_line] {
+            found_config.known_error.insert(ke.name().to_string(), ke);
+        }
+        let input = BufReader::new(Cursor::new(
+            "start\nTraceback (most recent call last):\n  File \"main.py\"\nKeyError: 'a'\n"
+                .to_string(),
+        ));
+
+        let found = process_lines(
+            &found_config,
+            &AnalyzeOptions::default(),
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input,
+        )
+        .await?;
+        assert_eq!(
+            vec!["traceback"],
+            found
+                .iter()
+                .map(|found| found.known_error.name())
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_tracks_line_and_offset() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let ke = known_error("disk", "no space");
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let input = BufReader::new(Cursor::new(
+            "starting\r\nwriting\nno space left\n".to_string(),
+        ));
+
+        let found = process_lines(
+            &found_config,
+            &AnalyzeOptions::default(),
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input,
+        )
+        .await?;
+        assert_eq!(1, found.len());
+        assert_eq!(3, found[0].line_number);
+        assert_eq!(18, found[0].byte_offset);
+        assert_eq!("no space left", found[0].line);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_renders_captures() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let mut ke = known_error("port-in-use", r"port (?P<port>\d+) is in use");
+        ke.help_text = "Port {{ port }} is already in use".to_string();
+        ke.fix = Some(KnownErrorFix {
+            group: "server".to_string(),
+            action: "port".to_string(),
+            exec_path: String::new(),
+            working_dir: None,
+            env: BTreeMap::new(),
+            fix: DoctorGroupActionFixBuilder::default()
+                .command(Some(DoctorGroupActionCommand::from(vec![
+                    "npx kill-port {{ captures.port }}",
+                ])))
+                .build()
+                .unwrap(),
+        });
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let input = BufReader::new(Cursor::new("error: port 8080 is in use\n".to_string()));
+
+        let found = process_lines(
+            &found_config,
+            &AnalyzeOptions::default(),
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input,
+        )
+        .await?;
+        let known_error = &found[0].known_error;
+        assert_eq!("Port 8080 is already in use", known_error.help_text);
+        assert_eq!(
+            vec!["npx kill-port '8080'"],
+            known_error
+                .fix
+                .as_ref()
+                .unwrap()
+                .fix
+                .command
+                .as_ref()
+                .unwrap()
+                .commands
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_cuts_long_lines_and_skips_binary() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let ke = known_error("disk", "no space");
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let options = AnalyzeOptions {
+            max_line_length: 13,
+            ..Default::default()
+        };
+        let mut input = b"\x7fELF\0\0\0no space\n".to_vec();
+        input.extend(b"no space left on device\n");
+        let input = BufReader::new(Cursor::new(input));
+
+        let found = process_lines(
+            &found_config,
+            &options,
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input,
+        )
+        .await?;
+        assert_eq!(1, found.len());
+        assert_eq!(2, found[0].line_number);
+        assert_eq!("no space left [truncated]", found[0].line);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_lines_keeps_lines_around_known_errors() -> Result<()> {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let ke = known_error("disk", "no space");
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let options = AnalyzeOptions {
+            report_context_lines: 2,
+            ..Default::default()
+        };
+        let input = BufReader::new(Cursor::new(
+            "one\ntwo\nthree\nno space left\nfive\nsix\nseven\n".to_string(),
+        ));
+
+        let found = process_lines(
+            &found_config,
+            &options,
+            &RegexKnownErrorMatcher,
+            &NoOpProgressReporter,
+            input,
+        )
+        .await?;
+        assert_eq!(1, found.len());
+        assert_eq!(4, found[0].line_number);
+        assert_eq!(vec!["two", "three"], found[0].lines_before);
+        assert_eq!(vec!["five", "six"], found[0].lines_after);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_looks_binary() -> Result<()> {
+        let mut text = BufReader::new(Cursor::new(b"plain text\n".to_vec()));
+        let mut binary = BufReader::new(Cursor::new(b"\x7fELF\x02\x01\0\0".to_vec()));
+
+        assert!(!looks_binary(&mut text).await?);
+        assert!(looks_binary(&mut binary).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_follow_reports_every_occurrence() {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let ke = known_error("disk", "no space");
+        found_config.known_error.insert(ke.name().to_string(), ke);
+        let options = AnalyzeOptions::default();
+        let mut analyzer =
+            LineAnalyzer::new(&found_config, &options, &RegexKnownErrorMatcher, false);
+
+        for (line, expected) in [("no space left", 1), ("ok", 0), ("no space left", 1)] {
+            assert_eq!(
+                expected,
+                analyzer.analyze_line(line.to_string()).await.len()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_follower_reads_new_lines() -> Result<()> {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("server.log");
+        std::fs::write(&path, "old line\n")?;
+        let append = |text: &str| {
+            let mut file = std::fs::OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .unwrap();
+            std::io::Write::write_all(&mut file, text.as_bytes()).unwrap();
+        };
+
+        let mut follower = FileFollower::open(&path, false).await?;
+        append("first\nsec");
+        let next = tokio::spawn(async move {
+            let first = follower.next_line().await.unwrap();
+            let second = follower.next_line().await.unwrap();
+            (first, second)
+        });
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL * 2).await;
+        append("ond\r\n");
+
+        assert_eq!(
+            ("first".to_string(), "second".to_string()),
+            next.await.unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_options_from_json() {
+        let options: AnalyzeOptions =
+            serde_json::from_str(r#"{"allowMultipleMatches": true}"#).unwrap();
+        assert!(options.allow_multiple_matches);
+
+        let options: AnalyzeOptions = serde_json::from_str("{}").unwrap();
+        assert_eq!(AnalyzeOptions::default(), options);
+        assert_eq!(DEFAULT_MAX_LINE_LENGTH, options.max_line_length);
+        assert_eq!(DEFAULT_MAX_FIX_RUNS, options.max_fix_runs);
+        assert_eq!(KnownErrorSeverity::Error, options.fail_on);
+    }
+
+    #[test]
+    fn test_fail_on_threshold() {
+        let mut warning = known_error("deprecated", "is deprecated");
+        warning.severity = KnownErrorSeverity::Warning;
+        let error = known_error("disk", "no space");
+
+        let options: AnalyzeOptions = serde_json::from_str(r#"{"failOn": "warning"}"#).unwrap();
+        assert!(options.fails_on(&warning));
+        assert!(options.fails_on(&error));
+
+        let options = AnalyzeOptions::default();
+        assert!(!options.fails_on(&warning));
+        assert!(options.fails_on(&error));
+    }
+
+    #[test]
+    fn test_expand_locations() -> Result<()> {
+        let dir = assert_fs::TempDir::new()?;
+        for file in [
+            "logs/build.log",
+            "logs/test.log",
+            "logs/nested/deploy.log",
+            "notes.txt",
+        ] {
+            dir.child(file).touch()?;
+        }
+        let expand = |locations: &[&str]| {
+            let locations: Vec<_> = locations.iter().map(|l| l.to_string()).collect();
+            expand_locations(&DefaultGlobWalker::default(), dir.path(), &locations).map(|files| {
+                files
+                    .iter()
+                    .map(|file| display_file(dir.path(), file))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            vec!["logs/build.log", "logs/test.log"],
+            expand(&["logs/*.log"])?
+        );
+        assert_eq!(
+            vec!["logs/build.log", "logs/test.log"],
+            expand(&["logs/*.log", "logs/build.*"])?
+        );
+        assert_eq!(
+            vec![
+                "logs/build.log",
+                "logs/nested/deploy.log",
+                "logs/test.log",
+                "missing.log"
+            ],
+            expand(&["logs", "missing.log"])?
+        );
+        assert!(expand(&["*.json"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_summary() {
+        let mut warning = known_error("deprecated", "is deprecated");
+        warning.severity = KnownErrorSeverity::Warning;
+        let found = |known_error: &KnownError| FoundKnownError {
+            known_error: known_error.clone(),
+            line_number: 1,
+            byte_offset: 0,
+            line: String::new(),
+            lines_before: Vec::new(),
+            lines_after: Vec::new(),
+        };
+        let results = vec![
+            ("/repo/build.log".to_string(), Ok(Vec::new())),
+            (
+                "/repo/logs/test.log".to_string(),
+                Ok(vec![
+                    found(&warning),
+                    found(&known_error("disk", "no space")),
+                    found(&warning),
+                ]),
+            ),
+            ("/other/deploy.log".to_string(), Ok(vec![found(&warning)])),
+            (
+                "/repo/missing.log".to_string(),
+                Err(anyhow!("No such file")),
+            ),
+        ];
+
+        assert_eq!(
+            vec![
+                "File               Known errors      Result",
+                "build.log          -                 passed",
+                "logs/test.log      deprecated, disk  failed",
+                "/other/deploy.log  deprecated        passed",
+                "missing.log        -                 unable to read: No such file",
+            ],
+            file_summary(Path::new("/repo"), &AnalyzeOptions::default(), &results)
+        );
+    }
+}
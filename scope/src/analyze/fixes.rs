@@ -0,0 +1,270 @@
+use crate::models::HelpMetadata;
+use crate::shared::prelude::{FixCounts, KnownError, KnownErrorFix, KnownErrorStats, PromptAnswer};
+use std::collections::BTreeMap;
+
+/// Default for `--max-fix-runs`.
+pub const DEFAULT_MAX_FIX_RUNS: usize = 1;
+
+/// What to do with the fix of a known error that was found, decided by [`KnownErrorFixRegistry`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixDecision {
+    /// The fix hasn't come up yet, ask before running it.
+    Ask,
+    /// The fix was approved earlier in the run, run it again without asking.
+    Run,
+    /// The fix was declined earlier, couldn't be asked about, or already ran as often as allowed.
+    Skip,
+}
+
+/// What happened to the fix of a known error by the end of a run, for reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixOutcome {
+    /// The fix ran, and its last run succeeded.
+    Succeeded,
+    /// The fix ran, and its last run failed.
+    Failed,
+    /// The user declined to run the fix.
+    Declined,
+    /// The fix couldn't be asked about, because there wasn't a terminal.
+    NotAsked,
+    /// The fix only has help text, there's nothing to run.
+    HelpOnly,
+}
+
+impl FixOutcome {
+    pub fn description(&self) -> &'static str {
+        match self {
+            FixOutcome::Succeeded => "ran and succeeded",
+            FixOutcome::Failed => "ran and failed",
+            FixOutcome::Declined => "declined",
+            FixOutcome::NotAsked => "not run, there was no terminal to ask on",
+            FixOutcome::HelpOnly => "help only, nothing to run",
+        }
+    }
+}
+
+/// Fixes of known errors that came up during one run, so the same error matching again doesn't
+/// ask or run its fix over and over.
+///
+/// Answers are remembered by known error name, and shared with every known error that uses the
+/// same fix. A fix runs at most `max_runs` times, `0` allows any number of runs.
+#[derive(Debug, Clone)]
+pub struct KnownErrorFixRegistry {
+    max_runs: usize,
+    /// The answer for each known error, with the fix it was given for.
+    answers: BTreeMap<String, (String, PromptAnswer)>,
+    /// How many times each fix ran, keyed by `group/action`.
+    runs: BTreeMap<String, usize>,
+    /// Whether the last run of each fix succeeded, keyed by `group/action`.
+    results: BTreeMap<String, bool>,
+    /// Every run and decline of each fix, for [`KnownErrorStats`], keyed by `group/action`.
+    counts: BTreeMap<String, FixCounts>,
+}
+
+impl Default for KnownErrorFixRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_FIX_RUNS)
+    }
+}
+
+impl KnownErrorFixRegistry {
+    pub fn new(max_runs: usize) -> Self {
+        Self {
+            max_runs,
+            answers: BTreeMap::new(),
+            runs: BTreeMap::new(),
+            results: BTreeMap::new(),
+            counts: BTreeMap::new(),
+        }
+    }
+
+    /// Whether to ask about, run or skip the fix of `known_error`.
+    pub fn decide(&self, known_error: &KnownError, fix: &KnownErrorFix) -> FixDecision {
+        let fix_name = fix_name(fix);
+        let runs = self.runs.get(&fix_name).copied().unwrap_or_default();
+        if self.max_runs != 0 && runs >= self.max_runs {
+            return FixDecision::Skip;
+        }
+
+        let answer = self
+            .answers
+            .get(known_error.name())
+            .or_else(|| self.answers.values().find(|(name, _)| *name == fix_name))
+            .map(|(_, answer)| *answer);
+        match answer {
+            None => FixDecision::Ask,
+            Some(PromptAnswer::Approved) => FixDecision::Run,
+            Some(_) => FixDecision::Skip,
+        }
+    }
+
+    /// Remember how the user answered when asked about the fix of `known_error`.
+    pub fn record_answer(
+        &mut self,
+        known_error: &KnownError,
+        fix: &KnownErrorFix,
+        answer: PromptAnswer,
+    ) {
+        if answer == PromptAnswer::Denied && fix.fix.command.is_some() {
+            self.counts.entry(fix_name(fix)).or_default().declined += 1;
+        }
+        self.answers
+            .insert(known_error.name().to_string(), (fix_name(fix), answer));
+    }
+
+    /// Count a run of `fix`, whether or not it succeeded.
+    pub fn record_run(&mut self, fix: &KnownErrorFix) {
+        *self.runs.entry(fix_name(fix)).or_default() += 1;
+    }
+
+    /// Remember whether a run of `fix` succeeded, replacing the result of an earlier run.
+    pub fn record_result(&mut self, fix: &KnownErrorFix, succeeded: bool) {
+        let counts = self.counts.entry(fix_name(fix)).or_default();
+        if succeeded {
+            counts.succeeded += 1;
+        } else {
+            counts.failed += 1;
+        }
+        self.results.insert(fix_name(fix), succeeded);
+    }
+
+    /// What happened to the fix of `known_error`. `None` when it never came up, e.g. because it
+    /// already ran as often as allowed for another known error.
+    pub fn outcome(&self, known_error: &KnownError, fix: &KnownErrorFix) -> Option<FixOutcome> {
+        if fix.fix.command.is_none() {
+            return Some(FixOutcome::HelpOnly);
+        }
+        let fix_name = fix_name(fix);
+        if let Some(succeeded) = self.results.get(&fix_name) {
+            return Some(if *succeeded {
+                FixOutcome::Succeeded
+            } else {
+                FixOutcome::Failed
+            });
+        }
+
+        let answer = self
+            .answers
+            .get(known_error.name())
+            .or_else(|| self.answers.values().find(|(name, _)| *name == fix_name))
+            .map(|(_, answer)| *answer)?;
+        match answer {
+            PromptAnswer::Approved => None,
+            PromptAnswer::Denied => Some(FixOutcome::Declined),
+            PromptAnswer::NotInteractive => Some(FixOutcome::NotAsked),
+        }
+    }
+
+    /// Add the runs and declines of fixes during this run to `stats`.
+    pub fn record_stats(&self, stats: &mut KnownErrorStats) {
+        for (fix_name, counts) in &self.counts {
+            stats.fixes.entry(fix_name.clone()).or_default().add(counts);
+        }
+    }
+
+    /// How many times `fix` ran.
+    pub fn run_count(&self, fix: &KnownErrorFix) -> usize {
+        self.runs.get(&fix_name(fix)).copied().unwrap_or_default()
+    }
+}
+
+fn fix_name(fix: &KnownErrorFix) -> String {
+    format!("{}/{}", fix.group, fix.action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::shared::prelude::{
+        DoctorGroupActionCommand, DoctorGroupActionFixBuilder, KnownErrorSeverity,
+    };
+    use regex::Regex;
+
+    fn known_error(name: &str) -> KnownError {
+        KnownError {
+            full_name: format!("ScopeKnownError/{}", name),
+            metadata: ModelMetadata::new(name),
+            pattern: "error".to_string(),
+            regex: Regex::new("error").unwrap(),
+            help_text: String::new(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines: 0,
+            fix_ref: Some("node/install".to_string()),
+            fix: None,
+        }
+    }
+
+    fn fix() -> KnownErrorFix {
+        KnownErrorFix {
+            group: "node".to_string(),
+            action: "install".to_string(),
+            exec_path: String::new(),
+            working_dir: None,
+            env: BTreeMap::new(),
+            fix: DoctorGroupActionFixBuilder::default().build().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_answers_are_remembered_and_runs_are_capped() {
+        let (missing, outdated, fix) = (known_error("missing"), known_error("outdated"), fix());
+        let mut registry = KnownErrorFixRegistry::new(2);
+
+        assert_eq!(FixDecision::Ask, registry.decide(&missing, &fix));
+        registry.record_answer(&missing, &fix, PromptAnswer::Approved);
+        registry.record_run(&fix);
+
+        assert_eq!(FixDecision::Run, registry.decide(&missing, &fix));
+        assert_eq!(FixDecision::Run, registry.decide(&outdated, &fix));
+        registry.record_run(&fix);
+
+        assert_eq!(2, registry.run_count(&fix));
+        assert_eq!(FixDecision::Skip, registry.decide(&missing, &fix));
+
+        let mut registry = KnownErrorFixRegistry::default();
+        registry.record_answer(&missing, &fix, PromptAnswer::Denied);
+        assert_eq!(FixDecision::Skip, registry.decide(&missing, &fix));
+        assert_eq!(FixDecision::Skip, registry.decide(&outdated, &fix));
+    }
+
+    #[test]
+    fn test_outcomes() {
+        let (missing, outdated) = (known_error("missing"), known_error("outdated"));
+        let mut fix = fix();
+        let mut registry = KnownErrorFixRegistry::default();
+        assert_eq!(Some(FixOutcome::HelpOnly), registry.outcome(&missing, &fix));
+
+        fix.fix.command = Some(DoctorGroupActionCommand::from(vec!["install.sh"]));
+        assert_eq!(None, registry.outcome(&missing, &fix));
+        registry.record_answer(&missing, &fix, PromptAnswer::NotInteractive);
+        assert_eq!(
+            Some(FixOutcome::NotAsked),
+            registry.outcome(&outdated, &fix)
+        );
+
+        registry.record_answer(&missing, &fix, PromptAnswer::Approved);
+        registry.record_run(&fix);
+        registry.record_result(&fix, false);
+        assert_eq!(Some(FixOutcome::Failed), registry.outcome(&missing, &fix));
+        registry.record_result(&fix, true);
+        assert_eq!(
+            Some(FixOutcome::Succeeded),
+            registry.outcome(&outdated, &fix)
+        );
+        registry.record_answer(&outdated, &fix, PromptAnswer::Denied);
+
+        let mut stats = KnownErrorStats::default();
+        registry.record_stats(&mut stats);
+        registry.record_stats(&mut stats);
+        assert_eq!(
+            FixCounts {
+                succeeded: 2,
+                failed: 2,
+                declined: 2
+            },
+            stats.fixes["node/install"]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use super::cli::FoundKnownError;
+use super::fixes::KnownErrorFixRegistry;
+use crate::models::HelpMetadata;
+use crate::shared::prelude::FoundConfig;
+use anyhow::Result;
+use minijinja::Environment;
+use serde::Serialize;
+
+/// The `.html` extension turns on HTML escaping for everything the template prints.
+const TEMPLATE_NAME: &str = "analyze-report.html";
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>scope analyze: {{ total }} known error{{ "" if total == 1 else "s" }} found</title>
+<style>
+  body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", sans-serif; margin: 2em auto; max-width: 70em; padding: 0 1em; color: #1f2328; }
+  h2 { border-bottom: 1px solid #d0d7de; padding-bottom: .3em; }
+  .finding { border: 1px solid #d0d7de; border-radius: 6px; margin: 1em 0; padding: 0 1em 1em; }
+  .severity { border-radius: 1em; font-size: .8em; padding: .1em .6em; color: #fff; }
+  .severity.error { background: #cf222e; }
+  .severity.warning { background: #9a6700; }
+  .severity.info { background: #0969da; }
+  .meta { color: #59636e; }
+  pre { background: #f6f8fa; border-radius: 6px; overflow-x: auto; padding: .5em 0; }
+  pre span { display: block; padding: 0 1em; }
+  pre span.match { background: #ffebe9; font-weight: bold; }
+  .number { color: #59636e; display: inline-block; min-width: 4em; user-select: none; }
+</style>
+</head>
+<body>
+<h1>scope analyze</h1>
+<p class="meta">Run {{ run_id }}, scope {{ scope_version }}. {{ total }} known error{{ "" if total == 1 else "s" }} found.</p>
+{% for input in inputs %}
+<h2>{{ input.name }}</h2>
+{% for finding in input.findings %}
+<div class="finding">
+  <h3>{{ finding.name }} <span class="severity {{ finding.severity }}">{{ finding.severity }}</span></h3>
+  <p class="meta">{{ finding.description }}. Found on line {{ finding.line_number }}.</p>
+  <pre>{% for line in finding.lines %}<span{% if line.matched %} class="match"{% endif %}><span class="number">{{ line.number }}</span>{{ line.text }}</span>{% endfor %}</pre>
+  <p>{{ finding.help_text }}</p>
+  {% if finding.help_url %}<p><a href="{{ finding.help_url }}">{{ finding.help_url }}</a></p>{% endif %}
+  {% if finding.fix %}<p>Fix <code>{{ finding.fix }}</code>: {{ finding.fix_outcome or "not run" }}</p>{% endif %}
+</div>
+{% else %}
+<p>No known errors found.</p>
+{% endfor %}
+{% endfor %}
+</body>
+</html>
+"#;
+
+/// Known errors found by `scope analyze`, as a standalone HTML page for `--html-report`.
+#[derive(Debug, Serialize)]
+pub(super) struct HtmlReport {
+    run_id: String,
+    scope_version: &'static str,
+    total: usize,
+    inputs: Vec<HtmlInput>,
+}
+
+#[derive(Debug, Serialize)]
+struct HtmlInput {
+    name: String,
+    findings: Vec<HtmlFinding>,
+}
+
+#[derive(Debug, Serialize)]
+struct HtmlFinding {
+    name: String,
+    description: String,
+    severity: String,
+    line_number: usize,
+    lines: Vec<HtmlLine>,
+    help_text: String,
+    /// Only `http` and `https` links, other schemes aren't safe to link to.
+    help_url: Option<String>,
+    /// The fix, as `group/action`.
+    fix: Option<String>,
+    fix_outcome: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct HtmlLine {
+    number: usize,
+    text: String,
+    matched: bool,
+}
+
+impl HtmlReport {
+    /// `inputs` are the known errors found in each input, with the file the input was read from.
+    /// `fixes` has what happened to the fixes that were offered.
+    pub(super) fn new(
+        found_config: &FoundConfig,
+        inputs: &[(Option<&str>, &[FoundKnownError])],
+        fixes: &KnownErrorFixRegistry,
+    ) -> Self {
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|(file, found_errors)| HtmlInput {
+                name: file.unwrap_or("Analyzed output").to_string(),
+                findings: found_errors
+                    .iter()
+                    .map(|found| HtmlFinding::new(found, fixes))
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            run_id: found_config.run_id.clone(),
+            scope_version: env!("CARGO_PKG_VERSION"),
+            total: inputs.iter().map(|input| input.findings.len()).sum(),
+            inputs,
+        }
+    }
+
+    pub(super) fn render(&self) -> Result<String> {
+        let mut env = Environment::new();
+        env.add_template(TEMPLATE_NAME, TEMPLATE)?;
+        Ok(env.get_template(TEMPLATE_NAME)?.render(self)?)
+    }
+}
+
+impl HtmlFinding {
+    fn new(found: &FoundKnownError, fixes: &KnownErrorFixRegistry) -> Self {
+        let known_error = &found.known_error;
+        let first_line = found.line_number - found.lines_before.len();
+        let lines = found
+            .lines_before
+            .iter()
+            .chain(std::iter::once(&found.line))
+            .chain(&found.lines_after)
+            .enumerate()
+            .map(|(idx, text)| HtmlLine {
+                number: first_line + idx,
+                text: text.clone(),
+                matched: first_line + idx == found.line_number,
+            })
+            .collect();
+        let fix = known_error.fix.as_ref();
+
+        Self {
+            name: known_error.name().to_string(),
+            description: known_error.metadata.description(),
+            severity: known_error.severity.to_string(),
+            line_number: found.line_number,
+            lines,
+            help_text: known_error.help_text.clone(),
+            help_url: fix
+                .and_then(|fix| fix.fix.help_url.clone())
+                .filter(|url| url.starts_with("https://") || url.starts_with("http://")),
+            fix: fix.map(|fix| format!("{}/{}", fix.group, fix.action)),
+            fix_outcome: fix
+                .and_then(|fix| fixes.outcome(known_error, fix))
+                .map(|outcome| outcome.description()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::shared::prelude::{
+        DoctorGroupActionCommand, DoctorGroupActionFixBuilder, KnownError, KnownErrorFix,
+        KnownErrorSeverity, PromptAnswer,
+    };
+    use regex::Regex;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_render_html_report() {
+        let fix = KnownErrorFix {
+            group: "disk".to_string(),
+            action: "clean".to_string(),
+            exec_path: String::new(),
+            working_dir: None,
+            env: BTreeMap::new(),
+            fix: DoctorGroupActionFixBuilder::default()
+                .command(Some(DoctorGroupActionCommand::from(vec!["clean.sh"])))
+                .help_url(Some("https://example.com/disk".to_string()))
+                .build()
+                .unwrap(),
+        };
+        let known_error = KnownError {
+            full_name: "ScopeKnownError/disk-full".to_string(),
+            metadata: ModelMetadata::new("disk-full"),
+            pattern: "no space".to_string(),
+            regex: Regex::new("no space").unwrap(),
+            help_text: "Free up <some> space".to_string(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines: 0,
+            fix_ref: Some("disk/clean".to_string()),
+            fix: Some(fix.clone()),
+        };
+        let found = FoundKnownError {
+            known_error: known_error.clone(),
+            line_number: 3,
+            byte_offset: 20,
+            line: "write failed: no space".to_string(),
+            lines_before: vec!["starting".to_string(), "writing <cache>".to_string()],
+            lines_after: vec!["exiting".to_string()],
+        };
+        let mut fixes = KnownErrorFixRegistry::default();
+        fixes.record_answer(&known_error, &fix, PromptAnswer::Denied);
+
+        let found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let html = HtmlReport::new(&found_config, &[(Some("build.log"), &[found])], &fixes)
+            .render()
+            .unwrap();
+
+        assert!(html.contains("<h2>build.log</h2>"));
+        assert!(html.contains("Found on line 3."));
+        assert!(html.contains(r#"<span class="number">1</span>starting"#));
+        assert!(html.contains("writing &lt;cache&gt;"));
+        assert!(html.contains(
+            r#"<span class="match"><span class="number">3</span>write failed: no space"#
+        ));
+        assert!(html.contains(r#"<span class="number">4</span>exiting"#));
+        assert!(html.contains("Free up &lt;some&gt; space"));
+        assert!(html.contains(r#"<a href="https:&#x2f;&#x2f;example.com&#x2f;disk">"#));
+        assert!(html.contains("<code>disk&#x2f;clean</code>: declined"));
+    }
+}
@@ -0,0 +1,36 @@
+use crate::shared::prelude::KnownError;
+use async_trait::async_trait;
+
+/// Decides whether some output matches a known error.
+///
+/// The CLI uses [`RegexKnownErrorMatcher`], which applies the `pattern` from each
+/// `ScopeKnownError`. Embedders can provide their own matcher, for example a classifier or a
+/// similarity search, and pass it to [`analyze_root_with_matcher`](super::cli::analyze_root_with_matcher)
+/// to reuse the rest of the analyze flow: reporting matches and offering fixes.
+#[async_trait]
+pub trait KnownErrorMatcher: Send + Sync {
+    async fn is_match(&self, known_error: &KnownError, input: &str) -> bool;
+
+    /// Whether input only matches a known error when its `regex` does, like with
+    /// [`RegexKnownErrorMatcher`]. Lines are then run through a
+    /// [`KnownErrorPrefilter`](super::prefilter::KnownErrorPrefilter) first, and only the known
+    /// errors it finds are passed to `is_match`. `false` unless it's implemented.
+    fn matches_regex(&self) -> bool {
+        false
+    }
+}
+
+/// Matches known errors using the regex from their config.
+#[derive(Debug, Default, Clone)]
+pub struct RegexKnownErrorMatcher;
+
+#[async_trait]
+impl KnownErrorMatcher for RegexKnownErrorMatcher {
+    async fn is_match(&self, known_error: &KnownError, input: &str) -> bool {
+        known_error.regex.is_match(input)
+    }
+
+    fn matches_regex(&self) -> bool {
+        true
+    }
+}
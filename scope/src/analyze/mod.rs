@@ -1,6 +1,18 @@
 mod cli;
 mod error;
+mod fixes;
+mod html;
+mod matcher;
+mod prefilter;
+mod sarif;
 
 pub mod prelude {
-    pub use super::cli::{analyze_root, AnalyzeArgs};
+    pub use super::cli::{
+        analyze_root, analyze_root_with_interaction, analyze_root_with_matcher,
+        analyze_root_with_progress, analyze_text, AnalyzeArgs, AnalyzeOptions, AnalyzeOutputFormat,
+        KnownErrorMatch,
+    };
+    pub use super::fixes::{FixDecision, FixOutcome, KnownErrorFixRegistry, DEFAULT_MAX_FIX_RUNS};
+    pub use super::matcher::{KnownErrorMatcher, RegexKnownErrorMatcher};
+    pub use super::prefilter::{KnownErrorPrefilter, PrefilterCandidates};
 }
@@ -0,0 +1,130 @@
+use crate::shared::prelude::KnownError;
+use regex::{RegexSet, SetMatches};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// All the known error regexes combined into a single `RegexSet`, so a line is scanned once to
+/// find the known errors it could match, instead of once per known error. Only those are then
+/// matched on their own.
+///
+/// Known errors with `contextLines` are matched against more than one line, so they aren't in
+/// the set and are always candidates.
+#[derive(Debug, Clone)]
+pub struct KnownErrorPrefilter {
+    set: RegexSet,
+    /// Index in `set` of each known error, by full name.
+    index: HashMap<String, usize>,
+}
+
+impl KnownErrorPrefilter {
+    /// `None` when the regexes can't be combined, e.g. when they're too big for a single set.
+    pub fn new<'a>(known_errors: impl IntoIterator<Item = &'a KnownError>) -> Option<Self> {
+        let single_line: Vec<_> = known_errors
+            .into_iter()
+            .filter(|known_error| known_error.context_lines == 0)
+            .collect();
+        let set = match RegexSet::new(single_line.iter().map(|ke| ke.regex.as_str())) {
+            Ok(set) => set,
+            Err(e) => {
+                debug!(
+                    "Unable to combine known error patterns, matching each one. {}",
+                    e
+                );
+                return None;
+            }
+        };
+        let index = single_line
+            .iter()
+            .enumerate()
+            .map(|(idx, known_error)| (known_error.full_name.clone(), idx))
+            .collect();
+
+        Some(Self { set, index })
+    }
+
+    /// The known errors `line` could match.
+    pub fn candidates(&self, line: &str) -> PrefilterCandidates<'_> {
+        PrefilterCandidates {
+            prefilter: self,
+            matches: self.set.matches(line),
+        }
+    }
+}
+
+/// The known errors a line could match, from [`KnownErrorPrefilter::candidates`].
+#[derive(Debug)]
+pub struct PrefilterCandidates<'a> {
+    prefilter: &'a KnownErrorPrefilter,
+    matches: SetMatches,
+}
+
+impl PrefilterCandidates<'_> {
+    /// Whether none of the known errors in the prefilter can match the line. Known errors that
+    /// aren't in it still can.
+    pub fn is_empty(&self) -> bool {
+        !self.matches.matched_any()
+    }
+
+    /// Whether `known_error` could match the line.
+    pub fn contains(&self, known_error: &KnownError) -> bool {
+        match self.prefilter.index.get(&known_error.full_name) {
+            Some(idx) => self.matches.matched(*idx),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::shared::prelude::KnownErrorSeverity;
+    use regex::{Regex, RegexBuilder};
+
+    fn known_error(name: &str, pattern: &str, context_lines: usize) -> KnownError {
+        KnownError {
+            full_name: format!("ScopeKnownError/{}", name),
+            metadata: ModelMetadata::new(name),
+            pattern: pattern.to_string(),
+            regex: RegexBuilder::new(pattern)
+                .multi_line(context_lines > 0)
+                .build()
+                .unwrap(),
+            help_text: "help".to_string(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines,
+            fix_ref: None,
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn test_candidates_are_the_known_errors_a_line_could_match() {
+        let disk = known_error("disk-full", "no space left", 0);
+        let port = known_error("port-in-use", r"port \d+ .*in use", 0);
+        let traceback = known_error("traceback", "^Traceback\nValueError", 1);
+        let prefilter = KnownErrorPrefilter::new([&disk, &port, &traceback]).unwrap();
+
+        let candidates = prefilter.candidates("write failed: no space left on device");
+        assert!(!candidates.is_empty());
+        assert!(candidates.contains(&disk));
+        assert!(!candidates.contains(&port));
+        assert!(candidates.contains(&traceback));
+
+        let candidates = prefilter.candidates("everything is fine");
+        assert!(candidates.is_empty());
+        assert!(!candidates.contains(&disk));
+        assert!(candidates.contains(&traceback));
+    }
+
+    #[test]
+    fn test_prefilter_uses_the_compiled_regex() {
+        let mut disk = known_error("disk-full", "no space left", 0);
+        disk.regex = Regex::new("(?i)disk full").unwrap();
+        let prefilter = KnownErrorPrefilter::new([&disk]).unwrap();
+
+        assert!(prefilter.candidates("DISK FULL").contains(&disk));
+        assert!(!prefilter.candidates("no space left").contains(&disk));
+    }
+}
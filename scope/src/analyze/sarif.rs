@@ -0,0 +1,279 @@
+use super::cli::FoundKnownError;
+use crate::shared::prelude::KnownErrorSeverity;
+use serde::Serialize;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+
+/// Known errors found by `scope analyze`, in the SARIF 2.1.0 format read by GitHub code scanning
+/// and IDEs. Each known error that was found becomes a rule, and each time it was found a result.
+#[derive(Debug, Serialize)]
+pub(super) struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<SarifRun>,
+}
+
+#[derive(Debug, Serialize)]
+struct SarifRun {
+    tool: SarifTool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct SarifTool {
+    driver: SarifDriver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifDriver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<SarifRule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    id: String,
+    short_description: SarifMessage,
+    help: SarifMessage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SarifMessage {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    rule_index: usize,
+    level: &'static str,
+    message: SarifMessage,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<SarifLocation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    physical_location: SarifPhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifPhysicalLocation {
+    artifact_location: SarifArtifactLocation,
+    region: SarifRegion,
+}
+
+#[derive(Debug, Serialize)]
+struct SarifArtifactLocation {
+    uri: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRegion {
+    start_line: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    snippet: SarifMessage,
+}
+
+/// SARIF's name for the level of a known error's `severity`.
+fn sarif_level(severity: KnownErrorSeverity) -> &'static str {
+    match severity {
+        KnownErrorSeverity::Info => "note",
+        KnownErrorSeverity::Warning => "warning",
+        KnownErrorSeverity::Error => "error",
+    }
+}
+
+impl SarifLog {
+    /// `inputs` are the known errors found in each input, with the file the input was read from.
+    /// Without a file, like for a command's output, results have no location and the line is part
+    /// of the message instead. Known errors found in several files share a rule.
+    pub(super) fn new(inputs: &[(Option<&str>, &[FoundKnownError])]) -> Self {
+        let mut rules: Vec<SarifRule> = Vec::new();
+        let mut results = Vec::new();
+
+        for (artifact, found) in inputs.iter().flat_map(|(artifact, found_errors)| {
+            found_errors.iter().map(move |found| (*artifact, found))
+        }) {
+            let known_error = &found.known_error;
+            let rule_id = known_error.metadata.name();
+            let rule_index = match rules.iter().position(|rule| rule.id == rule_id) {
+                Some(idx) => idx,
+                None => {
+                    rules.push(SarifRule {
+                        id: rule_id.clone(),
+                        short_description: SarifMessage {
+                            text: known_error.metadata.description(),
+                        },
+                        help: SarifMessage {
+                            text: known_error.help_text.clone(),
+                        },
+                        help_uri: known_error
+                            .fix
+                            .as_ref()
+                            .and_then(|fix| fix.fix.help_url.clone()),
+                    });
+                    rules.len() - 1
+                }
+            };
+
+            let (message, locations) = match artifact {
+                Some(uri) => (
+                    known_error.help_text.clone(),
+                    vec![SarifLocation {
+                        physical_location: SarifPhysicalLocation {
+                            artifact_location: SarifArtifactLocation {
+                                uri: uri.to_string(),
+                            },
+                            region: SarifRegion {
+                                start_line: found.line_number,
+                                byte_offset: found.byte_offset,
+                                byte_length: found.line.len(),
+                                snippet: SarifMessage {
+                                    text: found.line.clone(),
+                                },
+                            },
+                        },
+                    }],
+                ),
+                None => (
+                    format!(
+                        "{}\nFound on line {}: {}",
+                        known_error.help_text, found.line_number, found.line
+                    ),
+                    Vec::new(),
+                ),
+            };
+
+            results.push(SarifResult {
+                rule_id,
+                rule_index,
+                level: sarif_level(known_error.severity),
+                message: SarifMessage { text: message },
+                locations,
+            });
+        }
+
+        Self {
+            schema: SARIF_SCHEMA,
+            version: SARIF_VERSION,
+            runs: vec![SarifRun {
+                tool: SarifTool {
+                    driver: SarifDriver {
+                        name: "scope",
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: "https://oscope-dev.github.io/scope/",
+                        rules,
+                    },
+                },
+                results,
+            }],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::shared::prelude::KnownError;
+    use regex::Regex;
+    use serde_json::json;
+
+    fn found(name: &str, line_number: usize, byte_offset: usize, line: &str) -> FoundKnownError {
+        let mut metadata = ModelMetadata::new(name);
+        metadata.description = format!("About {}", name);
+        FoundKnownError {
+            known_error: KnownError {
+                full_name: format!("ScopeKnownError/{}", name),
+                metadata,
+                pattern: "error".to_string(),
+                regex: Regex::new("error").unwrap(),
+                help_text: format!("Fix {}", name),
+                priority: 0,
+                severity: KnownErrorSeverity::Error,
+                context_lines: 0,
+                fix_ref: None,
+                fix: None,
+            },
+            line_number,
+            byte_offset,
+            line: line.to_string(),
+            lines_before: Vec::new(),
+            lines_after: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_results_share_rules() {
+        let build_log = [
+            found("disk", 2, 6, "disk error"),
+            found("auth", 3, 17, "auth error"),
+        ];
+        let test_log = [found("disk", 5, 40, "disk error again")];
+        let log = SarifLog::new(&[
+            (Some("build.log"), build_log.as_slice()),
+            (Some("test.log"), test_log.as_slice()),
+        ]);
+        let value = serde_json::to_value(&log).unwrap();
+
+        let rule_ids: Vec<_> = log.runs[0]
+            .tool
+            .driver
+            .rules
+            .iter()
+            .map(|rule| rule.id.as_str())
+            .collect();
+        assert_eq!(vec!["disk", "auth"], rule_ids);
+        assert_eq!(
+            json!({
+                "ruleId": "disk",
+                "ruleIndex": 0,
+                "level": "error",
+                "message": { "text": "Fix disk" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "test.log" },
+                        "region": {
+                            "startLine": 5,
+                            "byteOffset": 40,
+                            "byteLength": 16,
+                            "snippet": { "text": "disk error again" }
+                        }
+                    }
+                }]
+            }),
+            value["runs"][0]["results"][2]
+        );
+    }
+
+    #[test]
+    fn test_results_without_artifact_have_line_in_message() {
+        let found_errors = [found("disk", 2, 6, "disk error")];
+        let log = SarifLog::new(&[(None, found_errors.as_slice())]);
+        let value = serde_json::to_value(&log).unwrap();
+
+        assert_eq!(
+            json!({
+                "ruleId": "disk",
+                "ruleIndex": 0,
+                "level": "error",
+                "message": { "text": "Fix disk\nFound on line 2: disk error" }
+            }),
+            value["runs"][0]["results"][0]
+        );
+    }
+}
@@ -0,0 +1,73 @@
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::{Args, Subcommand};
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ArtifactsArgs {
+        #[cfg_attr(feature = "cli", clap(subcommand))]
+        pub command: ArtifactsCommands,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Subcommand))]
+    pub enum ArtifactsCommands {
+        /// Print the directory with the logs, results and reports of a run
+        Path(ArtifactsPathArgs),
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ArtifactsPathArgs {
+        /// Id of the run, defaults to the latest run before this one
+        pub run_id: Option<String>,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{ArtifactsArgs, ArtifactsCommands, ArtifactsPathArgs};
+    use crate::report_stdout;
+    use crate::shared::prelude::{FoundConfig, RunArtifacts};
+    use anyhow::Result;
+    use tracing::error;
+
+    pub async fn artifacts_root(found_config: &FoundConfig, args: &ArtifactsArgs) -> Result<i32> {
+        match &args.command {
+            ArtifactsCommands::Path(args) => artifacts_path(found_config, args).await,
+        }
+    }
+
+    async fn artifacts_path(found_config: &FoundConfig, args: &ArtifactsPathArgs) -> Result<i32> {
+        let root = RunArtifacts::root();
+        let artifacts = match &args.run_id {
+            Some(run_id) if !RunArtifacts::is_valid_run_id(run_id) => {
+                error!(target: "user", "`{}` isn't a run id", run_id);
+                return Ok(1);
+            }
+            Some(run_id) => {
+                Some(RunArtifacts::in_root(&root, run_id)).filter(|run| run.dir().is_dir())
+            }
+            None => RunArtifacts::latest(&root, &found_config.run_id),
+        };
+
+        match (artifacts, &args.run_id) {
+            (Some(artifacts), _) => {
+                report_stdout!("{}", artifacts.dir().display());
+                Ok(0)
+            }
+            (None, Some(run_id)) => {
+                error!(target: "user", "There are no artifacts for run `{}` in {}", run_id, root.display());
+                Ok(1)
+            }
+            (None, None) => {
+                error!(target: "user", "There are no earlier runs in {}", root.display());
+                Ok(1)
+            }
+        }
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::{ArtifactsArgs, ArtifactsCommands, ArtifactsPathArgs};
+    pub use super::commands::artifacts_root;
+}
@@ -1,9 +1,9 @@
 use clap::Parser;
+use dev_scope::intercept;
 use dev_scope::prelude::*;
 use human_panic::setup_panic;
 use std::env;
-use std::sync::Arc;
-use tracing::{debug, enabled, error, info, warn, Level};
+use tracing::{enabled, error, info, Level};
 
 /// A wrapper CLI that can be used to capture output from a program, check if there are known errors
 /// and let the user know.
@@ -25,6 +25,11 @@ struct Cli {
     #[clap(flatten)]
     config_options: ConfigOptions,
 
+    /// When the command fails, print where the report would be sent and exactly what it would
+    /// contain, without asking or sending it.
+    #[arg(long, default_value = "false")]
+    dry_run: bool,
+
     /// Command to execute withing scope-intercept.
     #[arg(required = true)]
     utility: String,
@@ -33,6 +38,8 @@ struct Cli {
     args: Vec<String>,
 }
 
+// `setup_panic!` from human-panic 1.x names `PanicInfo`, which newer Rust deprecates.
+#[allow(deprecated)]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     setup_panic!();
@@ -64,81 +71,20 @@ async fn main() -> anyhow::Result<()> {
 async fn run_command(opts: Cli) -> anyhow::Result<i32> {
     let mut command = vec![opts.utility];
     command.extend(opts.args);
-    let current_dir = std::env::current_dir()?;
-    let path = env::var("PATH").unwrap_or_default();
-
-    let capture = OutputCapture::capture_output(CaptureOpts {
-        working_dir: &current_dir,
-        args: &command,
-        output_dest: OutputDestination::StandardOut,
-        path: &path,
-        env_vars: Default::default(),
-    })
-    .await?;
-
-    let mut accepted_exit_codes = vec![0];
-    accepted_exit_codes.extend(opts.successful_exit);
-
-    let exit_code = capture.exit_code.unwrap_or(-1);
-    if accepted_exit_codes.contains(&exit_code) {
-        return Ok(exit_code);
-    }
 
-    error!(target: "user", "Command failed, checking for a known error");
     let found_config = opts.config_options.load_config().await.unwrap_or_else(|e| {
         error!(target: "user", "Unable to load configs from disk: {:?}", e);
         FoundConfig::empty(env::current_dir().unwrap())
     });
+    configure_colors(opts.logging.color, found_config.color_preference());
+    configure_redactions(found_config.redaction_patterns());
 
-    let command_output = capture.generate_output();
-
-    for known_error in found_config.known_error.values() {
-        debug!("Checking known error {}", known_error.name());
-        if known_error.regex.is_match(&command_output) {
-            info!(target: "always", "Known error '{}' found", known_error.name());
-            info!(target: "always", "\t==> {}", known_error.help_text);
-        }
-    }
+    let options = InterceptOptions {
+        successful_exit: opts.successful_exit,
+        dry_run: opts.dry_run,
+        ..Default::default()
+    };
 
-    if found_config.report_upload.is_empty() {
-        return Ok(exit_code);
-    }
-
-    let ans = inquire::Confirm::new("Do you want to upload a bug report?")
-        .with_default(true)
-        .with_help_message(
-            "This will allow you to share the error with other engineers for support.",
-        )
-        .prompt();
-
-    if let Ok(true) = ans {
-        let entrypoint = command.join(" ");
-        let exec_runner = Arc::new(DefaultExecutionProvider::default());
-
-        let builder = DefaultUnstructuredReportBuilder::new(&entrypoint, &capture);
-
-        for location in found_config.report_upload.values() {
-            let mut builder = builder.clone();
-            builder
-                .run_and_append_additional_data(
-                    &found_config,
-                    exec_runner.clone(),
-                    &location.additional_data,
-                )
-                .await
-                .ok();
-
-            let report = builder.render(location);
-
-            match report {
-                Err(e) => warn!(target: "user", "Unable to render report: {}", e),
-                Ok(report) => {
-                    if let Err(e) = report.distribute().await {
-                        warn!(target: "user", "Unable to upload report: {}", e);
-                    }
-                }
-            }
-        }
-    }
-    Ok(exit_code)
+    let outcome = intercept::run(&found_config, &command, &options, &InquireInteraction).await?;
+    Ok(outcome.exit_code)
 }
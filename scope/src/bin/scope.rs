@@ -30,6 +30,13 @@ struct Cli {
     command: Command,
 }
 
+#[derive(Parser, Debug)]
+struct ListArgs {
+    /// Never shorten descriptions or paths to fit the terminal
+    #[arg(long)]
+    wide: bool,
+}
+
 #[derive(Parser, Debug)]
 struct VersionArgs {
     #[arg(long, action)]
@@ -49,9 +56,23 @@ enum Command {
     Analyze(AnalyzeArgs),
     /// Validate inputs, providing recommendations about configuration
     Lint(LintArgs),
+    /// Generate documentation for the found config
+    Docs(DocsArgs),
+    /// Help writing config files
+    Config(ConfigArgs),
+    /// Find the logs, results and reports runs leave behind
+    Artifacts(ArtifactsArgs),
+    /// Show which known errors were found most often, and how their fixes went
+    Stats(StatsArgs),
+    /// Set up the shell to run everyday commands through `scope-intercept`
+    Intercept(InterceptArgs),
+    /// Answer JSON-RPC requests from editors and other tools, with the config loaded once
+    Serve(ServeArgs),
+    /// Update scope to the latest release
+    Update(UpdateArgs),
     /// List the found config files, and resources detected
     #[clap(alias("l"))]
-    List,
+    List(ListArgs),
     /// Print version info and exit
     #[clap(alias("v"))]
     Version(VersionArgs),
@@ -60,6 +81,8 @@ enum Command {
     ExternalSubCommand(Vec<String>),
 }
 
+// `setup_panic!` from human-panic 1.x names `PanicInfo`, which newer Rust deprecates.
+#[allow(deprecated)]
 #[tokio::main]
 async fn main() {
     setup_panic!();
@@ -91,8 +114,23 @@ async fn run_subcommand(opts: Cli) -> i32 {
         }
         Ok(c) => c,
     };
+    configure_colors(opts.logging.color, loaded_config.color_preference());
+    configure_redactions(loaded_config.redaction_patterns());
+
+    let default_args = loaded_config.default_args();
+    let opts = if default_args.is_empty() {
+        opts
+    } else {
+        let args = apply_default_args(
+            &Cli::command(),
+            std::env::args_os().collect(),
+            &default_args,
+        );
+        debug!("Running with default args from config: {:?}", args);
+        Cli::parse_from(args)
+    };
 
-    handle_commands(&loaded_config, &opts.command)
+    handle_commands(&loaded_config, &opts.config, &opts.command)
         .await
         .unwrap_or_else(|e| {
             error!(target: "user", "Critical Error. {}", e);
@@ -100,15 +138,26 @@ async fn run_subcommand(opts: Cli) -> i32 {
         })
 }
 
-async fn handle_commands(found_config: &FoundConfig, command: &Command) -> Result<i32> {
+async fn handle_commands(
+    found_config: &FoundConfig,
+    config_options: &ConfigOptions,
+    command: &Command,
+) -> Result<i32> {
     match command {
         Command::Doctor(args) => doctor_root(found_config, args).await,
         Command::Report(args) => report_root(found_config, args).await,
-        Command::List => show_config(found_config).await.map(|_| 0),
+        Command::List(args) => show_config(found_config, args).await.map(|_| 0),
         Command::Version(args) => print_version(args).await,
         Command::ExternalSubCommand(args) => exec_sub_command(found_config, args).await,
         Command::Analyze(args) => analyze_root(found_config, args).await,
         Command::Lint(args) => lint_root(found_config, args).await,
+        Command::Docs(args) => docs_root(found_config, args).await,
+        Command::Config(args) => config_root(found_config, args).await,
+        Command::Artifacts(args) => artifacts_root(found_config, args).await,
+        Command::Stats(args) => stats_root(found_config, args).await,
+        Command::Intercept(args) => intercept_root(found_config, args).await,
+        Command::Serve(args) => serve_root(found_config, config_options, args).await,
+        Command::Update(args) => update_root(found_config, args).await,
     }
 }
 
@@ -138,6 +187,8 @@ async fn exec_sub_command(found_config: &FoundConfig, args: &[String]) -> Result
             ),
             (RUN_ID_ENV_VAR.to_string(), found_config.run_id.clone()),
         ]),
+        timeout: None,
+        progress: None,
     })
     .await?;
 
@@ -151,9 +202,31 @@ lazy_static! {
 }
 
 #[instrument("scope list", skip_all)]
-async fn show_config(found_config: &FoundConfig) -> Result<()> {
+async fn show_config(found_config: &FoundConfig, args: &ListArgs) -> Result<()> {
+    let supported: Vec<_> = found_config
+        .raw_config
+        .iter()
+        .filter(|resource| ParsedConfig::is_supported(resource))
+        .cloned()
+        .collect();
     info!(target: "user", "Found Resources");
-    print_details(&found_config.working_dir, &found_config.raw_config).await;
+    print_details(&found_config.working_dir, &supported, args.wide).await;
+
+    if !found_config.unsupported.is_empty() {
+        info!(target: "user", "");
+        info!(target: "user", "Unsupported Resources");
+        for resource in &found_config.unsupported {
+            let path = pathdiff::diff_paths(&resource.file_path, &found_config.working_dir)
+                .map(|diff| diff.display().to_string())
+                .unwrap_or_else(|| resource.file_path.clone());
+            report_stdout!(
+                "- {} in {}: {}",
+                resource.full_name().bold(),
+                path,
+                resource.suggestion()
+            );
+        }
+    }
 
     info!(target: "user", "");
     info!(target: "user", "Commands");
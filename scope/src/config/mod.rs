@@ -0,0 +1,105 @@
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::{Args, Subcommand};
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ConfigArgs {
+        #[cfg_attr(feature = "cli", clap(subcommand))]
+        pub command: ConfigCommands,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Subcommand))]
+    pub enum ConfigCommands {
+        /// Print an example of a resource, with every field described
+        Example(ConfigExampleArgs),
+        /// Print a resource as it's used, after merging every file that defines it
+        Resolve(ConfigResolveArgs),
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ConfigExampleArgs {
+        /// Kind of resource, e.g. `ScopeDoctorGroup`. The `Scope` prefix is optional.
+        pub kind: String,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ConfigResolveArgs {
+        /// Name of the resource
+        pub name: String,
+
+        /// Only print the resource of this kind, e.g. `ScopeDoctorGroup`. The `Scope` prefix is
+        /// optional.
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub kind: Option<String>,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{
+        example_for_kind, example_kinds, ConfigArgs, ConfigCommands, ConfigExampleArgs,
+        ConfigResolveArgs, FoundConfig, HelpMetadata,
+    };
+    use crate::report_stdout;
+    use anyhow::Result;
+    use tracing::error;
+
+    pub async fn config_root(found_config: &FoundConfig, args: &ConfigArgs) -> Result<i32> {
+        match &args.command {
+            ConfigCommands::Example(args) => config_example(args).await,
+            ConfigCommands::Resolve(args) => config_resolve(found_config, args).await,
+        }
+    }
+
+    async fn config_example(args: &ConfigExampleArgs) -> Result<i32> {
+        match example_for_kind(&args.kind) {
+            Some(example) => {
+                report_stdout!("{}", example.trim_end());
+                Ok(0)
+            }
+            None => {
+                error!(target: "user", "Unknown kind `{}`, expected one of {}", args.kind, example_kinds().join(", "));
+                Ok(1)
+            }
+        }
+    }
+
+    /// Print each resource named `args.name` as YAML. Resources defined in more than one file
+    /// are printed merged, with annotations naming the file each field came from.
+    async fn config_resolve(found_config: &FoundConfig, args: &ConfigResolveArgs) -> Result<i32> {
+        let documents = found_config
+            .raw_config
+            .iter()
+            .filter(|resource| resource.name() == args.name)
+            .filter(|resource| match &args.kind {
+                Some(kind) => is_kind(&resource.kind, kind),
+                None => true,
+            })
+            .map(serde_yaml::to_string)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if documents.is_empty() {
+            error!(target: "user", "No resource named `{}` was found", args.name);
+            return Ok(1);
+        }
+
+        let yaml = documents.join("---\n");
+        report_stdout!("{}", yaml.trim_end());
+        Ok(0)
+    }
+
+    /// Whether `kind` is `wanted`, ignoring case and with an optional `Scope` prefix.
+    fn is_kind(kind: &str, wanted: &str) -> bool {
+        let kind = kind.to_lowercase();
+        let wanted = wanted.to_lowercase();
+        kind == wanted || kind.strip_prefix("scope") == Some(wanted.as_str())
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::{ConfigArgs, ConfigCommands, ConfigExampleArgs, ConfigResolveArgs};
+    pub use super::commands::config_root;
+}
@@ -0,0 +1,385 @@
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::{Args, Subcommand};
+    use std::path::PathBuf;
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct DocsArgs {
+        #[cfg_attr(feature = "cli", clap(subcommand))]
+        pub command: DocsCommands,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Subcommand))]
+    pub enum DocsCommands {
+        /// Write a markdown page for each doctor group and known error
+        Generate(DocsGenerateArgs),
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct DocsGenerateArgs {
+        /// Directory the pages are written to, it's created if it doesn't exist
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub out: PathBuf,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{
+        DocsArgs, DocsCommands, DocsGenerateArgs, DoctorGroup, DoctorGroupActionCommand,
+        FoundConfig, KnownError, ModelMetadata,
+    };
+    use anyhow::Result;
+    use std::fmt::Write;
+    use std::path::Path;
+    use tracing::info;
+
+    /// Label on a resource's metadata listing who owns it, separated by commas.
+    pub const OWNERS_LABEL: &str = "owners";
+
+    const DOCTOR_GROUP_DIR: &str = "doctor-groups";
+    const KNOWN_ERROR_DIR: &str = "known-errors";
+
+    pub async fn docs_root(found_config: &FoundConfig, args: &DocsArgs) -> Result<i32> {
+        match &args.command {
+            DocsCommands::Generate(args) => docs_generate(found_config, args).map(|_| 0),
+        }
+    }
+
+    fn docs_generate(found_config: &FoundConfig, args: &DocsGenerateArgs) -> Result<()> {
+        std::fs::create_dir_all(args.out.join(DOCTOR_GROUP_DIR))?;
+        std::fs::create_dir_all(args.out.join(KNOWN_ERROR_DIR))?;
+
+        let working_dir = &found_config.working_dir;
+        for group in found_config.doctor_group.values() {
+            let path = args
+                .out
+                .join(DOCTOR_GROUP_DIR)
+                .join(format!("{}.md", group.metadata.name()));
+            std::fs::write(&path, render_doctor_group(group, working_dir))?;
+        }
+
+        for known_error in found_config.known_error.values() {
+            let path = args
+                .out
+                .join(KNOWN_ERROR_DIR)
+                .join(format!("{}.md", known_error.metadata.name()));
+            std::fs::write(&path, render_known_error(known_error))?;
+        }
+
+        std::fs::write(args.out.join("index.md"), render_index(found_config))?;
+        info!(
+            target: "user",
+            "Wrote {} doctor groups and {} known errors to {}",
+            found_config.doctor_group.len(),
+            found_config.known_error.len(),
+            args.out.display()
+        );
+
+        Ok(())
+    }
+
+    fn render_index(found_config: &FoundConfig) -> String {
+        let mut page = String::from("# Scope\n");
+
+        if !found_config.doctor_group.is_empty() {
+            page.push_str("\n## Doctor Groups\n\n");
+            for group in found_config.doctor_group.values() {
+                writeln!(
+                    page,
+                    "- [{name}]({DOCTOR_GROUP_DIR}/{name}.md) - {}",
+                    group.metadata.description(),
+                    name = group.metadata.name()
+                )
+                .ok();
+            }
+        }
+
+        if !found_config.known_error.is_empty() {
+            page.push_str("\n## Known Errors\n\n");
+            for known_error in found_config.known_error.values() {
+                writeln!(
+                    page,
+                    "- [{name}]({KNOWN_ERROR_DIR}/{name}.md) - {}",
+                    known_error.metadata.description(),
+                    name = known_error.metadata.name()
+                )
+                .ok();
+            }
+        }
+
+        page
+    }
+
+    /// Render a doctor group's page. Commands and files under `working_dir` are shown relative to
+    /// it, so the page doesn't depend on where the repo was checked out.
+    pub fn render_doctor_group(group: &DoctorGroup, working_dir: &Path) -> String {
+        let mut page = String::new();
+        write_header(&mut page, &group.metadata);
+
+        if !group.requires.is_empty() {
+            let requires: Vec<_> = group
+                .requires
+                .iter()
+                .map(|name| format!("[{name}]({name}.md)"))
+                .collect();
+            writeln!(page, "**Requires:** {}\n", requires.join(", ")).ok();
+        }
+
+        if !group.run_by_default {
+            page.push_str("Only run when asked for by name.\n\n");
+        }
+
+        page.push_str("## Actions\n");
+        for action in &group.actions {
+            writeln!(page, "\n### {}\n\n{}\n", action.name, action.description).ok();
+            if !action.required {
+                page.push_str("Failing this action doesn't stop the group.\n\n");
+            }
+
+            if let Some(files) = &action.check.files {
+                writeln!(
+                    page,
+                    "Runs when these files change, relative to `{}`:\n",
+                    relative_path(&files.base_path.display().to_string(), working_dir)
+                )
+                .ok();
+                for path in &files.paths {
+                    writeln!(page, "- `{}`", path).ok();
+                }
+                page.push('\n');
+            }
+
+            for check in &action.check.builtin {
+                writeln!(page, "Checks that the {}.\n", check).ok();
+            }
+            if let Some(command) = &action.check.command {
+                write_commands(&mut page, "Check", command, working_dir);
+            }
+            if let Some(command) = &action.fix.command {
+                write_commands(&mut page, "Fix", command, working_dir);
+            }
+            if let Some(help_text) = &action.fix.help_text {
+                writeln!(page, "**Help:** {}\n", help_text.trim()).ok();
+            }
+            if let Some(help_url) = &action.fix.help_url {
+                writeln!(page, "**More info:** <{}>\n", help_url).ok();
+            }
+        }
+
+        trim_end(page)
+    }
+
+    pub fn render_known_error(known_error: &KnownError) -> String {
+        let mut page = String::new();
+        write_header(&mut page, &known_error.metadata);
+
+        page.push_str("## Pattern\n\n```text\n");
+        writeln!(page, "{}\n```\n", known_error.pattern).ok();
+        if known_error.context_lines > 0 {
+            writeln!(
+                page,
+                "Matched against the current line and the {} before it.\n",
+                known_error.context_lines
+            )
+            .ok();
+        }
+
+        writeln!(page, "## Help\n\n{}\n", known_error.help_text.trim()).ok();
+
+        if let Some(fix_ref) = &known_error.fix_ref {
+            page.push_str("## Fix\n\n");
+            match fix_ref.split_once('/') {
+                Some((group, action)) => writeln!(
+                    page,
+                    "Runs the fix of `{action}` from [{group}](../{DOCTOR_GROUP_DIR}/{group}.md).",
+                ),
+                None => writeln!(page, "Runs the fix `{}`.", fix_ref),
+            }
+            .ok();
+        }
+
+        trim_end(page)
+    }
+
+    fn write_header(page: &mut String, metadata: &ModelMetadata) {
+        writeln!(
+            page,
+            "# {}\n\n{}\n",
+            metadata.name(),
+            metadata.description()
+        )
+        .ok();
+
+        let owners: Vec<_> = metadata
+            .labels
+            .get(OWNERS_LABEL)
+            .map(|owners| owners.split(',').map(str::trim).collect())
+            .unwrap_or_default();
+        if !owners.is_empty() {
+            writeln!(page, "**Owners:** {}\n", owners.join(", ")).ok();
+        }
+    }
+
+    fn write_commands(
+        page: &mut String,
+        title: &str,
+        command: &DoctorGroupActionCommand,
+        working_dir: &Path,
+    ) {
+        writeln!(page, "**{}:**\n\n```shell", title).ok();
+        for command in &command.commands {
+            let (program, rest) = match command.split_once(' ') {
+                Some((program, rest)) => (program, Some(rest)),
+                None => (command.as_str(), None),
+            };
+            page.push_str(&relative_path(program, working_dir));
+            if let Some(rest) = rest {
+                page.push(' ');
+                page.push_str(rest);
+            }
+            page.push('\n');
+        }
+        page.push_str("```\n\n");
+    }
+
+    fn relative_path(path: &str, working_dir: &Path) -> String {
+        match Path::new(path).strip_prefix(working_dir) {
+            Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+            Ok(relative) => format!("./{}", relative.display()),
+            Err(_) => path.to_string(),
+        }
+    }
+
+    fn trim_end(mut page: String) -> String {
+        page.truncate(page.trim_end().len());
+        page.push('\n');
+        page
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::models::prelude::ModelMetadataAnnotations;
+        use crate::shared::prelude::{DoctorGroupAction, DoctorGroupBuilder, KnownErrorSeverity};
+        use regex::Regex;
+        use std::collections::BTreeMap;
+
+        fn metadata(name: &str) -> ModelMetadata {
+            ModelMetadata {
+                name: name.to_string(),
+                description: format!("About {}", name),
+                annotations: ModelMetadataAnnotations::default(),
+                labels: BTreeMap::from([(OWNERS_LABEL.to_string(), "infra, web".to_string())]),
+            }
+        }
+
+        #[test]
+        fn test_render_doctor_group() {
+            let mut action = DoctorGroupAction::make_from(
+                "node",
+                "Node is installed",
+                Some(vec!["/repo/.scope/bin/install-node --lts"]),
+                Some(("/repo", vec!["package.json"])),
+                Some(vec!["node --version"]),
+            );
+            action.fix.help_text = Some("Ask in #help-node\n".to_string());
+            action.fix.help_url = Some("https://example.com/node".to_string());
+
+            let group = DoctorGroupBuilder::default()
+                .full_name("ScopeDoctorGroup/setup")
+                .metadata(metadata("setup"))
+                .requires(vec!["brew".to_string()])
+                .run_by_default(true)
+                .actions(vec![action])
+                .extra_report_args(BTreeMap::new())
+                .build()
+                .unwrap();
+
+            assert_eq!(
+                "# setup
+
+About setup
+
+**Owners:** infra, web
+
+**Requires:** [brew](brew.md)
+
+## Actions
+
+### node
+
+Node is installed
+
+Runs when these files change, relative to `.`:
+
+- `package.json`
+
+**Check:**
+
+```shell
+node --version
+```
+
+**Fix:**
+
+```shell
+./.scope/bin/install-node --lts
+```
+
+**Help:** Ask in #help-node
+
+**More info:** <https://example.com/node>
+",
+                render_doctor_group(&group, Path::new("/repo"))
+            );
+        }
+
+        #[test]
+        fn test_render_known_error() {
+            let known_error = KnownError {
+                full_name: "ScopeKnownError/disk-full".to_string(),
+                metadata: metadata("disk-full"),
+                pattern: "No space left on device".to_string(),
+                regex: Regex::new("No space left on device").unwrap(),
+                help_text: "Free up some space.".to_string(),
+                priority: 0,
+                severity: KnownErrorSeverity::Error,
+                context_lines: 0,
+                fix_ref: Some("cleanup/docker".to_string()),
+                fix: None,
+            };
+
+            assert_eq!(
+                "# disk-full
+
+About disk-full
+
+**Owners:** infra, web
+
+## Pattern
+
+```text
+No space left on device
+```
+
+## Help
+
+Free up some space.
+
+## Fix
+
+Runs the fix of `docker` from [cleanup](../doctor-groups/cleanup.md).
+",
+                render_known_error(&known_error)
+            );
+        }
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::{DocsArgs, DocsCommands, DocsGenerateArgs};
+    pub use super::commands::docs_root;
+}
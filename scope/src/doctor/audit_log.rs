@@ -0,0 +1,147 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// A single fix command that was executed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FixExecution {
+    pub group: String,
+    pub action: String,
+    pub command: String,
+    pub exit_code: Option<i32>,
+    /// The user's answer when they were asked before the fix ran, `None` when no prompt was shown.
+    #[serde(default)]
+    pub prompt_answer: Option<bool>,
+}
+
+/// A line in the audit log.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub host: String,
+    pub run_id: String,
+    #[serde(flatten)]
+    pub fix: FixExecution,
+}
+
+/// Append-only record of every fix that ran on the machine.
+#[automock]
+#[async_trait]
+pub trait AuditLog: Sync + Send + Debug {
+    async fn record_fix(&self, fix: FixExecution);
+}
+
+#[derive(Debug)]
+pub struct FileBasedAuditLog {
+    path: PathBuf,
+    run_id: String,
+    user: String,
+    host: String,
+}
+
+impl FileBasedAuditLog {
+    pub fn new(cache_dir: &Path, run_id: &str) -> Self {
+        Self {
+            path: audit_log_path(cache_dir),
+            run_id: run_id.to_string(),
+            user: std::env::var("USER").unwrap_or_else(|_| "unknown".to_string()),
+            host: gethostname::gethostname().to_string_lossy().to_string(),
+        }
+    }
+
+    fn append(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuditLog for FileBasedAuditLog {
+    async fn record_fix(&self, fix: FixExecution) {
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            user: self.user.clone(),
+            host: self.host.clone(),
+            run_id: self.run_id.clone(),
+            fix,
+        };
+
+        if let Err(e) = self.append(&entry) {
+            warn!(target: "user", "Unable to write to audit log {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+pub fn audit_log_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("audit.log")
+}
+
+/// Read every entry from the audit log, oldest first. Lines that can't be parsed are skipped.
+pub fn read_audit_log(path: &Path) -> Result<Vec<AuditEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => warn!("Skipping invalid audit log line {:?}", e),
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_audit_log, AuditLog, FileBasedAuditLog, FixExecution};
+
+    fn fix(command: &str, exit_code: i32) -> FixExecution {
+        FixExecution {
+            group: "group".to_string(),
+            action: "action".to_string(),
+            command: command.to_string(),
+            exit_code: Some(exit_code),
+            prompt_answer: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entries_are_appended() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        FileBasedAuditLog::new(dir.path(), "run-1")
+            .record_fix(fix("first", 0))
+            .await;
+        FileBasedAuditLog::new(dir.path(), "run-2")
+            .record_fix(fix("second", 1))
+            .await;
+
+        let entries = read_audit_log(&dir.path().join("audit.log")).unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!("run-1", entries[0].run_id);
+        assert_eq!(fix("first", 0), entries[0].fix);
+        assert_eq!("run-2", entries[1].run_id);
+        assert_eq!(fix("second", 1), entries[1].fix);
+    }
+}
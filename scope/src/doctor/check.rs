@@ -1,23 +1,36 @@
+use super::audit_log::{AuditLog, FixExecution};
 use super::file_cache::{FileCache, FileCacheStatus};
+use super::plan::{ActionPlan, ActionPlanStatus};
+use super::sandbox::FixSandbox;
+use super::snapshot::{
+    add_file_diffs, diff_snapshots, read_files, take_snapshot, FileContents, Snapshot,
+};
 use anyhow::Result;
 use std::cmp;
 use std::cmp::max;
 use std::collections::BTreeMap;
 
+use crate::models::prelude::join_path_list;
 use crate::models::HelpMetadata;
-use crate::prelude::{ActionReport, ActionReportBuilder, ActionTaskReport};
+use crate::prelude::{ActionReport, ActionReportBuilder, ActionTaskReport, FixArtifact, FixChange};
 use crate::shared::prelude::{
-    CaptureError, CaptureOpts, DoctorGroup, DoctorGroupAction, DoctorGroupActionCommand,
-    DoctorGroupCachePath, ExecutionProvider, OutputDestination,
+    quote_sh, BuiltinCheck, CaptureError, CaptureOpts, DoctorGroup, DoctorGroupAction,
+    DoctorGroupActionCommand, DoctorGroupCachePath, ExecutionProvider, ExitCodeMeaning,
+    OutputDestination, ProgressReporter, PromptAnswer, UserInteraction,
 };
 use async_trait::async_trait;
+use chrono::Utc;
 use derive_builder::Builder;
 use educe::Educe;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::WalkBuilder;
+use lazy_static::lazy_static;
 use mockall::automock;
+use regex::Regex;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
-use tracing::{error, info, instrument};
+use tracing::{debug, info, instrument};
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
@@ -38,14 +51,19 @@ pub enum RuntimeError {
     AnyError(#[from] anyhow::Error),
     #[error(transparent)]
     PatternError(#[from] glob::PatternError),
+    #[error(transparent)]
+    IgnoreError(#[from] ignore::Error),
 }
 
 #[derive(Debug, Clone, PartialEq, Ord, Eq, PartialOrd)]
 pub enum CacheStatus {
     FixNotRequired = 1,
-    FixRequired = 2,
-    StopExecution = 3,
-    CacheNotDefined = 4,
+    /// A check command exited with one of the check's `exitCodes.skip`. It ranks below
+    /// `FixRequired`, so a command that skips doesn't hide an earlier one that failed.
+    Skip = 2,
+    FixRequired = 3,
+    StopExecution = 4,
+    CacheNotDefined = 5,
 }
 
 impl CacheStatus {
@@ -58,6 +76,23 @@ impl CacheStatus {
 pub struct CacheResults {
     pub status: CacheStatus,
     pub output: Option<Vec<ActionTaskReport>>,
+    /// A command was stopped by the action's timeout.
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Clone)]
+struct FixResults {
+    exit_code: i32,
+    output: Vec<ActionTaskReport>,
+    rollback: Vec<ActionTaskReport>,
+    timed_out: bool,
+}
+
+/// A single check or fix command that ran.
+#[derive(Debug, Clone)]
+struct TaskResult {
+    report: ActionTaskReport,
+    timed_out: bool,
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -71,11 +106,27 @@ pub enum ActionRunStatus {
     CheckFailedNoFixProvided,
     CheckFailedFixFailedStop,
     NoCheckFixSucceeded,
+    CheckFailedFixUserDenied,
+    CheckFailedFixNotInteractive,
+    /// A check, fix or validate command ran longer than the action's `timeoutSeconds`.
+    Timeout,
+    /// The action's `platforms` or `onlyIf` excluded this machine, nothing else ran.
+    NotApplicable,
+    /// The check exited with one of its `exitCodes.skip`, so the fix didn't run.
+    CheckSkipped,
+    /// The check exited with one of its `exitCodes.stop`, so the fix didn't run and the rest of
+    /// the group is skipped.
+    CheckFailedStop,
+    /// The fix of a `runAlways` action ran and succeeded.
+    SetupSucceeded,
+    /// The fix of a `runAlways` action ran and failed.
+    SetupFailed,
+    /// The fix of a `runAlways` action didn't run, because fixes are turned off.
+    SetupNotRun,
 }
 
 #[derive(Debug, Clone)]
 pub struct ActionRunResult {
-    pub action_name: String,
     pub status: ActionRunStatus,
     pub action_report: ActionReport,
 }
@@ -102,7 +153,6 @@ impl ActionRunResult {
         }
 
         Self {
-            action_name: name.to_string(),
             status,
             action_report: builder
                 .build()
@@ -111,6 +161,23 @@ impl ActionRunResult {
     }
 }
 
+impl ActionRunResult {
+    fn with_fix_changes(mut self, changes: &[FixChange]) -> Self {
+        self.action_report.fix_changes = changes.to_vec();
+        self
+    }
+
+    fn with_rollback(mut self, rollback: &[ActionTaskReport]) -> Self {
+        self.action_report.rollback = rollback.to_vec();
+        self
+    }
+
+    fn with_artifacts(mut self, artifacts: &[FixArtifact]) -> Self {
+        self.action_report.artifacts = artifacts.to_vec();
+        self
+    }
+}
+
 impl ActionRunStatus {
     pub(crate) fn is_failure(&self) -> bool {
         match self {
@@ -122,6 +189,15 @@ impl ActionRunStatus {
             ActionRunStatus::CheckFailedNoFixProvided => true,
             ActionRunStatus::CheckFailedFixFailedStop => true,
             ActionRunStatus::NoCheckFixSucceeded => false,
+            ActionRunStatus::CheckFailedFixUserDenied => true,
+            ActionRunStatus::CheckFailedFixNotInteractive => true,
+            ActionRunStatus::Timeout => true,
+            ActionRunStatus::NotApplicable => false,
+            ActionRunStatus::CheckSkipped => false,
+            ActionRunStatus::CheckFailedStop => true,
+            ActionRunStatus::SetupSucceeded => false,
+            ActionRunStatus::SetupFailed => true,
+            ActionRunStatus::SetupNotRun => false,
         }
     }
 }
@@ -130,7 +206,14 @@ impl ActionRunStatus {
 #[async_trait::async_trait]
 pub trait DoctorActionRun: Send + Sync {
     async fn run_action(&self) -> Result<ActionRunResult>;
+    /// What [`DoctorActionRun::run_action`] would do, without running any commands or updating
+    /// the cache.
+    async fn plan_action(&self) -> Result<ActionPlan>;
+    /// The files the check lists changed since they were last cached. False when the check
+    /// doesn't list files.
+    async fn files_changed(&self) -> Result<bool>;
     fn required(&self) -> bool;
+    fn allow_failure(&self) -> bool;
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn help_text(&self) -> Option<String>;
@@ -150,18 +233,53 @@ pub struct DefaultDoctorActionRun {
     pub exec_runner: Arc<dyn ExecutionProvider>,
     #[educe(Debug(ignore))]
     pub glob_walker: Arc<dyn GlobWalker>,
+    #[educe(Debug(ignore))]
+    pub audit_log: Arc<dyn AuditLog>,
+    #[educe(Debug(ignore))]
+    pub interaction: Arc<dyn UserInteraction>,
+    #[educe(Debug(ignore))]
+    pub progress: Arc<dyn ProgressReporter>,
 }
 
 #[async_trait::async_trait]
 impl DoctorActionRun for DefaultDoctorActionRun {
     #[instrument(skip_all, fields(model.name = self.model.name(), action.name = self.action.name, action.description = self.action.description ))]
     async fn run_action(&self) -> Result<ActionRunResult> {
+        if !self.is_applicable().await? {
+            return Ok(ActionRunResult::new(
+                &self.name(),
+                ActionRunStatus::NotApplicable,
+                None,
+                None,
+                None,
+            ));
+        }
+
+        if self.action.run_always {
+            return self.run_setup().await;
+        }
+
         let check_results = self.evaluate_checks().await?;
+        if check_results.timed_out {
+            return Ok(ActionRunResult::new(
+                &self.name(),
+                ActionRunStatus::Timeout,
+                check_results.output,
+                None,
+                None,
+            ));
+        }
         let check_status = check_results.status;
-        if check_status == CacheStatus::FixNotRequired {
+        let status = match check_status {
+            CacheStatus::FixNotRequired => Some(ActionRunStatus::CheckSucceeded),
+            CacheStatus::Skip => Some(ActionRunStatus::CheckSkipped),
+            CacheStatus::StopExecution => Some(ActionRunStatus::CheckFailedStop),
+            _ => None,
+        };
+        if let Some(status) = status {
             return Ok(ActionRunResult::new(
                 &self.name(),
-                ActionRunStatus::CheckSucceeded,
+                status,
                 check_results.output,
                 None,
                 None,
@@ -178,9 +296,58 @@ impl DoctorActionRun for DefaultDoctorActionRun {
             ));
         }
 
-        let (fix_result, fix_output) = self.run_fixes().await?;
+        let prompt_answer = self.confirm_fix();
+        match prompt_answer {
+            Some(PromptAnswer::Denied) => {
+                self.record_denied_fix().await;
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    ActionRunStatus::CheckFailedFixUserDenied,
+                    check_results.output,
+                    None,
+                    None,
+                ));
+            }
+            Some(PromptAnswer::NotInteractive) => {
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    ActionRunStatus::CheckFailedFixNotInteractive,
+                    check_results.output,
+                    None,
+                    None,
+                ));
+            }
+            Some(PromptAnswer::Approved) | None => {}
+        }
+
+        let snapshot_before = self.take_snapshot().await;
+        let fix_results = self
+            .run_fixes(prompt_answer.and_then(|answer| answer.as_recorded()))
+            .await?;
+        let fix_output = fix_results.output;
+        let fix_changes = match snapshot_before {
+            Some((before, files_before)) => {
+                let (after, files_after) = self.take_snapshot().await.unwrap_or_default();
+                let mut changes = diff_snapshots(&before, &after);
+                add_file_diffs(&mut changes, &files_before, &files_after);
+                changes
+            }
+            None => Vec::new(),
+        };
+
+        if fix_results.timed_out {
+            return Ok(ActionRunResult::new(
+                &self.name(),
+                ActionRunStatus::Timeout,
+                check_results.output,
+                Some(fix_output),
+                None,
+            )
+            .with_fix_changes(&fix_changes)
+            .with_rollback(&fix_results.rollback));
+        }
 
-        match fix_result {
+        match fix_results.exit_code {
             i32::MIN..=-1 => {
                 return Ok(ActionRunResult::new(
                     &self.name(),
@@ -198,7 +365,9 @@ impl DoctorActionRun for DefaultDoctorActionRun {
                     check_results.output,
                     Some(fix_output),
                     None,
-                ));
+                )
+                .with_fix_changes(&fix_changes)
+                .with_rollback(&fix_results.rollback));
             }
             _ => {
                 return Ok(ActionRunResult::new(
@@ -207,10 +376,25 @@ impl DoctorActionRun for DefaultDoctorActionRun {
                     check_results.output,
                     Some(fix_output),
                     None,
-                ));
+                )
+                .with_fix_changes(&fix_changes)
+                .with_rollback(&fix_results.rollback));
             }
         }
 
+        let artifacts = self.verify_artifacts().await;
+        if artifacts.iter().any(|artifact| !artifact.exists) {
+            return Ok(ActionRunResult::new(
+                &self.name(),
+                ActionRunStatus::CheckFailedFixSucceedVerifyFailed,
+                check_results.output,
+                Some(fix_output),
+                None,
+            )
+            .with_fix_changes(&fix_changes)
+            .with_artifacts(&artifacts));
+        }
+
         if check_status == CacheStatus::CacheNotDefined {
             self.update_caches().await;
             return Ok(ActionRunResult::new(
@@ -219,12 +403,44 @@ impl DoctorActionRun for DefaultDoctorActionRun {
                 check_results.output,
                 Some(fix_output),
                 None,
-            ));
+            )
+            .with_fix_changes(&fix_changes)
+            .with_artifacts(&artifacts));
+        }
+
+        if let Some(validate_result) = self.evaluate_builtin_checks().await? {
+            if validate_result.timed_out || validate_result.status != CacheStatus::FixNotRequired {
+                let status = if validate_result.timed_out {
+                    ActionRunStatus::Timeout
+                } else {
+                    ActionRunStatus::CheckFailedFixSucceedVerifyFailed
+                };
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    status,
+                    check_results.output,
+                    Some(fix_output),
+                    validate_result.output,
+                )
+                .with_fix_changes(&fix_changes)
+                .with_artifacts(&artifacts));
+            }
         }
 
         let mut validate_output = None;
         if let Some(validate_result) = self.evaluate_command_checks().await? {
             validate_output = validate_result.output;
+            if validate_result.timed_out {
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    ActionRunStatus::Timeout,
+                    check_results.output,
+                    Some(fix_output),
+                    validate_output,
+                )
+                .with_fix_changes(&fix_changes)
+                .with_artifacts(&artifacts));
+            }
             if validate_result.status != CacheStatus::FixNotRequired {
                 return Ok(ActionRunResult::new(
                     &self.name(),
@@ -232,7 +448,9 @@ impl DoctorActionRun for DefaultDoctorActionRun {
                     check_results.output,
                     Some(fix_output),
                     validate_output,
-                ));
+                )
+                .with_fix_changes(&fix_changes)
+                .with_artifacts(&artifacts));
             }
         }
 
@@ -244,13 +462,108 @@ impl DoctorActionRun for DefaultDoctorActionRun {
             check_results.output,
             Some(fix_output),
             validate_output,
-        ));
+        )
+        .with_fix_changes(&fix_changes)
+        .with_artifacts(&artifacts));
+    }
+
+    async fn plan_action(&self) -> Result<ActionPlan> {
+        if !self
+            .action
+            .condition
+            .includes_platform(std::env::consts::OS)
+        {
+            return Ok(ActionPlan {
+                name: self.name(),
+                description: self.description(),
+                status: ActionPlanStatus::NotApplicable,
+                check_commands: Vec::new(),
+                fix_commands: Vec::new(),
+                fix_prompts: false,
+            });
+        }
+
+        if self.action.run_always {
+            let fix_commands = match &self.action.fix.command {
+                Some(fix) if self.run_fix => fix.commands.clone(),
+                _ => Vec::new(),
+            };
+            return Ok(ActionPlan {
+                name: self.name(),
+                description: self.description(),
+                status: ActionPlanStatus::RunsAlways,
+                check_commands: Vec::new(),
+                fix_prompts: !fix_commands.is_empty() && self.action.fix.prompt.is_some(),
+                fix_commands,
+            });
+        }
+
+        let files_changed = match &self.action.check.files {
+            Some(cache_path) => {
+                Some(self.evaluate_path_check(cache_path).await? == CacheStatus::FixRequired)
+            }
+            None => None,
+        };
+        let check_commands: Vec<_> = self
+            .action
+            .check
+            .builtin
+            .iter()
+            .map(BuiltinCheck::command)
+            .chain(
+                self.action
+                    .check
+                    .command
+                    .iter()
+                    .flat_map(|c| c.commands.clone()),
+            )
+            .collect();
+
+        // A changed file makes the fix needed before any check command runs.
+        let status = match files_changed {
+            Some(true) => ActionPlanStatus::FixNeeded,
+            _ if !check_commands.is_empty() => ActionPlanStatus::CheckWillRun,
+            Some(false) => ActionPlanStatus::UpToDate,
+            None => ActionPlanStatus::FixNeeded,
+        };
+        let fix_commands = match &self.action.fix.command {
+            Some(fix) if self.run_fix && status != ActionPlanStatus::UpToDate => {
+                fix.commands.clone()
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(ActionPlan {
+            name: self.name(),
+            description: self.description(),
+            status,
+            check_commands: if status == ActionPlanStatus::CheckWillRun {
+                check_commands
+            } else {
+                Vec::new()
+            },
+            fix_prompts: !fix_commands.is_empty() && self.action.fix.prompt.is_some(),
+            fix_commands,
+        })
+    }
+
+    async fn files_changed(&self) -> Result<bool> {
+        match &self.action.check.files {
+            Some(cache_path) => {
+                Ok(self.evaluate_path_check(cache_path).await? == CacheStatus::FixRequired)
+            }
+            None => Ok(false),
+        }
     }
 
     fn required(&self) -> bool {
         self.action.required
     }
 
+    fn allow_failure(&self) -> bool {
+        self.action.allow_failure
+    }
+
     fn name(&self) -> String {
         self.action.name.to_string()
     }
@@ -269,13 +582,107 @@ impl DoctorActionRun for DefaultDoctorActionRun {
 }
 
 impl DefaultDoctorActionRun {
+    /// Directory the action's commands run in, its `workingDir` when it has one.
+    fn command_dir(&self) -> &Path {
+        self.action
+            .working_dir
+            .as_deref()
+            .unwrap_or(&self.working_dir)
+    }
+
+    /// Run the fix of a `runAlways` action. There's no check, and nothing is cached, so it runs
+    /// every time fixes are on.
+    async fn run_setup(&self) -> Result<ActionRunResult> {
+        if !self.run_fix {
+            return Ok(ActionRunResult::new(
+                &self.name(),
+                ActionRunStatus::SetupNotRun,
+                None,
+                None,
+                None,
+            ));
+        }
+
+        let prompt_answer = self.confirm_fix();
+        match prompt_answer {
+            Some(PromptAnswer::Denied) => {
+                self.record_denied_fix().await;
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    ActionRunStatus::CheckFailedFixUserDenied,
+                    None,
+                    None,
+                    None,
+                ));
+            }
+            Some(PromptAnswer::NotInteractive) => {
+                return Ok(ActionRunResult::new(
+                    &self.name(),
+                    ActionRunStatus::CheckFailedFixNotInteractive,
+                    None,
+                    None,
+                    None,
+                ));
+            }
+            Some(PromptAnswer::Approved) | None => {}
+        }
+
+        let fix_results = self
+            .run_fixes(prompt_answer.and_then(|answer| answer.as_recorded()))
+            .await?;
+        let artifacts = if fix_results.exit_code == 0 {
+            self.verify_artifacts().await
+        } else {
+            Vec::new()
+        };
+        let status = if fix_results.timed_out {
+            ActionRunStatus::Timeout
+        } else if fix_results.exit_code == 0 && artifacts.iter().all(|artifact| artifact.exists) {
+            ActionRunStatus::SetupSucceeded
+        } else {
+            ActionRunStatus::SetupFailed
+        };
+
+        Ok(
+            ActionRunResult::new(&self.name(), status, None, Some(fix_results.output), None)
+                .with_rollback(&fix_results.rollback)
+                .with_artifacts(&artifacts),
+        )
+    }
+
+    /// Whether each of the files the fix declares in `artifacts` is there now. Each one is looked
+    /// for with `test -e` where the fix ran, which may be a container or another machine.
+    async fn verify_artifacts(&self) -> Vec<FixArtifact> {
+        let mut artifacts = Vec::new();
+        for path in &self.action.fix.artifacts {
+            let args = vec![format!("test -e {}", quote_sh(path.clone()))];
+            let exists = self
+                .exec_runner
+                .run_command(CaptureOpts {
+                    working_dir: self.command_dir(),
+                    args: &args,
+                    output_dest: OutputDestination::Null,
+                    path: &self.model.metadata.exec_path(),
+                    env_vars: self.generate_env_vars(),
+                    timeout: self.action.timeout,
+                    progress: None,
+                })
+                .await
+                .is_ok_and(|capture| capture.exit_code == Some(0));
+            artifacts.push(FixArtifact {
+                path: path.clone(),
+                exists,
+            });
+        }
+        artifacts
+    }
+
     async fn update_caches(&self) {
         if let Some(cache_path) = &self.action.check.files {
             let result = self
                 .glob_walker
                 .update_cache(
-                    &cache_path.base_path,
-                    &cache_path.paths,
+                    cache_path,
                     &self.model.metadata.name(),
                     self.file_cache.clone(),
                 )
@@ -288,29 +695,145 @@ impl DefaultDoctorActionRun {
         }
     }
 
-    async fn run_fixes(&self) -> Result<(i32, Vec<ActionTaskReport>), RuntimeError> {
+    /// Snapshot of the values in the fix's `snapshot`, along with the text of its files so changes
+    /// to them can be shown as a diff.
+    async fn take_snapshot(&self) -> Option<(Snapshot, FileContents)> {
+        let spec = self.action.fix.snapshot.as_ref()?;
+        let snapshot = take_snapshot(
+            spec,
+            self.exec_runner.as_ref(),
+            self.command_dir(),
+            &self.model.metadata.exec_path(),
+        )
+        .await;
+        let files = read_files(&spec.files, self.command_dir()).await;
+        Some((snapshot, files))
+    }
+
+    /// Ask before running a fix that has a `prompt`, `None` when there is nothing to ask.
+    fn confirm_fix(&self) -> Option<PromptAnswer> {
+        self.action.fix.command.as_ref()?;
+        let prompt = self.action.fix.prompt.as_ref()?;
+        let extra_context = self.prompt_context(prompt.extra_context.as_deref());
+        self.progress.fix_prompt(&prompt.text);
+        Some(
+            self.interaction
+                .confirm(&prompt.text, extra_context.as_deref()),
+        )
+    }
+
+    /// The prompt's extra context, followed by the snapshot files the fix may change so they can
+    /// be reviewed before it runs.
+    fn prompt_context(&self, extra_context: Option<&str>) -> Option<String> {
+        let files = self
+            .action
+            .fix
+            .snapshot
+            .as_ref()
+            .map(|snapshot| snapshot.files.as_slice())
+            .unwrap_or_default();
+        if files.is_empty() {
+            return extra_context.map(str::to_string);
+        }
+
+        let files = format!("May change: {}", files.join(", "));
+        Some(match extra_context {
+            Some(extra_context) => format!("{} {}", extra_context, files),
+            None => files,
+        })
+    }
+
+    async fn record_denied_fix(&self) {
+        let Some(action_command) = &self.action.fix.command else {
+            return;
+        };
+        for command in &action_command.commands {
+            self.audit_log
+                .record_fix(FixExecution {
+                    group: self.model.metadata.name().to_string(),
+                    action: self.action.name.clone(),
+                    command: command.to_string(),
+                    exit_code: None,
+                    prompt_answer: Some(false),
+                })
+                .await;
+        }
+    }
+
+    async fn run_fixes(&self, prompt_answer: Option<bool>) -> Result<FixResults, RuntimeError> {
+        let sandbox = match &self.action.fix.sandbox_paths {
+            paths if paths.is_empty() => None,
+            paths => Some(FixSandbox::create(self.command_dir(), paths)?),
+        };
+        let fix_dir = match &sandbox {
+            Some(sandbox) => sandbox.dir().to_path_buf(),
+            None => self.command_dir().to_path_buf(),
+        };
+
         let mut action_reports = Vec::new();
         let mut highest_exit_code = -1;
+        let mut timed_out = false;
         if let Some(action_command) = &self.action.fix.command {
             for command in &action_command.commands {
-                let report = self.run_single_fix(command).await?;
-                highest_exit_code = max(highest_exit_code, report.exit_code.unwrap_or(-1));
-                action_reports.push(report);
+                let result = self
+                    .run_single_fix(command, &fix_dir, prompt_answer)
+                    .await?;
+                highest_exit_code = max(highest_exit_code, result.report.exit_code.unwrap_or(-1));
+                action_reports.push(result.report);
+                if result.timed_out {
+                    // Treated as a failed fix, so the sandbox is discarded and rollback runs.
+                    highest_exit_code = max(highest_exit_code, 1);
+                    timed_out = true;
+                    break;
+                }
                 if highest_exit_code >= 100 {
-                    return Ok((highest_exit_code, action_reports));
+                    break;
+                }
+            }
+        }
+
+        if let Some(sandbox) = sandbox {
+            if highest_exit_code == 0 {
+                sandbox.commit()?;
+            } else {
+                info!(target: "user", "Fix for `{}/{}` did not succeed, changes to {} were discarded", self.model.metadata.name(), self.action.name, self.action.fix.sandbox_paths.join(", "));
+            }
+        }
+
+        let mut rollback_reports = Vec::new();
+        if highest_exit_code > 0 {
+            if let Some(rollback_command) = &self.action.fix.rollback {
+                for command in &rollback_command.commands {
+                    let result = self
+                        .run_single_fix(command, self.command_dir(), prompt_answer)
+                        .await?;
+                    let succeeded = result.report.exit_code == Some(0);
+                    rollback_reports.push(result.report);
+                    if !succeeded {
+                        break;
+                    }
                 }
             }
         }
 
-        Ok((highest_exit_code, action_reports))
+        Ok(FixResults {
+            exit_code: highest_exit_code,
+            output: action_reports,
+            rollback: rollback_reports,
+            timed_out,
+        })
     }
 
-    async fn run_single_fix(&self, command: &str) -> Result<ActionTaskReport, RuntimeError> {
+    async fn run_single_fix(
+        &self,
+        command: &str,
+        working_dir: &Path,
+        prompt_answer: Option<bool>,
+    ) -> Result<TaskResult, RuntimeError> {
         let args = vec![command.to_string()];
-        let capture = self
-            .exec_runner
-            .run_command(CaptureOpts {
-                working_dir: &self.working_dir,
+        let result = self
+            .run_task(CaptureOpts {
+                working_dir,
                 args: &args,
                 output_dest: OutputDestination::StandardOutWithPrefix(format!(
                     "{}/{}",
@@ -319,31 +842,100 @@ impl DefaultDoctorActionRun {
                 )),
                 path: &self.model.metadata.exec_path(),
                 env_vars: self.generate_env_vars(),
+                timeout: self.action.timeout,
+                progress: Some(self.progress.as_ref()),
             })
             .await?;
 
-        info!("fix ran {} and exited {:?}", command, capture.exit_code);
+        info!(
+            "fix ran {} and exited {:?}",
+            command, result.report.exit_code
+        );
+        self.audit_log
+            .record_fix(FixExecution {
+                group: self.model.metadata.name().to_string(),
+                action: self.action.name.clone(),
+                command: command.to_string(),
+                exit_code: result.report.exit_code,
+                prompt_answer,
+            })
+            .await;
+
+        Ok(result)
+    }
 
-        Ok(ActionTaskReport::from(&capture))
+    /// Runs a check or fix command. A command that runs longer than the action's timeout is
+    /// reported without an exit code, rather than failing the run.
+    async fn run_task(&self, opts: CaptureOpts<'_>) -> Result<TaskResult, RuntimeError> {
+        self.progress.start_command(&opts.args.join(" "));
+        match self.exec_runner.run_command(opts).await {
+            Ok(capture) => Ok(TaskResult {
+                report: ActionTaskReport::from(&capture),
+                timed_out: false,
+            }),
+            Err(CaptureError::Timeout { command, timeout }) => {
+                info!(target: "user", "`{}` did not finish within {}s and was stopped", command, timeout.as_secs());
+                let end_time = Utc::now();
+                let duration = chrono::Duration::from_std(timeout)
+                    .unwrap_or_else(|_| chrono::Duration::zero());
+                Ok(TaskResult {
+                    report: ActionTaskReport {
+                        command,
+                        output: Some(format!("Timed out after {}s", timeout.as_secs())),
+                        exit_code: None,
+                        start_time: end_time - duration,
+                        end_time,
+                    },
+                    timed_out: true,
+                })
+            }
+            Err(error) => Err(error.into()),
+        }
     }
 
+    /// The action's `env`, along with `SCOPE_BIN_DIR`, which always points at this `scope`.
     fn generate_env_vars(&self) -> BTreeMap<String, String> {
-        let mut env_vars = BTreeMap::new();
-        env_vars.insert(
-            "SCOPE_BIN_DIR".to_string(),
-            std::env::current_exe()
-                .unwrap()
-                .parent()
-                .expect("executable should be in a directory")
-                .to_str()
-                .expect("bin directory should be a valid string")
-                .to_string(),
+        action_env_vars(&self.action.env)
+    }
+
+    /// The action's `platforms` include this machine, and its `onlyIf` command, when it has one,
+    /// exits 0.
+    async fn is_applicable(&self) -> Result<bool, RuntimeError> {
+        let condition = &self.action.condition;
+        if !condition.includes_platform(std::env::consts::OS) {
+            return Ok(false);
+        }
+        let Some(command) = &condition.only_if else {
+            return Ok(true);
+        };
+
+        let args = vec![command.clone()];
+        let path = join_path_list([
+            self.model.metadata().containing_dir(),
+            self.model.metadata().exec_path(),
+        ]);
+        let task = self
+            .run_task(CaptureOpts {
+                working_dir: self.command_dir(),
+                args: &args,
+                output_dest: OutputDestination::Logging,
+                path: &path,
+                env_vars: self.generate_env_vars(),
+                timeout: self.action.timeout,
+                progress: Some(self.progress.as_ref()),
+            })
+            .await?;
+
+        debug!(
+            "onlyIf `{}` exited with {:?}",
+            command, task.report.exit_code
         );
-        env_vars
+        Ok(task.report.exit_code == Some(0))
     }
 
     async fn evaluate_checks(&self) -> Result<CacheResults, RuntimeError> {
         let mut path_check = None;
+        let mut builtin_check = None;
         let mut command_check = None;
         if let Some(cache_path) = &self.action.check.files {
             let result = self.evaluate_path_check(cache_path).await?;
@@ -351,12 +943,20 @@ impl DefaultDoctorActionRun {
                 return Ok(CacheResults {
                     status: result,
                     output: None,
+                    timed_out: false,
                 });
             }
 
             path_check = Some(result);
         }
 
+        if let Some(results) = self.evaluate_builtin_checks().await? {
+            if !results.status.is_success() {
+                return Ok(results);
+            }
+            builtin_check = Some(results);
+        }
+
         if let Some(results) = self.evaluate_command_checks().await? {
             if !results.status.is_success() {
                 return Ok(results);
@@ -365,6 +965,7 @@ impl DefaultDoctorActionRun {
         }
 
         let status = match (&path_check, &command_check) {
+            (None, None) if builtin_check.is_some() => CacheStatus::FixNotRequired,
             (None, None) => CacheStatus::CacheNotDefined,
             (Some(p), None) if p.is_success() => CacheStatus::FixNotRequired,
             (None, Some(c)) if c.status.is_success() => CacheStatus::FixNotRequired,
@@ -374,12 +975,18 @@ impl DefaultDoctorActionRun {
             _ => CacheStatus::FixRequired,
         };
 
-        let output = match command_check {
-            Some(c) => c.output.clone(),
-            None => None,
+        let output = match (builtin_check, command_check) {
+            (Some(b), Some(c)) => Some(b.output.into_iter().chain(c.output).flatten().collect()),
+            (Some(b), None) => b.output,
+            (None, Some(c)) => c.output,
+            (None, None) => None,
         };
 
-        Ok(CacheResults { status, output })
+        Ok(CacheResults {
+            status,
+            output,
+            timed_out: false,
+        })
     }
 
     async fn evaluate_command_checks(&self) -> Result<Option<CacheResults>, RuntimeError> {
@@ -391,18 +998,69 @@ impl DefaultDoctorActionRun {
         Ok(None)
     }
 
+    /// Runs the `brew` and `toolVersion` checks, stopping at the first one that fails. A tool
+    /// that isn't installed fails its check.
+    async fn evaluate_builtin_checks(&self) -> Result<Option<CacheResults>, RuntimeError> {
+        if self.action.check.builtin.is_empty() {
+            return Ok(None);
+        }
+
+        let mut action_reports = Vec::new();
+        let mut status = CacheStatus::FixNotRequired;
+        let mut timed_out = false;
+        for check in &self.action.check.builtin {
+            let args = vec![check.command()];
+            let path = join_path_list([
+                self.model.metadata().containing_dir(),
+                self.model.metadata().exec_path(),
+            ]);
+            let task = match self
+                .run_task(CaptureOpts {
+                    working_dir: self.command_dir(),
+                    args: &args,
+                    output_dest: OutputDestination::Logging,
+                    path: &path,
+                    env_vars: self.generate_env_vars(),
+                    timeout: self.action.timeout,
+                    progress: Some(self.progress.as_ref()),
+                })
+                .await
+            {
+                Ok(task) => task,
+                Err(RuntimeError::CaptureError(CaptureError::MissingShExec { name })) => {
+                    info!(target: "user", "`{}` isn't installed", name);
+                    status = CacheStatus::FixRequired;
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+
+            let passed = task.report.exit_code == Some(0)
+                && builtin_check_passed(check, task.report.output.as_deref().unwrap_or_default());
+            action_reports.push(task.report);
+            if task.timed_out {
+                timed_out = true;
+            }
+            if !passed {
+                status = CacheStatus::FixRequired;
+                break;
+            }
+        }
+
+        Ok(Some(CacheResults {
+            status,
+            output: Some(action_reports),
+            timed_out,
+        }))
+    }
+
     async fn evaluate_path_check(
         &self,
         paths: &DoctorGroupCachePath,
     ) -> Result<CacheStatus, RuntimeError> {
         let result = self
             .glob_walker
-            .have_globs_changed(
-                &paths.base_path,
-                &paths.paths,
-                &self.model.metadata.name(),
-                self.file_cache.clone(),
-            )
+            .have_globs_changed(paths, &self.model.metadata.name(), self.file_cache.clone())
             .await?;
 
         if result {
@@ -419,45 +1077,60 @@ impl DefaultDoctorActionRun {
         info!("Evaluating {:?}", action_command);
         let mut action_reports = Vec::new();
         let mut result: Option<CacheStatus> = None;
+        let mut timed_out = false;
 
         for command in &action_command.commands {
             let args = vec![command.clone()];
-            let path = format!(
-                "{}:{}",
+            let path = join_path_list([
                 self.model.metadata().containing_dir(),
-                self.model.metadata().exec_path()
-            );
-            let output = self
-                .exec_runner
-                .run_command(CaptureOpts {
-                    working_dir: &self.working_dir,
+                self.model.metadata().exec_path(),
+            ]);
+            let task = self
+                .run_task(CaptureOpts {
+                    working_dir: self.command_dir(),
                     args: &args,
                     output_dest: OutputDestination::Logging,
                     path: &path,
                     env_vars: self.generate_env_vars(),
+                    timeout: self.action.timeout,
+                    progress: Some(self.progress.as_ref()),
                 })
                 .await?;
 
-            action_reports.push(ActionTaskReport::from(&output));
+            let exit_code = task.report.exit_code;
+            action_reports.push(task.report);
+            if task.timed_out {
+                timed_out = true;
+                break;
+            }
 
             info!(
                 "check ran command {} and result was {:?}",
-                command, output.exit_code
+                command, exit_code
             );
 
-            let command_result = match output.exit_code {
-                Some(0) => CacheStatus::FixNotRequired,
-                Some(100..=i32::MAX) => CacheStatus::StopExecution,
-                _ => CacheStatus::FixRequired,
+            // Without `exitCodes`, 100 and above only skip the rest of the check commands.
+            let (command_result, last_command) = match &self.action.check.exit_codes {
+                Some(exit_codes) => match exit_codes.meaning(exit_code) {
+                    ExitCodeMeaning::Success => (CacheStatus::FixNotRequired, false),
+                    ExitCodeMeaning::Skip => (CacheStatus::Skip, true),
+                    ExitCodeMeaning::Stop => (CacheStatus::StopExecution, true),
+                    ExitCodeMeaning::Failure => (CacheStatus::FixRequired, false),
+                },
+                None => match exit_code {
+                    Some(0) => (CacheStatus::FixNotRequired, false),
+                    Some(100..=i32::MAX) => (CacheStatus::FixRequired, true),
+                    _ => (CacheStatus::FixRequired, false),
+                },
             };
 
             let next = match &result {
                 None => command_result,
-                Some(prev) => cmp::max(prev.clone(), command_result.clone()),
+                Some(prev) => cmp::max(prev.clone(), command_result),
             };
 
             result.replace(next);
-            if result == Some(CacheStatus::StopExecution) {
+            if last_command {
                 break;
             }
         }
@@ -466,33 +1139,90 @@ impl DefaultDoctorActionRun {
         Ok(CacheResults {
             status,
             output: Some(action_reports),
+            timed_out,
         })
     }
 }
 
-#[automock]
-#[async_trait]
-pub trait GlobWalker: Send + Sync {
-    async fn have_globs_changed(
-        &self,
-        base_dir: &Path,
-        paths: &[String],
-        cache_name: &str,
-        file_cache: Arc<dyn FileCache>,
+/// Environment an action's commands run with: its `env`, and `SCOPE_BIN_DIR`, the directory of
+/// the running `scope`.
+pub(crate) fn action_env_vars(env: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut env_vars = env.clone();
+    env_vars.insert(
+        "SCOPE_BIN_DIR".to_string(),
+        std::env::current_exe()
+            .unwrap()
+            .parent()
+            .expect("executable should be in a directory")
+            .to_str()
+            .expect("bin directory should be a valid string")
+            .to_string(),
+    );
+    env_vars
+}
+
+/// Whether the output of a builtin check's command, which exited 0, passes the check.
+fn builtin_check_passed(check: &BuiltinCheck, output: &str) -> bool {
+    let BuiltinCheck::ToolVersion {
+        tool, constraint, ..
+    } = check
+    else {
+        return true;
+    };
+    match parse_tool_version(output) {
+        Some(version) if constraint.matches(&version) => true,
+        Some(version) => {
+            info!(target: "user", "`{}` is at {}, which doesn't match `{}`", tool, version, constraint);
+            false
+        }
+        None => {
+            info!(target: "user", "Unable to find the version of `{}` in its output", tool);
+            false
+        }
+    }
+}
+
+/// The first version number in `output`, like `20.11.0` in `v20.11.0`. Missing parts count as
+/// `0`, so `Python 3.12` is `3.12.0`.
+fn parse_tool_version(output: &str) -> Option<semver::Version> {
+    lazy_static! {
+        static ref VERSION: Regex = Regex::new(r"(\d+)(?:\.(\d+))?(?:\.(\d+))?").unwrap();
+    }
+    let captures = VERSION.captures(output)?;
+    let part = |idx| match captures.get(idx) {
+        Some(part) => part.as_str().parse::<u64>().ok(),
+        None => Some(0),
+    };
+    Some(semver::Version::new(part(1)?, part(2)?, part(3)?))
+}
+
+#[automock]
+#[async_trait]
+pub trait GlobWalker: Send + Sync {
+    async fn have_globs_changed(
+        &self,
+        cache_path: &DoctorGroupCachePath,
+        cache_name: &str,
+        file_cache: Arc<dyn FileCache>,
     ) -> Result<bool, RuntimeError>;
 
     async fn update_cache(
         &self,
-        base_dir: &Path,
-        paths: &[String],
+        cache_path: &DoctorGroupCachePath,
         cache_name: &str,
         file_cache: Arc<dyn FileCache>,
     ) -> Result<(), RuntimeError>;
+
+    /// Files matched by the globs in `cache_path`, without the ignored ones.
+    fn matching_files(
+        &self,
+        cache_path: &DoctorGroupCachePath,
+    ) -> Result<Vec<PathBuf>, RuntimeError>;
 }
 
 #[automock]
 trait FileSystem: Send + Sync {
-    fn find_files(&self, glob_pattern: &str) -> Result<Vec<PathBuf>>;
+    fn find_files(&self, glob_pattern: &str, respect_gitignore: bool) -> Result<Vec<PathBuf>>;
 }
 
 #[derive(Debug, Default)]
@@ -504,12 +1234,48 @@ struct DefaultFileSystem {}
 /// filesystem.
 impl FileSystem for DefaultFileSystem {
     /// Search for a glob pattern. This function expects the path to be absolute already,
-    /// so that it's not dependent on the working directory.
-    fn find_files(&self, glob_pattern: &str) -> Result<Vec<PathBuf>> {
-        Ok(glob::glob(glob_pattern)?.filter_map(Result::ok).collect())
+    /// so that it's not dependent on the working directory. With `respect_gitignore`, ignored
+    /// files are skipped and ignored directories aren't walked.
+    fn find_files(&self, glob_pattern: &str, respect_gitignore: bool) -> Result<Vec<PathBuf>> {
+        let pattern = glob::Pattern::new(glob_pattern)?;
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        let root = glob_walk_root(glob_pattern);
+        let mut walker = WalkBuilder::new(&root);
+        walker
+            .standard_filters(respect_gitignore)
+            .hidden(false)
+            .require_git(false)
+            .follow_links(true);
+        if !glob_pattern.contains("**") {
+            let depth = Path::new(glob_pattern).components().count() - root.components().count();
+            walker.max_depth(Some(depth));
+        }
+
+        Ok(walker
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry| pattern.matches_path_with(entry.path(), options))
+            .map(|entry| entry.into_path())
+            .collect())
     }
 }
 
+/// The directory of `glob_pattern` before its first wildcard, where the walk for it starts.
+fn glob_walk_root(glob_pattern: &str) -> PathBuf {
+    Path::new(glob_pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
 #[derive(Educe)]
 #[educe(Debug)]
 pub struct DefaultGlobWalker {
@@ -533,23 +1299,30 @@ fn make_absolute(base_dir: &Path, glob: &String) -> String {
     }
 }
 
+/// Matcher for the `ignore_paths` of `cache_path`. `.gitignore` files are handled while
+/// walking, see [`FileSystem::find_files`].
+fn build_ignore(cache_path: &DoctorGroupCachePath) -> Result<Gitignore, RuntimeError> {
+    let mut builder = GitignoreBuilder::new(&cache_path.base_path);
+    for pattern in &cache_path.ignore_paths {
+        builder.add_line(None, pattern)?;
+    }
+
+    Ok(builder.build()?)
+}
+
 #[async_trait]
 impl GlobWalker for DefaultGlobWalker {
     async fn have_globs_changed(
         &self,
-        base_dir: &Path,
-        paths: &[String],
+        cache_path: &DoctorGroupCachePath,
         cache_name: &str,
         file_cache: Arc<dyn FileCache>,
     ) -> Result<bool, RuntimeError> {
-        for glob_str in paths {
-            let glob_path = make_absolute(base_dir, glob_str);
-            for path in self.file_system.find_files(&glob_path)? {
-                let file_result = file_cache.check_file(cache_name.to_string(), &path).await?;
-                let check_result = file_result == FileCacheStatus::FileMatches;
-                if !check_result {
-                    return Ok(false);
-                }
+        for path in self.matching_files(cache_path)? {
+            let file_result = file_cache.check_file(cache_name.to_string(), &path).await?;
+            let check_result = file_result == FileCacheStatus::FileMatches;
+            if !check_result {
+                return Ok(false);
             }
         }
 
@@ -558,37 +1331,67 @@ impl GlobWalker for DefaultGlobWalker {
 
     async fn update_cache(
         &self,
-        base_dir: &Path,
-        paths: &[String],
+        cache_path: &DoctorGroupCachePath,
         cache_name: &str,
         file_cache: Arc<dyn FileCache>,
     ) -> Result<(), RuntimeError> {
-        for glob_str in paths {
-            let glob_path = make_absolute(base_dir, glob_str);
-            for path in self.file_system.find_files(&glob_path)? {
-                file_cache
-                    .update_cache_entry(cache_name.to_string(), &path)
-                    .await?;
-            }
+        for path in self.matching_files(cache_path)? {
+            file_cache
+                .update_cache_entry(cache_name.to_string(), &path)
+                .await?;
         }
 
         Ok(())
     }
+
+    fn matching_files(
+        &self,
+        cache_path: &DoctorGroupCachePath,
+    ) -> Result<Vec<PathBuf>, RuntimeError> {
+        let ignore = build_ignore(cache_path)?;
+        let mut files = Vec::new();
+        for glob_str in &cache_path.paths {
+            let glob_path = make_absolute(&cache_path.base_path, glob_str);
+            let found = self
+                .file_system
+                .find_files(&glob_path, cache_path.respect_gitignore)?;
+            for path in found {
+                // The matcher only knows about paths under the directory it was built for.
+                let ignored = path.starts_with(&cache_path.base_path)
+                    && ignore
+                        .matched_path_or_any_parents(&path, path.is_dir())
+                        .is_ignore();
+                if ignored {
+                    debug!("Leaving {} out of the cache, it's ignored", path.display());
+                } else {
+                    files.push(path);
+                }
+            }
+        }
+
+        Ok(files)
+    }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use crate::doctor::audit_log::MockAuditLog;
     use crate::doctor::check::{
-        ActionRunStatus, DefaultDoctorActionRun, DefaultGlobWalker, DoctorActionRun, GlobWalker,
-        MockFileSystem, MockGlobWalker, RuntimeError,
+        parse_tool_version, ActionRunStatus, DefaultDoctorActionRun, DefaultFileSystem,
+        DefaultGlobWalker, DoctorActionRun, FileSystem, GlobWalker, MockFileSystem, MockGlobWalker,
+        RuntimeError,
     };
     use crate::doctor::file_cache::{FileCache, MockFileCache, NoOpCache};
+    use crate::doctor::plan::ActionPlanStatus;
     use crate::doctor::tests::build_root_model;
+    use crate::prelude::DoctorPlatform;
     use crate::shared::prelude::*;
     use anyhow::{anyhow, Result};
     use predicates::prelude::predicate;
+    use std::collections::BTreeMap;
     use std::path::{Path, PathBuf};
     use std::sync::Arc;
+    use std::time::Duration;
 
     pub fn build_run_fail_fix_succeed_action() -> DoctorGroupAction {
         DoctorGroupActionBuilder::default()
@@ -663,6 +1466,8 @@ pub(crate) mod tests {
         let model = build_root_model(actions.clone());
         let path = PathBuf::from("/tmp/foo");
         let file_cache: Arc<dyn FileCache> = Arc::<NoOpCache>::default();
+        let mut audit_log = MockAuditLog::new();
+        audit_log.expect_record_fix().return_const(());
 
         DefaultDoctorActionRun {
             model,
@@ -672,9 +1477,31 @@ pub(crate) mod tests {
             run_fix: true,
             exec_runner: Arc::new(exec_runner),
             glob_walker: Arc::new(glob_walker),
+            audit_log: Arc::new(audit_log),
+            interaction: Arc::new(MockUserInteraction::new()),
+            progress: Arc::new(NoOpProgressReporter),
         }
     }
 
+    fn prompted_fix_action() -> DoctorGroupAction {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.fix.prompt = Some(DoctorGroupActionFixPrompt {
+            text: "Run the fix?".to_string(),
+            extra_context: None,
+        });
+        action
+    }
+
+    fn answer_prompt(answer: PromptAnswer) -> Arc<MockUserInteraction> {
+        let mut interaction = MockUserInteraction::new();
+        interaction
+            .expect_confirm()
+            .times(1)
+            .withf(|prompt, _| prompt == "Run the fix?")
+            .return_const(answer);
+        Arc::new(interaction)
+    }
+
     #[tokio::test]
     async fn test_only_exec_will_check_passes() -> Result<()> {
         let action = build_run_fail_fix_succeed_action();
@@ -692,6 +1519,84 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_action_env_is_passed_to_commands() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.env = BTreeMap::from([("NODE_ENV".to_string(), "test".to_string())]);
+        let mut exec_runner = MockExecutionProvider::new();
+        let glob_walker = MockGlobWalker::new();
+
+        exec_runner
+            .expect_run_command()
+            .times(1)
+            .withf(|params| {
+                params.env_vars.get("NODE_ENV").map(String::as_str) == Some("test")
+                    && params.env_vars.contains_key("SCOPE_BIN_DIR")
+            })
+            .returning(|_| {
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(0))
+                    .build()
+                    .unwrap())
+            });
+
+        let run = setup_test(vec![action], exec_runner, glob_walker);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckSucceeded, result.status);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_if_decides_whether_action_applies() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.condition.only_if = Some("test -f Brewfile".to_string());
+        let mut exec_runner = MockExecutionProvider::new();
+
+        command_result(&mut exec_runner, "test -f Brewfile", vec![1]);
+
+        let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::NotApplicable, result.status);
+        assert!(!result.status.is_failure());
+        assert!(result.action_report.check.is_empty());
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "test -f Brewfile", vec![0]);
+        command_result(&mut exec_runner, "check", vec![0]);
+
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckSucceeded, result.status);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_action_for_other_platform_is_not_applicable() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.condition.platforms = vec![if cfg!(windows) {
+            DoctorPlatform::Linux
+        } else {
+            DoctorPlatform::Windows
+        }];
+
+        let run = setup_test(
+            vec![action],
+            MockExecutionProvider::new(),
+            MockGlobWalker::new(),
+        );
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::NotApplicable, result.status);
+
+        let plan = run.plan_action().await?;
+        assert_eq!(ActionPlanStatus::NotApplicable, plan.status);
+        assert!(plan.check_commands.is_empty());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_only_exec_will_re_run() -> Result<()> {
         let action = build_run_fail_fix_succeed_action();
@@ -760,6 +1665,245 @@ pub(crate) mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_check_exit_codes_decide_status() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.check.exit_codes = Some(DoctorExitCodes {
+            success: vec![0..=0, 3..=3],
+            skip: vec![10..=10],
+            stop: vec![20..=30],
+        });
+
+        for (exit_code, status) in [
+            (3, ActionRunStatus::CheckSucceeded),
+            (10, ActionRunStatus::CheckSkipped),
+            (25, ActionRunStatus::CheckFailedStop),
+        ] {
+            let mut exec_runner = MockExecutionProvider::new();
+            command_result(&mut exec_runner, "check", vec![exit_code]);
+            let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+
+            let result = run.run_action().await?;
+            assert_eq!(status, result.status);
+            assert!(result.action_report.fix.is_empty());
+        }
+
+        // 100 isn't in `stop`, so it's a failure the fix can help with.
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![100, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            run.run_action().await?.status
+        );
+
+        // A command that skips after one that failed doesn't hide the failure.
+        action.check.command = Some(DoctorGroupActionCommand::from(vec!["check", "check"]));
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1, 10, 0, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            run.run_action().await?.status
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_exit_code_over_100_runs_fix_without_exit_codes() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.check.command = Some(DoctorGroupActionCommand::from(vec!["check", "check"]));
+
+        // The second check command doesn't run, and the fix still does.
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![100, 0, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+
+        let result = run.run_action().await?;
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            result.status
+        );
+        assert_eq!(1, result.action_report.check.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tool_version_check_runs_fix_until_version_matches() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.check.command = None;
+        action.check.builtin = vec![BuiltinCheck::ToolVersion {
+            tool: "node".to_string(),
+            command: "node --version".to_string(),
+            constraint: semver::VersionReq::parse(">=20").unwrap(),
+        }];
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let mut versions = vec!["v20.11.0", "v18.19.0"];
+        exec_runner
+            .expect_run_command()
+            .times(2)
+            .withf(|params| params.args[0] == "node --version")
+            .returning(move |_| {
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(0))
+                    .stdout(vec![(
+                        chrono::Utc::now(),
+                        versions.pop().unwrap().to_string(),
+                    )])
+                    .build()
+                    .unwrap())
+            });
+
+        let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            result.status
+        );
+
+        // A tool that isn't installed fails the check.
+        let mut exec_runner = MockExecutionProvider::new();
+        exec_runner
+            .expect_run_command()
+            .times(1)
+            .withf(|params| params.args[0] == "node --version")
+            .returning(|_| {
+                Err(CaptureError::MissingShExec {
+                    name: "node".to_string(),
+                })
+            });
+        action.fix.command = None;
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        assert_eq!(
+            ActionRunStatus::CheckFailedNoFixProvided,
+            run.run_action().await?.status
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_tool_version() {
+        let version = |output: &str| parse_tool_version(output).map(|v| v.to_string());
+        assert_eq!(Some("20.11.0".to_string()), version("v20.11.0"));
+        assert_eq!(Some("3.12.0".to_string()), version("Python 3.12"));
+        assert_eq!(
+            Some("1.22.1".to_string()),
+            version("go version go1.22.1 darwin/arm64")
+        );
+        assert_eq!(None, version("command not found"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_fix_runs_rollback_until_one_fails() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.fix.rollback = Some(DoctorGroupActionCommand::from(vec!["undo", "undo-more"]));
+        let mut exec_runner = MockExecutionProvider::new();
+        let glob_walker = MockGlobWalker::new();
+
+        command_result(&mut exec_runner, "check", vec![1]);
+        command_result(&mut exec_runner, "fix", vec![1]);
+        command_result(&mut exec_runner, "undo", vec![2]);
+
+        let run = setup_test(vec![action], exec_runner, glob_walker);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckFailedFixFailed, result.status);
+        assert_eq!(1, result.action_report.rollback.len());
+        assert_eq!(Some(2), result.action_report.rollback[0].exit_code);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_successful_fix_skips_rollback() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.fix.rollback = Some(DoctorGroupActionCommand::from(vec!["undo"]));
+        let mut exec_runner = MockExecutionProvider::new();
+        let glob_walker = MockGlobWalker::new();
+
+        command_result(&mut exec_runner, "check", vec![1, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+
+        let run = setup_test(vec![action], exec_runner, glob_walker);
+
+        let result = run.run_action().await?;
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            result.status
+        );
+        assert!(result.action_report.rollback.is_empty());
+
+        Ok(())
+    }
+
+    fn command_times_out(mock: &mut MockExecutionProvider, command: &'static str) {
+        mock.expect_run_command()
+            .times(1)
+            .withf(move |params| {
+                params.args[0].eq(command) && params.timeout == Some(Duration::from_secs(5))
+            })
+            .returning(move |_| {
+                Err(CaptureError::Timeout {
+                    command: command.to_string(),
+                    timeout: Duration::from_secs(5),
+                })
+            });
+    }
+
+    #[tokio::test]
+    async fn test_check_timeout_fails_action() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.timeout = Some(Duration::from_secs(5));
+        let mut exec_runner = MockExecutionProvider::new();
+        let glob_walker = MockGlobWalker::new();
+
+        command_times_out(&mut exec_runner, "check");
+
+        let run = setup_test(vec![action], exec_runner, glob_walker);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::Timeout, result.status);
+        assert_eq!(1, result.action_report.check.len());
+        assert_eq!(None, result.action_report.check[0].exit_code);
+        assert_eq!(
+            Some("Timed out after 5s".to_string()),
+            result.action_report.check[0].output
+        );
+        assert!(result.action_report.fix.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fix_timeout_runs_rollback() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.timeout = Some(Duration::from_secs(5));
+        action.fix.rollback = Some(DoctorGroupActionCommand::from(vec!["undo"]));
+        let mut exec_runner = MockExecutionProvider::new();
+        let glob_walker = MockGlobWalker::new();
+
+        command_result(&mut exec_runner, "check", vec![1]);
+        command_times_out(&mut exec_runner, "fix");
+        command_result(&mut exec_runner, "undo", vec![0]);
+
+        let run = setup_test(vec![action], exec_runner, glob_walker);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::Timeout, result.status);
+        assert_eq!(1, result.action_report.fix.len());
+        assert_eq!(1, result.action_report.rollback.len());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_file_cache_invalid_fix_works() -> Result<()> {
         let action = build_file_fix_action();
@@ -771,11 +1915,11 @@ pub(crate) mod tests {
         glob_walker
             .expect_have_globs_changed()
             .times(1)
-            .returning(|_, _, _, _| Ok(false));
+            .returning(|_, _, _| Ok(false));
         glob_walker
             .expect_update_cache()
             .times(1)
-            .returning(|_, _, _, _| Ok(()));
+            .returning(|_, _, _| Ok(()));
 
         let run = setup_test(vec![action], exec_runner, glob_walker);
 
@@ -801,11 +1945,11 @@ pub(crate) mod tests {
         glob_walker
             .expect_have_globs_changed()
             .times(1)
-            .returning(|_, _, _, _| Ok(false));
+            .returning(|_, _, _| Ok(false));
         glob_walker
             .expect_update_cache()
             .times(1)
-            .returning(|_, _, _, _| Err(RuntimeError::AnyError(anyhow!("bogus error"))));
+            .returning(|_, _, _| Err(RuntimeError::AnyError(anyhow!("bogus error"))));
 
         let run = setup_test(vec![action], exec_runner, glob_walker);
 
@@ -831,7 +1975,7 @@ pub(crate) mod tests {
         glob_walker
             .expect_have_globs_changed()
             .times(1)
-            .returning(|_, _, _, _| Ok(false));
+            .returning(|_, _, _| Ok(false));
         glob_walker.expect_update_cache().never();
 
         let run = setup_test(vec![action], exec_runner, glob_walker);
@@ -861,8 +2005,8 @@ pub(crate) mod tests {
         file_system
             .expect_find_files()
             .once()
-            .with(predicate::eq("/foo/root/*.txt"))
-            .returning(|_| Ok(vec![PathBuf::from("/foo/bar")]));
+            .with(predicate::eq("/foo/root/*.txt"), predicate::eq(true))
+            .returning(|_, _| Ok(vec![PathBuf::from("/foo/bar")]));
 
         let walker = DefaultGlobWalker {
             file_system: Box::new(file_system),
@@ -870,8 +2014,7 @@ pub(crate) mod tests {
 
         let res = walker
             .update_cache(
-                Path::new("/foo/root"),
-                &["*.txt".to_string()],
+                &DoctorGroupCachePath::from(("/foo/root", vec!["*.txt"])),
                 "file_cache",
                 Arc::new(file_cache),
             )
@@ -896,8 +2039,8 @@ pub(crate) mod tests {
         file_system
             .expect_find_files()
             .once()
-            .with(predicate::eq("/a/abs/path/*.txt"))
-            .returning(|_| Ok(vec![PathBuf::from("/foo/bar")]));
+            .with(predicate::eq("/a/abs/path/*.txt"), predicate::eq(true))
+            .returning(|_, _| Ok(vec![PathBuf::from("/foo/bar")]));
 
         let walker = DefaultGlobWalker {
             file_system: Box::new(file_system),
@@ -905,12 +2048,419 @@ pub(crate) mod tests {
 
         let res = walker
             .update_cache(
-                Path::new("/foo/root"),
-                &["/a/abs/path/*.txt".to_string()],
+                &DoctorGroupCachePath::from(("/foo/root", vec!["/a/abs/path/*.txt"])),
                 "file_cache",
                 Arc::new(file_cache),
             )
             .await;
         assert!(res.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_glob_walker_leaves_out_ignored_paths() {
+        let mut file_system = MockFileSystem::new();
+        let mut file_cache = MockFileCache::new();
+
+        file_cache
+            .expect_update_cache_entry()
+            .once()
+            .with(
+                predicate::eq("file_cache".to_string()),
+                predicate::eq(Path::new("/foo/root/src/main.rs")),
+            )
+            .returning(|_, _| Ok(()));
+
+        file_system
+            .expect_find_files()
+            .once()
+            .with(predicate::eq("/foo/root/**/*"), predicate::eq(true))
+            .returning(|_, _| {
+                Ok(vec![
+                    PathBuf::from("/foo/root/src/main.rs"),
+                    PathBuf::from("/foo/root/src/.main.rs.swp"),
+                    PathBuf::from("/foo/root/target/debug/scope"),
+                ])
+            });
+
+        let walker = DefaultGlobWalker {
+            file_system: Box::new(file_system),
+        };
+        let mut cache_path = DoctorGroupCachePath::from(("/foo/root", vec!["**/*"]));
+        cache_path.ignore_paths = vec!["*.swp".to_string(), "target/".to_string()];
+
+        let res = walker
+            .update_cache(&cache_path, "file_cache", Arc::new(file_cache))
+            .await;
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_find_files_skips_gitignored_directories() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "node_modules/\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/left-pad")).unwrap();
+        std::fs::write(dir.path().join("app.js"), "").unwrap();
+        std::fs::write(dir.path().join("src/main.js"), "").unwrap();
+        std::fs::write(dir.path().join("node_modules/left-pad/index.js"), "").unwrap();
+
+        let find = |glob: &str, respect_gitignore: bool| {
+            let mut files = DefaultFileSystem::default()
+                .find_files(
+                    &format!("{}/{}", dir.path().display(), glob),
+                    respect_gitignore,
+                )
+                .unwrap();
+            files.sort();
+            files
+        };
+
+        assert_eq!(
+            vec![dir.path().join("app.js"), dir.path().join("src/main.js")],
+            find("**/*.js", true)
+        );
+        assert_eq!(
+            vec![
+                dir.path().join("app.js"),
+                dir.path().join("node_modules/left-pad/index.js"),
+                dir.path().join("src/main.js"),
+            ],
+            find("**/*.js", false)
+        );
+        assert_eq!(vec![dir.path().join("app.js")], find("*.js", false));
+    }
+
+    #[tokio::test]
+    async fn test_sandboxed_fix_only_applies_on_success() -> Result<()> {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(working_dir.path().join("app.toml"), "old")?;
+
+        for (exit_code, expected) in [(1, "old"), (0, "new")] {
+            let mut action = build_run_fail_fix_succeed_action();
+            action.fix.sandbox_paths = vec!["app.toml".to_string()];
+
+            let mut exec_runner = MockExecutionProvider::new();
+            let check_results = if exit_code == 0 { vec![1, 0] } else { vec![1] };
+            command_result(&mut exec_runner, "check", check_results);
+            let original_dir = working_dir.path().to_path_buf();
+            exec_runner
+                .expect_run_command()
+                .times(1)
+                .withf(|params| params.args[0] == "fix")
+                .returning(move |params| {
+                    assert_ne!(original_dir, params.working_dir);
+                    std::fs::write(params.working_dir.join("app.toml"), "new").unwrap();
+                    Ok(OutputCaptureBuilder::default()
+                        .exit_code(Some(exit_code))
+                        .build()
+                        .unwrap())
+                });
+
+            let mut run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+            run.working_dir = working_dir.path().to_path_buf();
+            run.run_action().await?;
+
+            assert_eq!(
+                expected,
+                std::fs::read_to_string(working_dir.path().join("app.toml"))?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_artifacts_fail_the_fix() -> Result<()> {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        let mut action = build_run_fail_fix_succeed_action();
+        action.fix.artifacts = vec!["node_modules".to_string(), ".installed".to_string()];
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1]);
+        exec_runner
+            .expect_run_command()
+            .times(1)
+            .withf(|params| params.args[0] == "fix")
+            .returning(|params| {
+                std::fs::create_dir(params.working_dir.join("node_modules")).unwrap();
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(0))
+                    .build()
+                    .unwrap())
+            });
+        exec_runner
+            .expect_run_command()
+            .times(2)
+            .withf(|params| params.args[0].starts_with("test -e "))
+            .returning(|params| {
+                let path = params.args[0]["test -e ".len()..].trim_matches('\'');
+                let exists = params.working_dir.join(path).exists();
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(if exists { 0 } else { 1 }))
+                    .build()
+                    .unwrap())
+            });
+
+        let mut run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        run.working_dir = working_dir.path().to_path_buf();
+        let result = run.run_action().await?;
+
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifyFailed,
+            result.status
+        );
+        assert_eq!(
+            vec![
+                FixArtifact {
+                    path: "node_modules".to_string(),
+                    exists: true,
+                },
+                FixArtifact {
+                    path: ".installed".to_string(),
+                    exists: false,
+                },
+            ],
+            result.action_report.artifacts
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_always_runs_the_fix_without_a_check() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.check.command = None;
+        action.run_always = true;
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::SetupSucceeded, result.status);
+        assert!(result.action_report.check.is_empty());
+        assert_eq!(1, result.action_report.fix.len());
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "fix", vec![1]);
+        let run = setup_test(vec![action.clone()], exec_runner, MockGlobWalker::new());
+        assert_eq!(ActionRunStatus::SetupFailed, run.run_action().await?.status);
+
+        let mut run = setup_test(
+            vec![action],
+            MockExecutionProvider::new(),
+            MockGlobWalker::new(),
+        );
+        run.run_fix = false;
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::SetupNotRun, result.status);
+        assert!(!result.status.is_failure());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_run_in_action_working_dir() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.working_dir = Some(PathBuf::from("/tmp/foo/packages/api"));
+
+        let mut exec_runner = MockExecutionProvider::new();
+        let mut counter = 0;
+        exec_runner
+            .expect_run_command()
+            .times(3)
+            .withf(|params| params.working_dir == Path::new("/tmp/foo/packages/api"))
+            .returning(move |_| {
+                let exit_code = [1, 0, 0][counter];
+                counter += 1;
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(exit_code))
+                    .build()
+                    .unwrap())
+            });
+
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            result.status
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fix_changes_are_reported() -> Result<()> {
+        let mut action = build_run_fail_fix_succeed_action();
+        action.fix.snapshot = Some(DoctorGroupActionSnapshot {
+            commands: vec!["tool --version".to_string()],
+            ..Default::default()
+        });
+
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+        let mut versions = vec!["2.0.0", "1.0.0"];
+        exec_runner
+            .expect_run_command()
+            .times(2)
+            .withf(|params| params.args[0] == "tool --version")
+            .returning(move |_| {
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(0))
+                    .stdout(vec![(
+                        chrono::Utc::now(),
+                        versions.pop().unwrap().to_string(),
+                    )])
+                    .build()
+                    .unwrap())
+            });
+
+        let run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        let result = run.run_action().await?;
+
+        assert_eq!(
+            vec![FixChange {
+                kind: "command".to_string(),
+                name: "tool --version".to_string(),
+                before: Some("1.0.0".to_string()),
+                after: Some("2.0.0".to_string()),
+                diff: None,
+            }],
+            result.action_report.fix_changes
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompted_fix_runs_when_approved() -> Result<()> {
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+
+        let mut audit_log = MockAuditLog::new();
+        audit_log
+            .expect_record_fix()
+            .times(1)
+            .withf(|fix| fix.prompt_answer == Some(true) && fix.exit_code == Some(0))
+            .return_const(());
+
+        let mut run = setup_test(
+            vec![prompted_fix_action()],
+            exec_runner,
+            MockGlobWalker::new(),
+        );
+        run.interaction = answer_prompt(PromptAnswer::Approved);
+        run.audit_log = Arc::new(audit_log);
+
+        let result = run.run_action().await?;
+        assert_eq!(
+            ActionRunStatus::CheckFailedFixSucceedVerifySucceed,
+            result.status
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_commands_and_prompts_are_reported() -> Result<()> {
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1, 0]);
+        command_result(&mut exec_runner, "fix", vec![0]);
+
+        let progress = Arc::new(RecordingProgressReporter::default());
+        let mut run = setup_test(
+            vec![prompted_fix_action()],
+            exec_runner,
+            MockGlobWalker::new(),
+        );
+        run.interaction = answer_prompt(PromptAnswer::Approved);
+        run.progress = progress.clone();
+
+        run.run_action().await?;
+        assert_eq!(
+            vec![
+                "command check",
+                "prompt Run the fix?",
+                "command fix",
+                "command check"
+            ],
+            progress.events()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompted_fix_denied_is_recorded() -> Result<()> {
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1]);
+
+        let mut audit_log = MockAuditLog::new();
+        audit_log
+            .expect_record_fix()
+            .times(1)
+            .withf(|fix| fix.prompt_answer == Some(false) && fix.exit_code.is_none())
+            .return_const(());
+
+        let mut run = setup_test(
+            vec![prompted_fix_action()],
+            exec_runner,
+            MockGlobWalker::new(),
+        );
+        run.interaction = answer_prompt(PromptAnswer::Denied);
+        run.audit_log = Arc::new(audit_log);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckFailedFixUserDenied, result.status);
+        assert!(result.action_report.fix.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompt_lists_files_the_fix_may_change() -> Result<()> {
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1]);
+
+        let mut action = prompted_fix_action();
+        action.fix.snapshot = Some(DoctorGroupActionSnapshot {
+            files: vec!["Gemfile".to_string(), "Gemfile.lock".to_string()],
+            ..Default::default()
+        });
+        let mut interaction = MockUserInteraction::new();
+        interaction
+            .expect_confirm()
+            .times(1)
+            .withf(|_, help_text| *help_text == Some("May change: Gemfile, Gemfile.lock"))
+            .return_const(PromptAnswer::NotInteractive);
+
+        let mut run = setup_test(vec![action], exec_runner, MockGlobWalker::new());
+        run.interaction = Arc::new(interaction);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckFailedFixNotInteractive, result.status);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prompted_fix_without_terminal_is_not_a_denial() -> Result<()> {
+        let mut exec_runner = MockExecutionProvider::new();
+        command_result(&mut exec_runner, "check", vec![1]);
+
+        let mut run = setup_test(
+            vec![prompted_fix_action()],
+            exec_runner,
+            MockGlobWalker::new(),
+        );
+        run.interaction = answer_prompt(PromptAnswer::NotInteractive);
+
+        let result = run.run_action().await?;
+        assert_eq!(ActionRunStatus::CheckFailedFixNotInteractive, result.status);
+        assert!(result.status.is_failure());
+        assert!(result.action_report.fix.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,22 +1,36 @@
 use super::commands::*;
 use crate::shared::prelude::FoundConfig;
 use anyhow::Result;
+#[cfg(feature = "cli")]
 use clap::{Args, Subcommand};
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 pub struct DoctorArgs {
-    #[clap(subcommand)]
+    #[cfg_attr(feature = "cli", clap(subcommand))]
     command: DoctorCommands,
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Subcommand))]
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
 enum DoctorCommands {
     /// Run checks against your machine, generating support output.
     Run(DoctorRunArgs),
+    /// Run checks, then re-run the affected groups whenever their files or the config change
+    Watch(DoctorWatchArgs),
     /// List all doctor config, giving you the ability to know what is possible
     List(DoctorListArgs),
-    /// Create an example config file
-    #[command(hide(true))]
+    /// Explain what a group does: its dependencies, when it runs, and each action's commands
+    Explain(DoctorExplainArgs),
+    /// Show the fixes that have run on this machine, newest first
+    Audit(DoctorAuditArgs),
+    /// Manage the doctor cache
+    Cache(DoctorCacheArgs),
+    /// Run checks inside a container of an image, reporting if the image meets the requirements
+    VerifyImage(DoctorVerifyImageArgs),
+    /// Propose doctor groups for the project, based on the files in it, and write the ones you
+    /// accept to `.scope`
     Init(DoctorInitArgs),
 }
 
@@ -24,6 +38,11 @@ pub async fn doctor_root(found_config: &FoundConfig, args: &DoctorArgs) -> Resul
     match &args.command {
         DoctorCommands::List(args) => doctor_list(found_config, args).await.map(|_| 0),
         DoctorCommands::Run(args) => doctor_run(found_config, args).await,
+        DoctorCommands::Watch(args) => doctor_watch(found_config, args).await,
+        DoctorCommands::Explain(args) => doctor_explain(found_config, args).await.map(|_| 0),
+        DoctorCommands::Audit(args) => doctor_audit(found_config, args).await.map(|_| 0),
+        DoctorCommands::Cache(args) => doctor_cache(found_config, args).await.map(|_| 0),
+        DoctorCommands::VerifyImage(args) => doctor_verify_image(found_config, args).await,
         DoctorCommands::Init(args) => doctor_init(found_config, args).await.map(|_| 0),
     }
 }
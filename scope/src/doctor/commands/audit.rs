@@ -0,0 +1,118 @@
+use anyhow::Result;
+#[cfg(feature = "cli")]
+use clap::Args;
+use colored::Colorize;
+use tracing::instrument;
+
+use super::resolve_cache_dir;
+use crate::doctor::audit_log::{audit_log_path, read_audit_log, AuditEntry};
+use crate::report_stdout;
+use crate::shared::prelude::FoundConfig;
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorAuditArgs {
+    /// Only show fixes from this group
+    #[cfg_attr(feature = "cli", arg(short, long))]
+    pub group: Option<String>,
+    /// Only show fixes that exited with a non-zero exit code
+    #[cfg_attr(feature = "cli", arg(long, default_value = "false"))]
+    pub failed: bool,
+    /// Maximum number of entries to show, newest first
+    #[cfg_attr(feature = "cli", arg(short = 'n', long, default_value = "25"))]
+    pub limit: usize,
+    /// Location the cache and audit log are stored in
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_CACHE_DIR"))]
+    pub cache_dir: Option<String>,
+}
+
+#[instrument("scope doctor audit", skip_all)]
+pub async fn doctor_audit(found_config: &FoundConfig, args: &DoctorAuditArgs) -> Result<()> {
+    let path = audit_log_path(&resolve_cache_dir(found_config, &args.cache_dir));
+    let entries = filter_entries(read_audit_log(&path)?, args);
+
+    if entries.is_empty() {
+        report_stdout!("No fixes found in {}", path.display());
+        return Ok(());
+    }
+
+    for entry in entries {
+        let exit_code = match entry.fix.exit_code {
+            Some(0) => "0".green(),
+            Some(code) => code.to_string().red(),
+            None => "none".red(),
+        };
+        let prompt = match entry.fix.prompt_answer {
+            Some(true) => " (approved)",
+            Some(false) => " (denied)",
+            None => "",
+        };
+        report_stdout!(
+            "{} {}@{} {}/{} exit={}{} {}",
+            entry.timestamp.to_rfc3339(),
+            entry.user,
+            entry.host,
+            entry.fix.group.bold(),
+            entry.fix.action,
+            exit_code,
+            prompt,
+            entry.fix.command
+        );
+    }
+
+    Ok(())
+}
+
+fn filter_entries(entries: Vec<AuditEntry>, args: &DoctorAuditArgs) -> Vec<AuditEntry> {
+    entries
+        .into_iter()
+        .rev()
+        .filter(|e| args.group.is_none() || args.group.as_ref() == Some(&e.fix.group))
+        .filter(|e| !args.failed || e.fix.exit_code != Some(0))
+        .take(args.limit)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_entries, DoctorAuditArgs};
+    use crate::doctor::audit_log::{AuditEntry, FixExecution};
+    use chrono::Utc;
+
+    fn entry(group: &str, exit_code: i32) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            user: "user".to_string(),
+            host: "host".to_string(),
+            run_id: "run".to_string(),
+            fix: FixExecution {
+                group: group.to_string(),
+                action: "action".to_string(),
+                command: format!("fix {}", exit_code),
+                exit_code: Some(exit_code),
+                prompt_answer: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_filter_entries_newest_first() {
+        let entries = vec![entry("a", 0), entry("b", 1), entry("a", 2), entry("a", 0)];
+
+        let args = DoctorAuditArgs {
+            group: Some("a".to_string()),
+            failed: true,
+            limit: 25,
+            ..Default::default()
+        };
+        let filtered = filter_entries(entries.clone(), &args);
+        assert_eq!(vec![entries[2].clone()], filtered);
+
+        let args = DoctorAuditArgs {
+            limit: 2,
+            ..Default::default()
+        };
+        let filtered = filter_entries(entries.clone(), &args);
+        assert_eq!(vec![entries[3].clone(), entries[2].clone()], filtered);
+    }
+}
@@ -0,0 +1,120 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+#[cfg(feature = "cli")]
+use clap::{Args, Subcommand};
+use tracing::{instrument, warn};
+
+use super::{resolve_cache_dir, CACHE_FILE_NAME, RUN_HISTORY_FILE_NAME};
+use crate::doctor::audit_log::audit_log_path;
+use crate::doctor::file_cache::{FileBasedCache, FileCache};
+use crate::models::HelpMetadata;
+use crate::report_stdout;
+use crate::shared::prelude::FoundConfig;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorCacheArgs {
+    #[cfg_attr(feature = "cli", clap(subcommand))]
+    command: DoctorCacheCommands,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Subcommand))]
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+enum DoctorCacheCommands {
+    /// Move the cache, run history and audit log to the configured cache dir, and each group's
+    /// cached checks to the group's `cacheDir`.
+    Relocate(DoctorCacheRelocateArgs),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorCacheRelocateArgs {
+    /// Cache dir to move from
+    #[cfg_attr(feature = "cli", arg(long, default_value = super::DEFAULT_CACHE_DIR))]
+    pub from: String,
+    /// Cache dir to move to, defaults to the one from the `ScopeConfig`
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_CACHE_DIR"))]
+    pub cache_dir: Option<String>,
+}
+
+pub async fn doctor_cache(found_config: &FoundConfig, args: &DoctorCacheArgs) -> Result<()> {
+    match &args.command {
+        DoctorCacheCommands::Relocate(args) => doctor_cache_relocate(found_config, args).await,
+    }
+}
+
+#[instrument("scope doctor cache relocate", skip_all)]
+async fn doctor_cache_relocate(
+    found_config: &FoundConfig,
+    args: &DoctorCacheRelocateArgs,
+) -> Result<()> {
+    let from = PathBuf::from(&args.from);
+    let to = resolve_cache_dir(found_config, &args.cache_dir);
+
+    if from != to {
+        for (from_file, to_file) in [
+            (from.join(CACHE_FILE_NAME), to.join(CACHE_FILE_NAME)),
+            (
+                from.join(RUN_HISTORY_FILE_NAME),
+                to.join(RUN_HISTORY_FILE_NAME),
+            ),
+            (audit_log_path(&from), audit_log_path(&to)),
+        ] {
+            move_file(&from_file, &to_file).await?;
+        }
+    }
+
+    let shared_cache = FileBasedCache::new(&to.join(CACHE_FILE_NAME))?;
+    let mut shared_changed = false;
+    for group in found_config.doctor_group.values() {
+        let Some(group_dir) = &group.cache_dir else {
+            continue;
+        };
+        if *group_dir == to {
+            continue;
+        }
+
+        let group_cache_path = group_dir.join(CACHE_FILE_NAME);
+        let group_cache = FileBasedCache::new(&group_cache_path)?;
+        if shared_cache.move_entries(group.name(), &group_cache).await {
+            group_cache.persist().await?;
+            shared_changed = true;
+            report_stdout!(
+                "Moved cached checks for {} to {}",
+                group.name(),
+                group_cache_path.display()
+            );
+        }
+    }
+
+    if shared_changed {
+        shared_cache.persist().await?;
+    }
+
+    Ok(())
+}
+
+/// Move `from` to `to`, unless `to` already exists. Falls back to copying when the two are on
+/// different filesystems.
+async fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+    if to.exists() {
+        warn!(target: "user", "Not moving {}, {} already exists", from.display(), to.display());
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    if tokio::fs::rename(from, to).await.is_err() {
+        tokio::fs::copy(from, to).await?;
+        tokio::fs::remove_file(from).await?;
+    }
+    report_stdout!("Moved {} to {}", from.display(), to.display());
+
+    Ok(())
+}
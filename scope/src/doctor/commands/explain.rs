@@ -0,0 +1,304 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+#[cfg(feature = "cli")]
+use clap::Args;
+use colored::Colorize;
+use itertools::Itertools;
+use tracing::instrument;
+
+use super::{resolve_cache_dir, CACHE_FILE_NAME, RUN_HISTORY_FILE_NAME};
+use crate::doctor::run_history::{FileBasedRunHistory, RunHistory};
+use crate::doctor::runner::compute_group_order;
+use crate::models::HelpMetadata;
+use crate::report_stdout;
+use crate::shared::prelude::{
+    DoctorCondition, DoctorGroup, DoctorGroupAction, FoundConfig, ScheduleStatus,
+};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorExplainArgs {
+    /// Name of the group to explain
+    pub group: String,
+    /// Location the cache and run history are stored in
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_CACHE_DIR"))]
+    pub cache_dir: Option<String>,
+}
+
+/// What a group does, worked out from the config and the run history without running any
+/// commands. Commands are shown the way they'd run, with their templates rendered.
+#[instrument("scope doctor explain", skip_all)]
+pub async fn doctor_explain(found_config: &FoundConfig, args: &DoctorExplainArgs) -> Result<()> {
+    let Some(group) = found_config.doctor_group.get(&args.group) else {
+        return Err(anyhow!(
+            "No doctor group named {}, `scope doctor list` shows the groups there are",
+            args.group
+        ));
+    };
+
+    let cache_dir = resolve_cache_dir(found_config, &args.cache_dir);
+    let schedule_status = match &group.schedule {
+        Some(schedule) => {
+            let history = FileBasedRunHistory::new(&cache_dir.join(RUN_HISTORY_FILE_NAME));
+            let last_success = history.last_success(&args.group).await;
+            Some(schedule.status(Local::now(), last_success))
+        }
+        None => None,
+    };
+    let cache_file = group
+        .cache_dir
+        .as_deref()
+        .unwrap_or(&cache_dir)
+        .join(CACHE_FILE_NAME);
+
+    for line in explain_group(
+        &found_config.doctor_group,
+        &args.group,
+        &found_config.working_dir,
+        &cache_file,
+        schedule_status,
+    ) {
+        report_stdout!("{}", line);
+    }
+    Ok(())
+}
+
+/// Lines explaining the group `name`, which has to be in `groups`. `schedule_status` is where
+/// the group's `schedule` is at, when it has one.
+fn explain_group(
+    groups: &BTreeMap<String, DoctorGroup>,
+    name: &str,
+    working_dir: &Path,
+    cache_file: &Path,
+    schedule_status: Option<ScheduleStatus>,
+) -> Vec<String> {
+    let group = &groups[name];
+    let path = group.metadata().file_path();
+    let path = pathdiff::diff_paths(&path, working_dir)
+        .map(|diff| diff.display().to_string())
+        .unwrap_or(path);
+
+    let mut lines = vec![format!("{} {}", "Group".bold(), name.bold())];
+    lines.push(format!("  {}", group.description()));
+    lines.push(format!("  Defined in {}", path));
+    if !group.run_by_default {
+        lines.push("  Only runs with --only, or for a group that requires it".to_string());
+    }
+
+    lines.push(String::new());
+    lines.push("Dependencies".bold().to_string());
+    let runs_after: Vec<_> = compute_group_order(groups, BTreeSet::from([name.to_string()]))
+        .into_iter()
+        .filter(|other| other != name)
+        .collect();
+    lines.push(format!("  Requires: {}", listed(&group.requires)));
+    lines.push(format!("  Runs after: {}", listed(&runs_after)));
+    let required_by: Vec<_> = groups
+        .iter()
+        .filter(|(other, _)| other.as_str() != name)
+        .filter(|(other, _)| {
+            compute_group_order(groups, BTreeSet::from([other.to_string()]))
+                .iter()
+                .any(|dep| dep == name)
+        })
+        .map(|(other, _)| other.clone())
+        .collect();
+    lines.push(format!("  Required by: {}", listed(&required_by)));
+
+    lines.push(String::new());
+    lines.push("When it runs".bold().to_string());
+    explain_condition(&group.condition, &mut lines);
+    match schedule_status {
+        None => {}
+        Some(ScheduleStatus::Due) => lines.push("  Schedule: due".to_string()),
+        Some(ScheduleStatus::OutsideHours { start, end }) => lines.push(format!(
+            "  Schedule: {}, only runs between {:02}:00 and {:02}:00",
+            "skipped".yellow(),
+            start,
+            end
+        )),
+        Some(ScheduleStatus::IntervalNotElapsed { next_run }) => lines.push(format!(
+            "  Schedule: {} until {}",
+            "skipped".yellow(),
+            next_run.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        )),
+    }
+    if let Some(target) = &group.target {
+        lines.push(format!("  Runs on {}", target));
+    }
+    lines.push(format!("  Cache: {}", cache_file.display()));
+
+    for action in &group.actions {
+        lines.push(String::new());
+        explain_action(name, action, &mut lines);
+    }
+
+    lines
+}
+
+fn explain_action(group_name: &str, action: &DoctorGroupAction, lines: &mut Vec<String>) {
+    lines.push(format!(
+        "{} {}/{}",
+        "Action".bold(),
+        group_name.bold(),
+        action.name.bold()
+    ));
+    lines.push(format!("  {}", action.description));
+    if action.allow_failure {
+        lines.push("  Failures don't fail the group".to_string());
+    }
+    explain_condition(&action.condition, lines);
+    if let Some(working_dir) = &action.working_dir {
+        lines.push(format!("  Runs in {}", working_dir.display()));
+    }
+
+    if let Some(files) = &action.check.files {
+        lines.push(format!(
+            "  Check files: {}, in {}",
+            listed(&files.paths),
+            files.base_path.display()
+        ));
+    }
+    for check in &action.check.builtin {
+        lines.push(format!("  Check: {}", check));
+    }
+    match &action.check.command {
+        Some(command) => lines.push(format!("  Check: {}", quoted(&command.commands))),
+        None if action.check.files.is_none() && action.check.builtin.is_empty() => {
+            lines.push("  Check: none, the fix always runs".to_string())
+        }
+        None => {}
+    }
+
+    match &action.fix.command {
+        Some(command) => lines.push(format!("  Fix: {}", quoted(&command.commands))),
+        None => lines.push("  Fix: none".to_string()),
+    }
+    if let Some(prompt) = &action.fix.prompt {
+        lines.push(format!("  Asks first: {}", prompt.text));
+    }
+    if let Some(rollback) = &action.fix.rollback {
+        lines.push(format!("  Rollback: {}", quoted(&rollback.commands)));
+    }
+    if !action.fix.artifacts.is_empty() {
+        lines.push(format!("  Creates: {}", listed(&action.fix.artifacts)));
+    }
+    if let Some(help_text) = &action.fix.help_text {
+        lines.push(format!("  Help: {}", help_text.trim()));
+    }
+    if let Some(help_url) = &action.fix.help_url {
+        lines.push(format!("  Help: {}", help_url));
+    }
+}
+
+/// The `platforms` evaluated for this machine, and the `onlyIf` command, which isn't run.
+fn explain_condition(condition: &DoctorCondition, lines: &mut Vec<String>) {
+    if !condition.platforms.is_empty() {
+        let applies = if condition.includes_platform(std::env::consts::OS) {
+            "applies here".green()
+        } else {
+            "skipped here".yellow()
+        };
+        lines.push(format!(
+            "  Platforms: {}, {}",
+            condition.platforms.iter().join(", "),
+            applies
+        ));
+    }
+    if let Some(only_if) = &condition.only_if {
+        lines.push(format!("  Only if `{}` exits 0", only_if));
+    }
+}
+
+fn listed(names: &[String]) -> String {
+    if names.is_empty() {
+        return "nothing".to_string();
+    }
+    names.join(", ")
+}
+
+fn quoted(commands: &[String]) -> String {
+    commands.iter().map(|c| format!("`{}`", c)).join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::tests::{group_noop, make_root_model_additional};
+    use std::path::PathBuf;
+
+    fn groups() -> BTreeMap<String, DoctorGroup> {
+        let mut action = DoctorGroupAction::make_from(
+            "install",
+            "Install packages",
+            Some(vec!["npm install"]),
+            Some(("/foo/bar", vec!["package.json"])),
+            None,
+        );
+        action.condition.only_if = Some("test -f package.json".to_string());
+        action.fix.artifacts = vec!["node_modules".to_string()];
+
+        BTreeMap::from([
+            (
+                "brew".to_string(),
+                make_root_model_additional(vec![], |meta| meta.name("brew"), group_noop),
+            ),
+            (
+                "node".to_string(),
+                make_root_model_additional(
+                    vec![action],
+                    |meta| meta.name("node"),
+                    |group| group.requires(vec!["brew".to_string()]),
+                ),
+            ),
+            (
+                "web".to_string(),
+                make_root_model_additional(
+                    vec![],
+                    |meta| meta.name("web"),
+                    |group| group.requires(vec!["node".to_string()]),
+                ),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_explain_group() {
+        colored::control::set_override(false);
+        let lines = explain_group(
+            &groups(),
+            "node",
+            Path::new("/foo/bar"),
+            &PathBuf::from("/tmp/scope/cache-file.json"),
+            Some(ScheduleStatus::OutsideHours { start: 9, end: 17 }),
+        );
+
+        assert_eq!(
+            vec![
+                "Group node",
+                "  a description",
+                "  Defined in unknown",
+                "",
+                "Dependencies",
+                "  Requires: brew",
+                "  Runs after: brew",
+                "  Required by: web",
+                "",
+                "When it runs",
+                "  Schedule: skipped, only runs between 09:00 and 17:00",
+                "  Cache: /tmp/scope/cache-file.json",
+                "",
+                "Action node/install",
+                "  Install packages",
+                "  Only if `test -f package.json` exits 0",
+                "  Check files: package.json, in /foo/bar",
+                "  Fix: `npm install`",
+                "  Creates: node_modules",
+            ],
+            lines
+        );
+    }
+}
@@ -1,13 +1,417 @@
-use crate::shared::prelude::FoundConfig;
+use crate::models::prelude::V1AlphaDoctorGroup;
+use crate::models::InternalScopeModel;
+use crate::report_stdout;
+use crate::shared::prelude::{
+    AutoApprove, DefaultInteraction, FoundConfig, PromptAnswer, UserInteraction,
+};
 use anyhow::Result;
+#[cfg(feature = "cli")]
 use clap::Args;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tracing::{info, instrument, warn};
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 pub struct DoctorInitArgs {
-    /// Location to write the default init directory.
+    /// Directory the doctor groups are written to, relative to the working directory.
+    #[cfg_attr(feature = "cli", arg(default_value = ".scope"))]
     output: String,
+
+    /// Write every proposed group without asking.
+    #[cfg_attr(feature = "cli", arg(long, short))]
+    yes: bool,
+}
+
+/// A command proposed for a group, checking for something and, when it's missing, fixing it.
+struct ProposedAction {
+    name: &'static str,
+    description: &'static str,
+    check_paths: Vec<String>,
+    check_commands: Vec<String>,
+    fix_commands: Vec<String>,
+    help_text: Option<&'static str>,
+}
+
+/// A doctor group proposed because a file that marks a kind of project was found.
+struct ProposedGroup {
+    name: &'static str,
+    description: &'static str,
+    /// File that was found in the repository, shown when asking about the group.
+    marker: &'static str,
+    actions: Vec<ProposedAction>,
+}
+
+impl ProposedGroup {
+    fn to_resource(&self) -> Value {
+        let actions: Vec<_> = self
+            .actions
+            .iter()
+            .map(|action| {
+                let mut check = serde_json::Map::new();
+                if !action.check_paths.is_empty() {
+                    check.insert("paths".to_string(), json!(action.check_paths));
+                }
+                if !action.check_commands.is_empty() {
+                    check.insert("commands".to_string(), json!(action.check_commands));
+                }
+
+                let mut fix = serde_json::Map::new();
+                if !action.fix_commands.is_empty() {
+                    fix.insert("commands".to_string(), json!(action.fix_commands));
+                }
+                if let Some(help_text) = action.help_text {
+                    fix.insert("helpText".to_string(), json!(help_text));
+                }
+
+                json!({
+                    "name": action.name,
+                    "description": action.description,
+                    "check": check,
+                    "fix": fix,
+                })
+            })
+            .collect();
+
+        json!({
+            "apiVersion": V1AlphaDoctorGroup::int_api_version(),
+            "kind": V1AlphaDoctorGroup::int_kind(),
+            "metadata": {
+                "name": self.name,
+                "description": self.description,
+            },
+            "spec": {
+                "actions": actions,
+            },
+        })
+    }
+}
+
+fn tool_installed(
+    name: &'static str,
+    command: &str,
+    help_text: &'static str,
+    description: &'static str,
+) -> ProposedAction {
+    ProposedAction {
+        name,
+        description,
+        check_paths: Vec::new(),
+        check_commands: vec![format!("command -v {}", command)],
+        fix_commands: Vec::new(),
+        help_text: Some(help_text),
+    }
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Look for the files that mark common kinds of projects, proposing a group for each one found.
+fn detect_groups(working_dir: &Path) -> Vec<ProposedGroup> {
+    let exists = |file: &str| working_dir.join(file).exists();
+    let mut groups = Vec::new();
+
+    if exists("package.json") {
+        let (lock_file, install) = if exists("yarn.lock") {
+            ("yarn.lock", "yarn install")
+        } else if exists("pnpm-lock.yaml") {
+            ("pnpm-lock.yaml", "pnpm install")
+        } else {
+            ("package-lock.json", "npm install")
+        };
+        groups.push(ProposedGroup {
+            name: "node",
+            description: "Node.js and the project's packages",
+            marker: "package.json",
+            actions: vec![
+                tool_installed(
+                    "node-installed",
+                    "node",
+                    "Install Node.js, see https://nodejs.org",
+                    "Node.js is installed",
+                ),
+                ProposedAction {
+                    name: "install-packages",
+                    description: "Packages are installed",
+                    check_paths: strings(&["package.json", lock_file]),
+                    check_commands: Vec::new(),
+                    fix_commands: strings(&[install]),
+                    help_text: None,
+                },
+            ],
+        });
+    }
+
+    if exists("Gemfile") {
+        groups.push(ProposedGroup {
+            name: "ruby",
+            description: "Ruby and the project's gems",
+            marker: "Gemfile",
+            actions: vec![
+                tool_installed(
+                    "ruby-installed",
+                    "ruby",
+                    "Install Ruby, see https://www.ruby-lang.org",
+                    "Ruby is installed",
+                ),
+                ProposedAction {
+                    name: "install-gems",
+                    description: "Gems are installed",
+                    check_paths: Vec::new(),
+                    check_commands: strings(&["bundle check"]),
+                    fix_commands: strings(&["bundle install"]),
+                    help_text: None,
+                },
+            ],
+        });
+    }
+
+    if exists("Cargo.toml") {
+        groups.push(ProposedGroup {
+            name: "rust",
+            description: "Rust and the project's crates",
+            marker: "Cargo.toml",
+            actions: vec![
+                tool_installed(
+                    "cargo-installed",
+                    "cargo",
+                    "Install Rust, see https://rustup.rs",
+                    "Cargo is installed",
+                ),
+                ProposedAction {
+                    name: "fetch-crates",
+                    description: "Crates are downloaded",
+                    check_paths: strings(&["Cargo.toml", "Cargo.lock"]),
+                    check_commands: Vec::new(),
+                    fix_commands: strings(&["cargo fetch"]),
+                    help_text: None,
+                },
+            ],
+        });
+    }
+
+    let python_install = if exists("poetry.lock") {
+        Some((
+            "poetry.lock",
+            vec!["pyproject.toml", "poetry.lock"],
+            "poetry install",
+        ))
+    } else if exists("requirements.txt") {
+        Some((
+            "requirements.txt",
+            vec!["requirements.txt"],
+            "pip install -r requirements.txt",
+        ))
+    } else {
+        None
+    };
+    if let Some((marker, paths, install)) = python_install {
+        groups.push(ProposedGroup {
+            name: "python",
+            description: "Python and the project's packages",
+            marker,
+            actions: vec![
+                tool_installed(
+                    "python-installed",
+                    "python3",
+                    "Install Python, see https://www.python.org",
+                    "Python is installed",
+                ),
+                ProposedAction {
+                    name: "install-packages",
+                    description: "Packages are installed",
+                    check_paths: strings(&paths),
+                    check_commands: Vec::new(),
+                    fix_commands: strings(&[install]),
+                    help_text: None,
+                },
+            ],
+        });
+    }
+
+    if exists("go.mod") {
+        groups.push(ProposedGroup {
+            name: "go",
+            description: "Go and the project's modules",
+            marker: "go.mod",
+            actions: vec![
+                tool_installed(
+                    "go-installed",
+                    "go",
+                    "Install Go, see https://go.dev/doc/install",
+                    "Go is installed",
+                ),
+                ProposedAction {
+                    name: "download-modules",
+                    description: "Modules are downloaded",
+                    check_paths: strings(&["go.mod", "go.sum"]),
+                    check_commands: Vec::new(),
+                    fix_commands: strings(&["go mod download"]),
+                    help_text: None,
+                },
+            ],
+        });
+    }
+
+    groups
+}
+
+/// Render a proposed group as YAML, after checking it against the `ScopeDoctorGroup` schema.
+fn render_group(group: &ProposedGroup) -> Result<String> {
+    let resource = group.to_resource();
+    V1AlphaDoctorGroup::validate_resource(&resource)?;
+    Ok(serde_yaml::to_string(&resource)?)
 }
 
-pub async fn doctor_init(_found_config: &FoundConfig, _args: &DoctorInitArgs) -> Result<()> {
+#[instrument("scope doctor init", skip_all)]
+pub async fn doctor_init(found_config: &FoundConfig, args: &DoctorInitArgs) -> Result<()> {
+    let interaction: Box<dyn UserInteraction> = if args.yes {
+        Box::new(AutoApprove)
+    } else {
+        Box::new(DefaultInteraction::default())
+    };
+    let output = found_config.working_dir.join(&args.output);
+
+    let written = init_groups(&found_config.working_dir, &output, interaction.as_ref()).await?;
+    for path in &written {
+        report_stdout!("Wrote {}", path.display());
+    }
+    if !written.is_empty() {
+        report_stdout!("Run `scope doctor run` to try them out");
+    }
+
     Ok(())
 }
+
+/// Propose a group for each kind of project found in `working_dir`, writing the ones the user
+/// accepts to `output`. Existing files are never overwritten.
+async fn init_groups(
+    working_dir: &Path,
+    output: &Path,
+    interaction: &dyn UserInteraction,
+) -> Result<Vec<PathBuf>> {
+    let groups = detect_groups(working_dir);
+    if groups.is_empty() {
+        info!(target: "user", "Didn't find any files that scope knows how to set up, run `scope config example ScopeDoctorGroup` to start from an example");
+        return Ok(Vec::new());
+    }
+
+    let mut written = Vec::new();
+    for group in groups {
+        let path = output.join(format!("{}.yaml", group.name));
+        if path.exists() {
+            info!(target: "user", "Skipping the `{}` group, {} already exists", group.name, path.display());
+            continue;
+        }
+
+        let rendered = render_group(&group)?;
+        report_stdout!(
+            "Found {}, proposing the `{}` group:",
+            group.marker,
+            group.name
+        );
+        report_stdout!("{}", rendered);
+
+        let help_text = format!("Writes {}", path.display());
+        match interaction.confirm(
+            &format!("Add the `{}` group?", group.name),
+            Some(&help_text),
+        ) {
+            PromptAnswer::Approved => {
+                tokio::fs::create_dir_all(output).await?;
+                tokio::fs::write(&path, rendered).await?;
+                written.push(path);
+            }
+            PromptAnswer::Denied => {}
+            PromptAnswer::NotInteractive => {
+                warn!(target: "user", "Not writing any groups because the terminal isn't interactive, use `--yes` to write every proposed group");
+                break;
+            }
+        }
+    }
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::prelude::MockUserInteraction;
+    use assert_fs::prelude::*;
+
+    #[tokio::test]
+    async fn test_writes_accepted_groups() -> Result<()> {
+        let working_dir = assert_fs::TempDir::new()?;
+        working_dir.child("package.json").write_str("{}")?;
+        working_dir.child("yarn.lock").write_str("")?;
+        working_dir.child("Gemfile").write_str("")?;
+
+        let mut interaction = MockUserInteraction::new();
+        interaction
+            .expect_confirm()
+            .times(2)
+            .returning(|prompt, _| match prompt {
+                "Add the `node` group?" => PromptAnswer::Approved,
+                _ => PromptAnswer::Denied,
+            });
+
+        let output = working_dir.path().join(".scope");
+        let written = init_groups(working_dir.path(), &output, &interaction).await?;
+
+        assert_eq!(vec![output.join("node.yaml")], written);
+        let group: V1AlphaDoctorGroup =
+            serde_yaml::from_str(&std::fs::read_to_string(output.join("node.yaml"))?)?;
+        assert_eq!("node", group.metadata.name);
+        assert_eq!(
+            Some(vec!["yarn install".to_string()]),
+            group.spec.actions[1]
+                .fix
+                .as_ref()
+                .map(|fix| fix.commands.clone())
+        );
+        assert!(!output.join("ruby.yaml").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_existing_group_is_not_overwritten() -> Result<()> {
+        let working_dir = assert_fs::TempDir::new()?;
+        working_dir.child("Cargo.toml").write_str("")?;
+        working_dir.child(".scope/rust.yaml").write_str("custom")?;
+
+        let written = init_groups(
+            working_dir.path(),
+            &working_dir.path().join(".scope"),
+            &MockUserInteraction::new(),
+        )
+        .await?;
+
+        assert!(written.is_empty());
+        working_dir.child(".scope/rust.yaml").assert("custom");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_every_proposed_group_matches_the_schema() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        for file in [
+            "package.json",
+            "Gemfile",
+            "Cargo.toml",
+            "requirements.txt",
+            "go.mod",
+        ] {
+            working_dir.child(file).write_str("").unwrap();
+        }
+
+        let groups = detect_groups(working_dir.path());
+
+        assert_eq!(5, groups.len());
+        for group in groups {
+            render_group(&group).unwrap();
+        }
+    }
+}
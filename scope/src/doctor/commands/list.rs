@@ -1,22 +1,70 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use anyhow::Result;
-use clap::Args;
+#[cfg(feature = "cli")]
+use clap::{Args, ValueEnum};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
 use tracing::instrument;
 
-use crate::doctor::runner::compute_group_order;
+use super::{resolve_cache_dir, RUN_HISTORY_FILE_NAME};
+use crate::doctor::run_history::{FileBasedRunHistory, GroupRunStatus, RunHistory};
+use crate::doctor::runner::{compute_group_order, dependency_graph};
 use crate::report_stdout;
 use crate::shared::prelude::{DoctorGroup, FoundConfig};
 use crate::shared::print_details;
 
-#[derive(Debug, Args)]
-pub struct DoctorListArgs {}
+/// How `scope doctor list` prints the groups.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub enum DoctorListFormat {
+    /// A table of the groups that run by default, in the order they run
+    #[default]
+    Text,
+    /// A Graphviz graph of every group and the groups it needs
+    Dot,
+    /// A Mermaid flowchart of every group and the groups it needs
+    Mermaid,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorListArgs {
+    /// Never shorten descriptions or paths to fit the terminal
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub wide: bool,
+    /// How to print the groups. The graphs color each group by how it ended in the last run.
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = DoctorListFormat::Text))]
+    pub format: DoctorListFormat,
+    /// Location the cache and run history are stored in
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_CACHE_DIR"))]
+    pub cache_dir: Option<String>,
+}
 
 #[instrument("scope doctor list", skip_all)]
-pub async fn doctor_list(found_config: &FoundConfig, _args: &DoctorListArgs) -> Result<()> {
-    report_stdout!("Available checks that will run");
-    let order = generate_doctor_list(found_config).clone();
-    print_details(&found_config.working_dir, &order).await;
+pub async fn doctor_list(found_config: &FoundConfig, args: &DoctorListArgs) -> Result<()> {
+    if args.format == DoctorListFormat::Text {
+        report_stdout!("Available checks that will run");
+        let order = generate_doctor_list(found_config).clone();
+        print_details(&found_config.working_dir, &order, args.wide).await;
+        return Ok(());
+    }
+
+    let history_path = resolve_cache_dir(found_config, &args.cache_dir).join(RUN_HISTORY_FILE_NAME);
+    let history = FileBasedRunHistory::new(&history_path);
+    let mut statuses = BTreeMap::new();
+    for name in found_config.doctor_group.keys() {
+        if let Some(status) = history.last_status(name).await {
+            statuses.insert(name.to_string(), status);
+        }
+    }
+
+    let graph = match args.format {
+        DoctorListFormat::Mermaid => render_mermaid(&found_config.doctor_group, &statuses),
+        _ => render_dot(&found_config.doctor_group, &statuses),
+    };
+    report_stdout!("{}", graph);
     Ok(())
 }
 
@@ -35,3 +83,143 @@ pub fn generate_doctor_list(found_config: &FoundConfig) -> Vec<DoctorGroup> {
         .map(|name| found_config.doctor_group.get(name).unwrap().clone())
         .collect()
 }
+
+fn status_color(status: GroupRunStatus) -> &'static str {
+    match status {
+        GroupRunStatus::Succeeded => "#c8e6c9",
+        GroupRunStatus::Failed => "#ffcdd2",
+        GroupRunStatus::Skipped => "#ffe0b2",
+    }
+}
+
+/// Graphviz graph of every group, with an arrow from each group to the groups that need it.
+/// Groups that ran before are labelled and colored with their status from the last run.
+fn render_dot(
+    groups: &BTreeMap<String, DoctorGroup>,
+    statuses: &BTreeMap<String, GroupRunStatus>,
+) -> String {
+    let (graph, _) = dependency_graph(groups);
+    let quote = |name: &str| format!("\"{}\"", name.replace('"', "\\\""));
+
+    let mut lines = vec![
+        "digraph doctor {".to_string(),
+        "  rankdir=LR;".to_string(),
+        "  node [shape=box, style=\"rounded,filled\", fillcolor=white];".to_string(),
+    ];
+    for name in graph.node_weights() {
+        match statuses.get(*name) {
+            Some(status) => lines.push(format!(
+                "  {} [label={}, fillcolor=\"{}\"];",
+                quote(name),
+                quote(&format!("{}\\n{}", name, status)),
+                status_color(*status)
+            )),
+            None => lines.push(format!("  {};", quote(name))),
+        }
+    }
+    for edge in graph.edge_references() {
+        lines.push(format!(
+            "  {} -> {};",
+            quote(graph[edge.source()]),
+            quote(graph[edge.target()])
+        ));
+    }
+    lines.push("}".to_string());
+
+    lines.join("\n")
+}
+
+/// Mermaid flowchart of the same graph as [`render_dot`].
+fn render_mermaid(
+    groups: &BTreeMap<String, DoctorGroup>,
+    statuses: &BTreeMap<String, GroupRunStatus>,
+) -> String {
+    let (graph, _) = dependency_graph(groups);
+    let id = |index: NodeIndex| format!("g{}", index.index());
+
+    let mut lines = vec!["flowchart LR".to_string()];
+    for index in graph.node_indices() {
+        let name = graph[index].replace('"', "#quot;");
+        match statuses.get(graph[index]) {
+            Some(status) => lines.push(format!(
+                "  {}[\"{}<br/>{}\"]:::{}",
+                id(index),
+                name,
+                status,
+                status
+            )),
+            None => lines.push(format!("  {}[\"{}\"]", id(index), name)),
+        }
+    }
+    for edge in graph.edge_references() {
+        lines.push(format!("  {} --> {}", id(edge.source()), id(edge.target())));
+    }
+    for status in [
+        GroupRunStatus::Succeeded,
+        GroupRunStatus::Failed,
+        GroupRunStatus::Skipped,
+    ] {
+        lines.push(format!(
+            "  classDef {} fill:{}",
+            status,
+            status_color(status)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::tests::{group_noop, make_root_model_additional};
+
+    fn groups() -> BTreeMap<String, DoctorGroup> {
+        BTreeMap::from([
+            (
+                "brew".to_string(),
+                make_root_model_additional(vec![], |meta| meta.name("brew"), group_noop),
+            ),
+            (
+                "node".to_string(),
+                make_root_model_additional(
+                    vec![],
+                    |meta| meta.name("node"),
+                    |group| group.requires(vec!["brew".to_string()]),
+                ),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_render_dot_with_statuses() {
+        let statuses = BTreeMap::from([("brew".to_string(), GroupRunStatus::Succeeded)]);
+
+        assert_eq!(
+            r##"digraph doctor {
+  rankdir=LR;
+  node [shape=box, style="rounded,filled", fillcolor=white];
+  "brew" [label="brew\nsucceeded", fillcolor="#c8e6c9"];
+  "node";
+  "brew" -> "node";
+}"##,
+            render_dot(&groups(), &statuses)
+        );
+    }
+
+    #[test]
+    fn test_render_mermaid_with_statuses() {
+        let statuses = BTreeMap::from([("node".to_string(), GroupRunStatus::Failed)]);
+
+        assert_eq!(
+            r#"flowchart LR
+  g0["brew"]
+  g1["node<br/>failed"]:::failed
+  g0 --> g1
+  classDef succeeded fill:#c8e6c9
+  classDef failed fill:#ffcdd2
+  classDef skipped fill:#ffe0b2"#,
+            render_mermaid(&groups(), &statuses)
+        );
+    }
+}
@@ -1,7 +1,41 @@
+mod audit;
+mod cache;
+mod explain;
 mod init;
 mod list;
 mod run;
+mod verify_image;
+mod watch;
 
+pub use audit::{doctor_audit, DoctorAuditArgs};
+pub use cache::{doctor_cache, DoctorCacheArgs};
+pub use explain::{doctor_explain, DoctorExplainArgs};
 pub use init::{doctor_init, DoctorInitArgs};
 pub use list::{doctor_list, generate_doctor_list, DoctorListArgs};
-pub use run::{doctor_run, DoctorRunArgs};
+pub use run::{
+    doctor_run, plan, run, run_metrics, DoctorRunArgs, DoctorRunOptions, DoctorRunOutcome,
+    DoctorRunOutputFormat,
+};
+pub use verify_image::{doctor_verify_image, DoctorVerifyImageArgs};
+pub use watch::{doctor_watch, DoctorWatchArgs};
+
+use crate::shared::prelude::FoundConfig;
+
+/// Cache dir used when neither `--cache-dir` nor a `ScopeConfig` sets one.
+pub(crate) const DEFAULT_CACHE_DIR: &str = "/tmp/scope";
+pub(crate) const CACHE_FILE_NAME: &str = "cache-file.json";
+pub(crate) const RUN_HISTORY_FILE_NAME: &str = "run-history.json";
+
+/// Directory used to store the cache, run history and audit log. `--cache-dir` takes precedence
+/// over the `ScopeConfig`.
+pub(crate) fn resolve_cache_dir(
+    found_config: &FoundConfig,
+    cache_dir: &Option<String>,
+) -> std::path::PathBuf {
+    match cache_dir {
+        Some(cache_dir) => std::path::PathBuf::from(cache_dir),
+        None => found_config
+            .cache_dir()
+            .unwrap_or_else(|| std::path::PathBuf::from(DEFAULT_CACHE_DIR)),
+    }
+}
@@ -1,49 +1,216 @@
 use std::collections::{BTreeMap, BTreeSet};
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Result;
-use clap::Parser;
+use chrono::Utc;
+#[cfg(feature = "cli")]
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, instrument, warn};
 
+use super::{resolve_cache_dir, CACHE_FILE_NAME, RUN_HISTORY_FILE_NAME};
+use crate::doctor::audit_log::FileBasedAuditLog;
 use crate::doctor::check::{DefaultDoctorActionRun, DefaultGlobWalker};
+use crate::doctor::error::DoctorRunError;
 use crate::doctor::file_cache::{FileBasedCache, FileCache, NoOpCache};
-use crate::doctor::runner::{compute_group_order, GroupActionContainer, RunGroups};
+use crate::doctor::group_log::GroupLog;
+use crate::doctor::metrics::DoctorRunMetrics;
+use crate::doctor::plan::DoctorRunPlan;
+use crate::doctor::run_history::{FileBasedRunHistory, NoOpRunHistory, RunHistory};
+use crate::doctor::run_lock::RunLock;
+use crate::doctor::runner::{
+    compute_group_order, DoctorRunOutput, ExecutionPolicy, GroupActionContainer, PathRunResult,
+    RunGroups,
+};
+use crate::models::prelude::join_path_list;
+use crate::models::HelpMetadata;
 use crate::prelude::{
     DefaultGroupedReportBuilder, ExecutionProvider, GroupedReportBuilder, ReportRenderer,
 };
 use crate::report_stdout;
-use crate::shared::prelude::{DefaultExecutionProvider, FoundConfig};
+use crate::shared::prelude::{
+    redirect_stdout_to_stderr, AutoApprove, DefaultExecutionProvider, DefaultInteraction,
+    DockerExecutionProvider, DoctorGroup, DoctorGroupAction, DoctorGroupActionFixPrompt,
+    FoundConfig, NoInteraction, NoOpProgressReporter, PolicyViolation, ProgressReporter,
+    PromptAnswer, PromptDecision, PromptPolicyInteraction, RunArtifacts, SshExecutionProvider,
+    UserInteraction, STDERR_WRITER,
+};
+
+/// How `scope doctor run` reports the result of the run.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[serde(rename_all = "camelCase")]
+pub enum DoctorRunOutputFormat {
+    /// Progress and a summary for people
+    #[default]
+    Text,
+    /// A JSON document on stdout, other output moves to stderr
+    Json,
+}
 
-#[derive(Debug, Parser, Default)]
+/// Options for `scope doctor run`. Besides the CLI, these can be deserialized from JSON or YAML,
+/// using the camelCase field names; missing fields take their defaults.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(Parser))]
+#[serde(rename_all = "camelCase", default)]
 pub struct DoctorRunArgs {
-    /// When set, only the checks listed will run
-    #[arg(short, long)]
+    /// When set, only the checks listed will run. Use `group/action` to run a single action of a
+    /// group.
+    #[cfg_attr(feature = "cli", arg(short, long))]
     pub only: Option<Vec<String>>,
+    /// Run the named group and only the groups it needs, skipping everything else.
+    #[cfg_attr(feature = "cli", arg(long, conflicts_with = "only"))]
+    pub until: Option<String>,
     /// When set, if a fix is specified it will also run.
-    #[arg(long, short, default_value = "true")]
-    fix: Option<bool>,
+    #[cfg_attr(feature = "cli", arg(long, short, default_value = "true"))]
+    pub fix: Option<bool>,
     /// Location to store cache between runs
-    #[arg(long, env = "SCOPE_DOCTOR_CACHE_DIR")]
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_CACHE_DIR"))]
     pub cache_dir: Option<String>,
-    /// When set cache will be disabled, forcing all file based checks to run.
-    #[arg(long, short, default_value = "false")]
+    /// When set cache will be disabled, forcing all file based checks to run. Run history is
+    /// also ignored, so groups with a `schedule` interval will run.
+    #[cfg_attr(feature = "cli", arg(long, short, default_value = "false"))]
     pub no_cache: bool,
+    /// Skip the groups that succeeded in the previous run, when it didn't complete. A group still
+    /// runs when the files its checks list changed since.
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, default_value = "false", conflicts_with = "no_cache")
+    )]
+    pub resume: bool,
+    /// What happens to the groups after one that fails: `fail-fast` skips all of them,
+    /// `skip-dependents` only the ones that need it, and `keep-going` runs every group.
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = ExecutionPolicy::FailFast))]
+    pub execution_policy: ExecutionPolicy,
     /// Do not ask, create report on failure
-    #[arg(long, default_value = "false", env = "SCOPE_DOCTOR_AUTO_PUBLISH")]
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, default_value = "false", env = "SCOPE_DOCTOR_AUTO_PUBLISH")
+    )]
     pub auto_publish_report: bool,
+    /// Run fixes that ask for confirmation without asking. Without it, those fixes are skipped
+    /// when there isn't a terminal to ask on.
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, default_value = "false", env = "SCOPE_DOCTOR_AUTO_APPROVE")
+    )]
+    pub auto_approve: bool,
+    /// Write the full output of each group to a file. The path can use `{{ run_id }}`,
+    /// `{{ artifact_dir }}` and `{{ group }}`, without a path the output is written to the
+    /// `groups` directory of the run's artifacts.
+    #[cfg_attr(feature = "cli", arg(long, num_args = 0..=1, require_equals = true, default_missing_value = crate::doctor::group_log::DEFAULT_GROUP_LOG_PATTERN))]
+    pub group_log: Option<String>,
+    /// Format of the result. The JSON format is described by
+    /// `schema/v1.com.github.scope.DoctorRunOutput.json`.
+    #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = DoctorRunOutputFormat::Text))]
+    pub output: DoctorRunOutputFormat,
+    /// Don't warn when another run appears to be using the same cache dir. The file cache of the
+    /// run that finishes last is kept.
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, default_value = "false", env = "SCOPE_DOCTOR_ALLOW_CONCURRENT")
+    )]
+    pub allow_concurrent: bool,
+    /// Print the checks that would run and the fixes they'd propose, without running any check
+    /// or fix commands. Caches and run history are read, but not changed.
+    #[cfg_attr(feature = "cli", arg(long, default_value = "false"))]
+    pub dry_run: bool,
+    /// Print the slowest actions and how long each took at the end of the run.
+    #[cfg_attr(feature = "cli", arg(long, default_value = "false"))]
+    pub show_timings: bool,
+    /// Write metrics of the run to this file in the Prometheus textfile format, for the node
+    /// exporter's textfile collector.
+    #[cfg_attr(feature = "cli", arg(long, env = "SCOPE_DOCTOR_METRICS_FILE"))]
+    pub metrics_file: Option<PathBuf>,
+    /// Run the fixes of actions with this label without asking, like `risk=safe`. Can be used
+    /// multiple times, and takes precedence over `ScopePromptPolicy` resources.
+    #[cfg_attr(feature = "cli", arg(long, value_name = "KEY=VALUE", value_parser = parse_label))]
+    pub approve_labeled: Vec<String>,
+    /// Ask before running the fixes of actions with this label, even with `--auto-approve`.
+    #[cfg_attr(feature = "cli", arg(long, value_name = "KEY=VALUE", value_parser = parse_label))]
+    pub ask_labeled: Vec<String>,
+    /// Skip the fixes of actions with this label without asking.
+    #[cfg_attr(feature = "cli", arg(long, value_name = "KEY=VALUE", value_parser = parse_label))]
+    pub deny_labeled: Vec<String>,
 }
 
-fn get_cache(args: &DoctorRunArgs) -> Arc<dyn FileCache> {
+/// Only used to parse the `--*-labeled` flags.
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+fn parse_label(value: &str) -> Result<String, String> {
+    match value.split_once('=') {
+        Some((key, _)) if !key.trim().is_empty() => Ok(value.to_string()),
+        _ => Err(format!("`{}` should look like `key=value`", value)),
+    }
+}
+
+/// Most actions listed by `--show-timings`.
+const SLOWEST_ACTIONS_SHOWN: usize = 10;
+
+/// How fix prompts are answered, `prompts` asks the user unless `--auto-approve` is set.
+fn get_interaction(
+    args: &DoctorRunArgs,
+    prompts: Arc<dyn UserInteraction>,
+) -> Arc<dyn UserInteraction> {
+    if args.auto_approve {
+        Arc::new(AutoApprove)
+    } else {
+        prompts
+    }
+}
+
+/// How the prompt of the fix of `action` in `group` is answered, and what decided it. The
+/// `--*-labeled` flags come first, a denial over asking over approval, then the
+/// `ScopePromptPolicy` resources.
+fn prompt_decision(
+    found_config: &FoundConfig,
+    args: &DoctorRunArgs,
+    group: &DoctorGroup,
+    action: &DoctorGroupAction,
+) -> Option<(PromptDecision, String)> {
+    let flags = [
+        (PromptDecision::Deny, "--deny-labeled", &args.deny_labeled),
+        (PromptDecision::Ask, "--ask-labeled", &args.ask_labeled),
+        (
+            PromptDecision::Approve,
+            "--approve-labeled",
+            &args.approve_labeled,
+        ),
+    ];
+    for (decision, flag, labels) in flags {
+        let matched = labels.iter().find(|label| {
+            label.split_once('=').is_some_and(|(key, value)| {
+                action.labels.get(key.trim()).map(String::as_str) == Some(value.trim())
+            })
+        });
+        if let Some(label) = matched {
+            return Some((decision, format!("{} {}", flag, label)));
+        }
+    }
+
+    found_config
+        .prompt_decision(group, action)
+        .map(|(decision, policy)| (decision, policy.full_name()))
+}
+
+fn get_run_history(found_config: &FoundConfig, args: &DoctorRunArgs) -> Arc<dyn RunHistory> {
+    if args.no_cache {
+        Arc::<NoOpRunHistory>::default()
+    } else {
+        let history_path =
+            resolve_cache_dir(found_config, &args.cache_dir).join(RUN_HISTORY_FILE_NAME);
+        Arc::new(FileBasedRunHistory::new(&history_path))
+    }
+}
+
+fn get_cache(cache_dir: &Path, args: &DoctorRunArgs) -> Arc<dyn FileCache> {
     if args.no_cache {
         Arc::<NoOpCache>::default()
     } else {
-        let cache_dir = args
-            .cache_dir
-            .clone()
-            .unwrap_or_else(|| "/tmp/scope".to_string());
-        let cache_path = PathBuf::from(cache_dir).join("cache-file.json");
-        match FileBasedCache::new(&cache_path) {
+        match FileBasedCache::new(&cache_dir.join(CACHE_FILE_NAME)) {
             Ok(cache) => Arc::new(cache),
             Err(e) => {
                 warn!("Unable to create cache {:?}", e);
@@ -53,40 +220,50 @@ fn get_cache(args: &DoctorRunArgs) -> Arc<dyn FileCache> {
     }
 }
 
-#[instrument("scope doctor run", skip(found_config))]
+#[instrument("scope doctor run", skip(found_config), fields(run_id = %found_config.run_id))]
 pub async fn doctor_run(found_config: &FoundConfig, args: &DoctorRunArgs) -> Result<i32> {
-    let transform = transform_inputs(found_config, args);
-
-    let all_paths = compute_group_order(&found_config.doctor_group, transform.desired_groups);
-    if all_paths.is_empty() {
-        warn!(target: "user", "Could not find any tasks to execute");
+    if args.output == DoctorRunOutputFormat::Json {
+        redirect_stdout_to_stderr();
     }
 
-    let run_groups = RunGroups {
-        group_actions: transform.groups,
-        all_paths,
-    };
-
-    let result = run_groups.execute().await?;
-    report_stdout!("Summary: {}", result);
-
-    if let Err(e) = transform.file_cache.persist().await {
-        info!("Unable to store cache {:?}", e);
-        warn!(target: "user", "Unable to update cache, re-runs may redo work");
+    if args.dry_run {
+        let plan = plan(found_config, args).await?;
+        match args.output {
+            DoctorRunOutputFormat::Json => {
+                report_stdout!("{}", serde_json::to_string_pretty(&plan)?);
+            }
+            DoctorRunOutputFormat::Text => {
+                report_stdout!("{}", plan);
+            }
+        }
+        return Ok(0);
     }
 
+    let (result, exec_runner) = run_and_persist(
+        found_config,
+        args,
+        None,
+        Arc::new(NoOpProgressReporter),
+        Arc::new(DefaultInteraction::default()),
+    )
+    .await?;
+
     if !result.did_succeed && !found_config.report_upload.is_empty() {
-        println!();
+        writeln!(STDERR_WRITER.write().await).ok();
         let create_report = if args.auto_publish_report {
             true
         } else {
-            inquire::Confirm::new("Do you want to upload a bug report?")
-                .with_default(true)
-                .with_help_message(
-                    "This will allow you to share the error with other engineers for support.",
-                )
-                .prompt()
-                .unwrap_or(false)
+            match DefaultInteraction::default().confirm(
+                "Do you want to upload a bug report?",
+                Some("This will allow you to share the error with other engineers for support."),
+            ) {
+                PromptAnswer::Approved => true,
+                PromptAnswer::Denied => false,
+                PromptAnswer::NotInteractive => {
+                    warn!(target: "user", "Not asking to upload a bug report because the terminal isn't interactive, use `--auto-publish-report` to upload it");
+                    false
+                }
+            }
         };
 
         if create_report {
@@ -101,11 +278,14 @@ pub async fn doctor_run(found_config: &FoundConfig, args: &DoctorRunArgs) -> Res
                 builder
                     .run_and_append_additional_data(
                         found_config,
-                        transform.exec_runner.clone(),
+                        exec_runner.clone(),
                         &location.additional_data,
                     )
                     .await
                     .ok();
+                if let Err(e) = builder.collect_attachments(found_config, location).await {
+                    warn!(target: "user", "Unable to collect the report's attachments: {}", e);
+                }
 
                 let report = builder.render(location);
 
@@ -121,45 +301,538 @@ pub async fn doctor_run(found_config: &FoundConfig, args: &DoctorRunArgs) -> Res
         }
     }
 
-    if result.did_succeed {
-        Ok(0)
+    let exit_code = if result.did_succeed { 0 } else { 1 };
+    let output = DoctorRunOutput::new(&found_config.run_id, &found_config.fingerprint, result);
+    let output = serde_json::to_string_pretty(&output)?;
+    let result_file = found_config.artifacts().doctor_result_file();
+    if let Err(e) = RunArtifacts::write(&result_file, output.as_bytes()) {
+        info!("Unable to write {} {:?}", result_file.display(), e);
+    }
+    if args.output == DoctorRunOutputFormat::Json {
+        report_stdout!("{}", output);
+    }
+
+    Ok(exit_code)
+}
+
+/// Run the groups selected by `args`, print the summary and save the caches and run history.
+/// `prompts` asks the user about fixes.
+pub(super) async fn run_and_persist(
+    found_config: &FoundConfig,
+    args: &DoctorRunArgs,
+    exec_override: Option<&ExecutionOverride>,
+    progress: Arc<dyn ProgressReporter>,
+    prompts: Arc<dyn UserInteraction>,
+) -> Result<(PathRunResult, Arc<dyn ExecutionProvider>)> {
+    if args.dry_run {
+        return Err(DoctorRunError::DryRun.into());
+    }
+    check_until(found_config, args)?;
+
+    let _run_lock = if args.no_cache {
+        None
     } else {
-        Ok(1)
+        acquire_run_lock(&resolve_cache_dir(found_config, &args.cache_dir), args)
+    };
+    let transform = transform_inputs(found_config, args, exec_override, progress, prompts);
+
+    let all_paths = compute_group_order(&found_config.doctor_group, transform.desired_groups);
+    if all_paths.is_empty() {
+        warn!(target: "user", "Could not find any tasks to execute");
+    }
+
+    let resumed_groups = resumed_groups(transform.run_history.as_ref(), args).await;
+    transform
+        .run_history
+        .start_run(&found_config.run_id, Utc::now())
+        .await;
+
+    let run_groups = RunGroups {
+        group_actions: transform.groups,
+        all_paths,
+        run_history: transform.run_history.clone(),
+        group_log: args.group_log.as_ref().map(|pattern| GroupLog {
+            pattern: pattern.clone(),
+            run_id: found_config.run_id.clone(),
+            artifact_dir: found_config.artifacts().dir().to_path_buf(),
+        }),
+        progress: transform.progress,
+        resumed_groups,
+        execution_policy: args.execution_policy,
+    };
+
+    let result = run_groups.execute().await?;
+    if result.did_succeed {
+        transform.run_history.complete_run().await;
+    }
+    report_stdout!("Summary: {}", result);
+    for group_report in &result.group_reports {
+        if let Some(log_path) = group_report.log_path() {
+            report_stdout!("  {} output: {}", group_report.group_name(), log_path);
+        }
+    }
+    if args.show_timings {
+        report_timings(&result).await;
+    }
+
+    for file_cache in &transform.file_caches {
+        if let Err(e) = file_cache.persist().await {
+            info!("Unable to store cache {:?}", e);
+            warn!(target: "user", "Unable to update cache, re-runs may redo work");
+        }
+    }
+
+    if let Err(e) = transform.run_history.persist().await {
+        info!("Unable to store run history {:?}", e);
+        warn!(target: "user", "Unable to update run history, scheduled groups may run again");
+    }
+
+    if let Some(metrics_file) = &args.metrics_file {
+        let metrics =
+            DoctorRunMetrics::new(&result, Utc::now(), transform.run_history.as_ref()).await;
+        if let Err(e) = metrics.write_textfile(metrics_file) {
+            warn!(target: "user", "Unable to write metrics to {}: {}", metrics_file.display(), e);
+        }
+    }
+
+    Ok((result, transform.exec_runner))
+}
+
+/// Groups to skip for `--resume`, the ones that succeeded in the previous run when it didn't
+/// complete.
+async fn resumed_groups(run_history: &dyn RunHistory, args: &DoctorRunArgs) -> BTreeSet<String> {
+    if !args.resume {
+        return BTreeSet::new();
+    }
+    let groups = run_history.resumable_groups().await;
+    if groups.is_empty() {
+        info!(target: "user", "There isn't an incomplete run to resume, running every group");
+    }
+    groups
+}
+
+/// Print the slowest actions of the run, for `--show-timings`.
+async fn report_timings(result: &PathRunResult) {
+    let seconds = |duration_ms: u64| format!("{:.1}s", duration_ms as f64 / 1000.0);
+    report_stdout!(
+        "Slowest checks, of {} in total:",
+        seconds(result.duration_ms)
+    );
+    for (name, duration_ms) in result
+        .slowest_actions()
+        .into_iter()
+        .take(SLOWEST_ACTIONS_SHOWN)
+    {
+        report_stdout!("  {:>7}  {}", seconds(duration_ms), name);
     }
 }
 
-struct RunTransform {
-    groups: BTreeMap<String, GroupActionContainer<DefaultDoctorActionRun>>,
-    desired_groups: BTreeSet<String>,
-    file_cache: Arc<dyn FileCache>,
-    exec_runner: Arc<dyn ExecutionProvider>,
+fn check_until(found_config: &FoundConfig, args: &DoctorRunArgs) -> Result<()> {
+    match &args.until {
+        Some(until) if !found_config.doctor_group.contains_key(until) => {
+            Err(DoctorRunError::UnknownGroup {
+                name: until.clone(),
+            }
+            .into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Lock `cache_dir` for this run. When another run holds it, the run continues without the
+/// lock, with a warning unless `allow_concurrent` is set.
+fn acquire_run_lock(cache_dir: &Path, args: &DoctorRunArgs) -> Option<RunLock> {
+    match RunLock::try_acquire(cache_dir) {
+        Ok(lock @ Some(_)) => lock,
+        Ok(None) if args.allow_concurrent => {
+            info!("Another scope run appears active using {}, continuing because of --allow-concurrent", cache_dir.display());
+            None
+        }
+        Ok(None) => {
+            warn!(target: "user", "Another scope run appears active using {}, the run that finishes last keeps its cached checks. Pass --allow-concurrent to hide this warning", cache_dir.display());
+            None
+        }
+        Err(e) => {
+            info!("Unable to lock {} {:?}", cache_dir.display(), e);
+            None
+        }
+    }
+}
+
+/// How a library runs doctor: the same [`DoctorRunArgs`] as `scope doctor run`, and how the run
+/// reports progress, asks fix prompts, runs commands and stops early.
+#[derive(Clone)]
+pub struct DoctorRunOptions {
+    pub args: DoctorRunArgs,
+    progress: Arc<dyn ProgressReporter>,
+    interaction: Arc<dyn UserInteraction>,
+    exec_runner: Option<Arc<dyn ExecutionProvider>>,
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl Default for DoctorRunOptions {
+    fn default() -> Self {
+        Self::new(DoctorRunArgs::default())
+    }
+}
+
+impl std::fmt::Debug for DoctorRunOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DoctorRunOptions")
+            .field("args", &self.args)
+            .field("timeout", &self.timeout)
+            .field("cancellation", &self.cancellation)
+            .finish_non_exhaustive()
+    }
 }
 
-fn transform_inputs(found_config: &FoundConfig, args: &DoctorRunArgs) -> RunTransform {
+impl DoctorRunOptions {
+    pub fn new(args: DoctorRunArgs) -> Self {
+        Self {
+            args,
+            progress: Arc::new(NoOpProgressReporter),
+            interaction: Arc::new(NoInteraction),
+            exec_runner: None,
+            timeout: None,
+            cancellation: None,
+        }
+    }
+
+    /// Work out what the run would do instead of running it, see [`plan`].
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.args.dry_run = dry_run;
+        self
+    }
+
+    /// Tell `progress` about each group, action and command as it runs, for tools that show the
+    /// run their own way.
+    pub fn progress(mut self, progress: Arc<dyn ProgressReporter>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Ask fix prompts with `interaction`, like on the terminal with
+    /// [`DefaultInteraction`](crate::prelude::DefaultInteraction) or a tool's own dialog. Without
+    /// it, prompts aren't asked and the fixes that have one don't run. `autoApprove` still
+    /// answers every prompt, except the ones a `ScopePolicy` requires confirmation for.
+    pub fn interaction(mut self, interaction: Arc<dyn UserInteraction>) -> Self {
+        self.interaction = interaction;
+        self
+    }
+
+    /// Run every command with `exec_runner`, like a
+    /// [`ScriptedExecutionProvider`](crate::prelude::ScriptedExecutionProvider) in tests.
+    /// Required tools aren't checked, as nothing runs on this machine.
+    pub fn execution_provider(mut self, exec_runner: Arc<dyn ExecutionProvider>) -> Self {
+        self.exec_runner = Some(exec_runner);
+        self
+    }
+
+    /// Stop with [`DoctorRunError::TimedOut`] when the run takes longer than `duration`.
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// Stop with [`DoctorRunError::Cancelled`] once `token` is cancelled.
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+}
+
+/// What [`run`] did.
+#[derive(Debug)]
+pub enum DoctorRunOutcome {
+    /// What the run would do, when `dry_run` is set.
+    Planned(DoctorRunPlan),
+    /// The result of the run.
+    Completed(PathRunResult),
+}
+
+/// Run the groups selected by `options`, like `scope doctor run` does, and return the result.
+/// Fix prompts aren't asked unless [`DoctorRunOptions::interaction`] was set. Caches and run
+/// history are saved, no bug report is offered. When `dry_run` is set, the run is only planned,
+/// see [`plan`].
+///
+/// When the run times out or is cancelled, commands still running are killed, and caches and
+/// run history aren't saved.
+pub async fn run(
+    found_config: &FoundConfig,
+    options: &DoctorRunOptions,
+) -> Result<DoctorRunOutcome> {
+    if options.args.dry_run {
+        return Ok(DoctorRunOutcome::Planned(
+            plan(found_config, &options.args).await?,
+        ));
+    }
+
+    let exec_override = options
+        .exec_runner
+        .clone()
+        .map(|exec_runner| ExecutionOverride {
+            exec_runner,
+            required_tools: BTreeSet::new(),
+        });
+    let run = async {
+        run_and_persist(
+            found_config,
+            &options.args,
+            exec_override.as_ref(),
+            options.progress.clone(),
+            options.interaction.clone(),
+        )
+        .await
+        .map(|(result, _)| result)
+    };
+    let run = async {
+        match options.timeout {
+            Some(duration) => match tokio::time::timeout(duration, run).await {
+                Ok(result) => result,
+                Err(_) => Err(DoctorRunError::TimedOut { duration }.into()),
+            },
+            None => run.await,
+        }
+    };
+    let result = match &options.cancellation {
+        Some(token) => tokio::select! {
+            biased;
+            _ = token.cancelled() => Err(DoctorRunError::Cancelled.into()),
+            result = run => result,
+        },
+        None => run.await,
+    };
+    result.map(DoctorRunOutcome::Completed)
+}
+
+/// Metrics of a run that returned `result` with the same `options`, for a daemon to expose
+/// them. When each group last succeeded is read from the run history in the cache dir.
+pub async fn run_metrics(
+    found_config: &FoundConfig,
+    options: &DoctorRunArgs,
+    result: &PathRunResult,
+) -> DoctorRunMetrics {
+    let run_history = get_run_history(found_config, options);
+    DoctorRunMetrics::new(result, Utc::now(), run_history.as_ref()).await
+}
+
+/// Work out what [`run`] would do with `options`, without running any check or fix commands.
+/// The caches and run history are read to tell which checks are up to date, but nothing is
+/// saved. `dryRun` doesn't need to be set.
+pub async fn plan(found_config: &FoundConfig, options: &DoctorRunArgs) -> Result<DoctorRunPlan> {
+    check_until(found_config, options)?;
+    let transform = transform_inputs(
+        found_config,
+        options,
+        None,
+        Arc::new(NoOpProgressReporter),
+        Arc::new(NoInteraction),
+    );
+
+    let run_groups = RunGroups {
+        all_paths: compute_group_order(&found_config.doctor_group, transform.desired_groups),
+        group_actions: transform.groups,
+        resumed_groups: resumed_groups(transform.run_history.as_ref(), options).await,
+        run_history: transform.run_history,
+        group_log: None,
+        progress: transform.progress,
+        execution_policy: options.execution_policy,
+    };
+    run_groups.plan().await
+}
+
+pub(super) struct RunTransform {
+    pub(super) groups: BTreeMap<String, GroupActionContainer<DefaultDoctorActionRun>>,
+    pub(super) desired_groups: BTreeSet<String>,
+    /// The shared cache first, followed by the caches of groups with their own `cacheDir`.
+    pub(super) file_caches: Vec<Arc<dyn FileCache>>,
+    pub(super) run_history: Arc<dyn RunHistory>,
+    pub(super) exec_runner: Arc<dyn ExecutionProvider>,
+    pub(super) progress: Arc<dyn ProgressReporter>,
+}
+
+/// Runs every group with the same provider, instead of the one picked from the group's `target`.
+pub(super) struct ExecutionOverride {
+    pub(super) exec_runner: Arc<dyn ExecutionProvider>,
+    /// Tools needed on this machine for `exec_runner` to work.
+    pub(super) required_tools: BTreeSet<String>,
+}
+
+/// Groups selected by `--only`, with the actions to run. `None` runs every action of the group,
+/// which wins when a group is listed both on its own and as `group/action`.
+fn only_selectors(only: &[String]) -> BTreeMap<&str, Option<BTreeSet<&str>>> {
+    let mut selectors: BTreeMap<&str, Option<BTreeSet<&str>>> = BTreeMap::new();
+    for name in only {
+        match name.split_once('/') {
+            Some((group, action)) => {
+                if let Some(actions) = selectors
+                    .entry(group)
+                    .or_insert_with(|| Some(BTreeSet::new()))
+                {
+                    actions.insert(action);
+                }
+            }
+            None => {
+                selectors.insert(name, None);
+            }
+        }
+    }
+    selectors
+}
+
+pub(super) fn transform_inputs(
+    found_config: &FoundConfig,
+    args: &DoctorRunArgs,
+    exec_override: Option<&ExecutionOverride>,
+    progress: Arc<dyn ProgressReporter>,
+    prompts: Arc<dyn UserInteraction>,
+) -> RunTransform {
     let mut groups = BTreeMap::new();
     let mut desired_groups = BTreeSet::new();
 
-    let file_cache: Arc<dyn FileCache> = get_cache(args);
-    let exec_runner = Arc::new(DefaultExecutionProvider::default());
+    let cache_dir = resolve_cache_dir(found_config, &args.cache_dir);
+    let file_cache: Arc<dyn FileCache> = get_cache(&cache_dir, args);
+    let mut group_file_caches: BTreeMap<PathBuf, Arc<dyn FileCache>> = BTreeMap::new();
+    let exec_runner: Arc<dyn ExecutionProvider> = match exec_override {
+        Some(exec_override) => exec_override.exec_runner.clone(),
+        None => Arc::new(DefaultExecutionProvider::default()),
+    };
     let glob_walker = Arc::new(DefaultGlobWalker::default());
+    let interaction = get_interaction(args, prompts.clone());
+    let audit_log = Arc::new(FileBasedAuditLog::new(&cache_dir, &found_config.run_id));
+    let selectors = match (&args.until, &args.only) {
+        (Some(until), _) => Some(BTreeMap::from([(until.as_str(), None)])),
+        (None, Some(only)) => Some(only_selectors(only)),
+        (None, None) => None,
+    };
 
     for group in found_config.doctor_group.values() {
-        let should_group_run = match &args.only {
-            None => group.run_by_default,
-            Some(names) => names.contains(&group.metadata.name().to_string()),
+        let (should_group_run, selected_actions) = match &selectors {
+            None => (group.run_by_default, None),
+            Some(selectors) => match selectors.get(group.metadata.name().as_str()) {
+                Some(actions) => (true, actions.as_ref()),
+                None => (false, None),
+            },
+        };
+
+        if let Some(actions) = selected_actions {
+            for name in actions {
+                if !group.actions.iter().any(|action| action.name == *name) {
+                    warn!(target: "user", "Group `{}` doesn't have an action named `{}`", group.metadata.name(), name);
+                }
+            }
+        }
+
+        let (should_group_run, selected_actions) = apply_mandatory_policies(
+            found_config,
+            group,
+            should_group_run,
+            selected_actions.map(|names| names.iter().map(|name| name.to_string()).collect()),
+        );
+
+        // Commands that don't run on this machine only need the local client (ssh, docker) to be
+        // installed, which `required_tools` already accounts for with container `runtime`s.
+        let (group_exec_runner, required_tools): (Arc<dyn ExecutionProvider>, _) =
+            match (exec_override, &group.target) {
+                (Some(exec_override), _) => (
+                    exec_override.exec_runner.clone(),
+                    exec_override.required_tools.clone(),
+                ),
+                (None, Some(target)) => (
+                    Arc::new(SshExecutionProvider::new(target.clone())),
+                    BTreeSet::from(["ssh".to_string()]),
+                ),
+                (None, None) => (exec_runner.clone(), group.required_tools()),
+            };
+
+        let group_file_cache = match &group.cache_dir {
+            Some(dir) if *dir != cache_dir => group_file_caches
+                .entry(dir.clone())
+                .or_insert_with(|| get_cache(dir, args))
+                .clone(),
+            _ => file_cache.clone(),
         };
 
         let mut action_runs = Vec::new();
 
-        for action in &group.actions {
+        for action in group
+            .actions
+            .iter()
+            .filter(|action| match &selected_actions {
+                Some(names) => names.contains(action.name.as_str()),
+                None => true,
+            })
+        {
+            let mut action = action.clone();
+            let mut action_interaction = interaction.clone();
+            if let Some(policy) =
+                found_config.confirmation_policy(&group.metadata.name(), &action.name)
+            {
+                if action.fix.command.is_some() {
+                    if args.auto_approve {
+                        info!(target: "user", "{}", PolicyViolation::AutoApproveNotAllowed {
+                            policy: policy.full_name(),
+                            action: format!("{}/{}", group.metadata.name(), action.name),
+                        });
+                    }
+                    if action.fix.prompt.is_none() {
+                        action.fix.prompt = Some(DoctorGroupActionFixPrompt {
+                            text: format!(
+                                "Run the fix for `{}/{}`?",
+                                group.metadata.name(),
+                                action.name
+                            ),
+                            extra_context: Some(format!(
+                                "Confirmation is required by {}",
+                                policy.full_name()
+                            )),
+                        });
+                    }
+                    action_interaction = prompts.clone();
+                }
+            } else if let Some((decision, source)) =
+                prompt_decision(found_config, args, group, &action)
+            {
+                if action.fix.command.is_some() {
+                    if decision != PromptDecision::Approve && action.fix.prompt.is_none() {
+                        action.fix.prompt = Some(DoctorGroupActionFixPrompt {
+                            text: format!(
+                                "Run the fix for `{}/{}`?",
+                                group.metadata.name(),
+                                action.name
+                            ),
+                            extra_context: Some(format!("Answered by {}", source)),
+                        });
+                    }
+                    action_interaction = Arc::new(PromptPolicyInteraction::new(
+                        decision,
+                        source,
+                        prompts.clone(),
+                    ));
+                }
+            }
+
+            let action_exec_runner: Arc<dyn ExecutionProvider> =
+                match (exec_override, &action.container_image) {
+                    (None, Some(image)) => Arc::new(DockerExecutionProvider::for_image(
+                        image,
+                        &found_config.working_dir,
+                    )),
+                    _ => group_exec_runner.clone(),
+                };
+
             let run = DefaultDoctorActionRun {
                 model: group.clone(),
-                action: action.clone(),
+                action,
                 working_dir: found_config.working_dir.clone(),
-                file_cache: file_cache.clone(),
+                file_cache: group_file_cache.clone(),
                 run_fix: args.fix.unwrap_or(true),
-                exec_runner: exec_runner.clone(),
+                exec_runner: action_exec_runner,
                 glob_walker: glob_walker.clone(),
+                audit_log: audit_log.clone(),
+                interaction: action_interaction,
+                progress: progress.clone(),
             };
 
             action_runs.push(run);
@@ -169,9 +842,17 @@ fn transform_inputs(found_config: &FoundConfig, args: &DoctorRunArgs) -> RunTran
             group_name: group.metadata.name().to_string(),
             actions: action_runs,
             additional_report_details: group.extra_report_args.clone(),
-            exec_provider: exec_runner.clone(),
+            exec_provider: group_exec_runner,
             exec_working_dir: found_config.working_dir.clone(),
             sys_path: found_config.bin_path.clone(),
+            schedule: group.schedule.clone(),
+            required_tools,
+            tools_path: join_path_list([
+                group.metadata.containing_dir(),
+                group.metadata.exec_path(),
+            ]),
+            requires: group.requires.clone(),
+            condition: group.condition.clone(),
         };
 
         groups.insert(group.metadata.name().to_string(), container);
@@ -181,23 +862,101 @@ fn transform_inputs(found_config: &FoundConfig, args: &DoctorRunArgs) -> RunTran
         }
     }
 
+    let mut file_caches = vec![file_cache];
+    file_caches.extend(group_file_caches.into_values());
+
     RunTransform {
         groups,
         desired_groups,
-        file_cache,
+        file_caches,
+        run_history: get_run_history(found_config, args),
         exec_runner,
+        progress,
     }
 }
 
+/// Make the groups and actions a `ScopePolicy` marks as mandatory run, whatever was selected.
+/// `selected_actions` of `None` runs every action in the group.
+fn apply_mandatory_policies(
+    found_config: &FoundConfig,
+    group: &DoctorGroup,
+    should_group_run: bool,
+    selected_actions: Option<BTreeSet<String>>,
+) -> (bool, Option<BTreeSet<String>>) {
+    let group_name = group.metadata.name();
+    if let Some(policy) = found_config.mandatory_policy(&group_name, None) {
+        if !should_group_run || selected_actions.is_some() {
+            info!(target: "user", "{}", PolicyViolation::MandatorySkipped {
+                policy: policy.full_name(),
+                selector: format!("`{}`", group_name),
+            });
+        }
+        return (true, None);
+    }
+
+    let mut should_group_run = should_group_run;
+    let mut selected_actions = selected_actions;
+    for action in &group.actions {
+        let Some(policy) = found_config.mandatory_policy(&group_name, Some(&action.name)) else {
+            continue;
+        };
+        let is_selected = should_group_run
+            && match &selected_actions {
+                Some(names) => names.contains(&action.name),
+                None => true,
+            };
+        if is_selected {
+            continue;
+        }
+
+        info!(target: "user", "{}", PolicyViolation::MandatorySkipped {
+            policy: policy.full_name(),
+            selector: format!("`{}/{}`", group_name, action.name),
+        });
+        // Only the mandatory actions of a group that wasn't selected run.
+        if !should_group_run {
+            should_group_run = true;
+            selected_actions = Some(BTreeSet::new());
+        }
+        if let Some(names) = selected_actions.as_mut() {
+            names.insert(action.name.clone());
+        }
+    }
+
+    (should_group_run, selected_actions)
+}
+
 #[cfg(test)]
 mod test {
-    use std::collections::BTreeSet;
+    use std::collections::{BTreeMap, BTreeSet};
     use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::time::Duration;
 
-    use crate::doctor::commands::run::transform_inputs;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::doctor::commands::run::{
+        acquire_run_lock, parse_label, plan, run, transform_inputs, DoctorRunOptions,
+        DoctorRunOutcome,
+    };
     use crate::doctor::commands::DoctorRunArgs;
+    use crate::doctor::error::DoctorRunError;
+    use crate::doctor::plan::ActionPlanStatus;
+    use crate::doctor::run_lock::RunLock;
+    use crate::doctor::runner::compute_group_order;
     use crate::doctor::tests::{group_noop, make_root_model_additional, meta_noop};
+    use crate::models::prelude::ModelMetadata;
     use crate::prelude::FoundConfig;
+    use crate::shared::prelude::{
+        DoctorGroupAction, DoctorGroupActionBuilder, DoctorGroupActionCheckBuilder,
+        DoctorGroupActionCommand, DoctorGroupActionFixBuilder, MockUserInteraction, NoInteraction,
+        NoOpProgressReporter, Policy, PromptAnswer, PromptDecision, PromptPolicy, PromptRule,
+        UserInteraction,
+    };
+
+    fn prompts() -> Arc<dyn UserInteraction> {
+        Arc::new(NoInteraction)
+    }
 
     #[test]
     fn test_will_include_by_default() {
@@ -212,7 +971,8 @@ mod test {
             ..Default::default()
         };
 
-        let transform = transform_inputs(&fc, &args);
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
         assert_eq!(
             BTreeSet::from(["included".to_string()]),
             transform.desired_groups
@@ -232,7 +992,422 @@ mod test {
             ..Default::default()
         };
 
-        let transform = transform_inputs(&fc, &args);
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
         assert!(transform.desired_groups.is_empty());
     }
+
+    #[test]
+    fn test_only_selects_single_action() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let actions = ["install-ruby", "install-gems"]
+            .into_iter()
+            .map(|name| {
+                DoctorGroupActionBuilder::default()
+                    .name(name)
+                    .description("")
+                    .required(true)
+                    .check(
+                        DoctorGroupActionCheckBuilder::default()
+                            .command(None)
+                            .files(None)
+                            .build()
+                            .unwrap(),
+                    )
+                    .fix(DoctorGroupActionFixBuilder::default().build().unwrap())
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for name in ["ruby", "node"] {
+            fc.doctor_group.insert(
+                name.to_string(),
+                make_root_model_additional(actions.clone(), |meta| meta.name(name), group_noop),
+            );
+        }
+        let args = DoctorRunArgs {
+            only: Some(vec!["ruby/install-gems".to_string(), "node".to_string()]),
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
+        assert_eq!(
+            BTreeSet::from(["node".to_string(), "ruby".to_string()]),
+            transform.desired_groups
+        );
+        let action_names = |group: &str| {
+            transform.groups[group]
+                .actions
+                .iter()
+                .map(|run| run.action.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["install-gems"], action_names("ruby"));
+        assert_eq!(vec!["install-ruby", "install-gems"], action_names("node"));
+    }
+
+    #[test]
+    fn test_until_selects_only_the_target_group() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        fc.doctor_group.insert(
+            "brew".to_string(),
+            make_root_model_additional(vec![], |meta| meta.name("brew"), group_noop),
+        );
+        fc.doctor_group.insert(
+            "node".to_string(),
+            make_root_model_additional(
+                vec![],
+                |meta| meta.name("node"),
+                |group| group.requires(vec!["brew".to_string()]),
+            ),
+        );
+        fc.doctor_group.insert(
+            "ruby".to_string(),
+            make_root_model_additional(
+                vec![],
+                |meta| meta.name("ruby"),
+                |group| group.requires(vec!["node".to_string()]),
+            ),
+        );
+        let args = DoctorRunArgs {
+            until: Some("node".to_string()),
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
+        assert_eq!(
+            BTreeSet::from(["node".to_string()]),
+            transform.desired_groups
+        );
+        assert_eq!(
+            vec!["brew", "node"],
+            compute_group_order(&fc.doctor_group, transform.desired_groups)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_until_unknown_group_fails() {
+        let fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let args = DoctorRunArgs {
+            until: Some("missing".to_string()),
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let options = DoctorRunOptions::new(args).timeout(Duration::from_secs(5));
+        let err = run(&fc, &options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DoctorRunError>(),
+            Some(DoctorRunError::UnknownGroup { name }) if name == "missing"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_plan_lists_checks_and_fixes_without_running_them() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let action = |name: &str, check: Option<Vec<&str>>| {
+            DoctorGroupActionBuilder::default()
+                .name(name)
+                .description("")
+                .required(true)
+                .check(
+                    DoctorGroupActionCheckBuilder::default()
+                        .command(check.map(DoctorGroupActionCommand::from))
+                        .files(None)
+                        .build()
+                        .unwrap(),
+                )
+                .fix(
+                    DoctorGroupActionFixBuilder::default()
+                        .command(Some(DoctorGroupActionCommand::from(vec!["exit 1"])))
+                        .build()
+                        .unwrap(),
+                )
+                .build()
+                .unwrap()
+        };
+        fc.doctor_group.insert(
+            "node".to_string(),
+            make_root_model_additional(
+                vec![
+                    action("version", Some(vec!["exit 1"])),
+                    action("setup", None),
+                ],
+                |meta| meta.name("node"),
+                group_noop,
+            ),
+        );
+        let args = DoctorRunArgs {
+            no_cache: true,
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let plan = plan(&fc, &args).await.unwrap();
+        let actions = &plan.groups[0].actions;
+        assert_eq!(ActionPlanStatus::CheckWillRun, actions[0].status);
+        assert_eq!(vec!["exit 1"], actions[0].check_commands);
+        assert_eq!(ActionPlanStatus::FixNeeded, actions[1].status);
+        assert_eq!(vec!["exit 1"], actions[1].fix_commands);
+
+        let options = DoctorRunOptions::new(DoctorRunArgs {
+            no_cache: true,
+            ..Default::default()
+        })
+        .dry_run(true);
+        let outcome = run(&fc, &options).await.unwrap();
+        assert!(matches!(outcome, DoctorRunOutcome::Planned(plan) if plan.groups.len() == 1));
+    }
+
+    #[test]
+    fn test_policy_overrides_selection_and_auto_approve() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let actions = ["install", "configure"]
+            .into_iter()
+            .map(|name| DoctorGroupAction::make_from(name, "", Some(vec!["fix"]), None, None))
+            .collect::<Vec<_>>();
+        for name in ["ruby", "node", "security"] {
+            fc.doctor_group.insert(
+                name.to_string(),
+                make_root_model_additional(actions.clone(), |meta| meta.name(name), group_noop),
+            );
+        }
+        fc.policy.insert(
+            "company".to_string(),
+            Policy {
+                full_name: "ScopePolicy/company".to_string(),
+                metadata: ModelMetadata::new("company"),
+                mandatory: vec!["security".to_string(), "ruby/configure".to_string()],
+                require_confirmation: vec!["node/install".to_string()],
+                report_destinations: None,
+            },
+        );
+        let args = DoctorRunArgs {
+            only: Some(vec!["node".to_string()]),
+            auto_approve: true,
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
+        assert_eq!(
+            BTreeSet::from([
+                "node".to_string(),
+                "ruby".to_string(),
+                "security".to_string()
+            ]),
+            transform.desired_groups
+        );
+        let action_names = |group: &str| {
+            transform.groups[group]
+                .actions
+                .iter()
+                .map(|run| run.action.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec!["configure"], action_names("ruby"));
+        assert_eq!(vec!["install", "configure"], action_names("security"));
+
+        let node = &transform.groups["node"].actions;
+        assert_eq!(
+            Some("Run the fix for `node/install`?".to_string()),
+            node[0].action.fix.prompt.as_ref().map(|p| p.text.clone())
+        );
+        assert_eq!(None, node[1].action.fix.prompt);
+    }
+
+    #[test]
+    fn test_prompt_policies_answer_fix_prompts() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let actions = [
+            ("format", Some(("risk", "safe"))),
+            ("login", Some(("touches", "credentials"))),
+            ("reset", None),
+        ]
+        .into_iter()
+        .map(|(name, label)| {
+            let mut action = DoctorGroupAction::make_from(name, "", Some(vec!["fix"]), None, None);
+            action.labels = label
+                .map(|(key, value)| BTreeMap::from([(key.to_string(), value.to_string())]))
+                .unwrap_or_default();
+            action
+        })
+        .collect::<Vec<_>>();
+        fc.doctor_group.insert(
+            "tools".to_string(),
+            make_root_model_additional(actions, |meta| meta.name("tools"), group_noop),
+        );
+        fc.prompt_policy.insert(
+            "fixes".to_string(),
+            PromptPolicy {
+                full_name: "ScopePromptPolicy/fixes".to_string(),
+                metadata: ModelMetadata::new("fixes"),
+                rules: vec![
+                    PromptRule {
+                        actions: Vec::new(),
+                        labels: BTreeMap::from([("risk".to_string(), "safe".to_string())]),
+                        annotations: BTreeMap::new(),
+                        decision: PromptDecision::Approve,
+                    },
+                    PromptRule {
+                        actions: vec!["tools/reset".to_string()],
+                        labels: BTreeMap::new(),
+                        annotations: BTreeMap::new(),
+                        decision: PromptDecision::Deny,
+                    },
+                ],
+            },
+        );
+        let args = DoctorRunArgs {
+            auto_approve: true,
+            ask_labeled: vec!["touches=credentials".to_string()],
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let mut ask = MockUserInteraction::new();
+        ask.expect_confirm()
+            .times(1)
+            .return_const(PromptAnswer::Approved);
+        let transform = transform_inputs(
+            &fc,
+            &args,
+            None,
+            Arc::new(NoOpProgressReporter),
+            Arc::new(ask),
+        );
+        let runs = &transform.groups["tools"].actions;
+        let extra_context = |idx: usize| {
+            runs[idx]
+                .action
+                .fix
+                .prompt
+                .as_ref()
+                .and_then(|p| p.extra_context.clone())
+        };
+        assert_eq!(None, runs[0].action.fix.prompt);
+        assert_eq!(
+            PromptAnswer::Approved,
+            runs[0].interaction.confirm("Run it?", None)
+        );
+        assert_eq!(
+            Some("Answered by --ask-labeled touches=credentials".to_string()),
+            extra_context(1)
+        );
+        assert_eq!(
+            PromptAnswer::Approved,
+            runs[1].interaction.confirm("Run it?", None)
+        );
+        assert_eq!(
+            Some("Answered by ScopePromptPolicy/fixes".to_string()),
+            extra_context(2)
+        );
+        assert_eq!(
+            PromptAnswer::Denied,
+            runs[2].interaction.confirm("Run it?", None)
+        );
+
+        assert!(parse_label("risk=safe").is_ok());
+        assert!(parse_label("risk").is_err());
+        assert!(parse_label("=safe").is_err());
+    }
+
+    #[test]
+    fn test_groups_with_cache_dir_get_their_own_cache() {
+        let mut fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        for (name, cache_dir) in [
+            ("shared", None),
+            ("own-1", Some("/tmp/scope-test/own")),
+            ("own-2", Some("/tmp/scope-test/own")),
+            ("same-as-shared", Some("/tmp/scope-test/shared")),
+        ] {
+            fc.doctor_group.insert(
+                name.to_string(),
+                make_root_model_additional(
+                    vec![],
+                    |meta| meta.name(name),
+                    |group| group.cache_dir(cache_dir.map(PathBuf::from)),
+                ),
+            );
+        }
+        let args = DoctorRunArgs {
+            cache_dir: Some("/tmp/scope-test/shared".to_string()),
+            ..Default::default()
+        };
+
+        let transform =
+            transform_inputs(&fc, &args, None, Arc::new(NoOpProgressReporter), prompts());
+        assert_eq!(2, transform.file_caches.len());
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_when_cancelled() {
+        let fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let args = DoctorRunArgs {
+            no_cache: true,
+            ..Default::default()
+        };
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let options = DoctorRunOptions::new(args).cancellation(token);
+        let err = run(&fc, &options).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DoctorRunError>(),
+            Some(DoctorRunError::Cancelled)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_run_with_timeout_returns_result() {
+        let fc = FoundConfig::empty(PathBuf::from("/tmp"));
+        let args = DoctorRunArgs {
+            no_cache: true,
+            ..Default::default()
+        };
+
+        let options = DoctorRunOptions::new(args).timeout(Duration::from_secs(60));
+        let outcome = run(&fc, &options).await.unwrap();
+        assert!(matches!(outcome, DoctorRunOutcome::Completed(result) if result.did_succeed));
+    }
+
+    #[test]
+    fn test_run_args_from_json() {
+        let args: DoctorRunArgs =
+            serde_json::from_str(r#"{"only": ["setup"], "fix": false, "autoApprove": true}"#)
+                .unwrap();
+        assert_eq!(
+            DoctorRunArgs {
+                only: Some(vec!["setup".to_string()]),
+                fix: Some(false),
+                auto_approve: true,
+                ..Default::default()
+            },
+            args
+        );
+
+        let args: DoctorRunArgs = serde_json::from_str("{}").unwrap();
+        assert_eq!(DoctorRunArgs::default(), args);
+    }
+
+    #[test]
+    fn test_concurrent_run_continues_without_the_lock() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let other_run = RunLock::try_acquire(dir.path()).unwrap().unwrap();
+
+        assert!(acquire_run_lock(dir.path(), &DoctorRunArgs::default()).is_none());
+        let args = DoctorRunArgs {
+            allow_concurrent: true,
+            ..Default::default()
+        };
+        assert!(acquire_run_lock(dir.path(), &args).is_none());
+
+        drop(other_run);
+        assert!(acquire_run_lock(dir.path(), &DoctorRunArgs::default()).is_some());
+    }
 }
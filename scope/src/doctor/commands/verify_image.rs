@@ -0,0 +1,157 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+#[cfg(feature = "cli")]
+use clap::Args;
+use colored::Colorize;
+use tracing::{info, instrument, warn};
+
+use super::run::{transform_inputs, ExecutionOverride};
+use super::DoctorRunArgs;
+use crate::doctor::runner::{compute_group_order, ExecutionPolicy, RunGroups};
+use crate::report_stdout;
+use crate::shared::prelude::{
+    CaptureOpts, DockerExecutionProvider, FoundConfig, NoInteraction, NoOpProgressReporter,
+    OutputCapture, OutputDestination,
+};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorVerifyImageArgs {
+    /// Image to verify, e.g. `ghcr.io/my-org/devcontainer:latest`
+    #[cfg_attr(feature = "cli", arg(long))]
+    pub image: String,
+    /// When set, only the checks listed will run
+    #[cfg_attr(feature = "cli", arg(short, long))]
+    pub only: Option<Vec<String>>,
+}
+
+/// A container that is removed when dropped.
+struct RunningContainer {
+    id: String,
+}
+
+impl RunningContainer {
+    /// Start a container from `image` that stays up until removed, with `working_dir` mounted at
+    /// the same path so relative scripts resolve the same way they do locally.
+    async fn start(image: &str, working_dir: &Path, path: &str) -> Result<Self> {
+        let working_dir_str = working_dir.display().to_string();
+        let args = vec![
+            "docker".to_string(),
+            "run".to_string(),
+            "--detach".to_string(),
+            "--rm".to_string(),
+            "--volume".to_string(),
+            format!("{}:{}", working_dir_str, working_dir_str),
+            "--workdir".to_string(),
+            working_dir_str,
+            "--entrypoint".to_string(),
+            "sleep".to_string(),
+            image.to_string(),
+            "infinity".to_string(),
+        ];
+        let capture = OutputCapture::capture_output(CaptureOpts {
+            working_dir,
+            env_vars: Default::default(),
+            path,
+            args: &args,
+            output_dest: OutputDestination::Null,
+            timeout: None,
+            progress: None,
+        })
+        .await?;
+
+        if capture.exit_code != Some(0) {
+            return Err(anyhow!(
+                "Unable to start a container from `{}`: {}",
+                image,
+                capture.get_stderr().trim()
+            ));
+        }
+
+        Ok(Self {
+            id: capture.get_stdout().trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RunningContainer {
+    fn drop(&mut self) {
+        if let Err(e) = std::process::Command::new("docker")
+            .args(["rm", "--force", &self.id])
+            .output()
+        {
+            info!("Unable to remove container {}: {:?}", self.id, e);
+            warn!(target: "user", "Unable to remove container {}, remove it with `docker rm --force {}`", self.id, self.id);
+        }
+    }
+}
+
+#[instrument("scope doctor verify-image", skip(found_config))]
+pub async fn doctor_verify_image(
+    found_config: &FoundConfig,
+    args: &DoctorVerifyImageArgs,
+) -> Result<i32> {
+    let container = RunningContainer::start(
+        &args.image,
+        &found_config.working_dir,
+        &found_config.bin_path,
+    )
+    .await?;
+    info!(target: "user", "Verifying `{}` in container {}", args.image, container.id);
+
+    // Fixes would only change a throw away container, and a fresh container has nothing cached.
+    let run_args = DoctorRunArgs {
+        only: args.only.clone(),
+        fix: Some(false),
+        no_cache: true,
+        ..Default::default()
+    };
+    let exec_override = ExecutionOverride {
+        exec_runner: Arc::new(DockerExecutionProvider::new(&container.id)),
+        required_tools: BTreeSet::from(["docker".to_string()]),
+    };
+    let transform = transform_inputs(
+        found_config,
+        &run_args,
+        Some(&exec_override),
+        Arc::new(NoOpProgressReporter),
+        Arc::new(NoInteraction),
+    );
+
+    let all_paths = compute_group_order(&found_config.doctor_group, transform.desired_groups);
+    if all_paths.is_empty() {
+        warn!(target: "user", "Could not find any tasks to execute");
+    }
+
+    let run_groups = RunGroups {
+        group_actions: transform.groups,
+        all_paths,
+        run_history: transform.run_history,
+        group_log: None,
+        progress: transform.progress,
+        resumed_groups: BTreeSet::new(),
+        execution_policy: ExecutionPolicy::default(),
+    };
+
+    let result = run_groups.execute().await?;
+    report_stdout!("Summary: {}", result);
+
+    if result.did_succeed {
+        report_stdout!(
+            "Image `{}` {}",
+            args.image,
+            "satisfies the environment requirements".green()
+        );
+        Ok(0)
+    } else {
+        report_stdout!(
+            "Image `{}` {}",
+            args.image,
+            "does not satisfy the environment requirements".red()
+        );
+        Ok(1)
+    }
+}
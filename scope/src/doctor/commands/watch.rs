@@ -0,0 +1,359 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+#[cfg(feature = "cli")]
+use clap::Args;
+use glob::Pattern;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::{debug, info, instrument, warn};
+
+use super::run::{plan, run_and_persist};
+use super::DoctorRunArgs;
+use crate::doctor::runner::compute_group_order;
+use crate::models::HelpMetadata;
+use crate::report_stdout;
+use crate::shared::prelude::{
+    configure_redactions, DefaultInteraction, FoundConfig, NoOpProgressReporter,
+};
+
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
+pub struct DoctorWatchArgs {
+    #[cfg_attr(feature = "cli", clap(flatten))]
+    pub run: DoctorRunArgs,
+    /// How long to wait for more changes before re-running, in milliseconds
+    #[cfg_attr(feature = "cli", arg(long, default_value = "500"))]
+    pub debounce: u64,
+}
+
+/// Run the groups, or with `--dry-run` print what would run.
+async fn run_or_plan(found_config: &FoundConfig, args: &DoctorRunArgs) -> Result<()> {
+    if args.dry_run {
+        let plan = plan(found_config, args).await?;
+        report_stdout!("{}", plan);
+    } else {
+        run_and_persist(
+            found_config,
+            args,
+            None,
+            Arc::new(NoOpProgressReporter),
+            Arc::new(DefaultInteraction::default()),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[instrument("scope doctor watch", skip_all, fields(run_id = %found_config.run_id))]
+pub async fn doctor_watch(found_config: &FoundConfig, args: &DoctorWatchArgs) -> Result<i32> {
+    let mut found_config = found_config.clone();
+    run_or_plan(&found_config, &args.run).await?;
+
+    let (tx, mut rx) = unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            tx.send(event).ok();
+        }
+    })?;
+    let mut plan = WatchPlan::new(&found_config);
+    plan.watch(&mut watcher);
+
+    info!(target: "user", "Watching for changes, press Ctrl-C to stop");
+    loop {
+        let changed = tokio::select! {
+            changed = next_changes(&mut rx, Duration::from_millis(args.debounce)) => changed,
+            _ = tokio::signal::ctrl_c() => return Ok(0),
+        };
+
+        let groups = if plan.is_config_change(&changed) {
+            info!(target: "user", "Config changed, reloading");
+            plan.unwatch(&mut watcher);
+            found_config = found_config.reload().await;
+            configure_redactions(found_config.redaction_patterns());
+            plan = WatchPlan::new(&found_config);
+            plan.watch(&mut watcher);
+            found_config.doctor_group.keys().cloned().collect()
+        } else {
+            plan.affected_groups(&changed)
+        };
+
+        let groups: Vec<_> = groups
+            .into_iter()
+            .filter(|name| is_selected(&found_config, &args.run, name))
+            .collect();
+        if groups.is_empty() {
+            debug!("No groups affected by {:?}", changed);
+            continue;
+        }
+
+        info!(target: "user", "Re-running {}", groups.join(", "));
+        let run_args = DoctorRunArgs {
+            only: Some(groups),
+            until: None,
+            ..args.run.clone()
+        };
+        if let Err(e) = run_or_plan(&found_config, &run_args).await {
+            warn!(target: "user", "Unable to run checks: {}", e);
+        }
+
+        // Fixes change files too, those changes shouldn't start another run.
+        while rx.try_recv().is_ok() {}
+        info!(target: "user", "Watching for changes, press Ctrl-C to stop");
+    }
+}
+
+/// Wait for a change, then collect everything that changes until `debounce` passes quietly.
+async fn next_changes(rx: &mut UnboundedReceiver<Event>, debounce: Duration) -> Vec<PathBuf> {
+    let mut changed = Vec::new();
+    loop {
+        let event = if changed.is_empty() {
+            rx.recv().await
+        } else {
+            match tokio::time::timeout(debounce, rx.recv()).await {
+                Ok(event) => event,
+                Err(_) => return changed,
+            }
+        };
+
+        match event {
+            None => return changed,
+            Some(event) if matches!(event.kind, EventKind::Access(_)) => {}
+            Some(event) => changed.extend(event.paths),
+        }
+    }
+}
+
+/// Groups selected the same way `scope doctor run` selects them.
+fn is_selected(found_config: &FoundConfig, args: &DoctorRunArgs, name: &str) -> bool {
+    if let Some(until) = &args.until {
+        return compute_group_order(&found_config.doctor_group, BTreeSet::from([until.clone()]))
+            .iter()
+            .any(|group| group == name);
+    }
+    match &args.only {
+        Some(only) => only.iter().any(|o| o == name),
+        None => found_config
+            .doctor_group
+            .get(name)
+            .map(|group| group.run_by_default)
+            .unwrap_or(false),
+    }
+}
+
+/// The files a watch depends on: the check `paths` of each group, and the config files.
+#[derive(Debug, Default, PartialEq)]
+struct WatchPlan {
+    /// Directories to watch, and if they're watched recursively.
+    dirs: BTreeMap<PathBuf, bool>,
+    group_patterns: BTreeMap<String, Vec<Pattern>>,
+    config_dirs: BTreeSet<PathBuf>,
+}
+
+impl WatchPlan {
+    fn new(found_config: &FoundConfig) -> Self {
+        let mut plan = WatchPlan::default();
+
+        for group in found_config.doctor_group.values() {
+            plan.add_config_file(Path::new(&group.metadata.file_path()));
+
+            for files in group.actions.iter().filter_map(|a| a.check.files.as_ref()) {
+                for path in &files.paths {
+                    let pattern = files.base_path.join(path).display().to_string();
+                    let Ok(compiled) = Pattern::new(&pattern) else {
+                        warn!(target: "user", "Unable to watch `{}`, it's not a valid glob", pattern);
+                        continue;
+                    };
+                    plan.group_patterns
+                        .entry(group.name().to_string())
+                        .or_default()
+                        .push(compiled);
+
+                    let (dir, recursive) = watch_root(&pattern);
+                    let entry = plan.dirs.entry(dir).or_insert(false);
+                    *entry |= recursive;
+                }
+            }
+        }
+
+        for config in &found_config.raw_config {
+            plan.add_config_file(Path::new(&config.metadata.file_path()));
+        }
+
+        plan
+    }
+
+    fn add_config_file(&mut self, file: &Path) {
+        if let Some(dir) = file.parent().filter(|dir| dir.is_absolute()) {
+            self.config_dirs.insert(dir.to_path_buf());
+            self.dirs.entry(dir.to_path_buf()).or_insert(false);
+        }
+    }
+
+    fn watch(&self, watcher: &mut RecommendedWatcher) {
+        for (dir, recursive) in &self.dirs {
+            let mode = if *recursive {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            if let Err(e) = watcher.watch(dir, mode) {
+                warn!(target: "user", "Unable to watch {}: {}", dir.display(), e);
+            }
+        }
+    }
+
+    fn unwatch(&self, watcher: &mut RecommendedWatcher) {
+        for dir in self.dirs.keys() {
+            watcher.unwatch(dir).ok();
+        }
+    }
+
+    fn is_config_change(&self, changed: &[PathBuf]) -> bool {
+        changed.iter().any(|path| {
+            let is_yaml = matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("yaml") | Some("yml")
+            );
+            is_yaml
+                && path
+                    .parent()
+                    .map(|dir| self.config_dirs.contains(dir))
+                    .unwrap_or(false)
+        })
+    }
+
+    fn affected_groups(&self, changed: &[PathBuf]) -> BTreeSet<String> {
+        self.group_patterns
+            .iter()
+            .filter(|(_, patterns)| {
+                patterns
+                    .iter()
+                    .any(|pattern| changed.iter().any(|path| pattern.matches_path(path)))
+            })
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+}
+
+/// The directory to watch for an absolute glob, the deepest one without wildcards. Directories
+/// that don't exist yet can't be watched, so their closest existing parent is watched instead,
+/// with the same recursive mode to avoid watching large trees by accident.
+fn watch_root(pattern: &str) -> (PathBuf, bool) {
+    let path = Path::new(pattern);
+    let mut root = PathBuf::new();
+    let mut components = path.components().peekable();
+    while let Some(component) = components.peek() {
+        let text = component.as_os_str().to_string_lossy();
+        if text.contains(['*', '?', '[', '{']) {
+            break;
+        }
+        root.push(component);
+        components.next();
+    }
+
+    // A plain file path, watch the file's directory.
+    let recursive = components.count() > 1;
+    if root == path {
+        root.pop();
+    }
+
+    while !root.exists() && root.pop() {}
+
+    (root, recursive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::tests::make_root_model_additional;
+    use crate::shared::prelude::{
+        DoctorGroupActionBuilder, DoctorGroupActionCheckBuilder, DoctorGroupActionFixBuilder,
+        DoctorGroupCachePath,
+    };
+
+    fn group_with_paths(name: &str, base: &str, paths: Vec<&str>) -> crate::prelude::DoctorGroup {
+        let action = DoctorGroupActionBuilder::default()
+            .name("action")
+            .description("")
+            .required(true)
+            .check(
+                DoctorGroupActionCheckBuilder::default()
+                    .command(None)
+                    .files(Some(DoctorGroupCachePath::from((base, paths))))
+                    .build()
+                    .unwrap(),
+            )
+            .fix(DoctorGroupActionFixBuilder::default().build().unwrap())
+            .build()
+            .unwrap();
+        make_root_model_additional(
+            vec![action],
+            |meta| {
+                meta.name(name)
+                    .annotations(crate::models::prelude::ModelMetadataAnnotations {
+                        file_path: Some(format!("{}/.scope/{}.yaml", base, name)),
+                        ..Default::default()
+                    })
+            },
+            |group| group,
+        )
+    }
+
+    #[test]
+    fn test_watch_root() {
+        let dir = std::env::temp_dir();
+        let base = dir.display().to_string();
+
+        assert_eq!(
+            (dir.clone(), false),
+            watch_root(&format!("{}/Gemfile", base))
+        );
+        assert_eq!(
+            (dir.clone(), false),
+            watch_root(&format!("{}/*.json", base))
+        );
+        assert_eq!(
+            (dir.clone(), true),
+            watch_root(&format!("{}/**/package.json", base))
+        );
+        assert_eq!(
+            (dir.clone(), false),
+            watch_root(&format!("{}/scope-missing-dir/*.json", base))
+        );
+    }
+
+    #[test]
+    fn test_affected_groups() {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/repo"));
+        for group in [
+            group_with_paths("ruby", "/repo", vec!["Gemfile", "Gemfile.lock"]),
+            group_with_paths("node", "/repo", vec!["**/package.json"]),
+        ] {
+            found_config
+                .doctor_group
+                .insert(group.name().to_string(), group);
+        }
+
+        let plan = WatchPlan::new(&found_config);
+        assert_eq!(
+            BTreeSet::from(["ruby".to_string()]),
+            plan.affected_groups(&[PathBuf::from("/repo/Gemfile.lock")])
+        );
+        assert_eq!(
+            BTreeSet::from(["node".to_string()]),
+            plan.affected_groups(&[PathBuf::from("/repo/web/package.json")])
+        );
+        assert!(plan
+            .affected_groups(&[PathBuf::from("/repo/README.md")])
+            .is_empty());
+
+        assert!(plan.is_config_change(&[PathBuf::from("/repo/.scope/ruby.yaml")]));
+        assert!(plan.is_config_change(&[PathBuf::from("/repo/.scope/new.yml")]));
+        assert!(!plan.is_config_change(&[PathBuf::from("/repo/.scope/notes.md")]));
+        assert!(!plan.is_config_change(&[PathBuf::from("/repo/config.yaml")]));
+    }
+}
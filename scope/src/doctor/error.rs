@@ -1,14 +1,5 @@
 use thiserror::Error;
 
-#[derive(Error, Debug)]
-pub enum ConfigError {
-    #[error("Unable to process file. {error:?}")]
-    IoError {
-        #[from]
-        error: std::io::Error,
-    },
-}
-
 #[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum FileCacheError {
@@ -21,3 +12,16 @@ pub enum FileCacheError {
     #[error("IoError {0:?}")]
     IoError(#[from] std::io::Error),
 }
+
+/// Why a doctor run started through the library API stopped early.
+#[derive(Error, Debug)]
+pub enum DoctorRunError {
+    #[error("Doctor run did not finish within {duration:?}")]
+    TimedOut { duration: std::time::Duration },
+    #[error("Doctor run was cancelled")]
+    Cancelled,
+    #[error("There is no doctor group named `{name}` to run until")]
+    UnknownGroup { name: String },
+    #[error("A dry run doesn't run anything, use `plan` to get what would run")]
+    DryRun,
+}
@@ -1,4 +1,5 @@
 use super::error::FileCacheError;
+use super::run_lock::with_file_lock;
 use anyhow::Result;
 use async_trait::async_trait;
 use mockall::automock;
@@ -83,6 +84,27 @@ impl FileBasedCache {
     }
 }
 
+impl FileBasedCache {
+    /// Move the cached checksums for `check_name` into `other`. Entries `other` already has are
+    /// kept. Returns `false` when there was nothing to move.
+    pub async fn move_entries(&self, check_name: &str, other: &FileBasedCache) -> bool {
+        let Some(entries) = self.data.write().await.checksums.remove(check_name) else {
+            return false;
+        };
+
+        let mut other_data = other.data.write().await;
+        let other_entries = other_data
+            .checksums
+            .entry(check_name.to_string())
+            .or_default();
+        for (path, checksum) in entries {
+            other_entries.entry(path).or_insert(checksum);
+        }
+
+        true
+    }
+}
+
 #[async_trait]
 impl FileCache for FileBasedCache {
     #[tracing::instrument(skip_all, fields(check.name = %check_name))]
@@ -133,7 +155,20 @@ impl FileCache for FileBasedCache {
         let cache_data = self.data.read().await;
         match serde_json::to_string(cache_data.deref()) {
             Ok(text) => {
-                if let Err(e) = std::fs::write(&self.path, text.as_bytes()) {
+                // Other runs sharing the cache may persist at the same time. Writing under the
+                // lock, to a temp file that replaces the cache, keeps the file from being mixed.
+                // Waiting for the lock blocks, so it's done off the async runtime.
+                let write = tokio::task::spawn_blocking(move || {
+                    with_file_lock(&file_path, || {
+                        let temp_path =
+                            file_path.with_extension(format!("tmp-{}", std::process::id()));
+                        std::fs::write(&temp_path, text.as_bytes())?;
+                        std::fs::rename(&temp_path, &file_path)
+                    })
+                })
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::other(e)));
+                if let Err(e) = write {
                     warn!(target: "user", "Failed to write updated cache to disk, next run will show incorrect results");
                     return Err(FileCacheError::WriteIoError(e));
                 }
@@ -157,3 +192,37 @@ async fn make_checksum(path: &Path) -> Result<String> {
 
     Ok(sha256::try_async_digest(path).await?)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_move_entries() {
+        let shared = FileBasedCache::default();
+        shared.data.write().await.checksums.insert(
+            "group".to_string(),
+            BTreeMap::from([
+                ("/a".to_string(), "1".to_string()),
+                ("/b".to_string(), "2".to_string()),
+            ]),
+        );
+        let group = FileBasedCache::default();
+        group.data.write().await.checksums.insert(
+            "group".to_string(),
+            BTreeMap::from([("/a".to_string(), "3".to_string())]),
+        );
+
+        assert!(shared.move_entries("group", &group).await);
+        assert!(!shared.move_entries("group", &group).await);
+
+        assert!(shared.data.read().await.checksums.is_empty());
+        assert_eq!(
+            BTreeMap::from([
+                ("/a".to_string(), "3".to_string()),
+                ("/b".to_string(), "2".to_string()),
+            ]),
+            group.data.read().await.checksums["group"]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use minijinja::{context, Environment};
+
+use crate::prelude::{ActionTaskReport, GroupReport};
+
+/// Where group logs are written when `--group-log` is passed without a pattern, the `groups`
+/// directory of the run's artifacts.
+#[cfg_attr(not(feature = "cli"), allow(dead_code))]
+pub const DEFAULT_GROUP_LOG_PATTERN: &str = "{{ artifact_dir }}/groups/{{ group }}.log";
+
+/// Writes the full output of every command a group ran to a file, see `--group-log`.
+#[derive(Debug, Clone)]
+pub struct GroupLog {
+    /// Path of the log file, templated with `run_id`, `artifact_dir` and `group`.
+    pub pattern: String,
+    pub run_id: String,
+    /// Artifact directory of the run.
+    pub artifact_dir: PathBuf,
+}
+
+impl GroupLog {
+    pub fn path(&self, group_name: &str) -> Result<PathBuf> {
+        let path = Environment::new().render_str(
+            &self.pattern,
+            context! {
+                run_id => self.run_id,
+                artifact_dir => self.artifact_dir.display().to_string(),
+                group => group_name,
+            },
+        )?;
+
+        Ok(PathBuf::from(path))
+    }
+
+    /// Write the log for `report`, returning where it was written.
+    pub async fn write(&self, report: &GroupReport) -> Result<PathBuf> {
+        let path = self.path(report.group_name())?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, format_group_log(report)).await?;
+
+        Ok(path)
+    }
+}
+
+fn format_group_log(report: &GroupReport) -> String {
+    let mut log = String::new();
+    for action in report.actions() {
+        writeln!(log, "==> {}/{}", report.group_name(), action.action_name).ok();
+        for (kind, tasks) in [
+            ("check", &action.check),
+            ("fix", &action.fix),
+            ("verify", &action.validate),
+            ("rollback", &action.rollback),
+        ] {
+            for task in tasks {
+                format_task(&mut log, kind, task);
+            }
+        }
+        log.push('\n');
+    }
+
+    log
+}
+
+fn format_task(log: &mut String, kind: &str, task: &ActionTaskReport) {
+    writeln!(log, "--- {}: {}", kind, task.command).ok();
+    writeln!(log, "started at {}", task.start_time).ok();
+    if let Some(output) = &task.output {
+        log.push_str(output);
+        if !output.ends_with('\n') {
+            log.push('\n');
+        }
+    }
+    let exit_code = task
+        .exit_code
+        .map(|code| code.to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    writeln!(
+        log,
+        "finished at {}, exit code {}",
+        task.end_time, exit_code
+    )
+    .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::ActionReport;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_path_is_templated() {
+        let log = GroupLog {
+            pattern: DEFAULT_GROUP_LOG_PATTERN.to_string(),
+            run_id: "20240101-abcd".to_string(),
+            artifact_dir: PathBuf::from("/tmp/scope/runs/20240101-abcd"),
+        };
+        assert_eq!(
+            PathBuf::from("/tmp/scope/runs/20240101-abcd/groups/setup.log"),
+            log.path("setup").unwrap()
+        );
+
+        let log = GroupLog {
+            pattern: "/var/log/scope/{{ run_id }}-{{ group }}.log".to_string(),
+            ..log
+        };
+        assert_eq!(
+            PathBuf::from("/var/log/scope/20240101-abcd-setup.log"),
+            log.path("setup").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_group_log() {
+        let mut report = GroupReport::new("setup");
+        report.add_action(&ActionReport {
+            action_name: "deps".to_string(),
+            check: vec![ActionTaskReport {
+                command: "check.sh".to_string(),
+                output: Some("missing deps".to_string()),
+                exit_code: Some(1),
+                start_time: DateTime::from_timestamp(1715612600, 0).unwrap(),
+                end_time: DateTime::from_timestamp(1715612601, 0).unwrap(),
+            }],
+            fix: vec![ActionTaskReport {
+                command: "fix.sh".to_string(),
+                output: None,
+                exit_code: Some(0),
+                start_time: DateTime::from_timestamp(1715612601, 0).unwrap(),
+                end_time: DateTime::from_timestamp(1715612602, 0).unwrap(),
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(
+            "==> setup/deps
+--- check: check.sh
+started at 2024-05-13 15:03:20 UTC
+missing deps
+finished at 2024-05-13 15:03:21 UTC, exit code 1
+--- fix: fix.sh
+started at 2024-05-13 15:03:21 UTC
+finished at 2024-05-13 15:03:22 UTC, exit code 0
+
+",
+            format_group_log(&report)
+        );
+    }
+}
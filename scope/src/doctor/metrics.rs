@@ -0,0 +1,241 @@
+use super::run_history::RunHistory;
+use super::runner::PathRunResult;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Health of the machine after a doctor run, for monitoring a fleet of machines. Written in the
+/// Prometheus textfile format with `--metrics-file`, or exposed some other way by a daemon that
+/// embeds the crate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorRunMetrics {
+    pub succeeded: bool,
+    pub duration_seconds: f64,
+    pub finished_at: DateTime<Utc>,
+    /// The groups that ran, in the order they ran, then the groups that were skipped.
+    pub groups: Vec<GroupMetrics>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMetrics {
+    pub name: String,
+    /// Whether the group succeeded, `None` when it was skipped.
+    pub succeeded: Option<bool>,
+    /// How long the group took, `None` when it was skipped.
+    pub duration_seconds: Option<f64>,
+    /// When the group last ran, `None` when it was skipped.
+    pub last_run: Option<DateTime<Utc>>,
+    /// When the group last succeeded, in this run or an earlier one.
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+impl DoctorRunMetrics {
+    /// Metrics of the run that returned `result`. When each group last succeeded comes from
+    /// `run_history`, which has this run's successes recorded already.
+    pub(crate) async fn new(
+        result: &PathRunResult,
+        finished_at: DateTime<Utc>,
+        run_history: &dyn RunHistory,
+    ) -> Self {
+        let mut groups = Vec::new();
+        for report in &result.group_reports {
+            let name = report.group_name().to_string();
+            groups.push(GroupMetrics {
+                succeeded: Some(!result.failed_group.contains(&name)),
+                duration_seconds: Some(report.duration_ms() as f64 / 1000.0),
+                last_run: Some(finished_at),
+                last_success: run_history.last_success(&name).await,
+                name,
+            });
+        }
+        for name in result.skip_reasons.keys() {
+            groups.push(GroupMetrics {
+                name: name.clone(),
+                succeeded: None,
+                duration_seconds: None,
+                last_run: None,
+                last_success: run_history.last_success(name).await,
+            });
+        }
+
+        Self {
+            succeeded: result.did_succeed,
+            duration_seconds: result.duration_ms as f64 / 1000.0,
+            finished_at,
+            groups,
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format, as read by the node exporter's
+    /// textfile collector.
+    pub fn to_prometheus_text(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, help: &str, samples: Vec<(Option<&str>, f64)>| {
+            if samples.is_empty() {
+                return;
+            }
+            writeln!(text, "# HELP {} {}", name, help).unwrap();
+            writeln!(text, "# TYPE {} gauge", name).unwrap();
+            for (group, value) in samples {
+                let labels = match group {
+                    Some(group) => format!("{{group=\"{}\"}}", escape_label(group)),
+                    None => String::new(),
+                };
+                writeln!(text, "{}{} {}", name, labels, value).unwrap();
+            }
+        };
+        let groups = |value: &dyn Fn(&GroupMetrics) -> Option<f64>| {
+            self.groups
+                .iter()
+                .filter_map(|group| Some((Some(group.name.as_str()), value(group)?)))
+                .collect::<Vec<_>>()
+        };
+        let bool_value = |value: bool| if value { 1.0 } else { 0.0 };
+
+        metric(
+            "scope_doctor_run_success",
+            "Whether the last doctor run succeeded.",
+            vec![(None, bool_value(self.succeeded))],
+        );
+        metric(
+            "scope_doctor_run_duration_seconds",
+            "How long the last doctor run took.",
+            vec![(None, self.duration_seconds)],
+        );
+        metric(
+            "scope_doctor_run_timestamp_seconds",
+            "When the last doctor run finished.",
+            vec![(None, timestamp(&self.finished_at))],
+        );
+        metric(
+            "scope_doctor_group_success",
+            "Whether the group succeeded in the last doctor run, for the groups that ran.",
+            groups(&|group| group.succeeded.map(bool_value)),
+        );
+        metric(
+            "scope_doctor_group_skipped",
+            "Whether the last doctor run skipped the group.",
+            groups(&|group| Some(bool_value(group.succeeded.is_none()))),
+        );
+        metric(
+            "scope_doctor_group_duration_seconds",
+            "How long the group took in the last doctor run.",
+            groups(&|group| group.duration_seconds),
+        );
+        metric(
+            "scope_doctor_group_last_run_timestamp_seconds",
+            "When the group last ran.",
+            groups(&|group| group.last_run.as_ref().map(timestamp)),
+        );
+        metric(
+            "scope_doctor_group_last_success_timestamp_seconds",
+            "When the group last succeeded.",
+            groups(&|group| group.last_success.as_ref().map(timestamp)),
+        );
+
+        text
+    }
+
+    /// Write the metrics to `path` in the Prometheus text format. The file is replaced in one
+    /// go, so a collector never reads half of it.
+    pub fn write_textfile(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, self.to_prometheus_text())?;
+        std::fs::rename(&tmp_path, path)
+    }
+}
+
+/// Seconds since the Unix epoch, with milliseconds.
+fn timestamp(at: &DateTime<Utc>) -> f64 {
+    at.timestamp_millis() as f64 / 1000.0
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::run_history::NoOpRunHistory;
+    use crate::doctor::runner::SkipReason;
+    use crate::prelude::GroupReport;
+    use chrono::TimeZone;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_metrics_in_prometheus_text_format() {
+        let mut setup = GroupReport::new("setup");
+        setup.set_duration(Duration::from_millis(1500));
+        let mut node = GroupReport::new("node \"lts\"");
+        node.set_duration(Duration::from_millis(250));
+        let result = PathRunResult {
+            did_succeed: false,
+            succeeded_groups: BTreeSet::from(["setup".to_string()]),
+            failed_group: BTreeSet::from(["node \"lts\"".to_string()]),
+            skipped_group: BTreeSet::from(["yarn".to_string()]),
+            soft_failed_groups: BTreeSet::new(),
+            skip_reasons: BTreeMap::from([(
+                "yarn".to_string(),
+                SkipReason::GroupFailed {
+                    failed_group: "node \"lts\"".to_string(),
+                    chain: vec!["node \"lts\"".to_string()],
+                },
+            )]),
+            group_reports: vec![setup, node],
+            duration_ms: 2000,
+        };
+        let finished_at = Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap();
+
+        let metrics = DoctorRunMetrics::new(&result, finished_at, &NoOpRunHistory::default()).await;
+        assert_eq!(
+            vec![Some(true), Some(false), None],
+            metrics
+                .groups
+                .iter()
+                .map(|group| group.succeeded)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            r#"# HELP scope_doctor_run_success Whether the last doctor run succeeded.
+# TYPE scope_doctor_run_success gauge
+scope_doctor_run_success 0
+# HELP scope_doctor_run_duration_seconds How long the last doctor run took.
+# TYPE scope_doctor_run_duration_seconds gauge
+scope_doctor_run_duration_seconds 2
+# HELP scope_doctor_run_timestamp_seconds When the last doctor run finished.
+# TYPE scope_doctor_run_timestamp_seconds gauge
+scope_doctor_run_timestamp_seconds 1704164645
+# HELP scope_doctor_group_success Whether the group succeeded in the last doctor run, for the groups that ran.
+# TYPE scope_doctor_group_success gauge
+scope_doctor_group_success{group="setup"} 1
+scope_doctor_group_success{group="node \"lts\""} 0
+# HELP scope_doctor_group_skipped Whether the last doctor run skipped the group.
+# TYPE scope_doctor_group_skipped gauge
+scope_doctor_group_skipped{group="setup"} 0
+scope_doctor_group_skipped{group="node \"lts\""} 0
+scope_doctor_group_skipped{group="yarn"} 1
+# HELP scope_doctor_group_duration_seconds How long the group took in the last doctor run.
+# TYPE scope_doctor_group_duration_seconds gauge
+scope_doctor_group_duration_seconds{group="setup"} 1.5
+scope_doctor_group_duration_seconds{group="node \"lts\""} 0.25
+# HELP scope_doctor_group_last_run_timestamp_seconds When the group last ran.
+# TYPE scope_doctor_group_last_run_timestamp_seconds gauge
+scope_doctor_group_last_run_timestamp_seconds{group="setup"} 1704164645
+scope_doctor_group_last_run_timestamp_seconds{group="node \"lts\""} 1704164645
+"#,
+            metrics.to_prometheus_text()
+        );
+    }
+}
@@ -1,14 +1,35 @@
+mod audit_log;
 mod check;
 mod cli;
 mod commands;
 mod error;
 mod file_cache;
+mod group_log;
+mod metrics;
+mod plan;
+mod run_history;
+mod run_lock;
 mod runner;
+mod sandbox;
+mod snapshot;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use check::{action_env_vars, DefaultGlobWalker, GlobWalker};
+pub use commands::{plan, run, run_metrics, DoctorRunOptions, DoctorRunOutcome};
+pub use error::DoctorRunError;
+pub use metrics::{DoctorRunMetrics, GroupMetrics};
+pub use plan::{ActionPlan, ActionPlanStatus, DoctorRunPlan, GroupPlan};
+pub use runner::{
+    DoctorRunOutput, DoctorRunOutputVersion, ExecutionPolicy, PathRunResult, SkipReason,
+};
+
 pub mod prelude {
     pub use super::cli::doctor_root;
     pub use super::cli::DoctorArgs;
     pub use super::commands::generate_doctor_list;
+    pub use super::commands::{doctor_run, DoctorRunArgs, DoctorRunOutputFormat};
+    pub use super::error::DoctorRunError;
+    pub use super::plan::DoctorRunPlan;
+    pub use super::runner::{DoctorRunOutput, DoctorRunOutputVersion, PathRunResult};
 }
@@ -0,0 +1,175 @@
+use super::check::DoctorActionRun;
+use super::runner::{RunGroups, SkipReason};
+use crate::shared::prelude::ScheduleStatus;
+use anyhow::Result;
+use chrono::Local;
+use colored::Colorize;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// What `scope doctor run --dry-run` would do, worked out from the group order, caches, run
+/// history, schedules and platforms without running any check or fix commands. `onlyIf`
+/// commands don't run either, so actions and groups they'd skip are listed as running.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorRunPlan {
+    /// Groups in the order they'd run.
+    pub groups: Vec<GroupPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupPlan {
+    pub name: String,
+    /// Why the group wouldn't run. A group can also be skipped because a group before it fails,
+    /// which isn't known without running it.
+    pub skip_reason: Option<SkipReason>,
+    /// Tools the group needs that aren't installed. Unless a group before it installs them, the
+    /// group fails without running any action.
+    pub missing_tools: Vec<String>,
+    /// Actions that would run, empty when the group is skipped.
+    pub actions: Vec<ActionPlan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionPlan {
+    pub name: String,
+    pub description: String,
+    pub status: ActionPlanStatus,
+    /// Check commands that would run to decide whether the fix is needed.
+    pub check_commands: Vec<String>,
+    /// Fix commands that would be proposed. Empty when the action doesn't need a fix, doesn't
+    /// have one, or fixes are turned off with `--fix false`.
+    pub fix_commands: Vec<String>,
+    /// The fix asks for confirmation before it runs.
+    pub fix_prompts: bool,
+}
+
+/// How far an action would get, as far as can be known without running commands.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ActionPlanStatus {
+    /// The check's files haven't changed since the last run, nothing would run.
+    UpToDate,
+    /// The check commands decide whether the fix is needed.
+    CheckWillRun,
+    /// The check's files changed, or there is no check, so the fix is needed.
+    FixNeeded,
+    /// The action's `platforms` don't include this machine, nothing would run.
+    NotApplicable,
+    /// The action has `runAlways`, its fix runs without a check.
+    RunsAlways,
+}
+
+impl<T> RunGroups<T>
+where
+    T: DoctorActionRun,
+{
+    /// Work out what [`RunGroups::execute`] would do, without running commands or recording
+    /// anything.
+    pub async fn plan(&self) -> Result<DoctorRunPlan> {
+        let mut groups = Vec::new();
+        for container in self
+            .all_paths
+            .iter()
+            .filter_map(|path| self.group_actions.get(path))
+        {
+            let mut group = GroupPlan {
+                name: container.group_name.clone(),
+                skip_reason: None,
+                missing_tools: container.missing_tools(),
+                actions: Vec::new(),
+            };
+
+            if !container.condition.includes_platform(std::env::consts::OS) {
+                group.skip_reason = Some(SkipReason::NotApplicable);
+                groups.push(group);
+                continue;
+            }
+            if self.is_resumed(container).await? {
+                group.skip_reason = Some(SkipReason::Resumed);
+                groups.push(group);
+                continue;
+            }
+            if let Some(schedule) = &container.schedule {
+                let last_success = self.run_history.last_success(&container.group_name).await;
+                if schedule.status(Local::now(), last_success) != ScheduleStatus::Due {
+                    group.skip_reason = Some(SkipReason::NotScheduled);
+                    groups.push(group);
+                    continue;
+                }
+            }
+
+            for action in &container.actions {
+                group.actions.push(action.plan_action().await?);
+            }
+            groups.push(group);
+        }
+
+        Ok(DoctorRunPlan { groups })
+    }
+}
+
+fn quoted(commands: &[String]) -> String {
+    commands.iter().map(|c| format!("`{}`", c)).join(", ")
+}
+
+impl Display for ActionPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let fix = match (self.fix_commands.is_empty(), self.fix_prompts) {
+            (true, _) => "no fix would run".to_string(),
+            (false, true) => format!("would ask to fix with {}", quoted(&self.fix_commands)),
+            (false, false) => format!("would fix with {}", quoted(&self.fix_commands)),
+        };
+        match self.status {
+            ActionPlanStatus::UpToDate => write!(f, "{}", "up to date".green()),
+            ActionPlanStatus::CheckWillRun => write!(
+                f,
+                "would check with {}, when it fails {}",
+                quoted(&self.check_commands),
+                fix
+            ),
+            ActionPlanStatus::FixNeeded => write!(f, "{}, {}", "needs a fix".yellow(), fix),
+            ActionPlanStatus::NotApplicable => write!(f, "{}", "not applicable".dimmed()),
+            ActionPlanStatus::RunsAlways => write!(f, "{}, {}", "runs always".cyan(), fix),
+        }
+    }
+}
+
+impl Display for DoctorRunPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut lines = vec![format!(
+            "Dry run, no commands were run. {} groups would run in this order:",
+            self.groups
+                .iter()
+                .filter(|group| group.skip_reason.is_none())
+                .count()
+        )];
+        for group in &self.groups {
+            if let Some(reason) = &group.skip_reason {
+                lines.push(format!(
+                    "{} {} because {}",
+                    group.name.bold(),
+                    "would be skipped".yellow(),
+                    reason
+                ));
+                continue;
+            }
+            lines.push(group.name.bold().to_string());
+            if !group.missing_tools.is_empty() {
+                lines.push(format!(
+                    "  {}: {}",
+                    "missing required tools".red(),
+                    group.missing_tools.join(", ")
+                ));
+            }
+            for action in &group.actions {
+                lines.push(format!("  {}: {}", action.name, action));
+            }
+        }
+
+        write!(f, "{}", lines.join("\n"))
+    }
+}
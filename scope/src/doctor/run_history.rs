@@ -0,0 +1,303 @@
+use super::error::FileCacheError;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mockall::automock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::fs::File;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::warn;
+
+/// How a group ended the last time it was part of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum GroupRunStatus {
+    Succeeded,
+    Failed,
+    /// A group it needs failed, so it didn't run.
+    Skipped,
+}
+
+impl std::fmt::Display for GroupRunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupRunStatus::Succeeded => write!(f, "succeeded"),
+            GroupRunStatus::Failed => write!(f, "failed"),
+            GroupRunStatus::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Runs kept in the history to resume from, the oldest are dropped first.
+const RUNS_KEPT: usize = 5;
+
+/// Record of when each group last ran successfully, used to evaluate a group's `schedule`, and
+/// how it ended the last time, shown by `scope doctor list`. The status of each group is also
+/// kept per run, so a run that didn't complete can be resumed with `--resume`.
+#[automock]
+#[async_trait]
+pub trait RunHistory: Sync + Send + Debug {
+    async fn last_success(&self, group_name: &str) -> Option<DateTime<Utc>>;
+    async fn record_success(&self, group_name: &str, at: DateTime<Utc>);
+    async fn last_status(&self, group_name: &str) -> Option<GroupRunStatus>;
+    /// Also recorded for the run started with [`RunHistory::start_run`].
+    async fn record_status(&self, group_name: &str, status: GroupRunStatus);
+    /// Groups that succeeded in the run started last, when that run didn't complete.
+    async fn resumable_groups(&self) -> BTreeSet<String>;
+    /// Record the status of each group under `run_id` from now on.
+    async fn start_run(&self, run_id: &str, at: DateTime<Utc>);
+    /// Mark the run started with [`RunHistory::start_run`] as complete, so it isn't resumed.
+    async fn complete_run(&self);
+    async fn persist(&self) -> Result<(), FileCacheError>;
+}
+
+#[derive(Default, Debug)]
+pub struct NoOpRunHistory {}
+
+#[async_trait]
+impl RunHistory for NoOpRunHistory {
+    async fn last_success(&self, _group_name: &str) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    async fn record_success(&self, _group_name: &str, _at: DateTime<Utc>) {}
+
+    async fn last_status(&self, _group_name: &str) -> Option<GroupRunStatus> {
+        None
+    }
+
+    async fn record_status(&self, _group_name: &str, _status: GroupRunStatus) {}
+
+    async fn resumable_groups(&self) -> BTreeSet<String> {
+        BTreeSet::new()
+    }
+
+    async fn start_run(&self, _run_id: &str, _at: DateTime<Utc>) {}
+
+    async fn complete_run(&self) {}
+
+    async fn persist(&self) -> Result<(), FileCacheError> {
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct RunHistoryData {
+    #[serde(default)]
+    last_success: BTreeMap<String, DateTime<Utc>>,
+    #[serde(default)]
+    last_status: BTreeMap<String, GroupRunStatus>,
+    /// Progress of the latest runs, by run id.
+    #[serde(default)]
+    runs: BTreeMap<String, RunProgress>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct RunProgress {
+    started_at: DateTime<Utc>,
+    /// The run finished and every group succeeded.
+    complete: bool,
+    groups: BTreeMap<String, GroupRunStatus>,
+}
+
+impl RunHistoryData {
+    fn latest_run(&self) -> Option<&RunProgress> {
+        self.runs.values().max_by_key(|run| run.started_at)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FileBasedRunHistory {
+    data: Arc<RwLock<RunHistoryData>>,
+    path: String,
+    /// Run the group statuses are recorded for.
+    run_id: RwLock<Option<String>>,
+}
+
+impl FileBasedRunHistory {
+    pub fn new(history_path: &Path) -> Self {
+        let data = match File::open(history_path) {
+            Err(_) => RunHistoryData::default(),
+            Ok(file) => serde_json::from_reader(file).unwrap_or_else(|e| {
+                warn!("Error when parsing run history {:?}", e);
+                warn!(target: "user", "Unable to load run history, the file was not valid. Scheduled groups will run.");
+                RunHistoryData::default()
+            }),
+        };
+
+        Self {
+            data: Arc::new(RwLock::new(data)),
+            path: history_path.display().to_string(),
+            run_id: RwLock::new(None),
+        }
+    }
+}
+
+#[async_trait]
+impl RunHistory for FileBasedRunHistory {
+    async fn last_success(&self, group_name: &str) -> Option<DateTime<Utc>> {
+        self.data.read().await.last_success.get(group_name).cloned()
+    }
+
+    async fn record_success(&self, group_name: &str, at: DateTime<Utc>) {
+        self.data
+            .write()
+            .await
+            .last_success
+            .insert(group_name.to_string(), at);
+    }
+
+    async fn last_status(&self, group_name: &str) -> Option<GroupRunStatus> {
+        self.data.read().await.last_status.get(group_name).cloned()
+    }
+
+    async fn record_status(&self, group_name: &str, status: GroupRunStatus) {
+        let mut data = self.data.write().await;
+        data.last_status.insert(group_name.to_string(), status);
+        if let Some(run) = self
+            .run_id
+            .read()
+            .await
+            .as_ref()
+            .and_then(|run_id| data.runs.get_mut(run_id))
+        {
+            run.groups.insert(group_name.to_string(), status);
+        }
+    }
+
+    async fn resumable_groups(&self) -> BTreeSet<String> {
+        let data = self.data.read().await;
+        match data.latest_run() {
+            Some(run) if !run.complete => run
+                .groups
+                .iter()
+                .filter(|(_, status)| **status == GroupRunStatus::Succeeded)
+                .map(|(name, _)| name.clone())
+                .collect(),
+            _ => BTreeSet::new(),
+        }
+    }
+
+    async fn start_run(&self, run_id: &str, at: DateTime<Utc>) {
+        let mut data = self.data.write().await;
+        data.runs.insert(
+            run_id.to_string(),
+            RunProgress {
+                started_at: at,
+                complete: false,
+                groups: BTreeMap::new(),
+            },
+        );
+        while data.runs.len() > RUNS_KEPT {
+            let oldest = data
+                .runs
+                .iter()
+                .min_by_key(|(_, run)| run.started_at)
+                .map(|(run_id, _)| run_id.clone());
+            if let Some(oldest) = oldest {
+                data.runs.remove(&oldest);
+            }
+        }
+        *self.run_id.write().await = Some(run_id.to_string());
+    }
+
+    async fn complete_run(&self) {
+        let mut data = self.data.write().await;
+        if let Some(run) = self
+            .run_id
+            .read()
+            .await
+            .as_ref()
+            .and_then(|run_id| data.runs.get_mut(run_id))
+        {
+            run.complete = true;
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn persist(&self) -> Result<(), FileCacheError> {
+        let file_path = PathBuf::from(&self.path);
+        let parent = match file_path.parent() {
+            Some(parent) => parent,
+            None => {
+                return Err(FileCacheError::FsError);
+            }
+        };
+        std::fs::create_dir_all(parent)?;
+        let history = self.data.read().await;
+        let text =
+            serde_json::to_string(history.deref()).map_err(FileCacheError::SerializationError)?;
+        std::fs::write(&self.path, text.as_bytes()).map_err(FileCacheError::WriteIoError)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileBasedRunHistory, GroupRunStatus, RunHistory};
+    use chrono::{Duration, TimeZone, Utc};
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_history_round_trips_through_disk() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("run-history.json");
+        let at = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+
+        let history = FileBasedRunHistory::new(&path);
+        assert_eq!(None, history.last_success("audit").await);
+        history.record_success("audit", at).await;
+        history.record_status("audit", GroupRunStatus::Failed).await;
+        history.persist().await.unwrap();
+
+        let reloaded = FileBasedRunHistory::new(&path);
+        assert_eq!(Some(at), reloaded.last_success("audit").await);
+        assert_eq!(
+            Some(GroupRunStatus::Failed),
+            reloaded.last_status("audit").await
+        );
+    }
+
+    #[tokio::test]
+    async fn test_groups_of_incomplete_run_are_resumable() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("run-history.json");
+        let at = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+
+        let history = FileBasedRunHistory::new(&path);
+        history.start_run("first", at).await;
+        history
+            .record_status("setup", GroupRunStatus::Succeeded)
+            .await;
+        history.record_status("build", GroupRunStatus::Failed).await;
+        history.persist().await.unwrap();
+
+        let reloaded = FileBasedRunHistory::new(&path);
+        assert_eq!(
+            BTreeSet::from(["setup".to_string()]),
+            reloaded.resumable_groups().await
+        );
+
+        reloaded
+            .start_run("second", at + Duration::minutes(5))
+            .await;
+        reloaded
+            .record_status("build", GroupRunStatus::Succeeded)
+            .await;
+        reloaded.complete_run().await;
+        assert_eq!(BTreeSet::new(), reloaded.resumable_groups().await);
+
+        for minutes in 10..20 {
+            reloaded
+                .start_run(&format!("run-{}", minutes), at + Duration::minutes(minutes))
+                .await;
+        }
+        assert_eq!(5, reloaded.data.read().await.runs.len());
+    }
+}
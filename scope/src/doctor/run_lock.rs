@@ -0,0 +1,90 @@
+use fs4::FileExt;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+const RUN_LOCK_FILE_NAME: &str = "doctor-run.lock";
+
+/// Advisory lock on a cache dir, held for as long as a doctor run uses it. Other runs sharing the
+/// cache dir can tell that it's in use. The OS releases the lock if scope exits without dropping it.
+#[derive(Debug)]
+pub struct RunLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl RunLock {
+    /// Take the lock for `cache_dir`. Returns `None` when another run already holds it.
+    pub fn try_acquire(cache_dir: &Path) -> std::io::Result<Option<Self>> {
+        std::fs::create_dir_all(cache_dir)?;
+        let path = cache_dir.join(RUN_LOCK_FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { file, path })),
+            Err(e) if e.kind() == fs4::lock_contended_error().kind() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.unlock() {
+            debug!("Unable to release {}. {:?}", self.path.display(), e);
+        }
+    }
+}
+
+/// Run `f` while holding an exclusive lock on `<path>.lock`, waiting for other processes to
+/// release it first. Used so that two runs never write the same file at once.
+pub fn with_file_lock<T>(
+    path: &Path,
+    f: impl FnOnce() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)?;
+
+    file.lock_exclusive()?;
+    let result = f();
+    file.unlock()?;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_run_lock_is_refused_until_released() {
+        let dir = assert_fs::TempDir::new().unwrap();
+
+        let first = RunLock::try_acquire(dir.path()).unwrap();
+        assert!(first.is_some());
+        assert!(RunLock::try_acquire(dir.path()).unwrap().is_none());
+
+        drop(first);
+        assert!(RunLock::try_acquire(dir.path()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_with_file_lock_returns_result() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("cache-file.json");
+
+        let result = with_file_lock(&path, || std::fs::write(&path, "{}"));
+
+        assert!(result.is_ok());
+        assert!(dir.path().join("cache-file.json.lock").exists());
+    }
+}
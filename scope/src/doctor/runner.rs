@@ -1,26 +1,138 @@
 use super::check::{ActionRunResult, ActionRunStatus, DoctorActionRun};
-use crate::prelude::{progress_bar_without_pos, ExecutionProvider, GroupReport};
+use super::group_log::GroupLog;
+use super::run_history::{GroupRunStatus, RunHistory};
+use crate::prelude::{progress_bar_without_pos, ExecutionProvider, GroupReport, IndicatifSpanExt};
 use crate::report_stdout;
-use crate::shared::prelude::DoctorGroup;
+use crate::shared::prelude::{
+    markdown_to_plain, render_markdown, CaptureOpts, DoctorCondition, DoctorGroup,
+    DoctorGroupSchedule, OutputDestination, ProgressReporter, ProgressStatus, RunFingerprint,
+    ScheduleStatus,
+};
 use anyhow::Result;
+use chrono::{Local, Utc};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
 use colored::Colorize;
+use itertools::Itertools;
 use petgraph::dot::{Config, Dot};
 use petgraph::prelude::*;
 use petgraph::visit::{DfsPostOrder, Walker};
-use std::collections::{BTreeMap, BTreeSet};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Instant;
 use tracing::{debug, error, info, info_span, warn, Instrument, Span};
-use tracing_indicatif::span_ext::IndicatifSpanExt;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct PathRunResult {
     pub did_succeed: bool,
     pub succeeded_groups: BTreeSet<String>,
+    #[serde(rename = "failedGroups")]
     pub failed_group: BTreeSet<String>,
+    #[serde(rename = "skippedGroups")]
     pub skipped_group: BTreeSet<String>,
+    /// Groups where an action with `allowFailure` failed. They're still listed as succeeded
+    /// unless another action failed.
+    #[serde(default)]
+    pub soft_failed_groups: BTreeSet<String>,
+    /// Why the groups that didn't run at all were skipped.
+    pub skip_reasons: BTreeMap<String, SkipReason>,
+    #[serde(rename = "groups")]
     pub group_reports: Vec<GroupReport>,
+    /// How long the run took, in milliseconds.
+    #[serde(default)]
+    pub duration_ms: u64,
+}
+
+/// What a run does with the groups after one that fails.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[serde(rename_all = "camelCase")]
+pub enum ExecutionPolicy {
+    /// Skip every group after the first one that fails
+    #[default]
+    FailFast,
+    /// Skip only the groups that need a group that failed, the others still run
+    SkipDependents,
+    /// Run every group, even when a group it needs failed, and summarize the failures at the end
+    KeepGoing,
+}
+
+/// Why a group didn't run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum SkipReason {
+    /// `failedGroup` failed, and the run's execution policy skips the groups after it. When the
+    /// skipped group needs it, `chain` lists the groups in between: the group's dependency first,
+    /// ending with `failedGroup`.
+    #[serde(rename_all = "camelCase")]
+    GroupFailed {
+        failed_group: String,
+        chain: Vec<String>,
+    },
+    /// The group's `schedule` didn't allow it to run.
+    NotScheduled,
+    /// The group's `platforms` or `onlyIf` excluded this machine.
+    NotApplicable,
+    /// The group succeeded in the run being resumed with `--resume`, and the files its checks
+    /// list haven't changed since.
+    Resumed,
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipReason::GroupFailed {
+                failed_group,
+                chain,
+            } if chain.len() > 1 => write!(
+                f,
+                "`{}` failed, needed through {}",
+                failed_group,
+                chain.iter().map(|name| format!("`{}`", name)).join(" -> ")
+            ),
+            SkipReason::GroupFailed { failed_group, .. } => write!(f, "`{}` failed", failed_group),
+            SkipReason::NotScheduled => write!(f, "it isn't scheduled to run now"),
+            SkipReason::NotApplicable => write!(f, "it doesn't apply to this machine"),
+            SkipReason::Resumed => write!(f, "it succeeded in the run being resumed"),
+        }
+    }
+}
+
+/// Version of [`DoctorRunOutput`], changed whenever a field is renamed or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub enum DoctorRunOutputVersion {
+    #[serde(rename = "v1")]
+    V1,
+}
+
+/// What `scope doctor run --output json` prints. The schema is published as
+/// `schema/v1.com.github.scope.DoctorRunOutput.json`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorRunOutput {
+    pub version: DoctorRunOutputVersion,
+    pub run_id: String,
+    /// The machine and config the run used.
+    #[serde(default)]
+    pub fingerprint: RunFingerprint,
+    #[serde(flatten)]
+    pub result: PathRunResult,
+}
+
+impl DoctorRunOutput {
+    pub fn new(run_id: &str, fingerprint: &RunFingerprint, result: PathRunResult) -> Self {
+        Self {
+            version: DoctorRunOutputVersion::V1,
+            run_id: run_id.to_string(),
+            fingerprint: fingerprint.clone(),
+            result,
+        }
+    }
 }
 
 impl Display for PathRunResult {
@@ -45,17 +157,49 @@ impl Display for PathRunResult {
                 "skipped".bold().yellow()
             ));
         }
+        if !self.soft_failed_groups.is_empty() {
+            output.push(format!(
+                "{} groups with {}",
+                self.soft_failed_groups.len(),
+                "allowed failures".bold().yellow()
+            ));
+        }
 
         write!(f, "{}", output.join(", "))
     }
 }
 
 impl PathRunResult {
+    /// The actions that ran, slowest first, named `group/action` with how long each took in
+    /// milliseconds. Actions that took as long keep the order they ran in.
+    pub fn slowest_actions(&self) -> Vec<(String, u64)> {
+        self.group_reports
+            .iter()
+            .flat_map(|group| {
+                group.actions().iter().map(move |action| {
+                    (
+                        format!("{}/{}", group.group_name(), action.action_name),
+                        action.duration_ms,
+                    )
+                })
+            })
+            .sorted_by(|a, b| b.1.cmp(&a.1))
+            .collect()
+    }
+
+    fn skip(&mut self, group_name: &str, reason: SkipReason) {
+        self.skipped_group.insert(group_name.to_string());
+        self.skip_reasons.insert(group_name.to_string(), reason);
+    }
+
     fn process(&mut self, group: &GroupExecutionResult) {
         let group_name = group.group_name.to_string();
         if group.skip_remaining {
             self.skipped_group.insert(group_name.clone());
         }
+        if group.has_allowed_failure {
+            self.soft_failed_groups.insert(group_name.clone());
+        }
         if group.has_failure {
             self.failed_group.insert(group_name);
             self.did_succeed = false;
@@ -71,6 +215,8 @@ impl PathRunResult {
 struct GroupExecutionResult {
     group_name: String,
     has_failure: bool,
+    /// An action with `allowFailure` failed.
+    has_allowed_failure: bool,
     skip_remaining: bool,
     group_report: GroupReport,
 }
@@ -85,12 +231,60 @@ where
     pub exec_provider: Arc<dyn ExecutionProvider>,
     pub exec_working_dir: PathBuf,
     pub sys_path: String,
+    pub schedule: Option<DoctorGroupSchedule>,
+    /// Executables the group's commands need, checked right before the group runs so that an
+    /// earlier group is able to install them.
+    pub required_tools: BTreeSet<String>,
+    /// PATH used to look up `required_tools`.
+    pub tools_path: String,
+    /// Groups this group needs, used to explain why it was skipped.
+    pub requires: Vec<String>,
+    /// When the group applies to this machine, checked before it runs.
+    pub condition: DoctorCondition,
 }
 
 impl<T> GroupActionContainer<T>
 where
     T: DoctorActionRun,
 {
+    pub(super) fn missing_tools(&self) -> Vec<String> {
+        self.required_tools
+            .iter()
+            .filter(|tool| {
+                which::which_in(tool, Some(&self.tools_path), &self.exec_working_dir).is_err()
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// The group's `platforms` include this machine, and its `onlyIf` command, when it has one,
+    /// exits 0.
+    pub(super) async fn is_applicable(&self) -> Result<bool> {
+        if !self.condition.includes_platform(std::env::consts::OS) {
+            return Ok(false);
+        }
+        let Some(command) = &self.condition.only_if else {
+            return Ok(true);
+        };
+
+        let args = vec![command.clone()];
+        let capture = self
+            .exec_provider
+            .run_command(CaptureOpts {
+                working_dir: &self.exec_working_dir,
+                args: &args,
+                output_dest: OutputDestination::Logging,
+                path: &self.tools_path,
+                env_vars: Default::default(),
+                timeout: None,
+                progress: None,
+            })
+            .await?;
+
+        debug!("onlyIf `{}` exited with {:?}", command, capture.exit_code);
+        Ok(capture.exit_code == Some(0))
+    }
+
     pub async fn execute_command(&self, command: &str) -> Result<String> {
         Ok(self
             .exec_provider
@@ -105,6 +299,14 @@ where
 {
     pub(crate) group_actions: BTreeMap<String, GroupActionContainer<T>>,
     pub(crate) all_paths: Vec<String>,
+    pub(crate) run_history: Arc<dyn RunHistory>,
+    /// When set, each group's full output is written to a file.
+    pub(crate) group_log: Option<GroupLog>,
+    pub(crate) progress: Arc<dyn ProgressReporter>,
+    /// Groups that succeeded in the run being resumed. They're skipped unless the files their
+    /// checks list changed.
+    pub(crate) resumed_groups: BTreeSet<String>,
+    pub(crate) execution_policy: ExecutionPolicy,
 }
 
 impl<T> RunGroups<T>
@@ -128,14 +330,19 @@ where
         header_span.pb_set_message("scope doctor run");
 
         let _span = header_span.enter();
+        let run_started = Instant::now();
 
-        let mut skip_remaining = false;
+        // Groups that failed with an action that stops the run, in the order they ran.
+        let mut stopped_by: Vec<String> = Vec::new();
         let mut run_result = PathRunResult {
             did_succeed: true,
             succeeded_groups: BTreeSet::new(),
             failed_group: BTreeSet::new(),
             skipped_group: BTreeSet::new(),
+            soft_failed_groups: BTreeSet::new(),
+            skip_reasons: BTreeMap::new(),
             group_reports: Vec::new(),
+            duration_ms: 0,
         };
 
         for group_container in groups {
@@ -143,8 +350,40 @@ where
             header_span.pb_inc(1);
             debug!(target: "user", "Running check {}", group_name);
 
-            if skip_remaining {
-                run_result.skipped_group.insert(group_name.to_string());
+            if let Some(reason) = self.failed_dependency(&group_name, &stopped_by) {
+                info!(target: "user", "Group `{}` was skipped because {}", group_name.bold(), reason);
+                run_result.skip(&group_name, reason);
+                self.progress
+                    .finish_group(&group_name, ProgressStatus::Skipped);
+                self.run_history
+                    .record_status(&group_name, GroupRunStatus::Skipped)
+                    .await;
+                continue;
+            }
+            if !group_container.is_applicable().await? {
+                let reason = SkipReason::NotApplicable;
+                info!(target: "user", "Group `{}` was skipped because {}", group_name.bold(), reason);
+                run_result.skip(&group_name, reason);
+                self.progress
+                    .finish_group(&group_name, ProgressStatus::NotApplicable);
+                continue;
+            }
+            if self.is_resumed(group_container).await? {
+                let reason = SkipReason::Resumed;
+                info!(target: "user", "Group `{}` was skipped because {}", group_name.bold(), reason);
+                run_result.skip(&group_name, reason);
+                self.progress
+                    .finish_group(&group_name, ProgressStatus::Skipped);
+                // Still succeeded, should this run be resumed too.
+                self.run_history
+                    .record_status(&group_name, GroupRunStatus::Succeeded)
+                    .await;
+                continue;
+            }
+            if !self.is_scheduled(group_container).await {
+                run_result.skip(&group_name, SkipReason::NotScheduled);
+                self.progress
+                    .finish_group(&group_name, ProgressStatus::Skipped);
                 continue;
             }
 
@@ -153,15 +392,166 @@ where
             group_span.pb_set_message(&format!("group {}", group_name));
             let _span = group_span.enter();
 
-            let group_result = self.execute_group(&group_span, group_container).await?;
+            self.progress.start_group(&group_name);
+            let group_started = Instant::now();
+            let mut group_result = self.execute_group(&group_span, group_container).await?;
+            group_result
+                .group_report
+                .set_duration(group_started.elapsed());
+            if let Some(group_log) = &self.group_log {
+                match group_log.write(&group_result.group_report).await {
+                    Ok(path) => group_result
+                        .group_report
+                        .set_log_path(&path.display().to_string()),
+                    Err(e) => {
+                        warn!(target: "user", "Unable to write the log for group `{}`: {}", group_name, e)
+                    }
+                }
+            }
             run_result.process(&group_result);
+            if !group_result.has_failure {
+                self.run_history
+                    .record_success(&group_name, Utc::now())
+                    .await;
+            }
+            let status = if group_result.has_failure {
+                GroupRunStatus::Failed
+            } else {
+                GroupRunStatus::Succeeded
+            };
+            self.run_history.record_status(&group_name, status).await;
+            self.progress.finish_group(
+                &group_name,
+                if group_result.has_failure {
+                    ProgressStatus::Failed
+                } else {
+                    ProgressStatus::Succeeded
+                },
+            );
+            // Metric attributes are only read from plain values, not formatted ones.
+            let status_name = status.to_string();
+            info!(
+                monotonic_counter.doctor_group_runs = 1u64,
+                histogram.doctor_group_duration_seconds = group_started.elapsed().as_secs_f64(),
+                group = group_name.as_str(),
+                status = status_name.as_str(),
+                "Group `{}` {} in {:?}",
+                group_name,
+                status,
+                group_started.elapsed()
+            );
 
-            skip_remaining |= group_result.skip_remaining;
+            if group_result.skip_remaining {
+                stopped_by.push(group_name);
+            }
         }
 
+        run_result.duration_ms = run_started.elapsed().as_millis() as u64;
+        let status = if run_result.did_succeed {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        info!(
+            monotonic_counter.doctor_runs = 1u64,
+            histogram.doctor_run_duration_seconds = run_started.elapsed().as_secs_f64(),
+            status,
+            "Doctor run {} in {:?}",
+            status,
+            run_started.elapsed()
+        );
+
         Ok(run_result)
     }
 
+    /// Why `group_name` is skipped, given the groups in `stopped_by` failed, or `None` when the
+    /// [`ExecutionPolicy`] still runs it.
+    fn failed_dependency(&self, group_name: &str, stopped_by: &[String]) -> Option<SkipReason> {
+        let reason = |failed_group: &String, chain: Vec<String>| SkipReason::GroupFailed {
+            failed_group: failed_group.clone(),
+            chain,
+        };
+        match self.execution_policy {
+            ExecutionPolicy::FailFast => stopped_by.first().map(|failed_group| {
+                reason(
+                    failed_group,
+                    self.dependency_chain(group_name, failed_group),
+                )
+            }),
+            ExecutionPolicy::SkipDependents => stopped_by.iter().find_map(|failed_group| {
+                let chain = self.dependency_chain(group_name, failed_group);
+                (!chain.is_empty()).then(|| reason(failed_group, chain))
+            }),
+            ExecutionPolicy::KeepGoing => None,
+        }
+    }
+
+    /// The shortest path of `requires` from `group_name` to `failed_group`, without `group_name`.
+    /// Empty when `group_name` doesn't need `failed_group`.
+    fn dependency_chain(&self, group_name: &str, failed_group: &str) -> Vec<String> {
+        let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+        let mut queue = VecDeque::from([group_name]);
+        while let Some(name) = queue.pop_front() {
+            if name == failed_group {
+                let mut chain = vec![name.to_string()];
+                let mut current = name;
+                while let Some(prev) = previous.get(current).filter(|prev| **prev != group_name) {
+                    chain.push(prev.to_string());
+                    current = *prev;
+                }
+                chain.reverse();
+                return chain;
+            }
+
+            let requires = self
+                .group_actions
+                .get(name)
+                .map(|container| container.requires.as_slice())
+                .unwrap_or_default();
+            for dep in requires {
+                if dep != group_name && !previous.contains_key(dep.as_str()) {
+                    previous.insert(dep, name);
+                    queue.push_back(dep);
+                }
+            }
+        }
+
+        Vec::new()
+    }
+
+    /// The group succeeded in the run being resumed, and none of its actions' files changed.
+    pub(super) async fn is_resumed(&self, container: &GroupActionContainer<T>) -> Result<bool> {
+        if !self.resumed_groups.contains(&container.group_name) {
+            return Ok(false);
+        }
+        for action in &container.actions {
+            if action.files_changed().await? {
+                info!(target: "user", "Group `{}` will run again, the files of `{}` changed", container.group_name.bold(), action.name());
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn is_scheduled(&self, container: &GroupActionContainer<T>) -> bool {
+        let Some(schedule) = &container.schedule else {
+            return true;
+        };
+
+        let last_success = self.run_history.last_success(&container.group_name).await;
+        match schedule.status(Local::now(), last_success) {
+            ScheduleStatus::Due => true,
+            ScheduleStatus::OutsideHours { start, end } => {
+                info!(target: "user", "Group `{}` was skipped, it is scheduled to run between {}:00 and {}:00", container.group_name.bold(), start, end);
+                false
+            }
+            ScheduleStatus::IntervalNotElapsed { next_run } => {
+                info!(target: "user", "Group `{}` was skipped, it is scheduled to run again after {}", container.group_name.bold(), next_run.with_timezone(&Local).format("%Y-%m-%d %H:%M"));
+                false
+            }
+        }
+    }
+
     async fn execute_group(
         &self,
         group_span: &Span,
@@ -170,14 +560,30 @@ where
         let mut results = GroupExecutionResult {
             group_name: container.group_name.to_string(),
             has_failure: false,
+            has_allowed_failure: false,
             skip_remaining: false,
             group_report: GroupReport::new(&container.group_name),
         };
+        // Set when an action with `allowFailure` stops the group, without stopping the run.
+        let mut skip_remaining_actions = false;
+
+        let missing_tools = container.missing_tools();
+        if !missing_tools.is_empty() {
+            error!(target: "user", group = container.group_name, "Missing required tools: {}. Install them and re-run.", missing_tools.join(", ").bold());
+            results.has_failure = true;
+            results.skip_remaining = true;
+            return Ok(results);
+        }
 
         for action in &container.actions {
             group_span.pb_inc(1);
-            if results.skip_remaining {
+            if results.skip_remaining || skip_remaining_actions {
                 info!(target: "user", "Check `{}/{}` was skipped.", container.group_name.bold(), action.name());
+                self.progress.finish_action(
+                    &container.group_name,
+                    &action.name(),
+                    ProgressStatus::Skipped,
+                );
                 continue;
             }
 
@@ -185,26 +591,68 @@ where
             action_span.pb_set_message(&format!(
                 "action {} - {}",
                 action.name(),
-                action.description()
+                markdown_to_plain(&action.description())
             ));
             action_span.pb_set_style(&progress_bar_without_pos());
 
+            self.progress
+                .start_action(&container.group_name, &action.name());
+            let action_started = Instant::now();
             let action_result = action.run_action().instrument(action_span).await?;
 
-            results
-                .group_report
-                .add_action(&action_result.action_report);
+            let not_applicable = action_result.status == ActionRunStatus::NotApplicable;
+            let allowed_failure = action_result.status.is_failure() && action.allow_failure();
+            let progress_status = if not_applicable {
+                ProgressStatus::NotApplicable
+            } else if action_result.status == ActionRunStatus::SetupNotRun {
+                ProgressStatus::Skipped
+            } else if allowed_failure {
+                ProgressStatus::AllowedFailure
+            } else if action_result.status.is_failure() {
+                ProgressStatus::Failed
+            } else {
+                ProgressStatus::Succeeded
+            };
+            self.progress
+                .finish_action(&container.group_name, &action.name(), progress_status);
+            let mut action_report = action_result.action_report.clone();
+            action_report.description = action.description();
+            action_report.allowed_failure = allowed_failure;
+            action_report.duration_ms = action_started.elapsed().as_millis() as u64;
+            // Actions that don't apply to this machine are left out of reports.
+            if !not_applicable {
+                results.group_report.add_action(&action_report);
+            }
 
             // ignore the result, because reporting shouldn't cause app to crash
             report_action_output(&container.group_name, action, &action_result)
                 .await
                 .ok();
+            if allowed_failure {
+                warn!(target: "user", group = container.group_name, name = action.name(), "Failure is {}, the group doesn't fail because of it", "allowed".yellow().bold());
+            }
 
             match action_result.status {
                 ActionRunStatus::CheckSucceeded
                 | ActionRunStatus::NoCheckFixSucceeded
-                | ActionRunStatus::CheckFailedFixSucceedVerifySucceed => {}
-                ActionRunStatus::CheckFailedFixFailedStop => {
+                | ActionRunStatus::CheckFailedFixSucceedVerifySucceed
+                | ActionRunStatus::NotApplicable
+                | ActionRunStatus::CheckSkipped
+                | ActionRunStatus::SetupSucceeded
+                | ActionRunStatus::SetupNotRun => {}
+                ActionRunStatus::CheckFailedFixFailedStop | ActionRunStatus::CheckFailedStop
+                    if allowed_failure =>
+                {
+                    skip_remaining_actions = true;
+                    results.has_allowed_failure = true;
+                }
+                _ if allowed_failure => {
+                    if action.required() {
+                        skip_remaining_actions = true;
+                    }
+                    results.has_allowed_failure = true;
+                }
+                ActionRunStatus::CheckFailedFixFailedStop | ActionRunStatus::CheckFailedStop => {
                     results.skip_remaining = true;
                     results.has_failure = true;
                 }
@@ -275,11 +723,74 @@ where
                 .await
                 .ok();
         }
+        ActionRunStatus::CheckFailedFixUserDenied => {
+            error!(target: "user", group = group_name, name = action.name(), "Check failed, fix was not run because it was denied");
+        }
+        ActionRunStatus::CheckFailedFixNotInteractive => {
+            error!(target: "user", group = group_name, name = action.name(), "Check failed, fix asks for confirmation but the terminal isn't interactive so it was {}. Re-run from a terminal, or with `--auto-approve` to run it without asking", "skipped".yellow().bold());
+        }
+        ActionRunStatus::Timeout => {
+            error!(target: "user", group = group_name, name = action.name(), "A command {}, it ran longer than the action's timeoutSeconds", "timed out".red().bold());
+            print_pretty_result(group_name, &action.name(), action_result)
+                .await
+                .ok();
+        }
+        ActionRunStatus::NotApplicable => {
+            info!(target: "user", group = group_name, name = action.name(), "Check was skipped, it doesn't apply to this machine");
+        }
+        ActionRunStatus::CheckSkipped => {
+            info!(target: "user", group = group_name, name = action.name(), "Check exited with a skip exit code, fix was not run");
+        }
+        ActionRunStatus::CheckFailedStop => {
+            error!(target: "user", group = group_name, name = action.name(), "Check {} with a stop exit code, fix was not run and the group was aborted", "failed".red().bold());
+            print_pretty_result(group_name, &action.name(), action_result)
+                .await
+                .ok();
+        }
+        ActionRunStatus::SetupSucceeded => {
+            info!(target: "progress", group = group_name, name = action.name(), "Setup ran successfully");
+        }
+        ActionRunStatus::SetupFailed => {
+            error!(target: "user", group = group_name, name = action.name(), "Setup ran and {}", "failed".red().bold());
+            print_pretty_result(group_name, &action.name(), action_result)
+                .await
+                .ok();
+        }
+        ActionRunStatus::SetupNotRun => {
+            info!(target: "user", group = group_name, name = action.name(), "Setup was not run, fixes are turned off");
+        }
+    }
+
+    for change in &action_result.action_report.fix_changes {
+        match &change.diff {
+            Some(diff) => {
+                info!(target: "user", group = group_name, name = action.name(), "Fix changed {} `{}`:\n{}", change.kind, change.name, diff.trim_end())
+            }
+            None => {
+                info!(target: "user", group = group_name, name = action.name(), "Fix changed {} `{}` from {} to {}",
+                    change.kind, change.name, change.before.as_deref().unwrap_or("(unset)"), change.after.as_deref().unwrap_or("(unset)"))
+            }
+        }
+    }
+
+    for artifact in &action_result.action_report.artifacts {
+        if !artifact.exists {
+            error!(target: "user", group = group_name, name = action.name(), "Fix succeeded but didn't create `{}`", artifact.path);
+        }
+    }
+
+    let rollback = &action_result.action_report.rollback;
+    if !rollback.is_empty() {
+        if rollback.iter().all(|report| report.exit_code == Some(0)) {
+            warn!(target: "user", group = group_name, name = action.name(), "Fix failed, rollback ran and {}", "succeeded".green().bold());
+        } else {
+            error!(target: "user", group = group_name, name = action.name(), "Fix failed, rollback ran and {}, changes from the fix may be left behind", "failed".red().bold());
+        }
     }
 
     if action_result.status.is_failure() {
         if let Some(help_text) = &action.help_text() {
-            error!(target: "user", group = group_name, name = action.name(), "Action Help: {}", help_text);
+            error!(target: "user", group = group_name, name = action.name(), "Action Help: {}", render_markdown(help_text));
         }
         if let Some(help_url) = &action.help_url() {
             error!(target: "user", group = group_name, name = action.name(), "For more help, please visit {}", help_url);
@@ -313,10 +824,11 @@ async fn print_pretty_result(
     Ok(())
 }
 
-pub fn compute_group_order(
+/// Graph of the groups, with an edge from each group to every group that needs it. Nodes are
+/// added in name order. Dependencies that don't exist are left out, with a warning.
+pub fn dependency_graph(
     groups: &BTreeMap<String, DoctorGroup>,
-    desired_groups: BTreeSet<String>,
-) -> Vec<String> {
+) -> (DiGraph<&str, i32>, BTreeMap<String, NodeIndex>) {
     let mut graph = DiGraph::<&str, i32>::new();
     let mut node_graph: BTreeMap<String, NodeIndex> = BTreeMap::new();
 
@@ -335,6 +847,15 @@ pub fn compute_group_order(
         }
     }
 
+    (graph, node_graph)
+}
+
+pub fn compute_group_order(
+    groups: &BTreeMap<String, DoctorGroup>,
+    desired_groups: BTreeSet<String>,
+) -> Vec<String> {
+    let (mut graph, node_graph) = dependency_graph(groups);
+
     let start = graph.add_node("start");
 
     for name in &desired_groups {
@@ -369,10 +890,19 @@ mod tests {
     use crate::doctor::check::{
         ActionRunResult, ActionRunStatus, DoctorActionRun, MockDoctorActionRun,
     };
-    use crate::doctor::runner::{compute_group_order, GroupActionContainer, RunGroups};
+    use crate::doctor::run_history::{GroupRunStatus, MockRunHistory, NoOpRunHistory};
+    use crate::doctor::runner::{
+        compute_group_order, DoctorRunOutput, ExecutionPolicy, GroupActionContainer, PathRunResult,
+        RunGroups, SkipReason,
+    };
     use crate::doctor::tests::{group_noop, make_root_model_additional};
-    use crate::prelude::MockExecutionProvider;
+    use crate::prelude::{
+        ActionReportBuilder, ActionTaskReportBuilder, DoctorCondition, DoctorGroupSchedule,
+        GroupReport, MockExecutionProvider, NoOpProgressReporter, OutputCaptureBuilder,
+        RecordingProgressReporter, RunFingerprint,
+    };
     use anyhow::Result;
+    use chrono::{Duration, Utc};
     use std::collections::{BTreeMap, BTreeSet};
     use std::sync::Arc;
 
@@ -549,6 +1079,7 @@ mod tests {
         run.expect_help_url().return_const(None);
         run.expect_name().returning(|| "step name".to_string());
         run.expect_required().return_const(true);
+        run.expect_allow_failure().return_const(false);
         run.expect_description()
             .returning(|| "description".to_string());
         vec![run]
@@ -562,6 +1093,7 @@ mod tests {
         run.expect_name()
             .returning(|| "step name not run".to_string());
         run.expect_required().return_const(true);
+        run.expect_allow_failure().return_const(false);
         run.expect_description()
             .returning(|| "description".to_string());
         vec![run]
@@ -580,6 +1112,11 @@ mod tests {
                 exec_provider: Arc::new(MockExecutionProvider::new()),
                 exec_working_dir: Default::default(),
                 sys_path: "".to_string(),
+                schedule: None,
+                required_tools: BTreeSet::new(),
+                tools_path: "".to_string(),
+                requires: Vec::new(),
+                condition: DoctorCondition::default(),
             },
         )
     }
@@ -599,6 +1136,11 @@ mod tests {
                 "group_2".to_string(),
                 "group_3".to_string(),
             ],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
         };
 
         let exit_code = run_groups.execute().await?;
@@ -607,6 +1149,60 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_execute_allowed_failure_doesnt_fail_the_run() -> Result<()> {
+        let mut allowed = MockDoctorActionRun::new();
+        allowed.expect_run_action().returning(|| {
+            Ok(ActionRunResult::new(
+                "a_name",
+                ActionRunStatus::CheckFailedNoFixProvided,
+                None,
+                None,
+                None,
+            ))
+        });
+        allowed.expect_help_text().return_const(None);
+        allowed.expect_help_url().return_const(None);
+        allowed.expect_name().returning(|| "lint".to_string());
+        allowed.expect_required().return_const(true);
+        allowed.expect_allow_failure().return_const(true);
+        allowed
+            .expect_description()
+            .returning(|| "description".to_string());
+        let mut group_1_actions = vec![allowed];
+        group_1_actions.extend(will_not_run());
+
+        let group_actions = BTreeMap::from([
+            make_group_action("group_1", group_1_actions),
+            make_group_action("group_2", make_action_run(ActionRunStatus::CheckSucceeded)),
+        ]);
+
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec!["group_1".to_string(), "group_2".to_string()],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        assert!(result.did_succeed);
+        assert_eq!(
+            BTreeSet::from(["group_1".to_string(), "group_2".to_string()]),
+            result.succeeded_groups
+        );
+        assert_eq!(
+            BTreeSet::from(["group_1".to_string()]),
+            result.soft_failed_groups
+        );
+        assert!(result.skipped_group.is_empty());
+        assert!(result.group_reports[0].actions()[0].allowed_failure);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_execute_dep_fails_wont_run_others() -> Result<()> {
         let group_actions = BTreeMap::from([
@@ -625,6 +1221,11 @@ mod tests {
                 "group_2".to_string(),
                 "group_3".to_string(),
             ],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
         };
 
         let exit_code = run_groups.execute().await?;
@@ -633,6 +1234,166 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_execute_reports_progress() -> Result<()> {
+        let mut group_1_actions =
+            make_action_run(ActionRunStatus::CheckFailedFixSucceedVerifyFailed);
+        group_1_actions.extend(will_not_run());
+        let group_actions = BTreeMap::from([
+            make_group_action("group_1", group_1_actions),
+            make_group_action("group_2", will_not_run()),
+        ]);
+
+        let progress = Arc::new(RecordingProgressReporter::default());
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec!["group_1".to_string(), "group_2".to_string()],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: progress.clone(),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        run_groups.execute().await?;
+        assert_eq!(
+            vec![
+                "start group group_1",
+                "start action group_1/step name",
+                "finish action group_1/step name failed",
+                "finish action group_1/step name not run skipped",
+                "finish group group_1 failed",
+                "finish group group_2 skipped",
+            ],
+            progress.events()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_records_failed_group_that_skipped_others() -> Result<()> {
+        let (name_2, mut group_2) = make_group_action("group_2", will_not_run());
+        group_2.requires = vec!["group_1".to_string()];
+        let (name_3, mut group_3) = make_group_action("group_3", will_not_run());
+        group_3.requires = vec!["group_2".to_string()];
+        let group_actions = BTreeMap::from([
+            make_group_action(
+                "group_1",
+                make_action_run(ActionRunStatus::CheckFailedFixSucceedVerifyFailed),
+            ),
+            (name_2, group_2),
+            (name_3, group_3),
+            make_group_action("group_4", will_not_run()),
+        ]);
+
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec![
+                "group_1".to_string(),
+                "group_2".to_string(),
+                "group_3".to_string(),
+                "group_4".to_string(),
+            ],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        let failed = |chain: &[&str]| SkipReason::GroupFailed {
+            failed_group: "group_1".to_string(),
+            chain: chain.iter().map(|name| name.to_string()).collect(),
+        };
+        assert_eq!(
+            BTreeMap::from([
+                ("group_2".to_string(), failed(&["group_1"])),
+                ("group_3".to_string(), failed(&["group_2", "group_1"])),
+                ("group_4".to_string(), failed(&[])),
+            ]),
+            result.skip_reasons
+        );
+        assert_eq!(
+            "`group_1` failed, needed through `group_2` -> `group_1`",
+            result.skip_reasons["group_3"].to_string()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execution_policy_decides_which_groups_run_after_a_failure() -> Result<()> {
+        let run = |execution_policy: ExecutionPolicy| async move {
+            let group_2_runs = execution_policy == ExecutionPolicy::KeepGoing;
+            let depends_on_group_1 = |name: &str| {
+                let actions = if group_2_runs {
+                    make_action_run(ActionRunStatus::CheckSucceeded)
+                } else {
+                    will_not_run()
+                };
+                let (name, mut group) = make_group_action(name, actions);
+                group.requires = vec!["group_1".to_string()];
+                (name, group)
+            };
+            let group_3 = if execution_policy == ExecutionPolicy::FailFast {
+                will_not_run()
+            } else {
+                make_action_run(ActionRunStatus::CheckSucceeded)
+            };
+            let run_groups = RunGroups {
+                group_actions: BTreeMap::from([
+                    make_group_action(
+                        "group_1",
+                        make_action_run(ActionRunStatus::CheckFailedFixSucceedVerifyFailed),
+                    ),
+                    depends_on_group_1("group_2"),
+                    make_group_action("group_3", group_3),
+                ]),
+                all_paths: vec![
+                    "group_1".to_string(),
+                    "group_2".to_string(),
+                    "group_3".to_string(),
+                ],
+                run_history: Arc::<NoOpRunHistory>::default(),
+                group_log: None,
+                progress: Arc::new(NoOpProgressReporter),
+                resumed_groups: BTreeSet::new(),
+                execution_policy,
+            };
+            run_groups.execute().await
+        };
+
+        let result = run(ExecutionPolicy::FailFast).await?;
+        assert_eq!(
+            vec!["group_2", "group_3"],
+            result.skip_reasons.keys().collect::<Vec<_>>()
+        );
+
+        let result = run(ExecutionPolicy::SkipDependents).await?;
+        assert_eq!(
+            vec!["group_2"],
+            result.skip_reasons.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            BTreeSet::from(["group_3".to_string()]),
+            result.succeeded_groups
+        );
+        assert!(!result.did_succeed);
+
+        let result = run(ExecutionPolicy::KeepGoing).await?;
+        assert!(result.skip_reasons.is_empty());
+        assert_eq!(
+            BTreeSet::from(["group_2".to_string(), "group_3".to_string()]),
+            result.succeeded_groups
+        );
+        assert_eq!(BTreeSet::from(["group_1".to_string()]), result.failed_group);
+        assert!(!result.did_succeed);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_execute_branch_fails_but_other_branch_continues() -> Result<()> {
         let group_actions = BTreeMap::from([
@@ -653,6 +1414,11 @@ mod tests {
                 "group_3".to_string(),
                 "group_4".to_string(),
             ],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
         };
 
         let exit_code = run_groups.execute().await?;
@@ -660,4 +1426,277 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_execute_skips_group_ran_within_schedule_interval() -> Result<()> {
+        let (name, mut scheduled) = make_group_action("group_1", will_not_run());
+        scheduled.schedule = Some(DoctorGroupSchedule {
+            interval: Some(Duration::days(1)),
+            hours: None,
+        });
+        let group_actions = BTreeMap::from([
+            (name, scheduled),
+            make_group_action("group_2", make_action_run(ActionRunStatus::CheckSucceeded)),
+        ]);
+
+        let mut run_history = MockRunHistory::new();
+        run_history
+            .expect_last_success()
+            .returning(|_| Some(Utc::now() - Duration::hours(1)));
+        run_history
+            .expect_record_success()
+            .withf(|name, _| name == "group_2")
+            .times(1)
+            .return_const(());
+        run_history
+            .expect_record_status()
+            .withf(|name, status| name == "group_2" && *status == GroupRunStatus::Succeeded)
+            .times(1)
+            .return_const(());
+
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec!["group_1".to_string(), "group_2".to_string()],
+            run_history: Arc::new(run_history),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        assert!(result.did_succeed);
+        assert_eq!(
+            BTreeSet::from(["group_1".to_string()]),
+            result.skipped_group
+        );
+        assert_eq!(
+            BTreeSet::from(["group_2".to_string()]),
+            result.succeeded_groups
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_skips_resumed_group_unless_files_changed() -> Result<()> {
+        let mut unchanged = will_not_run();
+        unchanged[0].expect_files_changed().returning(|| Ok(false));
+        let mut changed = make_action_run(ActionRunStatus::CheckSucceeded);
+        changed[0].expect_files_changed().returning(|| Ok(true));
+        let group_actions = BTreeMap::from([
+            make_group_action("group_1", unchanged),
+            make_group_action("group_2", changed),
+            make_group_action("group_3", make_action_run(ActionRunStatus::CheckSucceeded)),
+        ]);
+
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec![
+                "group_1".to_string(),
+                "group_2".to_string(),
+                "group_3".to_string(),
+            ],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::from(["group_1".to_string(), "group_2".to_string()]),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        assert!(result.did_succeed);
+        assert_eq!(
+            Some(&SkipReason::Resumed),
+            result.skip_reasons.get("group_1")
+        );
+        assert_eq!(
+            BTreeSet::from(["group_2".to_string(), "group_3".to_string()]),
+            result.succeeded_groups
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_skips_group_that_does_not_apply() -> Result<()> {
+        let (name, mut brew) = make_group_action("brew", will_not_run());
+        brew.condition.only_if = Some("test -f Brewfile".to_string());
+        let mut exec_provider = MockExecutionProvider::new();
+        exec_provider
+            .expect_run_command()
+            .times(1)
+            .withf(|opts| opts.args == ["test -f Brewfile"])
+            .returning(|_| {
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(1))
+                    .build()
+                    .unwrap())
+            });
+        brew.exec_provider = Arc::new(exec_provider);
+        let (rails_name, mut rails) =
+            make_group_action("rails", make_action_run(ActionRunStatus::CheckSucceeded));
+        rails.requires = vec!["brew".to_string()];
+        let group_actions = BTreeMap::from([
+            (name, brew),
+            (rails_name, rails),
+            make_group_action("node", make_action_run(ActionRunStatus::NotApplicable)),
+        ]);
+
+        let progress = Arc::new(RecordingProgressReporter::default());
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec!["brew".to_string(), "rails".to_string(), "node".to_string()],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: progress.clone(),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        assert!(result.did_succeed);
+        assert_eq!(BTreeSet::from(["brew".to_string()]), result.skipped_group);
+        assert_eq!(
+            Some(&SkipReason::NotApplicable),
+            result.skip_reasons.get("brew")
+        );
+        assert_eq!(
+            BTreeSet::from(["node".to_string(), "rails".to_string()]),
+            result.succeeded_groups
+        );
+        let node_report = serde_json::to_value(&result.group_reports[1]).unwrap();
+        assert_eq!(serde_json::json!([]), node_report["actions"]);
+        assert!(progress
+            .events()
+            .contains(&"finish group brew not applicable".to_string()));
+        assert!(progress
+            .events()
+            .contains(&"finish action node/step name not applicable".to_string()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_execute_fails_group_with_missing_tool() -> Result<()> {
+        let (name, mut missing_tool) = make_group_action("group_1", will_not_run());
+        missing_tool.required_tools = BTreeSet::from(["scope-missing-tool".to_string()]);
+        let group_actions = BTreeMap::from([(name, missing_tool)]);
+
+        let run_groups = RunGroups {
+            group_actions,
+            all_paths: vec!["group_1".to_string()],
+            run_history: Arc::<NoOpRunHistory>::default(),
+            group_log: None,
+            progress: Arc::new(NoOpProgressReporter),
+            resumed_groups: BTreeSet::new(),
+            execution_policy: ExecutionPolicy::default(),
+        };
+
+        let result = run_groups.execute().await?;
+        assert!(!result.did_succeed);
+        assert_eq!(BTreeSet::from(["group_1".to_string()]), result.failed_group);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slowest_actions() {
+        let action = |name: &str, duration_ms: u64| {
+            ActionReportBuilder::default()
+                .action_name(name)
+                .duration_ms(duration_ms)
+                .build()
+                .unwrap()
+        };
+        let mut setup = GroupReport::new("setup");
+        setup.add_action(&action("brew", 200));
+        setup.add_action(&action("node", 4500));
+        let mut lint = GroupReport::new("lint");
+        lint.add_action(&action("eslint", 200));
+
+        let result = PathRunResult {
+            did_succeed: true,
+            succeeded_groups: BTreeSet::from(["lint".to_string(), "setup".to_string()]),
+            failed_group: BTreeSet::new(),
+            skipped_group: BTreeSet::new(),
+            soft_failed_groups: BTreeSet::new(),
+            skip_reasons: BTreeMap::new(),
+            group_reports: vec![setup, lint],
+            duration_ms: 5000,
+        };
+        assert_eq!(
+            vec![
+                ("setup/node".to_string(), 4500),
+                ("setup/brew".to_string(), 200),
+                ("lint/eslint".to_string(), 200),
+            ],
+            result.slowest_actions()
+        );
+    }
+
+    #[test]
+    fn test_run_output_matches_schema() {
+        let mut group_report = GroupReport::new("setup");
+        group_report.add_action(
+            &ActionReportBuilder::default()
+                .action_name("install")
+                .check(vec![ActionTaskReportBuilder::default()
+                    .command("bin/check")
+                    .output(Some("missing".to_string()))
+                    .exit_code(Some(1))
+                    .build()
+                    .unwrap()])
+                .build()
+                .unwrap(),
+        );
+        group_report.add_additional_details("env", "env", "PATH=/bin");
+        group_report.set_log_path("/tmp/scope/run/setup.log");
+
+        let output = DoctorRunOutput::new(
+            "run-1",
+            &RunFingerprint::new(&[]),
+            PathRunResult {
+                did_succeed: false,
+                succeeded_groups: BTreeSet::new(),
+                failed_group: BTreeSet::from(["setup".to_string()]),
+                skipped_group: BTreeSet::from(["lint".to_string()]),
+                skip_reasons: BTreeMap::from([(
+                    "lint".to_string(),
+                    SkipReason::GroupFailed {
+                        failed_group: "setup".to_string(),
+                        chain: vec!["setup".to_string()],
+                    },
+                )]),
+                soft_failed_groups: BTreeSet::new(),
+                group_reports: vec![group_report],
+                duration_ms: 1500,
+            },
+        );
+        let value = serde_json::to_value(&output).unwrap();
+        assert_eq!("v1", value["version"]);
+        assert_eq!("run-1", value["runId"]);
+        assert_eq!(std::env::consts::OS, value["fingerprint"]["os"]);
+        assert_eq!(serde_json::json!(["setup"]), value["failedGroups"]);
+        assert_eq!(
+            serde_json::json!({
+                "reason": "groupFailed",
+                "failedGroup": "setup",
+                "chain": ["setup"],
+            }),
+            value["skipReasons"]["lint"]
+        );
+        assert_eq!(1500, value["durationMs"]);
+        assert_eq!("setup", value["groups"][0]["name"]);
+        assert_eq!(
+            "bin/check",
+            value["groups"][0]["actions"][0]["check"][0]["command"]
+        );
+
+        let schema =
+            crate::models::make_schema_generator().into_root_schema_for::<DoctorRunOutput>();
+        let schema = serde_json::to_value(&schema).unwrap();
+        let compiled = jsonschema::JSONSchema::compile(&schema).unwrap();
+        assert!(compiled.is_valid(&value));
+    }
 }
@@ -0,0 +1,261 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// A temporary copy of some paths from the working directory. Fixes with `sandboxPaths` run
+/// inside the copy, and the results are only copied back to the working directory with
+/// [`FixSandbox::commit`]. Dropping the sandbox without committing discards the changes.
+#[derive(Debug)]
+pub struct FixSandbox {
+    dir: PathBuf,
+    working_dir: PathBuf,
+    paths: Vec<String>,
+}
+
+impl FixSandbox {
+    pub fn create(working_dir: &Path, paths: &[String]) -> io::Result<Self> {
+        let id = nanoid::nanoid!(10, &nanoid::alphabet::SAFE);
+        let dir = std::env::temp_dir().join(format!("scope-fix-{}", id));
+        fs::create_dir_all(&dir)?;
+        debug!("Created fix sandbox in {}", dir.display());
+
+        let sandbox = Self {
+            dir,
+            working_dir: working_dir.to_path_buf(),
+            paths: paths.to_vec(),
+        };
+
+        for path in &sandbox.paths {
+            let source = sandbox.working_dir.join(path);
+            if path_exists(&source) {
+                copy_recursive(&source, &sandbox.dir.join(path))?;
+            }
+        }
+
+        Ok(sandbox)
+    }
+
+    /// Directory the fix should run in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Replace the sandboxed paths in the working directory with the sandbox's copy. Every path
+    /// is copied next to the one it replaces first, so a failed copy leaves the working directory
+    /// as it was, then each one is renamed into place.
+    pub fn commit(self) -> io::Result<()> {
+        let id = nanoid::nanoid!(10, &nanoid::alphabet::SAFE);
+        let mut staged: Vec<(PathBuf, Option<PathBuf>)> = Vec::new();
+        for path in &self.paths {
+            let sandboxed = self.dir.join(path);
+            let original = self.working_dir.join(path);
+            if !path_exists(&sandboxed) {
+                staged.push((original, None));
+                continue;
+            }
+            let staging = sibling(&original, "staged", &id);
+            if let Err(e) = copy_recursive(&sandboxed, &staging) {
+                remove_path(&staging).ok();
+                for staging in staged.iter().filter_map(|(_, staging)| staging.as_ref()) {
+                    remove_path(staging).ok();
+                }
+                return Err(e);
+            }
+            staged.push((original, Some(staging)));
+        }
+
+        for (original, staging) in staged {
+            let replaced = sibling(&original, "replaced", &id);
+            if path_exists(&original) {
+                fs::rename(&original, &replaced)?;
+            }
+            if let Some(staging) = staging {
+                if let Err(e) = fs::rename(&staging, &original) {
+                    if path_exists(&replaced) {
+                        fs::rename(&replaced, &original).ok();
+                    }
+                    remove_path(&staging).ok();
+                    return Err(e);
+                }
+            }
+            remove_path(&replaced)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for FixSandbox {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            warn!(
+                "Unable to remove fix sandbox {}: {:?}",
+                self.dir.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Whether `path` exists, without following it when it's a symlink.
+fn path_exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// A path next to `path`, in the same directory so it can be renamed to `path`.
+fn sibling(path: &Path, kind: &str, id: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".scope-{}-{}", kind, id));
+    path.with_file_name(name)
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+    }
+}
+
+fn copy_recursive(source: &Path, dest: &Path) -> io::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file_type = fs::symlink_metadata(source)?.file_type();
+    if file_type.is_symlink() {
+        copy_symlink(source, dest)?;
+    } else if file_type.is_dir() {
+        fs::create_dir_all(dest)?;
+        for entry in fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &dest.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(source, dest)?;
+    }
+
+    Ok(())
+}
+
+/// Copy the symlink at `source` itself, pointing at the same target, instead of what it points
+/// at. Targets outside of the sandboxed paths are never copied or changed.
+#[cfg(unix)]
+fn copy_symlink(source: &Path, dest: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, dest)
+}
+
+#[cfg(windows)]
+fn copy_symlink(source: &Path, dest: &Path) -> io::Result<()> {
+    let target = fs::read_link(source)?;
+    if fs::metadata(source).is_ok_and(|meta| meta.is_dir()) {
+        std::os::windows::fs::symlink_dir(target, dest)
+    } else {
+        std::os::windows::fs::symlink_file(target, dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixSandbox;
+    use assert_fs::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn test_commit_copies_changes_back() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir
+            .child("config/app.toml")
+            .write_str("old")
+            .unwrap();
+        working_dir
+            .child("config/remove.toml")
+            .write_str("x")
+            .unwrap();
+
+        let sandbox = FixSandbox::create(working_dir.path(), &["config".to_string()]).unwrap();
+        fs::write(sandbox.dir().join("config/app.toml"), "new").unwrap();
+        fs::remove_file(sandbox.dir().join("config/remove.toml")).unwrap();
+        let sandbox_dir = sandbox.dir().to_path_buf();
+        sandbox.commit().unwrap();
+
+        working_dir.child("config/app.toml").assert("new");
+        working_dir
+            .child("config/remove.toml")
+            .assert(predicates::path::missing());
+        assert!(!sandbox_dir.exists());
+    }
+
+    #[test]
+    fn test_drop_discards_changes() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir.child("app.toml").write_str("old").unwrap();
+
+        let sandbox = FixSandbox::create(working_dir.path(), &["app.toml".to_string()]).unwrap();
+        fs::write(sandbox.dir().join("app.toml"), "partial").unwrap();
+        drop(sandbox);
+
+        working_dir.child("app.toml").assert("old");
+    }
+
+    #[test]
+    fn test_commit_leaves_no_staged_copies() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir
+            .child("config/app.toml")
+            .write_str("old")
+            .unwrap();
+
+        let paths = ["config".to_string(), "new.toml".to_string()];
+        let sandbox = FixSandbox::create(working_dir.path(), &paths).unwrap();
+        fs::write(sandbox.dir().join("new.toml"), "new").unwrap();
+        sandbox.commit().unwrap();
+
+        let mut names = fs::read_dir(working_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(vec!["config", "new.toml"], names);
+        working_dir.child("config/app.toml").assert("old");
+        working_dir.child("new.toml").assert("new");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_copied_as_symlinks() {
+        let outside = assert_fs::TempDir::new().unwrap();
+        outside.child("secrets.toml").write_str("secret").unwrap();
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir
+            .child("config/app.toml")
+            .write_str("old")
+            .unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("secrets.toml"),
+            working_dir.path().join("config/secrets.toml"),
+        )
+        .unwrap();
+
+        let sandbox = FixSandbox::create(working_dir.path(), &["config".to_string()]).unwrap();
+        let sandboxed_link = sandbox.dir().join("config/secrets.toml");
+        assert!(fs::symlink_metadata(&sandboxed_link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        sandbox.commit().unwrap();
+
+        let link = working_dir.path().join("config/secrets.toml");
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            outside.path().join("secrets.toml"),
+            fs::read_link(&link).unwrap()
+        );
+        outside.child("secrets.toml").assert("secret");
+    }
+}
@@ -0,0 +1,299 @@
+use crate::prelude::{CaptureOpts, ExecutionProvider, FixChange, OutputDestination};
+use crate::shared::prelude::DoctorGroupActionSnapshot;
+use similar::TextDiff;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// State of the machine, keyed by the kind of value (`env`, `command`, `file`) and its name.
+pub type Snapshot = BTreeMap<(String, String), String>;
+
+/// Text of the snapshot files, keyed by the path from the snapshot.
+pub type FileContents = BTreeMap<String, String>;
+
+/// Files larger than this aren't diffed, only their checksum is compared.
+const MAX_DIFF_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Capture the values listed in `spec`. Values that can't be read are left out, so they show
+/// up in the diff if they appear or disappear.
+pub async fn take_snapshot(
+    spec: &DoctorGroupActionSnapshot,
+    exec_runner: &dyn ExecutionProvider,
+    working_dir: &Path,
+    path: &str,
+) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+
+    if !spec.env.is_empty() {
+        // A fix can't change scope's own environment, but it can change what a new shell sees.
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+        let output = run_for_stdout(
+            exec_runner,
+            working_dir,
+            path,
+            &format!("{} -lc printenv", shell),
+        )
+        .await
+        .unwrap_or_default();
+        let env: BTreeMap<_, _> = output
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect();
+        for name in &spec.env {
+            if let Some(value) = env.get(name.as_str()) {
+                snapshot.insert(("env".to_string(), name.clone()), value.to_string());
+            }
+        }
+    }
+
+    for command in &spec.commands {
+        if let Some(output) = run_for_stdout(exec_runner, working_dir, path, command).await {
+            snapshot.insert(("command".to_string(), command.clone()), output);
+        }
+    }
+
+    for file in &spec.files {
+        if let Ok(digest) = sha256::try_async_digest(working_dir.join(file)).await {
+            snapshot.insert(("file".to_string(), file.clone()), digest);
+        }
+    }
+
+    snapshot
+}
+
+/// Read the snapshot `files` that are text, so a diff can be shown when a fix changes them.
+/// Missing, binary, and large files are left out.
+pub async fn read_files(files: &[String], working_dir: &Path) -> FileContents {
+    let mut contents = FileContents::new();
+    for file in files {
+        let path = working_dir.join(file);
+        let small_enough = tokio::fs::metadata(&path)
+            .await
+            .map(|meta| meta.len() <= MAX_DIFF_FILE_SIZE)
+            .unwrap_or(false);
+        if !small_enough {
+            continue;
+        }
+        if let Ok(text) = tokio::fs::read_to_string(&path).await {
+            contents.insert(file.clone(), text);
+        }
+    }
+
+    contents
+}
+
+async fn run_for_stdout(
+    exec_runner: &dyn ExecutionProvider,
+    working_dir: &Path,
+    path: &str,
+    command: &str,
+) -> Option<String> {
+    let args = vec![command.to_string()];
+    let capture = exec_runner
+        .run_command(CaptureOpts {
+            working_dir,
+            args: &args,
+            output_dest: OutputDestination::Null,
+            path,
+            env_vars: Default::default(),
+            timeout: None,
+            progress: None,
+        })
+        .await
+        .ok()?;
+
+    match capture.exit_code {
+        Some(0) => Some(capture.get_stdout().trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Every value that is different between the two snapshots.
+pub fn diff_snapshots(before: &Snapshot, after: &Snapshot) -> Vec<FixChange> {
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+    keys.into_iter()
+        .filter(|key| before.get(*key) != after.get(*key))
+        .map(|(kind, name)| FixChange {
+            kind: kind.clone(),
+            name: name.clone(),
+            before: before.get(&(kind.clone(), name.clone())).cloned(),
+            after: after.get(&(kind.clone(), name.clone())).cloned(),
+            diff: None,
+        })
+        .collect()
+}
+
+/// Add a unified diff to each changed file whose text was read before or after the fix. A file
+/// that didn't exist on one side is diffed against an empty file.
+pub fn add_file_diffs(changes: &mut [FixChange], before: &FileContents, after: &FileContents) {
+    for change in changes.iter_mut().filter(|change| change.kind == "file") {
+        let (old, new) = (before.get(&change.name), after.get(&change.name));
+        if old.is_none() && new.is_none() {
+            continue;
+        }
+
+        let old_header = match old {
+            Some(_) => format!("a/{}", change.name),
+            None => "/dev/null".to_string(),
+        };
+        let new_header = match new {
+            Some(_) => format!("b/{}", change.name),
+            None => "/dev/null".to_string(),
+        };
+        let diff = TextDiff::from_lines(
+            old.map(String::as_str).unwrap_or_default(),
+            new.map(String::as_str).unwrap_or_default(),
+        )
+        .unified_diff()
+        .header(&old_header, &new_header)
+        .to_string();
+        change.diff = Some(diff);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_file_diffs, diff_snapshots, read_files, take_snapshot, FileContents, Snapshot,
+    };
+    use crate::prelude::{FixChange, MockExecutionProvider, OutputCaptureBuilder};
+    use crate::shared::prelude::DoctorGroupActionSnapshot;
+    use assert_fs::prelude::*;
+
+    fn key(kind: &str, name: &str) -> (String, String) {
+        (kind.to_string(), name.to_string())
+    }
+
+    #[test]
+    fn test_diff_only_includes_changes() {
+        let before = Snapshot::from([
+            (key("command", "node --version"), "v18.0.0".to_string()),
+            (key("file", "package.json"), "abc".to_string()),
+            (key("env", "REMOVED"), "1".to_string()),
+        ]);
+        let after = Snapshot::from([
+            (key("command", "node --version"), "v20.1.0".to_string()),
+            (key("file", "package.json"), "abc".to_string()),
+            (key("env", "ADDED"), "1".to_string()),
+        ]);
+
+        assert_eq!(
+            vec![
+                FixChange {
+                    kind: "command".to_string(),
+                    name: "node --version".to_string(),
+                    before: Some("v18.0.0".to_string()),
+                    after: Some("v20.1.0".to_string()),
+                    diff: None,
+                },
+                FixChange {
+                    kind: "env".to_string(),
+                    name: "ADDED".to_string(),
+                    before: None,
+                    after: Some("1".to_string()),
+                    diff: None,
+                },
+                FixChange {
+                    kind: "env".to_string(),
+                    name: "REMOVED".to_string(),
+                    before: Some("1".to_string()),
+                    after: None,
+                    diff: None,
+                },
+            ],
+            diff_snapshots(&before, &after)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_snapshot() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir.child("package.json").write_str("{}").unwrap();
+
+        let mut exec_runner = MockExecutionProvider::new();
+        exec_runner
+            .expect_run_command()
+            .withf(|opts| opts.args[0] == "node --version")
+            .returning(|_| {
+                Ok(OutputCaptureBuilder::default()
+                    .exit_code(Some(0))
+                    .stdout(vec![(chrono::Utc::now(), "v20.1.0".to_string())])
+                    .build()
+                    .unwrap())
+            });
+
+        let spec = DoctorGroupActionSnapshot {
+            env: Vec::new(),
+            commands: vec!["node --version".to_string()],
+            files: vec!["package.json".to_string(), "missing.json".to_string()],
+        };
+        let snapshot = take_snapshot(&spec, &exec_runner, working_dir.path(), "").await;
+
+        assert_eq!(2, snapshot.len());
+        assert_eq!(
+            Some(&"v20.1.0".to_string()),
+            snapshot.get(&key("command", "node --version"))
+        );
+        assert!(snapshot.contains_key(&key("file", "package.json")));
+    }
+
+    #[tokio::test]
+    async fn test_file_diff_shows_changed_lines() {
+        let working_dir = assert_fs::TempDir::new().unwrap();
+        working_dir
+            .child(".tool-versions")
+            .write_str("ruby 3.2.0\nnode 18.0.0\n")
+            .unwrap();
+        let files = vec![".tool-versions".to_string(), "new.txt".to_string()];
+        let before = read_files(&files, working_dir.path()).await;
+
+        working_dir
+            .child(".tool-versions")
+            .write_str("ruby 3.2.0\nnode 20.1.0\n")
+            .unwrap();
+        working_dir.child("new.txt").write_str("hello\n").unwrap();
+        let after = read_files(&files, working_dir.path()).await;
+
+        let mut changes = vec![
+            FixChange {
+                kind: "file".to_string(),
+                name: ".tool-versions".to_string(),
+                before: Some("abc".to_string()),
+                after: Some("def".to_string()),
+                diff: None,
+            },
+            FixChange {
+                kind: "file".to_string(),
+                name: "new.txt".to_string(),
+                before: None,
+                after: Some("123".to_string()),
+                diff: None,
+            },
+        ];
+        add_file_diffs(&mut changes, &before, &after);
+
+        assert_eq!(
+            Some(
+                "--- a/.tool-versions\n+++ b/.tool-versions\n@@ -1,2 +1,2 @@\n ruby 3.2.0\n-node 18.0.0\n+node 20.1.0\n"
+            ),
+            changes[0].diff.as_deref()
+        );
+        assert_eq!(
+            Some("--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1 @@\n+hello\n"),
+            changes[1].diff.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_file_diff_skips_unread_files() {
+        let mut changes = vec![FixChange {
+            kind: "file".to_string(),
+            name: "image.png".to_string(),
+            before: Some("abc".to_string()),
+            after: Some("def".to_string()),
+            diff: None,
+        }];
+        add_file_diffs(&mut changes, &FileContents::new(), &FileContents::new());
+
+        assert_eq!(None, changes[0].diff);
+    }
+}
@@ -0,0 +1,453 @@
+use crate::prelude::{
+    CaptureOpts, DefaultExecutionProvider, DefaultUnstructuredReportBuilder, FoundConfig,
+    HelpMetadata, KnownError, KnownErrorMatcher, OutputCapture, OutputDestination, PromptAnswer,
+    RegexKnownErrorMatcher, ReportRenderer, UnstructuredReportBuilder, UserInteraction,
+};
+use anyhow::Result;
+use std::env;
+use std::sync::Arc;
+use tracing::{debug, error, info, instrument, warn};
+
+/// Controls how [`run`] treats the command it intercepts.
+#[derive(Debug, Clone)]
+pub struct InterceptOptions {
+    /// Exit codes, in addition to `0`, that are considered a success. Successful commands aren't
+    /// analyzed.
+    pub successful_exit: Vec<i32>,
+    /// When the command fails, print where the report would be sent and what it would contain,
+    /// without asking or sending it.
+    pub dry_run: bool,
+    /// Where the output of the command is written while it runs.
+    pub output_dest: OutputDestination,
+}
+
+impl Default for InterceptOptions {
+    fn default() -> Self {
+        Self {
+            successful_exit: Vec::new(),
+            dry_run: false,
+            output_dest: OutputDestination::StandardOut,
+        }
+    }
+}
+
+/// What happened when a command was intercepted.
+#[derive(Debug)]
+pub struct InterceptOutcome {
+    /// Exit code of the command, `-1` when it was stopped by a signal. `0` when the `ScopeConfig`'s
+    /// `ignoreExitCodes` counts the command's exit code as a success.
+    pub exit_code: i32,
+    /// Known errors found in the output, in priority order. Empty when the command succeeded or
+    /// wasn't analyzed.
+    pub known_errors: Vec<KnownError>,
+    /// Captured output of the command.
+    pub capture: Arc<OutputCapture>,
+}
+
+/// Run `command` and, when it fails, look for known errors in its output and offer to upload a
+/// bug report to every configured location. `interaction` is asked before anything is uploaded.
+///
+/// This is the pipeline behind `scope-intercept`, callers are expected to have already configured
+/// colors and redactions from `found_config`.
+#[instrument("scope intercept", skip_all, fields(run_id = %found_config.run_id))]
+pub async fn run(
+    found_config: &FoundConfig,
+    command: &[String],
+    options: &InterceptOptions,
+    interaction: &impl UserInteraction,
+) -> Result<InterceptOutcome> {
+    let path = env::var("PATH").unwrap_or_default();
+
+    let capture = Arc::new(
+        OutputCapture::capture_output(CaptureOpts {
+            working_dir: &found_config.working_dir,
+            args: command,
+            output_dest: options.output_dest.clone(),
+            path: &path,
+            env_vars: Default::default(),
+            timeout: None,
+            progress: None,
+        })
+        .await?,
+    );
+
+    let mut outcome = InterceptOutcome {
+        exit_code: capture.exit_code.unwrap_or(-1),
+        known_errors: Vec::new(),
+        capture: capture.clone(),
+    };
+
+    let exit_code = outcome.exit_code;
+    if exit_code == 0 || options.successful_exit.contains(&exit_code) {
+        return Ok(outcome);
+    }
+
+    let intercept_filter = found_config.intercept_filter();
+    if intercept_filter.is_ignored_exit_code(command, exit_code) {
+        debug!("Exit code {} is ignored for this command", exit_code);
+        outcome.exit_code = 0;
+        return Ok(outcome);
+    }
+    if !intercept_filter.should_analyze(command) {
+        debug!("Command is not configured for interception, skipping analysis");
+        return Ok(outcome);
+    }
+
+    error!(target: "user", "Command failed, checking for a known error");
+
+    let command_output = capture.generate_output();
+
+    let matcher = RegexKnownErrorMatcher;
+    let mut known_errors: Vec<_> = found_config.known_error.values().collect();
+    known_errors.sort_by(|a, b| a.cmp_priority(b));
+    for known_error in known_errors {
+        debug!("Checking known error {}", known_error.name());
+        if matcher.is_match(known_error, &command_output).await {
+            info!(target: "always", "Known error '{}' found", known_error.name());
+            info!(target: "always", "\t==> {}", known_error.help_text);
+            outcome.known_errors.push(known_error.clone());
+        }
+    }
+
+    if found_config.report_upload.is_empty() {
+        return Ok(outcome);
+    }
+
+    let ans = if options.dry_run {
+        PromptAnswer::Approved
+    } else {
+        interaction.confirm(
+            "Do you want to upload a bug report?",
+            Some("This will allow you to share the error with other engineers for support."),
+        )
+    };
+
+    if ans == PromptAnswer::NotInteractive {
+        warn!(target: "user", "Not asking to upload a bug report because the terminal isn't interactive");
+    }
+
+    if ans == PromptAnswer::Approved {
+        upload_reports(found_config, command, capture, options.dry_run).await;
+    }
+
+    Ok(outcome)
+}
+
+async fn upload_reports(
+    found_config: &FoundConfig,
+    command: &[String],
+    capture: Arc<OutputCapture>,
+    dry_run: bool,
+) {
+    let entrypoint = command.join(" ");
+    let exec_runner = Arc::new(DefaultExecutionProvider::default());
+
+    let builder = DefaultUnstructuredReportBuilder::new(&entrypoint, capture);
+
+    for location in found_config.report_upload.values() {
+        let mut builder = builder.clone();
+        builder
+            .run_and_append_additional_data(
+                found_config,
+                exec_runner.clone(),
+                &location.additional_data,
+            )
+            .await
+            .ok();
+        if let Err(e) = builder.collect_attachments(found_config, location).await {
+            warn!(target: "user", "Unable to collect the report's attachments: {}", e);
+        }
+
+        let report = builder.render(location);
+
+        match report {
+            Err(e) => warn!(target: "user", "Unable to render report: {}", e),
+            Ok(report) if dry_run => report.print_dry_run().await,
+            Ok(report) => {
+                if let Err(e) = report.distribute().await {
+                    warn!(target: "user", "Unable to upload report: {}", e);
+                }
+            }
+        }
+    }
+}
+
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::{Args, Subcommand, ValueEnum};
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct InterceptArgs {
+        #[cfg_attr(feature = "cli", clap(subcommand))]
+        pub command: InterceptCommands,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Subcommand))]
+    pub enum InterceptCommands {
+        /// Print shell functions that run the commands in `intercept.commands` through
+        /// `scope-intercept`, to load from the shell's startup file
+        Install(InterceptInstallArgs),
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct InterceptInstallArgs {
+        /// Shell to print the functions for
+        #[cfg_attr(feature = "cli", arg(long, value_enum))]
+        pub shell: HookShell,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "cli", derive(ValueEnum))]
+    pub enum HookShell {
+        Bash,
+        Zsh,
+        Fish,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{HookShell, InterceptArgs, InterceptCommands, InterceptInstallArgs};
+    use crate::report_stdout;
+    use crate::shared::prelude::FoundConfig;
+    use anyhow::Result;
+    use tracing::error;
+
+    pub async fn intercept_root(found_config: &FoundConfig, args: &InterceptArgs) -> Result<i32> {
+        match &args.command {
+            InterceptCommands::Install(args) => intercept_install(found_config, args).await,
+        }
+    }
+
+    async fn intercept_install(
+        found_config: &FoundConfig,
+        args: &InterceptInstallArgs,
+    ) -> Result<i32> {
+        let commands = found_config.intercept_filter().commands;
+        if commands.is_empty() {
+            error!(target: "user", "There are no commands to intercept, list them in `intercept.commands` of a ScopeConfig");
+            return Ok(1);
+        }
+
+        report_stdout!("{}", shell_hooks(args.shell, &commands));
+        Ok(0)
+    }
+
+    /// Functions for `shell` that replace each of `commands` with a call to `scope-intercept`.
+    /// They're only defined when `scope-intercept` is on the `PATH`, and `command <name>` still
+    /// runs a command without it.
+    pub(crate) fn shell_hooks(shell: HookShell, commands: &[String]) -> String {
+        let mut hooks = vec![
+            "# Added by `scope intercept install`, runs these commands through scope-intercept."
+                .to_string(),
+        ];
+        match shell {
+            // `function name` keeps an alias with the same name from being expanded.
+            HookShell::Bash | HookShell::Zsh => {
+                hooks.push("if command -v scope-intercept >/dev/null 2>&1; then".to_string());
+                for command in commands {
+                    hooks.push(format!(
+                        "  function {} {{ command scope-intercept -- {} \"$@\"; }}",
+                        command, command
+                    ));
+                }
+                hooks.push("fi".to_string());
+            }
+            HookShell::Fish => {
+                hooks.push("if command -q scope-intercept".to_string());
+                for command in commands {
+                    hooks.push(format!("    function {} --wraps {}", command, command));
+                    hooks.push(format!(
+                        "        command scope-intercept -- {} $argv",
+                        command
+                    ));
+                    hooks.push("    end".to_string());
+                }
+                hooks.push("end".to_string());
+            }
+        }
+
+        hooks.join("\n")
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_shell_hooks() {
+            let commands = vec!["bundle".to_string(), "npm".to_string()];
+
+            assert_eq!(
+                [
+                    "# Added by `scope intercept install`, runs these commands through scope-intercept.",
+                    "if command -v scope-intercept >/dev/null 2>&1; then",
+                    "  function bundle { command scope-intercept -- bundle \"$@\"; }",
+                    "  function npm { command scope-intercept -- npm \"$@\"; }",
+                    "fi",
+                ]
+                .join("\n"),
+                shell_hooks(HookShell::Zsh, &commands)
+            );
+            assert_eq!(
+                shell_hooks(HookShell::Zsh, &commands),
+                shell_hooks(HookShell::Bash, &commands)
+            );
+            assert_eq!(
+                [
+                    "# Added by `scope intercept install`, runs these commands through scope-intercept.",
+                    "if command -q scope-intercept",
+                    "    function bundle --wraps bundle",
+                    "        command scope-intercept -- bundle $argv",
+                    "    end",
+                    "    function npm --wraps npm",
+                    "        command scope-intercept -- npm $argv",
+                    "    end",
+                    "end",
+                ]
+                .join("\n"),
+                shell_hooks(HookShell::Fish, &commands)
+            );
+        }
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::{HookShell, InterceptArgs, InterceptCommands, InterceptInstallArgs};
+    pub use super::commands::intercept_root;
+    pub use super::{InterceptOptions, InterceptOutcome};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::prelude::{
+        InterceptFilter, KnownErrorSeverity, MockUserInteraction, ScopeConfig,
+        ScopeConfigIgnoreExitCodesSpec, ScopeConfigInterceptSpec,
+    };
+    use regex::Regex;
+    use std::path::PathBuf;
+
+    fn found_config_with_known_error() -> FoundConfig {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        let known_error = KnownError {
+            full_name: "ScopeKnownError/disk-full".to_string(),
+            metadata: ModelMetadata::new("disk-full"),
+            pattern: "no space left".to_string(),
+            regex: Regex::new("no space left").unwrap(),
+            help_text: "Free up some disk space".to_string(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines: 0,
+            fix_ref: None,
+            fix: None,
+        };
+        found_config
+            .known_error
+            .insert(known_error.name().to_string(), known_error);
+        found_config
+    }
+
+    fn shell(script: &str) -> Vec<String> {
+        vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+    }
+
+    fn options() -> InterceptOptions {
+        InterceptOptions {
+            output_dest: OutputDestination::Null,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_command_reports_known_errors() {
+        let found_config = found_config_with_known_error();
+        let interaction = MockUserInteraction::new();
+
+        let outcome = run(
+            &found_config,
+            &shell("echo 'no space left on device' >&2; exit 3"),
+            &options(),
+            &interaction,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(3, outcome.exit_code);
+        assert_eq!(
+            vec!["disk-full"],
+            outcome
+                .known_errors
+                .iter()
+                .map(|ke| ke.name())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_successful_exit_skips_analysis() {
+        let found_config = found_config_with_known_error();
+        let interaction = MockUserInteraction::new();
+        let options = InterceptOptions {
+            successful_exit: vec![3],
+            ..options()
+        };
+
+        let outcome = run(
+            &found_config,
+            &shell("echo 'no space left on device'; exit 3"),
+            &options,
+            &interaction,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(3, outcome.exit_code);
+        assert!(outcome.known_errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_ignored_exit_code_counts_as_success() {
+        let mut found_config = found_config_with_known_error();
+        found_config.scope_config = Some(ScopeConfig {
+            metadata: ModelMetadata::new("settings"),
+            full_name: "ScopeConfig/settings".to_string(),
+            color: None,
+            cache_dir: None,
+            intercept: InterceptFilter::try_from(ScopeConfigInterceptSpec {
+                ignore_exit_codes: vec![ScopeConfigIgnoreExitCodesSpec {
+                    command: "sh".to_string(),
+                    exit_codes: vec![3],
+                }],
+                ..Default::default()
+            })
+            .unwrap(),
+            default_args: Default::default(),
+        });
+        let interaction = MockUserInteraction::new();
+
+        let outcome = run(
+            &found_config,
+            &shell("echo 'no space left on device'; exit 3"),
+            &options(),
+            &interaction,
+        )
+        .await
+        .unwrap();
+        assert_eq!(0, outcome.exit_code);
+        assert!(outcome.known_errors.is_empty());
+
+        let outcome = run(
+            &found_config,
+            &shell("echo 'no space left on device'; exit 4"),
+            &options(),
+            &interaction,
+        )
+        .await
+        .unwrap();
+        assert_eq!(4, outcome.exit_code);
+    }
+}
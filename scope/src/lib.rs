@@ -1,17 +1,31 @@
 pub mod analyze;
+pub mod artifacts;
+pub mod config;
+pub mod docs;
 pub mod doctor;
+pub mod intercept;
 pub mod lint;
 pub mod models;
 pub mod report;
+pub mod serve;
 pub mod shared;
+pub mod stats;
+pub mod update;
 
 pub mod prelude {
     pub use crate::analyze::prelude::*;
+    pub use crate::artifacts::prelude::*;
+    pub use crate::config::prelude::*;
+    pub use crate::docs::prelude::*;
     pub use crate::doctor::prelude::*;
+    pub use crate::intercept::prelude::*;
     pub use crate::lint::prelude::*;
     pub use crate::models::prelude::*;
     pub use crate::report::prelude::*;
+    pub use crate::serve::prelude::*;
     pub use crate::shared::prelude::*;
+    pub use crate::stats::prelude::*;
+    pub use crate::update::prelude::*;
 }
 
 /// Preferred way to output data to users. This macro will write the output to tracing for debugging
@@ -1,8 +1,21 @@
+mod resources;
+
 pub mod cli {
+    #[cfg(feature = "cli")]
     use clap::Args;
 
-    #[derive(Debug, Args)]
-    pub struct LintArgs {}
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct LintArgs {
+        /// Print the reports of each `ScopeReportLocation`, rendered with sample data, to preview
+        /// their templates
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub render: bool,
+
+        /// Only render the reports of the `ScopeReportLocation` with this name
+        #[cfg_attr(feature = "cli", arg(long, requires = "render"))]
+        pub location: Option<String>,
+    }
 }
 
 pub mod commands {
@@ -10,27 +23,57 @@ pub mod commands {
     use crate::prelude::{
         ActionReport, ActionTaskReport, DefaultExecutionProvider, DefaultGroupedReportBuilder,
         DefaultUnstructuredReportBuilder, FoundConfig, GroupReport, GroupedReportBuilder, LintArgs,
-        OutputCaptureBuilder, ReportRenderer,
+        OutputCaptureBuilder, Report, ReportRenderer,
     };
+    use crate::report_stdout;
     use anyhow::Result;
     use chrono::DateTime;
     use fake::faker::lorem::en::*;
     use fake::Fake;
     use std::sync::Arc;
-    use tracing::info;
+    use tracing::{error, info};
+
+    use super::resources::check_resources;
 
-    pub async fn lint_root(found_config: &FoundConfig, _args: &LintArgs) -> Result<i32> {
-        lint_locations(found_config).await?;
+    pub async fn lint_root(found_config: &FoundConfig, args: &LintArgs) -> Result<i32> {
+        let problems = check_resources(found_config);
+        for problem in &problems {
+            error!(target: "user", "{}", problem);
+        }
+        let mut exit_code = if problems.is_empty() {
+            info!(target: "user", "Checked {} resources, no problems found", found_config.raw_config.len());
+            0
+        } else {
+            1
+        };
 
-        Ok(0)
+        if let Some(name) = &args.location {
+            if !found_config.report_upload.contains_key(name) {
+                error!(target: "user", "There is no ScopeReportLocation named {}", name);
+                return Ok(1);
+            }
+        }
+        if !lint_locations(found_config, args).await? {
+            exit_code = 1;
+        }
+
+        Ok(exit_code)
     }
 
-    async fn lint_locations(found_config: &FoundConfig) -> Result<()> {
+    /// Render the reports of every location with sample data, so broken templates are found
+    /// before a real report needs them. The reports are written to
+    /// `<location>-{unstructured,structured}-<run-id>.md`, or printed with `--render`.
+    async fn lint_locations(found_config: &FoundConfig, args: &LintArgs) -> Result<bool> {
         let unstructured = default_unstructured()?;
         let structured = default_structured()?;
         let exec_runner = Arc::new(DefaultExecutionProvider::default());
+        let mut rendered_all = true;
 
         for (name, location) in &found_config.report_upload {
+            if args.location.as_ref().is_some_and(|only| only != name) {
+                continue;
+            }
+
             let mut unstructured_builder = unstructured.clone();
             unstructured_builder
                 .run_and_append_additional_data(
@@ -39,21 +82,6 @@ pub mod commands {
                     &location.additional_data,
                 )
                 .await?;
-
-            let unstructured_report = unstructured_builder.render(location)?;
-
-            let unstructured_path = format!("{}-unstructured-{}.md", name, found_config.run_id);
-            info!(target: "always", "Creating template at {}", unstructured_path);
-
-            std::fs::write(
-                unstructured_path,
-                format!(
-                    "{}\n{}",
-                    unstructured_report.title(),
-                    unstructured_report.body()
-                ),
-            )?;
-
             let mut structured_builder = structured.clone();
             structured_builder
                 .run_and_append_additional_data(
@@ -63,23 +91,42 @@ pub mod commands {
                 )
                 .await?;
 
-            let structured_report = structured_builder.render(location)?;
-
-            let structured_path = format!("{}-structured-{}.md", name, found_config.run_id);
-
-            info!(target: "always", "Creating template at {}", structured_path);
-
-            std::fs::write(
-                structured_path,
-                format!(
-                    "{}\n{}",
-                    structured_report.title(),
-                    structured_report.body()
+            let reports = [
+                (
+                    "analyze",
+                    "unstructured",
+                    unstructured_builder.render(location),
                 ),
-            )?;
+                ("doctor", "structured", structured_builder.render(location)),
+            ];
+            for (template, kind, report) in reports {
+                match report {
+                    Ok(report) if args.render => print_report(name, template, &report).await,
+                    Ok(report) => {
+                        let path = format!("{}-{}-{}.md", name, kind, found_config.run_id);
+                        info!(target: "always", "Creating template at {}", path);
+                        tokio::fs::write(path, format!("{}\n{}", report.title(), report.body()))
+                            .await?;
+                    }
+                    Err(e) => {
+                        error!(target: "user", "ScopeReportLocation/{} can't render its {} report. {:?}", name, template, e);
+                        rendered_all = false;
+                    }
+                }
+            }
         }
 
-        Ok(())
+        Ok(rendered_all)
+    }
+
+    async fn print_report(location: &str, template: &str, report: &Report) {
+        report_stdout!(
+            "==> ScopeReportLocation/{}, {} report\n{}\n\n{}\n",
+            location,
+            template,
+            report.title(),
+            report.body()
+        );
     }
 
     fn default_structured() -> Result<DefaultGroupedReportBuilder> {
@@ -104,9 +151,15 @@ pub mod commands {
 
         ActionReport {
             action_name: format!("{} {}", prefix, idx),
+            description: String::new(),
             check: vec![action_report()],
             fix: vec![action_report()],
             validate: vec![action_report(), action_report()],
+            fix_changes: Vec::new(),
+            rollback: Vec::new(),
+            allowed_failure: false,
+            duration_ms: 0,
+            artifacts: Vec::new(),
         }
     }
 
@@ -148,7 +201,7 @@ pub mod commands {
 
         Ok(DefaultUnstructuredReportBuilder::new(
             "sample command",
-            &capture,
+            Arc::new(capture),
         ))
     }
 
@@ -0,0 +1,462 @@
+use crate::models::prelude::{ModelRoot, V1AlphaDoctorGroup};
+use crate::models::{HelpMetadata, InternalScopeModel};
+use crate::shared::prelude::{ConfigValidationError, FoundConfig, ParsedConfig};
+use minijinja::Environment;
+use serde_yaml::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+/// A problem with a resource, pointing at the line of the file that has it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ResourceProblem {
+    pub file_path: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Display for ResourceProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file_path, line, self.message),
+            None => write!(f, "{}: {}", self.file_path, self.message),
+        }
+    }
+}
+
+/// Check every resource that was found: that it matches the schema of its kind, that it can be
+/// loaded, that it only uses `vars` it defines, and that what it refers to exists. Problems are
+/// sorted by file and line.
+pub fn check_resources(found_config: &FoundConfig) -> Vec<ResourceProblem> {
+    let mut sources = SourceFiles::default();
+    let mut problems = Vec::new();
+
+    for resource in &found_config.raw_config {
+        if !ParsedConfig::is_supported(resource) {
+            continue;
+        }
+
+        let schema_errors = ParsedConfig::schema_errors(resource);
+        if schema_errors.is_empty() {
+            if let Err(e) = ParsedConfig::from_model(resource.clone(), &found_config.action_library)
+            {
+                let message = format!("{} can't be loaded, {}", resource.full_name(), e);
+                problems.push(sources.problem(resource, &[], message));
+            }
+        }
+        for (path, error) in schema_errors {
+            let message = format!(
+                "{} doesn't match the schema, {}",
+                resource.full_name(),
+                error
+            );
+            problems.push(sources.problem(resource, &path, message));
+        }
+        for (path, name) in unknown_vars(resource) {
+            let message = format!(
+                "{} uses `vars.{}`, but it isn't defined in `vars`",
+                resource.full_name(),
+                name
+            );
+            problems.push(sources.problem(resource, &path, message));
+        }
+    }
+
+    if let Err(errors) = found_config.validate() {
+        for error in errors {
+            let resource = found_config
+                .raw_config
+                .iter()
+                .find(|resource| resource.full_name().eq_ignore_ascii_case(error.resource()));
+            problems.push(match resource {
+                Some(resource) => {
+                    sources.problem(resource, &error_path(resource, &error), error.to_string())
+                }
+                None => ResourceProblem {
+                    file_path: "unknown".to_string(),
+                    line: None,
+                    message: error.to_string(),
+                },
+            });
+        }
+    }
+
+    problems.sort();
+    problems
+}
+
+/// Where in a resource a problem found by [`FoundConfig::validate`] is.
+fn error_path(resource: &ModelRoot<Value>, error: &ConfigValidationError) -> Vec<String> {
+    let path: &[&str] = match error {
+        ConfigValidationError::MissingDependency { dependency, .. } => {
+            let index = resource.spec["needs"].as_sequence().and_then(|needs| {
+                needs
+                    .iter()
+                    .position(|need| need.as_str() == Some(dependency))
+            });
+            return match index {
+                Some(index) => vec!["spec".to_string(), "needs".to_string(), index.to_string()],
+                None => vec!["spec".to_string(), "needs".to_string()],
+            };
+        }
+        ConfigValidationError::DuplicateName { .. } => &["metadata", "name"],
+        ConfigValidationError::InvalidPattern { .. } => &["spec", "pattern"],
+        ConfigValidationError::MissingReportDefinition { .. } => &["spec", "reportDefinition"],
+        ConfigValidationError::InvalidFixRef { .. } => &["spec", "fixRef"],
+    };
+
+    path.iter().map(|segment| segment.to_string()).collect()
+}
+
+/// `vars` used by the templates of a `ScopeDoctorGroup` that it doesn't define, with the path
+/// to the template using them.
+fn unknown_vars(resource: &ModelRoot<Value>) -> Vec<(Vec<String>, String)> {
+    if !resource
+        .kind
+        .eq_ignore_ascii_case(&V1AlphaDoctorGroup::int_kind())
+    {
+        return Vec::new();
+    }
+
+    let known: BTreeSet<String> = resource.spec["vars"]
+        .as_mapping()
+        .map(|vars| {
+            vars.keys()
+                .filter_map(|key| key.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut found = Vec::new();
+    find_unknown_vars(
+        &resource.spec,
+        &known,
+        &mut vec!["spec".to_string()],
+        &mut found,
+    );
+    found
+}
+
+fn find_unknown_vars(
+    value: &Value,
+    known: &BTreeSet<String>,
+    path: &mut Vec<String>,
+    found: &mut Vec<(Vec<String>, String)>,
+) {
+    match value {
+        Value::String(text) => {
+            let env = Environment::new();
+            // Templates that don't parse fail loading the resource, which is reported already.
+            let Ok(template) = env.template_from_str(text) else {
+                return;
+            };
+            let names: BTreeSet<String> = template
+                .undeclared_variables(true)
+                .iter()
+                .filter_map(|name| name.strip_prefix("vars."))
+                .filter_map(|name| name.split('.').next())
+                .filter(|name| !known.contains(*name))
+                .map(str::to_string)
+                .collect();
+            for name in names {
+                found.push((path.clone(), name));
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter().enumerate() {
+                path.push(index.to_string());
+                find_unknown_vars(item, known, path, found);
+                path.pop();
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                if let Some(key) = key.as_str() {
+                    path.push(key.to_string());
+                    find_unknown_vars(value, known, path, found);
+                    path.pop();
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Contents of the config files, read once, to find the lines problems are on.
+#[derive(Default)]
+struct SourceFiles {
+    contents: BTreeMap<String, Option<String>>,
+}
+
+impl SourceFiles {
+    fn problem(
+        &mut self,
+        resource: &ModelRoot<Value>,
+        path: &[String],
+        message: String,
+    ) -> ResourceProblem {
+        let file_path = resource.metadata.file_path();
+        let contents = self
+            .contents
+            .entry(file_path.clone())
+            .or_insert_with(|| std::fs::read_to_string(&file_path).ok());
+        let line = contents
+            .as_deref()
+            .and_then(|contents| find_line(contents, &resource.kind, resource.name(), path));
+
+        ResourceProblem {
+            file_path,
+            line,
+            message,
+        }
+    }
+}
+
+/// A line of YAML that isn't blank or a comment.
+#[derive(Debug, Clone)]
+struct YamlLine {
+    number: usize,
+    indent: usize,
+    text: String,
+}
+
+/// Line, starting at 1, of the value at `path` in the document of `contents` that defines the
+/// resource `kind`/`name`. When the value can't be found, like a value that's missing, the line
+/// of the closest value containing it is used instead.
+fn find_line(contents: &str, kind: &str, name: &str, path: &[String]) -> Option<usize> {
+    let document = split_documents(contents).into_iter().find(|document| {
+        let field = |field: &[&str]| {
+            let path: Vec<String> = field.iter().map(|segment| segment.to_string()).collect();
+            let (line, depth) = locate(document, &path);
+            line.filter(|_| depth == path.len())
+                .and_then(|number| document.iter().find(|line| line.number == number))
+                .and_then(|line| key_value(&line.text))
+                .map(|(_, value)| value.to_string())
+        };
+        field(&["kind"]).is_some_and(|value| value.eq_ignore_ascii_case(kind))
+            && field(&["metadata", "name"]).as_deref() == Some(name)
+    })?;
+
+    let (line, _) = locate(&document, path);
+    line.or_else(|| document.first().map(|line| line.number))
+}
+
+fn split_documents(contents: &str) -> Vec<Vec<YamlLine>> {
+    let mut documents = vec![Vec::new()];
+    for (index, line) in contents.lines().enumerate() {
+        if line.starts_with("---") {
+            documents.push(Vec::new());
+            continue;
+        }
+        let text = line.trim_start();
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        documents.last_mut().unwrap().push(YamlLine {
+            number: index + 1,
+            indent: line.len() - text.len(),
+            text: text.trim_end().to_string(),
+        });
+    }
+
+    documents.retain(|document| !document.is_empty());
+    documents
+}
+
+/// Follow `path` through `lines`, returning the line of the last value found and how many
+/// segments of `path` were found.
+fn locate(lines: &[YamlLine], path: &[String]) -> (Option<usize>, usize) {
+    let mut scope = lines.to_vec();
+    let mut found = None;
+    for (depth, segment) in path.iter().enumerate() {
+        let Some(indent) = scope.iter().map(|line| line.indent).min() else {
+            return (found, depth);
+        };
+        let position = match segment.parse::<usize>() {
+            Ok(index) => scope
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| line.indent == indent && is_item(&line.text))
+                .map(|(position, _)| position)
+                .nth(index),
+            Err(_) => scope.iter().position(|line| {
+                line.indent == indent
+                    && !is_item(&line.text)
+                    && key_value(&line.text).is_some_and(|(key, _)| key == segment)
+            }),
+        };
+        let Some(position) = position else {
+            return (found, depth);
+        };
+
+        let line = &scope[position];
+        let item = is_item(&line.text);
+        found = Some(line.number);
+        let mut children = Vec::new();
+        if item {
+            // The first value of an item can be on the same line as the `-`.
+            let rest = &line.text[1..];
+            let value = rest.trim_start();
+            if !value.is_empty() {
+                children.push(YamlLine {
+                    number: line.number,
+                    indent: line.indent + 1 + rest.len() - value.len(),
+                    text: value.to_string(),
+                });
+            }
+        }
+        children.extend(
+            scope[position + 1..]
+                .iter()
+                .take_while(|child| {
+                    child.indent > indent
+                        || (!item && child.indent == indent && is_item(&child.text))
+                })
+                .cloned(),
+        );
+        scope = children;
+    }
+
+    (found, path.len())
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+fn key_value(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = match text.find(": ") {
+        Some(index) => (&text[..index], &text[index + 2..]),
+        None => (text.strip_suffix(':')?, ""),
+    };
+
+    Some((unquote(key), unquote(value)))
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim().trim_matches(|c| c == '"' || c == '\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = "# Groups for the repo
+apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: setup
+spec:
+  pattern: setup failed
+  help: Run setup again
+---
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: setup
+spec:
+  needs:
+    - brew
+    - missing
+  actions:
+  - name: node
+    check:
+      commands:
+        - node --version
+
+  - name: python
+    fix:
+      commands: [\"pyenv install {{ vars.python }}\"]
+";
+
+    fn path(path: &[&str]) -> Vec<String> {
+        path.iter().map(|segment| segment.to_string()).collect()
+    }
+
+    #[test]
+    fn test_find_line() {
+        let find =
+            |kind: &str, segments: &[&str]| find_line(CONFIG, kind, "setup", &path(segments));
+
+        assert_eq!(Some(2), find("ScopeKnownError", &[]));
+        assert_eq!(Some(7), find("ScopeKnownError", &["spec", "pattern"]));
+        assert_eq!(Some(10), find("scopedoctorgroup", &[]));
+        assert_eq!(Some(17), find("ScopeDoctorGroup", &["spec", "needs", "1"]));
+        assert_eq!(
+            Some(19),
+            find("ScopeDoctorGroup", &["spec", "actions", "0"])
+        );
+        assert_eq!(
+            Some(22),
+            find(
+                "ScopeDoctorGroup",
+                &["spec", "actions", "0", "check", "commands", "0"]
+            )
+        );
+        assert_eq!(
+            Some(26),
+            find(
+                "ScopeDoctorGroup",
+                &["spec", "actions", "1", "fix", "commands", "0"]
+            )
+        );
+        // Missing values point at the closest value that contains them.
+        assert_eq!(
+            Some(24),
+            find("ScopeDoctorGroup", &["spec", "actions", "1", "check"])
+        );
+        assert_eq!(None, find("ScopeReportLocation", &[]));
+    }
+
+    #[tokio::test]
+    async fn test_check_resources() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let file_path = dir.path().join("setup.yaml");
+        std::fs::write(
+            &file_path,
+            CONFIG.replace("help: Run setup again", "help: 42\n  extra: true"),
+        )
+        .unwrap();
+
+        let config = FoundConfig::load(
+            dir.path().to_path_buf(),
+            vec![dir.path().to_path_buf()],
+            "run".to_string(),
+        )
+        .await;
+
+        let file_path = file_path.display().to_string();
+        let problems: Vec<_> = check_resources(&config)
+            .into_iter()
+            .map(|problem| {
+                assert_eq!(file_path, problem.file_path);
+                (problem.line, problem.message)
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    Some(6),
+                    "ScopeKnownError/setup doesn't match the schema, Additional properties are not allowed ('extra' was unexpected)".to_string()
+                ),
+                (
+                    Some(8),
+                    "ScopeKnownError/setup doesn't match the schema, 42 is not of type \"string\"".to_string()
+                ),
+                (
+                    Some(17),
+                    "ScopeDoctorGroup/setup needs brew but no such group was found".to_string()
+                ),
+                (
+                    Some(18),
+                    "ScopeDoctorGroup/setup needs missing but no such group was found".to_string()
+                ),
+                (
+                    Some(27),
+                    "ScopeDoctorGroup/setup uses `vars.python`, but it isn't defined in `vars`".to_string()
+                ),
+            ],
+            problems
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! Builders for defining doctor groups in Rust, for tools embedding `dev-scope` that keep their
+//! health checks in code instead of YAML.
+//!
+//! A built group is the same as one loaded from a `ScopeDoctorGroup` file: commands and paths are
+//! templates, relative paths resolve against the group's
+//! [`config_dir`](DoctorGroupBuilder::config_dir), and the same validation applies.
+//!
+//! ```no_run
+//! use dev_scope::models::build::{DoctorActionBuilder, DoctorGroupBuilder};
+//! use dev_scope::models::HelpMetadata;
+//! use dev_scope::prelude::FoundConfig;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let group = DoctorGroupBuilder::new("node")
+//!     .description("Node is installed")
+//!     .config_dir("/path/to/repo/.scope")
+//!     .action(
+//!         DoctorActionBuilder::new("install")
+//!             .check_command("node --version")
+//!             .fix_command("nvm install")
+//!             .fix_help_text("Install nvm, then run `nvm install`"),
+//!     )
+//!     .build()?;
+//!
+//! let mut found_config = FoundConfig::empty(std::env::current_dir()?);
+//! found_config
+//!     .doctor_group
+//!     .insert(group.name().to_string(), group);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::models::core::{ModelMetadata, ModelMetadataAnnotations};
+use crate::models::v1alpha::prelude::{
+    DoctorCheckSpec, DoctorFixSpec, DoctorGroupActionSpec, DoctorGroupKind, DoctorGroupSpec,
+    DoctorInclude, DoctorOnlyIfSpec, DoctorPlatform, V1AlphaDoctorGroup,
+};
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::shared::prelude::DoctorGroup;
+
+/// Builds a [`DoctorGroup`], the same as a `ScopeDoctorGroup` resource.
+#[derive(Debug, Clone)]
+pub struct DoctorGroupBuilder {
+    name: String,
+    description: Option<String>,
+    config_dir: Option<PathBuf>,
+    spec: DoctorGroupSpec,
+}
+
+impl DoctorGroupBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            config_dir: None,
+            spec: DoctorGroupSpec {
+                needs: Vec::new(),
+                actions: Vec::new(),
+                include: DoctorInclude::ByDefault,
+                report_extra_details: BTreeMap::new(),
+                schedule: None,
+                target: None,
+                cache_dir: None,
+                vars: BTreeMap::new(),
+                runtime: None,
+                env: BTreeMap::new(),
+                platforms: Vec::new(),
+                only_if: None,
+                required_tools: Vec::new(),
+            },
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Directory the group acts as if its config file was in, like a repo's `.scope` directory.
+    /// Relative commands, like `./bin/setup`, resolve against it and check paths against its
+    /// parent, the same as for a config file. Defaults to `.scope` in the current directory.
+    pub fn config_dir(mut self, config_dir: impl Into<PathBuf>) -> Self {
+        self.config_dir = Some(config_dir.into());
+        self
+    }
+
+    /// Another group that has to succeed before this one runs, see `needs`.
+    pub fn needs(mut self, group: impl Into<String>) -> Self {
+        self.spec.needs.push(group.into());
+        self
+    }
+
+    /// Add an action, they run in the order they're added.
+    pub fn action(mut self, action: DoctorActionBuilder) -> Self {
+        self.spec.actions.push(action.spec);
+        self
+    }
+
+    /// Only run the group when another group needs it, see `include: when-required`.
+    pub fn when_required(mut self) -> Self {
+        self.spec.include = DoctorInclude::WhenRequired;
+        self
+    }
+
+    /// A value for the group's templates, available as `{{ vars.<name> }}`.
+    pub fn var(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// An environment variable for every command in the group.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Limit the group to `platform`, can be called for each platform it applies to.
+    pub fn platform(mut self, platform: DoctorPlatform) -> Self {
+        self.spec.platforms.push(platform);
+        self
+    }
+
+    /// Command that has to exit 0 for the group to apply to this machine, see `onlyIf`.
+    pub fn only_if(mut self, command: impl Into<String>) -> Self {
+        self.spec.only_if = Some(DoctorOnlyIfSpec {
+            command: command.into(),
+        });
+        self
+    }
+
+    /// An executable that needs to be installed before the group runs, see `requiredTools`.
+    pub fn required_tool(mut self, tool: impl Into<String>) -> Self {
+        self.spec.required_tools.push(tool.into());
+        self
+    }
+
+    /// A command run when a bug report is built, its output is added to the report under `name`.
+    pub fn report_extra_detail(
+        mut self,
+        name: impl Into<String>,
+        command: impl Into<String>,
+    ) -> Self {
+        self.spec
+            .report_extra_details
+            .insert(name.into(), command.into());
+        self
+    }
+
+    /// The group, with its templates rendered and its paths resolved. Fails the same way loading
+    /// an invalid `ScopeDoctorGroup` does, e.g. when a template can't be rendered.
+    pub fn build(self) -> Result<DoctorGroup> {
+        let working_dir = std::env::current_dir()?;
+        let config_dir = self
+            .config_dir
+            .unwrap_or_else(|| working_dir.join(".scope"))
+            .display()
+            .to_string();
+        let mut metadata = ModelMetadata::new(&self.name);
+        if let Some(description) = self.description {
+            metadata.description = description;
+        }
+        metadata.annotations = ModelMetadataAnnotations {
+            file_dir: Some(config_dir.clone()),
+            working_dir: Some(working_dir.display().to_string()),
+            bin_path: Some(config_dir),
+            ..Default::default()
+        };
+
+        DoctorGroup::try_from(V1AlphaDoctorGroup {
+            api_version: V1AlphaApiVersion::ScopeV1Alpha,
+            kind: DoctorGroupKind::ScopeDoctorGroup,
+            metadata,
+            spec: self.spec,
+        })
+    }
+}
+
+/// Builds an action of a [`DoctorGroupBuilder`]. An action needs a check, a fix, or both.
+#[derive(Debug, Clone)]
+pub struct DoctorActionBuilder {
+    spec: DoctorGroupActionSpec,
+}
+
+impl DoctorActionBuilder {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            spec: DoctorGroupActionSpec {
+                name: Some(name.into()),
+                description: None,
+                check: DoctorCheckSpec::default(),
+                fix: None,
+                required: true,
+                allow_failure: false,
+                run_always: false,
+                timeout_seconds: None,
+                use_action: None,
+                with: BTreeMap::new(),
+                runtime: None,
+                env: BTreeMap::new(),
+                platforms: Vec::new(),
+                only_if: None,
+                labels: BTreeMap::new(),
+                working_dir: None,
+            },
+        }
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.spec.description = Some(description.into());
+        self
+    }
+
+    /// A check command, the fix runs when it exits non-zero.
+    pub fn check_command(mut self, command: impl Into<String>) -> Self {
+        self.spec
+            .check
+            .commands
+            .get_or_insert_with(Vec::new)
+            .push(command.into());
+        self
+    }
+
+    /// A glob of files the check watches, the fix runs when they change.
+    pub fn check_path(mut self, glob: impl Into<String>) -> Self {
+        self.spec
+            .check
+            .paths
+            .get_or_insert_with(Vec::new)
+            .push(glob.into());
+        self
+    }
+
+    /// A fix command, run when the check fails.
+    pub fn fix_command(mut self, command: impl Into<String>) -> Self {
+        self.fix().commands.push(command.into());
+        self
+    }
+
+    /// Text shown when the action fails, to help fixing it by hand.
+    pub fn fix_help_text(mut self, help_text: impl Into<String>) -> Self {
+        self.fix().help_text = Some(help_text.into());
+        self
+    }
+
+    /// Link shown when the action fails, to help fixing it by hand.
+    pub fn fix_help_url(mut self, help_url: impl Into<String>) -> Self {
+        self.fix().help_url = Some(help_url.into());
+        self
+    }
+
+    /// Whether the rest of the group is skipped when the action fails. Defaults to `true`.
+    pub fn required(mut self, required: bool) -> Self {
+        self.spec.required = required;
+        self
+    }
+
+    /// A failure is recorded, but doesn't fail the group, see `allowFailure`.
+    pub fn allow_failure(mut self, allow_failure: bool) -> Self {
+        self.spec.allow_failure = allow_failure;
+        self
+    }
+
+    /// The fix runs every time without a check, see `runAlways`.
+    pub fn run_always(mut self, run_always: bool) -> Self {
+        self.spec.run_always = run_always;
+        self
+    }
+
+    /// How long each check and fix command can run, rounded up to a second.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        self.spec.timeout_seconds = Some(seconds);
+        self
+    }
+
+    /// Directory the action's commands run in, relative to the file the group is written to.
+    pub fn working_dir(mut self, working_dir: impl Into<String>) -> Self {
+        self.spec.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// An environment variable for the action's commands, on top of the group's.
+    pub fn env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.spec.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Limit the action to `platform`, can be called for each platform it applies to.
+    pub fn platform(mut self, platform: DoctorPlatform) -> Self {
+        self.spec.platforms.push(platform);
+        self
+    }
+
+    /// Command that has to exit 0 for the action to apply to this machine, see `onlyIf`.
+    pub fn only_if(mut self, command: impl Into<String>) -> Self {
+        self.spec.only_if = Some(DoctorOnlyIfSpec {
+            command: command.into(),
+        });
+        self
+    }
+
+    fn fix(&mut self) -> &mut DoctorFixSpec {
+        self.spec.fix.get_or_insert_with(|| DoctorFixSpec {
+            commands: Vec::new(),
+            rollback: Vec::new(),
+            help_text: None,
+            help_url: None,
+            sandbox_paths: Vec::new(),
+            artifacts: Vec::new(),
+            snapshot: None,
+            prompt: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HelpMetadata;
+    use std::path::Path;
+
+    #[test]
+    fn test_build_group() {
+        let group = DoctorGroupBuilder::new("node")
+            .description("Node is installed")
+            .config_dir("/repo/.scope")
+            .needs("brew")
+            .var("version", "20")
+            .action(
+                DoctorActionBuilder::new("install")
+                    .check_command("./bin/check-node {{ vars.version }}")
+                    .check_path(".nvmrc")
+                    .fix_command("nvm install {{ vars.version }}")
+                    .fix_help_text("Install nvm first")
+                    .timeout(Duration::from_millis(1500)),
+            )
+            .action(DoctorActionBuilder::new("optional").required(false))
+            .build()
+            .unwrap();
+
+        assert_eq!("node", group.name());
+        assert_eq!("ScopeDoctorGroup/node", group.full_name);
+        assert_eq!("Node is installed", group.description());
+        assert_eq!(vec!["brew"], group.requires);
+        assert!(group.run_by_default);
+
+        let install = &group.actions[0];
+        assert_eq!("install", install.name);
+        assert_eq!(
+            vec!["/repo/.scope/bin/check-node 20"],
+            install.check.command.as_ref().unwrap().commands
+        );
+        let files = install.check.files.as_ref().unwrap();
+        assert_eq!(vec![".nvmrc"], files.paths);
+        assert_eq!(Path::new("/repo"), files.base_path);
+        assert_eq!(
+            vec!["nvm install 20"],
+            install.fix.command.as_ref().unwrap().commands
+        );
+        assert_eq!(Some("Install nvm first".to_string()), install.fix.help_text);
+        assert_eq!(Some(Duration::from_secs(2)), install.timeout);
+        assert!(install.required);
+        assert!(!group.actions[1].required);
+    }
+
+    #[test]
+    fn test_build_fails_like_config() {
+        let result = DoctorGroupBuilder::new("node")
+            .config_dir("/repo/.scope")
+            .action(DoctorActionBuilder::new("install").fix_command("nvm install {{ oops("))
+            .build();
+        assert!(result.is_err());
+    }
+}
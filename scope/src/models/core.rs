@@ -1,4 +1,5 @@
 use crate::models::{HelpMetadata, ScopeModel};
+use crate::shared::prelude::FIELD_SOURCE_ANNOTATION_PREFIX;
 use derive_builder::Builder;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -7,6 +8,33 @@ use std::collections::BTreeMap;
 pub const FILE_PATH_ANNOTATION: &str = "scope.github.com/file-path";
 pub const FILE_DIR_ANNOTATION: &str = "scope.github.com/file-dir";
 pub const FILE_EXEC_PATH_ANNOTATION: &str = "scope.github.com/bin-path";
+/// Oldest version of `scope` that supports a resource, shown when the resource isn't supported.
+pub const MIN_SCOPE_VERSION_ANNOTATION: &str = "scope.github.com/min-version";
+
+/// Separator between entries of a `PATH` style list on the current platform.
+pub const PATH_LIST_SEPARATOR: char = if cfg!(windows) { ';' } else { ':' };
+
+/// Join entries into a `PATH` style list for the current platform. Empty entries are dropped.
+pub fn join_path_list<I, S>(entries: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    join_path_list_with(entries, PATH_LIST_SEPARATOR)
+}
+
+fn join_path_list_with<I, S>(entries: I, separator: char) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    entries
+        .into_iter()
+        .filter(|entry| !entry.as_ref().is_empty())
+        .map(|entry| entry.as_ref().to_string())
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, Builder, JsonSchema)]
 pub struct ModelMetadataAnnotations {
@@ -54,16 +82,28 @@ impl ModelMetadata {
         }
     }
 
+    /// Directory relative paths in `field`, like `spec.actions`, are relative to. When the resource
+    /// was merged from several files, that's the directory of the file the field came from.
+    pub fn containing_dir_of(&self, field: &str) -> String {
+        let exact = format!("{}{}", FIELD_SOURCE_ANNOTATION_PREFIX, field);
+        let nested = format!("{}.", exact);
+        let source = self
+            .annotations
+            .extra
+            .iter()
+            .find(|(key, _)| **key == exact || key.starts_with(&nested))
+            .map(|(_, file)| file);
+        match source.and_then(|file| std::path::Path::new(file).parent()) {
+            Some(dir) => dir.display().to_string(),
+            None => self.containing_dir(),
+        }
+    }
+
     pub fn exec_path(&self) -> String {
+        let system_path = std::env::var("PATH").unwrap_or_else(|_| "".to_string());
         match &self.annotations.bin_path {
-            Some(v) => {
-                format!(
-                    "{}:{}",
-                    v,
-                    std::env::var("PATH").unwrap_or_else(|_| "".to_string())
-                )
-            }
-            None => std::env::var("PATH").unwrap_or_else(|_| "".to_string()),
+            Some(v) => join_path_list([v.as_str(), &system_path]),
+            None => system_path,
         }
     }
 
@@ -133,3 +173,24 @@ impl<S> ScopeModel<S> for ModelRoot<S> {
         &self.spec
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::join_path_list_with;
+
+    #[test]
+    fn test_join_path_list_unix() {
+        assert_eq!(
+            "/opt/bin:/usr/bin",
+            join_path_list_with(["/opt/bin", "", "/usr/bin"], ':')
+        );
+    }
+
+    #[test]
+    fn test_join_path_list_windows() {
+        assert_eq!(
+            "C:\\tools\\bin;C:\\Windows",
+            join_path_list_with(["C:\\tools\\bin", "C:\\Windows", ""], ';')
+        );
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::BTreeSet;
+
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::models::make_schema_generator;
+use crate::models::prelude::*;
+use crate::models::InternalScopeModel;
+
+/// Comments are wrapped to fit this width, but are never narrower than `MIN_COMMENT_WIDTH`.
+const COMMENT_WIDTH: usize = 100;
+const MIN_COMMENT_WIDTH: usize = 40;
+
+fn root_schema<T: JsonSchema>() -> Value {
+    serde_json::to_value(make_schema_generator().into_root_schema_for::<T>())
+        .expect("internal json schema to serialize")
+}
+
+fn kind_schemas() -> Vec<(String, Value)> {
+    vec![
+        (
+            V1AlphaDoctorGroup::int_kind(),
+            root_schema::<V1AlphaDoctorGroup>(),
+        ),
+        (
+            V1AlphaActionLibrary::int_kind(),
+            root_schema::<V1AlphaActionLibrary>(),
+        ),
+        (
+            V1AlphaKnownError::int_kind(),
+            root_schema::<V1AlphaKnownError>(),
+        ),
+        (
+            V1AlphaReportLocation::int_kind(),
+            root_schema::<V1AlphaReportLocation>(),
+        ),
+        (
+            V1AlphaReportDefinition::int_kind(),
+            root_schema::<V1AlphaReportDefinition>(),
+        ),
+        (
+            V1AlphaScopeConfig::int_kind(),
+            root_schema::<V1AlphaScopeConfig>(),
+        ),
+        (
+            V1AlphaRedaction::int_kind(),
+            root_schema::<V1AlphaRedaction>(),
+        ),
+        (V1AlphaPolicy::int_kind(), root_schema::<V1AlphaPolicy>()),
+        (
+            V1AlphaPromptPolicy::int_kind(),
+            root_schema::<V1AlphaPromptPolicy>(),
+        ),
+    ]
+}
+
+/// Kinds that [`example_for_kind`] can write an example of.
+pub fn example_kinds() -> Vec<String> {
+    kind_schemas().into_iter().map(|(kind, _)| kind).collect()
+}
+
+/// Writes an example resource of `kind` as YAML, with the schema's descriptions as comments.
+/// Required fields are set, and optional fields are commented out with their default.
+/// `kind` is matched ignoring case, and the `Scope` prefix can be left out.
+pub fn example_for_kind(kind: &str) -> Option<String> {
+    let wanted = kind.to_lowercase();
+    kind_schemas()
+        .into_iter()
+        .find(|(name, _)| {
+            let name = name.to_lowercase();
+            name == wanted || name.strip_prefix("scope") == Some(wanted.as_str())
+        })
+        .map(|(_, schema)| ExampleWriter::new(&schema).render())
+}
+
+struct Line {
+    indent: usize,
+    text: String,
+    /// False for comments, including fields that are commented out.
+    active: bool,
+}
+
+impl Line {
+    fn active(indent: usize, text: impl Into<String>) -> Self {
+        Self {
+            indent,
+            text: text.into(),
+            active: true,
+        }
+    }
+
+    fn comment(indent: usize, text: impl AsRef<str>) -> Self {
+        Self {
+            indent,
+            text: format!("# {}", text.as_ref()),
+            active: false,
+        }
+    }
+}
+
+fn has_active(lines: &[Line]) -> bool {
+    lines.iter().any(|line| line.active)
+}
+
+fn comment_out(lines: Vec<Line>) -> Vec<Line> {
+    lines
+        .into_iter()
+        .map(|line| {
+            if line.active {
+                Line::comment(line.indent, line.text)
+            } else {
+                line
+            }
+        })
+        .collect()
+}
+
+/// A schema with `$ref`, `allOf`, `anyOf` and `oneOf` replaced by the schema they point to.
+struct Resolved {
+    schema: Map<String, Value>,
+    /// The values of an enum, or the keys of the variants of a `oneOf`.
+    choices: Vec<String>,
+}
+
+struct ExampleWriter<'a> {
+    root: &'a Value,
+    definitions: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> ExampleWriter<'a> {
+    fn new(root: &'a Value) -> Self {
+        Self {
+            root,
+            definitions: root.get("definitions").and_then(Value::as_object),
+        }
+    }
+
+    fn render(&self) -> String {
+        let empty = Map::new();
+        let root = self.root.as_object().unwrap_or(&empty);
+        let mut lines = comments(root, &[], 0);
+        lines.extend(self.object(root, 0, false));
+
+        lines
+            .iter()
+            .map(|line| format!("{}{}\n", " ".repeat(line.indent), line.text))
+            .collect()
+    }
+
+    fn definition(&self, reference: &str) -> Option<Value> {
+        let name = reference.strip_prefix("#/definitions/")?;
+        self.definitions?.get(name).cloned()
+    }
+
+    /// Keys set next to a `$ref`, like `description` and `default`, take precedence over the
+    /// schema it points to. Of `anyOf` and `oneOf`, the first variant that isn't `null` is used.
+    fn resolve(&self, schema: &Value) -> Resolved {
+        let mut schema = schema.as_object().cloned().unwrap_or_default();
+        let mut choices = Vec::new();
+        loop {
+            let inner = if let Some(Value::String(reference)) = schema.remove("$ref") {
+                self.definition(&reference)
+            } else if let Some(Value::Array(variants)) = schema.remove("anyOf") {
+                variants
+                    .into_iter()
+                    .find(|variant| variant.get("type") != Some(&json!("null")))
+            } else if let Some(Value::Array(variants)) = schema.remove("allOf") {
+                variants.into_iter().next()
+            } else if let Some(Value::Array(variants)) = schema.remove("oneOf") {
+                if variants.iter().all(|variant| variant.get("enum").is_some()) {
+                    let values: Vec<Value> = variants
+                        .iter()
+                        .filter_map(|variant| variant.get("enum")?.as_array())
+                        .flatten()
+                        .cloned()
+                        .collect();
+                    Some(json!({ "enum": values }))
+                } else {
+                    choices = variants
+                        .iter()
+                        .filter_map(|variant| variant.get("required")?.as_array())
+                        .flatten()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect();
+                    variants.into_iter().next()
+                }
+            } else {
+                break;
+            };
+
+            if let Some(Value::Object(inner)) = inner {
+                for (key, value) in inner {
+                    schema.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        if let Some(Value::Array(values)) = schema.get("enum") {
+            if values.len() > 1 {
+                choices = values.iter().map(display_value).collect();
+            }
+        }
+
+        Resolved { schema, choices }
+    }
+
+    /// Required fields are written in full, optional fields are commented out and written with
+    /// every field they have, so uncommenting them gives a complete example.
+    fn object(&self, schema: &Map<String, Value>, indent: usize, example: bool) -> Vec<Line> {
+        let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+            return Vec::new();
+        };
+        let required: BTreeSet<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+
+        let mut fields: Vec<Vec<Line>> = properties
+            .iter()
+            .map(|(key, field)| {
+                if example || required.contains(key.as_str()) {
+                    self.property(key, field, indent, example)
+                } else {
+                    comment_out(self.property(key, field, indent, true))
+                }
+            })
+            .collect();
+
+        // Without any required fields the object would be empty, so set the first field with a
+        // default to keep the example readable.
+        if !example && !fields.iter().any(|lines| has_active(lines)) {
+            let with_default = properties.iter().enumerate().find(|(_, (_, field))| {
+                let field = self.resolve(field).schema;
+                default_value(&field).is_some()
+                    && !matches!(schema_type(&field), Some("object") | Some("array"))
+            });
+            if let Some((index, (key, field))) = with_default {
+                fields[index] = self.property(key, field, indent, false);
+            }
+        }
+
+        fields.into_iter().flatten().collect()
+    }
+
+    fn property(&self, key: &str, schema: &Value, indent: usize, example: bool) -> Vec<Line> {
+        let Resolved { schema, choices } = self.resolve(schema);
+        let mut lines = comments(&schema, &choices, indent);
+        let default = default_value(&schema);
+
+        match schema_type(&schema) {
+            Some("object") if schema.contains_key("properties") => {
+                let fields = self.object(&schema, indent + 2, example);
+                if has_active(&fields) {
+                    lines.push(Line::active(indent, format!("{}:", key)));
+                } else {
+                    lines.push(Line::active(indent, format!("{}: {{}}", key)));
+                }
+                lines.extend(fields);
+            }
+            Some("array") if example || default.is_none() => {
+                lines.push(Line::active(indent, format!("{}:", key)));
+                let items = schema.get("items").unwrap_or(&Value::Null);
+                lines.extend(self.item(items, indent + 2, example));
+            }
+            _ => {
+                let value = default.cloned().unwrap_or_else(|| placeholder(&schema));
+                lines.push(Line::active(
+                    indent,
+                    format!("{}: {}", key, format_value(&value)),
+                ));
+            }
+        }
+
+        lines
+    }
+
+    fn item(&self, schema: &Value, indent: usize, example: bool) -> Vec<Line> {
+        let Resolved { schema, choices } = self.resolve(schema);
+        let mut lines = match schema_type(&schema) {
+            Some("object") if schema.contains_key("properties") => {
+                self.object(&schema, indent + 2, example)
+            }
+            _ => {
+                let mut lines = comments(&schema, &choices, indent + 2);
+                lines.push(Line::active(
+                    indent + 2,
+                    format_value(&placeholder(&schema)),
+                ));
+                lines
+            }
+        };
+
+        if has_active(&lines) {
+            let first = &mut lines[0];
+            first.indent = indent;
+            first.text = format!("- {}", first.text);
+            first.active = true;
+        } else {
+            lines.insert(0, Line::active(indent, "- {}"));
+        }
+
+        lines
+    }
+}
+
+fn comments(schema: &Map<String, Value>, choices: &[String], indent: usize) -> Vec<Line> {
+    let mut lines = Vec::new();
+    if let Some(description) = schema.get("description").and_then(Value::as_str) {
+        lines.extend(
+            wrap(description, indent)
+                .into_iter()
+                .map(|text| Line::comment(indent, text)),
+        );
+    }
+    if !choices.is_empty() {
+        let choices: Vec<String> = choices
+            .iter()
+            .map(|choice| format!("`{}`", choice))
+            .collect();
+        lines.extend(
+            wrap(&format!("One of {}.", choices.join(", ")), indent)
+                .into_iter()
+                .map(|text| Line::comment(indent, text)),
+        );
+    }
+    lines
+}
+
+fn wrap(text: &str, indent: usize) -> Vec<String> {
+    let width = COMMENT_WIDTH
+        .saturating_sub(indent + 2)
+        .max(MIN_COMMENT_WIDTH);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.len() + 1 + word.len() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn schema_type(schema: &Map<String, Value>) -> Option<&str> {
+    match schema.get("type") {
+        Some(Value::String(schema_type)) => Some(schema_type.as_str()),
+        Some(Value::Array(types)) => types
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|schema_type| *schema_type != "null"),
+        _ if schema.contains_key("properties") => Some("object"),
+        _ if schema.contains_key("enum") => Some("string"),
+        _ => None,
+    }
+}
+
+fn default_value(schema: &Map<String, Value>) -> Option<&Value> {
+    schema.get("default").filter(|value| !value.is_null())
+}
+
+fn placeholder(schema: &Map<String, Value>) -> Value {
+    if let Some(first) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .and_then(|values| values.first())
+    {
+        return first.clone();
+    }
+
+    match schema_type(schema) {
+        Some("string") => json!(""),
+        Some("integer") | Some("number") => json!(0),
+        Some("boolean") => json!(false),
+        Some("array") => json!([]),
+        Some("object") => json!({}),
+        _ => Value::Null,
+    }
+}
+
+/// Collections are written in flow style, which is valid YAML and keeps them on one line.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Array(_) | Value::Object(_) => value.to_string(),
+        _ => serde_yaml::to_string(value)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_example_matches_its_schema() {
+        for (kind, schema) in kind_schemas() {
+            let example = example_for_kind(&kind).unwrap();
+            let parsed: serde_yaml::Value = serde_yaml::from_str(&example).unwrap();
+            let parsed = serde_json::to_value(parsed).unwrap();
+
+            let compiled = jsonschema::JSONSchema::compile(&schema).unwrap();
+            assert!(compiled.is_valid(&parsed), "{}:\n{}", kind, example);
+            assert_eq!(json!(kind), parsed["kind"]);
+        }
+    }
+
+    #[test]
+    fn test_doctor_group_example() {
+        let example = example_for_kind("doctorgroup").unwrap();
+
+        assert!(example.starts_with("# Resource used to define a `ScopeDoctorGroup`.\n"));
+        assert!(example.contains("\nkind: ScopeDoctorGroup\n"));
+        assert!(
+            example.contains("  # A series of steps to check and fix for the group.\n  actions:\n")
+        );
+        assert!(example.contains("      allowFailure: false\n"));
+        assert!(example
+            .contains("  # One of `by-default`, `when-required`.\n  # include: by-default\n"));
+        assert!(example.contains("      # fix:\n"));
+        assert!(example.contains("        # commands:\n          # - ''\n"));
+    }
+
+    #[test]
+    fn test_unknown_kind() {
+        assert_eq!(None, example_for_kind("ScopeUnknown"));
+        assert!(example_for_kind("SCOPEREDACTION").is_some());
+    }
+}
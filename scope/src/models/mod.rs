@@ -6,11 +6,14 @@ use serde::{Deserialize, Serialize};
 use serde_yaml::Value;
 use tracing::warn;
 
+pub mod build;
 mod core;
+mod example;
 mod v1alpha;
 
 pub mod prelude {
     pub use crate::models::core::*;
+    pub use crate::models::example::{example_for_kind, example_kinds};
     pub use crate::models::v1alpha::prelude::*;
     pub use crate::models::{HelpMetadata, ScopeModel};
 }
@@ -62,20 +65,30 @@ where
     }
 
     fn validate_resource(input: &serde_json::Value) -> anyhow::Result<()> {
+        let errors = Self::schema_errors(input);
+        if !errors.is_empty() {
+            let errors: Vec<_> = errors.into_iter().map(|(_, error)| error).collect();
+            return Err(anyhow!(errors.join("\n")));
+        }
+
+        Ok(())
+    }
+
+    /// Where `input` doesn't match the schema, as the path to the value that's wrong, like
+    /// `["spec", "actions", "0"]`, and what's wrong with it.
+    fn schema_errors(input: &serde_json::Value) -> Vec<(Vec<String>, String)> {
         let mut schema_gen = make_schema_generator();
         let schema = schema_gen.root_schema_for::<Self>();
-        let schema_json = serde_json::to_value(&schema)?;
+        let schema_json = serde_json::to_value(&schema).expect("internal json schema to serialize");
         let compiled_schema = jsonschema::JSONSchema::compile(&schema_json)
             .expect("internal json schema to be valid");
-        if let Err(err_iter) = compiled_schema.validate(input) {
-            let mut errors = Vec::new();
-            for err in err_iter {
-                errors.push(err.to_string());
-            }
-            return Err(anyhow!(errors.join("\n")));
+        let errors = match compiled_schema.validate(input) {
+            Ok(()) => Vec::new(),
+            Err(err_iter) => err_iter
+                .map(|err| (err.instance_path.clone().into_vec(), err.to_string()))
+                .collect(),
         };
-
-        Ok(())
+        errors
     }
 
     #[cfg(test)]
@@ -157,8 +170,14 @@ mod schema_gen {
     #[serde(untagged)]
     enum ScopeTypes {
         ReportLocation(V1AlphaReportLocation),
+        ReportDefinition(V1AlphaReportDefinition),
         KnownError(V1AlphaKnownError),
         DoctorGroup(V1AlphaDoctorGroup),
+        ScopeConfig(V1AlphaScopeConfig),
+        ActionLibrary(V1AlphaActionLibrary),
+        Redaction(V1AlphaRedaction),
+        Policy(V1AlphaPolicy),
+        PromptPolicy(V1AlphaPromptPolicy),
     }
 
     #[test]
@@ -174,9 +193,33 @@ mod schema_gen {
 
         V1AlphaReportLocation::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
             .unwrap();
+        V1AlphaReportDefinition::create_and_validate(
+            &mut schema_gen,
+            &out_dir,
+            &merged_schema_json,
+        )
+        .unwrap();
         V1AlphaKnownError::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
             .unwrap();
         V1AlphaDoctorGroup::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
             .unwrap();
+        V1AlphaScopeConfig::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
+            .unwrap();
+        V1AlphaActionLibrary::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
+            .unwrap();
+        V1AlphaRedaction::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
+            .unwrap();
+        V1AlphaPolicy::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json).unwrap();
+        V1AlphaPromptPolicy::create_and_validate(&mut schema_gen, &out_dir, &merged_schema_json)
+            .unwrap();
+
+        // Not a config kind, a separate generator keeps the config definitions out of it.
+        let output_schema = crate::models::make_schema_generator()
+            .into_root_schema_for::<crate::doctor::DoctorRunOutput>();
+        std::fs::write(
+            format!("{}/v1.com.github.scope.DoctorRunOutput.json", out_dir),
+            serde_json::to_string_pretty(&output_schema).unwrap(),
+        )
+        .unwrap();
     }
 }
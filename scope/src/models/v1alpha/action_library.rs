@@ -0,0 +1,86 @@
+use crate::models::core::ModelMetadata;
+use crate::models::prelude::DoctorGroupActionSpec;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Actions shared by many `ScopeDoctorGroup`s.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ActionLibrarySpec {
+    /// Default values for parameters, available to the actions' templates as
+    /// `{{ params.<name> }}`. A group can override them with `with`.
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+
+    /// Actions a group can use with `useAction: <library>/<action>`. Every action needs a
+    /// unique `name`. Relative commands are relative to the directory containing this file.
+    pub actions: Vec<DoctorGroupActionSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum ActionLibraryKind {
+    #[strum(serialize = "ScopeActionLibrary")]
+    ScopeActionLibrary,
+}
+
+/// Resource used to define a `ScopeActionLibrary`, a set of named actions that doctor groups
+/// can reuse instead of copying them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaActionLibrary {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: ActionLibraryKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: ActionLibrarySpec,
+}
+
+impl HelpMetadata for V1AlphaActionLibrary {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<ActionLibrarySpec> for V1AlphaActionLibrary {
+    fn api_version(&self) -> String {
+        V1AlphaActionLibrary::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaActionLibrary::int_kind()
+    }
+
+    fn spec(&self) -> &ActionLibrarySpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<ActionLibrarySpec, V1AlphaActionLibrary> for V1AlphaActionLibrary {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        ActionLibraryKind::ScopeActionLibrary.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/ActionLibrary.yaml".to_string()]
+    }
+}
@@ -2,13 +2,14 @@ use derive_builder::Builder;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::path::PathBuf;
 
 use crate::models::core::ModelMetadata;
 use crate::models::v1alpha::V1AlphaApiVersion;
 use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
 
 /// What needs to be checked before the action will run. All `paths` will be checked first, then
-/// `commands`. If a `path` has changed, the `command` will not run.
+/// `brew` and `toolVersion`, then `commands`. If a `path` has changed, the `command` will not run.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[schemars(deny_unknown_fields)]
@@ -19,13 +20,124 @@ pub struct DoctorCheckSpec {
     /// Relative paths are relative to the scope config directory containing the config file.
     ///
     /// Shared configs can use the template string `{{ working_dir }}` to access the working
-    /// directory.
+    /// directory. The filters `quote_sh`, `to_upper`, `dirname` and `semver_major` are available,
+    /// e.g. `{{ working_dir | dirname }}`.
     #[serde(default)]
     pub paths: Option<Vec<String>>,
 
-    /// A list of commands to execute to check the environment.
+    /// Files matched by `paths` to leave out of the cache, like editor swap files or build
+    /// output. Patterns use `.gitignore` syntax and are relative to the same directory as
+    /// `paths`, e.g. `*.swp`, `target/` or `!keep.log`.
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+
+    /// Skip files ignored by `.gitignore`, `.ignore` and git's exclude files while looking for
+    /// files matching `paths`, so ignored directories like `node_modules` aren't walked at all.
+    /// Defaults to `true`, set it to `false` to match ignored files too.
+    #[serde(default = "doctor_check_respect_gitignore_default")]
+    pub respect_gitignore: bool,
+
+    /// A list of commands to execute to check the environment. Commands are templates, like
+    /// `paths`, with `{{ working_dir }}` and the same filters available.
     #[serde(default)]
     pub commands: Option<Vec<String>>,
+
+    /// What the exit codes of `commands` mean, for scripts that already use exit codes of their
+    /// own. Without it, `0` means the check passed and any other code runs the fix, with `100` and
+    /// above also skipping the rest of the check commands.
+    #[serde(default)]
+    pub exit_codes: Option<DoctorExitCodesSpec>,
+
+    /// A Homebrew formula that needs to be installed, checked with `brew list`. Without fix
+    /// commands, the fix is `brew install <formula>`.
+    #[serde(default)]
+    pub brew: Option<DoctorBrewCheckSpec>,
+
+    /// A tool whose version needs to match a constraint, like `node` at `>=20`.
+    #[serde(default)]
+    pub tool_version: Option<DoctorToolVersionCheckSpec>,
+
+    /// Directory that relative `paths` and `ignorePaths` are relative to when it isn't the
+    /// group's, like for a check from a `ScopeActionLibrary`. Set when config is loaded.
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub base_path: Option<PathBuf>,
+}
+
+/// A Homebrew formula that needs to be installed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorBrewCheckSpec {
+    /// Name of the formula, like `jq`. It's a template, like the check commands.
+    pub formula: String,
+}
+
+/// A tool whose version needs to match a constraint. The version is the first version number the
+/// tool prints, like `20.11.0` from `v20.11.0`, with missing parts counted as `0`. A tool that
+/// isn't installed fails the check.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorToolVersionCheckSpec {
+    /// Executable of the tool, like `node`.
+    pub tool: String,
+
+    /// Versions that pass the check, like `>=20`, `>=18, <21` or `~3.11`, in the syntax Cargo
+    /// uses for dependency versions.
+    pub constraint: String,
+
+    /// Command that prints the version. Defaults to `<tool> --version`.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+fn doctor_check_respect_gitignore_default() -> bool {
+    true
+}
+
+/// Exit codes of check commands, by what they mean. A code in more than one list means the first
+/// of `success`, `skip` and `stop` it's in, and a code in none of them means the check failed and
+/// the fix runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorExitCodesSpec {
+    /// The check passed, the fix doesn't need to run. Defaults to `[0]`.
+    #[serde(default)]
+    pub success: Option<Vec<DoctorExitCodeSpec>>,
+
+    /// The action doesn't apply, it's skipped without running the fix and doesn't fail the group.
+    #[serde(default)]
+    pub skip: Vec<DoctorExitCodeSpec>,
+
+    /// The check failed in a way the fix can't help with. The fix doesn't run, the action fails
+    /// and the rest of the group is skipped.
+    #[serde(default)]
+    pub stop: Vec<DoctorExitCodeSpec>,
+}
+
+/// An exit code, like `10`, or an inclusive range of them, like `"100-255"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum DoctorExitCodeSpec {
+    Code(i32),
+    Range(String),
+}
+
+impl Default for DoctorCheckSpec {
+    fn default() -> Self {
+        Self {
+            paths: None,
+            ignore_paths: Vec::new(),
+            respect_gitignore: doctor_check_respect_gitignore_default(),
+            commands: None,
+            exit_codes: None,
+            brew: None,
+            tool_version: None,
+            base_path: None,
+        }
+    }
 }
 
 /// Definition for fixing the environment.
@@ -33,17 +145,81 @@ pub struct DoctorCheckSpec {
 #[serde(rename_all = "camelCase")]
 #[schemars(deny_unknown_fields)]
 pub struct DoctorFixSpec {
-    /// List of commands to run to fix the env.
+    /// List of commands to run to fix the env. Commands are templates with `{{ working_dir }}`
+    /// and the filters `quote_sh`, `to_upper`, `dirname` and `semver_major` available.
     #[serde(default)]
     pub commands: Vec<String>,
 
-    /// Text to display when no command is provided / fails to fix the env.
+    /// Commands to run when a fix command exits non-zero, to undo what the commands before it
+    /// did. They run in order, stopping at the first one that fails. Templates work the same as
+    /// in `commands`.
+    #[serde(default)]
+    pub rollback: Vec<String>,
+
+    /// Text to display when no command is provided / fails to fix the env. Inline markdown is
+    /// rendered in the terminal.
     #[serde(default)]
     pub help_text: Option<String>,
 
     /// Link to documentation to fix the issue.
     #[serde(default)]
     pub help_url: Option<String>,
+
+    /// Paths, relative to the working directory, to copy into a temporary directory before the
+    /// fix runs. The fix runs from the temporary directory, and the paths are only copied back
+    /// when every command succeeds, so a failing fix can't leave them partially modified.
+    #[serde(default)]
+    pub sandbox_paths: Vec<String>,
+
+    /// State to capture before and after the fix runs. Anything that changed is included in the
+    /// action report, so it's clear what the fix did to the machine.
+    #[serde(default)]
+    pub snapshot: Option<DoctorFixSnapshotSpec>,
+
+    /// Files and directories the fix is expected to create, e.g. `node_modules`. After the fix
+    /// succeeds each one has to exist, or the action fails, a cheap way to check the fix worked
+    /// when a check command isn't practical. Paths are templates, like commands, and relative
+    /// paths are relative to the directory the fix ran in.
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+
+    /// Ask the user to confirm before the fix runs, for fixes that are slow or change things
+    /// outside the project. When there isn't a terminal to ask on, the fix is skipped unless
+    /// `scope doctor run` is given `--auto-approve`.
+    #[serde(default)]
+    pub prompt: Option<DoctorFixPromptSpec>,
+}
+
+/// Question asked before a fix runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorFixPromptSpec {
+    /// Question to ask, e.g. "Reinstall all gems?"
+    pub text: String,
+
+    /// Additional context shown below the question.
+    #[serde(default)]
+    pub extra_context: Option<String>,
+}
+
+/// Values captured before and after a fix runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorFixSnapshotSpec {
+    /// Names of environment variables, as seen by a new login shell.
+    #[serde(default)]
+    pub env: Vec<String>,
+
+    /// Commands whose output is captured, e.g. `node --version`.
+    #[serde(default)]
+    pub commands: Vec<String>,
+
+    /// Files, relative to the working directory, whose checksum is captured. When a text file
+    /// changes, a diff is shown, and the files are listed when the fix asks before running.
+    #[serde(default)]
+    pub files: Vec<String>,
 }
 
 /// An action is a single step used to check in a group. This is most commonly used to build a
@@ -56,12 +232,15 @@ pub struct DoctorGroupActionSpec {
     /// This is used when reporting status to the users.
     pub name: Option<String>,
 
-    /// A description of this specific action, used for information to the users.
+    /// A description of this specific action, used for information to the users. Inline markdown,
+    /// like `**bold**`, `` `code` `` and `[links](https://example.com)`, is rendered in the
+    /// terminal and kept as-is in reports.
     pub description: Option<String>,
 
     /// The `check` run before `fix` (if provided). A check is used to determine if the fix needs
     /// to be executed, or fail the action if no fix is provided. If a fix is specified, the check
     /// will re-execute to ensure that the fix applied correctly.
+    #[serde(default)]
     pub check: DoctorCheckSpec,
 
     /// A fix defines how to fix the issue that a `check` is validating. When provided, will only
@@ -72,6 +251,89 @@ pub struct DoctorGroupActionSpec {
     /// to `true`.
     #[serde(default = "doctor_group_action_required_default")]
     pub required: bool,
+
+    /// If true, a failure of this action is recorded, and marked as allowed in `--output json`,
+    /// but doesn't fail the group or the run. `required` still decides whether the rest of the
+    /// group runs after it fails. Defaults to `false`.
+    #[serde(default)]
+    pub allow_failure: bool,
+
+    /// If true, the action is a setup task, like `direnv allow`, without a check. Its fix runs
+    /// every time the group runs, and isn't cached. The action can't have a `check`. With
+    /// `--fix false` it doesn't run, and doesn't fail the group. Defaults to `false`.
+    #[serde(default)]
+    pub run_always: bool,
+
+    /// Seconds each check and fix command of the action can run before it's stopped, failing
+    /// the action. Without it, commands can run forever, e.g. when waiting on input.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+
+    /// Use an action from a `ScopeActionLibrary`, written as `library/action`. `name`,
+    /// `description`, `check`, `fix`, `timeoutSeconds`, `runtime`, `platforms`, `onlyIf` and
+    /// `workingDir` set here replace the library's, `env` and `labels` are merged over the
+    /// library's, `runAlways` is set when either sets it, and `required` and `allowFailure`
+    /// always come from the group.
+    #[serde(default)]
+    pub use_action: Option<String>,
+
+    /// Parameters available to the action's templates as `{{ params.<name> }}`. When the action
+    /// comes from a library, these override the library's `parameters`.
+    #[serde(default)]
+    pub with: BTreeMap<String, String>,
+
+    /// Run the action's checks and fixes in a container instead of on this machine, replacing
+    /// the group's `runtime`.
+    #[serde(default)]
+    pub runtime: Option<DoctorRuntimeSpec>,
+
+    /// Environment variables set for the action's check, fix and rollback commands, on top of
+    /// the group's `env`. Values are templates, like commands.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Platforms the action applies to, e.g. `[macos, linux]`. On any other platform the action
+    /// is not applicable, it's skipped without running its check and doesn't fail the group.
+    /// When empty, the action applies everywhere.
+    #[serde(default)]
+    pub platforms: Vec<DoctorPlatform>,
+
+    /// Condition checked before the action's check runs. When its command exits non-zero, the
+    /// action is not applicable and skipped, the same as with `platforms`.
+    #[serde(default)]
+    pub only_if: Option<DoctorOnlyIfSpec>,
+
+    /// Key/value pairs describing the action, on top of the group's `metadata.labels`, e.g.
+    /// `risk: safe`. A `ScopePromptPolicy` can answer the prompt of the fix based on them.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+
+    /// Directory the action's check, fix, rollback and `onlyIf` commands run in, instead of the
+    /// directory scope runs in, e.g. a package of a monorepo. It's a template, like commands. A
+    /// relative path is relative to the file the group is defined in,
+    /// `{{ working_dir }}/packages/api` is relative to the directory scope runs in.
+    #[serde(default)]
+    pub working_dir: Option<String>,
+}
+
+/// A platform, named the same as Rust's `std::env::consts::OS`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum DoctorPlatform {
+    Macos,
+    Linux,
+    Windows,
+}
+
+/// A command deciding whether an action or a group applies to this machine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorOnlyIfSpec {
+    /// Command that exits 0 when it applies, e.g. `test -f Brewfile`. It's a template, like the
+    /// check commands, and relative paths start with `.`, the same as commands.
+    pub command: String,
 }
 
 fn doctor_group_action_required_default() -> bool {
@@ -103,25 +365,115 @@ pub struct DoctorGroupSpec {
     /// When a report is built, the commands will be run and automatically included in the report.
     #[serde(default)]
     pub report_extra_details: BTreeMap<String, String>,
+
+    /// Limit when the group is allowed to run. Useful for expensive checks, like a full dependency
+    /// audit, that don't need to run on every invocation. When the schedule doesn't allow the
+    /// group to run, it will be skipped.
+    #[serde(default)]
+    pub schedule: Option<DoctorGroupScheduleSpec>,
+
+    /// Run the group's checks and fixes on another machine, e.g. `ssh://user@host` or
+    /// `ssh://user@host:2222`. Commands are run with the local `ssh` client from the remote
+    /// user's login directory, so scripts referenced by the group must exist at the same path on
+    /// the target. `sandboxPaths`, snapshot files, and check file caches always refer to the local
+    /// machine.
+    #[serde(default)]
+    pub target: Option<String>,
+
+    /// Directory to keep this group's file cache in, instead of the shared cache dir. Supports
+    /// `{{ working_dir }}`, and relative paths are relative to the directory containing this
+    /// file. `--cache-dir` does not change it.
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+
+    /// Variables available to the group's templates as `{{ vars.<name> }}`, so groups that only
+    /// differ by a value like a version don't need to be repeated. Values are templates too, with
+    /// `{{ working_dir }}` and the environment of `scope` as `{{ env.<NAME> }}`, e.g.
+    /// `{{ env.NODE_VERSION | default('20.11.0') }}` lets each user override the default.
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+
+    /// Run the group's checks and fixes in a container instead of on this machine, so toolchains
+    /// can be checked without installing them locally. Can't be used with `target`.
+    #[serde(default)]
+    pub runtime: Option<DoctorRuntimeSpec>,
+
+    /// Environment variables set for every check, fix and rollback command in the group, e.g.
+    /// `NODE_ENV: development`. Values are templates, like commands, so they can use
+    /// `{{ vars.<name> }}` or `{{ env.<NAME> }}`. An action's `env` is merged on top.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Platforms the group applies to, e.g. `[macos]`. On any other platform the group is
+    /// skipped as not applicable, which doesn't stop the groups that need it.
+    #[serde(default)]
+    pub platforms: Vec<DoctorPlatform>,
+
+    /// Condition checked before the group runs. When its command exits non-zero, the group is
+    /// skipped as not applicable, the same as with `platforms`.
+    #[serde(default)]
+    pub only_if: Option<DoctorOnlyIfSpec>,
+
+    /// Executables the group's commands need, e.g. `[node, pnpm]`. Right before the group runs,
+    /// they're looked up on the `PATH`, and when any are missing the group fails listing them,
+    /// instead of running the actions. Not needed when every action runs in a container
+    /// `runtime`.
+    #[serde(default)]
+    pub required_tools: Vec<String>,
 }
 
-/// Configure how a groups will be used when determining the task graph.
+/// A container to run commands in. Each command runs in a new container from `image` with the
+/// local `docker` client, with the working directory mounted at the same path. Only changes to
+/// the working directory outlive a command, and check file caches always refer to the local
+/// machine.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorRuntimeSpec {
+    /// Image to run commands in, e.g. `node:20-bookworm`. The image must have `sh`.
+    pub image: String,
+}
+
+/// Constraints on when a group will run. All provided constraints must be met for the group to
+/// run.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorGroupScheduleSpec {
+    /// Minimum time between successful runs of the group, e.g. `30m`, `12h`, `1d` or `1w`.
+    /// The last successful run is read from the run history stored in the cache directory.
+    #[serde(default)]
+    pub interval: Option<String>,
+
+    /// Only run the group between these hours, in local time.
+    #[serde(default)]
+    pub hours: Option<DoctorGroupScheduleHoursSpec>,
+}
+
+/// A window of hours in local time. When `end` is less than `start` the window wraps past
+/// midnight.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct DoctorGroupScheduleHoursSpec {
+    /// First hour (0-23) the group is allowed to run.
+    pub start: u32,
+    /// Hour (0-24) the group stops being allowed to run, exclusive.
+    pub end: u32,
+}
+
+/// Configure how a groups will be used when determining the task graph.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum DoctorInclude {
     /// Default option, the group will be included by default when determining which groups should
     /// run.
+    #[default]
     ByDefault,
     /// Useful for shared configuration. The group will not run unless another group depends on it.
     WhenRequired,
 }
 
-impl Default for DoctorInclude {
-    fn default() -> Self {
-        Self::ByDefault
-    }
-}
-
 #[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
 pub enum DoctorGroupKind {
     #[strum(serialize = "ScopeDoctorGroup")]
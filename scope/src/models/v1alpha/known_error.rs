@@ -10,11 +10,48 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "camelCase")]
 #[schemars(deny_unknown_fields)]
 pub struct KnownErrorSpec {
-    /// Text that the user can use to fix the issue
+    /// Text that the user can use to fix the issue. Named capture groups from `pattern`, like
+    /// `(?P<port>\d+)`, can be used as `{{ port }}`.
     pub help: String,
 
     /// A Regex used to determine if the line is an error.
     pub pattern: String,
+
+    /// When multiple known errors match the same line, errors with a higher priority are
+    /// evaluated first. Errors with the same priority are ordered by the length of their
+    /// `pattern`, longest first. Defaults to `0`.
+    #[serde(default)]
+    pub priority: i32,
+
+    /// Number of lines before the current one that `pattern` is also matched against, for
+    /// errors that span several lines like stack traces. The lines are joined with `\n`, and `^`
+    /// and `$` match at the start and end of each line. Defaults to `0`, matching one line at a
+    /// time.
+    #[serde(default)]
+    pub context_lines: u32,
+
+    /// Reuse the fix of a doctor action, written as `group/action`, instead of repeating its
+    /// commands here. The reference is resolved when config is loaded.
+    #[serde(default)]
+    pub fix_ref: Option<String>,
+
+    /// How serious the error is, `info`, `warning` or `error`. It's shown when the error is found,
+    /// and `scope analyze --fail-on` uses it to decide the exit code. Defaults to `error`.
+    #[serde(default)]
+    pub severity: KnownErrorSeveritySpec,
+}
+
+/// How serious a known error is.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum KnownErrorSeveritySpec {
+    /// Worth knowing about, nothing has to be done.
+    Info,
+    /// Likely to cause problems, but doesn't fail `scope analyze` by default.
+    Warning,
+    /// Has to be fixed, `scope analyze` exits non-zero when it's found.
+    #[default]
+    Error,
 }
 
 #[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
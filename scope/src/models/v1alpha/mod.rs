@@ -2,9 +2,15 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use strum::EnumString;
 
+mod action_library;
 mod doctor_group;
 mod known_error;
+mod policy;
+mod prompt_policy;
+mod redaction;
+mod report_definition;
 mod report_location;
+mod scope_config;
 
 #[derive(
     Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, EnumString, JsonSchema,
@@ -18,7 +24,13 @@ pub enum V1AlphaApiVersion {
 }
 
 pub mod prelude {
+    pub use super::action_library::*;
     pub use super::doctor_group::*;
     pub use super::known_error::*;
+    pub use super::policy::*;
+    pub use super::prompt_policy::*;
+    pub use super::redaction::*;
+    pub use super::report_definition::*;
     pub use super::report_location::*;
+    pub use super::scope_config::*;
 }
@@ -0,0 +1,93 @@
+use crate::models::core::ModelMetadata;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Rules that a project's own config can't opt out of.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct PolicySpec {
+    /// Groups, or `group/action` pairs, that always run. They can't be skipped with `--only` or
+    /// `include: when-required`.
+    #[serde(default)]
+    pub mandatory: Vec<String>,
+
+    /// Groups, or `group/action` pairs, whose fixes always ask before running, even with
+    /// `--auto-approve`. Fixes without a `prompt` get a generic one.
+    #[serde(default)]
+    pub require_confirmation: Vec<String>,
+
+    /// Glob patterns for the destinations reports may be uploaded to. When set, uploads to any
+    /// other destination are refused. Destinations are matched as `https://github.com/owner/repo`
+    /// for GitHub issues, `s3://bucket/prefix` for S3, `file://directory` for local files and
+    /// the url for RustyPaste and webhooks.
+    #[serde(default)]
+    pub report_destinations: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum PolicyKind {
+    #[strum(serialize = "ScopePolicy")]
+    ScopePolicy,
+}
+
+/// A `ScopePolicy` lets a central config, like one shipped next to the `scope` binary, enforce
+/// rules on every project: groups that must run, fixes that must be confirmed, and where
+/// reports may go.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaPolicy {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: PolicyKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: PolicySpec,
+}
+
+impl HelpMetadata for V1AlphaPolicy {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<PolicySpec> for V1AlphaPolicy {
+    fn api_version(&self) -> String {
+        V1AlphaPolicy::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaPolicy::int_kind()
+    }
+
+    fn spec(&self) -> &PolicySpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<PolicySpec, V1AlphaPolicy> for V1AlphaPolicy {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        PolicyKind::ScopePolicy.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/Policy.yaml".to_string()]
+    }
+}
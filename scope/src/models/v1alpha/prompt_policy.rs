@@ -0,0 +1,115 @@
+use crate::models::core::ModelMetadata;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Rules that answer the prompts of fixes, so runs don't have to ask about every one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct PromptPolicySpec {
+    /// Rules in the order they're checked, the first one that matches a fix decides.
+    pub rules: Vec<PromptRuleSpec>,
+}
+
+/// Which fixes a rule applies to, and how their prompts are answered. Every field that is set
+/// has to match, a rule without any applies to every fix.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct PromptRuleSpec {
+    /// Groups, or `group/action` pairs, the rule applies to.
+    #[serde(default)]
+    pub actions: Vec<String>,
+
+    /// Labels the action has to have. An action has its group's labels, with its own `labels`
+    /// on top.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+
+    /// Annotations the action's group has to have.
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+
+    /// How the prompt is answered.
+    pub decision: PromptDecisionSpec,
+}
+
+/// How the prompt of a fix is answered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum PromptDecisionSpec {
+    /// Run the fix without asking.
+    Approve,
+    /// Skip the fix without asking.
+    Deny,
+    /// Ask, even with `--auto-approve`. Fixes without a `prompt` get a generic one.
+    Ask,
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum PromptPolicyKind {
+    #[strum(serialize = "ScopePromptPolicy")]
+    ScopePromptPolicy,
+}
+
+/// A `ScopePromptPolicy` decides how the prompts of fixes are answered, based on the labels of
+/// the actions and the annotations of their groups, e.g. to run fixes labeled `risk: safe`
+/// without asking.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaPromptPolicy {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: PromptPolicyKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: PromptPolicySpec,
+}
+
+impl HelpMetadata for V1AlphaPromptPolicy {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<PromptPolicySpec> for V1AlphaPromptPolicy {
+    fn api_version(&self) -> String {
+        V1AlphaPromptPolicy::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaPromptPolicy::int_kind()
+    }
+
+    fn spec(&self) -> &PromptPolicySpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<PromptPolicySpec, V1AlphaPromptPolicy> for V1AlphaPromptPolicy {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        PromptPolicyKind::ScopePromptPolicy.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/PromptPolicy.yaml".to_string()]
+    }
+}
@@ -0,0 +1,80 @@
+use crate::models::core::ModelMetadata;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Patterns to remove from captured output and reports, in addition to the built-in ones.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct RedactionSpec {
+    /// Regexes to replace with `[REDACTED]`. The whole match is replaced, and counted under
+    /// `metadata.name` when `scope report --dry-run` lists the redactions that fired.
+    pub patterns: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum RedactionKind {
+    #[strum(serialize = "ScopeRedaction")]
+    ScopeRedaction,
+}
+
+/// A `ScopeRedaction` adds patterns for secrets that are specific to a project, like internal
+/// token formats. They apply everywhere built-in redaction does: command output, reports and
+/// uploads.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaRedaction {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: RedactionKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: RedactionSpec,
+}
+
+impl HelpMetadata for V1AlphaRedaction {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<RedactionSpec> for V1AlphaRedaction {
+    fn api_version(&self) -> String {
+        V1AlphaRedaction::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaRedaction::int_kind()
+    }
+
+    fn spec(&self) -> &RedactionSpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<RedactionSpec, V1AlphaRedaction> for V1AlphaRedaction {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        RedactionKind::ScopeRedaction.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/Redaction.yaml".to_string()]
+    }
+}
@@ -0,0 +1,119 @@
+use crate::models::core::ModelMetadata;
+use crate::models::v1alpha::prelude::ReportDestinationTemplates;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Templates and data that can be shared by multiple `ScopeReportLocation`s.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportDefinitionSpec {
+    #[serde(default)]
+    /// Templates to use when rendering a report. A `ScopeReportLocation` using this definition
+    /// can override any of these templates.
+    pub templates: ReportDestinationTemplates,
+
+    #[serde(default)]
+    /// defines additional data that needs to be pulled from the system when reporting a bug.
+    /// `additionalData` is a map of `string:string`, the value is a command that should be run.
+    /// A `ScopeReportLocation` using this definition will run these commands, in addition to
+    /// its own.
+    pub additional_data: BTreeMap<String, String>,
+
+    #[serde(default)]
+    /// Files to bundle into a `.tar.gz` that's uploaded with the report. Everything in the
+    /// bundle is redacted like the report. When not set, reports don't have attachments. GitHub
+    /// issues get them in a secret gist, which anyone with its URL can read.
+    pub attachments: Option<ReportAttachmentsSpec>,
+}
+
+/// What goes into the attachments of a report.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportAttachmentsSpec {
+    #[serde(default)]
+    /// Glob patterns of log files to include, relative to the directory of the file that defines
+    /// the `ScopeReportDefinition`. Patterns that don't match any file are skipped.
+    pub logs: Vec<String>,
+
+    #[serde(default = "default_include")]
+    /// Include a snapshot of the loaded scope config. Defaults to `true`.
+    pub config: bool,
+
+    #[serde(default = "default_include")]
+    /// Include the results of the doctor run as JSON, for reports from `scope doctor run`.
+    /// Defaults to `true`.
+    pub doctor_result: bool,
+}
+
+fn default_include() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum ReportDefinitionKind {
+    #[strum(serialize = "ScopeReportDefinition")]
+    ScopeReportDefinition,
+}
+
+/// A `ScopeReportDefinition` defines named templates that are reusable across multiple
+/// `ScopeReportLocation`s.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaReportDefinition {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: ReportDefinitionKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: ReportDefinitionSpec,
+}
+
+impl HelpMetadata for V1AlphaReportDefinition {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<ReportDefinitionSpec> for V1AlphaReportDefinition {
+    fn api_version(&self) -> String {
+        V1AlphaReportDefinition::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaReportDefinition::int_kind()
+    }
+
+    fn spec(&self) -> &ReportDefinitionSpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<ReportDefinitionSpec, V1AlphaReportDefinition> for V1AlphaReportDefinition {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        ReportDefinitionKind::ScopeReportDefinition.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/ReportDefinition.yaml".to_string()]
+    }
+}
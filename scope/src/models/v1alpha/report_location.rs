@@ -40,6 +40,60 @@ pub struct ReportDestinationLocalSpec {
     pub directory: String,
 }
 
+/// Upload the report to an S3 bucket. Credentials come from the standard AWS environment and
+/// config files, like `AWS_PROFILE` or `AWS_ACCESS_KEY_ID`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportDestinationS3Spec {
+    /// Name of the bucket to upload the report to
+    pub bucket: String,
+
+    #[serde(default)]
+    /// Prefix for the object key, reports are uploaded to `<prefix>/scope-<id>.md`
+    pub prefix: Option<String>,
+
+    #[serde(default)]
+    /// Region of the bucket. When not set, the region comes from the AWS environment and config.
+    pub region: Option<String>,
+
+    #[serde(default)]
+    /// Server-side encryption to request when the report is stored
+    pub server_side_encryption: Option<S3ServerSideEncryption>,
+
+    #[serde(default)]
+    /// KMS key used with `aws:kms` encryption. When not set, the bucket's default key is used.
+    pub kms_key_id: Option<String>,
+}
+
+/// POST the report as JSON to a webhook, like Slack, Jira or an internal ticketing system
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportDestinationWebhookSpec {
+    /// URL the report is sent to
+    pub url: String,
+
+    #[serde(default)]
+    /// Headers added to the request, for example for auth. `${NAME}` in a value is replaced with
+    /// the environment variable `NAME`, so secrets don't need to be in the config.
+    pub headers: BTreeMap<String, String>,
+
+    #[serde(default)]
+    /// Template for the request body. This is a Jinja2 style template that should render JSON,
+    /// with `title`, `body`, `runId` and `metadata` provided as variables. By default, those
+    /// variables are sent as a JSON object.
+    pub payload: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+pub enum S3ServerSideEncryption {
+    #[serde(rename = "AES256")]
+    Aes256,
+    #[serde(rename = "aws:kms")]
+    AwsKms,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[schemars(deny_unknown_fields)]
@@ -47,8 +101,40 @@ pub enum ReportDestinationSpec {
     RustyPaste(ReportDestinationRustyPasteSpec),
     GithubIssue(ReportDestinationGithubIssueSpec),
     Local(ReportDestinationLocalSpec),
+    S3(ReportDestinationS3Spec),
+    Webhook(ReportDestinationWebhookSpec),
+}
+
+/// A report template, written in the config or kept in a file of its own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(untagged)]
+pub enum ReportTemplateSpec {
+    Inline(String),
+    Source(ReportTemplateSourceSpec),
+}
+
+/// Where to find a report template. Exactly one of `template` and `templateFile` must be set.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportTemplateSourceSpec {
+    #[serde(default)]
+    /// The template itself
+    pub template: Option<String>,
+
+    #[serde(default)]
+    /// File with the template, relative to the file this resource is defined in
+    pub template_file: Option<String>,
+}
+
+impl From<&str> for ReportTemplateSpec {
+    fn from(template: &str) -> Self {
+        ReportTemplateSpec::Inline(template.to_string())
+    }
 }
 
+/// Templates used to render a report. All templates can use the filters `quote_sh`, `to_upper`,
+/// `dirname` and `semver_major`.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
 #[schemars(deny_unknown_fields)]
@@ -59,13 +145,15 @@ pub struct ReportDestinationTemplates {
 
     /// Template to use when generating a bug report with `scope doctor`
     /// A Jinja2 style template, to be included. The text should be in Markdown format. Scope
-    /// injects `command` as the command that was run.
-    pub doctor: Option<String>,
+    /// injects `command` as the command that was run. Either the template itself, or an object
+    /// with the `template` or a `templateFile` to read it from.
+    pub doctor: Option<ReportTemplateSpec>,
 
     /// Template to use when generating a bug without with analyze or intercept
     /// A Jinja2 style template, to be included. The text should be in Markdown format. Scope
-    /// injects `command` as the command that was run.
-    pub analyze: Option<String>,
+    /// injects `command` as the command that was run. Either the template itself, or an object
+    /// with the `template` or a `templateFile` to read it from.
+    pub analyze: Option<ReportTemplateSpec>,
 
     #[serde(default, flatten)]
     /// Additional templates, when provided they will be available to `doctor` or `command` templates.
@@ -74,6 +162,61 @@ pub struct ReportDestinationTemplates {
     pub extra_definitions: BTreeMap<String, String>,
 }
 
+impl ReportDestinationTemplates {
+    /// Combine these templates with `overrides`. Any template set in `overrides` will replace
+    /// the one defined here.
+    pub fn with_overrides(&self, overrides: &ReportDestinationTemplates) -> Self {
+        let mut extra_definitions = self.extra_definitions.clone();
+        extra_definitions.extend(overrides.extra_definitions.clone());
+
+        Self {
+            title: overrides.title.clone().or_else(|| self.title.clone()),
+            doctor: overrides.doctor.clone().or_else(|| self.doctor.clone()),
+            analyze: overrides.analyze.clone().or_else(|| self.analyze.clone()),
+            extra_definitions,
+        }
+    }
+}
+
+/// Limits on uploads to a location, so many reports at once don't trip the destination's rate
+/// limits. The limits apply to the uploads of one `scope` process.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ReportRateLimitSpec {
+    #[serde(default = "default_max_concurrent")]
+    /// Most uploads to this location in flight at once. Defaults to `1`.
+    pub max_concurrent: u32,
+
+    #[serde(default)]
+    /// Most uploads to this location started each minute. When not set, uploads aren't spaced
+    /// out.
+    pub per_minute: Option<u32>,
+
+    #[serde(default = "default_max_retries")]
+    /// Times an upload the destination refused with HTTP 429 is retried, waiting as long as the
+    /// destination asks, or backing off when it doesn't say. Defaults to `3`.
+    pub max_retries: u32,
+}
+
+fn default_max_concurrent() -> u32 {
+    1
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+impl Default for ReportRateLimitSpec {
+    fn default() -> Self {
+        Self {
+            max_concurrent: default_max_concurrent(),
+            per_minute: None,
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
 /// Define where to upload the report to
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
 #[serde(rename_all = "camelCase")]
@@ -84,6 +227,11 @@ pub struct ReportLocationSpec {
     /// Destination the report should be uploaded to
     pub destination: ReportDestinationSpec,
 
+    #[serde(default)]
+    /// Name of a `ScopeReportDefinition` to use for templates and additional data. Templates
+    /// defined on this location override the templates of the same name in the definition.
+    pub report_definition: Option<String>,
+
     #[serde(default)]
     /// Templates to use when uploading a report
     pub templates: ReportDestinationTemplates,
@@ -93,6 +241,10 @@ pub struct ReportLocationSpec {
     /// `additionalData` is a map of `string:string`, the value is a command that should be run.
     /// When a report is built, the commands will be run and automatically included in the report.
     pub additional_data: BTreeMap<String, String>,
+
+    #[serde(default)]
+    /// Limits on how fast reports are uploaded to this location
+    pub rate_limit: ReportRateLimitSpec,
 }
 
 #[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
@@ -157,6 +309,8 @@ impl InternalScopeModel<ReportLocationSpec, V1AlphaReportLocation> for V1AlphaRe
             "v1alpha/ReportLocation.github.yaml".to_string(),
             "v1alpha/ReportLocation.rustyPaste.yaml".to_string(),
             "v1alpha/ReportLocation.local.yaml".to_string(),
+            "v1alpha/ReportLocation.s3.yaml".to_string(),
+            "v1alpha/ReportLocation.webhook.yaml".to_string(),
         ]
     }
 }
@@ -0,0 +1,140 @@
+use crate::models::core::ModelMetadata;
+use crate::models::v1alpha::V1AlphaApiVersion;
+use crate::models::{HelpMetadata, InternalScopeModel, ScopeModel};
+use derive_builder::Builder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Settings for how `scope` itself behaves.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ScopeConfigSpec {
+    /// When to color console output. `auto` colors output when it's written to a terminal.
+    /// `--color`, `NO_COLOR` and `FORCE_COLOR` take precedence over this setting.
+    #[serde(default)]
+    pub color: Option<ScopeConfigColorSpec>,
+
+    /// Directory for the doctor cache, run history and audit log. Supports `{{ working_dir }}`, and
+    /// relative paths are relative to the directory containing this file. `--cache-dir` and
+    /// `SCOPE_DOCTOR_CACHE_DIR` take precedence.
+    #[serde(default)]
+    pub cache_dir: Option<String>,
+
+    /// Which commands `scope-intercept` analyzes when they fail.
+    #[serde(default)]
+    pub intercept: ScopeConfigInterceptSpec,
+
+    /// Args added to a subcommand every time it runs, keyed by the subcommand, e.g.
+    /// `doctor run: ["--no-cache"]`. Options given on the command line replace the defaults for
+    /// the same option.
+    #[serde(default)]
+    pub default_args: BTreeMap<String, Vec<String>>,
+}
+
+/// Filters for `scope-intercept`. Patterns are globs, matched against the command's file name
+/// and against the whole command line, e.g. `cargo` or `cargo test *`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ScopeConfigInterceptSpec {
+    /// When set, only commands matching one of these patterns are analyzed.
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Commands matching one of these patterns are never analyzed, even when they're included.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// Exit codes that don't count as a failure for matching commands.
+    #[serde(default)]
+    pub ignore_exit_codes: Vec<ScopeConfigIgnoreExitCodesSpec>,
+
+    /// Commands that the shell hooks printed by `scope intercept install` run through
+    /// `scope-intercept`, e.g. `bundle` or `npm`. Each is the name of an executable, not a
+    /// command line.
+    #[serde(default)]
+    pub commands: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct ScopeConfigIgnoreExitCodesSpec {
+    /// Pattern for the commands these exit codes apply to.
+    pub command: String,
+    /// Exit codes to treat as success.
+    pub exit_codes: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ScopeConfigColorSpec {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Serialize, Deserialize, Debug, strum::Display, Clone, PartialEq, JsonSchema)]
+pub enum ScopeConfigKind {
+    #[strum(serialize = "ScopeConfig")]
+    ScopeConfig,
+}
+
+/// A `ScopeConfig` holds preferences for `scope` itself, rather than checks or reports. When
+/// more than one is found, the one "closest" to the execution dir is used.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Builder, JsonSchema)]
+#[builder(setter(into))]
+#[serde(rename_all = "camelCase")]
+#[schemars(deny_unknown_fields)]
+pub struct V1AlphaScopeConfig {
+    /// API version of the resource
+    pub api_version: V1AlphaApiVersion,
+    /// The type of resource.
+    pub kind: ScopeConfigKind,
+    /// Standard set of options including name, description for the resource.
+    /// Together `kind` and `metadata.name` are required to be unique. If there are duplicate, the
+    /// resources "closest" to the execution dir will take precedence.
+    pub metadata: ModelMetadata,
+    /// Options for the resource.
+    pub spec: ScopeConfigSpec,
+}
+
+impl HelpMetadata for V1AlphaScopeConfig {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        format!("{}/{}", self.kind(), self.name())
+    }
+}
+
+impl ScopeModel<ScopeConfigSpec> for V1AlphaScopeConfig {
+    fn api_version(&self) -> String {
+        V1AlphaScopeConfig::int_api_version()
+    }
+
+    fn kind(&self) -> String {
+        V1AlphaScopeConfig::int_kind()
+    }
+
+    fn spec(&self) -> &ScopeConfigSpec {
+        &self.spec
+    }
+}
+
+impl InternalScopeModel<ScopeConfigSpec, V1AlphaScopeConfig> for V1AlphaScopeConfig {
+    fn int_api_version() -> String {
+        V1AlphaApiVersion::ScopeV1Alpha.to_string()
+    }
+
+    fn int_kind() -> String {
+        ScopeConfigKind::ScopeConfig.to_string()
+    }
+    #[cfg(test)]
+    fn examples() -> Vec<String> {
+        vec!["v1alpha/ScopeConfig.yaml".to_string()]
+    }
+}
@@ -1,32 +1,59 @@
 use crate::prelude::{
-    DefaultExecutionProvider, DefaultUnstructuredReportBuilder, ReportRenderer,
-    UnstructuredReportBuilder,
+    DefaultExecutionProvider, DefaultGroupedReportBuilder, DefaultUnstructuredReportBuilder,
+    DoctorRunOutput, GroupedReportBuilder, ReportRenderer, UnstructuredReportBuilder,
 };
-use crate::shared::prelude::{CaptureOpts, FoundConfig, OutputCapture, OutputDestination};
-use anyhow::Result;
+use crate::shared::prelude::{
+    CaptureOpts, FoundConfig, OutputCapture, OutputDestination, RunArtifacts,
+};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "cli")]
 use clap::Args;
+use std::path::Path;
 use std::sync::Arc;
-use tracing::{instrument, warn};
+use tracing::{error, instrument, warn};
 
-#[derive(Debug, Args)]
+#[derive(Debug)]
+#[cfg_attr(feature = "cli", derive(Args))]
 pub struct ReportArgs {
     /// Where the report will be generated, if not set a location will be determined at runtime.
-    #[arg(long, short = 'o')]
+    #[cfg_attr(feature = "cli", arg(long, short = 'o'))]
+    #[cfg_attr(not(feature = "cli"), allow(dead_code))]
     report_location: Option<String>,
 
+    /// Print where the report would be sent and exactly what it would contain, without sending it.
+    #[cfg_attr(feature = "cli", arg(long, default_value = "false"))]
+    dry_run: bool,
+
+    /// Report on the result of an earlier `scope doctor run`, saved in its artifact directory,
+    /// instead of running a command. See `scope artifacts path` for the run ids.
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, value_name = "RUN_ID", conflicts_with = "command")
+    )]
+    from_run: Option<String>,
+
     /// The command that should be run and reported on
-    #[arg(last = true, required = true)]
+    #[cfg_attr(
+        feature = "cli",
+        arg(last = true, required_unless_present = "from_run")
+    )]
     command: Vec<String>,
 }
 
-#[instrument("scope report", skip_all)]
+#[instrument("scope report", skip_all, fields(run_id = %found_config.run_id))]
 pub async fn report_root(found_config: &FoundConfig, args: &ReportArgs) -> Result<i32> {
+    if let Some(run_id) = &args.from_run {
+        return report_from_run(found_config, args, run_id).await;
+    }
+
     let capture = OutputCapture::capture_output(CaptureOpts {
         working_dir: &found_config.working_dir,
         args: &args.command,
         output_dest: OutputDestination::Logging,
         path: &found_config.bin_path,
         env_vars: Default::default(),
+        timeout: None,
+        progress: None,
     })
     .await?;
     let exit_code = capture.exit_code.unwrap_or(-1);
@@ -34,7 +61,7 @@ pub async fn report_root(found_config: &FoundConfig, args: &ReportArgs) -> Resul
     let entrypoint = args.command.join(" ");
     let exec_runner = Arc::new(DefaultExecutionProvider::default());
 
-    let builder = DefaultUnstructuredReportBuilder::new(&entrypoint, &capture);
+    let builder = DefaultUnstructuredReportBuilder::new(&entrypoint, Arc::new(capture));
 
     for location in found_config.report_upload.values() {
         let mut builder = builder.clone();
@@ -46,11 +73,15 @@ pub async fn report_root(found_config: &FoundConfig, args: &ReportArgs) -> Resul
             )
             .await
             .ok();
+        if let Err(e) = builder.collect_attachments(found_config, location).await {
+            warn!(target: "user", "Unable to collect the report's attachments: {}", e);
+        }
 
         let report = builder.render(location);
 
         match report {
             Err(e) => warn!(target: "user", "Unable to render report: {}", e),
+            Ok(report) if args.dry_run => report.print_dry_run().await,
             Ok(report) => {
                 if let Err(e) = report.distribute().await {
                     warn!(target: "user", "Unable to upload report: {}", e);
@@ -61,3 +92,148 @@ pub async fn report_root(found_config: &FoundConfig, args: &ReportArgs) -> Resul
 
     Ok(exit_code)
 }
+
+/// Build a grouped report from the `doctor-result.json` an earlier doctor run left behind, like
+/// the one `scope doctor run` offers to upload when it fails.
+async fn report_from_run(
+    found_config: &FoundConfig,
+    args: &ReportArgs,
+    run_id: &str,
+) -> Result<i32> {
+    report_from_run_in(found_config, args, &RunArtifacts::root(), run_id).await
+}
+
+async fn report_from_run_in(
+    found_config: &FoundConfig,
+    args: &ReportArgs,
+    root: &Path,
+    run_id: &str,
+) -> Result<i32> {
+    if !RunArtifacts::is_valid_run_id(run_id) {
+        error!(target: "user", "`{}` isn't a run id, see `scope artifacts path` for the run ids", run_id);
+        return Ok(1);
+    }
+    let result_file = RunArtifacts::in_root(root, run_id).doctor_result_file();
+    let output = match std::fs::read_to_string(&result_file) {
+        Ok(output) => output,
+        Err(e) => {
+            error!(target: "user", "There is no doctor result for run `{}` at {}: {}", run_id, result_file.display(), e);
+            return Ok(1);
+        }
+    };
+    let output: DoctorRunOutput = serde_json::from_str(&output)
+        .map_err(|e| anyhow!("Unable to read {}: {}", result_file.display(), e))?;
+
+    let mut builder = DefaultGroupedReportBuilder::new("scope doctor run")
+        .with_run(&output.run_id, &output.fingerprint);
+    for group_report in &output.result.group_reports {
+        builder.append_group(group_report)?;
+    }
+
+    let exec_runner = Arc::new(DefaultExecutionProvider::default());
+    for location in found_config.report_upload.values() {
+        let mut builder = builder.clone();
+        builder
+            .run_and_append_additional_data(
+                found_config,
+                exec_runner.clone(),
+                &location.additional_data,
+            )
+            .await
+            .ok();
+        if let Err(e) = builder.collect_attachments(found_config, location).await {
+            warn!(target: "user", "Unable to collect the report's attachments: {}", e);
+        }
+
+        match builder.render(location) {
+            Err(e) => warn!(target: "user", "Unable to render report: {}", e),
+            Ok(report) if args.dry_run => report.print_dry_run().await,
+            Ok(report) => {
+                if let Err(e) = report.distribute().await {
+                    warn!(target: "user", "Unable to upload report: {}", e);
+                }
+            }
+        }
+    }
+
+    Ok(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{GroupReport, PathRunResult};
+    use crate::shared::prelude::RunFingerprint;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    fn args(run_id: &str) -> ReportArgs {
+        ReportArgs {
+            report_location: None,
+            dry_run: true,
+            from_run: Some(run_id.to_string()),
+            command: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_from_run_reads_the_runs_doctor_result() {
+        let root = assert_fs::TempDir::new().unwrap();
+        let found_config = FoundConfig::empty(root.path().to_path_buf());
+        let artifacts = RunArtifacts::in_root(root.path(), "20240101-aaaa");
+        artifacts.create().unwrap();
+        let output = DoctorRunOutput::new(
+            "20240101-aaaa",
+            &RunFingerprint::new(&[]),
+            PathRunResult {
+                did_succeed: false,
+                succeeded_groups: BTreeSet::new(),
+                failed_group: BTreeSet::from(["setup".to_string()]),
+                skipped_group: BTreeSet::new(),
+                skip_reasons: BTreeMap::new(),
+                soft_failed_groups: BTreeSet::new(),
+                group_reports: vec![GroupReport::new("setup")],
+                duration_ms: 0,
+            },
+        );
+        RunArtifacts::write(
+            &artifacts.doctor_result_file(),
+            serde_json::to_string(&output).unwrap().as_bytes(),
+        )
+        .unwrap();
+
+        let exit_code = report_from_run_in(
+            &found_config,
+            &args("20240101-aaaa"),
+            root.path(),
+            "20240101-aaaa",
+        )
+        .await
+        .unwrap();
+        assert_eq!(0, exit_code);
+
+        let exit_code = report_from_run_in(
+            &found_config,
+            &args("20240102-bbbb"),
+            root.path(),
+            "20240102-bbbb",
+        )
+        .await
+        .unwrap();
+        assert_eq!(1, exit_code);
+    }
+
+    #[tokio::test]
+    async fn test_report_from_run_rejects_run_ids_outside_the_root() {
+        let root = assert_fs::TempDir::new().unwrap();
+        let found_config = FoundConfig::empty(root.path().to_path_buf());
+        RunArtifacts::write(&root.path().join("doctor-result.json"), b"{}").unwrap();
+        let runs = root.path().join("runs");
+
+        for run_id in ["..", "../..", "a/..", "/tmp", ""] {
+            let exit_code = report_from_run_in(&found_config, &args(run_id), &runs, run_id)
+                .await
+                .unwrap();
+            assert_eq!(1, exit_code, "{}", run_id);
+        }
+    }
+}
@@ -1,10 +0,0 @@
-use thiserror::Error;
-
-#[derive(Error, Debug)]
-pub enum ConfigError {
-    #[error("Unable to process file. {error:?}")]
-    IoError {
-        #[from]
-        error: std::io::Error,
-    },
-}
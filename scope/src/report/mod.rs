@@ -1,5 +1,4 @@
 mod cli;
-mod error;
 
 pub mod prelude {
     pub use super::cli::report_root;
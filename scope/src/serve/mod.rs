@@ -0,0 +1,455 @@
+use crate::doctor::{run, DoctorRunOptions, DoctorRunOutcome, DoctorRunOutput};
+use crate::prelude::{
+    analyze_text, configure_redactions, AnalyzeOptions, ConfigOptions, DoctorRunArgs, FoundConfig,
+    KnownErrorMatch, NoInteraction, RegexKnownErrorMatcher,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::RwLock;
+use tracing::debug;
+
+/// Error codes from the JSON-RPC 2.0 spec.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+/// A JSON-RPC 2.0 request. Without an `id` it's a notification, which isn't answered.
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Result of `config/load`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LoadedConfig {
+    working_dir: PathBuf,
+    doctor_groups: Vec<String>,
+    known_errors: Vec<String>,
+}
+
+/// Params of `analyze/text`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzeTextParams {
+    text: String,
+    #[serde(default)]
+    options: AnalyzeOptions,
+}
+
+/// Result of `analyze/text`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzedText {
+    known_errors: Vec<KnownErrorMatch>,
+}
+
+/// Answers JSON-RPC 2.0 requests, one per line, with the config loaded once, so editors and other
+/// tools can use scope without starting it, and loading the config, for every call.
+///
+/// - `config/load` loads the config again, after it changed.
+/// - `doctor/run` runs doctor, its params are the options of `scope doctor run`.
+/// - `analyze/text` finds the known errors in `text`.
+///
+/// Nothing is asked on the terminal, fixes that need confirmation are skipped unless
+/// `autoApprove` is set.
+pub struct Server {
+    config_options: ConfigOptions,
+    found_config: RwLock<Arc<FoundConfig>>,
+}
+
+impl Server {
+    /// `found_config` was loaded with `config_options`, which `config/load` uses to load it again.
+    pub fn new(config_options: ConfigOptions, found_config: FoundConfig) -> Self {
+        Self {
+            config_options,
+            found_config: RwLock::new(Arc::new(found_config)),
+        }
+    }
+
+    /// Answer requests from `input` until it ends, writing each response to `output` on a line of
+    /// its own. Requests are answered one at a time, in the order they arrive.
+    pub async fn serve<R, W>(&self, input: R, mut output: W) -> std::io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = input.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line).await {
+                output.write_all(response.as_bytes()).await?;
+                output.write_all(b"\n").await?;
+                output.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to the request on `line`, `None` for a notification.
+    pub async fn handle(&self, line: &str) -> Option<String> {
+        let request = match serde_json::from_str::<Value>(line) {
+            Err(e) => {
+                return Some(respond(
+                    Value::Null,
+                    Err(ResponseError::new(PARSE_ERROR, e)),
+                ))
+            }
+            Ok(value) => match serde_json::from_value::<Request>(value) {
+                Ok(request) if request.jsonrpc == "2.0" => request,
+                Ok(_) => {
+                    return Some(respond(
+                        Value::Null,
+                        Err(ResponseError::new(
+                            INVALID_REQUEST,
+                            "jsonrpc should be \"2.0\"",
+                        )),
+                    ))
+                }
+                Err(e) => {
+                    return Some(respond(
+                        Value::Null,
+                        Err(ResponseError::new(INVALID_REQUEST, e)),
+                    ))
+                }
+            },
+        };
+
+        debug!("Handling {} {:?}", request.method, request.id);
+        let result = self.call(&request.method, request.params).await;
+        Some(respond(request.id?, result))
+    }
+
+    async fn call(&self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "config/load" => self.load_config().await,
+            "doctor/run" => self.run_doctor(parse_params(params)?).await,
+            "analyze/text" => self.analyze_text(parse_params(params)?).await,
+            _ => Err(ResponseError::new(
+                METHOD_NOT_FOUND,
+                format!("No method named `{}`", method),
+            )),
+        }
+    }
+
+    async fn load_config(&self) -> Result<Value, ResponseError> {
+        let found_config = self
+            .config_options
+            .load_config()
+            .await
+            .map_err(|e| ResponseError::new(INTERNAL_ERROR, e))?;
+        configure_redactions(found_config.redaction_patterns());
+
+        let loaded = LoadedConfig {
+            working_dir: found_config.working_dir.clone(),
+            doctor_groups: found_config.doctor_group.keys().cloned().collect(),
+            known_errors: found_config.known_error.keys().cloned().collect(),
+        };
+        *self.found_config.write().await = Arc::new(found_config);
+        to_result(&loaded)
+    }
+
+    /// Runs with the config loaded when it starts, a `config/load` while it runs doesn't wait for
+    /// it to finish.
+    async fn run_doctor(&self, args: DoctorRunArgs) -> Result<Value, ResponseError> {
+        let found_config = self.found_config.read().await.clone();
+        let options = DoctorRunOptions::new(args).interaction(Arc::new(NoInteraction));
+        match run(&found_config, &options)
+            .await
+            .map_err(|e| ResponseError::new(INTERNAL_ERROR, e))?
+        {
+            DoctorRunOutcome::Planned(plan) => to_result(&plan),
+            DoctorRunOutcome::Completed(result) => to_result(&DoctorRunOutput::new(
+                &found_config.run_id,
+                &found_config.fingerprint,
+                result,
+            )),
+        }
+    }
+
+    async fn analyze_text(&self, params: AnalyzeTextParams) -> Result<Value, ResponseError> {
+        let found_config = self.found_config.read().await.clone();
+        let known_errors = analyze_text(
+            &found_config,
+            &params.options,
+            &RegexKnownErrorMatcher,
+            &params.text,
+        )
+        .await
+        .map_err(|e| ResponseError::new(INTERNAL_ERROR, e))?;
+        to_result(&AnalyzedText { known_errors })
+    }
+}
+
+/// Params that aren't given are the same as `{}`, so every field takes its default.
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| ResponseError::new(INVALID_PARAMS, e))
+}
+
+fn to_result<T: Serialize>(value: &T) -> Result<Value, ResponseError> {
+    serde_json::to_value(value).map_err(|e| ResponseError::new(INTERNAL_ERROR, e))
+}
+
+fn respond(id: Value, result: Result<Value, ResponseError>) -> String {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    let response = Response {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    };
+    serde_json::to_string(&response).expect("a response can always be written as JSON")
+}
+
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::Args;
+    use std::path::PathBuf;
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct ServeArgs {
+        /// Listen for connections on this Unix socket, instead of reading requests from stdin
+        /// and writing the responses to stdout
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub socket: Option<PathBuf>,
+    }
+}
+
+pub mod commands {
+    use super::Server;
+    use crate::prelude::{
+        detach_child_stdin, redirect_stdout_to_stderr, ConfigOptions, FoundConfig, ServeArgs,
+    };
+    use anyhow::Result;
+    use std::sync::Arc;
+    use tokio::io::BufReader;
+    use tracing::instrument;
+
+    #[instrument("scope serve", skip_all)]
+    pub async fn serve_root(
+        found_config: &FoundConfig,
+        config_options: &ConfigOptions,
+        args: &ServeArgs,
+    ) -> Result<i32> {
+        let server = Arc::new(Server::new(config_options.clone(), found_config.clone()));
+        match &args.socket {
+            Some(path) => serve_socket(server, path).await?,
+            None => {
+                // Stdout only has responses on it, and stdin only requests.
+                redirect_stdout_to_stderr();
+                detach_child_stdin();
+                server
+                    .serve(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+                    .await?;
+            }
+        }
+        Ok(0)
+    }
+
+    /// Answer the requests of every connection to the socket at `path`, until Ctrl-C is pressed.
+    /// A socket left behind by an earlier server is replaced.
+    #[cfg(unix)]
+    async fn serve_socket(server: Arc<Server>, path: &std::path::Path) -> Result<()> {
+        use std::os::unix::fs::FileTypeExt;
+        use tokio::net::UnixListener;
+        use tracing::{info, warn};
+
+        if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)
+            .map_err(|e| anyhow::anyhow!("Unable to listen on {}. {}", path.display(), e))?;
+        info!(target: "user", "Listening on {}", path.display());
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    let server = server.clone();
+                    tokio::spawn(async move {
+                        let (input, output) = stream.into_split();
+                        if let Err(e) = server.serve(BufReader::new(input), output).await {
+                            warn!("Connection closed with an error: {}", e);
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        std::fs::remove_file(path).ok();
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    async fn serve_socket(_server: Arc<Server>, _path: &std::path::Path) -> Result<()> {
+        Err(anyhow::anyhow!(
+            "--socket is only supported on Unix, leave it out to use stdin and stdout"
+        ))
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::ServeArgs;
+    pub use super::commands::serve_root;
+    pub use super::Server;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ModelMetadata;
+    use crate::prelude::{KnownError, KnownErrorSeverity};
+    use regex::Regex;
+
+    fn server() -> Server {
+        let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+        found_config.known_error.insert(
+            "disk-full".to_string(),
+            KnownError {
+                full_name: "ScopeKnownError/disk-full".to_string(),
+                metadata: ModelMetadata::new("disk-full"),
+                pattern: "no space left".to_string(),
+                regex: Regex::new("no space left").unwrap(),
+                help_text: "Free up some space".to_string(),
+                priority: 0,
+                severity: KnownErrorSeverity::Error,
+                context_lines: 0,
+                fix_ref: Some("disk/clean".to_string()),
+                fix: None,
+            },
+        );
+        Server::new(ConfigOptions::default(), found_config)
+    }
+
+    async fn handle(server: &Server, request: Value) -> Value {
+        let response = server.handle(&request.to_string()).await.unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_analyze_text_finds_known_errors() {
+        let server = server();
+        let response = handle(
+            &server,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "analyze/text",
+                "params": {"text": "building\nwrite failed: no space left on device\n"},
+            }),
+        )
+        .await;
+
+        assert_eq!(
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "knownErrors": [{
+                        "name": "ScopeKnownError/disk-full",
+                        "severity": "error",
+                        "helpText": "Free up some space",
+                        "fix": "disk/clean",
+                        "lineNumber": 2,
+                        "line": "write failed: no space left on device",
+                    }],
+                },
+            }),
+            response
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bad_requests_get_errors() {
+        let server = server();
+        let code = |response: Value| response["error"]["code"].as_i64();
+
+        let response = server.handle("{not json").await.unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(Some(PARSE_ERROR), code(response));
+
+        let response = handle(&server, json!({"id": 1, "method": "analyze/text"})).await;
+        assert_eq!(Some(INVALID_REQUEST), code(response));
+
+        let response = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 2, "method": "doctor/fix"}),
+        )
+        .await;
+        assert_eq!(Some(METHOD_NOT_FOUND), code(response.clone()));
+        assert_eq!(json!(2), response["id"]);
+
+        let response = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "id": 3, "method": "analyze/text"}),
+        )
+        .await;
+        assert_eq!(Some(INVALID_PARAMS), code(response));
+    }
+
+    #[tokio::test]
+    async fn test_serve_answers_each_request_on_a_line() {
+        let server = server();
+        let input = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "analyze/text", "params": {"text": "ok"}}),
+            json!({"jsonrpc": "2.0", "method": "analyze/text", "params": {"text": "ok"}}),
+            json!({"jsonrpc": "2.0", "id": 2, "method": "analyze/text", "params": {"text": "ok"}}),
+        ]
+        .map(|request| request.to_string())
+        .join("\n\n");
+        let mut output = Vec::new();
+
+        server.serve(input.as_bytes(), &mut output).await.unwrap();
+
+        let ids: Vec<_> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].clone())
+            .collect();
+        assert_eq!(vec![json!(1), json!(2)], ids);
+    }
+}
@@ -0,0 +1,159 @@
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// Overrides the directory the artifact directories of runs are created in.
+pub const ARTIFACT_DIR_ENV: &str = "SCOPE_ARTIFACT_DIR";
+/// Where the artifact directories of runs are created, in the closest `.scope` dir.
+const ARTIFACT_ROOT: &str = "runs";
+
+/// Files a run leaves behind, kept together in one directory per run id:
+///
+/// ```text
+/// <root>/<run id>/
+///   scope-<binary>.log         the detailed log, e.g. scope-root.log
+///   config.json                the merged config passed to external sub-commands
+///   doctor-result.json         the result of `scope doctor run`, like `--output json`
+///   groups/<group>.log         full output of each group, with `--group-log`
+///   reports/<location>.md      each report that was uploaded, as rendered for the location
+///   reports/<location>.tar.gz  the attachments uploaded with the report, when it has any
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunArtifacts {
+    pub run_id: String,
+    dir: PathBuf,
+}
+
+impl RunArtifacts {
+    /// Artifacts of `run_id`, under [`RunArtifacts::root`].
+    pub fn new(run_id: &str) -> Self {
+        Self::in_root(&Self::root(), run_id)
+    }
+
+    pub fn in_root(root: &Path, run_id: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            dir: root.join(run_id),
+        }
+    }
+
+    /// Directory holding the artifact directory of every run, `SCOPE_ARTIFACT_DIR` when set,
+    /// otherwise `.scope/runs` in the closest `.scope` dir to the current dir. Outside of a
+    /// project with a `.scope` dir, it's `scope/runs` in the temp dir.
+    pub fn root() -> PathBuf {
+        if let Some(dir) = std::env::var_os(ARTIFACT_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            return PathBuf::from(dir);
+        }
+        let closest_scope_dir = std::env::current_dir().ok().and_then(|cwd| {
+            cwd.ancestors()
+                .map(|dir| dir.join(".scope"))
+                .find(|dir| dir.is_dir())
+        });
+        match closest_scope_dir {
+            Some(dir) => dir.join(ARTIFACT_ROOT),
+            None => std::env::temp_dir().join("scope").join(ARTIFACT_ROOT),
+        }
+    }
+
+    /// Whether `run_id` can be used as the name of a run's directory, it has to be a single
+    /// path component so a run id like `../..` can't point outside of the root.
+    pub fn is_valid_run_id(run_id: &str) -> bool {
+        let mut components = Path::new(run_id).components();
+        matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(name)), None) if name == run_id
+        )
+    }
+
+    /// The most recently changed run in `root`, leaving out `except`, usually the current run.
+    pub fn latest(root: &Path, except: &str) -> Option<Self> {
+        std::fs::read_dir(root)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name() != except)
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                metadata.is_dir().then(|| (modified, entry.file_name()))
+            })
+            .max()
+            .map(|(_, run_id)| Self::in_root(root, &run_id.to_string_lossy()))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Create the run's directory.
+    pub fn create(&self) -> std::io::Result<&Path> {
+        std::fs::create_dir_all(&self.dir)?;
+        Ok(&self.dir)
+    }
+
+    /// Log of the `binary` that ran, like `root` for `scope` or `intercept` for `scope-intercept`.
+    pub fn log_file(&self, binary: &str) -> PathBuf {
+        self.dir.join(format!("scope-{}.log", binary))
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.dir.join("config.json")
+    }
+
+    pub fn doctor_result_file(&self) -> PathBuf {
+        self.dir.join("doctor-result.json")
+    }
+
+    pub fn groups_dir(&self) -> PathBuf {
+        self.dir.join("groups")
+    }
+
+    /// Rendered report for the `ScopeReportLocation` named `location`.
+    pub fn report_file(&self, location: &str) -> PathBuf {
+        self.dir.join("reports").join(format!("{}.md", location))
+    }
+
+    /// Attachments uploaded with the report for the `ScopeReportLocation` named `location`.
+    pub fn report_attachments_file(&self, location: &str) -> PathBuf {
+        self.dir
+            .join("reports")
+            .join(format!("{}.tar.gz", location))
+    }
+
+    /// Write `contents` to `path`, creating the directories it's in.
+    pub fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_skips_the_current_run() {
+        let root = assert_fs::TempDir::new().unwrap();
+        for run_id in ["20240101-aaaa", "20240102-bbbb"] {
+            RunArtifacts::in_root(root.path(), run_id).create().unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+
+        let latest = RunArtifacts::latest(root.path(), "20240103-cccc").unwrap();
+        assert_eq!("20240102-bbbb", latest.run_id);
+        assert_eq!(root.path().join("20240102-bbbb"), latest.dir());
+
+        let latest = RunArtifacts::latest(root.path(), "20240102-bbbb").unwrap();
+        assert_eq!("20240101-aaaa", latest.run_id);
+        assert!(RunArtifacts::latest(&root.path().join("missing"), "").is_none());
+    }
+
+    #[test]
+    fn test_run_ids_are_a_single_path_component() {
+        assert!(RunArtifacts::is_valid_run_id("20240101-aaaa"));
+        assert!(RunArtifacts::is_valid_run_id("ci.1234"));
+        for run_id in ["", ".", "..", "../runs", "a/b", "/tmp", "a/"] {
+            assert!(!RunArtifacts::is_valid_run_id(run_id), "{}", run_id);
+        }
+    }
+}
@@ -1,15 +1,22 @@
+use super::console::child_stdin;
+use super::progress::ProgressReporter;
+#[cfg(unix)]
+use super::pty::Pty;
 use super::redact::Redactor;
+use super::spool::{spool_threshold, CapturedLines, LineSpooler, SpooledReader};
+use super::templates::quote_sh;
 use async_trait::async_trait;
 use chrono::{DateTime, Duration, Utc};
 use colored::Colorize;
 use derive_builder::Builder;
+use itertools::Itertools;
 use mockall::automock;
-use std::collections::BTreeMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, VecDeque};
 use std::ffi::OsString;
-use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::io;
 use tokio::io::{AsyncBufReadExt, BufReader};
@@ -17,27 +24,15 @@ use tokio::sync::RwLock;
 use tracing::{debug, error, info, instrument, Level};
 use which::which_in;
 
-#[derive(Debug, Default)]
-struct RwLockOutput {
-    output: RwLock<Vec<(DateTime<Utc>, String)>>,
-}
-
-impl RwLockOutput {
-    async fn add_line(&self, line: &str) {
-        let mut stdout = self.output.write().await;
-        stdout.push((Utc::now(), line.to_string()));
-    }
-}
-
 #[derive(Clone, Default, Builder, Debug)]
 #[builder(setter(into))]
 pub struct OutputCapture {
     #[builder(default)]
     pub working_dir: PathBuf,
     #[builder(default)]
-    stdout: Vec<(DateTime<Utc>, String)>,
+    stdout: CapturedLines,
     #[builder(default)]
-    stderr: Vec<(DateTime<Utc>, String)>,
+    stderr: CapturedLines,
     #[builder(default)]
     pub exit_code: Option<i32>,
     #[builder(default)]
@@ -56,20 +51,33 @@ pub enum OutputDestination {
     Null,
 }
 
-struct StreamCapture<R: io::AsyncRead + Unpin> {
+struct StreamCapture<'a, R: io::AsyncRead + Unpin> {
     reader: R,
     writer: Arc<RwLock<Box<dyn std::io::Write + Send + Sync>>>,
     level: Level,
     dest: OutputDestination,
+    progress: Option<&'a dyn ProgressReporter>,
+    /// Bytes of output kept in memory before the rest is spooled to a file.
+    spool_threshold: usize,
+    /// Remove ANSI escapes, like colors, from each line.
+    strip_ansi: bool,
 }
 
-impl<R: io::AsyncRead + Unpin> StreamCapture<R> {
-    async fn capture_output(self) -> Result<Vec<(DateTime<Utc>, String)>, anyhow::Error> {
-        let captured = RwLockOutput::default();
+impl<'a, R: io::AsyncRead + Unpin> StreamCapture<'a, R> {
+    async fn capture_output(self) -> Result<CapturedLines, anyhow::Error> {
+        let mut captured = LineSpooler::new(self.spool_threshold);
 
         let mut reader = BufReader::new(self.reader).lines();
         while let Some(line) = reader.next_line().await? {
-            captured.add_line(&line).await;
+            let line = if self.strip_ansi {
+                strip_ansi_escapes::strip_str(&line)
+            } else {
+                line
+            };
+            captured.push(Utc::now(), &line).await?;
+            if let Some(progress) = self.progress {
+                progress.output_line(&line);
+            }
             match &self.dest {
                 OutputDestination::Logging => match self.level {
                     Level::ERROR => error!("{}", line),
@@ -85,7 +93,7 @@ impl<R: io::AsyncRead + Unpin> StreamCapture<R> {
             };
         }
 
-        Ok::<_, anyhow::Error>(captured.output.into_inner())
+        Ok(captured.finish().await?)
     }
 }
 
@@ -103,6 +111,15 @@ pub enum CaptureError {
         #[from]
         error: std::string::FromUtf8Error,
     },
+    #[error("No scripted output for `{command}`")]
+    NotScripted { command: String },
+    #[error("`{command}` did not finish within {}s", .timeout.as_secs())]
+    Timeout {
+        command: String,
+        timeout: std::time::Duration,
+    },
+    #[error("Running commands in a pseudo-terminal isn't supported on this platform")]
+    PtyUnsupported,
 }
 
 #[automock]
@@ -119,6 +136,8 @@ pub trait ExecutionProvider: Send + Sync {
                 output_dest: OutputDestination::Null,
                 path,
                 env_vars: Default::default(),
+                timeout: None,
+                progress: None,
             })
             .await;
 
@@ -130,12 +149,362 @@ pub trait ExecutionProvider: Send + Sync {
 }
 
 #[derive(Default, Debug)]
-pub struct DefaultExecutionProvider {}
+pub struct DefaultExecutionProvider {
+    /// Run commands in a pseudo-terminal, see [`OutputCapture::capture_pty_output`].
+    pty: bool,
+}
+
+impl DefaultExecutionProvider {
+    /// Runs commands in a pseudo-terminal, for commands that write different output when it
+    /// isn't a terminal.
+    pub fn with_pty() -> Self {
+        Self { pty: true }
+    }
+}
 
 #[async_trait]
 impl ExecutionProvider for DefaultExecutionProvider {
     async fn run_command<'a>(&self, opts: CaptureOpts<'a>) -> Result<OutputCapture, CaptureError> {
-        OutputCapture::capture_output(opts).await
+        if self.pty {
+            OutputCapture::capture_pty_output_with_timeout(opts).await
+        } else {
+            OutputCapture::capture_output_with_timeout(opts).await
+        }
+    }
+}
+
+/// A remote machine to run commands on, parsed from `ssh://[user@]host[:port]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshTarget {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl SshTarget {
+    pub fn parse(target: &str) -> anyhow::Result<Self> {
+        let url = url::Url::parse(target)?;
+        if url.scheme() != "ssh" {
+            return Err(anyhow::anyhow!(
+                "target `{}` must start with ssh://, e.g. ssh://user@host",
+                target
+            ));
+        }
+        let host = match url.host_str() {
+            Some(host) => host.to_string(),
+            None => return Err(anyhow::anyhow!("target `{}` is missing a host", target)),
+        };
+        let user = match url.username() {
+            "" => None,
+            user => Some(user.to_string()),
+        };
+
+        Ok(Self {
+            user,
+            host,
+            port: url.port(),
+        })
+    }
+}
+
+impl std::fmt::Display for SshTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ssh://")?;
+        if let Some(user) = &self.user {
+            write!(f, "{}@", user)?;
+        }
+        write!(f, "{}", self.host)?;
+        if let Some(port) = self.port {
+            write!(f, ":{}", port)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs commands on a remote machine with the local `ssh` client. Commands run from the login
+/// directory of the remote user, with the remote `PATH`, and their env vars are set with `env` on
+/// the remote machine.
+#[derive(Debug)]
+pub struct SshExecutionProvider {
+    pub target: SshTarget,
+}
+
+impl SshExecutionProvider {
+    pub fn new(target: SshTarget) -> Self {
+        Self { target }
+    }
+
+    /// The ssh invocation is split into arguments by `env -S`, the remote command is passed as a
+    /// single argument so the remote shell is the only thing that interprets it. With `env_vars`,
+    /// the command runs as `env K='v' sh -c '<command>'`, so they apply to all of it.
+    fn ssh_args(&self, env_vars: &BTreeMap<String, String>, remote_command: &str) -> Vec<String> {
+        let mut command = vec!["ssh".to_string(), "-o BatchMode=yes".to_string()];
+        if let Some(port) = self.target.port {
+            command.push(format!("-p {}", port));
+        }
+        match &self.target.user {
+            Some(user) => command.push(format!("{}@{}", user, self.target.host)),
+            None => command.push(self.target.host.clone()),
+        }
+
+        if env_vars.is_empty() {
+            return vec![command.join(" "), remote_command.to_string()];
+        }
+        let mut remote = vec!["env".to_string()];
+        for (key, value) in env_vars {
+            remote.push(format!("{}={}", key, quote_sh(value.clone())));
+        }
+        remote.extend([
+            "sh".to_string(),
+            "-c".to_string(),
+            quote_sh(remote_command.to_string()),
+        ]);
+
+        vec![command.join(" "), remote.join(" ")]
+    }
+}
+
+#[async_trait]
+impl ExecutionProvider for SshExecutionProvider {
+    async fn run_command<'a>(&self, opts: CaptureOpts<'a>) -> Result<OutputCapture, CaptureError> {
+        let remote_command = opts.command();
+        let args = self.ssh_args(&opts.env_vars, &remote_command);
+        let mut capture = OutputCapture::capture_output_with_timeout(CaptureOpts {
+            working_dir: opts.working_dir,
+            env_vars: Default::default(),
+            path: opts.path,
+            args: &args,
+            output_dest: opts.output_dest,
+            timeout: opts.timeout,
+            progress: opts.progress,
+        })
+        .await?;
+
+        // Report the command that ran on the target, not the ssh invocation.
+        capture.command = remote_command;
+        Ok(capture)
+    }
+}
+
+/// Where a [`DockerExecutionProvider`] runs commands.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DockerTarget {
+    /// A container that's already running, commands run in it with `docker exec`.
+    Container(String),
+    /// Each command runs in a new container from the image, removed when the command exits.
+    /// `mount` is mounted at the same path, so the repo looks the same as it does locally.
+    Image { image: String, mount: PathBuf },
+}
+
+/// Runs commands in a container with the local `docker` client. The container must have `sh`,
+/// and commands run with the container's `PATH`.
+#[derive(Debug)]
+pub struct DockerExecutionProvider {
+    pub target: DockerTarget,
+}
+
+impl DockerExecutionProvider {
+    /// Run commands in the running container `container`.
+    pub fn new(container: &str) -> Self {
+        Self {
+            target: DockerTarget::Container(container.to_string()),
+        }
+    }
+
+    /// Run each command in a new container from `image`, with `mount` mounted at the same path.
+    /// Only changes under `mount` outlive a command.
+    pub fn for_image(image: &str, mount: &Path) -> Self {
+        Self {
+            target: DockerTarget::Image {
+                image: image.to_string(),
+                mount: mount.to_path_buf(),
+            },
+        }
+    }
+
+    /// Only the first argument is split by `env -S`, the rest are passed through as-is so paths
+    /// and the command don't need to be quoted.
+    fn docker_args(
+        &self,
+        working_dir: &Path,
+        env_vars: &BTreeMap<String, String>,
+        command: &str,
+    ) -> Vec<String> {
+        let mut args = vec!["docker".to_string()];
+        match &self.target {
+            DockerTarget::Container(_) => args.push("exec".to_string()),
+            DockerTarget::Image { mount, .. } => {
+                args.extend(["run".to_string(), "--rm".to_string()]);
+                // Fixes with `sandboxPaths` run from a temporary directory outside the repo.
+                let mut mounts = vec![mount.as_path()];
+                if !working_dir.starts_with(mount) {
+                    mounts.push(working_dir);
+                }
+                for path in mounts {
+                    args.push("--volume".to_string());
+                    args.push(format!("{}:{}", path.display(), path.display()));
+                }
+            }
+        }
+        args.push("--workdir".to_string());
+        args.push(working_dir.display().to_string());
+        for (key, value) in env_vars {
+            args.push("--env".to_string());
+            args.push(format!("{}={}", key, value));
+        }
+        match &self.target {
+            DockerTarget::Container(container) => {
+                args.extend([container.clone(), "sh".to_string()]);
+            }
+            DockerTarget::Image { image, .. } => {
+                args.extend(["--entrypoint".to_string(), "sh".to_string(), image.clone()]);
+            }
+        }
+        args.extend(["-c".to_string(), command.to_string()]);
+
+        args
+    }
+}
+
+#[async_trait]
+impl ExecutionProvider for DockerExecutionProvider {
+    async fn run_command<'a>(&self, opts: CaptureOpts<'a>) -> Result<OutputCapture, CaptureError> {
+        let container_command = opts.command();
+        let args = self.docker_args(opts.working_dir, &opts.env_vars, &container_command);
+        let mut capture = OutputCapture::capture_output_with_timeout(CaptureOpts {
+            working_dir: opts.working_dir,
+            env_vars: Default::default(),
+            path: opts.path,
+            args: &args,
+            output_dest: opts.output_dest,
+            timeout: opts.timeout,
+            progress: opts.progress,
+        })
+        .await?;
+
+        // Report the command that ran in the container, not the docker invocation.
+        capture.command = container_command;
+        Ok(capture)
+    }
+}
+
+/// What a [`ScriptedExecutionProvider`] returns for a command.
+#[derive(Debug, Clone, Default, Builder)]
+#[builder(setter(into))]
+pub struct ScriptedOutput {
+    #[builder(default)]
+    pub exit_code: i32,
+    /// Lines of stdout, split on `\n`.
+    #[builder(default)]
+    pub stdout: String,
+    /// Lines of stderr, split on `\n`.
+    #[builder(default)]
+    pub stderr: String,
+    /// How long the command takes before it returns.
+    #[builder(default)]
+    pub delay: std::time::Duration,
+}
+
+impl ScriptedOutput {
+    /// Output of a command that exits with `exit_code` and prints nothing.
+    pub fn exited(exit_code: i32) -> Self {
+        Self {
+            exit_code,
+            ..Default::default()
+        }
+    }
+}
+
+/// Returns scripted output instead of running commands, for deterministic tests of doctor
+/// configs. Commands are matched on the full command line, after templates are rendered.
+/// When a command has several outputs they're returned in order, and the last one repeats.
+/// Commands without a script fail with [`CaptureError::NotScripted`], unless a default is set.
+#[derive(Debug, Default)]
+pub struct ScriptedExecutionProvider {
+    scripts: Mutex<BTreeMap<String, VecDeque<ScriptedOutput>>>,
+    default: Option<ScriptedOutput>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl ScriptedExecutionProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an output for `command`, after the outputs already added for it.
+    pub fn with_output(self, command: &str, output: ScriptedOutput) -> Self {
+        self.scripts
+            .lock()
+            .unwrap()
+            .entry(command.to_string())
+            .or_default()
+            .push_back(output);
+        self
+    }
+
+    /// Output for commands that don't have a script.
+    pub fn with_default(self, output: ScriptedOutput) -> Self {
+        Self {
+            default: Some(output),
+            ..self
+        }
+    }
+
+    /// Every command that was run, in order.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn next_output(&self, command: &str) -> Option<ScriptedOutput> {
+        let mut scripts = self.scripts.lock().unwrap();
+        match scripts.get_mut(command) {
+            Some(outputs) if outputs.len() > 1 => outputs.pop_front(),
+            Some(outputs) => outputs.front().cloned(),
+            None => self.default.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionProvider for ScriptedExecutionProvider {
+    async fn run_command<'a>(&self, opts: CaptureOpts<'a>) -> Result<OutputCapture, CaptureError> {
+        let command = opts.command();
+        self.calls.lock().unwrap().push(command.clone());
+        let output = self
+            .next_output(&command)
+            .ok_or_else(|| CaptureError::NotScripted {
+                command: command.clone(),
+            })?;
+
+        let start_time = Utc::now();
+        if let Some(timeout) = opts.timeout.filter(|timeout| *timeout < output.delay) {
+            tokio::time::sleep(timeout).await;
+            return Err(CaptureError::Timeout { command, timeout });
+        }
+        if !output.delay.is_zero() {
+            tokio::time::sleep(output.delay).await;
+        }
+        if let Some(progress) = opts.progress {
+            for line in output.stdout.lines().chain(output.stderr.lines()) {
+                progress.output_line(line);
+            }
+        }
+        let end_time = Utc::now();
+        let lines = |text: &str| {
+            text.lines()
+                .map(|line| (end_time, line.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        Ok(OutputCapture {
+            working_dir: opts.working_dir.to_path_buf(),
+            stdout: lines(&output.stdout).into(),
+            stderr: lines(&output.stderr).into(),
+            exit_code: Some(output.exit_code),
+            start_time,
+            end_time,
+            command,
+        })
     }
 }
 
@@ -145,6 +514,10 @@ pub struct CaptureOpts<'a> {
     pub path: &'a str,
     pub args: &'a [String],
     pub output_dest: OutputDestination,
+    /// Stop the command when it runs longer than this, failing with [`CaptureError::Timeout`].
+    pub timeout: Option<std::time::Duration>,
+    /// Told about every line of output as it's written.
+    pub progress: Option<&'a dyn ProgressReporter>,
 }
 
 impl<'a> CaptureOpts<'a> {
@@ -154,6 +527,38 @@ impl<'a> CaptureOpts<'a> {
 }
 
 impl OutputCapture {
+    /// Same as [`OutputCapture::capture_output`], stopping the command once `opts.timeout` has
+    /// passed. The command is killed when it's stopped.
+    pub async fn capture_output_with_timeout(opts: CaptureOpts<'_>) -> Result<Self, CaptureError> {
+        Self::capture_with_timeout(opts, false).await
+    }
+
+    /// Same as [`OutputCapture::capture_output_with_timeout`], running the command in a
+    /// pseudo-terminal like [`OutputCapture::capture_pty_output`].
+    pub async fn capture_pty_output_with_timeout(
+        opts: CaptureOpts<'_>,
+    ) -> Result<Self, CaptureError> {
+        Self::capture_with_timeout(opts, true).await
+    }
+
+    async fn capture_with_timeout(opts: CaptureOpts<'_>, pty: bool) -> Result<Self, CaptureError> {
+        let command = opts.command();
+        let timeout = opts.timeout;
+        let capture = async move {
+            if pty {
+                Self::capture_pty_output(opts).await
+            } else {
+                Self::capture_output(opts).await
+            }
+        };
+        let Some(timeout) = timeout else {
+            return capture.await;
+        };
+        tokio::time::timeout(timeout, capture)
+            .await
+            .map_err(|_| CaptureError::Timeout { command, timeout })?
+    }
+
     #[instrument(skip_all)]
     pub async fn capture_output(opts: CaptureOpts<'_>) -> Result<Self, CaptureError> {
         check_pre_exec(&opts)?;
@@ -162,15 +567,15 @@ impl OutputCapture {
         debug!("Executing PATH={} {:?}", &opts.path, &args);
 
         let start_time = Utc::now();
-        let mut command = tokio::process::Command::new("/usr/bin/env");
+        let mut command = ShellInvocation::new(&args, cfg!(windows)).command();
         let mut child = command
-            .arg("-S")
-            .args(args)
             .env("PATH", opts.path)
             .envs(&opts.env_vars)
+            .stdin(child_stdin())
             .stderr(Stdio::piped())
             .stdout(Stdio::piped())
             .current_dir(opts.working_dir)
+            .kill_on_drop(true)
             .spawn()?;
 
         // capture stdout
@@ -180,6 +585,9 @@ impl OutputCapture {
             writer: crate::shared::prelude::STDOUT_WRITER.clone(),
             level: Level::INFO,
             dest: opts.output_dest.clone(),
+            progress: opts.progress,
+            spool_threshold: spool_threshold(),
+            strip_ansi: false,
         };
         let stdout = stdout_stream.capture_output();
 
@@ -190,6 +598,9 @@ impl OutputCapture {
             writer: crate::shared::prelude::STDERR_WRITER.clone(),
             level: Level::ERROR,
             dest: opts.output_dest.clone(),
+            progress: opts.progress,
+            spool_threshold: spool_threshold(),
+            strip_ansi: false,
         };
         let stderr = stderr_stream.capture_output();
 
@@ -212,34 +623,79 @@ impl OutputCapture {
         })
     }
 
-    pub fn generate_output(&self) -> String {
-        let stdout: Vec<_> = self
-            .stdout
-            .iter()
-            .map(|(time, line)| {
-                let offset: Duration = *time - self.start_time;
-                (*time, format!("{} OUT: {}", offset, line))
-            })
-            .collect();
+    /// Same as [`OutputCapture::capture_output`], but the command runs in a pseudo-terminal, so
+    /// its output is what it would write on a terminal. Stdout and stderr are the same terminal,
+    /// so all of the output is captured as stdout, without the ANSI escapes used for colors.
+    #[cfg(unix)]
+    #[instrument(skip_all)]
+    pub async fn capture_pty_output(opts: CaptureOpts<'_>) -> Result<Self, CaptureError> {
+        check_pre_exec(&opts)?;
+        let args = opts.args.to_vec();
 
-        let stderr: Vec<_> = self
-            .stderr
-            .iter()
-            .map(|(time, line)| {
-                let offset: Duration = *time - self.start_time;
-                (*time, format!("{} ERR: {}", offset, line))
-            })
-            .collect();
+        debug!(
+            "Executing in a pseudo-terminal PATH={} {:?}",
+            &opts.path, &args
+        );
+
+        let start_time = Utc::now();
+        let pty = Pty::open()?;
+        let mut command = ShellInvocation::new(&args, false).command();
+        command
+            .env("PATH", opts.path)
+            .envs(&opts.env_vars)
+            .current_dir(opts.working_dir)
+            .kill_on_drop(true);
+        pty.attach(&mut command)?;
+        let mut child = command.spawn()?;
+        // The command holds on to the terminal until it's dropped, which would keep the output
+        // from ending.
+        drop(command);
+
+        let output_stream = StreamCapture {
+            reader: pty.into_reader()?,
+            writer: crate::shared::prelude::STDOUT_WRITER.clone(),
+            level: Level::INFO,
+            dest: opts.output_dest.clone(),
+            progress: opts.progress,
+            spool_threshold: spool_threshold(),
+            strip_ansi: true,
+        };
+
+        let (command_result, wait_output) =
+            tokio::join!(child.wait(), output_stream.capture_output());
+        let end_time = Utc::now();
+        debug!("join result {:?}", command_result);
 
-        let mut output = Vec::new();
-        output.extend(stdout);
-        output.extend(stderr);
+        Ok(Self {
+            working_dir: opts.working_dir.to_path_buf(),
+            stdout: wait_output.unwrap_or_default(),
+            stderr: CapturedLines::default(),
+            exit_code: command_result.ok().and_then(|x| x.code()),
+            start_time,
+            end_time,
+            command: opts.command(),
+        })
+    }
 
-        output.sort_by(|(l_time, _), (r_time, _)| l_time.cmp(r_time));
+    /// Pseudo-terminals are only supported on Unix.
+    #[cfg(not(unix))]
+    pub async fn capture_pty_output(_opts: CaptureOpts<'_>) -> Result<Self, CaptureError> {
+        Err(CaptureError::PtyUnsupported)
+    }
 
-        let text: String = output
-            .iter()
-            .map(|(_, line)| line.clone())
+    pub fn generate_output(&self) -> String {
+        let stdout = self.stdout.iter().map(|(time, line)| {
+            let offset: Duration = time - self.start_time;
+            (time, format!("{} OUT: {}", offset, line))
+        });
+        let stderr = self.stderr.iter().map(|(time, line)| {
+            let offset: Duration = time - self.start_time;
+            (time, format!("{} ERR: {}", offset, line))
+        });
+
+        let text: String = stdout
+            .merge_by(stderr, |(l_time, _), (r_time, _)| l_time <= r_time)
+            .map(|(_, line)| line)
             .collect::<Vec<_>>()
             .join("\n");
 
@@ -247,23 +703,98 @@ impl OutputCapture {
     }
 
     pub fn generate_user_output(&self) -> String {
-        let mut output = Vec::new();
-        output.extend(self.stdout.iter());
-        output.extend(self.stderr.iter());
+        let mut output = String::new();
+        self.write_user_output(&mut output, None).ok();
+        output
+    }
+
+    /// Read the same text as `generate_user_output` one line at a time. Output that was spooled
+    /// to a file is read from a file rather than memory.
+    pub async fn user_output_reader(&self) -> std::io::Result<SpooledReader> {
+        if self.stdout.is_spooled() || self.stderr.is_spooled() {
+            SpooledReader::from_lines(self.user_output_lines()).await
+        } else {
+            Ok(SpooledReader::from_memory(self.generate_user_output()))
+        }
+    }
 
-        output.sort_by(|(l_time, _), (r_time, _)| l_time.cmp(r_time));
+    /// True when the command wrote anything to stdout or stderr.
+    pub fn has_output(&self) -> bool {
+        !self.stdout.is_empty() || !self.stderr.is_empty()
+    }
 
-        output
+    /// Stdout and stderr lines, in the order they were captured.
+    fn user_output_lines(&self) -> impl Iterator<Item = Cow<'_, str>> {
+        self.stdout
             .iter()
-            .map(|(_, line)| line.clone())
-            .collect::<Vec<_>>()
-            .join("\n")
+            .merge_by(self.stderr.iter(), |(l_time, _), (r_time, _)| {
+                l_time <= r_time
+            })
+            .map(|(_, line)| line)
+    }
+
+    /// Write the same text as `generate_user_output` without building it in memory first. When
+    /// the output is longer than `max_len` bytes, only the first and last lines that fit in half
+    /// of `max_len` each are written, with a marker for the lines that were left out.
+    pub fn write_user_output<W: std::fmt::Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        max_len: Option<usize>,
+    ) -> std::fmt::Result {
+        let (line_count, total_len) =
+            self.user_output_lines().fold((0, 0), |(count, len), line| {
+                (count + 1, len + line.len() + 1)
+            });
+
+        let (head_end, tail_start) = match max_len {
+            Some(max_len) if total_len.saturating_sub(1) > max_len => {
+                let budget = max_len / 2;
+                let mut head_end = 0;
+                let mut head_len = 0;
+                let mut tail_start = line_count;
+                let mut seen_len = 0;
+                for (idx, line) in self.user_output_lines().enumerate() {
+                    let line_len = line.len() + 1;
+                    if head_end == idx && head_len + line_len <= budget {
+                        head_end += 1;
+                        head_len += line_len;
+                    }
+                    if tail_start == line_count && total_len - seen_len <= budget {
+                        tail_start = idx;
+                    }
+                    seen_len += line_len;
+                }
+                (head_end, tail_start.max(head_end))
+            }
+            _ => (line_count, line_count),
+        };
+
+        let mut first = true;
+        for (idx, line) in self.user_output_lines().enumerate() {
+            if idx == head_end && head_end != tail_start {
+                if !first {
+                    writeln!(writer)?;
+                }
+                write!(writer, "[... {} lines omitted ...]", tail_start - head_end)?;
+                first = false;
+            }
+            if idx >= head_end && idx < tail_start {
+                continue;
+            }
+            if !first {
+                writeln!(writer)?;
+            }
+            write!(writer, "{}", line)?;
+            first = false;
+        }
+
+        Ok(())
     }
 
     pub fn get_stdout(&self) -> String {
         self.stdout
             .iter()
-            .map(|(_, line)| line.clone())
+            .map(|(_, line)| line)
             .collect::<Vec<_>>()
             .join("\n")
     }
@@ -271,12 +802,73 @@ impl OutputCapture {
     pub fn get_stderr(&self) -> String {
         self.stderr
             .iter()
-            .map(|(_, line)| line.clone())
+            .map(|(_, line)| line)
             .collect::<Vec<_>>()
             .join("\n")
     }
 }
 
+/// How a command line is started, which depends on the platform.
+#[derive(Debug, PartialEq)]
+enum ShellInvocation {
+    /// `/usr/bin/env -S` splits the first argument, so a script with a shebang runs the same
+    /// way it would from a shell.
+    Env(Vec<String>),
+    /// Windows has no `env`, `cmd /C` runs the command line instead.
+    Cmd(String),
+    /// A PowerShell script on Windows, with its arguments.
+    PowerShell(String),
+}
+
+impl ShellInvocation {
+    fn new(args: &[String], windows: bool) -> Self {
+        if !windows {
+            return ShellInvocation::Env(args.to_vec());
+        }
+
+        let command_line = args.join(" ");
+        let program = command_line.split(' ').next().unwrap_or_default();
+        if program.to_lowercase().ends_with(".ps1") {
+            ShellInvocation::PowerShell(command_line)
+        } else {
+            ShellInvocation::Cmd(command_line)
+        }
+    }
+
+    fn command(&self) -> tokio::process::Command {
+        match self {
+            ShellInvocation::Env(args) => {
+                let mut command = tokio::process::Command::new("/usr/bin/env");
+                command.arg("-S").args(args);
+                command
+            }
+            ShellInvocation::Cmd(command_line) => {
+                let mut command = tokio::process::Command::new("cmd");
+                command.arg("/C");
+                append_command_line(&mut command, command_line);
+                command
+            }
+            ShellInvocation::PowerShell(command_line) => {
+                let mut command = tokio::process::Command::new("powershell");
+                command.args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-File"]);
+                append_command_line(&mut command, command_line);
+                command
+            }
+        }
+    }
+}
+
+/// `cmd` doesn't follow the usual quoting rules, so the command line is passed as-is.
+#[cfg(windows)]
+fn append_command_line(command: &mut tokio::process::Command, command_line: &str) {
+    command.raw_arg(command_line);
+}
+
+#[cfg(not(windows))]
+fn append_command_line(command: &mut tokio::process::Command, command_line: &str) {
+    command.arg(command_line);
+}
+
 fn check_pre_exec(opts: &CaptureOpts) -> Result<(), CaptureError> {
     let command = opts.command();
     let found_binary = match command.split(' ').collect::<Vec<_>>().first() {
@@ -297,9 +889,7 @@ fn check_pre_exec(opts: &CaptureOpts) -> Result<(), CaptureError> {
             name: path.display().to_string(),
         });
     }
-    let metadata = std::fs::metadata(&path)?;
-    let permissions = metadata.permissions().mode();
-    if permissions & 0x700 == 0 {
+    if !is_executable(&path)? {
         return Err(CaptureError::MissingShExec {
             name: path.display().to_string(),
         });
@@ -307,3 +897,301 @@ fn check_pre_exec(opts: &CaptureOpts) -> Result<(), CaptureError> {
 
     Ok(())
 }
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool, CaptureError> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::metadata(path)?.permissions().mode();
+    Ok(permissions & 0o700 != 0)
+}
+
+/// Windows has no executable bit, `which` already filtered on `PATHEXT`.
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> Result<bool, CaptureError> {
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use chrono::DateTime;
+
+    use super::{
+        CaptureError, CaptureOpts, DefaultExecutionProvider, DockerExecutionProvider,
+        ExecutionProvider, OutputCaptureBuilder, OutputDestination, ScriptedExecutionProvider,
+        ScriptedOutput, ScriptedOutputBuilder, ShellInvocation, SshExecutionProvider, SshTarget,
+        StreamCapture,
+    };
+    use tokio::io::AsyncBufReadExt;
+    use tracing::Level;
+
+    #[test]
+    fn test_shell_invocation() {
+        let args = vec!["./bin/setup.sh --force".to_string()];
+        assert_eq!(
+            ShellInvocation::Env(args.clone()),
+            ShellInvocation::new(&args, false)
+        );
+        assert_eq!(
+            ShellInvocation::Cmd("./bin/setup.sh --force".to_string()),
+            ShellInvocation::new(&args, true)
+        );
+
+        let args = vec!["C:\\repo\\bin\\Setup.PS1".to_string(), "-Force".to_string()];
+        assert_eq!(
+            ShellInvocation::PowerShell("C:\\repo\\bin\\Setup.PS1 -Force".to_string()),
+            ShellInvocation::new(&args, true)
+        );
+    }
+
+    #[test]
+    fn test_parse_ssh_target() {
+        assert_eq!(
+            SshTarget {
+                user: Some("dev".to_string()),
+                host: "build-agent".to_string(),
+                port: Some(2222),
+            },
+            SshTarget::parse("ssh://dev@build-agent:2222").unwrap()
+        );
+        assert_eq!(
+            SshTarget {
+                user: None,
+                host: "devbox".to_string(),
+                port: None,
+            },
+            SshTarget::parse("ssh://devbox").unwrap()
+        );
+        assert!(SshTarget::parse("https://devbox").is_err());
+        assert!(SshTarget::parse("devbox").is_err());
+    }
+
+    #[test]
+    fn test_ssh_args_keep_remote_command_whole() {
+        let provider =
+            SshExecutionProvider::new(SshTarget::parse("ssh://dev@devbox:2222").unwrap());
+
+        assert_eq!(
+            vec![
+                "ssh -o BatchMode=yes -p 2222 dev@devbox".to_string(),
+                "test -f 'my file'".to_string()
+            ],
+            provider.ssh_args(&BTreeMap::new(), "test -f 'my file'")
+        );
+
+        let env_vars = BTreeMap::from([
+            ("GREETING".to_string(), "it's me".to_string()),
+            ("SCOPE_RUN_ID".to_string(), "run 1".to_string()),
+        ]);
+        assert_eq!(
+            vec![
+                "ssh -o BatchMode=yes -p 2222 dev@devbox".to_string(),
+                "env GREETING='it'\\''s me' SCOPE_RUN_ID='run 1' sh -c 'cd app && make'"
+                    .to_string()
+            ],
+            provider.ssh_args(&env_vars, "cd app && make")
+        );
+    }
+
+    #[test]
+    fn test_docker_args_run_command_in_container() {
+        let provider = DockerExecutionProvider::new("abc123");
+        let env_vars = BTreeMap::from([("SCOPE_RUN_ID".to_string(), "run 1".to_string())]);
+
+        assert_eq!(
+            vec![
+                "docker",
+                "exec",
+                "--workdir",
+                "/src/my app",
+                "--env",
+                "SCOPE_RUN_ID=run 1",
+                "abc123",
+                "sh",
+                "-c",
+                "test -f 'my file'",
+            ],
+            provider.docker_args(Path::new("/src/my app"), &env_vars, "test -f 'my file'")
+        );
+    }
+
+    #[test]
+    fn test_docker_args_run_command_in_new_container() {
+        let provider = DockerExecutionProvider::for_image("node:20", Path::new("/src/app"));
+
+        assert_eq!(
+            vec![
+                "docker",
+                "run",
+                "--rm",
+                "--volume",
+                "/src/app:/src/app",
+                "--workdir",
+                "/src/app/web",
+                "--entrypoint",
+                "sh",
+                "node:20",
+                "-c",
+                "node --version",
+            ],
+            provider.docker_args(
+                Path::new("/src/app/web"),
+                &BTreeMap::new(),
+                "node --version"
+            )
+        );
+        assert_eq!(
+            "/tmp/sandbox:/tmp/sandbox",
+            provider.docker_args(Path::new("/tmp/sandbox"), &BTreeMap::new(), "true")[6]
+        );
+    }
+
+    #[test]
+    fn test_write_user_output_keeps_head_and_tail() {
+        let at = |secs: i64| DateTime::from_timestamp(1715612600 + secs, 0).unwrap();
+        let capture = OutputCaptureBuilder::default()
+            .stdout(
+                (0..10)
+                    .map(|idx| (at(idx), format!("line {}", idx)))
+                    .collect::<Vec<_>>(),
+            )
+            .stderr(vec![(at(3), "error".to_string())])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            "line 0\nline 1\nline 2\nline 3\nerror\nline 4\nline 5\nline 6\nline 7\nline 8\nline 9",
+            capture.generate_user_output()
+        );
+
+        let mut output = String::new();
+        capture.write_user_output(&mut output, Some(30)).unwrap();
+        assert_eq!(
+            "line 0\nline 1\n[... 7 lines omitted ...]\nline 8\nline 9",
+            output
+        );
+    }
+
+    #[tokio::test]
+    async fn test_output_past_spool_threshold_is_read_from_file() {
+        let text: String = (0..100).map(|idx| format!("line {}\n", idx)).collect();
+        let stdout = StreamCapture {
+            reader: text.as_bytes(),
+            writer: crate::shared::prelude::STDOUT_WRITER.clone(),
+            level: Level::INFO,
+            dest: OutputDestination::Null,
+            progress: None,
+            spool_threshold: 64,
+            strip_ansi: false,
+        }
+        .capture_output()
+        .await
+        .unwrap();
+        assert!(stdout.is_spooled());
+
+        let capture = OutputCaptureBuilder::default()
+            .stdout(stdout)
+            .build()
+            .unwrap();
+        assert_eq!(text.trim_end(), capture.get_stdout());
+        assert_eq!(text.trim_end(), capture.generate_user_output());
+
+        let mut lines = capture.user_output_reader().await.unwrap().lines();
+        let mut read = Vec::new();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            read.push(line);
+        }
+        assert_eq!(100, read.len());
+        assert_eq!("line 99", read[99]);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_provider_returns_outputs_in_order() {
+        let provider = ScriptedExecutionProvider::new()
+            .with_output("check", ScriptedOutput::exited(1))
+            .with_output(
+                "check",
+                ScriptedOutputBuilder::default()
+                    .stdout("ok\ndone")
+                    .build()
+                    .unwrap(),
+            );
+        let run = |command: &str| {
+            let args = vec![command.to_string()];
+            let provider = &provider;
+            async move {
+                provider
+                    .run_command(CaptureOpts {
+                        working_dir: Path::new("/tmp"),
+                        env_vars: BTreeMap::new(),
+                        path: "",
+                        args: &args,
+                        output_dest: OutputDestination::Null,
+                        timeout: None,
+                        progress: None,
+                    })
+                    .await
+            }
+        };
+
+        assert_eq!(Some(1), run("check").await.unwrap().exit_code);
+        let capture = run("check").await.unwrap();
+        assert_eq!(Some(0), capture.exit_code);
+        assert_eq!("ok\ndone", capture.get_stdout());
+        assert_eq!(Some(0), run("check").await.unwrap().exit_code);
+        assert!(matches!(
+            run("other").await,
+            Err(CaptureError::NotScripted { command }) if command == "other"
+        ));
+        assert_eq!(vec!["check", "check", "check", "other"], provider.calls());
+    }
+
+    #[tokio::test]
+    async fn test_command_is_stopped_at_timeout() {
+        let args = vec!["sleep 10".to_string()];
+        let path = std::env::var("PATH").unwrap_or_default();
+        let started = std::time::Instant::now();
+        let result = DefaultExecutionProvider::default()
+            .run_command(CaptureOpts {
+                working_dir: &std::env::temp_dir(),
+                env_vars: BTreeMap::new(),
+                path: &path,
+                args: &args,
+                output_dest: OutputDestination::Null,
+                timeout: Some(std::time::Duration::from_millis(100)),
+                progress: None,
+            })
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(CaptureError::Timeout { command, .. }) if command == "sleep 10"
+        ));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pty_command_writes_to_a_terminal() {
+        // `tty` names the terminal on stdin, which is left as it is, so point it at stdout.
+        let args = vec!["sh -c 'tty <&1'".to_string()];
+        let path = std::env::var("PATH").unwrap_or_default();
+        let capture = DefaultExecutionProvider::with_pty()
+            .run_command(CaptureOpts {
+                working_dir: &std::env::temp_dir(),
+                env_vars: BTreeMap::new(),
+                path: &path,
+                args: &args,
+                output_dest: OutputDestination::Null,
+                timeout: Some(std::time::Duration::from_secs(10)),
+                progress: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(Some(0), capture.exit_code);
+        assert!(capture.get_stdout().starts_with("/dev/"));
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// When console output should be colored.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum ColorChoice {
+    /// Color output when it's written to a terminal
+    #[default]
+    Auto,
+    /// Always color output
+    Always,
+    /// Never color output
+    Never,
+}
+
+static COLORS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Whether console output should currently be colored. Set by [`configure_colors`].
+pub fn colors_enabled() -> bool {
+    COLORS_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Decide if console output is colored, and apply that decision everywhere output is colored.
+///
+/// `flag` is the `--color` option, and wins when given. After that `NO_COLOR` turns color off
+/// and `FORCE_COLOR` turns it on, followed by the `ScopeConfig` preference. Otherwise, color is
+/// used when stdout is a terminal.
+pub fn configure_colors(flag: Option<ColorChoice>, preference: Option<ColorChoice>) {
+    let enabled = should_color(
+        flag,
+        preference,
+        std::env::var("NO_COLOR").ok().as_deref(),
+        std::env::var("FORCE_COLOR").ok().as_deref(),
+        std::io::stdout().is_terminal(),
+    );
+
+    COLORS_ENABLED.store(enabled, Ordering::Relaxed);
+    colored::control::set_override(enabled);
+}
+
+fn should_color(
+    flag: Option<ColorChoice>,
+    preference: Option<ColorChoice>,
+    no_color: Option<&str>,
+    force_color: Option<&str>,
+    is_terminal: bool,
+) -> bool {
+    if let Some(choice) = flag {
+        return resolve(choice, is_terminal);
+    }
+
+    // https://no-color.org: any non-empty value disables color
+    if no_color.is_some_and(|value| !value.is_empty()) {
+        return false;
+    }
+
+    if let Some(value) = force_color {
+        return value != "0";
+    }
+
+    resolve(preference.unwrap_or_default(), is_terminal)
+}
+
+fn resolve(choice: ColorChoice, is_terminal: bool) -> bool {
+    match choice {
+        ColorChoice::Auto => is_terminal,
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+/// Wraps a [`MakeWriter`] so escape codes are removed from what's written when colors are
+/// disabled. The check happens on every write, so decisions made after logging is set up,
+/// like the one from `ScopeConfig`, still apply.
+pub struct ColorAwareMakeWriter<M> {
+    inner: M,
+}
+
+impl<M> ColorAwareMakeWriter<M> {
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+}
+
+impl<'a, M> MakeWriter<'a> for ColorAwareMakeWriter<M>
+where
+    M: MakeWriter<'a>,
+{
+    type Writer = ColorAwareWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        let writer = self.inner.make_writer();
+        if colors_enabled() {
+            ColorAwareWriter::Colored(writer)
+        } else {
+            ColorAwareWriter::Plain(Box::new(strip_ansi_escapes::Writer::new(writer)))
+        }
+    }
+}
+
+pub enum ColorAwareWriter<W: Write> {
+    Colored(W),
+    Plain(Box<strip_ansi_escapes::Writer<W>>),
+}
+
+impl<W: Write> Write for ColorAwareWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            ColorAwareWriter::Colored(writer) => writer.write(buf),
+            ColorAwareWriter::Plain(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            ColorAwareWriter::Colored(writer) => writer.flush(),
+            ColorAwareWriter::Plain(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flag_wins_over_environment() {
+        assert!(should_color(
+            Some(ColorChoice::Always),
+            None,
+            Some("1"),
+            None,
+            false
+        ));
+        assert!(!should_color(
+            Some(ColorChoice::Never),
+            None,
+            None,
+            Some("1"),
+            true
+        ));
+        assert!(should_color(
+            Some(ColorChoice::Auto),
+            None,
+            None,
+            None,
+            true
+        ));
+    }
+
+    #[test]
+    fn test_no_color_wins_over_force_color_and_preference() {
+        assert!(!should_color(
+            None,
+            Some(ColorChoice::Always),
+            Some("1"),
+            Some("1"),
+            true
+        ));
+        assert!(should_color(None, None, Some(""), None, true));
+    }
+
+    #[test]
+    fn test_force_color_wins_over_preference() {
+        assert!(should_color(
+            None,
+            Some(ColorChoice::Never),
+            None,
+            Some("1"),
+            false
+        ));
+        assert!(!should_color(None, None, None, Some("0"), true));
+    }
+
+    #[test]
+    fn test_preference_then_terminal() {
+        assert!(!should_color(
+            None,
+            Some(ColorChoice::Never),
+            None,
+            None,
+            true
+        ));
+        assert!(should_color(
+            None,
+            Some(ColorChoice::Always),
+            None,
+            None,
+            false
+        ));
+        assert!(should_color(None, None, None, None, true));
+        assert!(!should_color(None, None, None, None, false));
+    }
+
+    #[test]
+    fn test_plain_writer_strips_escape_codes() {
+        let mut out = Vec::new();
+        {
+            let mut writer =
+                ColorAwareWriter::Plain(Box::new(strip_ansi_escapes::Writer::new(&mut out)));
+            writer.write_all(b"\x1b[1mbold\x1b[0m text").unwrap();
+        }
+        assert_eq!("bold text", String::from_utf8(out).unwrap());
+    }
+}
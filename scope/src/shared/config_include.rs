@@ -0,0 +1,251 @@
+use path_clean::PathClean;
+use serde_yaml::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Tag that replaces a value with the YAML in another file, like `actions: !include node.yaml`.
+pub const INCLUDE_TAG: &str = "!include";
+/// Annotation on a resource that uses `!include`, listing the files it includes.
+pub const INCLUDED_FILES_ANNOTATION: &str = "scope.github.com/included-files";
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IncludeError {
+    #[error("{file} has an `!include` without a path")]
+    MissingPath { file: String },
+    #[error("unable to read {path}, included from {file}. {error}")]
+    Read {
+        file: String,
+        path: String,
+        error: String,
+    },
+    #[error("unable to parse {path}, included from {file}. {error}")]
+    Parse {
+        file: String,
+        path: String,
+        error: String,
+    },
+    #[error("{file} includes {path}, which includes itself: {chain}")]
+    Cycle {
+        file: String,
+        path: String,
+        chain: String,
+    },
+}
+
+/// Replace every `!include <path>` in `value`, which was read from `file_path`, with the YAML in
+/// the file at `path`. Relative paths resolve against the directory of the file that includes
+/// them, and included files can include others. An include that is an item of a list, and
+/// includes a list, adds its items to the list instead of nesting it.
+///
+/// Returns the files that were included, in the order they were first included.
+pub fn resolve_includes(value: &mut Value, file_path: &Path) -> Result<Vec<PathBuf>, IncludeError> {
+    let mut included = Vec::new();
+    resolve(
+        value,
+        file_path,
+        &mut vec![file_path.clean()],
+        &mut included,
+    )?;
+    Ok(included)
+}
+
+/// Files `value`, read from `file_path`, includes directly. Unlike [`resolve_includes`], nothing
+/// is read.
+pub fn included_paths(value: &Value, file_path: &Path) -> Vec<PathBuf> {
+    match value {
+        Value::Tagged(tagged) if tagged.tag == INCLUDE_TAG => tagged
+            .value
+            .as_str()
+            .map(|path| vec![include_path(file_path, path)])
+            .unwrap_or_default(),
+        Value::Tagged(tagged) => included_paths(&tagged.value, file_path),
+        Value::Sequence(items) => items
+            .iter()
+            .flat_map(|item| included_paths(item, file_path))
+            .collect(),
+        Value::Mapping(mapping) => mapping
+            .values()
+            .flat_map(|value| included_paths(value, file_path))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn include_path(file_path: &Path, path: &str) -> PathBuf {
+    match file_path.parent() {
+        Some(parent) => parent.join(path).clean(),
+        None => PathBuf::from(path).clean(),
+    }
+}
+
+fn resolve(
+    value: &mut Value,
+    file_path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<(), IncludeError> {
+    match value {
+        Value::Tagged(tagged) if tagged.tag == INCLUDE_TAG => {
+            *value = include(&tagged.value, file_path, stack, included)?;
+        }
+        Value::Tagged(tagged) => resolve(&mut tagged.value, file_path, stack, included)?,
+        Value::Sequence(items) => {
+            let mut resolved = Vec::with_capacity(items.len());
+            for mut item in items.drain(..) {
+                match item {
+                    Value::Tagged(tagged) if tagged.tag == INCLUDE_TAG => {
+                        match include(&tagged.value, file_path, stack, included)? {
+                            Value::Sequence(inner) => resolved.extend(inner),
+                            other => resolved.push(other),
+                        }
+                    }
+                    _ => {
+                        resolve(&mut item, file_path, stack, included)?;
+                        resolved.push(item);
+                    }
+                }
+            }
+            *items = resolved;
+        }
+        Value::Mapping(mapping) => {
+            for value in mapping.values_mut() {
+                resolve(value, file_path, stack, included)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn include(
+    path: &Value,
+    file_path: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<Value, IncludeError> {
+    let file = file_path.display().to_string();
+    let Some(path) = path.as_str().filter(|path| !path.is_empty()) else {
+        return Err(IncludeError::MissingPath { file });
+    };
+    let path = include_path(file_path, path);
+    if stack.contains(&path) {
+        let chain = stack
+            .iter()
+            .chain([&path])
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        return Err(IncludeError::Cycle {
+            file,
+            path: path.display().to_string(),
+            chain,
+        });
+    }
+    if !included.contains(&path) {
+        included.push(path.clone());
+    }
+
+    let contents = fs::read_to_string(&path).map_err(|e| IncludeError::Read {
+        file: file.clone(),
+        path: path.display().to_string(),
+        error: e.to_string(),
+    })?;
+    let mut value: Value = serde_yaml::from_str(&contents).map_err(|e| IncludeError::Parse {
+        file,
+        path: path.display().to_string(),
+        error: e.to_string(),
+    })?;
+
+    stack.push(path.clone());
+    resolve(&mut value, &path, stack, included)?;
+    stack.pop();
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_resolve_includes() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let group = write(
+            dir.path(),
+            "setup.yaml",
+            "spec:\n  vars: !include parts/vars.yaml\n  actions:\n    - name: brew\n    - !include parts/actions.yaml\n",
+        );
+        write(
+            dir.path(),
+            "parts/actions.yaml",
+            "- name: node\n  check:\n    commands: !include ../commands.yaml\n- name: python\n",
+        );
+        write(dir.path(), "parts/vars.yaml", "node: '20'\n");
+        write(dir.path(), "commands.yaml", "- node --version\n");
+
+        let text = fs::read_to_string(&group).unwrap();
+        let mut value: Value = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(
+            vec![
+                dir.path().join("parts/vars.yaml"),
+                dir.path().join("parts/actions.yaml")
+            ],
+            included_paths(&value, &group)
+        );
+
+        let included = resolve_includes(&mut value, &group).unwrap();
+        let expected: Value = serde_yaml::from_str(
+            "spec:\n  vars:\n    node: '20'\n  actions:\n    - name: brew\n    - name: node\n      check:\n        commands: [node --version]\n    - name: python\n",
+        )
+        .unwrap();
+        assert_eq!(expected, value);
+        assert_eq!(
+            vec![
+                dir.path().join("parts/vars.yaml"),
+                dir.path().join("parts/actions.yaml"),
+                dir.path().join("commands.yaml"),
+            ],
+            included
+        );
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let group = write(dir.path(), "setup.yaml", "spec: !include a.yaml\n");
+        write(dir.path(), "a.yaml", "actions: !include setup.yaml\n");
+
+        let mut value: Value = serde_yaml::from_str("spec: !include a.yaml\n").unwrap();
+        let error = resolve_includes(&mut value, &group).unwrap_err();
+        let setup = group.display().to_string();
+        let a = dir.path().join("a.yaml").display().to_string();
+        assert_eq!(
+            IncludeError::Cycle {
+                file: a.clone(),
+                path: setup.clone(),
+                chain: format!("{} -> {} -> {}", setup, a, setup),
+            },
+            error
+        );
+    }
+
+    #[test]
+    fn test_include_missing_file() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let group = dir.path().join("setup.yaml");
+        let mut value: Value = serde_yaml::from_str("spec: !include missing.yaml\n").unwrap();
+        assert!(matches!(
+            resolve_includes(&mut value, &group),
+            Err(IncludeError::Read { .. })
+        ));
+    }
+}
@@ -1,53 +1,108 @@
-use crate::models::prelude::ModelRoot;
-use crate::models::HelpMetadata;
-use crate::shared::models::prelude::{DoctorGroup, KnownError, ParsedConfig, ReportUploadLocation};
+use crate::models::prelude::{
+    join_path_list, ModelRoot, V1AlphaActionLibrary, MIN_SCOPE_VERSION_ANNOTATION,
+};
+use crate::models::{HelpMetadata, InternalScopeModel};
+use crate::shared::models::prelude::{
+    ActionLibrary, DoctorGroup, DoctorGroupAction, InterceptFilter, KnownError, ParsedConfig,
+    Policy, PromptPolicy, Redaction, ReportDefinition, ReportUploadLocation, ScopeConfig,
+};
+use crate::shared::prelude::{
+    included_paths, merge_layers, resolve_includes, ColorChoice, PromptDecision, RunArtifacts,
+    RunFingerprint, INCLUDED_FILES_ANNOTATION,
+};
+#[cfg(feature = "cli")]
 use crate::shared::RUN_ID_ENV_VAR;
 use anyhow::{anyhow, Result};
+#[cfg(feature = "cli")]
 use clap::{ArgGroup, Parser};
 use colored::*;
 use directories::{BaseDirs, UserDirs};
 use ignore::Walk;
-use itertools::Itertools;
+use path_clean::PathClean;
+use regex::Regex;
 use serde::Deserialize;
 use serde_yaml::{Deserializer, Value};
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsStr;
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs;
 use std::path::{Path, PathBuf};
+use thiserror::Error;
 use tracing::{debug, error, info, warn};
 
-#[derive(Parser, Debug)]
-#[clap(group = ArgGroup::new("config"))]
+/// Where config is loaded from. The CLI parses it from arguments, embedders start from
+/// `ConfigOptions::default()`, which loads config the way `scope` does without any options.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "cli", derive(Parser))]
+#[cfg_attr(feature = "cli", clap(group = ArgGroup::new("config")))]
 pub struct ConfigOptions {
     /// Add a paths to search for configuration. By default, `scope` will search up
     /// for `.scope` directories and attempt to load `.yml` and `.yaml` files for config.
     /// If the config directory is somewhere else, specifying this option will _add_
     /// the paths/files to the loaded config.
-    #[clap(long, env = "SCOPE_CONFIG_DIR", global(true))]
+    #[cfg_attr(feature = "cli", clap(long, env = "SCOPE_CONFIG_DIR", global(true)))]
     extra_config: Vec<String>,
 
     /// When set, default config files will not be loaded and only specified config will be loaded.
-    #[arg(
-        long,
-        env = "SCOPE_DISABLE_DEFAULT_CONFIG",
-        default_value = "false",
-        global(true)
+    #[cfg_attr(
+        feature = "cli",
+        arg(
+            long,
+            env = "SCOPE_DISABLE_DEFAULT_CONFIG",
+            default_value = "false",
+            global(true)
+        )
     )]
     disable_default_config: bool,
 
     /// Override the working directory
-    #[arg(long, short = 'C', global(true))]
+    #[cfg_attr(feature = "cli", arg(long, short = 'C', global(true)))]
     working_dir: Option<String>,
 
     /// When outputting logs, or other files, the run-id is the unique value that will define where these go.
     /// In the case that the run-id is re-used, the old values will be overwritten.
-    #[arg(long, global(true), env = RUN_ID_ENV_VAR)]
+    #[cfg_attr(
+        feature = "cli",
+        arg(long, global(true), env = RUN_ID_ENV_VAR, value_parser = parse_run_id)
+    )]
     run_id: Option<String>,
 }
 
+#[cfg(feature = "cli")]
+fn parse_run_id(run_id: &str) -> std::result::Result<String, String> {
+    if RunArtifacts::is_valid_run_id(run_id) {
+        Ok(run_id.to_string())
+    } else {
+        Err("a run id can't be empty or contain path separators".to_string())
+    }
+}
+
 impl ConfigOptions {
+    /// Load config as if `scope` was run in `working_dir`, like `--working-dir`.
+    pub fn with_working_dir(mut self, working_dir: impl Into<String>) -> Self {
+        self.working_dir = Some(working_dir.into());
+        self
+    }
+
+    /// Load config from `path` as well, like `--extra-config`.
+    pub fn with_extra_config(mut self, path: impl Into<String>) -> Self {
+        self.extra_config.push(path.into());
+        self
+    }
+
+    /// Only load config added with [`ConfigOptions::with_extra_config`], like
+    /// `--disable-default-config`.
+    pub fn without_default_config(mut self) -> Self {
+        self.disable_default_config = true;
+        self
+    }
+
+    /// Use `run_id` instead of generating one, like `--run-id`.
+    pub fn with_run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
     pub fn generate_run_id() -> String {
         let id = nanoid::nanoid!(4, &nanoid::alphabet::SAFE);
         let now = chrono::Local::now();
@@ -55,7 +110,14 @@ impl ConfigOptions {
         format!("{}-{}", current_time, id)
     }
     pub fn get_run_id(&self) -> String {
-        self.run_id.clone().unwrap_or_else(Self::generate_run_id)
+        match &self.run_id {
+            Some(run_id) if RunArtifacts::is_valid_run_id(run_id) => run_id.clone(),
+            Some(run_id) => {
+                warn!(target: "user", "Run id `{}` can't be used as a directory name, generating one", run_id);
+                Self::generate_run_id()
+            }
+            None => Self::generate_run_id(),
+        }
     }
 
     pub async fn load_config(&self) -> Result<FoundConfig> {
@@ -73,6 +135,7 @@ impl ConfigOptions {
         let found_config = FoundConfig::new(self, working_dir, config_path).await;
 
         debug!("Loaded config {:?}", found_config);
+        info!(run_id = %found_config.run_id, fingerprint = %found_config.fingerprint, "Run fingerprint");
 
         Ok(found_config)
     }
@@ -101,6 +164,55 @@ impl ConfigOptions {
     }
 }
 
+/// A resource with a `kind` or `apiVersion` this version of `scope` doesn't know, e.g. from a
+/// config written for a newer version. It's kept out of the config, but listed by `scope list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedResource {
+    pub name: String,
+    pub api_version: String,
+    pub kind: String,
+    pub file_path: String,
+    /// From the `scope.github.com/min-version` annotation, when the resource has one.
+    pub min_scope_version: Option<String>,
+}
+
+impl UnsupportedResource {
+    pub fn full_name(&self) -> String {
+        format!("{}/{}", self.kind, self.name)
+    }
+
+    /// What to do about the resource, naming the version that supports it when it's known.
+    pub fn suggestion(&self) -> String {
+        match &self.min_scope_version {
+            Some(version) => format!(
+                "Unsupported by this version, needs scope {} or newer",
+                version
+            ),
+            None => format!(
+                "Unsupported by this version, {} is unknown",
+                self.api_version
+            ),
+        }
+    }
+}
+
+impl From<&ModelRoot<Value>> for UnsupportedResource {
+    fn from(value: &ModelRoot<Value>) -> Self {
+        Self {
+            name: value.name().to_string(),
+            api_version: value.api_version.clone(),
+            kind: value.kind.clone(),
+            file_path: value.file_path(),
+            min_scope_version: value
+                .metadata
+                .annotations
+                .extra
+                .get(MIN_SCOPE_VERSION_ANNOTATION)
+                .cloned(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FoundConfig {
     pub working_dir: PathBuf,
@@ -108,9 +220,19 @@ pub struct FoundConfig {
     pub doctor_group: BTreeMap<String, DoctorGroup>,
     pub known_error: BTreeMap<String, KnownError>,
     pub report_upload: BTreeMap<String, ReportUploadLocation>,
+    pub report_definition: BTreeMap<String, ReportDefinition>,
+    pub scope_config: Option<ScopeConfig>,
+    pub action_library: BTreeMap<String, ActionLibrary>,
+    pub redaction: BTreeMap<String, Redaction>,
+    pub policy: BTreeMap<String, Policy>,
+    pub prompt_policy: BTreeMap<String, PromptPolicy>,
+    /// Resources this version doesn't support, they aren't in any of the maps above.
+    pub unsupported: Vec<UnsupportedResource>,
     pub config_path: Vec<PathBuf>,
     pub bin_path: String,
     pub run_id: String,
+    /// The machine and config of this run, computed when the config is loaded.
+    pub fingerprint: RunFingerprint,
 }
 
 impl FoundConfig {
@@ -123,8 +245,16 @@ impl FoundConfig {
             doctor_group: BTreeMap::new(),
             known_error: BTreeMap::new(),
             report_upload: BTreeMap::new(),
+            report_definition: BTreeMap::new(),
+            scope_config: None,
+            action_library: BTreeMap::new(),
+            redaction: BTreeMap::new(),
+            policy: BTreeMap::new(),
+            prompt_policy: BTreeMap::new(),
+            unsupported: Vec::new(),
             config_path: Vec::new(),
             run_id: ConfigOptions::generate_run_id(),
+            fingerprint: RunFingerprint::new(&[]),
             bin_path,
         }
     }
@@ -133,8 +263,6 @@ impl FoundConfig {
         working_dir: PathBuf,
         config_path: Vec<PathBuf>,
     ) -> Self {
-        let default_path = std::env::var("PATH").unwrap_or_default();
-
         let mut config_path = config_path.to_vec();
         let exe_path = std::env::current_exe().unwrap();
         let shared_path = exe_path.parent().unwrap().join("../etc/scope");
@@ -145,12 +273,33 @@ impl FoundConfig {
             config_path.push(can_path);
         }
 
-        let scope_path = config_path
-            .iter()
-            .map(|x| x.join("bin").display().to_string())
-            .join(":");
+        Self::load(working_dir, config_path, config_options.get_run_id()).await
+    }
 
-        let mut raw_config = load_all_config(&working_dir, &config_path).await;
+    /// Load the config again from the same paths, keeping the run id. Used to pick up edits
+    /// while `scope` is running.
+    pub async fn reload(&self) -> Self {
+        Self::load(
+            self.working_dir.clone(),
+            self.config_path.clone(),
+            self.run_id.clone(),
+        )
+        .await
+    }
+
+    pub(crate) async fn load(
+        working_dir: PathBuf,
+        config_path: Vec<PathBuf>,
+        run_id: String,
+    ) -> Self {
+        let default_path = std::env::var("PATH").unwrap_or_default();
+        let scope_path = join_path_list(
+            config_path
+                .iter()
+                .map(|x| x.join("bin").display().to_string()),
+        );
+
+        let mut raw_config = merge_layers(load_all_config(&working_dir, &config_path).await);
         raw_config.sort_by_key(|x| x.full_name());
 
         let mut this = Self {
@@ -159,37 +308,170 @@ impl FoundConfig {
             doctor_group: BTreeMap::new(),
             known_error: BTreeMap::new(),
             report_upload: BTreeMap::new(),
+            report_definition: BTreeMap::new(),
+            scope_config: None,
+            action_library: BTreeMap::new(),
+            redaction: BTreeMap::new(),
+            policy: BTreeMap::new(),
+            prompt_policy: BTreeMap::new(),
+            unsupported: Vec::new(),
             config_path,
-            bin_path: [scope_path, default_path].join(":"),
-            run_id: config_options.get_run_id(),
+            bin_path: join_path_list([scope_path, default_path]),
+            run_id,
+            fingerprint: RunFingerprint::new(&raw_config),
         };
 
-        for raw_config in raw_config {
-            if let Ok(value) = raw_config.try_into() {
+        // Libraries are loaded first, so groups can use their actions while they're parsed.
+        let (libraries, others): (Vec<_>, Vec<_>) = raw_config.into_iter().partition(|config| {
+            config
+                .kind
+                .eq_ignore_ascii_case(&V1AlphaActionLibrary::int_kind())
+        });
+        for raw_config in libraries.into_iter().chain(others) {
+            if !ParsedConfig::is_supported(&raw_config) {
+                debug!(
+                    "Skipping {}, it isn't supported by this version",
+                    raw_config.full_name()
+                );
+                this.unsupported
+                    .push(UnsupportedResource::from(&raw_config));
+                continue;
+            }
+            if let Ok(value) = ParsedConfig::from_model(raw_config, &this.action_library) {
                 this.add_model(value);
             }
         }
 
+        this.resolve_report_definitions();
+        this.resolve_known_error_fixes();
+        this.apply_report_policies();
+
         this
     }
 
+    /// Point each known error with a `fixRef` at the doctor action it references, so the fix
+    /// is only defined once.
+    pub fn resolve_known_error_fixes(&mut self) {
+        for known_error in self.known_error.values_mut() {
+            if let Err(e) = known_error.resolve_fix(&self.doctor_group) {
+                warn!(target: "user", "Unable to resolve the fix for {}: {}", known_error.full_name().bold(), e);
+            }
+        }
+    }
+
+    /// Apply the `ScopeReportDefinition` referenced by each report location, so that the
+    /// location only needs to declare the templates it overrides.
+    pub fn resolve_report_definitions(&mut self) {
+        for location in self.report_upload.values_mut() {
+            let Some(definition_name) = &location.report_definition else {
+                continue;
+            };
+            let Some(definition) = self.report_definition.get(definition_name) else {
+                warn!(target: "user", "Report location {} uses {} but no such report definition was found", location.full_name().bold(), definition_name.bold());
+                continue;
+            };
+            if let Err(e) = location.apply_definition(definition) {
+                warn!(target: "user", "Unable to apply {} to {}: {}", definition.full_name().bold(), location.full_name().bold(), e);
+            }
+        }
+    }
+
+    /// Mark the report locations that a `ScopePolicy` doesn't allow uploads to, so they're
+    /// refused when a report is sent.
+    pub fn apply_report_policies(&mut self) {
+        for location in self.report_upload.values_mut() {
+            location.policy_violation = self
+                .policy
+                .values()
+                .find_map(|policy| policy.check_report_destination(&location.destination).err());
+        }
+    }
+
+    /// The `ScopePolicy` that makes `group`, or `action` in `group`, mandatory.
+    pub fn mandatory_policy(&self, group: &str, action: Option<&str>) -> Option<&Policy> {
+        self.policy
+            .values()
+            .find(|policy| policy.is_mandatory(group, action))
+    }
+
+    /// The `ScopePolicy` that requires the fix for `action` in `group` to be confirmed.
+    pub fn confirmation_policy(&self, group: &str, action: &str) -> Option<&Policy> {
+        self.policy
+            .values()
+            .find(|policy| policy.requires_confirmation(group, action))
+    }
+
+    /// How the `ScopePromptPolicy` resources answer the prompt of the fix of `action` in
+    /// `group`, with the policy that decided. Policies are checked in name order.
+    pub fn prompt_decision(
+        &self,
+        group: &DoctorGroup,
+        action: &DoctorGroupAction,
+    ) -> Option<(PromptDecision, &PromptPolicy)> {
+        self.prompt_policy.values().find_map(|policy| {
+            policy
+                .decision(group, action)
+                .map(|decision| (decision, policy))
+        })
+    }
+
+    /// Where this run keeps its logs, results and reports.
+    pub fn artifacts(&self) -> RunArtifacts {
+        RunArtifacts::new(&self.run_id)
+    }
+
     pub fn write_raw_config_to_disk(&self) -> Result<PathBuf> {
         let json = serde_json::to_string(&self.raw_config)?;
-        let json_bytes = json.as_bytes();
-        let file_path = PathBuf::from_iter(vec![
-            "/tmp",
-            "scope",
-            &format!("config-{}.json", self.run_id),
-        ]);
+        let file_path = self.artifacts().config_file();
 
         debug!("Merged config destination is to {}", file_path.display());
 
-        let mut file = File::create(&file_path)?;
-        file.write_all(json_bytes)?;
+        RunArtifacts::write(&file_path, json.as_bytes())?;
 
         Ok(file_path)
     }
 
+    /// Color preference from the `ScopeConfig`, if there is one.
+    pub fn color_preference(&self) -> Option<ColorChoice> {
+        self.scope_config.as_ref().and_then(|config| config.color)
+    }
+
+    /// Cache dir from the `ScopeConfig`, if there is one.
+    pub fn cache_dir(&self) -> Option<PathBuf> {
+        self.scope_config
+            .as_ref()
+            .and_then(|config| config.cache_dir.clone())
+    }
+
+    /// Default args for subcommands from the `ScopeConfig`.
+    pub fn default_args(&self) -> BTreeMap<String, Vec<String>> {
+        self.scope_config
+            .as_ref()
+            .map(|config| config.default_args.clone())
+            .unwrap_or_default()
+    }
+
+    /// Patterns from every `ScopeRedaction`, named after the resource they came from.
+    pub fn redaction_patterns(&self) -> Vec<(String, Regex)> {
+        self.redaction
+            .values()
+            .flat_map(|redaction| {
+                redaction
+                    .regexes
+                    .iter()
+                    .map(|regex| (redaction.name().to_string(), regex.clone()))
+            })
+            .collect()
+    }
+
+    /// Intercept filter from the `ScopeConfig`. Without one, every command is analyzed.
+    pub fn intercept_filter(&self) -> InterceptFilter {
+        self.scope_config
+            .as_ref()
+            .map(|config| config.intercept.clone())
+            .unwrap_or_default()
+    }
+
     fn add_model(&mut self, parsed_config: ParsedConfig) {
         match parsed_config {
             ParsedConfig::DoctorGroup(exec) => {
@@ -201,10 +483,147 @@ impl FoundConfig {
             ParsedConfig::ReportUpload(report_upload) => {
                 insert_if_absent(&mut self.report_upload, report_upload);
             }
+            ParsedConfig::ReportDefinition(report_definition) => {
+                insert_if_absent(&mut self.report_definition, report_definition);
+            }
+            ParsedConfig::ActionLibrary(action_library) => {
+                insert_if_absent(&mut self.action_library, action_library);
+            }
+            ParsedConfig::Redaction(redaction) => {
+                insert_if_absent(&mut self.redaction, redaction);
+            }
+            ParsedConfig::Policy(policy) => {
+                insert_if_absent(&mut self.policy, policy);
+            }
+            ParsedConfig::PromptPolicy(prompt_policy) => {
+                insert_if_absent(&mut self.prompt_policy, prompt_policy);
+            }
+            ParsedConfig::ScopeConfig(scope_config) => {
+                if self.scope_config.is_some() {
+                    info!(target: "user", "Duplicate {} found, dropping {} in {}", "ScopeConfig".bold(), scope_config.name().bold(), scope_config.metadata().file_path());
+                } else {
+                    self.scope_config = Some(scope_config);
+                }
+            }
+        }
+    }
+}
+
+/// Semantic problems found in a config by [`FoundConfig::validate`].
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigValidationError {
+    #[error("{group} needs {dependency} but no such group was found")]
+    MissingDependency { group: String, dependency: String },
+    #[error("{name} is defined more than once")]
+    DuplicateName { name: String },
+    #[error("{name} has an invalid pattern `{pattern}`. {error}")]
+    InvalidPattern {
+        name: String,
+        pattern: String,
+        error: String,
+    },
+    #[error("{location} uses {definition} but no such report definition was found")]
+    MissingReportDefinition {
+        location: String,
+        definition: String,
+    },
+    #[error("{name} has a fixRef `{fix_ref}` that can't be used, {error}")]
+    InvalidFixRef {
+        name: String,
+        fix_ref: String,
+        error: String,
+    },
+}
+
+impl ConfigValidationError {
+    /// Full name of the resource with the problem, like `ScopeDoctorGroup/setup`.
+    pub fn resource(&self) -> &str {
+        match self {
+            ConfigValidationError::MissingDependency { group, .. } => group,
+            ConfigValidationError::DuplicateName { name } => name,
+            ConfigValidationError::InvalidPattern { name, .. } => name,
+            ConfigValidationError::MissingReportDefinition { location, .. } => location,
+            ConfigValidationError::InvalidFixRef { name, .. } => name,
+        }
+    }
+}
+
+impl FoundConfig {
+    /// Run the semantic checks that are normally surfaced when linting (dangling `needs`,
+    /// duplicate names, and invalid known-error patterns or fix references). Useful when the config was
+    /// assembled programmatically, instead of loaded from disk, to fail before anything runs.
+    pub fn validate(&self) -> Result<(), Vec<ConfigValidationError>> {
+        let mut errors = Vec::new();
+
+        errors.extend(find_duplicate_names(&self.doctor_group));
+        errors.extend(find_duplicate_names(&self.known_error));
+        errors.extend(find_duplicate_names(&self.report_upload));
+        errors.extend(find_duplicate_names(&self.report_definition));
+        errors.extend(find_duplicate_names(&self.action_library));
+
+        for group in self.doctor_group.values() {
+            for dependency in &group.requires {
+                if !self.doctor_group.contains_key(dependency) {
+                    errors.push(ConfigValidationError::MissingDependency {
+                        group: group.full_name(),
+                        dependency: dependency.to_string(),
+                    });
+                }
+            }
+        }
+
+        for known_error in self.known_error.values() {
+            if let Err(e) = Regex::new(&known_error.pattern) {
+                errors.push(ConfigValidationError::InvalidPattern {
+                    name: known_error.full_name(),
+                    pattern: known_error.pattern.to_string(),
+                    error: e.to_string(),
+                });
+            }
+            if let Some(fix_ref) = &known_error.fix_ref {
+                if let Err(e) = known_error.clone().resolve_fix(&self.doctor_group) {
+                    errors.push(ConfigValidationError::InvalidFixRef {
+                        name: known_error.full_name(),
+                        fix_ref: fix_ref.to_string(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        for location in self.report_upload.values() {
+            if let Some(definition) = &location.report_definition {
+                if !self.report_definition.contains_key(definition) {
+                    errors.push(ConfigValidationError::MissingReportDefinition {
+                        location: location.full_name(),
+                        definition: definition.to_string(),
+                    });
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
         }
     }
 }
 
+fn find_duplicate_names<T: HelpMetadata>(map: &BTreeMap<String, T>) -> Vec<ConfigValidationError> {
+    let mut seen = BTreeSet::new();
+    let mut errors = Vec::new();
+    for entry in map.values() {
+        if !seen.insert(entry.name().to_string()) {
+            errors.push(ConfigValidationError::DuplicateName {
+                name: entry.full_name(),
+            });
+        }
+    }
+
+    errors
+}
+
 fn insert_if_absent<T: HelpMetadata>(map: &mut BTreeMap<String, T>, entry: T) {
     let name = entry.name().to_string();
     if map.contains_key(&name) {
@@ -215,7 +634,7 @@ fn insert_if_absent<T: HelpMetadata>(map: &mut BTreeMap<String, T>, entry: T) {
 }
 
 async fn load_all_config(working_dir: &Path, paths: &Vec<PathBuf>) -> Vec<ModelRoot<Value>> {
-    let mut loaded_values = Vec::new();
+    let mut documents = Vec::new();
 
     for file_path in expand_to_files(paths) {
         let file_contents = match fs::read_to_string(&file_path) {
@@ -226,27 +645,72 @@ async fn load_all_config(working_dir: &Path, paths: &Vec<PathBuf>) -> Vec<ModelR
             Ok(content) => content,
         };
         for doc in Deserializer::from_str(&file_contents) {
-            if let Some(parsed_model) = parse_model(doc, working_dir, &file_path) {
-                loaded_values.push(parsed_model)
+            if let Some(value) = parse_document(doc, &file_path) {
+                documents.push((file_path.clean(), value));
             }
         }
     }
 
+    // Files included by another file are parts of a resource, not resources of their own.
+    let included: BTreeSet<PathBuf> = documents
+        .iter()
+        .flat_map(|(file_path, value)| included_paths(value, file_path))
+        .collect();
+
+    let mut loaded_values = Vec::new();
+    for (file_path, value) in documents {
+        if included.contains(&file_path) {
+            debug!(
+                "Skipping {}, it's included by another file",
+                file_path.display()
+            );
+            continue;
+        }
+        if let Some(parsed_model) = model_from_value(value, working_dir, &file_path) {
+            loaded_values.push(parsed_model)
+        }
+    }
+
     loaded_values
 }
 
+#[cfg(test)]
 pub(crate) fn parse_model(
     doc: Deserializer,
     working_dir: &Path,
     file_path: &Path,
 ) -> Option<ModelRoot<Value>> {
-    let value = match Value::deserialize(doc) {
-        Ok(value) => value,
+    let value = parse_document(doc, file_path)?;
+    model_from_value(value, working_dir, file_path)
+}
+
+fn parse_document(doc: Deserializer, file_path: &Path) -> Option<Value> {
+    match Value::deserialize(doc) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!(target: "user", "Unable to load document from {} because {}", file_path.display(), e);
+            None
+        }
+    }
+}
+
+/// Resolve the `!include`s and merge keys of a document, then parse it as a model.
+fn model_from_value(
+    mut value: Value,
+    working_dir: &Path,
+    file_path: &Path,
+) -> Option<ModelRoot<Value>> {
+    let included = match resolve_includes(&mut value, file_path) {
+        Ok(included) => included,
         Err(e) => {
             warn!(target: "user", "Unable to load document from {} because {}", file_path.display(), e);
             return None;
         }
     };
+    if let Err(e) = value.apply_merge() {
+        warn!(target: "user", "Unable to load document from {} because {}", file_path.display(), e);
+        return None;
+    }
 
     match serde_yaml::from_value::<ModelRoot<Value>>(value) {
         Ok(mut value) => {
@@ -258,6 +722,17 @@ pub(crate) fn parse_model(
             value.metadata.annotations.bin_path = Some(build_exec_path(file_path));
 
             value.metadata.annotations.working_dir = Some(working_dir.display().to_string());
+
+            if !included.is_empty() {
+                value.metadata.annotations.extra.insert(
+                    INCLUDED_FILES_ANNOTATION.to_string(),
+                    included
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                );
+            }
             Some(value)
         }
         Err(e) => {
@@ -278,7 +753,7 @@ fn build_exec_path(file_path: &Path) -> String {
 
     paths.push(std::env::var("PATH").unwrap_or_default());
 
-    paths.join(":")
+    join_path_list(paths)
 }
 
 fn expand_to_files(paths: &Vec<PathBuf>) -> Vec<PathBuf> {
@@ -345,3 +820,280 @@ pub fn build_config_path(working_dir: &Path) -> Vec<PathBuf> {
 
     scope_path
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{
+        ConfigOptions, ConfigValidationError, DoctorGroupAction, DoctorGroupBuilder, FoundConfig,
+        KnownError, KnownErrorSeverity, ModelMetadata,
+    };
+    use crate::shared::prelude::INCLUDED_FILES_ANNOTATION;
+    use regex::Regex;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    fn make_group(name: &str, requires: Vec<&str>) -> crate::prelude::DoctorGroup {
+        DoctorGroupBuilder::default()
+            .full_name(format!("ScopeDoctorGroup/{}", name))
+            .metadata(ModelMetadata::new(name))
+            .requires(crate::shared::convert_to_string(requires))
+            .run_by_default(true)
+            .actions(vec![])
+            .extra_report_args(BTreeMap::new())
+            .build()
+            .unwrap()
+    }
+
+    fn make_known_error(name: &str, pattern: &str) -> KnownError {
+        KnownError {
+            full_name: format!("ScopeKnownError/{}", name),
+            metadata: ModelMetadata::new(name),
+            pattern: pattern.to_string(),
+            regex: Regex::new("placeholder").unwrap(),
+            help_text: "help".to_string(),
+            priority: 0,
+            severity: KnownErrorSeverity::Error,
+            context_lines: 0,
+            fix_ref: None,
+            fix: None,
+        }
+    }
+
+    #[test]
+    fn test_valid_config_passes() {
+        let mut config = FoundConfig::empty(PathBuf::from("/tmp"));
+        config
+            .doctor_group
+            .insert("a".to_string(), make_group("a", vec![]));
+        config
+            .doctor_group
+            .insert("b".to_string(), make_group("b", vec!["a"]));
+        config
+            .known_error
+            .insert("error".to_string(), make_known_error("error", "error"));
+
+        assert_eq!(Ok(()), config.validate());
+    }
+
+    #[test]
+    fn test_invalid_config_reports_all_errors() {
+        let mut config = FoundConfig::empty(PathBuf::from("/tmp"));
+        config
+            .doctor_group
+            .insert("a".to_string(), make_group("a", vec!["missing"]));
+        config
+            .doctor_group
+            .insert("also-a".to_string(), make_group("a", vec![]));
+        config
+            .known_error
+            .insert("bad".to_string(), make_known_error("bad", "(unclosed"));
+
+        let errors = config.validate().unwrap_err();
+        assert_eq!(3, errors.len());
+        assert!(errors.contains(&ConfigValidationError::DuplicateName {
+            name: "ScopeDoctorGroup/a".to_string()
+        }));
+        assert!(errors.contains(&ConfigValidationError::MissingDependency {
+            group: "ScopeDoctorGroup/a".to_string(),
+            dependency: "missing".to_string()
+        }));
+        assert!(matches!(
+            errors.last(),
+            Some(ConfigValidationError::InvalidPattern { name, .. }) if name == "ScopeKnownError/bad"
+        ));
+    }
+
+    #[test]
+    fn test_known_error_fix_ref_uses_doctor_action_fix() {
+        let mut group = make_group("node", vec![]);
+        group.actions.push(DoctorGroupAction::make_from(
+            "install",
+            "Install node",
+            Some(vec!["/bin/install-node"]),
+            None,
+            None,
+        ));
+
+        let mut config = FoundConfig::empty(PathBuf::from("/tmp"));
+        config.doctor_group.insert("node".to_string(), group);
+        let mut known_error = make_known_error("missing-node", "node: not found");
+        known_error.fix_ref = Some("node/install".to_string());
+        config
+            .known_error
+            .insert("missing-node".to_string(), known_error);
+
+        assert_eq!(Ok(()), config.validate());
+        config.resolve_known_error_fixes();
+
+        let fix = config.known_error["missing-node"].fix.clone().unwrap();
+        assert_eq!("node", fix.group);
+        assert_eq!("install", fix.action);
+        assert_eq!(
+            vec!["/bin/install-node".to_string()],
+            fix.fix.command.unwrap().commands
+        );
+    }
+
+    #[test]
+    fn test_known_error_fix_ref_to_missing_action_is_invalid() {
+        let mut config = FoundConfig::empty(PathBuf::from("/tmp"));
+        config
+            .doctor_group
+            .insert("node".to_string(), make_group("node", vec![]));
+        let mut known_error = make_known_error("missing-node", "node: not found");
+        known_error.fix_ref = Some("node/install".to_string());
+        config
+            .known_error
+            .insert("missing-node".to_string(), known_error);
+
+        let errors = config.validate().unwrap_err();
+        assert_eq!(
+            vec![ConfigValidationError::InvalidFixRef {
+                name: "ScopeKnownError/missing-node".to_string(),
+                fix_ref: "node/install".to_string(),
+                error: "doctor group `node` has no action named `install`".to_string(),
+            }],
+            errors
+        );
+
+        config.resolve_known_error_fixes();
+        assert_eq!(None, config.known_error["missing-node"].fix);
+    }
+
+    #[tokio::test]
+    async fn test_config_options_without_cli() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.yaml"),
+            "apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: disk-full
+spec:
+  pattern: no space left
+  help: Free up some disk space
+",
+        )
+        .unwrap();
+        let working_dir = dir.path().display().to_string();
+
+        let config = ConfigOptions::default()
+            .with_working_dir(&working_dir)
+            .with_extra_config(&working_dir)
+            .without_default_config()
+            .with_run_id("embedded")
+            .load_config()
+            .await
+            .unwrap();
+
+        assert_eq!("embedded", config.run_id);
+        assert_eq!(dir.path(), config.working_dir);
+        assert_eq!(vec![dir.path().to_path_buf()], config.config_path);
+        assert!(config.known_error.contains_key("disk-full"));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_kinds_are_recorded_as_unsupported() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("config.yaml"),
+            "apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: disk-full
+spec:
+  pattern: no space left
+  help: Free up some disk space
+---
+apiVersion: scope.github.com/v1beta
+kind: ScopeWorkspace
+metadata:
+  name: monorepo
+  annotations:
+    scope.github.com/min-version: 2025.1.0
+spec:
+  members: []
+---
+apiVersion: scope.github.com/v2
+kind: ScopeKnownError
+metadata:
+  name: future-error
+spec: {}
+",
+        )
+        .unwrap();
+
+        let config = FoundConfig::load(
+            dir.path().to_path_buf(),
+            vec![dir.path().to_path_buf()],
+            "run".to_string(),
+        )
+        .await;
+
+        assert_eq!(1, config.known_error.len());
+        assert!(config.known_error.contains_key("disk-full"));
+        let unsupported: Vec<_> = config
+            .unsupported
+            .iter()
+            .map(|resource| (resource.full_name(), resource.suggestion()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "ScopeKnownError/future-error".to_string(),
+                    "Unsupported by this version, scope.github.com/v2 is unknown".to_string()
+                ),
+                (
+                    "ScopeWorkspace/monorepo".to_string(),
+                    "Unsupported by this version, needs scope 2025.1.0 or newer".to_string()
+                ),
+            ],
+            unsupported
+        );
+    }
+
+    #[tokio::test]
+    async fn test_included_files_are_part_of_the_resource() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("parts")).unwrap();
+        std::fs::write(
+            dir.path().join("setup.yaml"),
+            "apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: setup
+spec:
+  actions:
+    - name: brew
+      check:
+        commands: [brew --version]
+    - !include parts/actions.yaml
+",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("parts/actions.yaml"),
+            "- name: node
+  check:
+    commands: [node --version]
+",
+        )
+        .unwrap();
+
+        let config = FoundConfig::load(
+            dir.path().to_path_buf(),
+            vec![dir.path().to_path_buf()],
+            "run".to_string(),
+        )
+        .await;
+
+        assert_eq!(1, config.raw_config.len());
+        let group = &config.doctor_group["setup"];
+        let actions: Vec<_> = group.actions.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(vec!["brew", "node"], actions);
+        assert_eq!(
+            dir.path().join("parts/actions.yaml").display().to_string(),
+            group.metadata.annotations.extra[INCLUDED_FILES_ANNOTATION]
+        );
+    }
+}
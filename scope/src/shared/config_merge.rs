@@ -0,0 +1,259 @@
+use crate::models::prelude::{join_path_list, ModelRoot};
+use crate::models::HelpMetadata;
+use itertools::Itertools;
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+
+/// Annotation on a merged resource, listing the files it was merged from, highest precedence
+/// first.
+pub const MERGED_FROM_ANNOTATION: &str = "scope.github.com/merged-from";
+/// Prefix of the annotations on a merged resource naming the file each field came from, e.g.
+/// `scope.github.com/field-source.spec.actions`.
+pub const FIELD_SOURCE_ANNOTATION_PREFIX: &str = "scope.github.com/field-source.";
+
+/// Merge resources that share a kind and name into one resource. `resources` are in precedence
+/// order, the first one wins, which is the order config dirs are searched in: the `.scope` dir
+/// closest to the working dir first, then its parents, the home dir and `--extra-config`.
+///
+/// A field set by a resource with higher precedence replaces the same field from the ones below
+/// it. Mappings, like `spec` or `vars`, are merged key by key, while lists, like `actions`, are
+/// replaced as a whole. Labels are merged the same way, and the rest of `metadata` comes from the
+/// resource with the highest precedence. Each field keeps the file it came from, so its relative
+/// paths resolve against that file, see `ModelMetadata::containing_dir_of`, and commands can use
+/// the `bin` directory of every file.
+pub fn merge_layers(resources: Vec<ModelRoot<Value>>) -> Vec<ModelRoot<Value>> {
+    let mut index: BTreeMap<(String, String, String), usize> = BTreeMap::new();
+    let mut layers: Vec<Vec<ModelRoot<Value>>> = Vec::new();
+    for resource in resources {
+        let key = (
+            resource.api_version.to_lowercase(),
+            resource.kind.to_lowercase(),
+            resource.name().to_string(),
+        );
+        match index.get(&key) {
+            Some(idx) => layers[*idx].push(resource),
+            None => {
+                index.insert(key, layers.len());
+                layers.push(vec![resource]);
+            }
+        }
+    }
+
+    layers.into_iter().map(merge_resource).collect()
+}
+
+fn merge_resource(mut layers: Vec<ModelRoot<Value>>) -> ModelRoot<Value> {
+    if layers.len() == 1 {
+        return layers.remove(0);
+    }
+
+    let mut merged = layers[0].clone();
+    merged.spec = Value::Null;
+    merged.metadata.labels.clear();
+    for layer in layers.iter().rev() {
+        merge_value(&mut merged.spec, layer.spec.clone());
+        merged.metadata.labels.extend(layer.metadata.labels.clone());
+    }
+
+    let bin_paths = layers
+        .iter()
+        .filter_map(|layer| layer.metadata.annotations.bin_path.as_deref())
+        .unique()
+        .collect::<Vec<_>>();
+    if !bin_paths.is_empty() {
+        merged.metadata.annotations.bin_path = Some(join_path_list(bin_paths));
+    }
+
+    let mut paths = Vec::new();
+    leaf_paths(&merged.spec, &mut Vec::new(), &mut paths);
+
+    let annotations = &mut merged.metadata.annotations.extra;
+    annotations.insert(
+        MERGED_FROM_ANNOTATION.to_string(),
+        layers
+            .iter()
+            .map(|layer| layer.file_path())
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    for path in paths {
+        if let Some(layer) = layers
+            .iter()
+            .find(|layer| lookup(&layer.spec, &path).is_some())
+        {
+            let name = path.iter().map(key_name).collect::<Vec<_>>().join(".");
+            annotations.insert(
+                format!("{}spec.{}", FIELD_SOURCE_ANNOTATION_PREFIX, name),
+                layer.file_path(),
+            );
+        }
+    }
+
+    merged
+}
+
+/// Overlay `value` on `base`. Mappings are merged key by key, anything else replaces `base`.
+fn merge_value(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Mapping(base), Value::Mapping(value)) => {
+            for (key, value) in value {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_value(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+/// Paths to the values in `value` that aren't merged any further, like `actions` or
+/// `schedule.interval`.
+fn leaf_paths(value: &Value, prefix: &mut Vec<Value>, paths: &mut Vec<Vec<Value>>) {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, value) in mapping {
+                prefix.push(key.clone());
+                leaf_paths(value, prefix, paths);
+                prefix.pop();
+            }
+        }
+        _ if !prefix.is_empty() => paths.push(prefix.clone()),
+        _ => {}
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[Value]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |value, key| value.as_mapping()?.get(key))
+}
+
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(key) => key.clone(),
+        other => serde_yaml::to_string(other)
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::{ModelMetadata, ModelMetadataAnnotations};
+
+    fn resource(file: &str, labels: &[(&str, &str)], spec: &str) -> ModelRoot<Value> {
+        ModelRoot {
+            api_version: "scope.github.com/v1alpha".to_string(),
+            kind: "ScopeDoctorGroup".to_string(),
+            metadata: ModelMetadata {
+                name: "setup".to_string(),
+                description: format!("From {}", file),
+                annotations: ModelMetadataAnnotations {
+                    file_path: Some(file.to_string()),
+                    ..Default::default()
+                },
+                labels: labels
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            },
+            spec: serde_yaml::from_str(spec).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_closest_resource_wins_field_by_field() {
+        let repo = resource(
+            "/repo/.scope/setup.yaml",
+            &[("team", "web")],
+            "vars:\n  node: '20'\nactions:\n  - name: node\n",
+        );
+        let home = resource(
+            "/home/me/.scope/setup.yaml",
+            &[("team", "infra"), ("owner", "me")],
+            "vars:\n  node: '18'\n  python: '3.12'\nactions:\n  - name: brew\n  - name: python\nneeds: [brew]\n",
+        );
+
+        let merged = merge_layers(vec![repo, home]);
+        assert_eq!(1, merged.len());
+        let merged = &merged[0];
+
+        let expected: Value = serde_yaml::from_str("node: '20'\npython: '3.12'\n").unwrap();
+        assert_eq!(expected, merged.spec["vars"]);
+        let expected: Value = serde_yaml::from_str("[{name: node}]").unwrap();
+        assert_eq!(expected, merged.spec["actions"]);
+        let expected: Value = serde_yaml::from_str("[brew]").unwrap();
+        assert_eq!(expected, merged.spec["needs"]);
+
+        assert_eq!("From /repo/.scope/setup.yaml", merged.metadata.description);
+        assert_eq!("web", merged.metadata.labels["team"]);
+        assert_eq!("me", merged.metadata.labels["owner"]);
+
+        let annotations = &merged.metadata.annotations.extra;
+        assert_eq!(
+            "/repo/.scope/setup.yaml, /home/me/.scope/setup.yaml",
+            annotations[MERGED_FROM_ANNOTATION]
+        );
+        let source = |field: &str| {
+            annotations[&format!("{}{}", FIELD_SOURCE_ANNOTATION_PREFIX, field)].as_str()
+        };
+        assert_eq!("/repo/.scope/setup.yaml", source("spec.vars.node"));
+        assert_eq!("/home/me/.scope/setup.yaml", source("spec.vars.python"));
+        assert_eq!("/repo/.scope/setup.yaml", source("spec.actions"));
+        assert_eq!("/home/me/.scope/setup.yaml", source("spec.needs"));
+    }
+
+    #[test]
+    fn test_resources_without_duplicates_are_unchanged() {
+        let group = resource("/repo/.scope/setup.yaml", &[], "actions: []\n");
+        let mut other = group.clone();
+        other.kind = "ScopeKnownError".to_string();
+
+        let merged = merge_layers(vec![group.clone(), other.clone()]);
+        assert_eq!(vec![group, other], merged);
+
+        let group = resource("/repo/.scope/setup.yaml", &[], "actions: []\n");
+        let mut other = group.clone();
+        other.api_version = "scope.github.com/v1beta".to_string();
+
+        let merged = merge_layers(vec![group.clone(), other.clone()]);
+        assert_eq!(vec![group, other], merged);
+    }
+
+    #[test]
+    fn test_fields_resolve_against_the_file_they_came_from() {
+        let mut repo = resource("/repo/.scope/setup.yaml", &[], "vars:\n  node: '20'\n");
+        repo.metadata.annotations.file_dir = Some("/repo/.scope".to_string());
+        repo.metadata.annotations.bin_path = Some("/repo/.scope/bin".to_string());
+        let mut home = resource(
+            "/home/me/.scope/setup.yaml",
+            &[],
+            "actions:\n  - name: lint\n    check:\n      paths: [lint.yaml]\n",
+        );
+        home.metadata.annotations.file_dir = Some("/home/me/.scope".to_string());
+        home.metadata.annotations.bin_path = Some("/home/me/.scope/bin".to_string());
+
+        let merged = merge_layers(vec![repo, home]).remove(0);
+        assert_eq!("/repo/.scope", merged.metadata.containing_dir());
+        assert_eq!(
+            "/home/me/.scope",
+            merged.metadata.containing_dir_of("spec.actions")
+        );
+        assert_eq!(
+            "/repo/.scope",
+            merged.metadata.containing_dir_of("spec.vars")
+        );
+        assert_eq!(
+            "/repo/.scope",
+            merged.metadata.containing_dir_of("spec.cacheDir")
+        );
+        assert_eq!(
+            Some(join_path_list(["/repo/.scope/bin", "/home/me/.scope/bin"])),
+            merged.metadata.annotations.bin_path
+        );
+    }
+}
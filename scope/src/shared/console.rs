@@ -0,0 +1,42 @@
+use lazy_static::lazy_static;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+lazy_static! {
+    pub static ref STDOUT_WRITER: Arc<RwLock<Box<dyn Write + Sync + Send>>> =
+        Arc::new(RwLock::new(Box::new(std::io::stdout())));
+    pub static ref STDERR_WRITER: Arc<RwLock<Box<dyn Write + Sync + Send>>> =
+        Arc::new(RwLock::new(Box::new(std::io::stderr())));
+}
+
+static STDOUT_TO_STDERR: AtomicBool = AtomicBool::new(false);
+static CHILD_STDIN_NULL: AtomicBool = AtomicBool::new(false);
+
+/// Send console output that would go to stdout to stderr instead, keeping stdout free for
+/// machine readable output like `scope doctor run --output json`.
+pub fn redirect_stdout_to_stderr() {
+    STDOUT_TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+/// Give the commands scope runs an empty stdin instead of scope's, for when stdin has input for
+/// scope itself, like the requests of `scope serve`.
+pub fn detach_child_stdin() {
+    CHILD_STDIN_NULL.store(true, Ordering::Relaxed);
+}
+
+/// Stdin for a command scope runs, scope's own unless [`detach_child_stdin`] was called.
+pub(crate) fn child_stdin() -> std::process::Stdio {
+    if CHILD_STDIN_NULL.load(Ordering::Relaxed) {
+        std::process::Stdio::null()
+    } else {
+        std::process::Stdio::inherit()
+    }
+}
+
+/// Whether [`redirect_stdout_to_stderr`] was called.
+#[cfg(feature = "cli")]
+pub(crate) fn stdout_redirected() -> bool {
+    STDOUT_TO_STDERR.load(Ordering::Relaxed)
+}
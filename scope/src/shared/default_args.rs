@@ -0,0 +1,202 @@
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+/// Add the default args from the `ScopeConfig` for the subcommand being run to `args`.
+///
+/// `default_args` is keyed by the subcommand's path, like `doctor run`. The defaults are inserted
+/// right after the subcommand name, as if they had been typed. Options given on the command line
+/// take precedence: a default option, with its values, is dropped when the same option was passed.
+pub fn apply_default_args(
+    command: &Command,
+    args: Vec<OsString>,
+    default_args: &BTreeMap<String, Vec<String>>,
+) -> Vec<OsString> {
+    if default_args.is_empty() {
+        return args;
+    }
+    // Invalid args are reported when they're parsed for real.
+    let Ok(root_matches) = command.clone().try_get_matches_from(&args) else {
+        return args;
+    };
+
+    let mut path = Vec::new();
+    let mut insert_at = 1;
+    let mut commands = vec![command];
+    let mut command = command;
+    let mut matches = &root_matches;
+    while let Some((name, sub_matches)) = matches.subcommand() {
+        let Some(sub_command) = command.find_subcommand(name) else {
+            break;
+        };
+        let is_name = |arg: &OsString| {
+            arg.to_str().is_some_and(|arg| {
+                arg == sub_command.get_name() || sub_command.get_all_aliases().any(|a| a == arg)
+            })
+        };
+        let Some(position) = args.iter().skip(insert_at).position(is_name) else {
+            return args;
+        };
+        insert_at += position + 1;
+        path.push(name);
+        commands.push(sub_command);
+        command = sub_command;
+        matches = sub_matches;
+    }
+
+    let Some(defaults) = default_args.get(&path.join(" ")) else {
+        return args;
+    };
+
+    let mut args = args;
+    args.splice(
+        insert_at..insert_at,
+        without_overridden(&commands, matches, defaults)
+            .into_iter()
+            .map(OsString::from),
+    );
+    args
+}
+
+/// Keep the options in `defaults`, and the values that follow them, that weren't given on the
+/// command line. `commands` is the path to the subcommand, its parents' global options apply too.
+fn without_overridden(
+    commands: &[&Command],
+    matches: &ArgMatches,
+    defaults: &[String],
+) -> Vec<String> {
+    let mut kept = Vec::new();
+    let mut keep = true;
+    for arg in defaults {
+        if arg.starts_with('-') {
+            keep = match find_arg_id(commands, arg) {
+                Some(id) => matches.value_source(id) != Some(ValueSource::CommandLine),
+                None => true,
+            };
+        }
+        if keep {
+            kept.push(arg.clone());
+        }
+    }
+    kept
+}
+
+fn find_arg_id<'a>(commands: &[&'a Command], option: &str) -> Option<&'a str> {
+    let (command, parents) = commands.split_last()?;
+    let mut args = command.get_arguments().chain(
+        parents
+            .iter()
+            .copied()
+            .flat_map(Command::get_arguments)
+            .filter(|arg| arg.is_global_set()),
+    );
+
+    let option = option.split('=').next().unwrap_or(option);
+    let arg = if let Some(long) = option.strip_prefix("--") {
+        args.find(|arg| {
+            arg.get_long() == Some(long)
+                || arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&long))
+        })
+    } else {
+        let short = option.strip_prefix('-')?.chars().next()?;
+        args.find(|arg| {
+            arg.get_short() == Some(short)
+                || arg
+                    .get_all_short_aliases()
+                    .is_some_and(|aliases| aliases.contains(&short))
+        })
+    }?;
+
+    Some(arg.get_id().as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, ArgAction};
+
+    fn command() -> Command {
+        Command::new("scope")
+            .arg(
+                Arg::new("debug")
+                    .long("debug")
+                    .global(true)
+                    .action(ArgAction::SetTrue),
+            )
+            .subcommand(
+                Command::new("doctor").alias("d").subcommand(
+                    Command::new("run")
+                        .arg(
+                            Arg::new("no-cache")
+                                .long("no-cache")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("only")
+                                .long("only")
+                                .short('o')
+                                .action(ArgAction::Append),
+                        ),
+                ),
+            )
+    }
+
+    fn apply(args: &str, defaults: &[&str]) -> Vec<String> {
+        let default_args = BTreeMap::from([(
+            "doctor run".to_string(),
+            defaults.iter().map(|arg| arg.to_string()).collect(),
+        )]);
+        let args = args.split(' ').map(OsString::from).collect();
+
+        apply_default_args(&command(), args, &default_args)
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_defaults_inserted_after_subcommand() {
+        assert_eq!(
+            vec![
+                "scope",
+                "--debug",
+                "d",
+                "run",
+                "--no-cache",
+                "--only",
+                "setup"
+            ],
+            apply("scope --debug d run --only setup", &["--no-cache"])
+        );
+    }
+
+    #[test]
+    fn test_command_line_takes_precedence() {
+        assert_eq!(
+            vec!["scope", "doctor", "run", "--no-cache", "-o", "mine"],
+            apply(
+                "scope doctor run -o mine",
+                &["--only", "a", "--only=b", "--no-cache"]
+            )
+        );
+    }
+
+    #[test]
+    fn test_global_option_on_command_line_takes_precedence() {
+        assert_eq!(
+            vec!["scope", "doctor", "run", "--no-cache", "--debug"],
+            apply("scope doctor run --debug", &["--debug", "--no-cache"])
+        );
+    }
+
+    #[test]
+    fn test_other_subcommands_unchanged() {
+        assert_eq!(
+            vec!["scope", "doctor"],
+            apply("scope doctor", &["--no-cache"])
+        );
+    }
+}
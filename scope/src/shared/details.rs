@@ -0,0 +1,234 @@
+use std::cmp::{max, min};
+use std::path::Path;
+
+use colored::Colorize;
+use unicode_width::UnicodeWidthChar;
+use unicode_width::UnicodeWidthStr;
+
+use crate::models::HelpMetadata;
+use crate::report_stdout;
+use crate::shared::prelude::{markdown_to_plain, render_markdown};
+
+/// Narrowest the description column gets before paths are shortened instead.
+const MIN_DESCRIPTION_WIDTH: usize = 20;
+/// Narrowest a path gets when it's shortened.
+const MIN_PATH_WIDTH: usize = 20;
+/// Space between columns.
+const GAP: usize = 2;
+
+/// One resource in the table printed by [`print_details`].
+struct DetailsRow {
+    name: String,
+    /// Description as markdown.
+    description: String,
+    path: String,
+}
+
+/// Print the name, description and path of each resource as a table. The table is sized to the
+/// terminal, shortening descriptions, then paths, so each resource stays on one line. When
+/// `wide` is set, or output isn't a terminal, nothing is shortened.
+pub async fn print_details<T>(working_dir: &Path, config: &[T], wide: bool)
+where
+    T: HelpMetadata,
+{
+    let rows: Vec<_> = config
+        .iter()
+        .map(|resource| {
+            let path = resource.metadata().file_path();
+            DetailsRow {
+                name: resource.full_name(),
+                description: resource.description(),
+                path: pathdiff::diff_paths(&path, working_dir)
+                    .map(|diff| diff.display().to_string())
+                    .unwrap_or(path),
+            }
+        })
+        .collect();
+
+    let max_width = if wide {
+        None
+    } else {
+        console::Term::stdout()
+            .size_checked()
+            .map(|(_, columns)| columns as usize)
+    };
+
+    for line in format_details(&rows, max_width) {
+        report_stdout!("{}", line);
+    }
+}
+
+fn format_details(rows: &[DetailsRow], max_width: Option<usize>) -> Vec<String> {
+    let plain_descriptions: Vec<_> = rows
+        .iter()
+        .map(|row| markdown_to_plain(&row.description))
+        .collect();
+
+    let name_width = max(
+        rows.iter().map(|row| row.name.width()).max().unwrap_or(0),
+        20,
+    ) + GAP;
+    let description_natural = max(
+        plain_descriptions
+            .iter()
+            .map(|text| text.width())
+            .max()
+            .unwrap_or(0),
+        "Description".len(),
+    ) + GAP;
+    let path_natural = rows.iter().map(|row| row.path.width()).max().unwrap_or(0);
+
+    let (description_width, path_width) = match max_width {
+        None => (description_natural, path_natural),
+        Some(max_width) => {
+            let available = max_width.saturating_sub(GAP + name_width);
+            let description_width = min(
+                description_natural,
+                max(
+                    available.saturating_sub(path_natural),
+                    MIN_DESCRIPTION_WIDTH,
+                ),
+            );
+            let path_width = max(available.saturating_sub(description_width), MIN_PATH_WIDTH);
+            (description_width, path_width)
+        }
+    };
+
+    let mut lines = vec![format!(
+        "  {}{}{}",
+        pad("Name".white().bold().to_string(), "Name".len(), name_width),
+        pad(
+            "Description".white().bold().to_string(),
+            "Description".len(),
+            description_width
+        ),
+        "Path".white().bold()
+    )];
+
+    for (row, plain) in rows.iter().zip(plain_descriptions) {
+        // Markup is only rendered when the description fits, truncated markup can't be styled.
+        let text_width = description_width.saturating_sub(GAP);
+        let description = if plain.width() > text_width {
+            let truncated = truncate_end(&plain, text_width);
+            let width = truncated.width();
+            pad(truncated, width, description_width)
+        } else {
+            pad(
+                render_markdown(&row.description),
+                plain.width(),
+                description_width,
+            )
+        };
+
+        let path = if row.path.width() > path_width {
+            truncate_start(&row.path, path_width)
+        } else {
+            row.path.to_string()
+        };
+
+        lines.push(format!(
+            "- {}{}{}",
+            pad(row.name.to_string(), row.name.width(), name_width),
+            description,
+            path
+        ));
+    }
+
+    lines
+}
+
+/// Pad `text`, which takes `width` columns when displayed, to `target` columns.
+fn pad(text: String, width: usize, target: usize) -> String {
+    format!("{}{}", text, " ".repeat(target.saturating_sub(width)))
+}
+
+/// Keep the start of `text`, ending with `...`, so it fits in `width` columns.
+fn truncate_end(text: &str, width: usize) -> String {
+    let budget = width.saturating_sub(3);
+    let mut used = 0;
+    let kept: String = text
+        .chars()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= budget
+        })
+        .collect();
+    format!("{}...", kept)
+}
+
+/// Keep the end of `text`, starting with `...`, so it fits in `width` columns.
+fn truncate_start(text: &str, width: usize) -> String {
+    let budget = width.saturating_sub(3);
+    let mut used = 0;
+    let mut kept: Vec<_> = text
+        .chars()
+        .rev()
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= budget
+        })
+        .collect();
+    kept.reverse();
+    format!("...{}", kept.into_iter().collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, description: &str, path: &str) -> DetailsRow {
+        DetailsRow {
+            name: name.to_string(),
+            description: description.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    fn table(max_width: Option<usize>) -> Vec<String> {
+        colored::control::set_override(false);
+        format_details(
+            &[
+                row(
+                    "ScopeDoctorGroup/node",
+                    "Makes sure the right version of node is installed, and that npm can reach the registry",
+                    ".scope/node.yaml",
+                ),
+                row("ScopeDoctorGroup/日本", "Überprüft 日本語 Text", ".scope/japan.yaml"),
+            ],
+            max_width,
+        )
+    }
+
+    #[test]
+    fn test_wide_never_truncates() {
+        let lines = table(None);
+        assert!(lines[1].contains("npm can reach the registry  .scope/node.yaml"));
+        assert!(!lines[1].contains("..."));
+    }
+
+    #[test]
+    fn test_sized_to_terminal() {
+        let lines = table(Some(80));
+        for line in &lines {
+            assert!(line.width() <= 80, "{} is too wide", line);
+        }
+        assert!(lines[1].contains("..."));
+        assert!(lines[1].ends_with(".scope/node.yaml"));
+    }
+
+    #[test]
+    fn test_multi_byte_characters_stay_aligned() {
+        let lines = table(None);
+        let path_column = |line: &str| line.width() - line.rsplit(' ').next().unwrap().width();
+        assert_eq!(path_column(&lines[1]), path_column(&lines[2]));
+    }
+
+    #[test]
+    fn test_truncate_keeps_whole_characters() {
+        assert_eq!("日本...", truncate_end("日本語テキスト", 8));
+        assert_eq!(
+            "...ne.yaml",
+            truncate_start("a/very/long/path/to/one.yaml", 10)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use crate::models::prelude::{ModelMetadataAnnotations, ModelRoot};
+use gethostname::gethostname;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::fmt::{Display, Formatter};
+
+/// Length of the hashes in a [`RunFingerprint`], in hex characters.
+const HASH_LEN: usize = 16;
+
+/// Describes the machine and config a run used. It's computed once per run and included in logs,
+/// JSON results and uploaded reports, so failures can be correlated across machines and stale
+/// config rollouts spotted.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RunFingerprint {
+    /// Hash of the hostname, tells machines apart without naming them.
+    pub host_hash: String,
+    /// Operating system, like `linux` or `macos`.
+    pub os: String,
+    /// CPU architecture, like `x86_64` or `aarch64`.
+    pub arch: String,
+    /// Version of scope that ran.
+    pub scope_version: String,
+    /// Hash of every loaded resource. Machines with the same config have the same hash, wherever
+    /// the files are.
+    pub config_hash: String,
+}
+
+impl RunFingerprint {
+    pub fn new(raw_config: &[ModelRoot<Value>]) -> Self {
+        Self {
+            host_hash: short_hash(&gethostname().to_string_lossy()),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            scope_version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: config_hash(raw_config),
+        }
+    }
+}
+
+impl Display for RunFingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "host={} os={} arch={} scope={} config={}",
+            self.host_hash, self.os, self.arch, self.scope_version, self.config_hash
+        )
+    }
+}
+
+/// Hash the resources without the annotations scope adds while loading them, as those hold paths
+/// that differ between machines.
+fn config_hash(raw_config: &[ModelRoot<Value>]) -> String {
+    let resources: Vec<_> = raw_config
+        .iter()
+        .map(|resource| {
+            let mut resource = resource.clone();
+            resource.metadata.annotations = ModelMetadataAnnotations {
+                extra: resource.metadata.annotations.extra,
+                ..Default::default()
+            };
+            resource
+        })
+        .collect();
+
+    short_hash(&serde_json::to_string(&resources).unwrap_or_default())
+}
+
+fn short_hash(input: &str) -> String {
+    let mut digest = sha256::digest(input);
+    digest.truncate(HASH_LEN);
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::config_load::parse_model;
+    use serde_yaml::Deserializer;
+    use std::path::Path;
+
+    fn raw_config(dir: &str) -> Vec<ModelRoot<Value>> {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: error-exists
+spec:
+  pattern: error
+  help: There is an error
+";
+        Deserializer::from_str(text)
+            .filter_map(|doc| {
+                parse_model(
+                    doc,
+                    Path::new(dir),
+                    &Path::new(dir).join("known-error.yaml"),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_config_hash_ignores_where_the_files_are() {
+        let here = RunFingerprint::new(&raw_config("/home/a/project"));
+        let there = RunFingerprint::new(&raw_config("/home/b/src"));
+
+        assert_eq!(here.config_hash, there.config_hash);
+        assert_eq!(HASH_LEN, here.config_hash.len());
+        assert_ne!(here.config_hash, RunFingerprint::new(&[]).config_hash);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::models::prelude::PromptDecisionSpec;
+use colored::Colorize;
+#[cfg(feature = "interactive")]
+use inquire::InquireError;
+use mockall::automock;
+#[cfg(feature = "interactive")]
+use std::io::IsTerminal;
+use std::sync::Arc;
+use tracing::info;
+
+/// Result of asking the user to confirm something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptAnswer {
+    Approved,
+    Denied,
+    /// The prompt was never shown because there isn't a terminal to answer it, like in CI.
+    NotInteractive,
+}
+
+impl PromptAnswer {
+    /// Value to record in the audit log, `None` when the user wasn't asked.
+    pub fn as_recorded(&self) -> Option<bool> {
+        match self {
+            PromptAnswer::Approved => Some(true),
+            PromptAnswer::Denied => Some(false),
+            PromptAnswer::NotInteractive => None,
+        }
+    }
+}
+
+#[automock]
+pub trait UserInteraction: Send + Sync {
+    fn confirm<'a>(&self, prompt: &'a str, help_text: Option<&'a str>) -> PromptAnswer;
+}
+
+/// Ask on the terminal. When stdin isn't a terminal nothing is asked, and the answer is
+/// `NotInteractive` instead of a silent "no".
+#[cfg(feature = "interactive")]
+#[derive(Debug, Default)]
+pub struct InquireInteraction;
+
+#[cfg(feature = "interactive")]
+impl UserInteraction for InquireInteraction {
+    fn confirm(&self, prompt: &str, help_text: Option<&str>) -> PromptAnswer {
+        if !std::io::stdin().is_terminal() {
+            return PromptAnswer::NotInteractive;
+        }
+
+        let mut confirm = inquire::Confirm::new(prompt).with_default(true);
+        if let Some(help_text) = help_text {
+            confirm = confirm.with_help_message(help_text);
+        }
+
+        match confirm.prompt() {
+            Ok(true) => PromptAnswer::Approved,
+            Ok(false) => PromptAnswer::Denied,
+            Err(InquireError::NotTTY) => PromptAnswer::NotInteractive,
+            Err(_) => PromptAnswer::Denied,
+        }
+    }
+}
+
+/// Never ask, every prompt is answered `NotInteractive`, as if there isn't a terminal.
+#[derive(Debug, Default)]
+pub struct NoInteraction;
+
+impl UserInteraction for NoInteraction {
+    fn confirm(&self, _prompt: &str, _help_text: Option<&str>) -> PromptAnswer {
+        PromptAnswer::NotInteractive
+    }
+}
+
+/// How prompts are answered when nothing else was chosen: on the terminal when built with the
+/// `interactive` feature, otherwise never.
+#[cfg(feature = "interactive")]
+pub type DefaultInteraction = InquireInteraction;
+#[cfg(not(feature = "interactive"))]
+pub type DefaultInteraction = NoInteraction;
+
+/// Approve every prompt without asking, for unattended runs.
+#[derive(Debug, Default)]
+pub struct AutoApprove;
+
+impl UserInteraction for AutoApprove {
+    fn confirm(&self, _prompt: &str, _help_text: Option<&str>) -> PromptAnswer {
+        PromptAnswer::Approved
+    }
+}
+
+/// How a prompt is answered, decided by a `ScopePromptPolicy` or the `--*-labeled` flags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptDecision {
+    Approve,
+    Deny,
+    Ask,
+}
+
+impl From<PromptDecisionSpec> for PromptDecision {
+    fn from(value: PromptDecisionSpec) -> Self {
+        match value {
+            PromptDecisionSpec::Approve => PromptDecision::Approve,
+            PromptDecisionSpec::Deny => PromptDecision::Deny,
+            PromptDecisionSpec::Ask => PromptDecision::Ask,
+        }
+    }
+}
+
+/// Answers prompts the way a prompt policy decided, in front of the interaction that asks.
+/// `source` names what made the decision, it's logged when a prompt is answered without asking.
+pub struct PromptPolicyInteraction {
+    decision: PromptDecision,
+    source: String,
+    ask: Arc<dyn UserInteraction>,
+}
+
+impl PromptPolicyInteraction {
+    pub fn new(decision: PromptDecision, source: String, ask: Arc<dyn UserInteraction>) -> Self {
+        Self {
+            decision,
+            source,
+            ask,
+        }
+    }
+}
+
+impl UserInteraction for PromptPolicyInteraction {
+    fn confirm(&self, prompt: &str, help_text: Option<&str>) -> PromptAnswer {
+        match self.decision {
+            PromptDecision::Approve => {
+                info!(target: "user", "{} {}", prompt, format!("approved by {}", self.source).dimmed());
+                PromptAnswer::Approved
+            }
+            PromptDecision::Deny => {
+                info!(target: "user", "{} {}", prompt, format!("denied by {}", self.source).dimmed());
+                PromptAnswer::Denied
+            }
+            PromptDecision::Ask => self.ask.confirm(prompt, help_text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_policy_interaction() {
+        let mut ask = MockUserInteraction::new();
+        ask.expect_confirm()
+            .times(1)
+            .returning(|_, _| PromptAnswer::Denied);
+        let ask: Arc<dyn UserInteraction> = Arc::new(ask);
+
+        let source = "ScopePromptPolicy/fixes".to_string();
+        let approve =
+            PromptPolicyInteraction::new(PromptDecision::Approve, source.clone(), ask.clone());
+        assert_eq!(PromptAnswer::Approved, approve.confirm("Run it?", None));
+        let deny = PromptPolicyInteraction::new(PromptDecision::Deny, source.clone(), ask.clone());
+        assert_eq!(PromptAnswer::Denied, deny.confirm("Run it?", None));
+        let prompt = PromptPolicyInteraction::new(PromptDecision::Ask, source, ask);
+        assert_eq!(PromptAnswer::Denied, prompt.confirm("Run it?", None));
+    }
+}
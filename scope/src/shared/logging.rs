@@ -1,6 +1,10 @@
+use super::console::stdout_redirected;
+use super::progress_bar::default_progress_bar;
+use crate::shared::prelude::{
+    configure_colors, ColorAwareMakeWriter, ColorChoice, RunArtifacts, STDERR_WRITER, STDOUT_WRITER,
+};
 use clap::{ArgGroup, Parser, ValueEnum};
 use gethostname::gethostname;
-use indicatif::ProgressStyle;
 use lazy_static::lazy_static;
 use opentelemetry::{global, KeyValue};
 use opentelemetry_otlp::{
@@ -14,20 +18,21 @@ use opentelemetry_sdk::{
     trace::{self, RandomIdGenerator, Sampler},
     Resource,
 };
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
-use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::RwLock;
-use tonic::metadata::MetadataMap;
+use tonic::metadata::{MetadataKey, MetadataMap};
 
 use tracing::level_filters::LevelFilter;
 use tracing::warn;
 use tracing_indicatif::filter::{hide_indicatif_span_fields, IndicatifFilter};
+use tracing_indicatif::writer::{IndicatifWriter, Stderr, Stdout};
 use tracing_indicatif::IndicatifLayer;
 use tracing_opentelemetry::MetricsLayer;
 use tracing_subscriber::fmt::format::DefaultFields;
+use tracing_subscriber::fmt::MakeWriter;
 use tracing_subscriber::{filter::filter_fn, prelude::*};
 use tracing_subscriber::{
     fmt::format::{Format, PrettyFields},
@@ -49,22 +54,6 @@ lazy_static! {
     ];
 }
 
-pub fn default_progress_bar() -> ProgressStyle {
-    ProgressStyle::with_template(
-        "{span_child_prefix} {spinner:.green} {wide_msg} {pos:>7}/{len:7} [{elapsed_precise}]",
-    )
-    .unwrap()
-    .progress_chars("##-")
-}
-
-pub fn progress_bar_without_pos() -> ProgressStyle {
-    ProgressStyle::with_template(
-        "{span_child_prefix} {spinner:.green} {wide_msg} [{elapsed_precise}]",
-    )
-    .unwrap()
-    .progress_chars("##-")
-}
-
 #[derive(Parser, Debug)]
 #[clap(group = ArgGroup::new("logging"))]
 pub struct LoggingOpts {
@@ -96,12 +85,54 @@ pub struct LoggingOpts {
     )]
     otel_protocol: OtelProtocol,
 
+    /// Header to send to the otel collector, as `name=value`, e.g. for an API key. Can be used
+    /// multiple times, the env var takes a comma separated list
+    #[clap(
+        long = "otel-header",
+        env = "SCOPE_OTEL_HEADERS",
+        global(true),
+        value_delimiter = ',',
+        value_parser = parse_otel_header
+    )]
+    otel_headers: Vec<(String, String)>,
+
+    /// Share of traces sent to the otel collector, from 0 to 1. Metrics are always sent
+    #[clap(
+        long = "otel-sample-ratio",
+        env = "SCOPE_OTEL_SAMPLE_RATIO",
+        global(true),
+        default_value = "1",
+        value_parser = parse_sample_ratio
+    )]
+    otel_sample_ratio: f64,
+
+    /// When to color output. Defaults to the `ScopeConfig` preference, `NO_COLOR` or `FORCE_COLOR`,
+    /// and otherwise colors output written to a terminal.
+    #[arg(long, global(true), env = "SCOPE_COLOR")]
+    pub color: Option<ColorChoice>,
+
     /// When set, we'll send debug details to otel endpoint.
     /// This option is hidden when running --help
     #[arg(long, hide = true, global(true))]
     pub otel_debug: bool,
 }
 
+fn parse_otel_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("`{}` should look like `name=value`", value)),
+    }
+}
+
+fn parse_sample_ratio(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(ratio) if (0.0..=1.0).contains(&ratio) => Ok(ratio),
+        _ => Err(format!("`{}` isn't a number from 0 to 1", value)),
+    }
+}
+
 #[derive(ValueEnum, Debug, Copy, Clone)]
 pub enum OtelProtocol {
     Http,
@@ -128,11 +159,38 @@ impl LoggingProgress {
     }
 }
 
-lazy_static! {
-    pub static ref STDOUT_WRITER: Arc<RwLock<Box<dyn Write + Sync + Send>>> =
-        Arc::new(RwLock::new(Box::new(std::io::stdout())));
-    pub static ref STDERR_WRITER: Arc<RwLock<Box<dyn Write + Sync + Send>>> =
-        Arc::new(RwLock::new(Box::new(std::io::stderr())));
+/// Writes to stdout, or to stderr after `redirect_stdout_to_stderr`. The check happens on every
+/// write, so the redirect applies to logging that was set up before it.
+#[derive(Clone)]
+struct ConsoleWriter {
+    stdout: IndicatifWriter<Stdout>,
+    stderr: IndicatifWriter<Stderr>,
+}
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if stdout_redirected() {
+            self.stderr.write(buf)
+        } else {
+            self.stdout.write(buf)
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if stdout_redirected() {
+            self.stderr.flush()
+        } else {
+            self.stdout.flush()
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for ConsoleWriter {
+    type Writer = ConsoleWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
 }
 
 pub struct ConfiguredLogger {
@@ -165,7 +223,10 @@ impl LoggingOpts {
             default_level: new_default,
             otel_collector: self.otel_collector.clone(),
             otel_protocol: self.otel_protocol,
+            otel_headers: self.otel_headers.clone(),
+            otel_sample_ratio: self.otel_sample_ratio,
             otel_debug: self.otel_debug,
+            color: self.color,
         }
     }
 
@@ -191,6 +252,17 @@ impl LoggingOpts {
                 .unwrap(),
         );
         map.insert("scope.id", id.parse().unwrap());
+        for (name, value) in &self.otel_headers {
+            match (MetadataKey::from_bytes(name.as_bytes()), value.parse()) {
+                (Ok(key), Ok(value)) => {
+                    map.insert(key, value);
+                }
+                _ => warn!(
+                    "Unable to send otel header `{}`, it isn't a valid header",
+                    name
+                ),
+            }
+        }
 
         opentelemetry_otlp::new_exporter()
             .tonic()
@@ -206,6 +278,7 @@ impl LoggingOpts {
             .http()
             .with_endpoint(endpoint)
             .with_timeout(Duration::from_secs(3))
+            .with_headers(self.otel_headers.iter().cloned().collect::<HashMap<_, _>>())
     }
 
     fn make_span_exporter_builder(&self, id: &str) -> SpanExporterBuilder {
@@ -222,6 +295,14 @@ impl LoggingOpts {
         }
     }
 
+    fn sampler(&self) -> Sampler {
+        if self.otel_sample_ratio >= 1.0 {
+            Sampler::AlwaysOn
+        } else {
+            Sampler::TraceIdRatioBased(self.otel_sample_ratio)
+        }
+    }
+
     fn setup_otel(&self, run_id: &str) -> Result<Option<OtelProperties>, anyhow::Error> {
         if self.otel_collector.is_some() {
             let resources = Resource::new(vec![
@@ -241,7 +322,7 @@ impl LoggingOpts {
                 .with_exporter(self.make_span_exporter_builder(run_id))
                 .with_trace_config(
                     trace::config()
-                        .with_sampler(Sampler::AlwaysOn)
+                        .with_sampler(self.sampler())
                         .with_id_generator(RandomIdGenerator::default())
                         .with_max_events_per_span(64)
                         .with_max_attributes_per_span(16)
@@ -267,9 +348,13 @@ impl LoggingOpts {
     }
 
     pub async fn configure_logging(&self, run_id: &str, prefix: &str) -> ConfiguredLogger {
-        let file_name = format!("scope-{}-{}.log", prefix, run_id);
-        let full_file_name = format!("/tmp/scope/{}", file_name);
-        std::fs::create_dir_all("/tmp/scope").expect("to be able to create tmp dir");
+        let artifacts = RunArtifacts::new(run_id);
+        artifacts
+            .create()
+            .expect("to be able to create the artifact dir");
+        let full_file_name = artifacts.log_file(prefix).display().to_string();
+
+        configure_colors(self.color, None);
 
         let otel_props = self.setup_otel(run_id).unwrap_or_else(|e| {
             println!(
@@ -293,9 +378,12 @@ impl LoggingOpts {
         let indicatif_layer = IndicatifLayer::new()
             .with_span_field_formatter(hide_indicatif_span_fields(DefaultFields::new()))
             .with_progress_style(default_progress_bar());
-        let indicatif_writer = indicatif_layer.get_stdout_writer();
+        let indicatif_writer = ConsoleWriter {
+            stdout: indicatif_layer.get_stdout_writer(),
+            stderr: indicatif_layer.get_stderr_writer(),
+        };
 
-        *STDOUT_WRITER.write().await = Box::new(indicatif_layer.get_stdout_writer());
+        *STDOUT_WRITER.write().await = Box::new(indicatif_writer.clone());
         *STDERR_WRITER.write().await = Box::new(indicatif_layer.get_stderr_writer());
 
         let is_tty_output = self.progress.is_tty();
@@ -308,7 +396,8 @@ impl LoggingOpts {
                     .without_time()
                     .compact(),
             )
-            .with_writer(indicatif_writer)
+            .with_ansi(true)
+            .with_writer(ColorAwareMakeWriter::new(indicatif_writer))
             .fmt_fields(PrettyFields::new())
             .with_filter(filter_fn(move |metadata| match metadata.target() {
                 "user" => level_filter >= *metadata.level(),
@@ -370,3 +459,25 @@ impl LoggingOpts {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_otel_header, parse_sample_ratio};
+
+    #[test]
+    fn test_parse_otel_header() {
+        assert_eq!(
+            Ok(("x-api-key".to_string(), "abc=123".to_string())),
+            parse_otel_header("x-api-key=abc=123")
+        );
+        assert!(parse_otel_header("x-api-key").is_err());
+        assert!(parse_otel_header("=abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_sample_ratio() {
+        assert_eq!(Ok(0.25), parse_sample_ratio("0.25"));
+        assert!(parse_sample_ratio("1.5").is_err());
+        assert!(parse_sample_ratio("all").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::shared::prelude::colors_enabled;
+use colored::Colorize;
+
+/// A piece of inline markdown. Only the inline syntax that reads well in a terminal is
+/// understood, everything else is kept as text.
+#[derive(Debug, PartialEq)]
+enum Span<'a> {
+    Text(&'a str),
+    Bold(&'a str),
+    Italic(&'a str),
+    Code(&'a str),
+    Link { text: &'a str, url: &'a str },
+}
+
+fn parse(input: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut text_start = 0;
+    let mut idx = 0;
+
+    while idx < input.len() {
+        let rest = &input[idx..];
+        let found = if let Some(inner) = rest.strip_prefix("**") {
+            inner
+                .find("**")
+                .map(|end| (Span::Bold(&inner[..end]), end + 4))
+        } else if let Some(inner) = rest.strip_prefix('`') {
+            inner
+                .find('`')
+                .map(|end| (Span::Code(&inner[..end]), end + 2))
+        } else if (rest.starts_with('*') || rest.starts_with('_'))
+            && !input[..idx].ends_with(|c: char| c.is_alphanumeric())
+        {
+            let marker = &rest[..1];
+            rest[1..]
+                .find(marker)
+                .map(|end| &rest[1..end + 1])
+                .filter(|inner| is_emphasis(inner))
+                .map(|inner| (Span::Italic(inner), inner.len() + 2))
+        } else if let Some(inner) = rest.strip_prefix('[') {
+            parse_link(inner).map(|(span, len)| (span, len + 1))
+        } else {
+            None
+        };
+
+        match found {
+            Some((span, len)) => {
+                if text_start < idx {
+                    spans.push(Span::Text(&input[text_start..idx]));
+                }
+                spans.push(span);
+                idx += len;
+                text_start = idx;
+            }
+            None => idx += rest.chars().next().map(char::len_utf8).unwrap_or(1),
+        }
+    }
+
+    if text_start < input.len() {
+        spans.push(Span::Text(&input[text_start..]));
+    }
+    spans
+}
+
+/// Emphasis can't be empty, or start or end with whitespace, so `2 * 3 * 4` stays as text.
+fn is_emphasis(inner: &str) -> bool {
+    !inner.is_empty() && inner.trim() == inner
+}
+
+/// Parse `text](url)`, the part of a link after the opening bracket.
+fn parse_link(input: &str) -> Option<(Span<'_>, usize)> {
+    let text_end = input.find("](")?;
+    let url_start = text_end + 2;
+    let url_end = url_start + input[url_start..].find(')')?;
+    let span = Span::Link {
+        text: &input[..text_end],
+        url: &input[url_start..url_end],
+    };
+    Some((span, url_end + 1))
+}
+
+/// Render inline markdown (`**bold**`, `*italic*`, `` `code` `` and `[links](url)`) for the
+/// terminal. Links are clickable with OSC 8 when colors are enabled, otherwise the url is
+/// written after the text.
+pub fn render_markdown(input: &str) -> String {
+    let styled = colors_enabled();
+    parse(input)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(text) => text.to_string(),
+            Span::Bold(text) => text.bold().to_string(),
+            Span::Italic(text) => text.italic().to_string(),
+            Span::Code(text) => text.cyan().to_string(),
+            Span::Link { text, url } if styled => {
+                format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text.underline())
+            }
+            Span::Link { text, url } => format!("{} ({})", text, url),
+        })
+        .collect()
+}
+
+/// Inline markdown as plain text, without any markup. Links keep their url after the text.
+pub fn markdown_to_plain(input: &str) -> String {
+    parse(input)
+        .into_iter()
+        .map(|span| match span {
+            Span::Text(text) | Span::Bold(text) | Span::Italic(text) | Span::Code(text) => {
+                text.to_string()
+            }
+            Span::Link { text, url } => format!("{} ({})", text, url),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inline_markdown() {
+        assert_eq!(
+            vec![
+                Span::Text("Run "),
+                Span::Code("npm ci"),
+                Span::Text(", it's "),
+                Span::Bold("required"),
+                Span::Text(". See "),
+                Span::Link {
+                    text: "the docs",
+                    url: "https://example.com"
+                },
+                Span::Text(" or ask "),
+                Span::Italic("someone"),
+            ],
+            parse("Run `npm ci`, it's **required**. See [the docs](https://example.com) or ask _someone_")
+        );
+    }
+
+    #[test]
+    fn test_unmatched_markers_are_text() {
+        assert_eq!(
+            vec![Span::Text("5 * 3 * 2 [x] snake_case_name** `")],
+            parse("5 * 3 * 2 [x] snake_case_name** `")
+        );
+    }
+
+    #[test]
+    fn test_markdown_to_plain() {
+        assert_eq!(
+            "Install node, see docs (https://example.com) ✓",
+            markdown_to_plain("Install **node**, see [docs](https://example.com) ✓")
+        );
+    }
+}
@@ -1,79 +1,93 @@
-use colored::Colorize;
-
-use crate::models::HelpMetadata;
-use crate::report_stdout;
-use std::cmp::max;
-use std::path::Path;
-
+mod artifacts;
 mod capture;
+mod color;
+mod config_include;
 mod config_load;
+mod config_merge;
+mod console;
+#[cfg(feature = "cli")]
+mod default_args;
+mod details;
+mod fingerprint;
+mod interaction;
+#[cfg(feature = "cli")]
 mod logging;
+mod markdown;
 // mod models_bck;
 mod models;
+mod progress;
+mod progress_bar;
+#[cfg(unix)]
+mod pty;
+mod rate_limit;
 mod redact;
 mod report;
+mod report_bundle;
+mod spool;
+mod stats;
+mod templates;
+
+pub use details::print_details;
 
 pub const CONFIG_FILE_PATH_ENV: &str = "SCOPE_CONFIG_JSON";
 pub const RUN_ID_ENV_VAR: &str = "SCOPE_RUN_ID";
 
 pub mod prelude {
+    pub use super::artifacts::{RunArtifacts, ARTIFACT_DIR_ENV};
     pub use super::capture::{
-        CaptureError, CaptureOpts, DefaultExecutionProvider, ExecutionProvider,
-        MockExecutionProvider, OutputCapture, OutputCaptureBuilder, OutputDestination,
+        CaptureError, CaptureOpts, DefaultExecutionProvider, DockerExecutionProvider, DockerTarget,
+        ExecutionProvider, MockExecutionProvider, OutputCapture, OutputCaptureBuilder,
+        OutputDestination, ScriptedExecutionProvider, ScriptedOutput, ScriptedOutputBuilder,
+        SshExecutionProvider, SshTarget,
+    };
+    pub use super::color::{
+        colors_enabled, configure_colors, ColorAwareMakeWriter, ColorAwareWriter, ColorChoice,
+    };
+    pub use super::config_include::{
+        included_paths, resolve_includes, IncludeError, INCLUDED_FILES_ANNOTATION, INCLUDE_TAG,
+    };
+    pub use super::config_load::{
+        build_config_path, ConfigOptions, ConfigValidationError, FoundConfig, UnsupportedResource,
+    };
+    pub use super::config_merge::{
+        merge_layers, FIELD_SOURCE_ANNOTATION_PREFIX, MERGED_FROM_ANNOTATION,
     };
-    pub use super::config_load::{build_config_path, ConfigOptions, FoundConfig};
-    pub use super::logging::{progress_bar_without_pos, LoggingOpts, STDERR_WRITER, STDOUT_WRITER};
+    pub use super::console::{
+        detach_child_stdin, redirect_stdout_to_stderr, STDERR_WRITER, STDOUT_WRITER,
+    };
+    #[cfg(feature = "cli")]
+    pub use super::default_args::apply_default_args;
+    pub use super::fingerprint::RunFingerprint;
+    #[cfg(feature = "interactive")]
+    pub use super::interaction::InquireInteraction;
+    pub use super::interaction::{
+        AutoApprove, DefaultInteraction, MockUserInteraction, NoInteraction, PromptAnswer,
+        PromptDecision, PromptPolicyInteraction, UserInteraction,
+    };
+    #[cfg(feature = "cli")]
+    pub use super::logging::LoggingOpts;
+    pub use super::markdown::{markdown_to_plain, render_markdown};
     pub use super::models::prelude::*;
     pub use super::print_details;
+    #[cfg(test)]
+    pub(crate) use super::progress::RecordingProgressReporter;
+    pub use super::progress::{NoOpProgressReporter, ProgressReporter, ProgressStatus};
+    pub use super::progress_bar::progress_bar_without_pos;
+    pub(crate) use super::progress_bar::IndicatifSpanExt;
+    pub use super::redact::configure_redactions;
     pub use super::report::{
-        ActionReport, ActionReportBuilder, ActionTaskReport, ActionTaskReportBuilder,
-        DefaultGroupedReportBuilder, DefaultUnstructuredReportBuilder, GroupReport,
-        GroupedReportBuilder, Report, ReportRenderer, UnstructuredReportBuilder,
+        render_report, ActionReport, ActionReportBuilder, ActionTaskReport,
+        ActionTaskReportBuilder, DefaultGroupedReportBuilder, DefaultUnstructuredReportBuilder,
+        FixArtifact, FixChange, GroupReport, GroupedReportBuilder, Report, ReportRenderer,
+        ReportSource, UnstructuredReportBuilder,
     };
+    pub use super::report_bundle::ReportBundle;
+    pub use super::spool::{CapturedLines, SpooledReader, CAPTURE_SPOOL_BYTES_ENV};
+    pub use super::stats::{FixCounts, KnownErrorCounts, KnownErrorStats, STATS_FILE_ENV};
+    pub(crate) use super::templates::quote_sh;
     pub use super::{CONFIG_FILE_PATH_ENV, RUN_ID_ENV_VAR};
 }
 
 pub(crate) fn convert_to_string(input: Vec<&str>) -> Vec<String> {
     input.iter().map(|x| x.to_string()).collect()
 }
-
-pub async fn print_details<T>(working_dir: &Path, config: &Vec<T>)
-where
-    T: HelpMetadata,
-{
-    let max_name_length = config
-        .iter()
-        .map(|x| x.full_name().len())
-        .max()
-        .unwrap_or(20);
-    let max_name_length = max(max_name_length, 20) + 2;
-
-    report_stdout!(
-        "  {:max_name_length$}{:60}{}",
-        "Name".white().bold(),
-        "Description".white().bold(),
-        "Path".white().bold()
-    );
-    for resource in config {
-        let mut description = resource.description().to_string();
-        if description.len() > 55 {
-            description.truncate(55);
-            description = format!("{}...", description);
-        }
-
-        let mut loc = resource.metadata().file_path();
-        let diff_path = pathdiff::diff_paths(&loc, working_dir);
-        if let Some(diff) = diff_path {
-            loc = diff.display().to_string();
-        } else if loc.len() > 35 {
-            loc = format!("...{}", loc.split_off(loc.len() - 35));
-        }
-
-        report_stdout!(
-            "- {:max_name_length$}{:60}{}",
-            resource.full_name(),
-            description,
-            loc
-        );
-    }
-}
@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+use crate::models::prelude::{DoctorGroupActionSpec, ModelMetadata, V1AlphaActionLibrary};
+use crate::models::HelpMetadata;
+use crate::shared::models::internal::extract_command_path;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ActionLibrary {
+    pub full_name: String,
+    pub metadata: ModelMetadata,
+    pub parameters: BTreeMap<String, String>,
+    /// Actions by name. Relative commands and `workingDir` are already resolved against the
+    /// library's directory, and check paths carry the directory they're relative to.
+    pub actions: BTreeMap<String, DoctorGroupActionSpec>,
+}
+
+impl HelpMetadata for ActionLibrary {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl ActionLibrary {
+    /// Build the action a group gets for `action`, which references this library. Fields set on
+    /// `action` win over the library's, `with` is layered over the library's `parameters`, and
+    /// `env` over the library action's `env`.
+    fn resolve(
+        &self,
+        action_name: &str,
+        action: &DoctorGroupActionSpec,
+    ) -> Result<DoctorGroupActionSpec> {
+        let library_action = self
+            .actions
+            .get(action_name)
+            .ok_or_else(|| anyhow!("{} has no action named `{}`", self.full_name(), action_name))?;
+
+        let mut params = self.parameters.clone();
+        params.extend(action.with.clone());
+        let mut env = library_action.env.clone();
+        env.extend(action.env.clone());
+        let mut labels = library_action.labels.clone();
+        labels.extend(action.labels.clone());
+
+        let has_check = action.check.paths.is_some()
+            || action.check.commands.is_some()
+            || action.check.brew.is_some()
+            || action.check.tool_version.is_some();
+        Ok(DoctorGroupActionSpec {
+            name: action.name.clone().or_else(|| library_action.name.clone()),
+            description: action
+                .description
+                .clone()
+                .or_else(|| library_action.description.clone()),
+            check: if has_check {
+                action.check.clone()
+            } else {
+                library_action.check.clone()
+            },
+            fix: action.fix.clone().or_else(|| library_action.fix.clone()),
+            required: action.required,
+            allow_failure: action.allow_failure,
+            run_always: action.run_always || library_action.run_always,
+            timeout_seconds: action.timeout_seconds.or(library_action.timeout_seconds),
+            use_action: None,
+            with: params,
+            runtime: action
+                .runtime
+                .clone()
+                .or_else(|| library_action.runtime.clone()),
+            env,
+            platforms: if action.platforms.is_empty() {
+                library_action.platforms.clone()
+            } else {
+                action.platforms.clone()
+            },
+            only_if: action
+                .only_if
+                .clone()
+                .or_else(|| library_action.only_if.clone()),
+            labels,
+            working_dir: action
+                .working_dir
+                .clone()
+                .or_else(|| library_action.working_dir.clone()),
+        })
+    }
+}
+
+/// Replace every action in `actions` that has `useAction` with the library action it names.
+pub fn use_library_actions(
+    actions: &mut [DoctorGroupActionSpec],
+    libraries: &BTreeMap<String, ActionLibrary>,
+) -> Result<()> {
+    for action in actions.iter_mut() {
+        let Some(use_action) = &action.use_action else {
+            continue;
+        };
+        let Some((library_name, action_name)) = use_action.split_once('/') else {
+            return Err(anyhow!(
+                "useAction `{}` should be written as library/action",
+                use_action
+            ));
+        };
+        let library = libraries.get(library_name).ok_or_else(|| {
+            anyhow!(
+                "useAction `{}` needs a ScopeActionLibrary named `{}`, but none was found",
+                use_action,
+                library_name
+            )
+        })?;
+        *action = library.resolve(action_name, action)?;
+    }
+
+    Ok(())
+}
+
+impl TryFrom<V1AlphaActionLibrary> for ActionLibrary {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaActionLibrary) -> Result<Self, Self::Error> {
+        let full_name = value.full_name();
+        let containing_dir = value.metadata.containing_dir_of("spec.actions");
+        let containing_dir = Path::new(&containing_dir);
+
+        let mut actions = BTreeMap::new();
+        for mut action in value.spec.actions {
+            let Some(name) = action.name.clone() else {
+                return Err(anyhow!("Every action in a ScopeActionLibrary needs a name"));
+            };
+            if action.use_action.is_some() {
+                return Err(anyhow!(
+                    "Action `{}` can't use `useAction` inside a ScopeActionLibrary",
+                    name
+                ));
+            }
+
+            if let Some(commands) = action.check.commands.as_mut() {
+                resolve_commands(containing_dir, commands);
+            }
+            if let Some(command) = action
+                .check
+                .tool_version
+                .as_mut()
+                .and_then(|tool_version| tool_version.command.as_mut())
+            {
+                resolve_commands(containing_dir, std::slice::from_mut(command));
+            }
+            if let Some(fix) = action.fix.as_mut() {
+                resolve_commands(containing_dir, &mut fix.commands);
+            }
+            if let Some(only_if) = action.only_if.as_mut() {
+                resolve_commands(containing_dir, std::slice::from_mut(&mut only_if.command));
+            }
+            if action.check.paths.is_some() {
+                action.check.base_path = containing_dir.parent().map(Path::to_path_buf);
+            }
+            // A template at the start, like `{{ working_dir }}`, can render an absolute path, so
+            // it's left for the group to resolve.
+            if let Some(working_dir) = action
+                .working_dir
+                .as_mut()
+                .filter(|working_dir| !working_dir.starts_with("{{"))
+            {
+                *working_dir = containing_dir.join(&working_dir).display().to_string();
+            }
+
+            if actions.insert(name.clone(), action).is_some() {
+                return Err(anyhow!("Action `{}` is defined more than once", name));
+            }
+        }
+
+        Ok(ActionLibrary {
+            full_name,
+            metadata: value.metadata,
+            parameters: value.spec.parameters,
+            actions,
+        })
+    }
+}
+
+fn resolve_commands(containing_dir: &Path, commands: &mut [String]) {
+    for command in commands.iter_mut() {
+        *command = extract_command_path(containing_dir, command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    use serde_yaml::Deserializer;
+
+    use crate::shared::config_load::parse_model;
+    use crate::shared::models::prelude::ParsedConfig;
+
+    const LIBRARY: &str = "apiVersion: scope.github.com/v1alpha
+kind: ScopeActionLibrary
+metadata:
+  name: library
+spec:
+  parameters:
+    version: \"20\"
+  actions:
+    - name: install-node
+      description: Node is installed
+      check:
+        commands:
+          - node --version | grep -q v{{ params.version }}
+      fix:
+        commands:
+          - ./install-node.sh {{ params.version }}";
+
+    fn parse(text: &str, libraries: &BTreeMap<String, super::ActionLibrary>) -> ParsedConfig {
+        parse_file(text, "/foo/lib/file.yaml", libraries)
+    }
+
+    fn parse_file(
+        text: &str,
+        file_path: &str,
+        libraries: &BTreeMap<String, super::ActionLibrary>,
+    ) -> ParsedConfig {
+        let doc = Deserializer::from_str(text).next().unwrap();
+        let model = parse_model(doc, Path::new("/foo/bar"), Path::new(file_path)).unwrap();
+        ParsedConfig::from_model(model, libraries).unwrap()
+    }
+
+    fn libraries() -> BTreeMap<String, super::ActionLibrary> {
+        let ParsedConfig::ActionLibrary(library) = parse(LIBRARY, &BTreeMap::new()) else {
+            panic!("expected a library");
+        };
+        BTreeMap::from([("library".to_string(), library)])
+    }
+
+    #[test]
+    fn test_group_uses_library_action_with_parameters() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  actions:
+    - useAction: library/install-node
+      required: false
+      with:
+        version: \"18\"";
+
+        let group = parse(text, &libraries()).get_doctor_group().unwrap();
+        let action = &group.actions[0];
+
+        assert_eq!("install-node", action.name);
+        assert_eq!("Node is installed", action.description);
+        assert!(!action.required);
+        assert_eq!(
+            vec!["node --version | grep -q v18".to_string()],
+            action.check.command.as_ref().unwrap().commands
+        );
+        assert_eq!(
+            vec!["/foo/lib/install-node.sh 18".to_string()],
+            action.fix.command.as_ref().unwrap().commands
+        );
+    }
+
+    #[test]
+    fn test_group_overrides_library_fields() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  actions:
+    - useAction: library/install-node
+      name: node-20
+      description: Node 20 is installed
+      fix:
+        helpText: Ask in the help-node channel";
+
+        let group = parse(text, &libraries()).get_doctor_group().unwrap();
+        let action = &group.actions[0];
+
+        assert_eq!("node-20", action.name);
+        assert_eq!("Node 20 is installed", action.description);
+        assert_eq!(
+            vec!["node --version | grep -q v20".to_string()],
+            action.check.command.as_ref().unwrap().commands
+        );
+        assert_eq!(
+            Some("Ask in the help-node channel".to_string()),
+            action.fix.help_text
+        );
+        assert!(action.fix.command.as_ref().unwrap().commands.is_empty());
+    }
+
+    #[test]
+    fn test_library_paths_are_relative_to_the_library() {
+        let library = "apiVersion: scope.github.com/v1alpha
+kind: ScopeActionLibrary
+metadata:
+  name: library
+spec:
+  actions:
+    - name: lockfile
+      workingDir: packages/api
+      check:
+        paths:
+          - package-lock.json";
+        let ParsedConfig::ActionLibrary(library) =
+            parse_file(library, "/shared/.scope/library.yaml", &BTreeMap::new())
+        else {
+            panic!("expected a library");
+        };
+        let libraries = BTreeMap::from([("library".to_string(), library)]);
+
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  actions:
+    - useAction: library/lockfile
+    - name: own
+      check:
+        paths:
+          - package-lock.json";
+        let group = parse_file(text, "/repo/.scope/group.yaml", &libraries)
+            .get_doctor_group()
+            .unwrap();
+
+        let files = group.actions[0].check.files.as_ref().unwrap();
+        assert_eq!(Path::new("/shared"), files.base_path);
+        assert_eq!(
+            Some(Path::new("/shared/.scope/packages/api")),
+            group.actions[0].working_dir.as_deref()
+        );
+        let files = group.actions[1].check.files.as_ref().unwrap();
+        assert_eq!(Path::new("/repo"), files.base_path);
+    }
+
+    #[test]
+    fn test_unknown_library_action_is_an_error() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  actions:
+    - useAction: library/install-ruby";
+
+        let doc = Deserializer::from_str(text).next().unwrap();
+        let model =
+            parse_model(doc, Path::new("/foo/bar"), Path::new("/foo/lib/file.yaml")).unwrap();
+        let err = ParsedConfig::from_model(model, &libraries()).unwrap_err();
+
+        assert_eq!(
+            "ScopeActionLibrary/library has no action named `install-ruby`",
+            err.to_string()
+        );
+    }
+}
@@ -1,14 +1,22 @@
-use std::collections::BTreeMap;
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::RangeInclusive;
+use std::path::{Component, Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local, Timelike, Utc};
 use derive_builder::Builder;
 use minijinja::{context, Environment};
+use path_clean::PathClean;
 
 use crate::models::prelude::{ModelMetadata, V1AlphaDoctorGroup};
 use crate::models::HelpMetadata;
-use crate::prelude::{DoctorGroupActionSpec, DoctorInclude};
-use crate::shared::models::internal::extract_command_path;
+use crate::prelude::{
+    DoctorExitCodeSpec, DoctorExitCodesSpec, DoctorGroupActionSpec, DoctorGroupScheduleSpec,
+    DoctorInclude, DoctorOnlyIfSpec, DoctorPlatform,
+};
+use crate::shared::models::internal::{extract_command_path, resolve_cache_dir_template};
+use crate::shared::prelude::SshTarget;
+use crate::shared::templates::{add_template_filters, DeferredCaptures};
 
 #[derive(Debug, PartialEq, Clone, Builder)]
 #[builder(setter(into))]
@@ -18,6 +26,54 @@ pub struct DoctorGroupAction {
     pub fix: DoctorGroupActionFix,
     pub check: DoctorGroupActionCheck,
     pub required: bool,
+    /// A failure is recorded but doesn't fail the group.
+    #[builder(default)]
+    pub allow_failure: bool,
+    /// A setup task, its fix runs every time without a check or the cache.
+    #[builder(default)]
+    pub run_always: bool,
+    /// How long each check and fix command can run before it's stopped.
+    #[builder(default)]
+    pub timeout: Option<std::time::Duration>,
+    /// Image of the container the check and fix commands run in, from the action's or the
+    /// group's `runtime`.
+    #[builder(default)]
+    pub container_image: Option<String>,
+    /// Environment variables for the check, fix and rollback commands, from the group's `env`
+    /// with the action's on top.
+    #[builder(default)]
+    pub env: BTreeMap<String, String>,
+    /// When the action applies to this machine.
+    #[builder(default)]
+    pub condition: DoctorCondition,
+    /// Labels of the action, from the group's `metadata.labels` with the action's on top.
+    #[builder(default)]
+    pub labels: BTreeMap<String, String>,
+    /// Directory the check, fix and rollback commands run in, when it isn't the directory scope
+    /// runs in. Always absolute.
+    #[builder(default)]
+    pub working_dir: Option<PathBuf>,
+}
+
+/// Where an action or a group applies, from its `platforms` and `onlyIf`. When it doesn't
+/// apply it's skipped as not applicable, instead of failing.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DoctorCondition {
+    pub platforms: Vec<DoctorPlatform>,
+    /// Command that exits 0 when it applies, already rendered.
+    pub only_if: Option<String>,
+}
+
+impl DoctorCondition {
+    /// Whether `platforms` includes `os`, named like `std::env::consts::OS`. No `platforms`
+    /// includes every platform.
+    pub fn includes_platform(&self, os: &str) -> bool {
+        self.platforms.is_empty()
+            || self
+                .platforms
+                .iter()
+                .any(|platform| platform.to_string() == os)
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Builder)]
@@ -25,10 +81,36 @@ pub struct DoctorGroupAction {
 pub struct DoctorGroupActionFix {
     #[builder(default)]
     pub command: Option<DoctorGroupActionCommand>,
+    /// Commands that undo a fix, run when one of the fix's commands fails.
+    #[builder(default)]
+    pub rollback: Option<DoctorGroupActionCommand>,
     #[builder(default)]
     pub help_text: Option<String>,
     #[builder(default)]
     pub help_url: Option<String>,
+    #[builder(default)]
+    pub sandbox_paths: Vec<String>,
+    #[builder(default)]
+    pub snapshot: Option<DoctorGroupActionSnapshot>,
+    #[builder(default)]
+    pub prompt: Option<DoctorGroupActionFixPrompt>,
+    /// Paths the fix should create, already rendered. Relative paths are relative to the
+    /// directory the fix runs in.
+    #[builder(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct DoctorGroupActionFixPrompt {
+    pub text: String,
+    pub extra_context: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DoctorGroupActionSnapshot {
+    pub env: Vec<String>,
+    pub commands: Vec<String>,
+    pub files: Vec<String>,
 }
 
 impl DoctorGroupAction {
@@ -41,19 +123,31 @@ impl DoctorGroupAction {
     ) -> Self {
         Self {
             required: true,
+            allow_failure: false,
+            run_always: false,
+            timeout: None,
+            container_image: None,
+            env: BTreeMap::new(),
+            condition: DoctorCondition::default(),
+            labels: BTreeMap::new(),
+            working_dir: None,
             name: name.to_string(),
             description: description.to_string(),
             fix: DoctorGroupActionFix {
                 command: fix_command.map(DoctorGroupActionCommand::from),
+                rollback: None,
                 help_text: None,
                 help_url: None,
+                sandbox_paths: Vec::new(),
+                snapshot: None,
+                prompt: None,
+                artifacts: Vec::new(),
             },
             check: DoctorGroupActionCheck {
                 command: check_command.map(DoctorGroupActionCommand::from),
-                files: check_path.map(|(base, paths)| DoctorGroupCachePath {
-                    base_path: PathBuf::from(base),
-                    paths: crate::shared::convert_to_string(paths),
-                }),
+                files: check_path.map(DoctorGroupCachePath::from),
+                exit_codes: None,
+                builtin: Vec::new(),
             },
         }
     }
@@ -64,6 +158,13 @@ impl DoctorGroupAction {
 pub struct DoctorGroupCachePath {
     pub paths: Vec<String>,
     pub base_path: PathBuf,
+    /// `.gitignore` style patterns, relative to `base_path`, for files to leave out of the cache.
+    #[builder(default)]
+    pub ignore_paths: Vec<String>,
+    /// Skip files ignored by `.gitignore` and friends while walking for `paths`. Defaults to
+    /// `true`, like `respectGitignore`.
+    #[builder(default = "true")]
+    pub respect_gitignore: bool,
 }
 
 impl From<(&str, Vec<&str>)> for DoctorGroupCachePath {
@@ -74,6 +175,8 @@ impl From<(&str, Vec<&str>)> for DoctorGroupCachePath {
         Self {
             paths,
             base_path: pb,
+            ignore_paths: Vec::new(),
+            respect_gitignore: true,
         }
     }
 }
@@ -83,6 +186,108 @@ impl From<(&str, Vec<&str>)> for DoctorGroupCachePath {
 pub struct DoctorGroupActionCheck {
     pub command: Option<DoctorGroupActionCommand>,
     pub files: Option<DoctorGroupCachePath>,
+    /// What the exit codes of `command` mean, when the check sets `exitCodes`.
+    #[builder(default)]
+    pub exit_codes: Option<DoctorExitCodes>,
+    /// Checks scope runs itself, from `brew` and `toolVersion`.
+    #[builder(default)]
+    pub builtin: Vec<BuiltinCheck>,
+}
+
+/// A check that scope knows how to run, instead of a check command from the config.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BuiltinCheck {
+    /// The Homebrew formula is installed.
+    BrewFormula(String),
+    /// The version `command` prints matches `constraint`.
+    ToolVersion {
+        tool: String,
+        command: String,
+        constraint: semver::VersionReq,
+    },
+}
+
+impl BuiltinCheck {
+    /// Command that's run for the check.
+    pub fn command(&self) -> String {
+        match self {
+            BuiltinCheck::BrewFormula(formula) => format!("brew list --versions {}", formula),
+            BuiltinCheck::ToolVersion { command, .. } => command.clone(),
+        }
+    }
+
+    /// Command that fixes a failed check, when scope knows one.
+    pub fn default_fix(&self) -> Option<String> {
+        match self {
+            BuiltinCheck::BrewFormula(formula) => Some(format!("brew install {}", formula)),
+            BuiltinCheck::ToolVersion { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for BuiltinCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuiltinCheck::BrewFormula(formula) => {
+                write!(f, "brew formula `{}` is installed", formula)
+            }
+            BuiltinCheck::ToolVersion {
+                tool, constraint, ..
+            } => write!(f, "`{}` version matches `{}`", tool, constraint),
+        }
+    }
+}
+
+/// What an exit code of a check command means.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ExitCodeMeaning {
+    /// The check passed.
+    Success,
+    /// The action doesn't apply, so it's skipped.
+    Skip,
+    /// The check failed and the fix can't help, so the group stops.
+    Stop,
+    /// The check failed and the fix should run.
+    Failure,
+}
+
+/// Exit codes of check commands by what they mean, from the check's `exitCodes`. Each list holds
+/// inclusive ranges, and a code is looked for in `success`, `skip` and `stop`, in that order.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DoctorExitCodes {
+    pub success: Vec<RangeInclusive<i32>>,
+    pub skip: Vec<RangeInclusive<i32>>,
+    pub stop: Vec<RangeInclusive<i32>>,
+}
+
+impl Default for DoctorExitCodes {
+    fn default() -> Self {
+        Self {
+            success: vec![0..=0],
+            skip: Vec::new(),
+            stop: Vec::new(),
+        }
+    }
+}
+
+impl DoctorExitCodes {
+    /// What `exit_code` means. A command without an exit code was stopped by a signal, which is
+    /// a failure.
+    pub fn meaning(&self, exit_code: Option<i32>) -> ExitCodeMeaning {
+        let Some(code) = exit_code else {
+            return ExitCodeMeaning::Failure;
+        };
+        let contains = |ranges: &[RangeInclusive<i32>]| ranges.iter().any(|r| r.contains(&code));
+        if contains(&self.success) {
+            ExitCodeMeaning::Success
+        } else if contains(&self.skip) {
+            ExitCodeMeaning::Skip
+        } else if contains(&self.stop) {
+            ExitCodeMeaning::Stop
+        } else {
+            ExitCodeMeaning::Failure
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Builder)]
@@ -124,6 +329,134 @@ pub struct DoctorGroup {
     pub run_by_default: bool,
     pub actions: Vec<DoctorGroupAction>,
     pub extra_report_args: BTreeMap<String, String>,
+    #[builder(default)]
+    pub schedule: Option<DoctorGroupSchedule>,
+    #[builder(default)]
+    pub target: Option<SshTarget>,
+    /// Where this group's file cache is kept, when it isn't the shared cache dir.
+    #[builder(default)]
+    pub cache_dir: Option<PathBuf>,
+    /// When the group applies to this machine.
+    #[builder(default)]
+    pub condition: DoctorCondition,
+    /// Executables listed in `requiredTools`.
+    #[builder(default)]
+    pub tools: Vec<String>,
+}
+
+/// Constraints on when a group is allowed to run, see [`DoctorGroupScheduleSpec`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct DoctorGroupSchedule {
+    pub interval: Option<Duration>,
+    pub hours: Option<(u32, u32)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScheduleStatus {
+    Due,
+    OutsideHours { start: u32, end: u32 },
+    IntervalNotElapsed { next_run: DateTime<Utc> },
+}
+
+impl DoctorGroupSchedule {
+    pub fn status(
+        &self,
+        now: DateTime<Local>,
+        last_success: Option<DateTime<Utc>>,
+    ) -> ScheduleStatus {
+        if let Some((start, end)) = self.hours {
+            let hour = now.hour();
+            let in_window = if start <= end {
+                start <= hour && hour < end
+            } else {
+                hour >= start || hour < end
+            };
+            if !in_window {
+                return ScheduleStatus::OutsideHours { start, end };
+            }
+        }
+
+        if let (Some(interval), Some(last_success)) = (self.interval, last_success) {
+            let next_run = last_success + interval;
+            if now.with_timezone(&Utc) < next_run {
+                return ScheduleStatus::IntervalNotElapsed { next_run };
+            }
+        }
+
+        ScheduleStatus::Due
+    }
+}
+
+impl TryFrom<DoctorGroupScheduleSpec> for DoctorGroupSchedule {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: DoctorGroupScheduleSpec) -> Result<Self, Self::Error> {
+        let interval = match spec.interval {
+            Some(interval) => Some(parse_interval(&interval)?),
+            None => None,
+        };
+
+        let hours = match spec.hours {
+            Some(hours) if hours.start > 23 || hours.end > 24 => {
+                return Err(anyhow!(
+                    "schedule hours must be between 0 and 24, got {}-{}",
+                    hours.start,
+                    hours.end
+                ));
+            }
+            Some(hours) => Some((hours.start, hours.end)),
+            None => None,
+        };
+
+        Ok(Self { interval, hours })
+    }
+}
+
+/// Parse a duration like `30m`, `12h`, `1d`, or `2w`.
+fn parse_interval(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| anyhow!("schedule interval `{}` must start with a number", input))?;
+
+    match unit.trim() {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        "w" => Ok(Duration::weeks(amount)),
+        other => Err(anyhow!(
+            "schedule interval `{}` has unknown unit `{}`, expected one of s, m, h, d, w",
+            input,
+            other
+        )),
+    }
+}
+
+impl DoctorGroup {
+    /// Executables that need to be installed before the group runs: the group's `requiredTools`,
+    /// and `docker` when any action runs in a container. Actions that run in a container only
+    /// need `docker`, so `requiredTools` don't count when every action does.
+    pub fn required_tools(&self) -> BTreeSet<String> {
+        let in_container = self
+            .actions
+            .iter()
+            .filter(|action| action.container_image.is_some())
+            .count();
+        let docker = (in_container > 0).then(|| "docker".to_string());
+        let all_in_container = in_container > 0 && in_container == self.actions.len();
+
+        self.tools
+            .iter()
+            .filter(|_| !all_in_container)
+            .cloned()
+            .chain(docker)
+            .collect()
+    }
 }
 
 impl HelpMetadata for DoctorGroup {
@@ -136,11 +469,75 @@ impl HelpMetadata for DoctorGroup {
     }
 }
 
-fn substitute_templates(work_dir: &str, input_str: &str) -> Result<String> {
+/// Values available to every template in a group, along with each action's `params`.
+struct GroupTemplateValues {
+    working_dir: String,
+    vars: BTreeMap<String, String>,
+    env: BTreeMap<String, String>,
+}
+
+impl GroupTemplateValues {
+    /// Render the group's `vars`, which can use `{{ working_dir }}` and `{{ env.<NAME> }}`.
+    fn new(working_dir: &str, vars: &BTreeMap<String, String>) -> Result<Self> {
+        let env: BTreeMap<String, String> = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+            .collect();
+
+        let mut template_env = Environment::new();
+        add_template_filters(&mut template_env);
+        let mut rendered = BTreeMap::new();
+        for (name, value) in vars {
+            let value = template_env
+                .render_str(value, context! { working_dir => working_dir, env => &env })
+                .map_err(|e| anyhow!("unable to render var `{}`: {}", name, e))?;
+            rendered.insert(name.clone(), value);
+        }
+
+        Ok(Self {
+            working_dir: working_dir.to_string(),
+            vars: rendered,
+            env,
+        })
+    }
+}
+
+fn parse_condition(
+    containing_dir: &Path,
+    values: &GroupTemplateValues,
+    params: &BTreeMap<String, String>,
+    platforms: &[DoctorPlatform],
+    only_if: Option<&DoctorOnlyIfSpec>,
+) -> Result<DoctorCondition> {
+    let only_if = match only_if {
+        Some(only_if) => {
+            let command = substitute_templates(values, params, &only_if.command)?;
+            Some(extract_command_path(containing_dir, &command))
+        }
+        None => None,
+    };
+
+    Ok(DoctorCondition {
+        platforms: platforms.to_vec(),
+        only_if,
+    })
+}
+
+fn substitute_templates(
+    values: &GroupTemplateValues,
+    params: &BTreeMap<String, String>,
+    input_str: &str,
+) -> Result<String> {
     let mut env = Environment::new();
+    add_template_filters(&mut env);
     env.add_template("input_str", input_str)?;
     let template = env.get_template("input_str")?;
-    let result = template.render(context! { working_dir => work_dir })?;
+    let result = template.render(context! {
+        working_dir => values.working_dir,
+        vars => values.vars,
+        env => values.env,
+        params => params,
+        captures => minijinja::Value::from_object(DeferredCaptures),
+    })?;
 
     Ok(result)
 }
@@ -149,10 +546,47 @@ impl TryFrom<V1AlphaDoctorGroup> for DoctorGroup {
     type Error = anyhow::Error;
 
     fn try_from(model: V1AlphaDoctorGroup) -> Result<Self, Self::Error> {
+        let values = GroupTemplateValues::new(
+            model
+                .metadata
+                .annotations
+                .working_dir
+                .as_deref()
+                .unwrap_or_default(),
+            &model.spec.vars,
+        )?;
         let mut actions: Vec<_> = Default::default();
         for (count, spec_action) in model.spec.actions.iter().enumerate() {
-            actions.push(parse_action(count, &model, spec_action)?);
+            actions.push(parse_action(count, &model, &values, spec_action)?);
         }
+        if model.spec.target.is_some() && actions.iter().any(|a| a.container_image.is_some()) {
+            return Err(anyhow!(
+                "{} can't run commands on a `target` and in a container `runtime`",
+                model.full_name()
+            ));
+        }
+
+        let condition = parse_condition(
+            Path::new(&model.metadata.containing_dir_of("spec.onlyIf")),
+            &values,
+            &BTreeMap::new(),
+            &model.spec.platforms,
+            model.spec.only_if.as_ref(),
+        )?;
+
+        let cache_dir = match &model.spec.cache_dir {
+            Some(cache_dir) => Some(resolve_cache_dir_template(
+                Path::new(&model.metadata.containing_dir_of("spec.cacheDir")),
+                model
+                    .metadata
+                    .annotations
+                    .working_dir
+                    .as_deref()
+                    .unwrap_or_default(),
+                cache_dir,
+            )?),
+            None => None,
+        };
 
         Ok(DoctorGroup {
             full_name: model.full_name(),
@@ -161,6 +595,20 @@ impl TryFrom<V1AlphaDoctorGroup> for DoctorGroup {
             requires: model.spec.needs,
             run_by_default: model.spec.include == DoctorInclude::ByDefault,
             extra_report_args: model.spec.report_extra_details,
+            schedule: model
+                .spec
+                .schedule
+                .map(DoctorGroupSchedule::try_from)
+                .transpose()?,
+            target: model
+                .spec
+                .target
+                .as_deref()
+                .map(SshTarget::parse)
+                .transpose()?,
+            cache_dir,
+            condition,
+            tools: model.spec.required_tools,
         })
     }
 }
@@ -168,28 +616,47 @@ impl TryFrom<V1AlphaDoctorGroup> for DoctorGroup {
 fn parse_action(
     idx: usize,
     group_model: &V1AlphaDoctorGroup,
+    values: &GroupTemplateValues,
     action: &DoctorGroupActionSpec,
 ) -> Result<DoctorGroupAction> {
-    let binding = group_model.containing_dir();
+    let binding = group_model.metadata.containing_dir_of("spec.actions");
     let containing_dir = Path::new(&binding);
-    let working_dir = group_model
-        .metadata
-        .annotations
-        .working_dir
-        .as_ref()
-        .unwrap()
-        .clone();
 
     let spec_action = action.clone();
+    if let Some(use_action) = &spec_action.use_action {
+        return Err(anyhow!(
+            "useAction `{}` needs a ScopeActionLibrary, but none was loaded",
+            use_action
+        ));
+    }
+    if spec_action.run_always {
+        if spec_action.check.paths.is_some()
+            || spec_action.check.commands.is_some()
+            || spec_action.check.brew.is_some()
+            || spec_action.check.tool_version.is_some()
+        {
+            return Err(anyhow!("runAlways actions can't have a check"));
+        }
+        if spec_action
+            .fix
+            .as_ref()
+            .map(|fix| fix.commands.is_empty())
+            .unwrap_or(true)
+        {
+            return Err(anyhow!("runAlways actions need fix commands to run"));
+        }
+    }
+    let builtin = parse_builtin_checks(containing_dir, values, &spec_action)?;
     let help_text = spec_action
         .fix
         .as_ref()
-        .and_then(|x| x.help_text.as_ref().map(|st| st.trim().to_string()).clone());
+        .and_then(|x| x.help_text.as_ref().map(|st| st.trim().to_string()).clone())
+        .or_else(|| builtin.iter().find_map(builtin_help_text));
     let help_url = spec_action.fix.as_ref().and_then(|x| x.help_url.clone());
     let fix_command = if let Some(fix) = &spec_action.fix {
         let mut templated_commands = Vec::new();
         for command in &fix.commands {
-            templated_commands.push(substitute_templates(&working_dir, command)?);
+            templated_commands.push(substitute_templates(values, &spec_action.with, command)?);
         }
         Some(DoctorGroupActionCommand::from((
             containing_dir,
@@ -198,11 +665,112 @@ fn parse_action(
     } else {
         None
     };
+    let fix_command = match fix_command {
+        Some(command) if !command.commands.is_empty() => Some(command),
+        fix_command => {
+            let derived: Vec<_> = builtin
+                .iter()
+                .filter_map(BuiltinCheck::default_fix)
+                .collect();
+            if derived.is_empty() {
+                fix_command
+            } else {
+                Some(DoctorGroupActionCommand::from((containing_dir, derived)))
+            }
+        }
+    };
+    let rollback_command = match &spec_action.fix {
+        Some(fix) if !fix.rollback.is_empty() => {
+            let mut templated_commands = Vec::new();
+            for command in &fix.rollback {
+                templated_commands.push(substitute_templates(values, &spec_action.with, command)?);
+            }
+            Some(DoctorGroupActionCommand::from((
+                containing_dir,
+                templated_commands,
+            )))
+        }
+        _ => None,
+    };
+
+    let mut sandbox_paths = Vec::new();
+    if let Some(fix) = &spec_action.fix {
+        for path in &fix.sandbox_paths {
+            let path = substitute_templates(values, &spec_action.with, path)?;
+            let relative = Path::new(&path);
+            if relative.is_absolute()
+                || relative
+                    .components()
+                    .any(|c| matches!(c, Component::ParentDir))
+            {
+                return Err(anyhow!(
+                    "sandboxPaths must be relative to the working directory, got {}",
+                    path
+                ));
+            }
+            sandbox_paths.push(path);
+        }
+    }
+
+    let snapshot = match spec_action.fix.as_ref().and_then(|f| f.snapshot.as_ref()) {
+        None => None,
+        Some(spec) => {
+            let mut snapshot = DoctorGroupActionSnapshot {
+                env: spec.env.clone(),
+                ..Default::default()
+            };
+            for command in &spec.commands {
+                snapshot
+                    .commands
+                    .push(substitute_templates(values, &spec_action.with, command)?);
+            }
+            for file in &spec.files {
+                snapshot
+                    .files
+                    .push(substitute_templates(values, &spec_action.with, file)?);
+            }
+            Some(snapshot)
+        }
+    };
+
+    let mut artifacts = Vec::new();
+    if let Some(fix) = &spec_action.fix {
+        for path in &fix.artifacts {
+            artifacts.push(substitute_templates(values, &spec_action.with, path)?);
+        }
+    }
+
+    let mut env = BTreeMap::new();
+    for (name, value) in group_model.spec.env.iter().chain(&spec_action.env) {
+        let value = substitute_templates(values, &spec_action.with, value)
+            .map_err(|e| anyhow!("unable to render env `{}`: {}", name, e))?;
+        env.insert(name.clone(), value);
+    }
+
+    let mut labels = group_model.metadata.labels.clone();
+    labels.extend(spec_action.labels.clone());
+
+    let working_dir = match &spec_action.working_dir {
+        Some(working_dir) => {
+            let working_dir = substitute_templates(values, &spec_action.with, working_dir)
+                .map_err(|e| anyhow!("unable to render workingDir: {}", e))?;
+            Some(containing_dir.join(working_dir).clean())
+        }
+        None => None,
+    };
+
+    let condition = parse_condition(
+        containing_dir,
+        values,
+        &spec_action.with,
+        &spec_action.platforms,
+        spec_action.only_if.as_ref(),
+    )?;
 
     let check_command = if let Some(ref check) = spec_action.check.commands {
         let mut templated_commands = Vec::new();
         for command in check {
-            templated_commands.push(substitute_templates(&working_dir, command)?);
+            templated_commands.push(substitute_templates(values, &spec_action.with, command)?);
         }
         Some(DoctorGroupActionCommand::from((
             containing_dir,
@@ -212,39 +780,630 @@ fn parse_action(
         None
     };
 
+    let exit_codes = match &spec_action.check.exit_codes {
+        Some(spec) => {
+            Some(parse_exit_codes(spec).map_err(|e| anyhow!("invalid exitCodes: {}", e))?)
+        }
+        None => None,
+    };
+
     Ok(DoctorGroupAction {
         name: spec_action.name.unwrap_or_else(|| format!("{}", idx + 1)),
         required: spec_action.required,
+        allow_failure: spec_action.allow_failure,
+        run_always: spec_action.run_always,
+        timeout: spec_action
+            .timeout_seconds
+            .map(std::time::Duration::from_secs),
+        container_image: spec_action
+            .runtime
+            .as_ref()
+            .or(group_model.spec.runtime.as_ref())
+            .map(|runtime| runtime.image.clone()),
+        env,
+        condition,
+        labels,
+        working_dir,
         description: spec_action
             .description
             .unwrap_or_else(|| "default".to_string()),
         fix: DoctorGroupActionFix {
             command: fix_command,
+            rollback: rollback_command,
             help_text,
             help_url,
+            sandbox_paths,
+            snapshot,
+            prompt: spec_action
+                .fix
+                .as_ref()
+                .and_then(|f| f.prompt.as_ref())
+                .map(|prompt| DoctorGroupActionFixPrompt {
+                    text: prompt.text.trim().to_string(),
+                    extra_context: prompt.extra_context.as_ref().map(|c| c.trim().to_string()),
+                }),
+            artifacts,
         },
         check: DoctorGroupActionCheck {
             command: check_command,
             files: spec_action.check.paths.map(|paths| DoctorGroupCachePath {
                 paths: paths
                     .iter() // TODO: should this be as_ref() still? Changed because type inference error
-                    .map(|p| substitute_templates(working_dir.as_str(), p).unwrap()) // TODO: implement a function here, make it an early exit
+                    .map(|p| substitute_templates(values, &spec_action.with, p).unwrap()) // TODO: implement a function here, make it an early exit
                     .collect(),
-                base_path: containing_dir.parent().unwrap().to_path_buf(),
+                base_path: spec_action
+                    .check
+                    .base_path
+                    .unwrap_or_else(|| containing_dir.parent().unwrap().to_path_buf()),
+                ignore_paths: spec_action.check.ignore_paths,
+                respect_gitignore: spec_action.check.respect_gitignore,
             }),
+            exit_codes,
+            builtin,
+        },
+    })
+}
+
+/// The `brew` and `toolVersion` checks, with their templates rendered.
+fn parse_builtin_checks(
+    containing_dir: &Path,
+    values: &GroupTemplateValues,
+    spec_action: &DoctorGroupActionSpec,
+) -> Result<Vec<BuiltinCheck>> {
+    let mut builtin = Vec::new();
+    if let Some(brew) = &spec_action.check.brew {
+        let formula = substitute_templates(values, &spec_action.with, &brew.formula)?;
+        builtin.push(BuiltinCheck::BrewFormula(formula));
+    }
+    if let Some(tool_version) = &spec_action.check.tool_version {
+        let tool = substitute_templates(values, &spec_action.with, &tool_version.tool)?;
+        let command = match &tool_version.command {
+            Some(command) => extract_command_path(
+                containing_dir,
+                &substitute_templates(values, &spec_action.with, command)?,
+            ),
+            None => format!("{} --version", tool),
+        };
+        let constraint = semver::VersionReq::parse(&tool_version.constraint).map_err(|e| {
+            anyhow!(
+                "invalid toolVersion constraint `{}`: {}",
+                tool_version.constraint,
+                e
+            )
+        })?;
+        builtin.push(BuiltinCheck::ToolVersion {
+            tool,
+            command,
+            constraint,
+        });
+    }
+    Ok(builtin)
+}
+
+/// Help for a failed builtin check that has no fix scope can derive.
+fn builtin_help_text(check: &BuiltinCheck) -> Option<String> {
+    match check {
+        BuiltinCheck::BrewFormula(_) => None,
+        BuiltinCheck::ToolVersion {
+            tool, constraint, ..
+        } => Some(format!(
+            "Install a version of `{}` matching `{}`",
+            tool, constraint
+        )),
+    }
+}
+
+/// `success` defaults to `[0]` when it isn't set.
+fn parse_exit_codes(spec: &DoctorExitCodesSpec) -> Result<DoctorExitCodes> {
+    let parse = |codes: &[DoctorExitCodeSpec]| -> Result<Vec<RangeInclusive<i32>>> {
+        codes.iter().map(parse_exit_code).collect()
+    };
+    let defaults = DoctorExitCodes::default();
+
+    Ok(DoctorExitCodes {
+        success: match &spec.success {
+            Some(codes) => parse(codes)?,
+            None => defaults.success,
         },
+        skip: parse(&spec.skip)?,
+        stop: parse(&spec.stop)?,
     })
 }
 
+fn parse_exit_code(spec: &DoctorExitCodeSpec) -> Result<RangeInclusive<i32>> {
+    let range = match spec {
+        DoctorExitCodeSpec::Code(code) => return Ok(*code..=*code),
+        DoctorExitCodeSpec::Range(range) => range,
+    };
+    let parsed = match range.trim().split_once('-') {
+        Some((first, last)) => first
+            .trim()
+            .parse::<i32>()
+            .ok()
+            .zip(last.trim().parse::<i32>().ok()),
+        None => range.trim().parse::<i32>().ok().map(|code| (code, code)),
+    };
+
+    match parsed {
+        Some((first, last)) if first <= last => Ok(first..=last),
+        _ => Err(anyhow!(
+            "`{}` isn't an exit code or a range like `100-255`",
+            range
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
 
+    use chrono::{Duration, Local, TimeZone, Utc};
+
+    use crate::models::prelude::ModelMetadata;
+    use crate::prelude::DoctorPlatform;
     use crate::shared::models::parse_models_from_string;
     use crate::shared::models::prelude::{
-        DoctorGroupAction, DoctorGroupActionCheck, DoctorGroupActionCommand, DoctorGroupActionFix,
+        BuiltinCheck, DoctorGroupAction, DoctorGroupActionCheck, DoctorGroupActionCommand,
+        DoctorGroupActionFix,
+    };
+    use crate::shared::prelude::{
+        DoctorCondition, DoctorGroupBuilder, DoctorGroupCachePath, DoctorGroupSchedule,
+        ScheduleStatus,
     };
-    use crate::shared::prelude::DoctorGroupCachePath;
+    use std::collections::BTreeMap;
+
+    use super::{
+        parse_exit_code, parse_interval, substitute_templates, ExitCodeMeaning, GroupTemplateValues,
+    };
+    use crate::prelude::DoctorExitCodeSpec;
+
+    #[test]
+    fn test_substitute_templates_with_filters() {
+        assert_eq!(
+            "cd '/foo/my project' && ls /foo",
+            substitute_templates(
+                &GroupTemplateValues::new("/foo/my project", &BTreeMap::new()).unwrap(),
+                &BTreeMap::new(),
+                "cd {{ working_dir | quote_sh }} && ls {{ working_dir | dirname }}"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_substitute_templates_with_vars_and_env() {
+        let values = GroupTemplateValues {
+            working_dir: "/foo".to_string(),
+            vars: BTreeMap::from([("node_version".to_string(), "20.11.0".to_string())]),
+            env: BTreeMap::from([("USER".to_string(), "jane".to_string())]),
+        };
+        assert_eq!(
+            "nvm install 20.11.0 # jane ",
+            substitute_templates(
+                &values,
+                &BTreeMap::new(),
+                "nvm install {{ vars.node_version }} # {{ env.USER }} {{ env.MISSING }}"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_vars_are_rendered() {
+        let vars = BTreeMap::from([
+            ("bin".to_string(), "{{ working_dir }}/bin".to_string()),
+            (
+                "version".to_string(),
+                "{{ env.SCOPE_TEST_UNSET_VERSION | default('18') }}".to_string(),
+            ),
+        ]);
+        let values = GroupTemplateValues::new("/foo", &vars).unwrap();
+
+        assert_eq!("/foo/bin", values.vars["bin"]);
+        assert_eq!("18", values.vars["version"]);
+    }
+
+    #[test]
+    fn test_required_tools() {
+        let mut group = DoctorGroupBuilder::default()
+            .full_name("ScopeDoctorGroup/tools")
+            .metadata(ModelMetadata::new("tools"))
+            .requires(Vec::new())
+            .run_by_default(true)
+            .extra_report_args(BTreeMap::new())
+            .actions(vec![
+                DoctorGroupAction::make_from(
+                    "a",
+                    "a",
+                    Some(vec!["brew install node"]),
+                    None,
+                    Some(vec!["node --version", "test -f package.json"]),
+                ),
+                DoctorGroupAction::make_from("b", "b", None, None, Some(vec!["node -e 1"])),
+            ])
+            .build()
+            .unwrap();
+
+        // Only tools listed in `requiredTools` are looked for, not the words of commands.
+        assert!(group.required_tools().is_empty());
+
+        group.tools = vec!["pnpm".to_string(), "node".to_string()];
+        assert_eq!(
+            vec!["node", "pnpm"],
+            group.required_tools().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(Duration::minutes(30), parse_interval("30m").unwrap());
+        assert_eq!(Duration::hours(12), parse_interval("12h").unwrap());
+        assert_eq!(Duration::days(1), parse_interval("1d").unwrap());
+        assert_eq!(Duration::weeks(2), parse_interval("2w").unwrap());
+        assert!(parse_interval("d").is_err());
+        assert!(parse_interval("3 fortnights").is_err());
+    }
+
+    #[test]
+    fn test_schedule_interval() {
+        let schedule = DoctorGroupSchedule {
+            interval: Some(Duration::days(1)),
+            hours: None,
+        };
+        let now = Local::now();
+        let last_run = now.with_timezone(&Utc) - Duration::hours(1);
+
+        assert_eq!(ScheduleStatus::Due, schedule.status(now, None));
+        assert_eq!(
+            ScheduleStatus::IntervalNotElapsed {
+                next_run: last_run + Duration::days(1)
+            },
+            schedule.status(now, Some(last_run))
+        );
+        assert_eq!(
+            ScheduleStatus::Due,
+            schedule.status(now, Some(last_run - Duration::days(1)))
+        );
+    }
+
+    #[test]
+    fn test_schedule_hours() {
+        let work_hours = DoctorGroupSchedule {
+            interval: None,
+            hours: Some((9, 17)),
+        };
+        let overnight = DoctorGroupSchedule {
+            interval: None,
+            hours: Some((22, 6)),
+        };
+        let morning = Local.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+        let night = Local.with_ymd_and_hms(2024, 6, 3, 23, 0, 0).unwrap();
+
+        assert_eq!(ScheduleStatus::Due, work_hours.status(morning, None));
+        assert_eq!(
+            ScheduleStatus::OutsideHours { start: 9, end: 17 },
+            work_hours.status(night, None)
+        );
+        assert_eq!(ScheduleStatus::Due, overnight.status(night, None));
+        assert_eq!(
+            ScheduleStatus::OutsideHours { start: 22, end: 6 },
+            overnight.status(morning, None)
+        );
+    }
+
+    #[test]
+    fn test_action_runtime_replaces_group_runtime() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  requiredTools: [node]
+  runtime:
+    image: node:20
+  actions:
+    - check:
+        commands: [node --version]
+    - check:
+        commands: [node --version]
+      runtime:
+        image: node:18
+";
+        let path = Path::new("/foo/bar/.scope/node.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        assert_eq!(Some("node:20"), dg.actions[0].container_image.as_deref());
+        assert_eq!(Some("node:18"), dg.actions[1].container_image.as_deref());
+        assert_eq!(
+            vec!["docker"],
+            dg.required_tools().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_action_env_is_merged_over_group_env() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  vars:
+    node: '20'
+  env:
+    NODE_ENV: development
+    NODE_VERSION: '{{ vars.node }}'
+  actions:
+    - check:
+        commands: [node --version]
+    - check:
+        commands: [node --version]
+      with:
+        env: test
+      env:
+        NODE_ENV: '{{ params.env }}'
+        CI: 'true'
+";
+        let path = Path::new("/foo/bar/.scope/node.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        assert_eq!(
+            BTreeMap::from([
+                ("NODE_ENV".to_string(), "development".to_string()),
+                ("NODE_VERSION".to_string(), "20".to_string()),
+            ]),
+            dg.actions[0].env
+        );
+        assert_eq!(
+            BTreeMap::from([
+                ("CI".to_string(), "true".to_string()),
+                ("NODE_ENV".to_string(), "test".to_string()),
+                ("NODE_VERSION".to_string(), "20".to_string()),
+            ]),
+            dg.actions[1].env
+        );
+    }
+
+    #[test]
+    fn test_action_working_dir_is_resolved() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: packages
+spec:
+  actions:
+    - check:
+        commands: [npm test]
+    - workingDir: ../packages/web
+      check:
+        commands: [npm test]
+    - workingDir: '{{ working_dir }}/packages/{{ params.package }}'
+      with:
+        package: api
+      check:
+        commands: [npm test]
+";
+        let path = Path::new("/foo/bar/.scope/packages.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        assert_eq!(None, dg.actions[0].working_dir);
+        assert_eq!(
+            Some(PathBuf::from("/foo/bar/packages/web")),
+            dg.actions[1].working_dir
+        );
+        assert_eq!(
+            Some(PathBuf::from("/foo/bar/packages/api")),
+            dg.actions[2].working_dir
+        );
+    }
+
+    #[test]
+    fn test_platforms_and_only_if_are_parsed() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: brew
+spec:
+  platforms: [macos]
+  onlyIf:
+    command: ./has-brewfile.sh
+  actions:
+    - check:
+        commands: [brew bundle check]
+    - platforms: [macos, linux]
+      onlyIf:
+        command: test -f {{ params.file }}
+      with:
+        file: Brewfile
+      check:
+        commands: [brew bundle check]
+";
+        let path = Path::new("/foo/bar/.scope/brew.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        assert_eq!(
+            DoctorCondition {
+                platforms: vec![DoctorPlatform::Macos],
+                only_if: Some("/foo/bar/.scope/has-brewfile.sh".to_string()),
+            },
+            dg.condition
+        );
+        assert_eq!(DoctorCondition::default(), dg.actions[0].condition);
+        assert_eq!(
+            DoctorCondition {
+                platforms: vec![DoctorPlatform::Macos, DoctorPlatform::Linux],
+                only_if: Some("test -f Brewfile".to_string()),
+            },
+            dg.actions[1].condition
+        );
+
+        assert!(dg.actions[0].condition.includes_platform("windows"));
+        assert!(dg.actions[1].condition.includes_platform("linux"));
+        assert!(!dg.actions[1].condition.includes_platform("windows"));
+    }
+
+    #[test]
+    fn test_exit_codes_are_parsed() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: db
+spec:
+  actions:
+    - check:
+        commands: [./check-db.sh]
+        exitCodes:
+          success: [0, 3]
+          skip: [10]
+          stop: [\"64-78\"]
+    - check:
+        commands: [./check-db.sh]
+        exitCodes:
+          skip: [10]
+    - check:
+        commands: [./check-db.sh]
+";
+        let path = Path::new("/foo/bar/.scope/db.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        let exit_codes = dg.actions[0].check.exit_codes.as_ref().unwrap();
+        assert_eq!(ExitCodeMeaning::Success, exit_codes.meaning(Some(3)));
+        assert_eq!(ExitCodeMeaning::Skip, exit_codes.meaning(Some(10)));
+        assert_eq!(ExitCodeMeaning::Stop, exit_codes.meaning(Some(70)));
+        assert_eq!(ExitCodeMeaning::Failure, exit_codes.meaning(Some(100)));
+        assert_eq!(ExitCodeMeaning::Failure, exit_codes.meaning(None));
+
+        let exit_codes = dg.actions[1].check.exit_codes.as_ref().unwrap();
+        assert_eq!(ExitCodeMeaning::Success, exit_codes.meaning(Some(0)));
+        assert_eq!(ExitCodeMeaning::Skip, exit_codes.meaning(Some(10)));
+        assert_eq!(ExitCodeMeaning::Failure, exit_codes.meaning(Some(100)));
+
+        assert_eq!(None, dg.actions[2].check.exit_codes);
+
+        assert!(parse_exit_code(&DoctorExitCodeSpec::Range("78-64".to_string())).is_err());
+        assert!(parse_exit_code(&DoctorExitCodeSpec::Range("many".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_builtin_checks_are_parsed() {
+        let group = |check: &str| {
+            format!(
+                "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: tools
+spec:
+  actions:
+    - check:
+{}",
+                check
+            )
+        };
+        let path = Path::new("/foo/bar/.scope/tools.yaml");
+        let parse =
+            |check: &str| parse_models_from_string(Path::new("/foo/bar"), path, &group(check));
+
+        let configs = parse("        brew:\n          formula: jq\n").unwrap();
+        let action = &configs[0].get_doctor_group().unwrap().actions[0];
+        assert_eq!(
+            vec![BuiltinCheck::BrewFormula("jq".to_string())],
+            action.check.builtin
+        );
+        assert_eq!(
+            Some(DoctorGroupActionCommand::from(vec!["brew install jq"])),
+            action.fix.command
+        );
+
+        let configs =
+            parse("        toolVersion:\n          tool: node\n          constraint: \">=20\"\n")
+                .unwrap();
+        let action = &configs[0].get_doctor_group().unwrap().actions[0];
+        assert_eq!(
+            vec![BuiltinCheck::ToolVersion {
+                tool: "node".to_string(),
+                command: "node --version".to_string(),
+                constraint: semver::VersionReq::parse(">=20").unwrap(),
+            }],
+            action.check.builtin
+        );
+        assert_eq!(None, action.fix.command);
+        assert_eq!(
+            Some("Install a version of `node` matching `>=20`".to_string()),
+            action.fix.help_text
+        );
+
+        assert!(parse(
+            "        toolVersion:\n          tool: node\n          constraint: latest\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_run_always_actions_have_no_check() {
+        let group = |action: &str| {
+            format!(
+                "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: direnv
+spec:
+  actions:
+    - runAlways: true
+{}",
+                action
+            )
+        };
+        let path = Path::new("/foo/bar/.scope/direnv.yaml");
+        let work_dir = Path::new("/foo/bar");
+
+        let text = group("      fix:\n        commands: [direnv allow]\n");
+        let configs = parse_models_from_string(work_dir, path, &text).unwrap();
+        assert!(configs[0].get_doctor_group().unwrap().actions[0].run_always);
+
+        let text = group(
+            "      check:\n        commands: [direnv status]\n      fix:\n        commands: [direnv allow]\n",
+        );
+        assert!(parse_models_from_string(work_dir, path, &text).is_err());
+        assert!(parse_models_from_string(work_dir, path, &group("")).is_err());
+    }
+
+    #[test]
+    fn test_fix_artifacts_are_rendered() {
+        let text = "
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: node
+spec:
+  actions:
+    - with:
+        dir: web
+      check:
+        paths: ['{{ params.dir }}/package.json']
+      fix:
+        commands: [npm install]
+        artifacts: ['{{ params.dir }}/node_modules', web/.installed]
+";
+        let path = Path::new("/foo/bar/.scope/node.yaml");
+        let configs = parse_models_from_string(Path::new("/foo/bar"), path, text).unwrap();
+        let dg = configs[0].get_doctor_group().unwrap();
+
+        assert_eq!(
+            vec!["web/node_modules".to_string(), "web/.installed".to_string()],
+            dg.actions[0].fix.artifacts
+        );
+    }
 
     #[test]
     fn parse_group_1() {
@@ -270,13 +1429,28 @@ mod tests {
             DoctorGroupAction {
                 name: "1".to_string(),
                 required: false,
+                allow_failure: false,
+                run_always: false,
+                timeout: None,
+                container_image: None,
+                env: BTreeMap::new(),
+                condition: Default::default(),
+                labels: BTreeMap::new(),
+                working_dir: None,
                 description: "foo1".to_string(),
                 fix: DoctorGroupActionFix {
                     command: Some(DoctorGroupActionCommand::from(vec![
                         "/foo/bar/.scope/fix1.sh"
                     ])),
+                    rollback: Some(DoctorGroupActionCommand::from(vec![
+                        "/foo/bar/.scope/undo1.sh"
+                    ])),
                     help_text: Some("There is a good way to fix this, maybe...".to_string()),
                     help_url: Some("https://go.example.com/fixit".to_string()),
+                    sandbox_paths: Vec::new(),
+                    snapshot: None,
+                    prompt: None,
+                    artifacts: Vec::new(),
                 },
                 check: DoctorGroupActionCheck {
                     command: Some(DoctorGroupActionCommand::from(vec![
@@ -285,7 +1459,9 @@ mod tests {
                     files: Some(DoctorGroupCachePath::from((
                         "/foo/bar",
                         vec!["flig/bar/**/*"]
-                    )))
+                    ))),
+                    exit_codes: Default::default(),
+                    builtin: Vec::new(),
                 }
             }
         );
@@ -294,15 +1470,30 @@ mod tests {
             DoctorGroupAction {
                 name: "2".to_string(),
                 required: true,
+                allow_failure: false,
+                run_always: false,
+                timeout: Some(std::time::Duration::from_secs(10)),
+                container_image: None,
+                env: BTreeMap::new(),
+                condition: Default::default(),
+                labels: BTreeMap::new(),
+                working_dir: None,
                 description: "foo2".to_string(),
                 fix: DoctorGroupActionFix {
                     command: None,
+                    rollback: None,
                     help_text: None,
                     help_url: None,
+                    sandbox_paths: Vec::new(),
+                    snapshot: None,
+                    prompt: None,
+                    artifacts: Vec::new(),
                 },
                 check: DoctorGroupActionCheck {
                     command: Some(DoctorGroupActionCommand::from(vec!["sleep infinity"])),
-                    files: Some(DoctorGroupCachePath::from(("/foo/bar", vec!["*/*.txt"])))
+                    files: Some(DoctorGroupCachePath::from(("/foo/bar", vec!["*/*.txt"]))),
+                    exit_codes: Default::default(),
+                    builtin: Vec::new(),
                 }
             }
         );
@@ -1,7 +1,17 @@
-use crate::models::prelude::{ModelMetadata, V1AlphaKnownError};
+use crate::models::prelude::{KnownErrorSeveritySpec, ModelMetadata, V1AlphaKnownError};
 use crate::models::HelpMetadata;
+use crate::shared::models::prelude::{DoctorGroup, DoctorGroupActionFix};
+use crate::shared::templates::{render_captures, render_captures_in_command};
+use anyhow::{anyhow, Result};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
 use derivative::Derivative;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::warn;
 
 #[derive(Derivative)]
 #[derivative(PartialEq)]
@@ -13,6 +23,156 @@ pub struct KnownError {
     #[derivative(PartialEq = "ignore")]
     pub regex: Regex,
     pub help_text: String,
+    pub priority: i32,
+    pub severity: KnownErrorSeverity,
+    /// Lines before the current one that `regex` is matched against as well.
+    pub context_lines: usize,
+    pub fix_ref: Option<String>,
+    /// The fix `fix_ref` points to, set by [`KnownError::resolve_fix`].
+    pub fix: Option<KnownErrorFix>,
+}
+
+/// How serious a known error is, from least to most.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    strum::Display,
+)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum KnownErrorSeverity {
+    Info,
+    Warning,
+    #[default]
+    Error,
+}
+
+impl From<KnownErrorSeveritySpec> for KnownErrorSeverity {
+    fn from(value: KnownErrorSeveritySpec) -> Self {
+        match value {
+            KnownErrorSeveritySpec::Info => KnownErrorSeverity::Info,
+            KnownErrorSeveritySpec::Warning => KnownErrorSeverity::Warning,
+            KnownErrorSeveritySpec::Error => KnownErrorSeverity::Error,
+        }
+    }
+}
+
+/// A doctor action's fix, reused by a known error.
+#[derive(Debug, PartialEq, Clone)]
+pub struct KnownErrorFix {
+    pub group: String,
+    pub action: String,
+    /// `PATH` to run the fix with, so it behaves the same as when doctor runs it.
+    pub exec_path: String,
+    /// Directory to run the fix in, from the action's `workingDir`.
+    pub working_dir: Option<PathBuf>,
+    /// The action's `env`, with the group's merged in.
+    pub env: BTreeMap<String, String>,
+    pub fix: DoctorGroupActionFix,
+}
+
+impl KnownError {
+    /// Order known errors "most specific first". Higher `priority` comes first, then longer
+    /// patterns, and finally the name to keep the order stable.
+    pub fn cmp_priority(&self, other: &Self) -> Ordering {
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| other.pattern.len().cmp(&self.pattern.len()))
+            .then_with(|| self.full_name.cmp(&other.full_name))
+    }
+
+    /// Copy of this known error for a match in `input`, with the named capture groups of
+    /// `pattern` rendered into `help` and into the fix's commands and help, e.g. `(?P<port>\d+)`
+    /// and `Port {{ port }} is already in use`. Captures are shell-quoted in the commands. Text
+    /// that can't be rendered is kept as it is.
+    pub fn for_match(&self, input: &str) -> KnownError {
+        let Some(found) = self.regex.captures(input) else {
+            return self.clone();
+        };
+        let captures: BTreeMap<String, String> = self
+            .regex
+            .capture_names()
+            .flatten()
+            .map(|name| {
+                let value = found.name(name).map(|m| m.as_str()).unwrap_or_default();
+                (name.to_string(), value.to_string())
+            })
+            .collect();
+        if captures.is_empty() {
+            return self.clone();
+        }
+
+        let render = |text: &mut String, in_command: bool| {
+            let rendered = if in_command {
+                render_captures_in_command(text, &captures)
+            } else {
+                render_captures(text, &captures)
+            };
+            match rendered {
+                Ok(rendered) => *text = rendered,
+                Err(e) => {
+                    warn!(target: "user", "Unable to render the captures of {}: {}", self.full_name, e)
+                }
+            }
+        };
+        let mut known_error = self.clone();
+        render(&mut known_error.help_text, false);
+        if let Some(fix) = &mut known_error.fix {
+            if let Some(command) = &mut fix.fix.command {
+                for text in command.commands.iter_mut() {
+                    render(text, true);
+                }
+            }
+            if let Some(help_text) = &mut fix.fix.help_text {
+                render(help_text, false);
+            }
+        }
+        known_error
+    }
+
+    /// Look up the doctor action named by `fix_ref` and keep a copy of its fix.
+    pub fn resolve_fix(&mut self, groups: &BTreeMap<String, DoctorGroup>) -> Result<()> {
+        let Some(fix_ref) = &self.fix_ref else {
+            return Ok(());
+        };
+        let Some((group_name, action_name)) = fix_ref.split_once('/') else {
+            return Err(anyhow!("`{}` should be written as group/action", fix_ref));
+        };
+        let group = groups
+            .get(group_name)
+            .ok_or_else(|| anyhow!("no doctor group named `{}` was found", group_name))?;
+        let action = group
+            .actions
+            .iter()
+            .find(|action| action.name == action_name)
+            .ok_or_else(|| {
+                anyhow!(
+                    "doctor group `{}` has no action named `{}`",
+                    group_name,
+                    action_name
+                )
+            })?;
+
+        self.fix = Some(KnownErrorFix {
+            group: group_name.to_string(),
+            action: action_name.to_string(),
+            exec_path: group.metadata.exec_path(),
+            working_dir: action.working_dir.clone(),
+            env: action.env.clone(),
+            fix: action.fix.clone(),
+        });
+        Ok(())
+    }
 }
 
 impl HelpMetadata for KnownError {
@@ -29,19 +189,27 @@ impl TryFrom<V1AlphaKnownError> for KnownError {
     type Error = anyhow::Error;
 
     fn try_from(value: V1AlphaKnownError) -> Result<Self, Self::Error> {
-        let regex = Regex::new(&value.spec.pattern)?;
+        let regex = RegexBuilder::new(&value.spec.pattern)
+            .multi_line(value.spec.context_lines > 0)
+            .build()?;
         Ok(KnownError {
             full_name: value.full_name(),
             metadata: value.metadata,
             pattern: value.spec.pattern,
             regex,
             help_text: value.spec.help,
+            priority: value.spec.priority,
+            severity: value.spec.severity.into(),
+            context_lines: value.spec.context_lines as usize,
+            fix_ref: value.spec.fix_ref,
+            fix: None,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::KnownErrorSeverity;
     use crate::shared::models::parse_models_from_string;
 
     use std::path::Path;
@@ -67,5 +235,90 @@ spec:
         assert_eq!("ScopeKnownError/error-exists", model.full_name);
         assert_eq!("The command had an error, try reading the logs around there to find out what happened.", model.help_text);
         assert_eq!("error", model.pattern);
+        assert_eq!(0, model.priority);
+        assert_eq!(0, model.context_lines);
+        assert_eq!(KnownErrorSeverity::Error, model.severity);
+    }
+
+    #[test]
+    fn test_parse_severity() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: deprecated-flag
+spec:
+  pattern: is deprecated
+  help: The flag will be removed, switch to the new one.
+  severity: warning";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        let model = configs[0].get_known_error_spec().unwrap();
+
+        assert_eq!(KnownErrorSeverity::Warning, model.severity);
+        assert!(KnownErrorSeverity::Warning < KnownErrorSeverity::Error);
+    }
+
+    #[test]
+    fn test_context_lines_match_per_line_anchors() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: python-traceback
+spec:
+  pattern: '^Traceback.*\\n.*\\n^ModuleNotFoundError'
+  help: A module is missing.
+  contextLines: 2";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        let model = configs[0].get_known_error_spec().unwrap();
+
+        assert_eq!(2, model.context_lines);
+        assert!(model.regex.is_match(
+            "Traceback (most recent call last):\n  File \"main.py\", line 1\nModuleNotFoundError: No module named 'foo'"
+        ));
+    }
+
+    #[test]
+    fn test_known_errors_sort_most_specific_first() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: a-generic
+spec:
+  pattern: error
+  help: generic
+---
+apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: b-specific
+spec:
+  pattern: disk error
+  help: specific
+---
+apiVersion: scope.github.com/v1alpha
+kind: ScopeKnownError
+metadata:
+  name: c-important
+spec:
+  pattern: err
+  help: important
+  priority: 10";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let mut errors: Vec<_> = parse_models_from_string(work_dir, path, text)
+            .unwrap()
+            .iter()
+            .map(|c| c.get_known_error_spec().unwrap())
+            .collect();
+        errors.sort_by(|a, b| a.cmp_priority(b));
+
+        let names: Vec<_> = errors.iter().map(|e| e.metadata.name.clone()).collect();
+        assert_eq!(vec!["c-important", "b-specific", "a-generic"], names);
     }
 }
@@ -1,31 +1,58 @@
 use crate::models::prelude::{
-    ModelRoot, V1AlphaDoctorGroup, V1AlphaKnownError, V1AlphaReportLocation,
+    ModelRoot, V1AlphaActionLibrary, V1AlphaDoctorGroup, V1AlphaKnownError, V1AlphaPolicy,
+    V1AlphaPromptPolicy, V1AlphaRedaction, V1AlphaReportDefinition, V1AlphaReportLocation,
+    V1AlphaScopeConfig,
 };
+use crate::models::HelpMetadata;
 use crate::models::InternalScopeModel;
 use crate::shared::prelude::*;
+use crate::shared::templates::add_template_filters;
 use anyhow::anyhow;
+use colored::Colorize;
 use path_clean::PathClean;
 use serde_yaml::Value;
-use std::collections::VecDeque;
-use std::path::Path;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tracing::warn;
 
+mod action_library;
 mod doctor_group;
 mod known_error;
+mod policy;
+mod prompt_policy;
+mod redaction;
+mod report_definition;
+mod scope_config;
 mod upload_location;
 
+use self::action_library::{use_library_actions, ActionLibrary};
 use self::known_error::KnownError;
+use self::policy::Policy;
+use self::prompt_policy::PromptPolicy;
+use self::redaction::Redaction;
+use self::report_definition::ReportDefinition;
+use self::scope_config::ScopeConfig;
 use self::upload_location::ReportUploadLocation;
 
 pub mod prelude {
     pub use super::ParsedConfig;
-    pub use super::{doctor_group::*, known_error::*, upload_location::*};
+    pub use super::{
+        action_library::*, doctor_group::*, known_error::*, policy::*, prompt_policy::*,
+        redaction::*, report_definition::*, scope_config::*, upload_location::*,
+    };
 }
 
 #[derive(Debug, PartialEq)]
 pub enum ParsedConfig {
     KnownError(KnownError),
     ReportUpload(ReportUploadLocation),
+    ReportDefinition(ReportDefinition),
     DoctorGroup(DoctorGroup),
+    ScopeConfig(ScopeConfig),
+    ActionLibrary(ActionLibrary),
+    Redaction(Redaction),
+    Policy(Policy),
+    PromptPolicy(PromptPolicy),
 }
 
 #[cfg(test)]
@@ -44,21 +71,182 @@ impl ParsedConfig {
         }
     }
 
+    pub fn get_report_definition(&self) -> Option<ReportDefinition> {
+        match self {
+            ParsedConfig::ReportDefinition(root) => Some(root.clone()),
+            _ => None,
+        }
+    }
+
     pub fn get_doctor_group(&self) -> Option<DoctorGroup> {
         match self {
             ParsedConfig::DoctorGroup(root) => Some(root.clone()),
             _ => None,
         }
     }
+
+    pub fn get_scope_config(&self) -> Option<ScopeConfig> {
+        match self {
+            ParsedConfig::ScopeConfig(root) => Some(root.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_redaction(&self) -> Option<Redaction> {
+        match self {
+            ParsedConfig::Redaction(root) => Some(root.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_policy(&self) -> Option<Policy> {
+        match self {
+            ParsedConfig::Policy(root) => Some(root.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_prompt_policy(&self) -> Option<PromptPolicy> {
+        match self {
+            ParsedConfig::PromptPolicy(root) => Some(root.clone()),
+            _ => None,
+        }
+    }
 }
 
 impl TryFrom<ModelRoot<Value>> for ParsedConfig {
     type Error = anyhow::Error;
 
     fn try_from(value: ModelRoot<Value>) -> Result<Self, Self::Error> {
-        if let Ok(Some(known)) = V1AlphaDoctorGroup::known_type(&value) {
+        ParsedConfig::from_model(value, &BTreeMap::new())
+    }
+}
+
+impl ParsedConfig {
+    /// Whether the `apiVersion` and `kind` of `value` are ones this version of `scope` can parse.
+    pub fn is_supported(value: &ModelRoot<Value>) -> bool {
+        [
+            (
+                V1AlphaDoctorGroup::int_api_version(),
+                V1AlphaDoctorGroup::int_kind(),
+            ),
+            (
+                V1AlphaActionLibrary::int_api_version(),
+                V1AlphaActionLibrary::int_kind(),
+            ),
+            (
+                V1AlphaKnownError::int_api_version(),
+                V1AlphaKnownError::int_kind(),
+            ),
+            (
+                V1AlphaReportLocation::int_api_version(),
+                V1AlphaReportLocation::int_kind(),
+            ),
+            (
+                V1AlphaReportDefinition::int_api_version(),
+                V1AlphaReportDefinition::int_kind(),
+            ),
+            (
+                V1AlphaScopeConfig::int_api_version(),
+                V1AlphaScopeConfig::int_kind(),
+            ),
+            (
+                V1AlphaRedaction::int_api_version(),
+                V1AlphaRedaction::int_kind(),
+            ),
+            (V1AlphaPolicy::int_api_version(), V1AlphaPolicy::int_kind()),
+            (
+                V1AlphaPromptPolicy::int_api_version(),
+                V1AlphaPromptPolicy::int_kind(),
+            ),
+        ]
+        .iter()
+        .any(|(api_version, kind)| {
+            api_version.eq_ignore_ascii_case(&value.api_version)
+                && kind.eq_ignore_ascii_case(&value.kind)
+        })
+    }
+
+    /// Where `value` doesn't match the schema of its kind, see
+    /// [`InternalScopeModel::schema_errors`]. Empty when the kind isn't supported.
+    pub fn schema_errors(value: &ModelRoot<Value>) -> Vec<(Vec<String>, String)> {
+        type SchemaErrors = fn(&serde_json::Value) -> Vec<(Vec<String>, String)>;
+        let kinds: [(String, String, SchemaErrors); 9] = [
+            (
+                V1AlphaDoctorGroup::int_api_version(),
+                V1AlphaDoctorGroup::int_kind(),
+                V1AlphaDoctorGroup::schema_errors,
+            ),
+            (
+                V1AlphaActionLibrary::int_api_version(),
+                V1AlphaActionLibrary::int_kind(),
+                V1AlphaActionLibrary::schema_errors,
+            ),
+            (
+                V1AlphaKnownError::int_api_version(),
+                V1AlphaKnownError::int_kind(),
+                V1AlphaKnownError::schema_errors,
+            ),
+            (
+                V1AlphaReportLocation::int_api_version(),
+                V1AlphaReportLocation::int_kind(),
+                V1AlphaReportLocation::schema_errors,
+            ),
+            (
+                V1AlphaReportDefinition::int_api_version(),
+                V1AlphaReportDefinition::int_kind(),
+                V1AlphaReportDefinition::schema_errors,
+            ),
+            (
+                V1AlphaScopeConfig::int_api_version(),
+                V1AlphaScopeConfig::int_kind(),
+                V1AlphaScopeConfig::schema_errors,
+            ),
+            (
+                V1AlphaRedaction::int_api_version(),
+                V1AlphaRedaction::int_kind(),
+                V1AlphaRedaction::schema_errors,
+            ),
+            (
+                V1AlphaPolicy::int_api_version(),
+                V1AlphaPolicy::int_kind(),
+                V1AlphaPolicy::schema_errors,
+            ),
+            (
+                V1AlphaPromptPolicy::int_api_version(),
+                V1AlphaPromptPolicy::int_kind(),
+                V1AlphaPromptPolicy::schema_errors,
+            ),
+        ];
+
+        let Some((_, _, schema_errors)) = kinds.iter().find(|(api_version, kind, _)| {
+            api_version.eq_ignore_ascii_case(&value.api_version)
+                && kind.eq_ignore_ascii_case(&value.kind)
+        }) else {
+            return Vec::new();
+        };
+
+        match serde_json::to_value(value) {
+            Ok(json) => schema_errors(&json),
+            Err(e) => vec![(Vec::new(), e.to_string())],
+        }
+    }
+
+    /// Parse a model, replacing actions that use `useAction` with the action from `libraries`.
+    pub fn from_model(
+        value: ModelRoot<Value>,
+        libraries: &BTreeMap<String, ActionLibrary>,
+    ) -> anyhow::Result<Self> {
+        if let Ok(Some(mut known)) = V1AlphaDoctorGroup::known_type(&value) {
+            if let Err(e) = use_library_actions(&mut known.spec.actions, libraries) {
+                warn!(target: "user", "Unable to load {}: {}", known.full_name().bold(), e);
+                return Err(e);
+            }
             return Ok(ParsedConfig::DoctorGroup(DoctorGroup::try_from(known)?));
         }
+        if let Ok(Some(known)) = V1AlphaActionLibrary::known_type(&value) {
+            return Ok(ParsedConfig::ActionLibrary(ActionLibrary::try_from(known)?));
+        }
         if let Ok(Some(known)) = V1AlphaKnownError::known_type(&value) {
             return Ok(ParsedConfig::KnownError(KnownError::try_from(known)?));
         }
@@ -67,10 +255,42 @@ impl TryFrom<ModelRoot<Value>> for ParsedConfig {
                 known,
             )?));
         }
+        if let Ok(Some(known)) = V1AlphaReportDefinition::known_type(&value) {
+            return Ok(ParsedConfig::ReportDefinition(ReportDefinition::try_from(
+                known,
+            )?));
+        }
+        if let Ok(Some(known)) = V1AlphaScopeConfig::known_type(&value) {
+            return Ok(ParsedConfig::ScopeConfig(ScopeConfig::try_from(known)?));
+        }
+        if let Ok(Some(known)) = V1AlphaRedaction::known_type(&value) {
+            return Ok(ParsedConfig::Redaction(Redaction::try_from(known)?));
+        }
+        if let Ok(Some(known)) = V1AlphaPolicy::known_type(&value) {
+            return Ok(ParsedConfig::Policy(Policy::try_from(known)?));
+        }
+        if let Ok(Some(known)) = V1AlphaPromptPolicy::known_type(&value) {
+            return Ok(ParsedConfig::PromptPolicy(PromptPolicy::try_from(known)?));
+        }
         Err(anyhow!("Error was know a known type"))
     }
 }
 
+/// Render a `cacheDir` and make it absolute, relative paths are relative to `containing_dir`.
+pub(crate) fn resolve_cache_dir_template(
+    containing_dir: &Path,
+    working_dir: &str,
+    template: &str,
+) -> anyhow::Result<PathBuf> {
+    let mut env = minijinja::Environment::new();
+    add_template_filters(&mut env);
+    let rendered = env.render_str(template, minijinja::context! { working_dir => working_dir })?;
+
+    Ok(containing_dir.join(rendered).clean())
+}
+
+/// Make a command that starts with `.` relative to `parent_dir`. The path is cleaned, so on
+/// Windows `./scripts/setup.ps1` and `.\scripts\setup.ps1` both use `\` separators.
 pub(crate) fn extract_command_path(parent_dir: &Path, exec: &str) -> String {
     let mut parts: VecDeque<_> = exec.split(' ').map(|x| x.to_string()).collect();
     let mut command = parts.pop_front().unwrap();
@@ -89,6 +309,19 @@ pub(crate) fn extract_command_path(parent_dir: &Path, exec: &str) -> String {
         .join(" ")
 }
 
+#[test]
+fn test_resolve_cache_dir_template() {
+    let base_path = Path::new("/repo/.scope");
+    assert_eq!(
+        PathBuf::from("/repo/build/scope-cache"),
+        resolve_cache_dir_template(base_path, "/repo", "../build/scope-cache").unwrap()
+    );
+    assert_eq!(
+        PathBuf::from("/work/cache"),
+        resolve_cache_dir_template(base_path, "/work", "{{ working_dir }}/cache").unwrap()
+    );
+}
+
 #[test]
 fn test_extract_command_path() {
     let base_path = Path::new("/foo/bar");
@@ -103,3 +336,21 @@ fn test_extract_command_path() {
     assert_eq!("foo", extract_command_path(base_path, "foo"));
     assert_eq!("foo bar", extract_command_path(base_path, "foo bar"));
 }
+
+#[cfg(windows)]
+#[test]
+fn test_extract_command_path_windows() {
+    let base_path = Path::new("C:\\foo\\bar");
+    assert_eq!(
+        "C:\\foo\\bar\\scripts\\foo.ps1",
+        extract_command_path(base_path, ".\\scripts\\foo.ps1")
+    );
+    assert_eq!(
+        "C:\\foo\\scripts\\foo.ps1 -Force",
+        extract_command_path(base_path, "..\\scripts\\foo.ps1 -Force")
+    );
+    assert_eq!(
+        "C:\\foo\\bar\\scripts\\foo.ps1",
+        extract_command_path(base_path, "./scripts/foo.ps1")
+    );
+}
@@ -0,0 +1,158 @@
+use crate::models::prelude::{ModelMetadata, V1AlphaPolicy};
+use crate::models::HelpMetadata;
+use crate::prelude::ReportUploadLocationDestination;
+use glob::Pattern;
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Policy {
+    pub full_name: String,
+    pub metadata: ModelMetadata,
+    pub mandatory: Vec<String>,
+    pub require_confirmation: Vec<String>,
+    pub report_destinations: Option<Vec<Pattern>>,
+}
+
+/// Something a `ScopePolicy` doesn't allow.
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum PolicyViolation {
+    #[error("{policy} doesn't allow reports to be uploaded to {destination}")]
+    ReportDestinationNotAllowed { policy: String, destination: String },
+    #[error("{policy} requires the fix for {action} to be confirmed, ignoring --auto-approve")]
+    AutoApproveNotAllowed { policy: String, action: String },
+    #[error("{policy} makes {selector} mandatory, it runs even though it wasn't selected")]
+    MandatorySkipped { policy: String, selector: String },
+}
+
+impl Policy {
+    /// `true` when one of `selectors` names `group`, or `action` in `group`. Without an
+    /// `action`, only selectors for the whole group match.
+    pub(crate) fn selects(selectors: &[String], group: &str, action: Option<&str>) -> bool {
+        selectors
+            .iter()
+            .any(|selector| match (selector.split_once('/'), action) {
+                (None, _) => selector == group,
+                (Some((g, a)), Some(action)) => g == group && a == action,
+                (Some(_), None) => false,
+            })
+    }
+
+    /// `true` when `group`, or `action` in `group`, has to run.
+    pub fn is_mandatory(&self, group: &str, action: Option<&str>) -> bool {
+        Self::selects(&self.mandatory, group, action)
+    }
+
+    /// `true` when the fix for `action` in `group` has to be confirmed.
+    pub fn requires_confirmation(&self, group: &str, action: &str) -> bool {
+        Self::selects(&self.require_confirmation, group, Some(action))
+    }
+
+    /// The violation when reports may not be uploaded to `destination`.
+    pub fn check_report_destination(
+        &self,
+        destination: &ReportUploadLocationDestination,
+    ) -> Result<(), PolicyViolation> {
+        let Some(patterns) = &self.report_destinations else {
+            return Ok(());
+        };
+        let target = destination.target();
+        if patterns.iter().any(|pattern| pattern.matches(&target)) {
+            Ok(())
+        } else {
+            Err(PolicyViolation::ReportDestinationNotAllowed {
+                policy: self.full_name.clone(),
+                destination: target,
+            })
+        }
+    }
+}
+
+impl HelpMetadata for Policy {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl TryFrom<V1AlphaPolicy> for Policy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaPolicy) -> Result<Self, Self::Error> {
+        let report_destinations = value
+            .spec
+            .report_destinations
+            .as_ref()
+            .map(|patterns| {
+                patterns
+                    .iter()
+                    .map(|pattern| Pattern::new(pattern))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        Ok(Policy {
+            full_name: value.full_name(),
+            metadata: value.metadata,
+            mandatory: value.spec.mandatory,
+            require_confirmation: value.spec.require_confirmation,
+            report_destinations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::ReportUploadLocationDestination;
+    use crate::shared::models::parse_models_from_string;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_scope_policy() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopePolicy
+metadata:
+  name: company
+spec:
+  mandatory:
+    - security-tools
+    - git/commit-signing
+  requireConfirmation:
+    - database
+  reportDestinations:
+    - 'https://github.com/example-org/*'";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        assert_eq!(1, configs.len());
+        let policy = configs[0].get_policy().unwrap();
+
+        assert_eq!("ScopePolicy/company", policy.full_name);
+        assert!(policy.is_mandatory("security-tools", None));
+        assert!(policy.is_mandatory("security-tools", Some("scan")));
+        assert!(!policy.is_mandatory("git", None));
+        assert!(policy.is_mandatory("git", Some("commit-signing")));
+        assert!(!policy.is_mandatory("git", Some("hooks")));
+        assert!(policy.requires_confirmation("database", "migrate"));
+        assert!(!policy.requires_confirmation("git", "commit-signing"));
+
+        let allowed = ReportUploadLocationDestination::GithubIssue {
+            owner: "example-org".to_string(),
+            repo: "dev-support".to_string(),
+            tags: Vec::new(),
+        };
+        assert!(policy.check_report_destination(&allowed).is_ok());
+        let refused = ReportUploadLocationDestination::RustyPaste {
+            url: "https://paste.example.com".to_string(),
+        };
+        assert_eq!(
+            "ScopePolicy/company doesn't allow reports to be uploaded to https://paste.example.com",
+            policy
+                .check_report_destination(&refused)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
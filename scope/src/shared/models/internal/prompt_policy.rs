@@ -0,0 +1,156 @@
+use super::policy::Policy;
+use crate::models::prelude::{ModelMetadata, V1AlphaPromptPolicy};
+use crate::models::HelpMetadata;
+use crate::shared::prelude::{DoctorGroup, DoctorGroupAction, PromptDecision};
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PromptPolicy {
+    pub full_name: String,
+    pub metadata: ModelMetadata,
+    pub rules: Vec<PromptRule>,
+}
+
+/// Which fixes a rule applies to, every field that isn't empty has to match.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PromptRule {
+    pub actions: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    pub decision: PromptDecision,
+}
+
+impl PromptRule {
+    /// `true` when the rule applies to the fix of `action` in `group`.
+    pub fn matches(&self, group: &DoctorGroup, action: &DoctorGroupAction) -> bool {
+        let group_name = group.metadata.name.as_str();
+        (self.actions.is_empty() || Policy::selects(&self.actions, group_name, Some(&action.name)))
+            && contains_all(&action.labels, &self.labels)
+            && contains_all(&group.metadata.annotations.extra, &self.annotations)
+    }
+}
+
+fn contains_all(values: &BTreeMap<String, String>, wanted: &BTreeMap<String, String>) -> bool {
+    wanted
+        .iter()
+        .all(|(key, value)| values.get(key) == Some(value))
+}
+
+impl PromptPolicy {
+    /// How the prompt of the fix of `action` in `group` is answered, from the first rule that
+    /// matches. `None` when no rule does.
+    pub fn decision(
+        &self,
+        group: &DoctorGroup,
+        action: &DoctorGroupAction,
+    ) -> Option<PromptDecision> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(group, action))
+            .map(|rule| rule.decision)
+    }
+}
+
+impl HelpMetadata for PromptPolicy {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl TryFrom<V1AlphaPromptPolicy> for PromptPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaPromptPolicy) -> Result<Self, Self::Error> {
+        let full_name = value.full_name();
+        let rules = value
+            .spec
+            .rules
+            .into_iter()
+            .map(|rule| PromptRule {
+                actions: rule.actions,
+                labels: rule.labels,
+                annotations: rule.annotations,
+                decision: rule.decision.into(),
+            })
+            .collect();
+        Ok(PromptPolicy {
+            full_name,
+            metadata: value.metadata,
+            rules,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::{DoctorGroupAction, PromptDecision};
+    use crate::shared::models::parse_models_from_string;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_scope_prompt_policy() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopePromptPolicy
+metadata:
+  name: fixes
+spec:
+  rules:
+    - labels:
+        touches: credentials
+      decision: ask
+    - actions:
+        - database/reset
+      decision: deny
+    - annotations:
+        owner: platform
+      labels:
+        risk: safe
+      decision: approve
+---
+apiVersion: scope.github.com/v1alpha
+kind: ScopeDoctorGroup
+metadata:
+  name: database
+  labels:
+    risk: safe
+  annotations:
+    owner: platform
+spec:
+  actions:
+    - name: migrate
+      fix:
+        commands:
+          - ./migrate.sh
+    - name: reset
+      fix:
+        commands:
+          - ./reset.sh
+    - name: credentials
+      labels:
+        touches: credentials
+      fix:
+        commands:
+          - ./login.sh";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        assert_eq!(2, configs.len());
+        let policy = configs[0].get_prompt_policy().unwrap();
+        let group = configs[1].get_doctor_group().unwrap();
+
+        assert_eq!("ScopePromptPolicy/fixes", policy.full_name);
+        let decision = |action: &DoctorGroupAction| policy.decision(&group, action);
+        assert_eq!(Some(PromptDecision::Approve), decision(&group.actions[0]));
+        assert_eq!(Some(PromptDecision::Deny), decision(&group.actions[1]));
+        assert_eq!(Some(PromptDecision::Ask), decision(&group.actions[2]));
+
+        let mut other = group.clone();
+        other.metadata.annotations.extra.clear();
+        assert_eq!(None, policy.decision(&other, &other.actions[0]));
+    }
+}
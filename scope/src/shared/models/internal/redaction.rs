@@ -0,0 +1,86 @@
+use crate::models::prelude::{ModelMetadata, V1AlphaRedaction};
+use crate::models::HelpMetadata;
+use derivative::Derivative;
+use regex::Regex;
+
+#[derive(Derivative)]
+#[derivative(PartialEq)]
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    pub full_name: String,
+    pub metadata: ModelMetadata,
+    pub patterns: Vec<String>,
+    #[derivative(PartialEq = "ignore")]
+    pub regexes: Vec<Regex>,
+}
+
+impl HelpMetadata for Redaction {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl TryFrom<V1AlphaRedaction> for Redaction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaRedaction) -> Result<Self, Self::Error> {
+        let regexes = value
+            .spec
+            .patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Redaction {
+            full_name: value.full_name(),
+            metadata: value.metadata,
+            patterns: value.spec.patterns,
+            regexes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::models::parse_models_from_string;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_scope_redaction() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeRedaction
+metadata:
+  name: internal-tokens
+spec:
+  patterns:
+    - 'itk_[A-Za-z0-9]{32}'";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        assert_eq!(1, configs.len());
+        let model = configs[0].get_redaction().unwrap();
+
+        assert_eq!("ScopeRedaction/internal-tokens", model.full_name);
+        assert_eq!(vec!["itk_[A-Za-z0-9]{32}".to_string()], model.patterns);
+        assert!(model.regexes[0].is_match("itk_0123456789abcdef0123456789abcdef"));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_an_error() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeRedaction
+metadata:
+  name: broken
+spec:
+  patterns:
+    - 'itk_[A-Z'";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        assert!(parse_models_from_string(work_dir, path, text).is_err());
+    }
+}
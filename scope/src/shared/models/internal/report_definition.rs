@@ -0,0 +1,167 @@
+use super::upload_location::{load_template_files, ReportAttachments};
+use crate::models::prelude::{ModelMetadata, ReportDestinationTemplates, V1AlphaReportDefinition};
+use crate::models::HelpMetadata;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportDefinition {
+    pub metadata: ModelMetadata,
+    pub full_name: String,
+    pub templates: ReportDestinationTemplates,
+    pub additional_data: BTreeMap<String, String>,
+    pub attachments: Option<ReportAttachments>,
+}
+
+impl HelpMetadata for ReportDefinition {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl TryFrom<V1AlphaReportDefinition> for ReportDefinition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaReportDefinition) -> Result<Self, Self::Error> {
+        let full_name = value.full_name();
+        let templates_dir = value.metadata.containing_dir_of("spec.templates");
+        let attachments_dir = value.metadata.containing_dir_of("spec.attachments");
+        let templates = load_template_files(value.spec.templates, Path::new(&templates_dir))?;
+        Ok(ReportDefinition {
+            full_name,
+            metadata: value.metadata,
+            templates,
+            additional_data: value.spec.additional_data,
+            attachments: value
+                .spec
+                .attachments
+                .map(|spec| ReportAttachments::from_spec(spec, Path::new(&attachments_dir))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::models::parse_models_from_string;
+    use crate::shared::prelude::{ReportAttachments, ReportTemplates};
+    use std::collections::BTreeMap;
+    use std::path::Path;
+
+    #[test]
+    fn test_location_overrides_report_definition() {
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeReportDefinition
+metadata:
+  name: team-report
+spec:
+  additionalData:
+    username: id -un
+    host: hostname
+  templates:
+    title: Team {{ entrypoint }}
+    analyze: team analyze
+  attachments:
+    logs:
+      - logs/*.log
+    config: false
+---
+apiVersion: scope.github.com/v1alpha
+kind: ScopeReportLocation
+metadata:
+  name: local
+spec:
+  reportDefinition: team-report
+  additionalData:
+    host: uname -n
+  templates:
+    analyze: local analyze
+  destination:
+    local:
+      directory: /tmp/scope";
+
+        let path = Path::new("/foo/bar/file.yaml");
+        let work_dir = Path::new("/foo/bar");
+        let configs = parse_models_from_string(work_dir, path, text).unwrap();
+        assert_eq!(2, configs.len());
+
+        let definition = configs[0].get_report_definition().unwrap();
+        assert_eq!("ScopeReportDefinition/team-report", definition.full_name);
+
+        let mut location = configs[1].get_report_upload_spec().unwrap();
+        assert_eq!(Some("team-report".to_string()), location.report_definition);
+        location.apply_definition(&definition).unwrap();
+
+        let ctx = BTreeMap::from([("entrypoint", "scope doctor")]);
+        assert_eq!(
+            "Team scope doctor",
+            location.templates.render_title(&ctx).unwrap()
+        );
+        assert_eq!(
+            "local analyze",
+            location.templates.render_analyze(&ctx).unwrap()
+        );
+        assert_eq!(
+            BTreeMap::from([
+                ("host".to_string(), "uname -n".to_string()),
+                ("username".to_string(), "id -un".to_string()),
+            ]),
+            location.additional_data
+        );
+        assert_eq!(
+            Some(ReportAttachments {
+                logs: vec!["/foo/bar/logs/*.log".to_string()],
+                config: false,
+                doctor_result: true,
+            }),
+            location.attachments
+        );
+    }
+
+    #[test]
+    fn test_templates_are_read_from_files_next_to_the_config() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        std::fs::create_dir(dir.path().join("templates")).unwrap();
+        std::fs::write(
+            dir.path().join("templates/doctor.md.jinja"),
+            "doctor report for {{ entrypoint }}",
+        )
+        .unwrap();
+        let text = "apiVersion: scope.github.com/v1alpha
+kind: ScopeReportDefinition
+metadata:
+  name: team-report
+spec:
+  templates:
+    doctor:
+      templateFile: templates/doctor.md.jinja
+    analyze:
+      template: analyze report for {{ entrypoint }}";
+
+        let path = dir.path().join("report.yaml");
+        let configs = parse_models_from_string(dir.path(), &path, text).unwrap();
+        let definition = configs[0].get_report_definition().unwrap();
+        let templates = ReportTemplates::try_from(definition.templates).unwrap();
+
+        let ctx = BTreeMap::from([("entrypoint", "scope doctor")]);
+        assert_eq!(
+            "doctor report for scope doctor",
+            templates.render_doctor(&ctx).unwrap()
+        );
+        assert_eq!(
+            "analyze report for scope doctor",
+            templates.render_analyze(&ctx).unwrap()
+        );
+
+        let missing = text.replace("templates/doctor.md.jinja", "templates/missing.md.jinja");
+        assert!(parse_models_from_string(dir.path(), &path, &missing).is_err());
+        let both = text.replace(
+            "      template: analyze",
+            "      templateFile: templates/doctor.md.jinja\n      template: analyze",
+        );
+        assert!(parse_models_from_string(dir.path(), &path, &both).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::models::prelude::{
+    ModelMetadata, ScopeConfigColorSpec, ScopeConfigInterceptSpec, V1AlphaScopeConfig,
+};
+use crate::models::HelpMetadata;
+use crate::shared::models::internal::resolve_cache_dir_template;
+use crate::shared::prelude::ColorChoice;
+use glob::Pattern;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScopeConfig {
+    pub metadata: ModelMetadata,
+    pub full_name: String,
+    pub color: Option<ColorChoice>,
+    pub cache_dir: Option<PathBuf>,
+    pub intercept: InterceptFilter,
+    pub default_args: BTreeMap<String, Vec<String>>,
+}
+
+/// Decides which commands `scope-intercept` analyzes.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InterceptFilter {
+    pub include: Vec<Pattern>,
+    pub exclude: Vec<Pattern>,
+    pub ignore_exit_codes: Vec<(Pattern, Vec<i32>)>,
+    /// Executables the shell hooks wrap with `scope-intercept`.
+    pub commands: Vec<String>,
+}
+
+impl InterceptFilter {
+    /// `true` when a failure of `command` should be analyzed.
+    pub fn should_analyze(&self, command: &[String]) -> bool {
+        let included =
+            self.include.is_empty() || self.include.iter().any(|p| Self::matches(p, command));
+        included && !self.exclude.iter().any(|p| Self::matches(p, command))
+    }
+
+    /// `true` when `exit_code` is configured to be ignored for `command`.
+    pub fn is_ignored_exit_code(&self, command: &[String], exit_code: i32) -> bool {
+        self.ignore_exit_codes
+            .iter()
+            .any(|(p, codes)| codes.contains(&exit_code) && Self::matches(p, command))
+    }
+
+    fn matches(pattern: &Pattern, command: &[String]) -> bool {
+        let Some(utility) = command.first() else {
+            return false;
+        };
+        let file_name = Path::new(utility)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| utility.to_string());
+
+        pattern.matches(&file_name) || pattern.matches(&command.join(" "))
+    }
+}
+
+impl TryFrom<ScopeConfigInterceptSpec> for InterceptFilter {
+    type Error = anyhow::Error;
+
+    fn try_from(value: ScopeConfigInterceptSpec) -> Result<Self, Self::Error> {
+        let patterns = |patterns: Vec<String>| -> Result<Vec<Pattern>, glob::PatternError> {
+            patterns.iter().map(|p| Pattern::new(p)).collect()
+        };
+        // The names become shell functions, so they can't have anything a shell would interpret.
+        if let Some(command) = value.commands.iter().find(|command| {
+            command.is_empty()
+                || !command
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "._+-".contains(c))
+        }) {
+            return Err(anyhow::anyhow!(
+                "intercept command `{}` must be the name of an executable, like `bundle`",
+                command
+            ));
+        }
+
+        Ok(InterceptFilter {
+            include: patterns(value.include)?,
+            exclude: patterns(value.exclude)?,
+            ignore_exit_codes: value
+                .ignore_exit_codes
+                .into_iter()
+                .map(|ignore| Ok((Pattern::new(&ignore.command)?, ignore.exit_codes)))
+                .collect::<Result<_, glob::PatternError>>()?,
+            commands: value.commands,
+        })
+    }
+}
+
+impl HelpMetadata for ScopeConfig {
+    fn metadata(&self) -> &ModelMetadata {
+        &self.metadata
+    }
+
+    fn full_name(&self) -> String {
+        self.full_name.to_string()
+    }
+}
+
+impl From<ScopeConfigColorSpec> for ColorChoice {
+    fn from(value: ScopeConfigColorSpec) -> Self {
+        match value {
+            ScopeConfigColorSpec::Auto => ColorChoice::Auto,
+            ScopeConfigColorSpec::Always => ColorChoice::Always,
+            ScopeConfigColorSpec::Never => ColorChoice::Never,
+        }
+    }
+}
+
+impl TryFrom<V1AlphaScopeConfig> for ScopeConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(value: V1AlphaScopeConfig) -> Result<Self, Self::Error> {
+        let cache_dir = match &value.spec.cache_dir {
+            Some(cache_dir) => Some(resolve_cache_dir_template(
+                Path::new(&value.metadata.containing_dir_of("spec.cacheDir")),
+                value
+                    .metadata
+                    .annotations
+                    .working_dir
+                    .as_deref()
+                    .unwrap_or_default(),
+                cache_dir,
+            )?),
+            None => None,
+        };
+
+        Ok(ScopeConfig {
+            full_name: value.full_name(),
+            metadata: value.metadata,
+            color: value.spec.color.map(ColorChoice::from),
+            cache_dir,
+            intercept: InterceptFilter::try_from(value.spec.intercept)?,
+            default_args: value.spec.default_args,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::prelude::ScopeConfigIgnoreExitCodesSpec;
+
+    fn command(line: &str) -> Vec<String> {
+        line.split(' ').map(|x| x.to_string()).collect()
+    }
+
+    fn filter() -> InterceptFilter {
+        InterceptFilter::try_from(ScopeConfigInterceptSpec {
+            include: vec!["cargo".to_string(), "npm".to_string()],
+            exclude: vec!["cargo fmt*".to_string()],
+            ignore_exit_codes: vec![ScopeConfigIgnoreExitCodesSpec {
+                command: "npm".to_string(),
+                exit_codes: vec![130],
+            }],
+            commands: vec!["bundle".to_string()],
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_default_filter_analyzes_everything() {
+        let filter = InterceptFilter::default();
+        assert!(filter.should_analyze(&command("make build")));
+        assert!(!filter.is_ignored_exit_code(&command("make build"), 1));
+    }
+
+    #[test]
+    fn test_include_and_exclude() {
+        let filter = filter();
+        assert!(filter.should_analyze(&command("cargo build")));
+        assert!(filter.should_analyze(&command("/usr/local/bin/npm install")));
+        assert!(!filter.should_analyze(&command("make build")));
+        assert!(!filter.should_analyze(&command("cargo fmt --check")));
+    }
+
+    #[test]
+    fn test_ignored_exit_codes() {
+        let filter = filter();
+        assert!(filter.is_ignored_exit_code(&command("npm test"), 130));
+        assert!(!filter.is_ignored_exit_code(&command("npm test"), 1));
+        assert!(!filter.is_ignored_exit_code(&command("cargo test"), 130));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = InterceptFilter::try_from(ScopeConfigInterceptSpec {
+            include: vec!["cargo[".to_string()],
+            ..Default::default()
+        });
+        assert!(result.is_err());
+
+        let result = InterceptFilter::try_from(ScopeConfigInterceptSpec {
+            commands: vec!["bundle install".to_string()],
+            ..Default::default()
+        });
+        assert!(result.is_err());
+    }
+}
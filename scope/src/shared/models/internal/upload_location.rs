@@ -1,10 +1,20 @@
-use crate::models::prelude::{ModelMetadata, V1AlphaReportLocation};
+use crate::models::prelude::{
+    ModelMetadata, ReportAttachmentsSpec, ReportRateLimitSpec, S3ServerSideEncryption,
+    V1AlphaReportLocation,
+};
 use crate::models::HelpMetadata;
-use crate::prelude::{ReportDestinationSpec, ReportDestinationTemplates};
+use crate::prelude::{
+    PolicyViolation, ReportDefinition, ReportDestinationSpec, ReportDestinationTemplates,
+    ReportTemplateSourceSpec, ReportTemplateSpec,
+};
+use crate::shared::templates::add_template_filters;
+use anyhow::{anyhow, bail};
 use derivative::Derivative;
 use minijinja::Environment;
 use serde::Serialize;
 use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum ReportUploadLocationDestination {
@@ -19,6 +29,75 @@ pub enum ReportUploadLocationDestination {
     Local {
         destination: String,
     },
+    S3 {
+        bucket: String,
+        prefix: Option<String>,
+        region: Option<String>,
+        server_side_encryption: Option<S3ServerSideEncryption>,
+        kms_key_id: Option<String>,
+    },
+    Webhook {
+        url: String,
+        headers: BTreeMap<String, String>,
+        payload: Option<String>,
+    },
+}
+
+impl ReportUploadLocationDestination {
+    /// Largest report body the destination accepts, longer reports are truncated.
+    pub fn max_body_len(&self) -> Option<usize> {
+        match self {
+            // GitHub rejects issues with a body over 65536 characters.
+            ReportUploadLocationDestination::GithubIssue { .. } => Some(65536),
+            _ => None,
+        }
+    }
+
+    /// Where reports end up, as a url. This is what `ScopePolicy` `reportDestinations` match.
+    pub fn target(&self) -> String {
+        match self {
+            ReportUploadLocationDestination::RustyPaste { url } => url.clone(),
+            ReportUploadLocationDestination::GithubIssue { owner, repo, .. } => {
+                format!("https://github.com/{}/{}", owner, repo)
+            }
+            ReportUploadLocationDestination::Local { destination } => {
+                format!("file://{}", destination)
+            }
+            ReportUploadLocationDestination::S3 { bucket, prefix, .. } => format!(
+                "s3://{}/{}",
+                bucket,
+                prefix.as_deref().unwrap_or_default().trim_matches('/')
+            ),
+            ReportUploadLocationDestination::Webhook { url, .. } => url.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for ReportUploadLocationDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportUploadLocationDestination::RustyPaste { url } => {
+                write!(f, "RustyPaste at {}", url)
+            }
+            ReportUploadLocationDestination::GithubIssue { owner, repo, tags } => {
+                write!(f, "GitHub issue in {}/{}", owner, repo)?;
+                if !tags.is_empty() {
+                    write!(f, " with labels {}", tags.join(", "))?;
+                }
+                Ok(())
+            }
+            ReportUploadLocationDestination::Local { destination } => {
+                write!(f, "local file in {}", destination)
+            }
+            ReportUploadLocationDestination::S3 { bucket, prefix, .. } => match prefix {
+                Some(prefix) => write!(f, "S3 bucket {} under {}", bucket, prefix),
+                None => write!(f, "S3 bucket {}", bucket),
+            },
+            ReportUploadLocationDestination::Webhook { url, .. } => {
+                write!(f, "webhook at {}", url)
+            }
+        }
+    }
 }
 
 #[derive(Derivative)]
@@ -51,6 +130,7 @@ impl Default for ReportTemplates {
 impl ReportTemplates {
     fn make_env<'a>(&self) -> anyhow::Result<Environment<'a>> {
         let mut env = Environment::new();
+        add_template_filters(&mut env);
         env.set_trim_blocks(true);
         env.set_lstrip_blocks(true);
 
@@ -127,16 +207,109 @@ impl TryFrom<ReportDestinationTemplates> for ReportTemplates {
             title_template: inputs
                 .title
                 .unwrap_or_else(ReportTemplates::default_title_template),
-            doctor_template: inputs
-                .doctor
+            doctor_template: inline_template(inputs.doctor)?
                 .unwrap_or_else(ReportTemplates::default_doctor_template),
-            analyze_template: inputs
-                .analyze
+            analyze_template: inline_template(inputs.analyze)?
                 .unwrap_or_else(ReportTemplates::default_command_template),
         })
     }
 }
 
+/// Read the `templateFile` of the `doctor` and `analyze` templates, relative to `base_dir`, so
+/// every template is inline by the time a location and its definition are combined.
+pub(crate) fn load_template_files(
+    templates: ReportDestinationTemplates,
+    base_dir: &Path,
+) -> anyhow::Result<ReportDestinationTemplates> {
+    let load = |template: Option<ReportTemplateSpec>| -> anyhow::Result<_> {
+        let Some(ReportTemplateSpec::Source(source)) = template else {
+            return Ok(template);
+        };
+        let text = match (source.template, source.template_file) {
+            (Some(template), None) => template,
+            (None, Some(file)) => {
+                let path = base_dir.join(file);
+                std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("unable to read template {}. {}", path.display(), e))?
+            }
+            (Some(_), Some(_)) => bail!("a template can't set both `template` and `templateFile`"),
+            (None, None) => bail!("a template must set `template` or `templateFile`"),
+        };
+        Ok(Some(ReportTemplateSpec::Inline(text)))
+    };
+
+    Ok(ReportDestinationTemplates {
+        doctor: load(templates.doctor)?,
+        analyze: load(templates.analyze)?,
+        ..templates
+    })
+}
+
+fn inline_template(template: Option<ReportTemplateSpec>) -> anyhow::Result<Option<String>> {
+    match template {
+        None => Ok(None),
+        Some(ReportTemplateSpec::Inline(template))
+        | Some(ReportTemplateSpec::Source(ReportTemplateSourceSpec {
+            template: Some(template),
+            template_file: None,
+        })) => Ok(Some(template)),
+        Some(ReportTemplateSpec::Source(_)) => {
+            bail!("template files must be loaded with `load_template_files` first")
+        }
+    }
+}
+
+/// Limits on uploads to a location, from `rateLimit`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportRateLimit {
+    pub max_concurrent: usize,
+    /// Time between the start of two uploads, `None` when uploads aren't spaced out.
+    pub min_interval: Option<Duration>,
+    pub max_retries: u32,
+}
+
+impl Default for ReportRateLimit {
+    fn default() -> Self {
+        Self::from(&ReportRateLimitSpec::default())
+    }
+}
+
+impl From<&ReportRateLimitSpec> for ReportRateLimit {
+    fn from(spec: &ReportRateLimitSpec) -> Self {
+        Self {
+            max_concurrent: spec.max_concurrent.max(1) as usize,
+            min_interval: spec
+                .per_minute
+                .filter(|per_minute| *per_minute > 0)
+                .map(|per_minute| Duration::from_secs(60) / per_minute),
+            max_retries: spec.max_retries,
+        }
+    }
+}
+
+/// Files bundled with a report, from the `attachments` of a `ScopeReportDefinition`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ReportAttachments {
+    /// Glob patterns of log files, relative patterns are joined to the definition's directory.
+    pub logs: Vec<String>,
+    pub config: bool,
+    pub doctor_result: bool,
+}
+
+impl ReportAttachments {
+    pub(crate) fn from_spec(spec: ReportAttachmentsSpec, base_dir: &Path) -> Self {
+        Self {
+            logs: spec
+                .logs
+                .iter()
+                .map(|pattern| base_dir.join(pattern).display().to_string())
+                .collect(),
+            config: spec.config,
+            doctor_result: spec.doctor_result,
+        }
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(PartialEq)]
 #[derive(Debug, Clone)]
@@ -146,6 +319,32 @@ pub struct ReportUploadLocation {
     pub destination: ReportUploadLocationDestination,
     pub templates: ReportTemplates,
     pub additional_data: BTreeMap<String, String>,
+    pub report_definition: Option<String>,
+    pub template_overrides: ReportDestinationTemplates,
+    /// Set when a `ScopePolicy` doesn't allow uploads to this location.
+    pub policy_violation: Option<PolicyViolation>,
+    pub rate_limit: ReportRateLimit,
+    /// Files bundled with reports, from the location's `ScopeReportDefinition`.
+    pub attachments: Option<ReportAttachments>,
+}
+
+impl ReportUploadLocation {
+    /// Apply a `ScopeReportDefinition` to this location. The templates defined on the location
+    /// take precedence over the ones from the definition, additional data is merged, and the
+    /// definition's attachments are used.
+    pub fn apply_definition(&mut self, definition: &ReportDefinition) -> anyhow::Result<()> {
+        let templates = definition
+            .templates
+            .with_overrides(&self.template_overrides);
+        self.templates = ReportTemplates::try_from(templates)?;
+
+        let mut additional_data = definition.additional_data.clone();
+        additional_data.extend(self.additional_data.clone());
+        self.additional_data = additional_data;
+        self.attachments = definition.attachments.clone();
+
+        Ok(())
+    }
 }
 
 impl HelpMetadata for ReportUploadLocation {
@@ -178,15 +377,39 @@ impl TryFrom<V1AlphaReportLocation> for ReportUploadLocation {
             ReportDestinationSpec::Local(ref loc) => ReportUploadLocationDestination::Local {
                 destination: loc.directory.clone(),
             },
+            ReportDestinationSpec::S3(ref s3) => ReportUploadLocationDestination::S3 {
+                bucket: s3.bucket.clone(),
+                prefix: s3.prefix.clone(),
+                region: s3.region.clone(),
+                server_side_encryption: s3.server_side_encryption,
+                kms_key_id: s3.kms_key_id.clone(),
+            },
+            ReportDestinationSpec::Webhook(ref webhook) => {
+                ReportUploadLocationDestination::Webhook {
+                    url: webhook.url.clone(),
+                    headers: webhook.headers.clone(),
+                    payload: webhook.payload.clone(),
+                }
+            }
         };
 
-        let report_templates = ReportTemplates::try_from(value.spec.templates.clone())?;
+        let full_name = value.full_name();
+        let templates = load_template_files(
+            value.spec.templates,
+            Path::new(&value.metadata.containing_dir_of("spec.templates")),
+        )?;
+        let report_templates = ReportTemplates::try_from(templates.clone())?;
         Ok(ReportUploadLocation {
-            full_name: value.full_name(),
+            full_name,
             metadata: value.metadata,
             destination,
             templates: report_templates,
             additional_data: value.spec.additional_data,
+            report_definition: value.spec.report_definition,
+            template_overrides: templates,
+            policy_violation: None,
+            rate_limit: ReportRateLimit::from(&value.spec.rate_limit),
+            attachments: None,
         })
     }
 }
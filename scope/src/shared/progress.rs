@@ -0,0 +1,105 @@
+/// How a group or an action ended, see [`ProgressReporter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProgressStatus {
+    Succeeded,
+    Failed,
+    /// An action with `allowFailure` failed, without failing its group.
+    AllowedFailure,
+    /// Nothing ran, because of a failure before it or the group's `schedule`.
+    Skipped,
+    /// Nothing ran, because `platforms` or `onlyIf` excluded this machine.
+    NotApplicable,
+}
+
+impl std::fmt::Display for ProgressStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgressStatus::Succeeded => write!(f, "succeeded"),
+            ProgressStatus::Failed => write!(f, "failed"),
+            ProgressStatus::AllowedFailure => write!(f, "failed, allowed"),
+            ProgressStatus::Skipped => write!(f, "skipped"),
+            ProgressStatus::NotApplicable => write!(f, "not applicable"),
+        }
+    }
+}
+
+/// Told about each step of a doctor run or an analysis as it happens, for tools embedding
+/// `dev-scope` that show progress their own way, like a GUI. Every method does nothing unless
+/// it's implemented, and events are sent along with the usual logs, not instead of them.
+pub trait ProgressReporter: Send + Sync {
+    /// `group` is about to run its actions.
+    fn start_group(&self, _group: &str) {}
+
+    /// `group` is done. Groups that are skipped finish without starting.
+    fn finish_group(&self, _group: &str, _status: ProgressStatus) {}
+
+    /// `action` of `group` is about to run its check.
+    fn start_action(&self, _group: &str, _action: &str) {}
+
+    /// `action` of `group` is done. Actions that are skipped finish without starting.
+    fn finish_action(&self, _group: &str, _action: &str, _status: ProgressStatus) {}
+
+    /// `command` is about to run, like a check, a fix, or the command `scope analyze` reads.
+    fn start_command(&self, _command: &str) {}
+
+    /// A line written by the command that is running, or read by `scope analyze`.
+    fn output_line(&self, _line: &str) {}
+
+    /// The user is about to be asked whether to run a fix.
+    fn fix_prompt(&self, _prompt: &str) {}
+}
+
+/// Reports nothing, used when no one is listening.
+#[derive(Debug, Default)]
+pub struct NoOpProgressReporter;
+
+impl ProgressReporter for NoOpProgressReporter {}
+
+/// Keeps every event as a line of text, so tests can check what was reported.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct RecordingProgressReporter {
+    pub(crate) events: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl RecordingProgressReporter {
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+
+    pub(crate) fn events(&self) -> Vec<String> {
+        self.events.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl ProgressReporter for RecordingProgressReporter {
+    fn start_group(&self, group: &str) {
+        self.record(format!("start group {}", group));
+    }
+
+    fn finish_group(&self, group: &str, status: ProgressStatus) {
+        self.record(format!("finish group {} {}", group, status));
+    }
+
+    fn start_action(&self, group: &str, action: &str) {
+        self.record(format!("start action {}/{}", group, action));
+    }
+
+    fn finish_action(&self, group: &str, action: &str, status: ProgressStatus) {
+        self.record(format!("finish action {}/{} {}", group, action, status));
+    }
+
+    fn start_command(&self, command: &str) {
+        self.record(format!("command {}", command));
+    }
+
+    fn output_line(&self, line: &str) {
+        self.record(format!("output {}", line));
+    }
+
+    fn fix_prompt(&self, prompt: &str) {
+        self.record(format!("prompt {}", prompt));
+    }
+}
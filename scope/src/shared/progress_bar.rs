@@ -0,0 +1,48 @@
+//! Progress bars drawn for spans with `indicatif.pb_show`. Without the `interactive` feature
+//! there are no progress bars, and setting one up on a span does nothing.
+
+#[cfg(feature = "interactive")]
+pub use indicatif::ProgressStyle;
+#[cfg(feature = "interactive")]
+pub use tracing_indicatif::span_ext::IndicatifSpanExt;
+
+/// Style of the progress bars the console logging draws, see `LoggingOpts`.
+#[cfg(feature = "cli")]
+pub fn default_progress_bar() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{span_child_prefix} {spinner:.green} {wide_msg} {pos:>7}/{len:7} [{elapsed_precise}]",
+    )
+    .unwrap()
+    .progress_chars("##-")
+}
+
+#[cfg(feature = "interactive")]
+pub fn progress_bar_without_pos() -> ProgressStyle {
+    ProgressStyle::with_template(
+        "{span_child_prefix} {spinner:.green} {wide_msg} [{elapsed_precise}]",
+    )
+    .unwrap()
+    .progress_chars("##-")
+}
+
+/// Stands in for the style of a progress bar when there are no progress bars.
+#[cfg(not(feature = "interactive"))]
+#[derive(Debug, Clone, Default)]
+pub struct ProgressStyle;
+
+#[cfg(not(feature = "interactive"))]
+pub fn progress_bar_without_pos() -> ProgressStyle {
+    ProgressStyle
+}
+
+/// The progress bar methods of a span that `tracing-indicatif` would add, doing nothing.
+#[cfg(not(feature = "interactive"))]
+pub trait IndicatifSpanExt {
+    fn pb_set_style(&self, _style: &ProgressStyle) {}
+    fn pb_set_length(&self, _len: u64) {}
+    fn pb_set_message(&self, _msg: &str) {}
+    fn pb_inc(&self, _delta: u64) {}
+}
+
+#[cfg(not(feature = "interactive"))]
+impl IndicatifSpanExt for tracing::Span {}
@@ -0,0 +1,104 @@
+//! Pseudo-terminals for commands that change their output when it isn't a terminal, like
+//! leaving out colors or buffering it differently.
+
+use super::console::child_stdin;
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::pty::{openpty, Winsize};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::task::{ready, Context, Poll};
+use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Size of the terminal commands see, the usual size of a new terminal window.
+const PTY_ROWS: u16 = 24;
+const PTY_COLS: u16 = 80;
+
+/// A pseudo-terminal for a single command.
+pub(super) struct Pty {
+    /// Side the command's output is read from.
+    master: OwnedFd,
+    /// Side the command's stdout and stderr are attached to.
+    slave: OwnedFd,
+}
+
+impl Pty {
+    pub(super) fn open() -> std::io::Result<Self> {
+        let size = Winsize {
+            ws_row: PTY_ROWS,
+            ws_col: PTY_COLS,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        };
+        let pty = openpty(&size, None)?;
+        Ok(Self {
+            master: pty.master,
+            slave: pty.slave,
+        })
+    }
+
+    /// Attach the command's stdout and stderr to the terminal, and make it the controlling
+    /// terminal of a new session for the command, like a terminal emulator does. Nothing is typed
+    /// into the terminal, so stdin is left as it is for other commands.
+    pub(super) fn attach(&self, command: &mut tokio::process::Command) -> std::io::Result<()> {
+        command
+            .stdin(child_stdin())
+            .stdout(Stdio::from(self.slave.try_clone()?))
+            .stderr(Stdio::from(self.slave.try_clone()?));
+        // SAFETY: `setsid` and `ioctl` are async-signal-safe, and nothing is allocated.
+        unsafe {
+            command.pre_exec(|| {
+                nix::unistd::setsid()?;
+                // Stdout is the terminal by now.
+                if nix::libc::ioctl(1, nix::libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Everything written to the terminal. Once the command is spawned, its side is closed here
+    /// so the output ends when the command, and anything it started, exits.
+    pub(super) fn into_reader(self) -> std::io::Result<PtyReader> {
+        drop(self.slave);
+        let fd = self.master.as_raw_fd();
+        let flags = OFlag::from_bits_truncate(fcntl(fd, FcntlArg::F_GETFL)?);
+        fcntl(fd, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK))?;
+        Ok(PtyReader(AsyncFd::new(self.master)?))
+    }
+}
+
+/// Reads the output of a command from its terminal, without blocking a thread while it waits.
+/// On Linux, reads fail with `EIO` once the command's side is closed, which is the end of the
+/// output, not an error.
+pub(super) struct PtyReader(AsyncFd<OwnedFd>);
+
+impl AsyncRead for PtyReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            let mut guard = ready!(self.0.poll_read_ready(cx))?;
+            let read = guard.try_io(|fd| {
+                nix::unistd::read(fd.as_raw_fd(), buf.initialize_unfilled()).map_err(Into::into)
+            });
+            match read {
+                Ok(Ok(len)) => {
+                    buf.advance(len);
+                    return Poll::Ready(Ok(()));
+                }
+                Ok(Err(e)) if e.raw_os_error() == Some(Errno::EIO as i32) => {
+                    return Poll::Ready(Ok(()))
+                }
+                Ok(Err(e)) => return Poll::Ready(Err(e)),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use crate::shared::prelude::ReportRateLimit;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+/// Longest wait before retrying an upload, whatever the destination asks for.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// The destination refused an upload because too many were sent.
+#[derive(Error, Debug)]
+#[error("{server} is rate limiting uploads")]
+pub struct RateLimited {
+    pub server: &'static str,
+    /// How long the destination asked to wait, from `Retry-After`.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// Read `Retry-After` when it's given in seconds. Dates aren't supported and fall back to
+    /// backing off.
+    pub fn from_response(server: &'static str, response: &reqwest::Response) -> Self {
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+        Self {
+            server,
+            retry_after,
+        }
+    }
+}
+
+/// How long to wait before retry number `attempt`, starting at 0. Uses what the destination
+/// asked for, and otherwise doubles from one second.
+pub fn retry_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt.min(6)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Uploads to one location within this process.
+struct LocationQueue {
+    permits: Arc<Semaphore>,
+    /// Earliest time the next upload may start.
+    next_start: tokio::sync::Mutex<Instant>,
+    waiting: AtomicUsize,
+}
+
+lazy_static! {
+    static ref QUEUES: Mutex<BTreeMap<String, Arc<LocationQueue>>> = Mutex::new(BTreeMap::new());
+}
+
+/// Wait until an upload to `location` is allowed by `limit`. The upload may run while the
+/// returned permit is held.
+pub async fn acquire(location: &str, limit: &ReportRateLimit) -> OwnedSemaphorePermit {
+    let queue = QUEUES
+        .lock()
+        .unwrap()
+        .entry(location.to_string())
+        .or_insert_with(|| {
+            Arc::new(LocationQueue {
+                permits: Arc::new(Semaphore::new(limit.max_concurrent)),
+                next_start: tokio::sync::Mutex::new(Instant::now()),
+                waiting: AtomicUsize::new(0),
+            })
+        })
+        .clone();
+
+    let started = Instant::now();
+    let waiting = queue.waiting.fetch_add(1, Ordering::SeqCst) + 1;
+    debug!(
+        destination.name = location,
+        queue.waiting = waiting,
+        queue.available = queue.permits.available_permits(),
+        "Waiting to upload report"
+    );
+
+    let permit = queue
+        .permits
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("upload queue is never closed");
+    if let Some(interval) = limit.min_interval {
+        let mut next_start = queue.next_start.lock().await;
+        tokio::time::sleep_until(*next_start).await;
+        *next_start = Instant::now() + interval;
+    }
+
+    let waiting = queue.waiting.fetch_sub(1, Ordering::SeqCst) - 1;
+    info!(
+        destination.name = location,
+        queue.waiting = waiting,
+        queue.waited_ms = started.elapsed().as_millis() as u64,
+        "Uploading report"
+    );
+
+    permit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(Duration::from_secs(1), retry_delay(0, None));
+        assert_eq!(Duration::from_secs(4), retry_delay(2, None));
+        assert_eq!(MAX_RETRY_DELAY, retry_delay(10, None));
+        assert_eq!(
+            Duration::from_secs(7),
+            retry_delay(0, Some(Duration::from_secs(7)))
+        );
+        assert_eq!(
+            MAX_RETRY_DELAY,
+            retry_delay(0, Some(Duration::from_secs(3600)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_uploads_are_spaced_out() {
+        let limit = ReportRateLimit {
+            max_concurrent: 2,
+            min_interval: Some(Duration::from_millis(200)),
+            max_retries: 0,
+        };
+        let started = Instant::now();
+
+        let first = acquire("spaced", &limit).await;
+        assert!(started.elapsed() < Duration::from_millis(200));
+        let second = acquire("spaced", &limit).await;
+        assert!(started.elapsed() >= Duration::from_millis(200));
+
+        drop((first, second));
+    }
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, Utc};
+use itertools::Either;
+use std::borrow::Cow;
+use std::io::BufRead;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tempfile::TempPath;
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncSeekExt, AsyncWriteExt, BufWriter, ReadBuf};
+use tracing::debug;
+
+/// Overrides how many bytes of output a stream keeps in memory before the rest is spooled to a
+/// temporary file.
+pub const CAPTURE_SPOOL_BYTES_ENV: &str = "SCOPE_CAPTURE_SPOOL_BYTES";
+const DEFAULT_SPOOL_BYTES: usize = 8 * 1024 * 1024;
+
+/// Bytes of output a stream keeps in memory, `SCOPE_CAPTURE_SPOOL_BYTES` when it's set to a
+/// number, otherwise 8 MiB.
+pub(crate) fn spool_threshold() -> usize {
+    std::env::var(CAPTURE_SPOOL_BYTES_ENV)
+        .ok()
+        .and_then(|bytes| bytes.trim().parse().ok())
+        .unwrap_or(DEFAULT_SPOOL_BYTES)
+}
+
+/// A temporary file only the current user can read, deleted when the last reference to it is
+/// dropped.
+#[derive(Debug)]
+struct SpoolFile {
+    path: TempPath,
+}
+
+impl SpoolFile {
+    /// Create the file, with the file to write it through.
+    fn create() -> std::io::Result<(Self, tokio::fs::File)> {
+        let (file, path) = tempfile::Builder::new()
+            .prefix("scope-capture-")
+            .tempfile()?
+            .into_parts();
+        Ok((Self { path }, tokio::fs::File::from_std(file)))
+    }
+}
+
+/// Lines of one output stream, with the time each was written. Short output is kept in memory,
+/// output past the spool threshold is kept in a temporary file and read back as it's used.
+#[derive(Debug, Clone, Default)]
+pub struct CapturedLines {
+    memory: Vec<(DateTime<Utc>, String)>,
+    spool: Option<Arc<SpoolFile>>,
+    len: usize,
+}
+
+impl From<Vec<(DateTime<Utc>, String)>> for CapturedLines {
+    fn from(lines: Vec<(DateTime<Utc>, String)>) -> Self {
+        Self {
+            len: lines.len(),
+            memory: lines,
+            spool: None,
+        }
+    }
+}
+
+impl CapturedLines {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// True when the lines are in a temporary file instead of memory.
+    pub fn is_spooled(&self) -> bool {
+        self.spool.is_some()
+    }
+
+    /// The lines, in the order they were written. Spooled lines are read from the file one at a
+    /// time, a file that can't be read ends the lines early.
+    pub fn iter(&self) -> impl Iterator<Item = (DateTime<Utc>, Cow<'_, str>)> + '_ {
+        let Some(spool) = &self.spool else {
+            return Either::Left(
+                self.memory
+                    .iter()
+                    .map(|(time, line)| (*time, Cow::Borrowed(line.as_str()))),
+            );
+        };
+
+        let lines = match std::fs::File::open(&spool.path) {
+            Ok(file) => Some(std::io::BufReader::new(file).lines()),
+            Err(e) => {
+                debug!(
+                    "Unable to read spooled output {}. {}",
+                    spool.path.display(),
+                    e
+                );
+                None
+            }
+        };
+        Either::Right(
+            lines
+                .into_iter()
+                .flatten()
+                .map_while(Result::ok)
+                .filter_map(|entry| parse_entry(&entry))
+                .map(|(time, line)| (time, Cow::Owned(line))),
+        )
+    }
+}
+
+/// Each spooled line is written as `<microseconds since the epoch>\t<line>`.
+fn parse_entry(entry: &str) -> Option<(DateTime<Utc>, String)> {
+    let (micros, line) = entry.split_once('\t')?;
+    let time = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+    Some((time, line.to_string()))
+}
+
+/// Collects the lines of one output stream into [`CapturedLines`]. Once the lines are past
+/// `threshold` bytes they're moved to a temporary file, and every line after is written there.
+/// Each line is written before the next one is read, so a command writing faster than the disk
+/// is slowed down by its pipe filling up rather than by memory growing.
+pub(crate) struct LineSpooler {
+    threshold: usize,
+    memory: Vec<(DateTime<Utc>, String)>,
+    memory_bytes: usize,
+    spool: Option<(Arc<SpoolFile>, BufWriter<tokio::fs::File>)>,
+    len: usize,
+}
+
+impl LineSpooler {
+    pub(crate) fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            memory: Vec::new(),
+            memory_bytes: 0,
+            spool: None,
+            len: 0,
+        }
+    }
+
+    /// `line` must not contain a newline, lines read with `lines()` never do.
+    pub(crate) async fn push(&mut self, time: DateTime<Utc>, line: &str) -> std::io::Result<()> {
+        self.len += 1;
+        if let Some((_, writer)) = &mut self.spool {
+            return write_entry(writer, time, line).await;
+        }
+
+        self.memory_bytes += line.len() + 1;
+        self.memory.push((time, line.to_string()));
+        if self.memory_bytes > self.threshold {
+            let (spool, file) = SpoolFile::create()?;
+            let mut writer = BufWriter::new(file);
+            for (time, line) in self.memory.drain(..) {
+                write_entry(&mut writer, time, &line).await?;
+            }
+            self.memory_bytes = 0;
+            self.spool = Some((Arc::new(spool), writer));
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn finish(self) -> std::io::Result<CapturedLines> {
+        let spool = match self.spool {
+            Some((spool, mut writer)) => {
+                writer.flush().await?;
+                Some(spool)
+            }
+            None => None,
+        };
+
+        Ok(CapturedLines {
+            memory: self.memory,
+            spool,
+            len: self.len,
+        })
+    }
+}
+
+async fn write_entry(
+    writer: &mut BufWriter<tokio::fs::File>,
+    time: DateTime<Utc>,
+    line: &str,
+) -> std::io::Result<()> {
+    writer
+        .write_all(format!("{}\t{}\n", time.timestamp_micros(), line).as_bytes())
+        .await
+}
+
+/// Reads text one line at a time, from memory when it's short or from a temporary file when it
+/// was spooled. The file is deleted when the reader is dropped.
+pub struct SpooledReader {
+    source: SpooledSource,
+}
+
+enum SpooledSource {
+    Memory(std::io::Cursor<Vec<u8>>),
+    File {
+        // Declared before `_spool` so the file is closed before it's deleted.
+        reader: tokio::io::BufReader<tokio::fs::File>,
+        _spool: SpoolFile,
+    },
+}
+
+impl SpooledReader {
+    pub(crate) fn from_memory(text: String) -> Self {
+        Self {
+            source: SpooledSource::Memory(std::io::Cursor::new(text.into_bytes())),
+        }
+    }
+
+    /// Spool the lines from `lines` to a temporary file, and read them back from it.
+    pub(crate) async fn from_lines<'a>(
+        lines: impl Iterator<Item = Cow<'a, str>>,
+    ) -> std::io::Result<Self> {
+        let (spool, file) = SpoolFile::create()?;
+        let mut writer = BufWriter::new(file);
+        for line in lines {
+            writer.write_all(line.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+        }
+        writer.flush().await?;
+        let mut file = writer.into_inner();
+        file.rewind().await?;
+
+        Ok(Self {
+            source: SpooledSource::File {
+                reader: tokio::io::BufReader::new(file),
+                _spool: spool,
+            },
+        })
+    }
+}
+
+impl AsyncRead for SpooledReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match &mut self.get_mut().source {
+            SpooledSource::Memory(cursor) => Pin::new(cursor).poll_read(cx, buf),
+            SpooledSource::File { reader, .. } => Pin::new(reader).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncBufRead for SpooledReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        match &mut self.get_mut().source {
+            SpooledSource::Memory(cursor) => Pin::new(cursor).poll_fill_buf(cx),
+            SpooledSource::File { reader, .. } => Pin::new(reader).poll_fill_buf(cx),
+        }
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        match &mut self.get_mut().source {
+            SpooledSource::Memory(cursor) => Pin::new(cursor).consume(amt),
+            SpooledSource::File { reader, .. } => Pin::new(reader).consume(amt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncBufReadExt;
+
+    #[tokio::test]
+    async fn test_lines_past_threshold_are_spooled() {
+        let at = |secs: i64| DateTime::from_timestamp(1715612600 + secs, 0).unwrap();
+        let mut spooler = LineSpooler::new(16);
+        spooler.push(at(0), "short").await.unwrap();
+        spooler.push(at(1), "still short").await.unwrap();
+        spooler.push(at(2), "spooled\twith a tab").await.unwrap();
+        let lines = spooler.finish().await.unwrap();
+
+        assert!(lines.is_spooled());
+        assert_eq!(3, lines.len());
+        assert_eq!(
+            vec![
+                (at(0), "short".to_string()),
+                (at(1), "still short".to_string()),
+                (at(2), "spooled\twith a tab".to_string()),
+            ],
+            lines
+                .iter()
+                .map(|(time, line)| (time, line.into_owned()))
+                .collect::<Vec<_>>()
+        );
+
+        let path = lines.spool.as_ref().unwrap().path.to_path_buf();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+        let copy = lines.clone();
+        drop(lines);
+        assert!(path.exists());
+        drop(copy);
+        assert!(!path.exists());
+
+        let mut spooler = LineSpooler::new(1024);
+        spooler.push(at(0), "short").await.unwrap();
+        assert!(!spooler.finish().await.unwrap().is_spooled());
+    }
+
+    #[tokio::test]
+    async fn test_spooled_reader_reads_lines_back() {
+        let mut reader =
+            SpooledReader::from_lines(["first", "second"].into_iter().map(Cow::Borrowed))
+                .await
+                .unwrap()
+                .lines();
+
+        assert_eq!(Some("first".to_string()), reader.next_line().await.unwrap());
+        assert_eq!(
+            Some("second".to_string()),
+            reader.next_line().await.unwrap()
+        );
+        assert_eq!(None, reader.next_line().await.unwrap());
+    }
+}
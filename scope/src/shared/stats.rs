@@ -0,0 +1,226 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use directories::BaseDirs;
+use fs4::FileExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+
+/// Overrides the file known error statistics are kept in.
+pub const STATS_FILE_ENV: &str = "SCOPE_STATS_FILE";
+const STATS_FILE_NAME: &str = "stats.json";
+
+/// Counts of the known errors `scope analyze` found on this machine, and of how their fixes went.
+/// Only names and counts are kept, nothing about the input that was analyzed, so the file can be
+/// shared to find the errors that come up most.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownErrorStats {
+    /// Counts for each known error, by name.
+    #[serde(default)]
+    pub known_errors: BTreeMap<String, KnownErrorCounts>,
+    /// Counts for each fix, by `group/action`.
+    #[serde(default)]
+    pub fixes: BTreeMap<String, FixCounts>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownErrorCounts {
+    /// Times the known error was found.
+    pub detected: u64,
+    pub last_detected: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FixCounts {
+    pub succeeded: u64,
+    pub failed: u64,
+    /// Times the user was asked and said no.
+    pub declined: u64,
+}
+
+impl FixCounts {
+    pub fn add(&mut self, other: &FixCounts) {
+        self.succeeded += other.succeeded;
+        self.failed += other.failed;
+        self.declined += other.declined;
+    }
+}
+
+impl KnownErrorStats {
+    /// File the statistics are kept in, `SCOPE_STATS_FILE` when set, otherwise `stats.json` in
+    /// the user's cache directory, like `~/.cache/scope/stats.json`.
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(STATS_FILE_ENV).filter(|path| !path.is_empty()) {
+            return PathBuf::from(path);
+        }
+        BaseDirs::new()
+            .map(|dirs| dirs.cache_dir().join("scope"))
+            .unwrap_or_else(|| PathBuf::from("/tmp/scope"))
+            .join(STATS_FILE_NAME)
+    }
+
+    /// The statistics in `path`, empty when the file doesn't exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| anyhow!("{} isn't valid statistics. {}", path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(anyhow!("unable to read {}. {}", path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("unable to write {}. {}", path.display(), e))
+    }
+
+    /// Load the statistics in `path`, change them with `update` and save them again. Statistics
+    /// that can't be read are started over, rather than failing the command that found errors.
+    ///
+    /// Other scope processes may update the file at the same time, so this holds a lock on
+    /// `<path>.lock` until the statistics are saved, waiting for it when it's taken. Call it off
+    /// the async runtime.
+    pub fn update(path: &Path, update: impl FnOnce(&mut Self)) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut lock_path = path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        lock.lock_exclusive()?;
+
+        let mut stats = Self::load(path).unwrap_or_default();
+        update(&mut stats);
+        let saved = stats.save(path);
+        lock.unlock()?;
+        saved
+    }
+
+    /// Add the counts in `other` to these.
+    pub fn add(&mut self, other: &KnownErrorStats) {
+        for (name, other) in &other.known_errors {
+            let counts = self.known_errors.entry(name.clone()).or_default();
+            counts.detected += other.detected;
+            counts.last_detected = counts.last_detected.max(other.last_detected);
+        }
+        for (name, other) in &other.fixes {
+            self.fixes.entry(name.clone()).or_default().add(other);
+        }
+    }
+
+    pub fn record_detected(&mut self, known_error: &str, at: DateTime<Utc>) {
+        let counts = self
+            .known_errors
+            .entry(known_error.to_string())
+            .or_default();
+        counts.detected += 1;
+        counts.last_detected = Some(at);
+    }
+
+    pub fn record_fix_result(&mut self, fix: &str, succeeded: bool) {
+        let counts = self.fixes.entry(fix.to_string()).or_default();
+        if succeeded {
+            counts.succeeded += 1;
+        } else {
+            counts.failed += 1;
+        }
+    }
+
+    pub fn record_fix_declined(&mut self, fix: &str) {
+        self.fixes.entry(fix.to_string()).or_default().declined += 1;
+    }
+
+    /// Known errors, the most often found first. Ties are in name order.
+    pub fn most_detected(&self) -> Vec<(&str, &KnownErrorCounts)> {
+        let mut known_errors: Vec<_> = self
+            .known_errors
+            .iter()
+            .map(|(name, counts)| (name.as_str(), counts))
+            .collect();
+        known_errors.sort_by_key(|(_, counts)| std::cmp::Reverse(counts.detected));
+        known_errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_stats_round_trip_through_disk() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("nested/stats.json");
+        let at = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+        assert_eq!(
+            KnownErrorStats::default(),
+            KnownErrorStats::load(&path).unwrap()
+        );
+
+        KnownErrorStats::update(&path, |stats| {
+            stats.record_detected("disk-full", at);
+            stats.record_detected("auth-expired", at);
+            stats.record_detected("disk-full", at);
+            stats.record_fix_result("disk/clean", true);
+            stats.record_fix_declined("disk/clean");
+        })
+        .unwrap();
+        KnownErrorStats::update(&path, |stats| {
+            stats.record_fix_result("disk/clean", false);
+        })
+        .unwrap();
+
+        let stats = KnownErrorStats::load(&path).unwrap();
+        assert_eq!(
+            vec![("disk-full", 2), ("auth-expired", 1)],
+            stats
+                .most_detected()
+                .into_iter()
+                .map(|(name, counts)| (name, counts.detected))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            FixCounts {
+                succeeded: 1,
+                failed: 1,
+                declined: 1
+            },
+            stats.fixes["disk/clean"]
+        );
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(KnownErrorStats::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_add_sums_counts() {
+        let earlier = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+        let later = Utc.with_ymd_and_hms(2024, 6, 4, 10, 0, 0).unwrap();
+        let mut stats = KnownErrorStats::default();
+        stats.record_detected("disk-full", later);
+        stats.record_fix_result("disk/clean", true);
+
+        let mut other = KnownErrorStats::default();
+        other.record_detected("disk-full", earlier);
+        other.record_detected("auth-expired", earlier);
+        other.record_fix_result("disk/clean", false);
+        stats.add(&other);
+
+        assert_eq!(2, stats.known_errors["disk-full"].detected);
+        assert_eq!(Some(later), stats.known_errors["disk-full"].last_detected);
+        assert_eq!(1, stats.known_errors["auth-expired"].detected);
+        assert_eq!(1, stats.fixes["disk/clean"].succeeded);
+        assert_eq!(1, stats.fixes["disk/clean"].failed);
+    }
+}
@@ -0,0 +1,149 @@
+use minijinja::value::{Object, Value};
+use minijinja::{context, Environment, Error, ErrorKind};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Add the filters available to every config template. Must be kept in sync with the
+/// "Templates" section of the model docs.
+pub(crate) fn add_template_filters(env: &mut Environment) {
+    env.add_filter("quote_sh", quote_sh);
+    env.add_filter("to_upper", to_upper);
+    env.add_filter("dirname", dirname);
+    env.add_filter("semver_major", semver_major);
+}
+
+/// Stands in for `captures` while doctor groups are loaded. `{{ captures.<name> }}` renders as
+/// itself, so it's still in a fix's commands when a known error runs the fix and its captures are
+/// known, see [`render_captures`].
+#[derive(Debug)]
+pub(crate) struct DeferredCaptures;
+
+impl Object for DeferredCaptures {
+    fn get_value(self: &Arc<Self>, key: &Value) -> Option<Value> {
+        Some(Value::from(format!("{{{{ captures.{} }}}}", key.as_str()?)))
+    }
+}
+
+/// Render the named capture groups of a known error's match into `template`, as `{{ <name> }}`
+/// or `{{ captures.<name> }}`.
+pub(crate) fn render_captures(
+    template: &str,
+    captures: &BTreeMap<String, String>,
+) -> Result<String, Error> {
+    let mut env = Environment::new();
+    add_template_filters(&mut env);
+    env.render_str(
+        template,
+        context! { captures => captures, ..Value::from_serialize(captures) },
+    )
+}
+
+/// Render the captures of a known error's match into a fix command, like [`render_captures`].
+/// Each capture is quoted with `quote_sh`, the matched text comes from the output of a command
+/// and can't be trusted to be run by a shell.
+pub(crate) fn render_captures_in_command(
+    template: &str,
+    captures: &BTreeMap<String, String>,
+) -> Result<String, Error> {
+    let quoted = captures
+        .iter()
+        .map(|(name, value)| (name.clone(), quote_sh(value.clone())))
+        .collect();
+    render_captures(template, &quoted)
+}
+
+/// Quote a value so it's passed to a shell as a single word.
+pub(crate) fn quote_sh(value: String) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+fn to_upper(value: String) -> String {
+    value.to_uppercase()
+}
+
+/// Parent directory of a path, empty when the path has no parent.
+fn dirname(value: String) -> String {
+    Path::new(&value)
+        .parent()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default()
+}
+
+/// Major version of a semver-like string, e.g. `v18.2.0` -> `18`.
+fn semver_major(value: String) -> Result<String, Error> {
+    let version = value.trim().trim_start_matches('v');
+    let major = version.split('.').next().unwrap_or_default();
+    if major.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("`{}` is not a semantic version", value),
+        ));
+    }
+
+    Ok(major.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_template_filters, render_captures, render_captures_in_command, DeferredCaptures,
+    };
+    use minijinja::value::Value;
+    use minijinja::{context, Environment};
+    use std::collections::BTreeMap;
+
+    fn render(template: &str, value: &str) -> Result<String, minijinja::Error> {
+        let mut env = Environment::new();
+        add_template_filters(&mut env);
+        env.render_str(template, context! { value => value })
+    }
+
+    #[test]
+    fn test_filters() {
+        assert_eq!(
+            "'it'\\''s here'",
+            render("{{ value | quote_sh }}", "it's here").unwrap()
+        );
+        assert_eq!("ABC", render("{{ value | to_upper }}", "abc").unwrap());
+        assert_eq!(
+            "/foo/bar",
+            render("{{ value | dirname }}", "/foo/bar/file.txt").unwrap()
+        );
+        assert_eq!("", render("{{ value | dirname }}", "/").unwrap());
+        assert_eq!(
+            "18",
+            render("{{ value | semver_major }}", "v18.2.0").unwrap()
+        );
+        assert_eq!("3", render("{{ value | semver_major }}", "3").unwrap());
+        assert!(render("{{ value | semver_major }}", "latest").is_err());
+    }
+
+    #[test]
+    fn test_captures_are_rendered_later() {
+        let env = Environment::new();
+        let command = env
+            .render_str(
+                "npx kill-port {{ captures.port }}",
+                context! { captures => Value::from_object(DeferredCaptures) },
+            )
+            .unwrap();
+        assert_eq!("npx kill-port {{ captures.port }}", command);
+
+        let captures = BTreeMap::from([("port".to_string(), "8080".to_string())]);
+        assert_eq!(
+            "npx kill-port '8080'",
+            render_captures_in_command(&command, &captures).unwrap()
+        );
+        assert_eq!(
+            "Port 8080 is already in use",
+            render_captures("Port {{ port }} is already in use", &captures).unwrap()
+        );
+
+        let captures = BTreeMap::from([("port".to_string(), "1; rm -rf ~".to_string())]);
+        assert_eq!(
+            "npx kill-port '1; rm -rf ~'",
+            render_captures_in_command(&command, &captures).unwrap()
+        );
+    }
+}
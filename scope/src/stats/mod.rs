@@ -0,0 +1,188 @@
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::{Args, ValueEnum};
+
+    /// How `scope stats` prints the statistics.
+    #[derive(Debug, Default, Clone, Copy, PartialEq)]
+    #[cfg_attr(feature = "cli", derive(ValueEnum))]
+    pub enum StatsFormat {
+        /// A table of the known errors, the most often found first
+        #[default]
+        Text,
+        /// The statistics file, as it's saved
+        Json,
+    }
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct StatsArgs {
+        /// How to print the statistics
+        #[cfg_attr(feature = "cli", arg(long, value_enum, default_value_t = StatsFormat::Text))]
+        pub format: StatsFormat,
+
+        /// Only show the known errors found most often
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub limit: Option<usize>,
+
+        /// Delete the statistics, starting the counts over
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub reset: bool,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{StatsArgs, StatsFormat};
+    use crate::report_stdout;
+    use crate::shared::prelude::{FoundConfig, KnownErrorStats};
+    use anyhow::Result;
+    use tracing::info;
+
+    pub async fn stats_root(found_config: &FoundConfig, args: &StatsArgs) -> Result<i32> {
+        let path = KnownErrorStats::default_path();
+        if args.reset {
+            if path.exists() {
+                std::fs::remove_file(&path)?;
+            }
+            info!(target: "user", "Cleared the known error statistics in {}", path.display());
+            return Ok(0);
+        }
+
+        let stats = KnownErrorStats::load(&path)?;
+        match args.format {
+            StatsFormat::Json => {
+                report_stdout!("{}", serde_json::to_string_pretty(&stats)?);
+            }
+            StatsFormat::Text if stats.known_errors.is_empty() => {
+                info!(target: "user", "No known errors have been found yet, `scope analyze` counts them in {}", path.display());
+            }
+            StatsFormat::Text => {
+                for line in stats_table(found_config, &stats, args.limit) {
+                    report_stdout!("{}", line);
+                }
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// A table with a row for each known error, the most often found first, with how the fix it
+    /// references went.
+    pub(crate) fn stats_table(
+        found_config: &FoundConfig,
+        stats: &KnownErrorStats,
+        limit: Option<usize>,
+    ) -> Vec<String> {
+        let header = [
+            "Known error",
+            "Found",
+            "Last found",
+            "Fix",
+            "Succeeded",
+            "Failed",
+            "Declined",
+        ]
+        .map(str::to_string);
+        let rows: Vec<_> = stats
+            .most_detected()
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|(name, counts)| {
+                let fix = found_config
+                    .known_error
+                    .get(name)
+                    .and_then(|known_error| known_error.fix_ref.clone());
+                let fix_counts = fix.as_ref().and_then(|fix| stats.fixes.get(fix));
+                let count = |count: Option<u64>| {
+                    count.map_or_else(|| "-".to_string(), |count| count.to_string())
+                };
+                [
+                    name.to_string(),
+                    counts.detected.to_string(),
+                    counts.last_detected.map_or_else(
+                        || "-".to_string(),
+                        |at| at.format("%Y-%m-%d %H:%M").to_string(),
+                    ),
+                    fix.unwrap_or_else(|| "-".to_string()),
+                    count(fix_counts.map(|counts| counts.succeeded)),
+                    count(fix_counts.map(|counts| counts.failed)),
+                    count(fix_counts.map(|counts| counts.declined)),
+                ]
+            })
+            .collect();
+
+        let widths: Vec<_> = (0..header.len())
+            .map(|column| {
+                std::iter::once(&header)
+                    .chain(&rows)
+                    .map(|row| row[column].chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        std::iter::once(&header)
+            .chain(&rows)
+            .map(|row| {
+                row.iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::models::prelude::ModelMetadata;
+        use crate::shared::prelude::{KnownError, KnownErrorSeverity};
+        use chrono::{TimeZone, Utc};
+        use regex::Regex;
+        use std::path::PathBuf;
+
+        #[test]
+        fn test_stats_table() {
+            let mut found_config = FoundConfig::empty(PathBuf::from("/tmp"));
+            found_config.known_error.insert(
+                "disk-full".to_string(),
+                KnownError {
+                    full_name: "ScopeKnownError/disk-full".to_string(),
+                    metadata: ModelMetadata::new("disk-full"),
+                    pattern: "no space".to_string(),
+                    regex: Regex::new("no space").unwrap(),
+                    help_text: String::new(),
+                    priority: 0,
+                    severity: KnownErrorSeverity::Error,
+                    context_lines: 0,
+                    fix_ref: Some("disk/clean".to_string()),
+                    fix: None,
+                },
+            );
+
+            let at = Utc.with_ymd_and_hms(2024, 6, 3, 10, 0, 0).unwrap();
+            let mut stats = KnownErrorStats::default();
+            stats.record_detected("auth-expired", at);
+            stats.record_detected("disk-full", at);
+            stats.record_detected("disk-full", at);
+            stats.record_fix_result("disk/clean", true);
+            stats.record_fix_declined("disk/clean");
+
+            assert_eq!(
+                vec![
+                    "Known error   Found  Last found        Fix         Succeeded  Failed  Declined",
+                    "disk-full     2      2024-06-03 10:00  disk/clean  1          0       1",
+                    "auth-expired  1      2024-06-03 10:00  -           -          -       -",
+                ],
+                stats_table(&found_config, &stats, None)
+            );
+            assert_eq!(2, stats_table(&found_config, &stats, Some(1)).len());
+        }
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::{StatsArgs, StatsFormat};
+    pub use super::commands::stats_root;
+}
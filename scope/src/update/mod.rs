@@ -0,0 +1,366 @@
+pub mod cli {
+    #[cfg(feature = "cli")]
+    use clap::Args;
+
+    #[derive(Debug)]
+    #[cfg_attr(feature = "cli", derive(Args))]
+    pub struct UpdateArgs {
+        /// Only check whether a newer version is available, without installing it
+        #[cfg_attr(feature = "cli", arg(long, conflicts_with = "rollback"))]
+        pub check: bool,
+
+        /// Install the release with this tag instead of the latest one, e.g. to go back to an
+        /// older version
+        #[cfg_attr(feature = "cli", arg(long, conflicts_with = "rollback"))]
+        pub tag: Option<String>,
+
+        /// Put back the binaries the last update replaced
+        #[cfg_attr(feature = "cli", arg(long))]
+        pub rollback: bool,
+
+        /// GitHub repository the releases are downloaded from
+        #[cfg_attr(
+            feature = "cli",
+            arg(long, env = "SCOPE_UPDATE_REPO", default_value = "oscope-dev/scope")
+        )]
+        pub repo: String,
+    }
+}
+
+pub mod commands {
+    use crate::prelude::{FoundConfig, UpdateArgs};
+    use crate::report_stdout;
+    use anyhow::{anyhow, Result};
+    use octocrab::models::repos::Release;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+    use tracing::{error, info, warn};
+
+    /// Binaries in a release archive, they're replaced when they're installed next to `scope`.
+    const BINARIES: [&str; 2] = ["scope", "scope-intercept"];
+    /// Suffix of the copy of a binary kept by an update, so it can be rolled back.
+    const BACKUP_SUFFIX: &str = "backup";
+
+    pub async fn update_root(found_config: &FoundConfig, args: &UpdateArgs) -> Result<i32> {
+        let exe = std::env::current_exe()?.canonicalize()?;
+        if args.rollback {
+            return rollback(&exe);
+        }
+
+        let release = fetch_release(&args.repo, args.tag.as_deref()).await?;
+        let current = env!("CARGO_PKG_VERSION");
+        if args.tag.is_none() && !is_newer(&release.tag_name, current) {
+            info!(target: "user", "scope {} is the latest version", current);
+            return Ok(0);
+        }
+        if args.check {
+            report_stdout!(
+                "scope {} is available, {} is installed. Run `scope update` to install it.",
+                release.tag_name,
+                current
+            );
+            return Ok(0);
+        }
+        if let Some((manager, command)) = package_manager(&exe) {
+            error!(target: "user", "scope was installed with {}, update it with {} instead", manager, command);
+            return Ok(1);
+        }
+
+        let Some(target) = current_target() else {
+            error!(target: "user", "There are no releases for this platform, build scope from source instead");
+            return Ok(1);
+        };
+        let archive_name = format!("dev-scope-{}.tar.xz", target);
+        let archive = download_asset(&release, &archive_name).await?;
+        let checksum = download_asset(&release, &format!("{}.sha256", archive_name)).await?;
+        let expected = parse_checksum(&String::from_utf8_lossy(&checksum))
+            .ok_or_else(|| anyhow!("{}.sha256 doesn't contain a checksum", archive_name))?;
+        let actual = sha256::digest(&archive[..]);
+        if !expected.eq_ignore_ascii_case(&actual) {
+            error!(target: "user", "The checksum of {} is {}, but the release says {}. Nothing was installed.", archive_name, actual, expected);
+            return Ok(1);
+        }
+
+        let download_dir =
+            std::env::temp_dir().join(format!("scope-update-{}", found_config.run_id));
+        std::fs::create_dir_all(&download_dir)?;
+        let archive_path = download_dir.join(&archive_name);
+        std::fs::write(&archive_path, &archive)?;
+        extract(&archive_path, &download_dir).await?;
+
+        let extracted = download_dir.join(format!("dev-scope-{}", target));
+        let install_dir = exe.parent().expect("binary to be in a directory");
+        for binary in BINARIES {
+            let installed = install_dir.join(binary);
+            let new = extracted.join(binary);
+            if installed.exists() && new.exists() {
+                replace_binary(&new, &installed)?;
+            }
+        }
+        std::fs::remove_dir_all(&download_dir).ok();
+
+        info!(target: "user", "Updated scope from {} to {}, run `scope update --rollback` to go back", current, release.tag_name);
+        Ok(0)
+    }
+
+    async fn fetch_release(repo: &str, tag: Option<&str>) -> Result<Release> {
+        let Some((owner, name)) = repo.split_once('/') else {
+            return Err(anyhow!("repo must look like <owner>/<name>, not {}", repo));
+        };
+        let octocrab = octocrab::instance();
+        let repo = octocrab.repos(owner, name);
+        let release = match tag {
+            Some(tag) => repo.releases().get_by_tag(tag).await?,
+            None => repo.releases().get_latest().await?,
+        };
+
+        Ok(release)
+    }
+
+    async fn download_asset(release: &Release, name: &str) -> Result<Vec<u8>> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| anyhow!("release {} has no {}", release.tag_name, name))?;
+        info!(target: "user", "Downloading {}", asset.browser_download_url);
+        let response = reqwest::get(asset.browser_download_url.clone())
+            .await?
+            .error_for_status()?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn extract(archive: &Path, into: &Path) -> Result<()> {
+        let status = tokio::process::Command::new("tar")
+            .arg("-xJf")
+            .arg(archive)
+            .arg("-C")
+            .arg(into)
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(anyhow!("unable to extract {}", archive.display()));
+        }
+
+        Ok(())
+    }
+
+    /// Whether the `available` version is newer than `current`, comparing each number of versions
+    /// like `2024.2.59`. A leading `v` is ignored.
+    pub(crate) fn is_newer(available: &str, current: &str) -> bool {
+        match (parse_version(available), parse_version(current)) {
+            (Some(available), Some(current)) => available > current,
+            _ => false,
+        }
+    }
+
+    fn parse_version(version: &str) -> Option<Vec<u64>> {
+        version
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    }
+
+    /// The checksum in a `.sha256` file, which is written as `<checksum>  <file name>`.
+    pub(crate) fn parse_checksum(contents: &str) -> Option<String> {
+        contents
+            .split_whitespace()
+            .next()
+            .filter(|checksum| checksum.len() == 64)
+            .map(str::to_string)
+    }
+
+    /// Name of the release archive for the platform this binary was built for.
+    fn current_target() -> Option<&'static str> {
+        if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+            Some("aarch64-apple-darwin")
+        } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
+            Some("x86_64-apple-darwin")
+        } else if cfg!(all(
+            target_os = "linux",
+            target_arch = "x86_64",
+            target_env = "musl"
+        )) {
+            Some("x86_64-unknown-linux-musl")
+        } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+            Some("x86_64-unknown-linux-gnu")
+        } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
+            Some("aarch64-unknown-linux-gnu")
+        } else {
+            None
+        }
+    }
+
+    /// Name of the package manager that installed `exe` and how to update with it, when scope
+    /// shouldn't replace itself.
+    fn package_manager(exe: &Path) -> Option<(&'static str, &'static str)> {
+        let path = exe.display().to_string();
+        if path.contains("/Cellar/") {
+            Some(("Homebrew", "`brew upgrade scope`"))
+        } else if installed_by_cargo(exe) {
+            Some(("cargo", "`cargo install dev-scope --features cli`"))
+        } else {
+            None
+        }
+    }
+
+    /// What `cargo install` keeps in `.crates2.json`, next to the `bin` directory it installs to.
+    #[derive(Deserialize)]
+    struct CargoInstalls {
+        installs: BTreeMap<String, CargoInstall>,
+    }
+
+    #[derive(Deserialize)]
+    struct CargoInstall {
+        #[serde(default)]
+        bins: Vec<String>,
+    }
+
+    /// Whether `exe` is a binary of `dev-scope` that cargo installed, in `~/.cargo/bin` or the
+    /// `bin` directory of another `--root`.
+    fn installed_by_cargo(exe: &Path) -> bool {
+        let (Some(root), Some(name)) = (exe.parent().and_then(Path::parent), exe.file_name())
+        else {
+            return false;
+        };
+        let Ok(text) = std::fs::read_to_string(root.join(".crates2.json")) else {
+            return false;
+        };
+        let Ok(cargo) = serde_json::from_str::<CargoInstalls>(&text) else {
+            return false;
+        };
+        cargo.installs.iter().any(|(package, install)| {
+            package.starts_with("dev-scope ")
+                && install
+                    .bins
+                    .iter()
+                    .any(|bin| name == std::ffi::OsStr::new(bin))
+        })
+    }
+
+    fn backup_path(binary: &Path) -> PathBuf {
+        let mut name = binary.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", BACKUP_SUFFIX));
+        binary.with_file_name(name)
+    }
+
+    /// Replace `installed` with `new`, keeping `installed` as a backup next to it. When the new
+    /// binary can't be put in place, the old one is restored.
+    pub(crate) fn replace_binary(new: &Path, installed: &Path) -> Result<()> {
+        let backup = backup_path(installed);
+        // Copied next to the installed binary first, so the last step is a rename on one file
+        // system and there's never a moment without a binary.
+        let staged = installed.with_extension("new");
+        std::fs::copy(new, &staged)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o755))?;
+        }
+
+        std::fs::copy(installed, &backup)?;
+        if let Err(e) = std::fs::rename(&staged, installed) {
+            std::fs::remove_file(&staged).ok();
+            return Err(anyhow!("unable to replace {}. {}", installed.display(), e));
+        }
+
+        Ok(())
+    }
+
+    /// Put back the binaries kept by the last update, next to `exe`.
+    pub(crate) fn rollback(exe: &Path) -> Result<i32> {
+        let install_dir = exe.parent().expect("binary to be in a directory");
+        let mut restored = 0;
+        for binary in BINARIES {
+            let installed = install_dir.join(binary);
+            let backup = backup_path(&installed);
+            if backup.exists() {
+                std::fs::rename(&backup, &installed)?;
+                restored += 1;
+            }
+        }
+
+        if restored == 0 {
+            warn!(target: "user", "There is no earlier version of scope in {} to roll back to", install_dir.display());
+            return Ok(1);
+        }
+
+        info!(target: "user", "Rolled back to the version of scope before the last update");
+        Ok(0)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_is_newer() {
+            assert!(is_newer("2024.2.60", "2024.2.59"));
+            assert!(is_newer("v2024.3.1", "2024.2.59"));
+            assert!(is_newer("2024.2.59.1", "2024.2.59"));
+            assert!(!is_newer("2024.2.59", "2024.2.59"));
+            assert!(!is_newer("2024.2.9", "2024.2.59"));
+            assert!(!is_newer("nightly", "2024.2.59"));
+        }
+
+        #[test]
+        fn test_parse_checksum() {
+            let checksum = "a".repeat(64);
+            assert_eq!(
+                Some(checksum.clone()),
+                parse_checksum(&format!(
+                    "{}  dev-scope-x86_64-apple-darwin.tar.xz\n",
+                    checksum
+                ))
+            );
+            assert_eq!(None, parse_checksum("not a checksum"));
+            assert_eq!(None, parse_checksum(""));
+        }
+
+        #[test]
+        fn test_installed_by_cargo() {
+            let root = assert_fs::TempDir::new().unwrap();
+            let exe = root.path().join("bin").join("scope");
+            assert!(!installed_by_cargo(&exe));
+
+            std::fs::write(
+                root.path().join(".crates2.json"),
+                r#"{"installs":{"dev-scope 2024.2.59 (registry+https://github.com/rust-lang/crates.io-index)":{"bins":["scope","scope-intercept"]}}}"#,
+            )
+            .unwrap();
+            assert!(installed_by_cargo(&exe));
+            assert!(!installed_by_cargo(&root.path().join("bin").join("other")));
+        }
+
+        #[test]
+        fn test_replace_and_rollback() {
+            let dir = assert_fs::TempDir::new().unwrap();
+            let installed = dir.path().join("scope");
+            let new = dir.path().join("download");
+            std::fs::write(&installed, "old").unwrap();
+            std::fs::write(&new, "new").unwrap();
+
+            replace_binary(&new, &installed).unwrap();
+            assert_eq!("new", std::fs::read_to_string(&installed).unwrap());
+            assert_eq!(
+                "old",
+                std::fs::read_to_string(dir.path().join("scope.backup")).unwrap()
+            );
+            assert!(!dir.path().join("scope.new").exists());
+
+            assert_eq!(0, rollback(&installed).unwrap());
+            assert_eq!("old", std::fs::read_to_string(&installed).unwrap());
+            assert!(!dir.path().join("scope.backup").exists());
+
+            assert_eq!(1, rollback(&installed).unwrap());
+        }
+    }
+}
+
+pub mod prelude {
+    pub use super::cli::UpdateArgs;
+    pub use super::commands::update_root;
+}
@@ -47,6 +47,10 @@ impl<'a> ScopeTestHelper<'a> {
             )
             .env("SCOPE_OUTPUT_PROGRESS", "plain")
             .env("NO_COLOR", "1")
+            .env(
+                "SCOPE_STATS_FILE",
+                self.work_dir.path().join(".cache/stats.json"),
+            )
             .args(args)
             .assert()
     }
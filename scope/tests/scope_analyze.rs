@@ -1,3 +1,4 @@
+use predicates::boolean::PredicateBooleanExt;
 use predicates::prelude::predicate;
 
 #[allow(dead_code)]
@@ -31,3 +32,64 @@ fn test_run_command_with_known_error_stderr() {
             "Known error 'error-exists' found on line 2",
         ));
 }
+
+#[test]
+fn test_analyze_logs_reports_most_specific_error() {
+    let helper = ScopeTestHelper::new(
+        "test_analyze_logs_reports_most_specific_error",
+        "known-errors-priority",
+    );
+
+    let results = helper.run_command(&["analyze", "logs", "app.log"]);
+
+    results
+        .failure()
+        .stdout(predicate::str::contains(
+            "Known error 'disk-full' found on line 1",
+        ))
+        .stdout(predicate::str::contains("Known error 'any-error'").not());
+
+    helper.clean_work_dir();
+}
+
+#[test]
+fn test_analyze_logs_allow_multiple_matches() {
+    let helper = ScopeTestHelper::new(
+        "test_analyze_logs_allow_multiple_matches",
+        "known-errors-priority",
+    );
+
+    let results = helper.run_command(&["analyze", "--allow-multiple-matches", "logs", "app.log"]);
+
+    results
+        .failure()
+        .stdout(predicate::str::contains(
+            "Known error 'disk-full' found on line 1",
+        ))
+        .stdout(predicate::str::contains(
+            "Known error 'any-error' found on line 1",
+        ));
+
+    helper.clean_work_dir();
+}
+
+#[test]
+fn test_analyze_logs_suggests_referenced_doctor_fix() {
+    let helper = ScopeTestHelper::new(
+        "test_analyze_logs_suggests_referenced_doctor_fix",
+        "known-error-fix-ref",
+    );
+
+    let results = helper.run_command(&["analyze", "logs", "app.log"]);
+
+    results
+        .failure()
+        .stdout(predicate::str::contains(
+            "Known error 'missing-node' found on line 1",
+        ))
+        .stdout(predicate::str::contains(
+            "A fix is available, run `scope doctor run --only node` to apply it",
+        ));
+
+    helper.clean_work_dir();
+}
@@ -147,3 +147,63 @@ fn test_sub_command_works() {
     ));
     test_helper.clean_work_dir();
 }
+
+#[test]
+fn test_doctor_audit_lists_fixes_that_ran() {
+    let helper = ScopeTestHelper::new("test_doctor_audit_lists_fixes_that_ran", "simple-check-fix");
+
+    helper.doctor_run(None).success();
+
+    let cache_args = format!("--cache-dir={}/.cache", helper.work_dir.to_str().unwrap());
+    helper
+        .run_command(&["doctor", "audit", &cache_args])
+        .success()
+        .stdout(predicate::str::contains("path-exists/file-exists exit=0"))
+        .stdout(predicate::str::contains("touch"));
+
+    helper.clean_work_dir();
+}
+
+#[test]
+fn test_run_missing_tool_fails_before_running_actions() {
+    let helper = ScopeTestHelper::new(
+        "test_run_missing_tool_fails_before_running_actions",
+        "missing-tool",
+    );
+
+    helper
+        .doctor_run(None)
+        .failure()
+        .stdout(predicate::str::contains(
+            "Missing required tools: scope-missing-tool-for-tests",
+        ))
+        .stdout(predicate::str::contains("tool-version").not());
+
+    helper.clean_work_dir();
+}
+
+#[test]
+fn test_run_prompted_fix_without_terminal_is_skipped() {
+    let helper = ScopeTestHelper::new(
+        "test_run_prompted_fix_without_terminal_is_skipped",
+        "prompted-fix",
+    );
+
+    helper
+        .doctor_run(None)
+        .failure()
+        .stdout(predicate::str::contains(
+            "the terminal isn't interactive so it was skipped",
+        ))
+        .stdout(predicate::str::contains("--auto-approve"));
+    assert!(!helper.work_dir.child("file-mod.txt").exists());
+
+    helper
+        .doctor_run(Some(&["--auto-approve"]))
+        .success()
+        .stdout(predicate::str::contains(
+            "Check initially failed, fix was successful",
+        ));
+
+    helper.clean_work_dir();
+}
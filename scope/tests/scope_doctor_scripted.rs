@@ -0,0 +1,74 @@
+use clap::Parser;
+use dev_scope::doctor::{run, DoctorRunOptions, DoctorRunOutcome};
+use dev_scope::prelude::{
+    ConfigOptions, DoctorRunArgs, FoundConfig, PathRunResult, ScriptedExecutionProvider,
+    ScriptedOutput,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+async fn run_scripted(
+    found_config: &FoundConfig,
+    provider: Arc<ScriptedExecutionProvider>,
+) -> PathRunResult {
+    let options = DoctorRunOptions::new(DoctorRunArgs {
+        no_cache: true,
+        ..Default::default()
+    })
+    .execution_provider(provider);
+    match run(found_config, &options).await.unwrap() {
+        DoctorRunOutcome::Completed(result) => result,
+        DoctorRunOutcome::Planned(_) => unreachable!("dry_run isn't set"),
+    }
+}
+
+async fn load_test_case(name: &str) -> (FoundConfig, PathBuf) {
+    let dir = PathBuf::from(format!(
+        "{}/tests/test-cases/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ));
+    let options = ConfigOptions::parse_from([
+        "scope",
+        "--disable-default-config",
+        "--working-dir",
+        &dir.display().to_string(),
+        "--extra-config",
+        &dir.join(".scope").display().to_string(),
+    ]);
+
+    (options.load_config().await.unwrap(), dir)
+}
+
+#[tokio::test]
+async fn test_scripted_check_fails_then_fix_succeeds() {
+    let (found_config, dir) = load_test_case("simple-check-fix").await;
+    let check = format!("test -f {}/file-mod.txt", dir.display());
+    let fix = format!("touch {}/file-mod.txt", dir.display());
+    let provider = Arc::new(
+        ScriptedExecutionProvider::new()
+            .with_output(&check, ScriptedOutput::exited(1))
+            .with_output(&check, ScriptedOutput::exited(0))
+            .with_output(&fix, ScriptedOutput::exited(0)),
+    );
+
+    let result = run_scripted(&found_config, provider.clone()).await;
+
+    assert!(result.did_succeed);
+    assert!(result.succeeded_groups.contains("path-exists"));
+    assert_eq!(vec![check.clone(), fix, check], provider.calls());
+    assert!(!dir.join("file-mod.txt").exists());
+}
+
+#[tokio::test]
+async fn test_scripted_fix_fails() {
+    let (found_config, dir) = load_test_case("simple-check-fix").await;
+    let provider =
+        Arc::new(ScriptedExecutionProvider::new().with_default(ScriptedOutput::exited(1)));
+
+    let result = run_scripted(&found_config, provider).await;
+
+    assert!(!result.did_succeed);
+    assert!(result.failed_group.contains("path-exists"));
+    assert!(!dir.join("file-mod.txt").exists());
+}